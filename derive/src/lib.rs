@@ -19,6 +19,22 @@
 //! }
 //! ```
 //!
+//! Without a `description`, the function's doc comment describes the tool, and the doc
+//! comments of its parameters describe the generated argument fields:
+//!
+//! ```rust
+//! /// Convert an amount between two currencies.
+//! #[tool]
+//! pub async fn convert(
+//!     /// The amount to convert
+//!     amount: f64,
+//!     /// ISO 4217 code of the target currency, e.g. `EUR`
+//!     to: String,
+//! ) -> Result<f64> {
+//!     Ok(amount * 0.9)
+//! }
+//! ```
+//!
 //! ## Function Patterns
 //!
 //! ### No Parameters
@@ -88,14 +104,14 @@ use convert_case::{Case, Casing};
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
 use syn::{
-    FnArg, Ident, ItemFn, LitStr, Token, Type, Visibility,
+    Attribute, Expr, FnArg, Ident, ItemFn, Lit, LitStr, Meta, Token, Type, Visibility,
     parse::{Parse, ParseStream},
     parse_macro_input, parse_quote,
 };
 
 /// Arguments for the `#[tool]` attribute macro
 struct ToolArgs {
-    description: String,
+    description: Option<String>,
     rename: Option<String>,
 }
 
@@ -103,7 +119,8 @@ impl Parse for ToolArgs {
     /// Parse the arguments from the `#[tool(...)]` attribute.
     ///
     /// Supports:
-    /// - `description = "..."` (required): Tool description for the AI model
+    /// - `description = "..."` (optional): Tool description for the AI model (defaults to
+    ///   the function's doc comment)
     /// - `rename = "..."` (optional): Custom name for the tool (defaults to function name)
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let mut description = None;
@@ -130,9 +147,6 @@ impl Parse for ToolArgs {
             }
         }
 
-        let description = description
-            .ok_or_else(|| syn::Error::new(input.span(), "description attribute is required"))?;
-
         Ok(Self {
             description,
             rename,
@@ -147,8 +161,8 @@ impl Parse for ToolArgs {
 ///
 /// # Arguments
 ///
-/// - `description` (required): A clear description of what the tool does. This helps the AI model
-///   decide when to use this tool.
+/// - `description` (optional): A clear description of what the tool does. This helps the AI model
+///   decide when to use this tool. If not provided, uses the function's doc comment.
 /// - `rename` (optional): A custom name for the tool. If not provided, uses the function name.
 ///
 /// # Examples
@@ -197,6 +211,21 @@ impl Parse for ToolArgs {
 /// }
 /// ```
 ///
+/// ## Described by Doc Comments
+///
+/// ```rust
+/// /// Look up the current weather in a city.
+/// #[tool]
+/// pub async fn weather(
+///     /// The name of the city
+///     city: String,
+///     /// Whether to report temperatures in Fahrenheit
+///     fahrenheit: bool,
+/// ) -> Result<String> {
+///     Ok(format!("Sunny in {city}"))
+/// }
+/// ```
+///
 /// ## With Custom Name
 ///
 /// ```rust
@@ -214,7 +243,8 @@ impl Parse for ToolArgs {
 ///
 /// For a function named `search`, the macro generates:
 ///
-/// 1. A `SearchArgs` struct (if the function has multiple parameters)
+/// 1. A `SearchArgs` struct (if the function has multiple parameters, or a documented one),
+///    whose fields carry the parameters' doc comments
 /// 2. A `Search` struct that implements `ai_types::llm::Tool`
 /// 3. All necessary trait implementations for JSON schema generation and deserialization
 ///
@@ -232,7 +262,7 @@ impl Parse for ToolArgs {
 /// - The function is not async
 /// - The function has `self` parameters
 /// - The function has more than the supported number of parameters
-/// - Required attributes are missing
+/// - Neither a `description` nor a doc comment is provided
 #[proc_macro_attribute]
 pub fn tool(args: TokenStream, input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as ToolArgs);
@@ -248,10 +278,38 @@ pub fn tool(args: TokenStream, input: TokenStream) -> TokenStream {
 ///
 /// This function performs the actual code generation, transforming the annotated async function
 /// into a struct that implements the `Tool` trait.
-fn tool_impl(args: ToolArgs, input_fn: ItemFn) -> syn::Result<proc_macro2::TokenStream> {
+fn tool_impl(args: ToolArgs, mut input_fn: ItemFn) -> syn::Result<proc_macro2::TokenStream> {
+    let description = match args.description {
+        Some(description) => description,
+        None => doc_comment(&input_fn.attrs).ok_or_else(|| {
+            syn::Error::new_spanned(
+                &input_fn.sig.ident,
+                "tools need a description: add a doc comment or `description = \"...\"`",
+            )
+        })?,
+    };
+
+    // Doc comments are not allowed on function parameters, so move them to the
+    // generated argument fields.
+    let param_docs: Vec<Vec<Attribute>> = input_fn
+        .sig
+        .inputs
+        .iter_mut()
+        .map(|arg| match arg {
+            FnArg::Typed(pat_type) => {
+                let (docs, others) = pat_type
+                    .attrs
+                    .drain(..)
+                    .partition(|attr| attr.path().is_ident("doc"));
+                pat_type.attrs = others;
+                docs
+            }
+            FnArg::Receiver(_) => Vec::new(),
+        })
+        .collect();
+
     let fn_name = &input_fn.sig.ident;
     let tool_name = args.rename.unwrap_or_else(|| fn_name.to_string());
-    let description = args.description;
     let fn_vis = &input_fn.vis;
 
     let tool_struct_name = format_ident!("{}", fn_name.to_string().to_case(Case::Pascal));
@@ -261,7 +319,7 @@ fn tool_impl(args: ToolArgs, input_fn: ItemFn) -> syn::Result<proc_macro2::Token
         args_type,
         params,
         stream,
-    } = analyze_function_args(fn_vis, &tool_struct_name, &input_fn.sig.inputs)?;
+    } = analyze_function_args(fn_vis, &tool_struct_name, &input_fn.sig.inputs, &param_docs)?;
 
     if input_fn.sig.asyncness.is_none() {
         return Err(syn::Error::new_spanned(
@@ -279,7 +337,7 @@ fn tool_impl(args: ToolArgs, input_fn: ItemFn) -> syn::Result<proc_macro2::Token
         quote! { #fn_name(#args_tuple).await }
     };
 
-    let extractor = if params.len() <= 1 {
+    let extractor = if stream.is_empty() {
         quote! {}
     } else {
         quote! { let Self::Arguments { #(#params),* } = args; }
@@ -314,6 +372,25 @@ fn tool_impl(args: ToolArgs, input_fn: ItemFn) -> syn::Result<proc_macro2::Token
     Ok(expanded)
 }
 
+/// Returns the text of the doc comments in `attrs`, or `None` if there are none.
+fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+                Expr::Lit(expr) => match &expr.lit {
+                    Lit::Str(text) => Some(text.value().trim().to_string()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    let doc = lines.join("\n").trim().to_string();
+    (!doc.is_empty()).then_some(doc)
+}
+
 /// Container for analyzed function arguments and generated types.
 struct AnalyzedArgs {
     /// The type used for the Tool's Arguments associated type
@@ -328,12 +405,13 @@ struct AnalyzedArgs {
 ///
 /// This function handles three cases:
 /// - No parameters: Uses unit type `()`
-/// - Single parameter: Uses the parameter type directly
-/// - Multiple parameters: Generates a new struct with all parameters as fields
+/// - Single undocumented parameter: Uses the parameter type directly
+/// - Multiple or documented parameters: Generates a new struct with all parameters as fields
 fn analyze_function_args(
     fn_vis: &Visibility,
     struct_name: &Ident,
     inputs: &syn::punctuated::Punctuated<FnArg, syn::Token![,]>,
+    docs: &[Vec<Attribute>],
 ) -> syn::Result<AnalyzedArgs> {
     match inputs.len() {
         0 => {
@@ -345,7 +423,8 @@ fn analyze_function_args(
                 stream: quote! {},
             })
         }
-        1 => {
+        // A single documented parameter needs a struct to carry its doc comment.
+        1 if docs[0].is_empty() => {
             // Single argument
             if let FnArg::Typed(pat_type) = &inputs[0] {
                 Ok(AnalyzedArgs {
@@ -363,11 +442,12 @@ fn analyze_function_args(
         _ => {
             let mut attributes = Vec::new();
 
-            for arg in inputs {
+            for (arg, docs) in inputs.iter().zip(docs) {
                 if let FnArg::Typed(pat_type) = arg {
                     let pat = &pat_type.pat;
                     let ty = &pat_type.ty;
                     attributes.push(quote! {
+                        #(#docs)*
                         #pat: #ty,
                     });
                 } else {
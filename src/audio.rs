@@ -1,5 +1,7 @@
 use alloc::{string::String, vec::Vec};
+use core::{fmt::Write, time::Duration};
 use futures_core::Stream;
+use futures_lite::{StreamExt, pin};
 
 /// Audio data as bytes.
 ///
@@ -10,7 +12,7 @@ pub type Data = Vec<u8>;
 /// # Example
 ///
 /// ```rust
-/// use ai_types::AudioGenerator;
+/// use ai_types::{AudioGenerator, audio::Profile};
 /// use futures_core::Stream;
 ///
 /// struct MyAudioGen;
@@ -19,6 +21,10 @@ pub type Data = Vec<u8>;
 ///     fn generate(&self, prompt: &str) -> impl Stream<Item = ai_types::audio::Data> + Send {
 ///         futures_lite::stream::iter(Some(vec![0u8; 1024]))
 ///     }
+///
+///     fn profile(&self) -> Profile {
+///         Profile::new("my-tts", "A text-to-speech voice").with_max_characters(4096)
+///     }
 /// }
 /// ```
 pub trait AudioGenerator {
@@ -26,6 +32,418 @@ pub trait AudioGenerator {
     ///
     /// Returns a [`Stream`] of [`Data`] chunks.
     fn generate(&self, prompt: &str) -> impl Stream<Item = Data> + Send;
+
+    /// Returns the generator's profile, describing its limits and speaking rate.
+    fn profile(&self) -> Profile;
+
+    /// Estimates how long the generated audio for `text` will play.
+    ///
+    /// The default implementation uses the profile's speaking rate. Providers with
+    /// better knowledge of their voices may override it.
+    fn estimate_duration(&self, text: &str) -> Duration {
+        self.profile().estimate_duration(text)
+    }
+
+    /// Generates audio along with speech marks, such as word boundaries and visemes.
+    ///
+    /// Lip-sync and captioning applications use the marks to align visuals with the
+    /// audio. The default implementation yields only [`SpeechEvent::Audio`] events from
+    /// [`generate`](Self::generate); providers that emit speech marks override it.
+    fn generate_events(&self, prompt: &str) -> impl Stream<Item = SpeechEvent> + Send {
+        self.generate(prompt).map(SpeechEvent::Audio)
+    }
+
+    /// Generates audio from plain text or [SSML](Ssml) markup.
+    ///
+    /// SSML controls pauses, emphasis and pronunciation, which a plain prompt cannot.
+    /// The default implementation speaks the [plain text](SpeechInput::text) of the
+    /// input with [`generate`](Self::generate), dropping the markup; generators that
+    /// understand SSML override it and set [`Profile::ssml`].
+    fn synthesize(&self, input: impl Into<SpeechInput>) -> impl Stream<Item = Data> + Send
+    where
+        Self: Sync,
+    {
+        let text = input.into().text();
+        async_stream::stream! {
+            let audio = self.generate(&text);
+            pin!(audio);
+            while let Some(chunk) = audio.next().await {
+                yield chunk;
+            }
+        }
+    }
+}
+
+/// Input to [`AudioGenerator::synthesize`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum SpeechInput {
+    /// Plain text, spoken as written.
+    Text(String),
+    /// A complete SSML document, such as one built with [`Ssml`].
+    Ssml(String),
+}
+
+impl SpeechInput {
+    /// Returns the text to speak, without markup.
+    ///
+    /// Tags are removed, `<break>` elements become spaces, and the predefined XML
+    /// entities are decoded.
+    #[must_use]
+    pub fn text(&self) -> String {
+        match self {
+            Self::Text(text) => text.clone(),
+            Self::Ssml(ssml) => ssml_text(ssml),
+        }
+    }
+}
+
+impl From<&str> for SpeechInput {
+    fn from(text: &str) -> Self {
+        Self::Text(text.into())
+    }
+}
+
+impl From<String> for SpeechInput {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<Ssml> for SpeechInput {
+    fn from(ssml: Ssml) -> Self {
+        Self::Ssml(ssml.build())
+    }
+}
+
+fn ssml_text(ssml: &str) -> String {
+    let mut text = String::with_capacity(ssml.len());
+    let mut rest = ssml;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = "";
+            break;
+        };
+        if rest[start + 1..].starts_with("break") {
+            text.push(' ');
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+
+    let text = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+    let mut words = text.split_whitespace();
+    let mut normalized = String::with_capacity(text.len());
+    if let Some(first) = words.next() {
+        normalized.push_str(first);
+        for word in words {
+            normalized.push(' ');
+            normalized.push_str(word);
+        }
+    }
+    normalized
+}
+
+/// How strongly [`Ssml::emphasis`] stresses its text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Emphasis {
+    /// Stronger than usual.
+    Strong,
+    /// The default emphasis.
+    #[default]
+    Moderate,
+    /// Weaker than usual, de-emphasizing the text.
+    Reduced,
+}
+
+impl Emphasis {
+    /// Returns the SSML name of the level.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Strong => "strong",
+            Self::Moderate => "moderate",
+            Self::Reduced => "reduced",
+        }
+    }
+}
+
+/// A builder for SSML documents using the elements common to major providers.
+///
+/// Text is escaped, so any string can be added safely.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::audio::{Emphasis, Ssml};
+/// use core::time::Duration;
+///
+/// let ssml = Ssml::new()
+///     .text("Your table is ready")
+///     .pause(Duration::from_millis(300))
+///     .emphasis(Emphasis::Strong, "now")
+///     .text(", at")
+///     .say_as("time", "7:30pm")
+///     .build();
+///
+/// assert_eq!(
+///     ssml,
+///     "<speak>Your table is ready<break time=\"300ms\"/><emphasis level=\"strong\">now</emphasis>, at<say-as interpret-as=\"time\">7:30pm</say-as></speak>"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ssml {
+    body: String,
+}
+
+impl Ssml {
+    /// Creates an empty document.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            body: String::new(),
+        }
+    }
+
+    /// Adds text spoken as written.
+    #[must_use]
+    pub fn text(mut self, text: &str) -> Self {
+        escape(&mut self.body, text);
+        self
+    }
+
+    /// Adds a pause of `duration`, in whole milliseconds.
+    #[must_use]
+    pub fn pause(mut self, duration: Duration) -> Self {
+        let _ = write!(self.body, "<break time=\"{}ms\"/>", duration.as_millis());
+        self
+    }
+
+    /// Adds `text` spoken with the given emphasis.
+    #[must_use]
+    pub fn emphasis(mut self, level: Emphasis, text: &str) -> Self {
+        let _ = write!(self.body, "<emphasis level=\"{}\">", level.as_str());
+        escape(&mut self.body, text);
+        self.body.push_str("</emphasis>");
+        self
+    }
+
+    /// Adds `text` interpreted as the given kind of content, such as `"date"`,
+    /// `"cardinal"`, `"characters"` or `"telephone"`.
+    #[must_use]
+    pub fn say_as(mut self, interpret_as: &str, text: &str) -> Self {
+        self.body.push_str("<say-as interpret-as=\"");
+        escape(&mut self.body, interpret_as);
+        self.body.push_str("\">");
+        escape(&mut self.body, text);
+        self.body.push_str("</say-as>");
+        self
+    }
+
+    /// Returns the document, wrapped in a `<speak>` element.
+    #[must_use]
+    pub fn build(self) -> String {
+        alloc::format!("<speak>{}</speak>", self.body)
+    }
+}
+
+fn escape(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// An event emitted by [`AudioGenerator::generate_events`].
+///
+/// Offsets are measured from the start of the generated audio.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SpeechEvent {
+    /// A chunk of audio data.
+    Audio(Data),
+    /// A word starts playing.
+    Word(WordBoundary),
+    /// A mouth shape starts.
+    Viseme(Viseme),
+}
+
+/// Timing of a spoken word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordBoundary {
+    /// The spoken word.
+    pub text: String,
+    /// When the word starts in the audio.
+    pub offset: Duration,
+    /// How long the word is spoken.
+    pub duration: Duration,
+    /// Start byte index of the word in the prompt.
+    pub start: usize,
+    /// End byte index of the word in the prompt.
+    pub end: usize,
+}
+
+/// Timing of a mouth shape, used to animate avatars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viseme {
+    /// Provider-specific viseme identifier.
+    pub id: u32,
+    /// When the mouth shape starts in the audio.
+    pub offset: Duration,
+}
+
+/// Describes an audio generator's limits and speaking rate.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::audio::Profile;
+/// use core::time::Duration;
+///
+/// let profile = Profile::new("narrator", "A calm narrator voice")
+///     .with_max_characters(20)
+///     .with_words_per_minute(120);
+///
+/// assert_eq!(profile.estimate_duration("one two"), Duration::from_secs(1));
+/// assert_eq!(
+///     profile.split_script("The quick brown fox. Jumps over the lazy dog."),
+///     ["The quick brown fox.", "Jumps over the lazy", "dog."]
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Profile {
+    /// The name of the generator or voice.
+    pub name: String,
+    /// A description of the generator or voice.
+    pub description: String,
+    /// Maximum number of characters accepted by a single [`AudioGenerator::generate`] call.
+    pub max_characters: Option<u32>,
+    /// Average speaking rate, used to estimate durations.
+    pub words_per_minute: u32,
+    /// Whether [`AudioGenerator::synthesize`] honors SSML markup rather than dropping it.
+    pub ssml: bool,
+}
+
+impl Profile {
+    /// Average speaking rate of conversational English speech.
+    pub const DEFAULT_WORDS_PER_MINUTE: u32 = 150;
+
+    /// Creates a new `Profile` with the given name and description.
+    pub fn new(name: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            max_characters: None,
+            words_per_minute: Self::DEFAULT_WORDS_PER_MINUTE,
+            ssml: false,
+        }
+    }
+
+    /// Sets whether the generator honors SSML markup.
+    #[must_use]
+    pub const fn with_ssml(mut self, ssml: bool) -> Self {
+        self.ssml = ssml;
+        self
+    }
+
+    /// Sets the maximum number of characters per request.
+    #[must_use]
+    pub const fn with_max_characters(mut self, max_characters: u32) -> Self {
+        self.max_characters = Some(max_characters);
+        self
+    }
+
+    /// Sets the average speaking rate.
+    #[must_use]
+    pub const fn with_words_per_minute(mut self, words_per_minute: u32) -> Self {
+        self.words_per_minute = words_per_minute;
+        self
+    }
+
+    /// Estimates how long `text` takes to speak at the profile's speaking rate.
+    #[must_use]
+    pub fn estimate_duration(&self, text: &str) -> Duration {
+        if self.words_per_minute == 0 {
+            return Duration::ZERO;
+        }
+        let words = text.split_whitespace().count() as u64;
+        Duration::from_millis(words * 60_000 / u64::from(self.words_per_minute))
+    }
+
+    /// Splits `text` into chunks that each fit within
+    /// [`max_characters`](Self::max_characters).
+    ///
+    /// Chunks end at sentence boundaries when possible, otherwise at word boundaries.
+    /// Words longer than the limit are split between characters. Returns the trimmed
+    /// text as a single chunk when there is no limit.
+    #[must_use]
+    pub fn split_script<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let text = text.trim();
+        let Some(max) = self
+            .max_characters
+            .and_then(|max| usize::try_from(max).ok())
+        else {
+            return if text.is_empty() {
+                Vec::new()
+            } else {
+                alloc::vec![text]
+            };
+        };
+        let max = max.max(1);
+
+        let mut chunks = Vec::new();
+        let mut rest = text;
+        while !rest.is_empty() {
+            // Byte offset just past the `max`-th character.
+            let limit = rest
+                .char_indices()
+                .nth(max)
+                .map_or(rest.len(), |(index, _)| index);
+            if limit == rest.len() {
+                chunks.push(rest);
+                break;
+            }
+
+            let window = &rest[..limit];
+            let next_is_space = rest[limit..].starts_with(char::is_whitespace);
+            let sentence_end = window
+                .char_indices()
+                .rev()
+                .find(|&(index, c)| {
+                    matches!(c, '.' | '!' | '?' | '。' | '！' | '？')
+                        && rest[index + c.len_utf8()..].starts_with(char::is_whitespace)
+                })
+                .map(|(index, c)| index + c.len_utf8());
+            let word_end = if next_is_space {
+                Some(limit)
+            } else {
+                window.rfind(char::is_whitespace).filter(|&index| index > 0)
+            };
+
+            // Avoid tiny chunks when the only sentence boundary is near the start.
+            let sentence_end = sentence_end.filter(|&end| end * 2 >= window.len());
+            let end = sentence_end.or(word_end).unwrap_or(limit);
+            chunks.push(rest[..end].trim_end());
+            rest = rest[end..].trim_start();
+        }
+        chunks
+    }
 }
 
 /// Transcribes audio to text.
@@ -33,22 +451,343 @@ pub trait AudioGenerator {
 /// # Example
 ///
 /// ```rust
-/// use ai_types::AudioTranscriber;
+/// use ai_types::{AudioTranscriber, audio::Segment};
+/// use core::time::Duration;
 /// use futures_core::Stream;
 ///
 /// struct MyTranscriber;
 ///
 /// impl AudioTranscriber for MyTranscriber {
-///     fn transcribe(&self, audio: &[u8]) -> impl Stream<Item = String> + Send {
-///         futures_lite::stream::iter(vec!["Hello world".to_string()])
+///     fn transcribe(&self, audio: &[u8]) -> impl Stream<Item = Segment> + Send {
+///         let segment = Segment::new("Hello world")
+///             .with_span(Duration::ZERO, Duration::from_millis(1200))
+///             .with_language("en");
+///         futures_lite::stream::iter(vec![segment])
 ///     }
 /// }
 /// ```
 pub trait AudioTranscriber {
     /// Transcribes audio data to text.
     ///
-    /// Returns a [`Stream`] of transcribed text chunks.
-    fn transcribe(&self, audio: &[u8]) -> impl Stream<Item = String> + Send;
+    /// Returns a [`Stream`] of timed [`Segment`]s, in order. Collect it into a
+    /// [`Transcript`] to get the full text.
+    fn transcribe(&self, audio: &[u8]) -> impl Stream<Item = Segment> + Send;
+
+    /// Transcribes live audio as it arrives, such as microphone capture.
+    ///
+    /// Yields [`TranscriptEvent::Partial`] hypotheses while an utterance is in progress,
+    /// each replacing the previous one, and a [`TranscriptEvent::Final`] event once its
+    /// text is settled.
+    ///
+    /// The default implementation waits for the audio stream to end, then
+    /// [transcribes](Self::transcribe) the whole recording, reporting each segment as a
+    /// final event. Providers with realtime recognition override it.
+    ///
+    /// # Errors
+    ///
+    /// The stream yields an error if recognition fails; the default implementation never
+    /// fails.
+    fn transcribe_stream(
+        &self,
+        audio: impl Stream<Item = Data> + Send,
+    ) -> impl Stream<Item = crate::Result<TranscriptEvent>> + Send
+    where
+        Self: Sync,
+    {
+        async_stream::stream! {
+            pin!(audio);
+            let mut recording = Vec::new();
+            while let Some(chunk) = audio.next().await {
+                recording.extend_from_slice(&chunk);
+            }
+
+            let segments = self.transcribe(&recording);
+            pin!(segments);
+            while let Some(segment) = segments.next().await {
+                yield Ok(TranscriptEvent::Final(segment));
+            }
+        }
+    }
+}
+
+/// An event emitted by [`AudioTranscriber::transcribe_stream`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum TranscriptEvent {
+    /// The current hypothesis for the utterance in progress.
+    ///
+    /// Replaces the previous partial event and may still change.
+    Partial(String),
+    /// The settled segment of an utterance.
+    ///
+    /// Ends the utterance; later events belong to the next one.
+    Final(Segment),
+}
+
+impl TranscriptEvent {
+    /// Returns the text of the event.
+    #[must_use]
+    pub fn text(&self) -> &str {
+        match self {
+            Self::Partial(text) => text,
+            Self::Final(segment) => &segment.text,
+        }
+    }
+
+    /// Returns whether the event ends an utterance.
+    #[must_use]
+    pub const fn is_final(&self) -> bool {
+        matches!(self, Self::Final(_))
+    }
+}
+
+/// A timed piece of a transcript, such as a sentence or a speaker turn.
+///
+/// Times are measured from the start of the audio.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Segment {
+    /// The transcribed text.
+    pub text: String,
+    /// When the segment starts.
+    pub start: Duration,
+    /// When the segment ends.
+    pub end: Duration,
+    /// The recognizer's confidence in the text, from 0 to 1, if reported.
+    pub confidence: Option<f32>,
+    /// The label of the speaker, such as `"A"`, for transcribers that diarize.
+    pub speaker: Option<String>,
+    /// The detected language as a BCP 47 tag, such as `"en"`, if reported.
+    pub language: Option<String>,
+}
+
+impl Segment {
+    /// Creates an untimed segment with the given text.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Sets when the segment starts and ends.
+    #[must_use]
+    pub const fn with_span(mut self, start: Duration, end: Duration) -> Self {
+        self.start = start;
+        self.end = end;
+        self
+    }
+
+    /// Sets the recognizer's confidence, from 0 to 1.
+    #[must_use]
+    pub const fn with_confidence(mut self, confidence: f32) -> Self {
+        self.confidence = Some(confidence);
+        self
+    }
+
+    /// Sets the label of the speaker.
+    #[must_use]
+    pub fn with_speaker(mut self, speaker: impl Into<String>) -> Self {
+        self.speaker = Some(speaker.into());
+        self
+    }
+
+    /// Sets the detected language.
+    #[must_use]
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    /// Returns how long the segment lasts.
+    #[must_use]
+    pub const fn duration(&self) -> Duration {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+/// The complete output of an [`AudioTranscriber`], as timed segments.
+///
+/// Collect a [`transcribe`](AudioTranscriber::transcribe) stream into a transcript:
+///
+/// ```rust
+/// use ai_types::{AudioTranscriber, audio::Transcript};
+/// use futures_lite::StreamExt;
+///
+/// async fn subtitles(transcriber: impl AudioTranscriber, audio: &[u8]) -> Vec<String> {
+///     let transcript: Transcript = transcriber.transcribe(audio).collect().await;
+///     transcript
+///         .segments
+///         .iter()
+///         .map(|segment| format!("{:?} --> {:?}: {}", segment.start, segment.end, segment.text))
+///         .collect()
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Transcript {
+    /// The segments, in order.
+    pub segments: Vec<Segment>,
+}
+
+impl Transcript {
+    /// Creates an empty transcript.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            segments: Vec::new(),
+        }
+    }
+
+    /// Returns the full text, joining the trimmed segments with spaces.
+    #[must_use]
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for segment in &self.segments {
+            let segment = segment.text.trim();
+            if segment.is_empty() {
+                continue;
+            }
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            text.push_str(segment);
+        }
+        text
+    }
+
+    /// Returns when the last segment ends.
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.segments
+            .iter()
+            .map(|segment| segment.end)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+impl Extend<Segment> for Transcript {
+    fn extend<I: IntoIterator<Item = Segment>>(&mut self, segments: I) {
+        self.segments.extend(segments);
+    }
+}
+
+impl FromIterator<Segment> for Transcript {
+    fn from_iter<I: IntoIterator<Item = Segment>>(segments: I) -> Self {
+        Self {
+            segments: segments.into_iter().collect(),
+        }
+    }
+}
+
+/// Re-transcribes low-confidence segments of a transcript, typically with a stronger
+/// model.
+///
+/// Noisy audio produces segments the transcriber is unsure about. A retranscriber sends
+/// the audio of every segment below its confidence threshold to its model again and
+/// replaces the segment with the result, unless the result is empty or reports an even
+/// lower confidence. Segments without a confidence score are kept as they are.
+///
+/// Audio formats differ in how a time range maps to bytes, so the retranscriber cuts
+/// segments out with a `clip` function returning the audio between two offsets, or
+/// `None` when it cannot be cut.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{AudioTranscriber, audio::{Retranscriber, Transcript}};
+/// use core::time::Duration;
+///
+/// /// Cuts 16 kHz, 16-bit mono PCM.
+/// fn clip(audio: &[u8], start: Duration, end: Duration) -> Option<Vec<u8>> {
+///     let offset = |time: Duration| (time.as_millis() as usize * 16 * 2).min(audio.len());
+///     Some(audio[offset(start)..offset(end)].to_vec())
+/// }
+///
+/// async fn transcribe(
+///     fast: &(impl AudioTranscriber + Sync),
+///     accurate: impl AudioTranscriber + Sync,
+///     pcm: &[u8],
+/// ) -> Transcript {
+///     Retranscriber::new(accurate, clip)
+///         .with_threshold(0.8)
+///         .transcribe(fast, pcm)
+///         .await
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Retranscriber<T, C> {
+    model: T,
+    clip: C,
+    threshold: f32,
+}
+
+impl<T, C> Retranscriber<T, C>
+where
+    T: AudioTranscriber + Sync,
+    C: Fn(&[u8], Duration, Duration) -> Option<Data> + Sync,
+{
+    /// The default confidence below which segments are re-transcribed.
+    pub const DEFAULT_THRESHOLD: f32 = 0.6;
+
+    /// Creates a retranscriber sending segments to `model`, cut out with `clip`.
+    pub const fn new(model: T, clip: C) -> Self {
+        Self {
+            model,
+            clip,
+            threshold: Self::DEFAULT_THRESHOLD,
+        }
+    }
+
+    /// Sets the confidence below which segments are re-transcribed.
+    #[must_use]
+    pub const fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Transcribes `audio` with `transcriber`, then [refines](Self::refine) the result.
+    pub async fn transcribe(
+        &self,
+        transcriber: &(impl AudioTranscriber + Sync),
+        audio: &[u8],
+    ) -> Transcript {
+        let transcript = transcriber.transcribe(audio).collect().await;
+        self.refine(audio, transcript).await
+    }
+
+    /// Re-transcribes the low-confidence segments of `transcript`, a transcript of `audio`.
+    ///
+    /// A replaced segment keeps its span, speaker and language, takes the joined text of
+    /// the new transcription, and the average of its confidence scores.
+    pub async fn refine(&self, audio: &[u8], mut transcript: Transcript) -> Transcript {
+        for segment in &mut transcript.segments {
+            if !segment.confidence.is_some_and(|c| c < self.threshold) {
+                continue;
+            }
+            let Some(clip) = (self.clip)(audio, segment.start, segment.end) else {
+                continue;
+            };
+            let retry: Transcript = self.model.transcribe(&clip).collect().await;
+            let text = retry.text();
+            if text.is_empty() {
+                continue;
+            }
+            let scores: Vec<f32> = retry.segments.iter().filter_map(|s| s.confidence).collect();
+            #[allow(clippy::cast_precision_loss)]
+            let confidence =
+                (!scores.is_empty()).then(|| scores.iter().sum::<f32>() / scores.len() as f32);
+            if confidence.is_some_and(|new| segment.confidence.is_some_and(|old| new < old)) {
+                continue;
+            }
+            segment.text = text;
+            segment.confidence = confidence;
+        }
+        transcript
+    }
 }
 
 #[cfg(test)]
@@ -76,12 +815,16 @@ mod tests {
 
             futures_lite::stream::iter(chunks)
         }
+
+        fn profile(&self) -> Profile {
+            Profile::new("mock", "Mock audio generator").with_max_characters(32)
+        }
     }
 
     struct MockAudioTranscriber;
 
     impl AudioTranscriber for MockAudioTranscriber {
-        fn transcribe(&self, audio: &[u8]) -> impl Stream<Item = String> + Send {
+        fn transcribe(&self, audio: &[u8]) -> impl Stream<Item = Segment> + Send {
             // Generate mock transcription based on audio length
             let text_chunks = if audio.is_empty() {
                 vec![]
@@ -99,7 +842,7 @@ mod tests {
                 ]
             };
 
-            futures_lite::stream::iter(text_chunks)
+            futures_lite::stream::iter(text_chunks.into_iter().map(Segment::new))
         }
     }
 
@@ -139,6 +882,93 @@ mod tests {
         assert_eq!(chunks[2][0], 0x03);
     }
 
+    #[tokio::test]
+    async fn retranscriber_replaces_low_confidence_segments() {
+        /// Hears the clipped bytes as text, with a fixed confidence.
+        struct Reader(Option<f32>);
+
+        impl AudioTranscriber for Reader {
+            fn transcribe(&self, audio: &[u8]) -> impl Stream<Item = Segment> + Send {
+                let mut segment = Segment::new(String::from_utf8_lossy(audio).into_owned());
+                segment.confidence = self.0;
+                futures_lite::stream::iter(vec![segment])
+            }
+        }
+
+        let secs = Duration::from_secs;
+        let clip = |audio: &[u8], start: Duration, end: Duration| {
+            let range =
+                usize::try_from(start.as_secs()).ok()?..usize::try_from(end.as_secs()).ok()?;
+            audio.get(range).map(<[u8]>::to_vec)
+        };
+        let transcript: Transcript = [
+            Segment::new("a")
+                .with_span(secs(0), secs(1))
+                .with_confidence(0.9),
+            Segment::new("?")
+                .with_span(secs(1), secs(3))
+                .with_confidence(0.2),
+            Segment::new("?").with_span(secs(3), secs(4)),
+            Segment::new("?")
+                .with_span(secs(4), secs(9))
+                .with_confidence(0.1),
+        ]
+        .into_iter()
+        .collect();
+
+        let refined = Retranscriber::new(Reader(Some(0.95)), clip)
+            .refine(b"abcd", transcript.clone())
+            .await;
+        assert_eq!(refined.text(), "a bc ? ?");
+        assert_eq!(refined.segments[1].confidence, Some(0.95));
+        assert_eq!(refined.segments[1].end, secs(3));
+
+        let refined = Retranscriber::new(Reader(Some(0.05)), clip)
+            .refine(b"abcd", transcript)
+            .await;
+        assert_eq!(refined.text(), "a ? ? ?");
+    }
+
+    #[test]
+    fn ssml_is_escaped_and_stripped() {
+        let ssml = Ssml::new()
+            .text("Fish & chips")
+            .pause(Duration::from_secs(1))
+            .emphasis(Emphasis::Reduced, "<cheap>")
+            .say_as("characters", "AI");
+        let input = SpeechInput::from(ssml);
+
+        let SpeechInput::Ssml(markup) = &input else {
+            panic!("expected SSML");
+        };
+        assert!(markup.contains("Fish &amp; chips<break time=\"1000ms\"/>"));
+        assert!(markup.contains("&lt;cheap&gt;"));
+        assert_eq!(input.text(), "Fish & chips <cheap>AI");
+        assert_eq!(SpeechInput::from("  as is ").text(), "  as is ");
+    }
+
+    #[tokio::test]
+    async fn synthesize_speaks_plain_text_by_default() {
+        struct Echo;
+
+        impl AudioGenerator for Echo {
+            fn generate(&self, prompt: &str) -> impl Stream<Item = Data> + Send {
+                futures_lite::stream::iter(vec![prompt.as_bytes().to_vec()])
+            }
+
+            fn profile(&self) -> Profile {
+                Profile::new("echo", "Speaks its prompt")
+            }
+        }
+
+        let ssml = Ssml::new()
+            .text("Wait")
+            .pause(Duration::from_millis(500))
+            .text("go");
+        let audio: Vec<Data> = Echo.synthesize(ssml).collect().await;
+        assert_eq!(audio, [b"Wait go".to_vec()]);
+    }
+
     #[tokio::test]
     async fn audio_generator_empty_prompt() {
         let generator = MockAudioGenerator;
@@ -159,8 +989,8 @@ mod tests {
         let mut stream = transcriber.transcribe(&audio_data);
 
         let mut text_chunks = Vec::new();
-        while let Some(chunk) = stream.next().await {
-            text_chunks.push(chunk);
+        while let Some(segment) = stream.next().await {
+            text_chunks.push(segment.text);
         }
 
         assert_eq!(text_chunks.len(), 1);
@@ -174,8 +1004,8 @@ mod tests {
         let mut stream = transcriber.transcribe(&audio_data);
 
         let mut text_chunks = Vec::new();
-        while let Some(chunk) = stream.next().await {
-            text_chunks.push(chunk);
+        while let Some(segment) = stream.next().await {
+            text_chunks.push(segment.text);
         }
 
         assert_eq!(text_chunks.len(), 2);
@@ -190,8 +1020,8 @@ mod tests {
         let mut stream = transcriber.transcribe(&audio_data);
 
         let mut text_chunks = Vec::new();
-        while let Some(chunk) = stream.next().await {
-            text_chunks.push(chunk);
+        while let Some(segment) = stream.next().await {
+            text_chunks.push(segment.text);
         }
 
         assert_eq!(text_chunks.len(), 5);
@@ -206,8 +1036,8 @@ mod tests {
         let mut stream = transcriber.transcribe(&audio_data);
 
         let mut text_chunks = Vec::new();
-        while let Some(chunk) = stream.next().await {
-            text_chunks.push(chunk);
+        while let Some(segment) = stream.next().await {
+            text_chunks.push(segment.text);
         }
 
         assert!(text_chunks.is_empty());
@@ -271,8 +1101,8 @@ mod tests {
         let mut transcription_stream = transcriber.transcribe(&all_audio_data);
 
         let mut transcription_chunks = Vec::new();
-        while let Some(chunk) = transcription_stream.next().await {
-            transcription_chunks.push(chunk);
+        while let Some(segment) = transcription_stream.next().await {
+            transcription_chunks.push(segment.text);
         }
 
         // Verify the workflow
@@ -282,4 +1112,88 @@ mod tests {
         let full_transcription: String = transcription_chunks.join("");
         assert_eq!(full_transcription, "This is a longer transcription");
     }
+
+    #[test]
+    fn estimate_duration_uses_speaking_rate() {
+        let generator = MockAudioGenerator;
+        let text = "word ".repeat(150);
+        assert_eq!(generator.estimate_duration(&text), Duration::from_secs(60));
+        assert_eq!(generator.estimate_duration(""), Duration::ZERO);
+
+        let silent = Profile::new("silent", "Never speaks").with_words_per_minute(0);
+        assert_eq!(silent.estimate_duration("hello"), Duration::ZERO);
+    }
+
+    #[test]
+    fn split_script_respects_max_characters() {
+        let profile = MockAudioGenerator.profile();
+        let script = "Hello there. This script is long enough to need splitting, \
+                      with a Supercalifragilisticexpialidociousnessity word.";
+
+        let chunks = profile.split_script(script);
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= 32, "{chunk:?} is too long");
+            assert!(!chunk.is_empty());
+        }
+        assert_eq!(chunks.concat().replace(' ', ""), script.replace(' ', ""));
+    }
+
+    #[test]
+    fn split_script_without_limit() {
+        let profile = Profile::new("unlimited", "No character limit");
+        assert_eq!(profile.split_script("  Hello world  "), ["Hello world"]);
+        assert!(profile.split_script("   ").is_empty());
+    }
+
+    #[tokio::test]
+    async fn transcribe_stream_defaults_to_whole_recording() {
+        let audio = futures_lite::stream::iter(vec![vec![0x01; 300], vec![0x02; 200]]);
+        let events: Vec<_> = MockAudioTranscriber
+            .transcribe_stream(audio)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(
+            events,
+            [
+                TranscriptEvent::Final(Segment::new("Hello")),
+                TranscriptEvent::Final(Segment::new(" world")),
+            ]
+        );
+        assert!(events[1].is_final());
+        assert_eq!(events[1].text(), " world");
+    }
+
+    #[tokio::test]
+    async fn transcript_joins_timed_segments() {
+        let transcript: Transcript = MockAudioTranscriber
+            .transcribe(&[0x01; 500])
+            .collect()
+            .await;
+        assert_eq!(transcript.text(), "Hello world");
+
+        let transcript = Transcript::from_iter([
+            Segment::new("Hi.")
+                .with_span(Duration::ZERO, Duration::from_millis(800))
+                .with_speaker("A"),
+            Segment::new("  ").with_span(Duration::from_secs(1), Duration::from_secs(2)),
+            Segment::new("Hello!")
+                .with_span(Duration::from_secs(2), Duration::from_millis(2500))
+                .with_speaker("B")
+                .with_confidence(0.9),
+        ]);
+        assert_eq!(transcript.text(), "Hi. Hello!");
+        assert_eq!(transcript.duration(), Duration::from_millis(2500));
+        assert_eq!(
+            transcript.segments[2].duration(),
+            Duration::from_millis(500)
+        );
+    }
+
+    #[tokio::test]
+    async fn generate_events_defaults_to_audio() {
+        let events: Vec<_> = MockAudioGenerator.generate_events("Hi").collect().await;
+        assert_eq!(events, vec![SpeechEvent::Audio(vec![0x01; 512])]);
+    }
 }
@@ -50,10 +50,38 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! ## Vector Stores
+//!
+//! Retrieval pipelines store embeddings in a vector database and look up the closest
+//! ones at query time. The [`VectorStore`] trait abstracts over databases such as Qdrant
+//! or pgvector, so they can be swapped like models. [`MemoryVectorStore`] is a simple
+//! in-memory implementation for tests and small corpora.
+//!
+//! Documents are embedded with [`embed_document`](EmbeddingModel::embed_document) and
+//! questions with [`embed_query`](EmbeddingModel::embed_query), so models that embed
+//! the two differently retrieve as intended.
+//!
+//! ```rust
+//! use ai_types::{EmbeddingModel, embedding::{VectorRecord, VectorStore}};
+//!
+//! async fn retrieve(
+//!     model: &impl EmbeddingModel,
+//!     store: &impl VectorStore,
+//!     question: &str,
+//! ) -> ai_types::Result<Vec<String>> {
+//!     let embedding = model.embed_query(question).await?;
+//!     let matches = store.query(&embedding, 3).await?;
+//!     Ok(matches.into_iter().map(|found| found.record.id).collect())
+//! }
+//! ```
 
-use alloc::vec::Vec;
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
 use core::future::Future;
 
+use serde_json::Value;
+use spin::Mutex;
+
 /// A type alias for an embedding vector of 32-bit floats.
 ///
 /// Embeddings are dense vector representations where each dimension captures
@@ -129,6 +157,546 @@ pub trait EmbeddingModel {
     /// A [`Vec<f32>`] with length equal to [`Self::dim`](EmbeddingModel::dim).
     /// The vector represents the semantic meaning of the input text in high-dimensional space.
     fn embed(&self, text: &str) -> impl Future<Output = crate::Result<Vec<f32>>> + Send;
+
+    /// Returns the dimensions [`embed_with_dim`](Self::embed_with_dim) accepts, in
+    /// ascending order.
+    ///
+    /// Models trained with Matryoshka representation learning, such as `OpenAI`
+    /// `text-embedding-3` or Nomic Embed, keep most of their quality when their vectors
+    /// are shortened, and override this method to list the dimensions they support.
+    ///
+    /// The default implementation returns only [`dim`](Self::dim).
+    fn supported_dims(&self) -> Vec<usize> {
+        alloc::vec![self.dim()]
+    }
+
+    /// Converts text to an embedding vector of `dim` dimensions.
+    ///
+    /// The default implementation [truncates](truncate_embedding) the vector from
+    /// [`embed`](Self::embed), which is how Matryoshka embeddings are shortened. Providers
+    /// that shorten vectors server-side override it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `dim` is not one of the [supported dimensions](Self::supported_dims)
+    /// or exceeds [`dim`](Self::dim), or if embedding fails.
+    fn embed_with_dim(
+        &self,
+        text: &str,
+        dim: usize,
+    ) -> impl Future<Output = crate::Result<Vec<f32>>> + Send {
+        let supported = self.supported_dims();
+        let valid = dim <= self.dim() && supported.contains(&dim);
+        let embedding = self.embed(text);
+        async move {
+            if !valid {
+                anyhow::bail!(
+                    "Embedding dimension {dim} is not supported, expected one of {supported:?}"
+                );
+            }
+            Ok(truncate_embedding(embedding.await?, dim))
+        }
+    }
+
+    /// Converts text to an embedding vector for the given [`EmbeddingTask`].
+    ///
+    /// Models like E5, BGE or Cohere's embed retrieve better when queries and documents
+    /// are embedded differently, with instruction prefixes or an input type parameter.
+    /// Implementations for such models override this method.
+    ///
+    /// The default implementation ignores the task and calls [`embed`](Self::embed).
+    fn embed_for(
+        &self,
+        text: &str,
+        task: EmbeddingTask,
+    ) -> impl Future<Output = crate::Result<Vec<f32>>> + Send {
+        let _ = task;
+        self.embed(text)
+    }
+
+    /// Embeds a search query, to be compared with embeddings from
+    /// [`embed_document`](Self::embed_document).
+    fn embed_query(&self, query: &str) -> impl Future<Output = crate::Result<Vec<f32>>> + Send {
+        self.embed_for(query, EmbeddingTask::Query)
+    }
+
+    /// Embeds a document to be retrieved by queries embedded with
+    /// [`embed_query`](Self::embed_query).
+    fn embed_document(
+        &self,
+        document: &str,
+    ) -> impl Future<Output = crate::Result<Vec<f32>>> + Send {
+        self.embed_for(document, EmbeddingTask::Document)
+    }
+}
+
+/// What an embedding is used for, passed to [`EmbeddingModel::embed_for`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum EmbeddingTask {
+    /// A search query, matched against documents.
+    Query,
+    /// A document stored for retrieval.
+    Document,
+    /// Text compared with other text of the same kind, as in deduplication.
+    Similarity,
+    /// Text fed to a classifier.
+    Classification,
+    /// Text grouped with similar texts.
+    Clustering,
+}
+
+/// An embedding stored in a [`VectorStore`], with an identifier and a payload.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct VectorRecord {
+    /// The identifier of the record, unique within the store.
+    pub id: String,
+    /// The embedding vector.
+    pub embedding: Embedding,
+    /// Data returned with the record, such as the embedded text and its source.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub payload: Value,
+}
+
+impl VectorRecord {
+    /// Creates a record without a payload.
+    pub fn new(id: impl Into<String>, embedding: Embedding) -> Self {
+        Self {
+            id: id.into(),
+            embedding,
+            payload: Value::Null,
+        }
+    }
+
+    /// Sets the data returned with the record.
+    #[must_use]
+    pub fn with_payload(mut self, payload: impl Into<Value>) -> Self {
+        self.payload = payload.into();
+        self
+    }
+}
+
+/// A record returned by [`VectorStore::query`], with its similarity to the query.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct VectorMatch {
+    /// The matching record.
+    pub record: VectorRecord,
+    /// The similarity to the query; higher is more similar.
+    ///
+    /// The scale depends on the store's distance metric.
+    pub score: f32,
+}
+
+impl VectorMatch {
+    /// Creates a match of `record` with `score`.
+    #[must_use]
+    pub const fn new(record: VectorRecord, score: f32) -> Self {
+        Self { record, score }
+    }
+}
+
+/// Stores embeddings and finds the ones closest to a query.
+///
+/// This trait provides a unified interface for vector databases (Qdrant, pgvector,
+/// in-memory indexes, etc.), so retrieval pipelines can swap them like models.
+///
+/// See the [module documentation](crate::embedding) for an example.
+///
+/// # Implementation Requirements
+///
+/// - [`upsert`](VectorStore::upsert) replaces records whose ID already exists
+/// - [`query`](VectorStore::query) returns at most `top_k` matches, most similar first
+/// - Deleting a record that does not exist should succeed
+pub trait VectorStore: Send + Sync {
+    /// Inserts `records`, replacing stored records with the same IDs.
+    fn upsert(&self, records: Vec<VectorRecord>) -> impl Future<Output = crate::Result<()>> + Send;
+
+    /// Returns the `top_k` records most similar to `embedding`, most similar first.
+    fn query(
+        &self,
+        embedding: &[f32],
+        top_k: usize,
+    ) -> impl Future<Output = crate::Result<Vec<VectorMatch>>> + Send;
+
+    /// Deletes the record with the given ID.
+    fn delete(&self, id: &str) -> impl Future<Output = crate::Result<()>> + Send;
+}
+
+/// A [`VectorStore`] keeping records in memory and ranking them by cosine similarity.
+///
+/// Queries scan every record, which is fast enough for tests and corpora of a few
+/// thousand records.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::embedding::{MemoryVectorStore, VectorRecord, VectorStore};
+///
+/// # tokio_test::block_on(async {
+/// let store = MemoryVectorStore::new();
+/// store
+///     .upsert(vec![
+///         VectorRecord::new("cats", vec![1.0, 0.0]).with_payload("Cats purr."),
+///         VectorRecord::new("dogs", vec![0.0, 1.0]).with_payload("Dogs bark."),
+///     ])
+///     .await
+///     .unwrap();
+///
+/// let matches = store.query(&[0.9, 0.1], 1).await.unwrap();
+/// assert_eq!(matches[0].record.payload, "Cats purr.");
+/// # });
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryVectorStore {
+    records: Mutex<BTreeMap<String, VectorRecord>>,
+}
+
+impl MemoryVectorStore {
+    /// Creates an empty store.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            records: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Returns the number of stored records.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.records.lock().len()
+    }
+
+    /// Returns whether the store is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.records.lock().is_empty()
+    }
+
+    /// Returns the stored record with the given ID.
+    #[must_use]
+    pub fn get(&self, id: &str) -> Option<VectorRecord> {
+        self.records.lock().get(id).cloned()
+    }
+
+    /// Returns the dimension of the stored embeddings, if any are stored.
+    fn dim(records: &BTreeMap<String, VectorRecord>) -> Option<usize> {
+        records.values().next().map(|record| record.embedding.len())
+    }
+}
+
+impl VectorStore for MemoryVectorStore {
+    async fn upsert(&self, records: Vec<VectorRecord>) -> crate::Result<()> {
+        let mut stored = self.records.lock();
+        let dim =
+            Self::dim(&stored).or_else(|| records.first().map(|record| record.embedding.len()));
+        if let Some(record) = records
+            .iter()
+            .find(|record| Some(record.embedding.len()) != dim)
+        {
+            anyhow::bail!(
+                "Embedding of record `{}` has {} dimensions, expected {}",
+                record.id,
+                record.embedding.len(),
+                dim.unwrap_or_default()
+            );
+        }
+        for record in records {
+            stored.insert(record.id.clone(), record);
+        }
+        drop(stored);
+        Ok(())
+    }
+
+    async fn query(&self, embedding: &[f32], top_k: usize) -> crate::Result<Vec<VectorMatch>> {
+        let records = self.records.lock();
+        if let Some(dim) = Self::dim(&records).filter(|dim| *dim != embedding.len()) {
+            anyhow::bail!(
+                "Query embedding has {} dimensions, expected {dim}",
+                embedding.len()
+            );
+        }
+        let mut matches: Vec<VectorMatch> = records
+            .values()
+            .map(|record| {
+                VectorMatch::new(
+                    record.clone(),
+                    cosine_similarity(embedding, &record.embedding),
+                )
+            })
+            .collect();
+        drop(records);
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(top_k);
+        Ok(matches)
+    }
+
+    async fn delete(&self, id: &str) -> crate::Result<()> {
+        self.records.lock().remove(id);
+        Ok(())
+    }
+}
+
+/// Shortens an embedding to its first `dim` dimensions and rescales it to unit length.
+///
+/// This is how embeddings from Matryoshka models are shortened; for other models the
+/// shortened vector loses most of its meaning. Embeddings already at most `dim` long are
+/// only rescaled.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::embedding::truncate_embedding;
+///
+/// assert_eq!(truncate_embedding(vec![3.0, 4.0, 12.0], 2), [0.6, 0.8]);
+/// ```
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub fn truncate_embedding(mut embedding: Embedding, dim: usize) -> Embedding {
+    embedding.truncate(dim);
+    let norm = sqrt(
+        embedding
+            .iter()
+            .map(|x| f64::from(*x) * f64::from(*x))
+            .sum(),
+    );
+    if norm > 0.0 {
+        for x in &mut embedding {
+            *x = (f64::from(*x) / norm) as f32;
+        }
+    }
+    embedding
+}
+
+/// Returns the dot product of two embeddings.
+///
+/// For embeddings normalized to unit length, this equals their cosine similarity and is
+/// cheaper to compute.
+///
+/// # Panics
+///
+/// Panics if the embeddings have different lengths.
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub fn dot_product(a: &[f32], b: &[f32]) -> f32 {
+    assert_eq!(a.len(), b.len(), "Embeddings must have the same length");
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| f64::from(*x) * f64::from(*y))
+        .sum::<f64>() as f32
+}
+
+/// Returns the cosine similarity of two embeddings, from -1 (opposite) to 1 (same
+/// direction).
+///
+/// Returns 0 if either embedding is all zeros.
+///
+/// # Panics
+///
+/// Panics if the embeddings have different lengths.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::embedding::cosine_similarity;
+///
+/// assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+/// assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 3.0]).abs() < 1e-6);
+/// ```
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    assert_eq!(a.len(), b.len(), "Embeddings must have the same length");
+    let (mut dot, mut norm_a, mut norm_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b).map(|(x, y)| (f64::from(*x), f64::from(*y))) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    (dot / sqrt(norm_a * norm_b)) as f32
+}
+
+/// Returns the Euclidean distance between two embeddings.
+///
+/// # Panics
+///
+/// Panics if the embeddings have different lengths.
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub fn euclidean_distance(a: &[f32], b: &[f32]) -> f32 {
+    assert_eq!(a.len(), b.len(), "Embeddings must have the same length");
+    let squared: f64 = a
+        .iter()
+        .zip(b)
+        .map(|(x, y)| {
+            let difference = f64::from(*x) - f64::from(*y);
+            difference * difference
+        })
+        .sum();
+    sqrt(squared) as f32
+}
+
+/// Returns the indices and cosine similarities of the `k` embeddings in `corpus` most
+/// similar to `query`, most similar first.
+///
+/// # Panics
+///
+/// Panics if an embedding in `corpus` has a different length than `query`.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::embedding::top_k_similar;
+///
+/// let corpus = [vec![1.0, 0.0], vec![0.0, 1.0], vec![0.7, 0.7]];
+/// let nearest = top_k_similar(&[1.0, 0.1], &corpus, 2);
+/// assert_eq!(nearest.iter().map(|(index, _)| *index).collect::<Vec<_>>(), [0, 2]);
+/// ```
+#[must_use]
+pub fn top_k_similar<E: AsRef<[f32]>>(query: &[f32], corpus: &[E], k: usize) -> Vec<(usize, f32)> {
+    let mut scores: Vec<(usize, f32)> = corpus
+        .iter()
+        .map(|embedding| cosine_similarity(query, embedding.as_ref()))
+        .enumerate()
+        .collect();
+    scores.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    scores.truncate(k);
+    scores
+}
+
+/// A similarity threshold chosen by [`calibrate_threshold`], with how it performs on the
+/// labeled pairs it was chosen from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Calibration {
+    /// The cosine similarity at or above which pairs are considered similar.
+    pub threshold: f32,
+    /// The fraction of pairs at or above the threshold that are labeled similar.
+    pub precision: f32,
+    /// The fraction of pairs labeled similar that are at or above the threshold.
+    pub recall: f32,
+    /// The harmonic mean of precision and recall, which the threshold maximizes.
+    pub f1: f32,
+    /// The fraction of all pairs the threshold classifies as labeled.
+    pub accuracy: f32,
+}
+
+/// Chooses the similarity threshold that best separates pairs labeled similar from
+/// pairs labeled dissimilar.
+///
+/// `scored` holds the similarity of each pair and whether it is labeled similar. The
+/// threshold maximizes the F1 score of classifying pairs as similar when their
+/// similarity is at or above it, and lies halfway between the similarities it separates.
+/// Of equally good thresholds, the highest is chosen.
+///
+/// Cosine similarities of unrelated texts differ widely between embedding models, so a
+/// threshold calibrated on a few dozen labeled pairs from the model in use beats a
+/// guess. [`calibrate`] embeds and scores the pairs first.
+///
+/// Returns `None` if no pair is labeled similar.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::embedding::calibrate_threshold;
+///
+/// let scored = [(0.92, true), (0.85, true), (0.81, false), (0.78, true), (0.40, false)];
+/// let calibration = calibrate_threshold(&scored).unwrap();
+/// assert!((calibration.threshold - 0.59).abs() < 1e-6);
+/// assert!((calibration.recall - 1.0).abs() < 1e-6);
+/// assert!((calibration.precision - 0.75).abs() < 1e-6);
+/// ```
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn calibrate_threshold(scored: &[(f32, bool)]) -> Option<Calibration> {
+    let positives = scored.iter().filter(|(_, similar)| *similar).count();
+    if positives == 0 {
+        return None;
+    }
+    let mut sorted = scored.to_vec();
+    sorted.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+    let mut best: Option<Calibration> = None;
+    let mut true_positives = 0;
+    for (index, (score, similar)) in sorted.iter().enumerate() {
+        true_positives += usize::from(*similar);
+        let next = sorted.get(index + 1).map(|(next, _)| *next);
+        // Pairs with the same similarity fall on the same side of any threshold.
+        if next == Some(*score) {
+            continue;
+        }
+        let predicted = index + 1;
+        let precision = true_positives as f32 / predicted as f32;
+        let recall = true_positives as f32 / positives as f32;
+        let f1 = if true_positives == 0 {
+            0.0
+        } else {
+            2.0 * precision * recall / (precision + recall)
+        };
+        if best.is_some_and(|best| best.f1 >= f1) {
+            continue;
+        }
+        let true_negatives = sorted.len() - predicted - (positives - true_positives);
+        best = Some(Calibration {
+            threshold: next.map_or(*score, |next| (score + next) / 2.0),
+            precision,
+            recall,
+            f1,
+            accuracy: (true_positives + true_negatives) as f32 / sorted.len() as f32,
+        });
+    }
+    best
+}
+
+/// Embeds labeled text pairs with `model` and chooses the cosine similarity threshold
+/// that best separates them.
+///
+/// `pairs` holds two texts and whether they are labeled similar. See
+/// [`calibrate_threshold`] for how the threshold is chosen.
+///
+/// # Errors
+///
+/// Returns an error if embedding fails, or if no pair is labeled similar.
+pub async fn calibrate<M: EmbeddingModel + Sync>(
+    model: &M,
+    pairs: &[(&str, &str, bool)],
+) -> crate::Result<Calibration> {
+    let mut embeddings: BTreeMap<&str, Embedding> = BTreeMap::new();
+    let mut scored = Vec::with_capacity(pairs.len());
+    for &(a, b, similar) in pairs {
+        for text in [a, b] {
+            if !embeddings.contains_key(text) {
+                embeddings.insert(text, model.embed(text).await?);
+            }
+        }
+        scored.push((cosine_similarity(&embeddings[a], &embeddings[b]), similar));
+    }
+    calibrate_threshold(&scored)
+        .ok_or_else(|| anyhow::anyhow!("Calibration needs at least one pair labeled similar"))
+}
+
+/// Square root of a non-negative, finite number.
+///
+/// `f64::sqrt` is not available in `core`, so this refines a bit-level estimate with
+/// Newton's method.
+pub(crate) fn sqrt(value: f64) -> f64 {
+    if value <= 0.0 {
+        return 0.0;
+    }
+    // Halving the exponent bits gives an estimate within a few percent.
+    let mut root = f64::from_bits((value.to_bits() >> 1) + (1023 << 51));
+    for _ in 0..5 {
+        root = 0.5 * (root + value / root);
+    }
+    root
 }
 
 #[cfg(test)]
@@ -214,4 +782,192 @@ mod tests {
         assert!((embedding[0] - 0.09).abs() < f32::EPSILON); // text length 9 + index 0 = 9 * 0.01
         assert!((embedding[1535] - 15.44).abs() < 0.01); // text length 9 + index 1535 = 1544 * 0.01
     }
+
+    #[tokio::test]
+    async fn memory_store_ranks_by_similarity() {
+        let store = MemoryVectorStore::new();
+        store
+            .upsert(vec![
+                VectorRecord::new("north", vec![0.0, 1.0]),
+                VectorRecord::new("east", vec![1.0, 0.0]),
+                VectorRecord::new("north-east", vec![1.0, 1.0]),
+            ])
+            .await
+            .unwrap();
+
+        let matches = store.query(&[0.1, 2.0], 2).await.unwrap();
+        let ids: Vec<_> = matches
+            .iter()
+            .map(|found| found.record.id.as_str())
+            .collect();
+        assert_eq!(ids, ["north", "north-east"]);
+        assert!((matches[1].score - 0.7415).abs() < 1e-4);
+
+        store.delete("north").await.unwrap();
+        store.delete("missing").await.unwrap();
+        assert_eq!(store.query(&[0.1, 2.0], 5).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn memory_store_replaces_and_checks_dimensions() {
+        let store = MemoryVectorStore::new();
+        store
+            .upsert(vec![
+                VectorRecord::new("a", vec![1.0, 0.0]).with_payload("old"),
+            ])
+            .await
+            .unwrap();
+        store
+            .upsert(vec![
+                VectorRecord::new("a", vec![0.0, 1.0]).with_payload("new"),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.get("a").unwrap().payload, "new");
+
+        assert!(
+            store
+                .upsert(vec![VectorRecord::new("b", vec![1.0])])
+                .await
+                .is_err()
+        );
+        assert!(store.query(&[1.0, 0.0, 0.0], 1).await.is_err());
+        assert!(store.get("b").is_none());
+    }
+
+    #[test]
+    fn square_roots() {
+        assert!(sqrt(0.0).abs() < f64::EPSILON);
+        for value in [1e-12, 0.25, 2.0, 1e6, 1e300] {
+            let root = sqrt(value);
+            assert!((root * root - value).abs() <= value * 1e-12);
+        }
+    }
+
+    #[test]
+    fn similarity_measures() {
+        let a = [3.0, 4.0];
+        let b = [4.0, 3.0];
+        assert!((dot_product(&a, &b) - 24.0).abs() < 1e-6);
+        assert!((cosine_similarity(&a, &b) - 0.96).abs() < 1e-6);
+        assert!((cosine_similarity(&a, &[-3.0, -4.0]) + 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&a, &[0.0, 0.0]).abs() < f32::EPSILON);
+        assert!((euclidean_distance(&a, &b) - core::f32::consts::SQRT_2).abs() < 1e-6);
+        assert!(euclidean_distance(&a, &a).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn top_k_orders_by_similarity() {
+        let corpus: Vec<&[f32]> = vec![&[0.0, 1.0], &[1.0, 0.0], &[1.0, 1.0]];
+        let nearest = top_k_similar(&[1.0, 0.2], &corpus, 5);
+        let order: Vec<usize> = nearest.iter().map(|(index, _)| *index).collect();
+        assert_eq!(order, [1, 2, 0]);
+        assert!(top_k_similar(&[1.0, 0.2], &corpus, 0).is_empty());
+    }
+
+    #[test]
+    fn calibration_maximizes_f1() {
+        let scored = [
+            (0.9, true),
+            (0.8, false),
+            (0.8, true),
+            (0.7, true),
+            (0.3, false),
+            (0.2, false),
+        ];
+        let calibration = calibrate_threshold(&scored).unwrap();
+        assert!((calibration.threshold - 0.5).abs() < 1e-6);
+        assert!((calibration.precision - 0.75).abs() < 1e-6);
+        assert!((calibration.recall - 1.0).abs() < 1e-6);
+        assert!((calibration.accuracy - 5.0 / 6.0).abs() < 1e-6);
+
+        let separable = calibrate_threshold(&[(0.6, true), (0.1, false)]).unwrap();
+        assert!((separable.f1 - 1.0).abs() < 1e-6);
+        assert!(calibrate_threshold(&[(0.5, false)]).is_none());
+    }
+
+    #[tokio::test]
+    async fn calibrate_embeds_each_text_once() {
+        let model = MockEmbeddingModel { dimension: 2 };
+        let pairs = [("a", "b", true), ("a", "abcdefghij", false)];
+        let calibration = calibrate(&model, &pairs).await.unwrap();
+        assert!((calibration.f1 - 1.0).abs() < 1e-6);
+        assert!(calibrate(&model, &[("a", "b", false)]).await.is_err());
+    }
+
+    /// Embeds queries and documents into different halves of the vector, like E5 prefixes.
+    struct Asymmetric;
+
+    impl EmbeddingModel for Asymmetric {
+        fn dim(&self) -> usize {
+            2
+        }
+
+        async fn embed(&self, _text: &str) -> crate::Result<Vec<f32>> {
+            Ok(vec![1.0, 1.0])
+        }
+
+        async fn embed_for(&self, _text: &str, task: EmbeddingTask) -> crate::Result<Vec<f32>> {
+            Ok(match task {
+                EmbeddingTask::Query => vec![1.0, 0.0],
+                EmbeddingTask::Document => vec![0.0, 1.0],
+                _ => vec![1.0, 1.0],
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn queries_and_documents_default_to_embed() {
+        let model = MockEmbeddingModel { dimension: 3 };
+        let plain = model.embed("question").await.unwrap();
+        assert_eq!(model.embed_query("question").await.unwrap(), plain);
+        assert_eq!(model.embed_document("question").await.unwrap(), plain);
+    }
+
+    #[tokio::test]
+    async fn queries_and_documents_use_their_task() {
+        let model = Asymmetric;
+        assert_eq!(model.embed_query("q").await.unwrap(), [1.0, 0.0]);
+        assert_eq!(model.embed_document("d").await.unwrap(), [0.0, 1.0]);
+        let clustered = model
+            .embed_for("c", EmbeddingTask::Clustering)
+            .await
+            .unwrap();
+        assert_eq!(clustered, model.embed("c").await.unwrap());
+    }
+
+    /// A Matryoshka model whose vectors can be shortened to 2 or 4 dimensions.
+    struct Nested;
+
+    impl EmbeddingModel for Nested {
+        fn dim(&self) -> usize {
+            4
+        }
+
+        fn supported_dims(&self) -> Vec<usize> {
+            vec![2, 4]
+        }
+
+        async fn embed(&self, _text: &str) -> crate::Result<Vec<f32>> {
+            Ok(vec![3.0, 4.0, 0.0, 0.0])
+        }
+    }
+
+    #[tokio::test]
+    async fn embeds_with_supported_dimensions() {
+        assert_eq!(Nested.embed_with_dim("a", 2).await.unwrap(), [0.6, 0.8]);
+        assert_eq!(Nested.embed_with_dim("a", 4).await.unwrap().len(), 4);
+        assert!(Nested.embed_with_dim("a", 3).await.is_err());
+
+        let model = MockEmbeddingModel { dimension: 3 };
+        assert_eq!(model.supported_dims(), [3]);
+        assert!(model.embed_with_dim("a", 2).await.is_err());
+    }
+
+    #[test]
+    #[should_panic = "same length"]
+    fn mismatched_lengths_panic() {
+        let _ = cosine_similarity(&[1.0], &[1.0, 2.0]);
+    }
 }
@@ -0,0 +1,279 @@
+//! Provider-agnostic error classification.
+//!
+//! Every provider reports failures differently, but applications react to a handful of
+//! categories: back off when rate limited, retry when the provider is down, fix the
+//! request when it is invalid. [`ErrorKind`] names these categories, and the [`Classify`]
+//! trait lets model errors report theirs, so middleware such as
+//! [`Retry`](crate::middleware::Retry) can decide what to do without knowing the provider.
+//!
+//! Provider adapters can use [`ProviderError`] as their error type, or implement
+//! [`Classify`] for their own.
+//!
+//! The taxonomy covers the common cases, but some applications need to branch on what a
+//! specific provider said, such as an `insufficient_quota` code. Adapters attach the
+//! status code, error code and body they received as a [`RawError`], which
+//! [`Classify::raw`] exposes through any middleware wrapping the model.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::error::{Classify, ErrorKind, ProviderError, RawError};
+//! use core::time::Duration;
+//!
+//! let error = ProviderError::new(ErrorKind::RateLimited, "Too many requests")
+//!     .with_retry_after(Duration::from_secs(2));
+//!
+//! assert!(error.kind().is_transient());
+//! assert_eq!(error.retry_after(), Some(Duration::from_secs(2)));
+//!
+//! let error = ProviderError::new(ErrorKind::RateLimited, "You exceeded your quota")
+//!     .with_raw(RawError::new().with_status(429).with_code("insufficient_quota"));
+//!
+//! if error.raw().and_then(|raw| raw.code.as_deref()) == Some("insufficient_quota") {
+//!     // Retrying will not help: ask the user to top up their account.
+//! }
+//! ```
+
+use alloc::{boxed::Box, string::String};
+use core::{fmt, time::Duration};
+
+use crate::rate_limit::RateLimitInfo;
+
+/// The category of a model failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The provider rejected the request because a rate limit or quota was exceeded.
+    RateLimited,
+    /// The request timed out.
+    Timeout,
+    /// The provider is unavailable or failed internally, e.g. with an HTTP 5xx status.
+    ProviderUnavailable,
+    /// The request is malformed or unsupported and will fail again if repeated.
+    InvalidRequest,
+    /// The credentials are missing, invalid, or lack permission.
+    Authentication,
+    /// The input or output was blocked by the provider's content policy.
+    ContentFiltered,
+    /// Any other failure.
+    Other,
+}
+
+impl ErrorKind {
+    /// Returns whether a failure of this kind may succeed when retried.
+    ///
+    /// Rate limits, timeouts, and unavailable providers are transient.
+    #[must_use]
+    pub const fn is_transient(self) -> bool {
+        matches!(
+            self,
+            Self::RateLimited | Self::Timeout | Self::ProviderUnavailable
+        )
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::RateLimited => "rate limited",
+            Self::Timeout => "timed out",
+            Self::ProviderUnavailable => "provider unavailable",
+            Self::InvalidRequest => "invalid request",
+            Self::Authentication => "authentication failed",
+            Self::ContentFiltered => "content filtered",
+            Self::Other => "error",
+        })
+    }
+}
+
+/// An error that knows its [`ErrorKind`].
+pub trait Classify {
+    /// Returns the category of the error.
+    fn kind(&self) -> ErrorKind;
+
+    /// Returns how long the provider asked to wait before retrying, if it did.
+    fn retry_after(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Returns the rate limit state the provider reported with the error, if any.
+    fn rate_limit(&self) -> Option<RateLimitInfo> {
+        None
+    }
+
+    /// Returns the error as the provider sent it, if the adapter kept it.
+    fn raw(&self) -> Option<&RawError> {
+        None
+    }
+}
+
+/// An error as a provider sent it, before classification.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[non_exhaustive]
+pub struct RawError {
+    /// The HTTP status code of the response.
+    pub status: Option<u16>,
+    /// The provider's error code, e.g. `insufficient_quota` or `overloaded_error`.
+    pub code: Option<String>,
+    /// The body of the response.
+    pub body: Option<String>,
+}
+
+impl RawError {
+    /// Creates an empty raw error.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            status: None,
+            code: None,
+            body: None,
+        }
+    }
+
+    /// Sets the HTTP status code.
+    #[must_use]
+    pub const fn with_status(mut self, status: u16) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Sets the provider's error code.
+    #[must_use]
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    /// Sets the body of the response.
+    #[must_use]
+    pub fn with_body(mut self, body: impl Into<String>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+}
+
+/// A classified error reported by a provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ProviderError {
+    /// The category of the error.
+    pub kind: ErrorKind,
+    /// The provider's description of the error.
+    pub message: String,
+    /// How long the provider asked to wait before retrying.
+    pub retry_after: Option<Duration>,
+    /// The rate limit state reported with the error.
+    pub rate_limit: Option<RateLimitInfo>,
+    /// The error as the provider sent it, boxed to keep the error small.
+    pub raw: Option<Box<RawError>>,
+}
+
+impl ProviderError {
+    /// Creates an error of the given kind.
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            retry_after: None,
+            rate_limit: None,
+            raw: None,
+        }
+    }
+
+    /// Sets how long the provider asked to wait before retrying.
+    #[must_use]
+    pub const fn with_retry_after(mut self, delay: Duration) -> Self {
+        self.retry_after = Some(delay);
+        self
+    }
+
+    /// Sets the rate limit state reported with the error.
+    #[must_use]
+    pub const fn with_rate_limit(mut self, rate_limit: RateLimitInfo) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Keeps the error as the provider sent it.
+    #[must_use]
+    pub fn with_raw(mut self, raw: RawError) -> Self {
+        self.raw = Some(Box::new(raw));
+        self
+    }
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)
+    }
+}
+
+impl core::error::Error for ProviderError {}
+
+impl Classify for ProviderError {
+    fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns the explicit retry delay, or the time until an exhausted rate limit
+    /// window resets.
+    fn retry_after(&self) -> Option<Duration> {
+        self.retry_after.or_else(|| {
+            self.rate_limit
+                .filter(RateLimitInfo::is_exhausted)
+                .map(|info| info.reset_after)
+        })
+    }
+
+    fn rate_limit(&self) -> Option<RateLimitInfo> {
+        self.rate_limit
+    }
+
+    fn raw(&self) -> Option<&RawError> {
+        self.raw.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exhausted_rate_limit_implies_retry_after() {
+        let exhausted = RateLimitInfo::new(10, 0, Duration::from_secs(7));
+        let error =
+            ProviderError::new(ErrorKind::RateLimited, "slow down").with_rate_limit(exhausted);
+        assert_eq!(error.retry_after(), Some(Duration::from_secs(7)));
+        assert_eq!(error.rate_limit(), Some(exhausted));
+
+        let explicit = error.with_retry_after(Duration::from_secs(1));
+        assert_eq!(explicit.retry_after(), Some(Duration::from_secs(1)));
+
+        let available = RateLimitInfo::new(10, 3, Duration::from_secs(7));
+        let error =
+            ProviderError::new(ErrorKind::ProviderUnavailable, "down").with_rate_limit(available);
+        assert_eq!(error.retry_after(), None);
+    }
+
+    #[test]
+    fn raw_errors_pass_through_wrappers() {
+        use crate::{llm::BoxedError, middleware::FallbackError};
+
+        let raw = RawError::new()
+            .with_status(429)
+            .with_code("insufficient_quota")
+            .with_body(r#"{"error": {"code": "insufficient_quota"}}"#);
+        let error = ProviderError::new(ErrorKind::RateLimited, "quota").with_raw(raw.clone());
+        assert_eq!(error.raw(), Some(&raw));
+
+        let wrapped: FallbackError<ProviderError, ProviderError> =
+            FallbackError::Secondary(error.clone());
+        assert_eq!(wrapped.raw().and_then(|raw| raw.status), Some(429));
+        assert_eq!(BoxedError::new(error).raw(), Some(&raw));
+        assert_eq!(ProviderError::new(ErrorKind::Other, "bare").raw(), None);
+    }
+}
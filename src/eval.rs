@@ -0,0 +1,420 @@
+//! Comparing generated outputs with expected ones.
+//!
+//! Evaluating structured generation needs more than equality: a score off by rounding,
+//! or tags listed in another order, should not count as a failure, and a report should
+//! say which fields were wrong. [`diff`] compares two serializable values field by field
+//! and returns a [`Diff`] listing each [`Difference`] at its JSON Pointer path, along
+//! with the share of expected fields that matched.
+//!
+//! [`DiffOptions`] tolerates small float differences, compares selected arrays as sets,
+//! and ignores fields such as generated IDs.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::eval::{DiffOptions, DifferenceKind};
+//! use serde_json::json;
+//!
+//! let expected = json!({"name": "Ada", "score": 0.9, "tags": ["math", "poetry"]});
+//! let actual = json!({"name": "Ada", "score": 0.900_001, "tags": ["poetry", "maths"]});
+//!
+//! let diff = DiffOptions::new()
+//!     .with_float_tolerance(1e-3)
+//!     .with_unordered("/tags")
+//!     .diff(&actual, &expected)
+//!     .unwrap();
+//!
+//! assert!(!diff.is_match());
+//! assert_eq!(diff.differences.len(), 2);
+//! assert_eq!(diff.differences[0].path, "/tags/0");
+//! assert!(matches!(diff.differences[0].kind, DifferenceKind::Missing { .. }));
+//! assert_eq!(diff.similarity(), 0.75);
+//! ```
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// How two values differ at a path.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum DifferenceKind {
+    /// The expected value is missing from the actual output.
+    Missing {
+        /// The expected value.
+        expected: Value,
+    },
+    /// The actual output has a value that was not expected.
+    Unexpected {
+        /// The actual value.
+        actual: Value,
+    },
+    /// The actual value differs from the expected one.
+    Changed {
+        /// The expected value.
+        expected: Value,
+        /// The actual value.
+        actual: Value,
+    },
+}
+
+/// A difference between the actual and expected output.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Difference {
+    /// The JSON Pointer to the differing value, such as `/items/0/price`.
+    pub path: String,
+    /// How the values differ.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub kind: DifferenceKind,
+}
+
+/// The field-level comparison of an actual output with the expected one.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Diff {
+    /// The differences, in the order of the expected value's fields.
+    pub differences: Vec<Difference>,
+    /// The number of leaf values in the expected output.
+    pub expected_fields: usize,
+    /// The number of expected leaf values matched by the actual output.
+    pub matched_fields: usize,
+}
+
+impl Diff {
+    /// Returns whether the outputs match.
+    #[must_use]
+    pub const fn is_match(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    /// Returns the share of expected leaf values that matched, from 0 to 1.
+    ///
+    /// Unexpected values do not lower the similarity; check
+    /// [`is_match`](Self::is_match) for an exact match.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn similarity(&self) -> f64 {
+        if self.expected_fields == 0 {
+            return if self.is_match() { 1.0 } else { 0.0 };
+        }
+        self.matched_fields as f64 / self.expected_fields as f64
+    }
+}
+
+/// Options for comparing outputs.
+///
+/// See the [module documentation](crate::eval) for an example.
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    float_tolerance: f64,
+    unordered: Vec<String>,
+    all_unordered: bool,
+    ignored: Vec<String>,
+}
+
+impl DiffOptions {
+    /// Creates options comparing numbers exactly and arrays in order.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            float_tolerance: 0.0,
+            unordered: Vec::new(),
+            all_unordered: false,
+            ignored: Vec::new(),
+        }
+    }
+
+    /// Treats numbers as equal when they differ by at most `tolerance`.
+    #[must_use]
+    pub const fn with_float_tolerance(mut self, tolerance: f64) -> Self {
+        self.float_tolerance = tolerance;
+        self
+    }
+
+    /// Compares the array at the JSON Pointer `path` as a set, ignoring order.
+    #[must_use]
+    pub fn with_unordered(mut self, path: impl Into<String>) -> Self {
+        self.unordered.push(path.into());
+        self
+    }
+
+    /// Compares every array as a set, ignoring order.
+    #[must_use]
+    pub const fn with_all_unordered(mut self) -> Self {
+        self.all_unordered = true;
+        self
+    }
+
+    /// Skips the value at the JSON Pointer `path`, such as a generated ID.
+    #[must_use]
+    pub fn with_ignored(mut self, path: impl Into<String>) -> Self {
+        self.ignored.push(path.into());
+        self
+    }
+
+    /// Compares `actual` with `expected` field by field.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either value cannot be serialized to JSON.
+    pub fn diff<A: Serialize, E: Serialize>(
+        &self,
+        actual: &A,
+        expected: &E,
+    ) -> crate::Result<Diff> {
+        let actual = serde_json::to_value(actual)?;
+        let expected = serde_json::to_value(expected)?;
+        let mut diff = Diff::default();
+        self.compare(&mut String::new(), &actual, &expected, &mut diff);
+        Ok(diff)
+    }
+
+    fn compare(&self, path: &mut String, actual: &Value, expected: &Value, diff: &mut Diff) {
+        if self.ignored.iter().any(|ignored| ignored == path) {
+            return;
+        }
+        match (actual, expected) {
+            (Value::Object(actual), Value::Object(expected)) => {
+                for (key, expected) in expected {
+                    let len = path.len();
+                    push_segment(path, key);
+                    match actual.get(key) {
+                        Some(actual) => self.compare(path, actual, expected, diff),
+                        None => self.missing(path, expected, diff),
+                    }
+                    path.truncate(len);
+                }
+                for (key, actual) in actual {
+                    if !expected.contains_key(key) {
+                        let len = path.len();
+                        push_segment(path, key);
+                        self.unexpected(path, actual, diff);
+                        path.truncate(len);
+                    }
+                }
+            }
+            (Value::Array(actual), Value::Array(expected)) => {
+                if self.all_unordered || self.unordered.iter().any(|unordered| unordered == path) {
+                    self.compare_unordered(path, actual, expected, diff);
+                    return;
+                }
+                for index in 0..actual.len().max(expected.len()) {
+                    let len = path.len();
+                    push_segment(path, &index.to_string());
+                    match (actual.get(index), expected.get(index)) {
+                        (Some(actual), Some(expected)) => {
+                            self.compare(path, actual, expected, diff);
+                        }
+                        (None, Some(expected)) => self.missing(path, expected, diff),
+                        (Some(actual), None) => self.unexpected(path, actual, diff),
+                        (None, None) => {}
+                    }
+                    path.truncate(len);
+                }
+            }
+            _ => {
+                diff.expected_fields += leaves(expected);
+                if self.leaf_eq(actual, expected) {
+                    diff.matched_fields += leaves(expected);
+                } else {
+                    diff.differences.push(Difference {
+                        path: path.clone(),
+                        kind: DifferenceKind::Changed {
+                            expected: expected.clone(),
+                            actual: actual.clone(),
+                        },
+                    });
+                }
+            }
+        }
+    }
+
+    /// Pairs each expected element with an equal actual element, in any order.
+    ///
+    /// Unpaired expected elements are missing, at their index in the expected array;
+    /// unpaired actual elements are unexpected, at their index in the actual array.
+    fn compare_unordered(
+        &self,
+        path: &mut String,
+        actual: &[Value],
+        expected: &[Value],
+        diff: &mut Diff,
+    ) {
+        let mut paired = alloc::vec![false; actual.len()];
+        for (index, expected) in expected.iter().enumerate() {
+            let len = path.len();
+            push_segment(path, &index.to_string());
+            let found = (0..actual.len()).find(|candidate| {
+                if paired[*candidate] {
+                    return false;
+                }
+                let mut trial = Diff::default();
+                self.compare(&mut path.clone(), &actual[*candidate], expected, &mut trial);
+                trial.is_match()
+            });
+            if let Some(candidate) = found {
+                paired[candidate] = true;
+                diff.expected_fields += leaves(expected);
+                diff.matched_fields += leaves(expected);
+            } else {
+                self.missing(path, expected, diff);
+            }
+            path.truncate(len);
+        }
+        for (index, actual) in actual.iter().enumerate() {
+            if !paired[index] {
+                let len = path.len();
+                push_segment(path, &index.to_string());
+                self.unexpected(path, actual, diff);
+                path.truncate(len);
+            }
+        }
+    }
+
+    fn missing(&self, path: &str, expected: &Value, diff: &mut Diff) {
+        if self.ignored.iter().any(|ignored| ignored == path) {
+            return;
+        }
+        diff.expected_fields += leaves(expected);
+        diff.differences.push(Difference {
+            path: path.into(),
+            kind: DifferenceKind::Missing {
+                expected: expected.clone(),
+            },
+        });
+    }
+
+    fn unexpected(&self, path: &str, actual: &Value, diff: &mut Diff) {
+        if self.ignored.iter().any(|ignored| ignored == path) {
+            return;
+        }
+        diff.differences.push(Difference {
+            path: path.into(),
+            kind: DifferenceKind::Unexpected {
+                actual: actual.clone(),
+            },
+        });
+    }
+
+    fn leaf_eq(&self, actual: &Value, expected: &Value) -> bool {
+        match (actual, expected) {
+            (Value::Number(actual), Value::Number(expected)) if self.float_tolerance > 0.0 => {
+                match (actual.as_f64(), expected.as_f64()) {
+                    (Some(actual), Some(expected)) => {
+                        (actual - expected).abs() <= self.float_tolerance
+                    }
+                    _ => actual == expected,
+                }
+            }
+            _ => actual == expected,
+        }
+    }
+}
+
+/// Compares `actual` with `expected` field by field, with default options.
+///
+/// # Errors
+///
+/// Returns an error if either value cannot be serialized to JSON.
+pub fn diff<A: Serialize, E: Serialize>(actual: &A, expected: &E) -> crate::Result<Diff> {
+    DiffOptions::new().diff(actual, expected)
+}
+
+/// Appends a JSON Pointer segment, escaping `~` and `/`.
+fn push_segment(path: &mut String, segment: &str) {
+    path.push('/');
+    path.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+}
+
+/// Counts the leaf values of `value`; empty containers count as one leaf.
+fn leaves(value: &Value) -> usize {
+    let count = match value {
+        Value::Object(object) => object.values().map(leaves).sum(),
+        Value::Array(array) => array.iter().map(leaves).sum(),
+        _ => 1,
+    };
+    count.max(1)
+}
+
+impl core::fmt::Display for Difference {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+        match &self.kind {
+            DifferenceKind::Missing { expected } => write!(f, "{path}: missing {expected}"),
+            DifferenceKind::Unexpected { actual } => write!(f, "{path}: unexpected {actual}"),
+            DifferenceKind::Changed { expected, actual } => {
+                write!(f, "{path}: expected {expected}, got {actual}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reports_paths_of_differences() {
+        let expected = json!({"user": {"name": "Ada", "a/b": 1}, "items": [1, 2]});
+        let actual = json!({"user": {"name": "Bob", "a/b": 1, "extra": true}, "items": [1]});
+        let diff = diff(&actual, &expected).unwrap();
+
+        let rendered: Vec<String> = diff.differences.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            rendered,
+            [
+                "/items/1: missing 2",
+                "/user/name: expected \"Ada\", got \"Bob\"",
+                "/user/extra: unexpected true",
+            ]
+        );
+        assert_eq!((diff.matched_fields, diff.expected_fields), (2, 4));
+    }
+
+    #[test]
+    fn tolerates_floats_and_ignores_paths() {
+        let expected = json!({"id": "a1", "price": 9.99, "count": 3});
+        let actual = json!({"id": "z9", "price": 9.990_000_1, "count": 3.0});
+
+        assert!(!diff(&actual, &expected).unwrap().is_match());
+        let diff = DiffOptions::new()
+            .with_float_tolerance(1e-6)
+            .with_ignored("/id")
+            .diff(&actual, &expected)
+            .unwrap();
+        assert!(diff.is_match());
+        assert!((diff.similarity() - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn compares_sets_of_objects() {
+        let expected = json!([{"tag": "a"}, {"tag": "b"}, {"tag": "b"}]);
+        let actual = json!([{"tag": "b"}, {"tag": "c"}, {"tag": "a"}]);
+        let diff = DiffOptions::new()
+            .with_all_unordered()
+            .diff(&actual, &expected)
+            .unwrap();
+
+        let paths: Vec<&str> = diff.differences.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, ["/2", "/1"]);
+        assert!(matches!(
+            diff.differences[1].kind,
+            DifferenceKind::Unexpected { .. }
+        ));
+        assert_eq!((diff.matched_fields, diff.expected_fields), (2, 3));
+    }
+}
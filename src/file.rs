@@ -0,0 +1,212 @@
+//! # File Module
+//!
+//! This module provides the [`FileStore`] trait for uploading files to a provider.
+//!
+//! Several providers require files to be uploaded before they can be referenced, whether
+//! as message attachments, batch job inputs, or fine-tuning data. A [`FileStore`] hides
+//! the provider's upload API behind a uniform interface, and hands back a [`StoredFile`]
+//! whose URL can be attached to a [`Message`](crate::llm::Message).
+//!
+//! ```rust
+//! use ai_types::{FileStore, file::FilePurpose, llm::Message};
+//!
+//! async fn describe(store: &impl FileStore, pdf: &[u8]) -> ai_types::Result<Message> {
+//!     let url = store.upload_attachment("report.pdf", pdf).await?;
+//!     Ok(Message::user("Summarize the attached report").with_attachment(url))
+//! }
+//! ```
+
+use alloc::string::String;
+use core::future::Future;
+use url::Url;
+
+/// What an uploaded file will be used for.
+///
+/// Providers store files differently, or reject them, depending on their purpose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum FilePurpose {
+    /// An attachment referenced in messages.
+    Attachment,
+    /// Input for a batch job.
+    Batch,
+    /// Training data for fine-tuning.
+    FineTune,
+}
+
+/// A file stored by a [`FileStore`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct StoredFile {
+    /// The provider's identifier for the file.
+    pub id: String,
+    /// The file name given when uploading.
+    pub name: String,
+    /// The size of the file in bytes.
+    pub size: u64,
+    /// What the file will be used for.
+    pub purpose: FilePurpose,
+    /// A URL referencing the file, for providers that return one on upload.
+    pub url: Option<Url>,
+}
+
+impl StoredFile {
+    /// Creates a record of a stored file.
+    pub fn new(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        size: u64,
+        purpose: FilePurpose,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            size,
+            purpose,
+            url: None,
+        }
+    }
+
+    /// Sets the URL referencing the file.
+    #[must_use]
+    pub fn with_url(mut self, url: Url) -> Self {
+        self.url = Some(url);
+        self
+    }
+}
+
+/// Stores files with a provider so they can be referenced in requests.
+///
+/// This trait provides a unified interface for provider file APIs (`OpenAI` files,
+/// Gemini files, object storage, etc.).
+///
+/// # Implementation Requirements
+///
+/// - [`url`](FileStore::url) must return a URL the provider accepts in messages, such as
+///   a signed URL or a provider-specific scheme
+/// - Deleting a file that does not exist should succeed
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{FileStore, file::{FilePurpose, StoredFile}};
+/// use std::{collections::HashMap, sync::Mutex};
+/// use url::Url;
+///
+/// #[derive(Default)]
+/// struct MemoryStore(Mutex<HashMap<String, Vec<u8>>>);
+///
+/// impl FileStore for MemoryStore {
+///     async fn upload(&self, name: &str, bytes: &[u8], purpose: FilePurpose) -> ai_types::Result<StoredFile> {
+///         let id = format!("file-{}", self.0.lock().unwrap().len());
+///         self.0.lock().unwrap().insert(id.clone(), bytes.to_vec());
+///         Ok(StoredFile::new(id, name, bytes.len() as u64, purpose))
+///     }
+///
+///     async fn url(&self, id: &str) -> ai_types::Result<Url> {
+///         Ok(Url::parse(&format!("memory://{id}"))?)
+///     }
+///
+///     async fn delete(&self, id: &str) -> ai_types::Result<()> {
+///         self.0.lock().unwrap().remove(id);
+///         Ok(())
+///     }
+/// }
+///
+/// # tokio_test::block_on(async {
+/// let store = MemoryStore::default();
+/// let url = store.upload_attachment("notes.txt", b"hello").await.unwrap();
+/// assert_eq!(url.as_str(), "memory://file-0");
+/// # });
+/// ```
+pub trait FileStore: Send + Sync {
+    /// Uploads `bytes` as a file named `name`.
+    fn upload(
+        &self,
+        name: &str,
+        bytes: &[u8],
+        purpose: FilePurpose,
+    ) -> impl Future<Output = crate::Result<StoredFile>> + Send;
+
+    /// Returns a URL referencing the file with the given ID.
+    fn url(&self, id: &str) -> impl Future<Output = crate::Result<Url>> + Send;
+
+    /// Deletes the file with the given ID.
+    fn delete(&self, id: &str) -> impl Future<Output = crate::Result<()>> + Send;
+
+    /// Uploads `bytes` as an attachment and returns a URL to reference it in messages.
+    ///
+    /// Uses the URL returned on upload when the provider gives one, and asks for it
+    /// with [`url`](FileStore::url) otherwise.
+    fn upload_attachment(
+        &self,
+        name: &str,
+        bytes: &[u8],
+    ) -> impl Future<Output = crate::Result<Url>> + Send {
+        async move {
+            let file = self.upload(name, bytes, FilePurpose::Attachment).await?;
+            match file.url {
+                Some(url) => Ok(url),
+                None => self.url(&file.id).await,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+    use spin::Mutex;
+
+    /// Returns URLs on upload and counts URL lookups.
+    #[derive(Default)]
+    struct Store {
+        with_url: bool,
+        lookups: Mutex<usize>,
+    }
+
+    impl FileStore for Store {
+        async fn upload(
+            &self,
+            name: &str,
+            bytes: &[u8],
+            purpose: FilePurpose,
+        ) -> crate::Result<StoredFile> {
+            let file = StoredFile::new("file-1", name, bytes.len() as u64, purpose);
+            Ok(if self.with_url {
+                file.with_url(Url::parse("https://files.example.com/file-1")?)
+            } else {
+                file
+            })
+        }
+
+        async fn url(&self, id: &str) -> crate::Result<Url> {
+            *self.lookups.lock() += 1;
+            Ok(Url::parse(&format!("provider://{id}"))?)
+        }
+
+        async fn delete(&self, _id: &str) -> crate::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn attachment_prefers_upload_url() {
+        let store = Store {
+            with_url: true,
+            ..Store::default()
+        };
+        let url = store.upload_attachment("a.txt", b"a").await.unwrap();
+        assert_eq!(url.as_str(), "https://files.example.com/file-1");
+        assert_eq!(*store.lookups.lock(), 0);
+
+        let store = Store::default();
+        let url = store.upload_attachment("a.txt", b"a").await.unwrap();
+        assert_eq!(url.as_str(), "provider://file-1");
+        assert_eq!(*store.lookups.lock(), 1);
+    }
+}
@@ -0,0 +1,36 @@
+//! A small non-cryptographic hash for content keys.
+
+use core::{fmt, hash::Hasher};
+
+/// The 64-bit FNV-1a hash, which is simple and available without `std`.
+///
+/// Its output is stable across platforms and releases, so keys derived from it can be
+/// persisted. Not suitable for cryptography.
+#[derive(Debug)]
+pub struct Fnv1a(u64);
+
+impl Fnv1a {
+    pub const fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+impl fmt::Write for Fnv1a {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write(s.as_bytes());
+        Ok(())
+    }
+}
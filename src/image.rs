@@ -1,11 +1,32 @@
 use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
+use async_stream::stream;
 use futures_core::Stream;
+use futures_lite::StreamExt;
 
 /// Image data as bytes.
 ///
 /// Type alias for [`Vec<u8>`] representing image data.
 pub type Data = Vec<u8>;
 
+/// An image produced by [`ImageGenerator::create_many`], tagged with the candidate it
+/// belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ImageChunk {
+    /// The index of the candidate, from `0` to `n - 1`.
+    pub index: usize,
+    /// A complete image of the candidate, at the quality reached so far.
+    pub data: Data,
+}
+
+impl ImageChunk {
+    /// Creates an image of the candidate at `index`.
+    #[must_use]
+    pub const fn new(index: usize, data: Data) -> Self {
+        Self { index, data }
+    }
+}
+
 /// Trait for generating and editing images from prompts and masks.
 ///
 /// Images are returned as a stream where each item represents a complete image
@@ -30,6 +51,55 @@ pub trait ImageGenerator {
         size: Size,
     ) -> impl Stream<Item = Result<Data, Self::Error>> + Unpin + Send;
 
+    /// Creates `n` candidate images from the same prompt.
+    ///
+    /// Each item is a complete image of one candidate, tagged with the candidate's index,
+    /// with progressively improving quality per candidate. Candidates may be interleaved,
+    /// so the latest image of each index is its current state. The stream ends at the
+    /// first error.
+    ///
+    /// The default implementation calls [`create`](ImageGenerator::create) once per
+    /// candidate, one after another. Generators that return several images per request
+    /// should override it, and report their limit in [`Profile::max_images`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ai_types::{ImageGenerator, image::{Data, Prompt, Size}};
+    /// use futures_lite::StreamExt;
+    ///
+    /// async fn candidates(generator: impl ImageGenerator) -> ai_types::Result<Vec<Data>> {
+    ///     let mut images = vec![Data::new(); 4];
+    ///     let mut stream = generator.create_many(Prompt::new("A lighthouse at dusk"), Size::square(1024), 4);
+    ///     while let Some(chunk) = stream.next().await {
+    ///         let chunk = chunk?;
+    ///         images[chunk.index] = chunk.data;
+    ///     }
+    ///     Ok(images)
+    /// }
+    /// ```
+    fn create_many(
+        &self,
+        prompt: Prompt,
+        size: Size,
+        n: usize,
+    ) -> impl Stream<Item = Result<ImageChunk, Self::Error>> + Unpin + Send {
+        let candidates: Vec<_> = (0..n).map(|_| self.create(prompt.clone(), size)).collect();
+        Box::pin(stream! {
+            for (index, mut images) in candidates.into_iter().enumerate() {
+                while let Some(image) = images.next().await {
+                    match image {
+                        Ok(data) => yield Ok(ImageChunk::new(index, data)),
+                        Err(error) => {
+                            yield Err(error);
+                            return;
+                        }
+                    }
+                }
+            }
+        })
+    }
+
     /// Edit an image using a prompt and a mask.
     ///
     /// # Arguments
@@ -45,6 +115,9 @@ pub trait ImageGenerator {
         prompt: Prompt,
         mask: &[u8],
     ) -> impl Stream<Item = Result<Data, Self::Error>> + Unpin + Send;
+
+    /// Returns the generator's profile, describing supported sizes and features.
+    fn profile(&self) -> Profile;
 }
 
 macro_rules! impl_image_generator {
@@ -61,6 +134,15 @@ macro_rules! impl_image_generator {
                     T::create(self, prompt, size)
                 }
 
+                fn create_many(
+                    &self,
+                    prompt: Prompt,
+                    size: Size,
+                    n: usize,
+                ) -> impl Stream<Item = Result<ImageChunk, Self::Error>> + Unpin + Send {
+                    T::create_many(self, prompt, size, n)
+                }
+
                 fn edit(
                     &self,
                     prompt: Prompt,
@@ -68,6 +150,10 @@ macro_rules! impl_image_generator {
                 ) -> impl Stream<Item = Result<Data, Self::Error>> + Unpin + Send {
                     T::edit(self, prompt, mask)
                 }
+
+                fn profile(&self) -> Profile {
+                    T::profile(self)
+                }
             }
         )*
     };
@@ -76,7 +162,7 @@ macro_rules! impl_image_generator {
 impl_image_generator!(Arc, Box);
 
 /// Represents a prompt for image generation, including text and optional images.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Prompt {
     /// The text description for the image generation.
     text: String,
@@ -140,8 +226,161 @@ impl From<&str> for Prompt {
     }
 }
 
+/// Describes an image generator's supported sizes, features, and pricing.
+///
+/// Applications use it to build option menus and to route requests to a capable
+/// generator.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::image::{Profile, Size};
+///
+/// let profile = Profile::new("painter", "An image model")
+///     .with_sizes([Size::square(1024), Size::new(1792, 1024)])
+///     .with_max_prompt_length(4000)
+///     .with_edit(true)
+///     .with_price_per_image(0.04);
+///
+/// assert!(profile.supports_size(&Size::square(1024)));
+/// assert_eq!(profile.aspect_ratios(), [(1, 1), (7, 4)]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Profile {
+    /// The name of the generator.
+    pub name: String,
+    /// A description of the generator.
+    pub description: String,
+    /// Supported output sizes. Empty when any size is accepted.
+    pub sizes: Vec<Size>,
+    /// Maximum prompt length in characters, if limited.
+    pub max_prompt_length: Option<u32>,
+    /// Maximum number of images generated by one request, if known.
+    ///
+    /// Generators without native support produce one image per request.
+    pub max_images: Option<u32>,
+    /// Whether [`ImageGenerator::edit`] is supported.
+    pub supports_edit: bool,
+    /// Whether edits honor the mask.
+    pub supports_mask: bool,
+    /// Whether variations of an existing image can be generated.
+    pub supports_variations: bool,
+    /// Price per generated image in USD, if known.
+    pub price_per_image: Option<f64>,
+    /// Maximum requests per minute, if rate limited.
+    pub requests_per_minute: Option<u32>,
+}
+
+impl Profile {
+    /// Creates a new `Profile` with the given name and description.
+    pub fn new(name: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            sizes: Vec::new(),
+            max_prompt_length: None,
+            max_images: None,
+            supports_edit: false,
+            supports_mask: false,
+            supports_variations: false,
+            price_per_image: None,
+            requests_per_minute: None,
+        }
+    }
+
+    /// Adds a supported size.
+    #[must_use]
+    pub fn with_size(mut self, size: Size) -> Self {
+        self.sizes.push(size);
+        self
+    }
+
+    /// Adds multiple supported sizes.
+    #[must_use]
+    pub fn with_sizes(mut self, sizes: impl IntoIterator<Item = Size>) -> Self {
+        self.sizes.extend(sizes);
+        self
+    }
+
+    /// Sets the maximum prompt length in characters.
+    #[must_use]
+    pub const fn with_max_prompt_length(mut self, length: u32) -> Self {
+        self.max_prompt_length = Some(length);
+        self
+    }
+
+    /// Sets the maximum number of images generated by one request.
+    #[must_use]
+    pub const fn with_max_images(mut self, max: u32) -> Self {
+        self.max_images = Some(max);
+        self
+    }
+
+    /// Sets whether editing is supported.
+    #[must_use]
+    pub const fn with_edit(mut self, supported: bool) -> Self {
+        self.supports_edit = supported;
+        self
+    }
+
+    /// Sets whether masks are supported when editing.
+    #[must_use]
+    pub const fn with_mask(mut self, supported: bool) -> Self {
+        self.supports_mask = supported;
+        self
+    }
+
+    /// Sets whether variations are supported.
+    #[must_use]
+    pub const fn with_variations(mut self, supported: bool) -> Self {
+        self.supports_variations = supported;
+        self
+    }
+
+    /// Sets the price per generated image in USD.
+    #[must_use]
+    pub const fn with_price_per_image(mut self, price: f64) -> Self {
+        self.price_per_image = Some(price);
+        self
+    }
+
+    /// Sets the maximum number of requests per minute.
+    #[must_use]
+    pub const fn with_requests_per_minute(mut self, limit: u32) -> Self {
+        self.requests_per_minute = Some(limit);
+        self
+    }
+
+    /// Returns whether the generator can produce an image of `size`.
+    #[must_use]
+    pub fn supports_size(&self, size: &Size) -> bool {
+        self.sizes.is_empty() || self.sizes.contains(size)
+    }
+
+    /// Returns whether `prompt` fits within the maximum prompt length.
+    #[must_use]
+    pub fn accepts_prompt(&self, prompt: &Prompt) -> bool {
+        self.max_prompt_length.is_none_or(|max| {
+            u32::try_from(prompt.text().chars().count()).is_ok_and(|length| length <= max)
+        })
+    }
+
+    /// Returns the distinct aspect ratios of the supported sizes, in order.
+    #[must_use]
+    pub fn aspect_ratios(&self) -> Vec<(u32, u32)> {
+        let mut ratios = Vec::new();
+        for ratio in self.sizes.iter().map(Size::aspect_ratio) {
+            if !ratios.contains(&ratio) {
+                ratios.push(ratio);
+            }
+        }
+        ratios
+    }
+}
+
 /// Represents the size (width and height) of an image.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Size {
     /// The width of the image in pixels.
     width: u32,
@@ -197,6 +436,166 @@ impl Size {
     pub const fn is_square(&self) -> bool {
         self.width == self.height
     }
+
+    /// Returns the aspect ratio as a reduced `(width, height)` pair, e.g. `(16, 9)`.
+    #[must_use]
+    pub const fn aspect_ratio(&self) -> (u32, u32) {
+        let (mut a, mut b) = (self.width, self.height);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        if a == 0 {
+            return (0, 0);
+        }
+        (self.width / a, self.height / a)
+    }
+}
+
+/// An image file format, detected from the first bytes of the file.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::image::ImageFormat;
+///
+/// let format = ImageFormat::detect(b"\x89PNG\r\n\x1a\n...").unwrap();
+/// assert_eq!(format, ImageFormat::Png);
+/// assert_eq!(format.mime_type(), "image/png");
+/// assert_eq!(ImageFormat::detect(b"<svg"), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum ImageFormat {
+    /// Portable Network Graphics.
+    Png,
+    /// JPEG.
+    Jpeg,
+    /// WebP.
+    Webp,
+    /// Graphics Interchange Format.
+    Gif,
+}
+
+impl ImageFormat {
+    /// Detects the format from the signature at the start of `bytes`.
+    ///
+    /// Returns `None` for unknown formats, or if `bytes` is too short to tell.
+    #[must_use]
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(Self::Jpeg)
+        } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+            Some(Self::Webp)
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(Self::Gif)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the MIME type of the format, e.g. `image/png`.
+    #[must_use]
+    pub const fn mime_type(self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Webp => "image/webp",
+            Self::Gif => "image/gif",
+        }
+    }
+
+    /// Returns the usual file extension of the format, without the dot.
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+            Self::Gif => "gif",
+        }
+    }
+}
+
+/// Waits for the first image of `stream` and detects its format.
+///
+/// The returned [`Sniffed`] stream yields every image of `stream`, including the first,
+/// so consumers can set a content type, such as an HTTP header, before forwarding the
+/// images.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{ImageGenerator, image::{Prompt, Size, sniff}};
+///
+/// async fn serve(generator: impl ImageGenerator) {
+///     let images = sniff(generator.create(Prompt::new("A red fox"), Size::square(512))).await;
+///     let content_type = images.format().map_or("application/octet-stream", |f| f.mime_type());
+///     println!("Content-Type: {content_type}");
+///     // Forward `images` as the response body.
+/// }
+/// ```
+pub async fn sniff<S, E>(mut stream: S) -> Sniffed<S>
+where
+    S: Stream<Item = Result<Data, E>> + Unpin,
+{
+    let first = stream.next().await;
+    let format = match &first {
+        Some(Ok(data)) => ImageFormat::detect(data),
+        _ => None,
+    };
+    Sniffed {
+        stream,
+        first,
+        format,
+    }
+}
+
+/// An image stream whose format was detected from its first image.
+///
+/// Created by [`sniff`].
+pub struct Sniffed<S: Stream> {
+    stream: S,
+    first: Option<S::Item>,
+    format: Option<ImageFormat>,
+}
+
+impl<S: Stream> core::fmt::Debug for Sniffed<S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Sniffed")
+            .field("format", &self.format)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: Stream> Sniffed<S> {
+    /// Returns the detected format, or `None` if it is unknown or the stream produced no
+    /// image.
+    #[must_use]
+    pub const fn format(&self) -> Option<ImageFormat> {
+        self.format
+    }
+}
+
+// The buffered first item is never pinned, so only the inner stream must be `Unpin`.
+impl<S: Stream + Unpin> Unpin for Sniffed<S> {}
+
+impl<S: Stream + Unpin> Stream for Sniffed<S> {
+    type Item = S::Item;
+
+    fn poll_next(
+        self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Some(first) = this.first.take() {
+            return core::task::Poll::Ready(Some(first));
+        }
+        this.stream.poll_next(cx)
+    }
 }
 
 #[cfg(test)]
@@ -238,6 +637,10 @@ mod tests {
 
             futures_lite::stream::iter(vec![chunk1, chunk2, chunk3].into_iter().map(Ok))
         }
+
+        fn profile(&self) -> Profile {
+            Profile::new("mock", "Mock image generator").with_size(Size::square(256))
+        }
     }
 
     #[tokio::test]
@@ -295,6 +698,46 @@ mod tests {
         assert_eq!(data[3], 4);
     }
 
+    #[tokio::test]
+    async fn sniff_replays_first_image() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        let images = futures_lite::stream::iter(vec![Ok::<_, Infallible>(png.clone()), Ok(png)]);
+        let sniffed = sniff(images).await;
+        assert_eq!(sniffed.format(), Some(ImageFormat::Png));
+        assert_eq!(sniffed.count().await, 2);
+
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8 ".to_vec();
+        assert_eq!(ImageFormat::detect(&webp), Some(ImageFormat::Webp));
+        webp.truncate(10);
+        assert_eq!(ImageFormat::detect(&webp), None);
+
+        let sniffed =
+            sniff(MockImageGenerator.create(Prompt::new("a cat"), Size::square(256))).await;
+        assert_eq!(sniffed.format(), None);
+        assert_eq!(sniffed.count().await, 3);
+    }
+
+    #[test]
+    fn size_aspect_ratio() {
+        assert_eq!(Size::new(1920, 1080).aspect_ratio(), (16, 9));
+        assert_eq!(Size::square(512).aspect_ratio(), (1, 1));
+        assert_eq!(Size::new(0, 0).aspect_ratio(), (0, 0));
+    }
+
+    #[test]
+    fn profile_capabilities() {
+        let profile = MockImageGenerator.profile().with_max_prompt_length(5);
+        assert!(profile.supports_size(&Size::square(256)));
+        assert!(!profile.supports_size(&Size::square(512)));
+        assert!(profile.accepts_prompt(&Prompt::new("a cat")));
+        assert!(!profile.accepts_prompt(&Prompt::new("a tabby cat")));
+        assert!(!profile.supports_edit);
+
+        let any_size = Profile::new("any", "Accepts any size");
+        assert!(any_size.supports_size(&Size::new(123, 456)));
+        assert!(any_size.aspect_ratios().is_empty());
+    }
+
     #[test]
     fn data_operations() {
         let mut data: Data = vec![0xFF; 1024];
@@ -309,4 +752,28 @@ mod tests {
         assert_eq!(data[1025], 0x01);
         assert_eq!(data[1026], 0x02);
     }
+
+    #[tokio::test]
+    async fn create_many_tags_candidates() {
+        let generator = MockImageGenerator;
+        let mut stream = generator.create_many(Prompt::new("a cat"), Size::square(256), 2);
+
+        let mut indices = Vec::new();
+        let mut latest = vec![Data::new(); 2];
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.unwrap();
+            indices.push(chunk.index);
+            latest[chunk.index] = chunk.data;
+        }
+
+        assert_eq!(indices, [0, 0, 0, 1, 1, 1]);
+        assert_eq!(latest, [vec![0x00; 100], vec![0x00; 100]]);
+        assert!(
+            generator
+                .create_many(Prompt::new("a cat"), Size::square(256), 0)
+                .next()
+                .await
+                .is_none()
+        );
+    }
 }
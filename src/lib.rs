@@ -25,10 +25,13 @@
 //! | **Language Models** | [`LanguageModel`] | Text generation, conversations, structured output |
 //! | **Text Streaming** | [`TextStream`] | Unified interface for streaming text responses |
 //! | **Embeddings** | [`EmbeddingModel`] | Convert text to vectors for semantic search |
+//! | **Vector Search** | [`VectorStore`] | Store embeddings and find the closest ones |
+//! | **Reranking** | [`Reranker`] | Score documents by relevance to a query |
 //! | **Image Generation** | [`ImageGenerator`] | Create images with progressive quality improvement |
 //! | **Text-to-Speech** | [`AudioGenerator`] | Generate speech audio from text |
 //! | **Speech-to-Text** | [`AudioTranscriber`] | Transcribe audio to text |
 //! | **Content Moderation** | [`Moderation`] | Detect policy violations with confidence scores |
+//! | **File Storage** | [`FileStore`] | Upload files to reference them in requests |
 //!
 //! ## Examples
 //!
@@ -92,20 +95,23 @@
 //! ### Semantic Search with Embeddings
 //!
 //! ```rust
-//! use ai_types::EmbeddingModel;
+//! use ai_types::{EmbeddingModel, embedding::top_k_similar};
 //!
-//! async fn find_similar_docs(
+//! async fn find_similar_docs<'a>(
 //!     model: impl EmbeddingModel,
 //!     query: &str,
-//!     documents: &[&str]
-//! ) -> ai_types::Result<Vec<f32>> {
-//!     // Convert query to vector
+//!     documents: &[&'a str]
+//! ) -> ai_types::Result<Vec<&'a str>> {
+//!     // Convert the query and documents to vectors
 //!     let query_embedding = model.embed(query).await?;
-//!     
-//!     // In a real app, you'd compare with document embeddings
-//!     // and find the most similar ones using cosine similarity
-//!     
-//!     Ok(query_embedding)
+//!     let mut document_embeddings = Vec::new();
+//!     for document in documents {
+//!         document_embeddings.push(model.embed(document).await?);
+//!     }
+//!
+//!     // Find the three documents closest in meaning
+//!     let nearest = top_k_similar(&query_embedding, &document_embeddings, 3);
+//!     Ok(nearest.into_iter().map(|(index, _)| documents[index]).collect())
 //! }
 //! ```
 //!
@@ -149,30 +155,91 @@ extern crate alloc;
 /// Contains [`AudioGenerator`] and [`AudioTranscriber`] traits.
 pub mod audio;
 /// Text embeddings.
+///
+/// Contains [`EmbeddingModel`] and [`VectorStore`] traits.
 pub mod embedding;
+/// Provider-agnostic error classification.
+///
+/// Contains [`ErrorKind`](error::ErrorKind) and the [`Classify`](error::Classify) trait.
+pub mod error;
+/// Evaluation utilities.
+///
+/// Contains [`diff`](eval::diff) for field-level comparison of structured outputs.
+pub mod eval;
+/// Provider file storage.
+///
+/// Contains [`FileStore`] trait for uploading files referenced in requests.
+pub mod file;
+mod hash;
 /// Text-to-image generation.
 ///
 /// Contains [`ImageGenerator`] trait for creating images from text.
 pub mod image;
+mod join;
 pub mod llm;
-
+/// Model middleware such as response caching and retries.
+///
+/// Contains wrappers that add behavior to any model, like [`Cached`](middleware::Cached)
+/// and [`Retry`](middleware::Retry).
+pub mod middleware;
 /// Content moderation utilities.
 ///
 /// Contains traits and types for detecting and handling unsafe or inappropriate content.
 pub mod moderation;
+/// Declarative model pipelines.
+///
+/// Contains [`PipelineConfig`](pipeline::PipelineConfig), describing a stack of models and
+/// middleware as data, and the [`Registry`](pipeline::Registry) it is built against.
+pub mod pipeline;
+/// Provenance metadata for generated content.
+///
+/// Contains [`Provenance`](provenance::Provenance) and the
+/// [`ProvenanceWriter`](provenance::ProvenanceWriter) trait for tagging generated files.
+pub mod provenance;
+/// Rate limit state reported by providers.
+///
+/// Contains [`RateLimitInfo`](rate_limit::RateLimitInfo).
+pub mod rate_limit;
+/// Realtime speech-to-speech sessions.
+///
+/// Contains [`RealtimeModel`](realtime::RealtimeModel) and the
+/// [`RealtimeSession`](realtime::RealtimeSession) trait for bidirectional audio sessions.
+pub mod realtime;
+/// Document reranking.
+///
+/// Contains [`Reranker`] trait for scoring documents against a query.
+pub mod rerank;
+mod rng;
+/// Test utilities: synthetic and mock models.
+///
+/// Requires the `test-util` feature.
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+/// Time sources for enforcing time limits without `std`.
+pub mod time;
+/// Voice assistant pipelines.
+///
+/// Contains [`VoicePipeline`](voice::VoicePipeline) composing transcription, language
+/// models, and speech synthesis, and [`Interpreter`](voice::Interpreter) translating
+/// speech.
+pub mod voice;
 
 use alloc::string::String;
 
 #[doc(inline)]
 pub use audio::{AudioGenerator, AudioTranscriber};
 #[doc(inline)]
-pub use embedding::EmbeddingModel;
+pub use embedding::{EmbeddingModel, VectorStore};
+#[doc(inline)]
+pub use file::FileStore;
 #[doc(inline)]
 pub use image::ImageGenerator;
 #[doc(inline)]
-pub use llm::LanguageModel;
+pub use llm::{LanguageModel, TextStream};
 #[doc(inline)]
 pub use moderation::Moderation;
+#[doc(inline)]
+pub use rerank::Reranker;
 
 /// Result type used throughout the crate.
 ///
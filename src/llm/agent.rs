@@ -0,0 +1,571 @@
+//! Tool-calling agent loop with safety limits.
+//!
+//! [`run`] lets a language model call tools repeatedly until it produces a final answer.
+//! Because a confused model can call tools forever, every run is bounded by
+//! [`AgentLimits`]: a maximum number of tool-calling rounds, a maximum number of identical
+//! tool calls (loop detection), and an optional time budget. When a limit is hit, the run
+//! stops with an [`AgentAbortReason`] error.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{
+//!     LanguageModel, Message, Request,
+//!     agent::{self, AgentAbortReason, AgentEvent, AgentLimits},
+//! };
+//! use core::time::Duration;
+//! use futures_lite::{StreamExt, pin};
+//! use std::time::Instant;
+//!
+//! async fn ask(model: impl LanguageModel, request: Request) -> ai_types::Result {
+//!     let start = Instant::now();
+//!     let limits = AgentLimits::default()
+//!         .with_max_depth(5)
+//!         .with_max_repeats(2)
+//!         .with_timeout(Duration::from_secs(30), move || start.elapsed());
+//!
+//!     let events = agent::run(&model, request, limits);
+//!     pin!(events);
+//!
+//!     let mut answer = String::new();
+//!     while let Some(event) = events.next().await {
+//!         match event {
+//!             Ok(AgentEvent::Text(chunk)) => answer.push_str(&chunk),
+//!             Ok(_) => {}
+//!             Err(error) => match error.downcast_ref::<AgentAbortReason>() {
+//!                 Some(reason) => return Ok(format!("Stopped early: {reason}")),
+//!                 None => return Err(error),
+//!             },
+//!         }
+//!     }
+//!     Ok(answer)
+//! }
+//! ```
+
+use alloc::{format, string::String, sync::Arc, vec::Vec};
+use core::{fmt, time::Duration};
+
+use async_stream::try_stream;
+use futures_core::Stream;
+use futures_lite::{StreamExt, pin};
+
+use crate::{
+    LanguageModel,
+    llm::{
+        Message, Request,
+        event::StreamEvent,
+        tool::{ToolCall, ToolError, Tools},
+        usage::Usage,
+        validation::{ValidationError, validate_tool_calls},
+    },
+    time::Clock,
+};
+
+/// Limits bounding a tool-calling run.
+#[derive(Clone)]
+pub struct AgentLimits {
+    max_depth: usize,
+    max_repeats: usize,
+    timeout: Option<(Duration, Arc<dyn Clock>)>,
+}
+
+impl fmt::Debug for AgentLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AgentLimits")
+            .field("max_depth", &self.max_depth)
+            .field("max_repeats", &self.max_repeats)
+            .field(
+                "timeout",
+                &self.timeout.as_ref().map(|(timeout, _)| timeout),
+            )
+            .finish()
+    }
+}
+
+impl Default for AgentLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AgentLimits {
+    /// Creates the default limits: at most 10 tool-calling rounds, each identical tool
+    /// call at most 3 times, and no time limit.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            max_depth: 10,
+            max_repeats: 3,
+            timeout: None,
+        }
+    }
+
+    /// Sets the maximum number of tool-calling rounds.
+    #[must_use]
+    pub const fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets how many times the same tool may be called with the same arguments.
+    #[must_use]
+    pub const fn with_max_repeats(mut self, max_repeats: usize) -> Self {
+        self.max_repeats = max_repeats;
+        self
+    }
+
+    /// Limits the total duration of a run, measured with `clock`.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration, clock: impl Clock + 'static) -> Self {
+        self.timeout = Some((timeout, Arc::new(clock)));
+        self
+    }
+
+    /// Returns the maximum number of tool-calling rounds.
+    #[must_use]
+    pub const fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Returns how many times the same tool call may be repeated.
+    #[must_use]
+    pub const fn max_repeats(&self) -> usize {
+        self.max_repeats
+    }
+
+    /// Returns the time limit of a run, if any.
+    #[must_use]
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout.as_ref().map(|(timeout, _)| *timeout)
+    }
+}
+
+/// Why a tool-calling run was aborted.
+///
+/// Returned as the error of an aborted [`run`]; use
+/// [`downcast_ref`](anyhow::Error::downcast_ref) to inspect it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AgentAbortReason {
+    /// The model kept calling tools beyond [`AgentLimits::max_depth`] rounds.
+    MaxDepth {
+        /// The depth limit that was exceeded.
+        depth: usize,
+    },
+    /// The model repeated the same tool call more than [`AgentLimits::max_repeats`] times.
+    LoopDetected {
+        /// The repeated call.
+        call: ToolCall,
+        /// How many times it was requested.
+        repeats: usize,
+    },
+    /// The run exceeded its time limit.
+    Timeout {
+        /// Time elapsed when the limit was detected.
+        elapsed: Duration,
+    },
+}
+
+impl fmt::Display for AgentAbortReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MaxDepth { depth } => {
+                write!(
+                    f,
+                    "Tool calling exceeded the maximum depth of {depth} rounds"
+                )
+            }
+            Self::LoopDetected { call, repeats } => write!(
+                f,
+                "Tool '{}' was called {repeats} times with the same arguments",
+                call.name
+            ),
+            Self::Timeout { elapsed } => {
+                write!(f, "Tool calling timed out after {}ms", elapsed.as_millis())
+            }
+        }
+    }
+}
+
+impl core::error::Error for AgentAbortReason {}
+
+/// Tracks a tool-calling run and enforces its [`AgentLimits`].
+///
+/// [`run`] uses a guard internally; custom agent loops can use one directly.
+#[derive(Debug)]
+pub struct ToolLoopGuard {
+    limits: AgentLimits,
+    started: Option<Duration>,
+    depth: usize,
+    calls: Vec<(String, serde_json::Value, usize)>,
+}
+
+impl ToolLoopGuard {
+    /// Starts tracking a run. The time limit is measured from now.
+    #[must_use]
+    pub fn new(limits: AgentLimits) -> Self {
+        let started = limits.timeout.as_ref().map(|(_, clock)| clock.now());
+        Self {
+            limits,
+            started,
+            depth: 0,
+            calls: Vec::new(),
+        }
+    }
+
+    /// Returns the number of tool-calling rounds so far.
+    #[must_use]
+    pub const fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Registers the start of a new tool-calling round.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AgentAbortReason::MaxDepth`] or [`AgentAbortReason::Timeout`] if a limit
+    /// is exceeded.
+    pub fn begin_round(&mut self) -> Result<(), AgentAbortReason> {
+        self.depth += 1;
+        if self.depth > self.limits.max_depth {
+            return Err(AgentAbortReason::MaxDepth {
+                depth: self.limits.max_depth,
+            });
+        }
+        self.check_time()
+    }
+
+    /// Registers a tool call about to be executed.
+    ///
+    /// Calls are identical when they name the same tool with equal JSON arguments,
+    /// regardless of formatting.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AgentAbortReason::LoopDetected`] or [`AgentAbortReason::Timeout`] if a
+    /// limit is exceeded.
+    pub fn record(&mut self, call: &ToolCall) -> Result<(), AgentAbortReason> {
+        let arguments = serde_json::from_str(&call.arguments)
+            .unwrap_or_else(|_| serde_json::Value::String(call.arguments.clone()));
+
+        let repeats = if let Some((_, _, count)) = self
+            .calls
+            .iter_mut()
+            .find(|(name, args, _)| *name == call.name && *args == arguments)
+        {
+            *count += 1;
+            *count
+        } else {
+            self.calls.push((call.name.clone(), arguments, 1));
+            1
+        };
+
+        if repeats > self.limits.max_repeats {
+            return Err(AgentAbortReason::LoopDetected {
+                call: call.clone(),
+                repeats,
+            });
+        }
+        self.check_time()
+    }
+
+    /// Checks the time limit.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AgentAbortReason::Timeout`] if the run has exceeded its time limit.
+    pub fn check_time(&self) -> Result<(), AgentAbortReason> {
+        if let (Some((timeout, clock)), Some(started)) = (&self.limits.timeout, self.started) {
+            let elapsed = clock.now().saturating_sub(started);
+            if elapsed > *timeout {
+                return Err(AgentAbortReason::Timeout { elapsed });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An event emitted by [`run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AgentEvent {
+    /// A chunk of the model's response text.
+    Text(String),
+    /// The model requested tool calls, which are about to be executed.
+    ToolCalls(Vec<ToolCall>),
+    /// A tool was called and returned `output`.
+    ToolResult {
+        /// The tool call requested by the model.
+        call: ToolCall,
+        /// The tool's output.
+        output: String,
+    },
+    /// Token usage of one model response.
+    Usage(Usage),
+}
+
+/// Runs `request` against `model`, executing the tools it calls until it answers.
+///
+/// Tool calls are executed with [`Tools::execute`](crate::llm::tool::Tools::execute) on
+/// the request's tools. The calls are sent back to the model as an assistant message,
+/// followed by one [tool result](Message::tool_result) per call.
+///
+/// Arguments are [validated](crate::llm::validation) before execution. An invalid call is
+/// not executed; instead, its tool result lists the offending fields so the model can
+/// correct them. Calls to unknown tools, and arguments the tool cannot deserialize, are
+/// likewise reported to the model rather than ending the run.
+///
+/// # Errors
+///
+/// The stream yields an error and ends if the model or a tool fails, or with an
+/// [`AgentAbortReason`] if a limit is exceeded.
+pub fn run<M: LanguageModel>(
+    model: &M,
+    request: Request,
+    limits: AgentLimits,
+) -> impl Stream<Item = crate::Result<AgentEvent>> + Send + '_ {
+    try_stream! {
+        let mut request = request;
+        let tools = request.tools.clone();
+        let mut guard = ToolLoopGuard::new(limits);
+
+        loop {
+            guard.check_time()?;
+            let events = validate_tool_calls(model.respond_events(request.clone()), &tools);
+            pin!(events);
+
+            let mut text = String::new();
+            let mut calls = Vec::new();
+            while let Some(event) = events.try_next().await? {
+                match event {
+                    StreamEvent::Text(chunk) => {
+                        text.push_str(&chunk);
+                        yield AgentEvent::Text(chunk);
+                    }
+                    StreamEvent::ToolCall(call) => calls.push((call, Vec::new())),
+                    StreamEvent::InvalidToolCall { call, errors } => calls.push((call, errors)),
+                    StreamEvent::Usage(usage) => yield AgentEvent::Usage(usage),
+                    _ => {}
+                }
+            }
+
+            if calls.is_empty() {
+                break;
+            }
+            guard.begin_round()?;
+            let requested: Vec<ToolCall> = calls.iter().map(|(call, _)| call.clone()).collect();
+            request
+                .messages
+                .push(Message::assistant_tool_calls(text, requested.clone()));
+            yield AgentEvent::ToolCalls(requested);
+            for (call, errors) in calls {
+                guard.record(&call)?;
+                let output = if errors.is_empty() {
+                    execute(&tools, &call).await?
+                } else {
+                    ValidationError::to_tool_result(&call.name, &errors)
+                };
+                request
+                    .messages
+                    .push(Message::tool_result(call.id.clone(), output.clone()));
+                yield AgentEvent::ToolResult { call, output };
+            }
+        }
+    }
+}
+
+/// Executes `call`, reporting unknown tools and undeserializable arguments to the model.
+async fn execute(tools: &Tools, call: &ToolCall) -> crate::Result {
+    if let Err(error) = tools.plan(call) {
+        return Ok(error.to_tool_result(&call.name));
+    }
+    match tools.execute(call).await {
+        Err(error) if error.is::<serde_json::Error>() => {
+            let error = ToolError::recoverable(format!("invalid arguments: {error}"));
+            Ok(error.to_tool_result(&call.name))
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        llm::Tool,
+        test_util::{MockLanguageModel, MockReply},
+    };
+    use alloc::{format, vec};
+    use core::sync::atomic::{AtomicU64, Ordering};
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+
+    /// A reply calling `echo` with `arguments`.
+    fn echo(id: usize, arguments: &str) -> MockReply {
+        MockReply::default().with_tool_call(ToolCall::new(format!("call_{id}"), "echo", arguments))
+    }
+
+    /// A model calling `echo` with different arguments on every turn.
+    fn rounds() -> MockLanguageModel {
+        MockLanguageModel::new().with_replies(
+            (1..=10).map(|round| echo(round, &format!(r#"{{"text": "round {round}"}}"#))),
+        )
+    }
+
+    #[derive(JsonSchema, Deserialize)]
+    struct EchoArgs {
+        text: String,
+    }
+
+    struct Echo;
+
+    impl Tool for Echo {
+        const NAME: &str = "echo";
+        const DESCRIPTION: &str = "Echoes text";
+        type Arguments = EchoArgs;
+
+        async fn call(&mut self, args: Self::Arguments) -> crate::Result {
+            Ok(args.text)
+        }
+    }
+
+    async fn collect(
+        model: &MockLanguageModel,
+        limits: AgentLimits,
+    ) -> crate::Result<Vec<AgentEvent>> {
+        let request = Request::new([Message::user("go")]).with_tool(Echo);
+        run(model, request, limits).try_collect().await
+    }
+
+    #[tokio::test]
+    async fn detects_repeated_calls() {
+        let model = MockLanguageModel::new().with_reply(echo(1, r#"{"text": "again"}"#));
+        let error = collect(&model, AgentLimits::default().with_max_repeats(2))
+            .await
+            .unwrap_err();
+
+        let reason = error.downcast_ref::<AgentAbortReason>().unwrap();
+        assert!(matches!(
+            reason,
+            AgentAbortReason::LoopDetected { repeats: 3, call } if call.name == "echo"
+        ));
+    }
+
+    #[tokio::test]
+    async fn enforces_max_depth() {
+        let error = collect(&rounds(), AgentLimits::default().with_max_depth(4))
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<AgentAbortReason>(),
+            Some(&AgentAbortReason::MaxDepth { depth: 4 })
+        );
+    }
+
+    #[tokio::test]
+    async fn enforces_timeout() {
+        static NOW: AtomicU64 = AtomicU64::new(0);
+        let clock = || Duration::from_secs(NOW.fetch_add(10, Ordering::Relaxed));
+
+        let limits = AgentLimits::default().with_timeout(Duration::from_secs(25), clock);
+        let error = collect(&rounds(), limits).await.unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<AgentAbortReason>(),
+            Some(AgentAbortReason::Timeout { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn finishes_after_tool_result() {
+        let model = MockLanguageModel::new()
+            .with_replies([echo(1, r#"{"text": "done"}"#), "finished".into()]);
+        let events = collect(&model, AgentLimits::default()).await.unwrap();
+
+        assert_eq!(
+            events,
+            vec![
+                AgentEvent::ToolCalls(vec![ToolCall::new("call_1", "echo", r#"{"text": "done"}"#)]),
+                AgentEvent::ToolResult {
+                    call: ToolCall::new("call_1", "echo", r#"{"text": "done"}"#),
+                    output: "done".into(),
+                },
+                AgentEvent::Text("finished".into()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_invalid_arguments_to_model() {
+        let model = MockLanguageModel::new().with_replies([
+            echo(1, r#"{"text": 1}"#),
+            echo(3, r#"{"text": "done"}"#),
+            "finished".into(),
+        ]);
+        let events = collect(&model, AgentLimits::default()).await.unwrap();
+
+        let AgentEvent::ToolResult { output, .. } = &events[1] else {
+            panic!("expected a tool result, got {:?}", events[1]);
+        };
+        assert!(output.contains("$.text: expected string, found number"));
+        assert_eq!(events.last(), Some(&AgentEvent::Text("finished".into())));
+    }
+
+    #[tokio::test]
+    async fn reports_unknown_tools_to_model() {
+        let call = ToolCall::new("call_1", "search", r#"{"query": "weather"}"#);
+        let model = MockLanguageModel::new().with_replies([
+            MockReply::default().with_tool_call(call.clone()),
+            "finished".into(),
+        ]);
+        let request = Request::new([Message::user("go")]).with_tool(Echo);
+        let events: Vec<_> = run(&model, request, AgentLimits::default())
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            events[1],
+            AgentEvent::ToolResult {
+                call,
+                output: "Error: tool 'search' failed: no tool named 'search'. \
+                         Adjust the arguments and try again."
+                    .into(),
+            }
+        );
+        assert_eq!(events.last(), Some(&AgentEvent::Text("finished".into())));
+    }
+
+    #[tokio::test]
+    async fn dry_run_reports_calls_without_executing() {
+        let model = MockLanguageModel::new().with_reply(echo(1, r#"{"text": "done"}"#));
+        let mut tools = Tools::new().with_dry_run();
+        tools.register(Echo);
+        let request = Request::new([Message::user("go")]).with_tools(tools);
+        let events = run(&model, request, AgentLimits::default().with_max_repeats(1));
+        pin!(events);
+
+        let mut outputs = Vec::new();
+        while let Some(Ok(event)) = events.next().await {
+            if let AgentEvent::ToolResult { output, .. } = event {
+                outputs.push(output);
+            }
+        }
+        // The echo never runs, and the repeated call is caught as a loop.
+        assert_eq!(outputs, ["Dry run: tool 'echo' was not executed."]);
+    }
+
+    #[test]
+    fn identical_calls_ignore_formatting() {
+        let mut guard = ToolLoopGuard::new(AgentLimits::default().with_max_repeats(1));
+        guard
+            .record(&ToolCall::new("1", "echo", r#"{"text":"a"}"#))
+            .unwrap();
+        assert!(
+            guard
+                .record(&ToolCall::new("2", "echo", r#"{ "text": "a" }"#))
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,498 @@
+//! Replacing personal data in conversations with stable placeholders.
+//!
+//! Transcripts are valuable as evaluation data or for debugging with a vendor, but they
+//! carry the personal data of real users. An [`Anonymizer`] finds emails, phone numbers,
+//! card numbers, IP addresses, and terms the application knows to be personal, such as
+//! user names, and replaces each distinct value with a placeholder like `[EMAIL_1]`.
+//!
+//! The same value always maps to the same placeholder, so anonymized conversations stay
+//! coherent. The mapping is returned separately as a [`PiiMap`], which is kept private and
+//! can [`restore`](PiiMap::restore) the original text.
+//!
+//! Detection is heuristic. The built-in [`Patterns`] favor precision over recall; pass a
+//! custom [`PiiDetector`] to [`Anonymizer::new`] to cover domain-specific identifiers.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{Message, anonymize::{Anonymizer, PiiKind}};
+//!
+//! let anonymizer = Anonymizer::default().with_term(PiiKind::Name, "Alice");
+//! let mut messages = [
+//!     Message::user("I'm Alice, reach me at alice@example.com"),
+//!     Message::assistant("Thanks Alice, I'll email alice@example.com."),
+//! ];
+//!
+//! let map = anonymizer.anonymize_messages(&mut messages);
+//! assert_eq!(messages[0].content(), "I'm [NAME_1], reach me at [EMAIL_1]");
+//! assert_eq!(messages[1].content(), "Thanks [NAME_1], I'll email [EMAIL_1].");
+//! assert_eq!(map.restore(&messages[0].content()), "I'm Alice, reach me at alice@example.com");
+//! ```
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::ops::Range;
+
+use crate::llm::{Message, message::Content};
+
+/// A kind of personal data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum PiiKind {
+    /// An email address.
+    Email,
+    /// A phone number.
+    Phone,
+    /// A payment card number.
+    CreditCard,
+    /// An IP address.
+    IpAddress,
+    /// The name of a person.
+    Name,
+    /// Any other personal data.
+    Other,
+}
+
+impl PiiKind {
+    /// Returns the label used in placeholders for this kind, such as `EMAIL`.
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Email => "EMAIL",
+            Self::Phone => "PHONE",
+            Self::CreditCard => "CARD",
+            Self::IpAddress => "IP",
+            Self::Name => "NAME",
+            Self::Other => "PII",
+        }
+    }
+}
+
+/// Personal data found in a text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Detection {
+    /// The kind of data found.
+    pub kind: PiiKind,
+    /// The byte range of the data in the text.
+    pub range: Range<usize>,
+}
+
+impl Detection {
+    /// Creates a detection of `kind` at `range`.
+    #[must_use]
+    pub const fn new(kind: PiiKind, range: Range<usize>) -> Self {
+        Self { kind, range }
+    }
+}
+
+/// Finds personal data in text.
+///
+/// Any closure returning the detections in a text is a detector.
+pub trait PiiDetector {
+    /// Returns the personal data found in `text`, in any order.
+    ///
+    /// Ranges must lie on character boundaries. Overlapping detections are allowed; the
+    /// earliest, then longest, wins.
+    fn detect(&self, text: &str) -> Vec<Detection>;
+}
+
+impl<F: Fn(&str) -> Vec<Detection>> PiiDetector for F {
+    fn detect(&self, text: &str) -> Vec<Detection> {
+        self(text)
+    }
+}
+
+/// The built-in detector for emails, phone numbers, card numbers, and IPv4 addresses.
+///
+/// Phone numbers need a leading `+` and at least 7 digits, or at least 10 digits. Card
+/// numbers need 13 to 19 digits passing the Luhn check. Digits may be grouped with
+/// spaces, dashes, dots, or parentheses.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Patterns;
+
+impl PiiDetector for Patterns {
+    fn detect(&self, text: &str) -> Vec<Detection> {
+        let mut detections = Vec::new();
+        emails(text, &mut detections);
+        numbers(text, &mut detections);
+        detections
+    }
+}
+
+/// Replaces personal data in messages with placeholders.
+///
+/// See the [module documentation](crate::llm::anonymize) for an example.
+#[derive(Debug, Clone)]
+pub struct Anonymizer<D = Patterns> {
+    detector: D,
+    terms: Vec<(PiiKind, String)>,
+}
+
+impl Default for Anonymizer {
+    fn default() -> Self {
+        Self::new(Patterns)
+    }
+}
+
+impl<D: PiiDetector> Anonymizer<D> {
+    /// Creates an anonymizer finding personal data with `detector`.
+    #[must_use]
+    pub const fn new(detector: D) -> Self {
+        Self {
+            detector,
+            terms: Vec::new(),
+        }
+    }
+
+    /// Also replaces every whole-word occurrence of `term`, such as a user's name.
+    #[must_use]
+    pub fn with_term(mut self, kind: PiiKind, term: impl Into<String>) -> Self {
+        let term = term.into();
+        if !term.is_empty() {
+            self.terms.push((kind, term));
+        }
+        self
+    }
+
+    /// Returns `text` with its personal data replaced, recording placeholders in `map`.
+    pub fn anonymize(&self, text: &str, map: &mut PiiMap) -> String {
+        let mut detections = self.detector.detect(text);
+        for (kind, term) in &self.terms {
+            for (start, _) in text.match_indices(term.as_str()) {
+                let end = start + term.len();
+                let before = text[..start].chars().next_back();
+                let after = text[end..].chars().next();
+                if !before.is_some_and(char::is_alphanumeric)
+                    && !after.is_some_and(char::is_alphanumeric)
+                {
+                    detections.push(Detection::new(*kind, start..end));
+                }
+            }
+        }
+        detections
+            .sort_by_key(|detection| (detection.range.start, usize::MAX - detection.range.end));
+
+        let mut anonymized = String::with_capacity(text.len());
+        let mut position = 0;
+        for Detection { kind, range } in detections {
+            if range.start < position || range.is_empty() {
+                continue;
+            }
+            anonymized.push_str(&text[position..range.start]);
+            anonymized.push_str(map.placeholder(kind, &text[range.clone()]));
+            position = range.end;
+        }
+        anonymized.push_str(&text[position..]);
+        anonymized
+    }
+
+    /// Anonymizes the text and tool-call arguments of `message` in place.
+    pub fn anonymize_message(&self, message: &mut Message, map: &mut PiiMap) {
+        for part in message.parts_mut() {
+            match part {
+                Content::Text(text) => *text = self.anonymize(text, map),
+                Content::ToolCall(call) => call.arguments = self.anonymize(&call.arguments, map),
+                _ => {}
+            }
+        }
+    }
+
+    /// Anonymizes `messages` in place and returns the mapping of placeholders.
+    pub fn anonymize_messages(&self, messages: &mut [Message]) -> PiiMap {
+        let mut map = PiiMap::new();
+        for message in messages {
+            self.anonymize_message(message, &mut map);
+        }
+        map
+    }
+}
+
+/// The mapping between placeholders and the personal data they replace.
+///
+/// Holds the personal data itself, so it must be stored as securely as the original
+/// transcripts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PiiMap {
+    originals: BTreeMap<String, String>,
+    placeholders: BTreeMap<String, String>,
+}
+
+impl PiiMap {
+    /// Creates an empty mapping.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            originals: BTreeMap::new(),
+            placeholders: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the placeholder for `value`, assigning the next one of `kind` if it has none.
+    pub fn placeholder(&mut self, kind: PiiKind, value: &str) -> &str {
+        if !self.placeholders.contains_key(value) {
+            let prefix = format!("[{}_", kind.label());
+            let taken = self
+                .originals
+                .range(prefix.clone()..)
+                .take_while(|(placeholder, _)| placeholder.starts_with(&prefix))
+                .count();
+            let placeholder = format!("{prefix}{}]", taken + 1);
+            self.originals
+                .insert(placeholder.clone(), value.to_string());
+            self.placeholders.insert(value.to_string(), placeholder);
+        }
+        &self.placeholders[value]
+    }
+
+    /// Returns the personal data a placeholder replaces.
+    #[must_use]
+    pub fn original(&self, placeholder: &str) -> Option<&str> {
+        self.originals.get(placeholder).map(String::as_str)
+    }
+
+    /// Returns `text` with every known placeholder replaced by its original value.
+    #[must_use]
+    pub fn restore(&self, text: &str) -> String {
+        let mut restored = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('[') {
+            let Some(end) = rest[start..].find(']').map(|end| start + end + 1) else {
+                break;
+            };
+            if let Some(original) = self.original(&rest[start..end]) {
+                restored.push_str(&rest[..start]);
+                restored.push_str(original);
+                rest = &rest[end..];
+            } else {
+                restored.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+        restored.push_str(rest);
+        restored
+    }
+
+    /// Returns the `(placeholder, original)` pairs, ordered by placeholder.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.originals
+            .iter()
+            .map(|(placeholder, original)| (placeholder.as_str(), original.as_str()))
+    }
+
+    /// Returns the number of distinct values replaced.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.originals.len()
+    }
+
+    /// Returns whether no values were replaced.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.originals.is_empty()
+    }
+}
+
+const fn is_local_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'.' | b'_' | b'%' | b'+' | b'-')
+}
+
+const fn is_domain_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'.' | b'-')
+}
+
+fn emails(text: &str, detections: &mut Vec<Detection>) {
+    let bytes = text.as_bytes();
+    for (at, _) in text.match_indices('@') {
+        let mut start = at;
+        while start > 0 && is_local_char(bytes[start - 1]) {
+            start -= 1;
+        }
+        while start < at && bytes[start] == b'.' {
+            start += 1;
+        }
+        let mut end = at + 1;
+        while end < bytes.len() && is_domain_char(bytes[end]) {
+            end += 1;
+        }
+        while end > at + 1 && matches!(bytes[end - 1], b'.' | b'-') {
+            end -= 1;
+        }
+        let domain = &text[at + 1..end];
+        let valid_domain = domain.rsplit_once('.').is_some_and(|(name, tld)| {
+            !name.is_empty() && tld.len() >= 2 && tld.bytes().all(|byte| byte.is_ascii_alphabetic())
+        });
+        if start < at && valid_domain {
+            detections.push(Detection::new(PiiKind::Email, start..end));
+        }
+    }
+}
+
+/// Finds phone numbers, card numbers, and IPv4 addresses among runs of grouped digits.
+fn numbers(text: &str, detections: &mut Vec<Detection>) {
+    let bytes = text.as_bytes();
+    let is_word = |index: usize| {
+        bytes
+            .get(index)
+            .is_some_and(|byte| byte.is_ascii_alphanumeric() || *byte == b'@')
+    };
+    let mut i = 0;
+    while i < bytes.len() {
+        let opens =
+            matches!(bytes[i], b'+' | b'(') && bytes.get(i + 1).is_some_and(u8::is_ascii_digit);
+        if !(bytes[i].is_ascii_digit() || opens) || (i > 0 && is_word(i - 1)) {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let mut end = i + 1;
+        let mut separators = 0;
+        let mut j = i + 1;
+        while j < bytes.len() && separators <= 2 {
+            match bytes[j] {
+                byte if byte.is_ascii_digit() => {
+                    separators = 0;
+                    end = j + 1;
+                }
+                b' ' | b'-' | b'.' | b'(' | b')' => separators += 1,
+                _ => break,
+            }
+            j += 1;
+        }
+        i = end;
+        if is_word(end) {
+            continue;
+        }
+
+        let run = &text[start..end];
+        let digits = run.bytes().filter(u8::is_ascii_digit).count();
+        let kind = if is_ipv4(run) {
+            PiiKind::IpAddress
+        } else if (13..=19).contains(&digits) && luhn(run) {
+            PiiKind::CreditCard
+        } else if (run.starts_with('+') && (7..=15).contains(&digits))
+            || (10..=15).contains(&digits)
+        {
+            PiiKind::Phone
+        } else {
+            continue;
+        };
+        detections.push(Detection::new(kind, start..end));
+    }
+}
+
+fn is_ipv4(run: &str) -> bool {
+    let groups: Vec<&str> = run.split('.').collect();
+    groups.len() == 4
+        && groups.iter().all(|group| {
+            (1..=3).contains(&group.len())
+                && group.bytes().all(|byte| byte.is_ascii_digit())
+                && group.parse::<u16>().is_ok_and(|value| value <= 255)
+        })
+}
+
+fn luhn(run: &str) -> bool {
+    let mut sum = 0;
+    for (index, digit) in run
+        .bytes()
+        .rev()
+        .filter(u8::is_ascii_digit)
+        .map(|byte| u32::from(byte - b'0'))
+        .enumerate()
+    {
+        sum += if index % 2 == 1 {
+            let doubled = digit * 2;
+            if doubled > 9 { doubled - 9 } else { doubled }
+        } else {
+            digit
+        };
+    }
+    sum % 10 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::tool::ToolCall;
+    use alloc::vec;
+
+    fn kinds(text: &str) -> Vec<(PiiKind, &str)> {
+        let mut detections = Patterns.detect(text);
+        detections.sort_by_key(|detection| detection.range.start);
+        detections
+            .into_iter()
+            .map(|detection| (detection.kind, &text[detection.range]))
+            .collect()
+    }
+
+    #[test]
+    fn detects_common_patterns() {
+        assert_eq!(
+            kinds("Mail j.doe+work@mail.example.org. or call +1 (555) 123-4567!"),
+            [
+                (PiiKind::Email, "j.doe+work@mail.example.org"),
+                (PiiKind::Phone, "+1 (555) 123-4567"),
+            ]
+        );
+        assert_eq!(
+            kinds("Card 4111 1111 1111 1111 from 192.168.0.1"),
+            [
+                (PiiKind::CreditCard, "4111 1111 1111 1111"),
+                (PiiKind::IpAddress, "192.168.0.1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_lookalikes() {
+        assert!(kinds("On 2024-02-29 at 10:30, order #A1234567890 cost 12.50").is_empty());
+        assert!(kinds("Version 1.2.3 and 999.1.1.1 and user@localhost").is_empty());
+        // Fails the Luhn check, and is too long for a phone number.
+        assert!(kinds("4111 1111 1111 1112").is_empty());
+    }
+
+    #[test]
+    fn placeholders_are_stable_and_restorable() {
+        let anonymizer = Anonymizer::default()
+            .with_term(PiiKind::Name, "Bob")
+            .with_term(PiiKind::Name, "Eve");
+        let mut messages = vec![
+            Message::user("Bob here, bob@example.com. Is Bobby [EMAIL_9] around?"),
+            Message::assistant_tool_calls(
+                "",
+                vec![ToolCall::new(
+                    "1",
+                    "email",
+                    r#"{"to": "eve@example.com", "cc": "bob@example.com"}"#,
+                )],
+            ),
+            Message::user("Eve said hi"),
+        ];
+
+        let map = anonymizer.anonymize_messages(&mut messages);
+        assert_eq!(
+            messages[0].content(),
+            "[NAME_1] here, [EMAIL_1]. Is Bobby [EMAIL_9] around?"
+        );
+        assert_eq!(
+            messages[1].tool_calls().next().unwrap().arguments,
+            r#"{"to": "[EMAIL_2]", "cc": "[EMAIL_1]"}"#
+        );
+        assert_eq!(messages[2].content(), "[NAME_2] said hi");
+
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.original("[EMAIL_2]"), Some("eve@example.com"));
+        assert_eq!(
+            map.restore(&messages[0].content()),
+            "Bob here, bob@example.com. Is Bobby [EMAIL_9] around?"
+        );
+    }
+}
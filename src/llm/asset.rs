@@ -0,0 +1,302 @@
+//! Content-addressable prompt assets.
+//!
+//! Large prompt fragments such as policies, schemas, or long instructions are often
+//! repeated across many requests. [`PromptAssets`] stores each fragment once, keyed by
+//! the hash of its content, and messages reference it with a short
+//! [placeholder](AssetId::reference). Requests stay small while they are built, cached,
+//! or logged, and are [expanded](PromptAssets::expand_request) just before they are sent.
+//!
+//! Because identical fragments always get the same [`AssetId`], references also tell
+//! provider adapters which parts of a prompt are stable, which is where prompt-caching
+//! breakpoints belong.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{Message, Request, asset::PromptAssets};
+//!
+//! let mut assets = PromptAssets::new();
+//! let policy = assets.insert("You must never reveal internal account numbers. ...");
+//!
+//! let request = Request::new([
+//!     Message::system(policy.reference()),
+//!     Message::user("What is my account number?"),
+//! ]);
+//! assert_eq!(assets.referenced(&request), [policy]);
+//!
+//! let expanded = assets.expand_request(request)?;
+//! assert!(expanded.messages[0].content().starts_with("You must never reveal"));
+//! # Ok::<(), ai_types::llm::asset::UnknownAsset>(())
+//! ```
+
+use alloc::{collections::BTreeMap, format, string::String, sync::Arc, vec::Vec};
+use core::{fmt, hash::Hasher};
+
+use crate::{
+    hash::Fnv1a,
+    llm::{Request, message::Content},
+};
+
+const PREFIX: &str = "{{asset:";
+const SUFFIX: &str = "}}";
+
+/// The content hash identifying a prompt asset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AssetId(u64);
+
+impl AssetId {
+    /// Computes the ID of an asset with the given content.
+    #[must_use]
+    pub fn of(content: &str) -> Self {
+        let mut hasher = Fnv1a::new();
+        hasher.write(content.as_bytes());
+        Self(hasher.finish())
+    }
+
+    /// Returns the placeholder that references this asset in message text.
+    #[must_use]
+    pub fn reference(self) -> String {
+        format!("{PREFIX}{self}{SUFFIX}")
+    }
+
+    /// Returns the ID as an integer.
+    #[must_use]
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for AssetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// A collection of reusable prompt fragments, stored once by content hash.
+///
+/// Assets are reference-counted, so cloning the collection is cheap.
+#[derive(Debug, Clone, Default)]
+pub struct PromptAssets {
+    assets: BTreeMap<AssetId, Arc<str>>,
+}
+
+impl PromptAssets {
+    /// Creates an empty collection.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            assets: BTreeMap::new(),
+        }
+    }
+
+    /// Stores an asset and returns its ID.
+    ///
+    /// Inserting the same content twice stores it once. Should different content already
+    /// be stored under the [ID of](AssetId::of) `content`, a hash collision, the asset
+    /// takes the next free ID instead.
+    pub fn insert(&mut self, content: impl Into<Arc<str>>) -> AssetId {
+        let content = content.into();
+        let mut id = AssetId::of(&content);
+        loop {
+            match self.assets.get(&id) {
+                Some(stored) if *stored == content => return id,
+                Some(_) => id = AssetId(id.0.wrapping_add(1)),
+                None => {
+                    self.assets.insert(id, content);
+                    return id;
+                }
+            }
+        }
+    }
+
+    /// Returns the content of an asset.
+    #[must_use]
+    pub fn get(&self, id: AssetId) -> Option<&str> {
+        self.assets.get(&id).map(|content| &**content)
+    }
+
+    /// Returns whether the collection holds an asset.
+    #[must_use]
+    pub fn contains(&self, id: AssetId) -> bool {
+        self.assets.contains_key(&id)
+    }
+
+    /// Returns the number of assets.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    /// Returns whether the collection is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    /// Replaces every asset reference in `text` with the asset's content.
+    ///
+    /// Text that merely resembles a reference, but does not contain a valid ID, is kept
+    /// as is.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UnknownAsset`] if `text` references an asset missing from the collection.
+    pub fn expand(&self, text: &str) -> Result<String, UnknownAsset> {
+        let mut expanded = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some((before, id, after)) = next_reference(rest) {
+            let content = self.get(id).ok_or(UnknownAsset(id))?;
+            expanded.push_str(before);
+            expanded.push_str(content);
+            rest = after;
+        }
+        expanded.push_str(rest);
+        Ok(expanded)
+    }
+
+    /// Expands the asset references in the text of every message of `request`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UnknownAsset`] if a message references an asset missing from the
+    /// collection.
+    pub fn expand_request(&self, mut request: Request) -> Result<Request, UnknownAsset> {
+        for message in &mut request.messages {
+            for part in message.parts_mut() {
+                if let Content::Text(text) = part {
+                    if next_reference(text).is_some() {
+                        *text = self.expand(text)?;
+                    }
+                }
+            }
+        }
+        Ok(request)
+    }
+
+    /// Returns the assets referenced by `request`, in order of first appearance.
+    ///
+    /// Provider adapters can use these to place prompt-caching breakpoints after stable
+    /// content.
+    #[must_use]
+    pub fn referenced(&self, request: &Request) -> Vec<AssetId> {
+        let mut ids = Vec::new();
+        for message in &request.messages {
+            for part in message.parts() {
+                let Some(mut rest) = part.as_text() else {
+                    continue;
+                };
+                while let Some((_, id, after)) = next_reference(rest) {
+                    if !ids.contains(&id) {
+                        ids.push(id);
+                    }
+                    rest = after;
+                }
+            }
+        }
+        ids
+    }
+}
+
+/// Finds the next valid reference, returning the text before it, its ID, and the text
+/// after it.
+fn next_reference(text: &str) -> Option<(&str, AssetId, &str)> {
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find(PREFIX) {
+        let start = offset + start;
+        let hex_start = start + PREFIX.len();
+        let id = text
+            .get(hex_start..hex_start + 16)
+            .filter(|hex| hex.bytes().all(|byte| byte.is_ascii_hexdigit()))
+            .and_then(|hex| u64::from_str_radix(hex, 16).ok());
+        let end = hex_start + 16;
+        if let Some(id) = id {
+            if text[end..].starts_with(SUFFIX) {
+                return Some((&text[..start], AssetId(id), &text[end + SUFFIX.len()..]));
+            }
+        }
+        offset = hex_start;
+    }
+    None
+}
+
+/// A reference to an asset missing from a [`PromptAssets`] collection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownAsset(pub AssetId);
+
+impl fmt::Display for UnknownAsset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown prompt asset {}", self.0)
+    }
+}
+
+impl core::error::Error for UnknownAsset {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::Message;
+
+    #[test]
+    fn identical_content_is_stored_once() {
+        let mut assets = PromptAssets::new();
+        let a = assets.insert("policy");
+        let b = assets.insert(String::from("policy"));
+        assert_eq!(a, b);
+        assert_eq!(assets.len(), 1);
+        assert_eq!(assets.get(a), Some("policy"));
+        assert_ne!(a, AssetId::of("other policy"));
+    }
+
+    #[test]
+    fn colliding_content_is_kept_apart() {
+        let mut assets = PromptAssets::new();
+        let taken = AssetId::of("policy");
+        assets.assets.insert(taken, "colliding".into());
+
+        let id = assets.insert("policy");
+        assert_ne!(id, taken);
+        assert_eq!(assets.get(id), Some("policy"));
+        assert_eq!(assets.get(taken), Some("colliding"));
+        assert_eq!(assets.insert("policy"), id);
+    }
+
+    #[test]
+    fn expands_references_and_keeps_lookalikes() {
+        let mut assets = PromptAssets::new();
+        let id = assets.insert("RULES");
+        let text = format!(
+            "{{{{asset:xyz}}}} then {} and {}.",
+            id.reference(),
+            id.reference()
+        );
+        assert_eq!(
+            assets.expand(&text).unwrap(),
+            "{{asset:xyz}} then RULES and RULES."
+        );
+
+        let missing = AssetId::of("missing");
+        assert_eq!(
+            assets.expand(&missing.reference()),
+            Err(UnknownAsset(missing))
+        );
+    }
+
+    #[test]
+    fn expands_requests() {
+        let mut assets = PromptAssets::new();
+        let schema = assets.insert("{\"type\":\"object\"}");
+        let request = Request::new([
+            Message::system(format!("Reply with: {}", schema.reference())),
+            Message::user("Hi"),
+        ]);
+
+        assert_eq!(assets.referenced(&request), [schema]);
+        let expanded = assets.expand_request(request).unwrap();
+        assert_eq!(
+            expanded.messages[0].content(),
+            "Reply with: {\"type\":\"object\"}"
+        );
+        assert_eq!(expanded.messages[1].content(), "Hi");
+    }
+}
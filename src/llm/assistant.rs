@@ -1,8 +1,15 @@
 use alloc::{string::String, vec::Vec};
+use core::mem;
+
+use futures_lite::{StreamExt, pin};
 
 use crate::{
     LanguageModel,
-    llm::{Message, Tool, model::Parameters, tool::Tools, try_collect},
+    llm::{
+        Message, Request, Tool,
+        agent::{self, AgentEvent, AgentLimits},
+        tool::Tools,
+    },
 };
 
 #[derive(Debug)]
@@ -20,10 +27,12 @@ use crate::{
 ///   various tasks.
 /// - `llm`: The language model instance used by the Assistant for generating
 ///   responses or performing language-related tasks.
+/// - `limits`: The limits bounding tool calling while answering a message.
 pub struct Assistant<LLM: LanguageModel> {
     messages: Vec<Message>,
     tools: Tools,
     llm: LLM,
+    limits: AgentLimits,
 }
 
 impl<LLM: LanguageModel> Assistant<LLM> {
@@ -40,6 +49,7 @@ impl<LLM: LanguageModel> Assistant<LLM> {
             messages: Vec::new(),
             tools: Tools::new(),
             llm,
+            limits: AgentLimits::new(),
         }
     }
 
@@ -69,19 +79,54 @@ impl<LLM: LanguageModel> Assistant<LLM> {
         self
     }
 
+    /// Sets the limits bounding tool calling while answering a message.
+    ///
+    /// # Parameters
+    /// - `limits`: The tool-calling limits. See [`AgentLimits`].
+    ///
+    /// # Returns
+    /// Returns the updated Assistant instance with the limits set.
+    #[must_use]
+    pub fn limits(mut self, limits: AgentLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
     /// Sends a user message to the assistant, processes it with the language model, and appends the response to the conversation history.
     ///
+    /// Tools called by the model are executed. The calls and their results are appended
+    /// to the history as an assistant message and tool result messages.
+    ///
     /// # Parameters
     /// - `message`: The user message to send to the assistant.
     ///
     /// # Errors
-    /// Returns an error if the language model fails to generate a response or if message processing fails.
+    /// Returns an error if the language model fails to generate a response, if a tool fails,
+    /// or with an [`AgentAbortReason`](crate::llm::agent::AgentAbortReason) if tool calling
+    /// exceeds its limits.
     pub async fn send(&mut self, message: impl Into<String>) -> anyhow::Result<()> {
         self.messages.push(Message::user(message));
-        let binding = Parameters::default();
-        let stream = self.llm.respond(&self.messages, &mut self.tools, &binding);
+        let request = Request::new(self.messages.clone()).with_tools(self.tools.clone());
+
+        let events = agent::run(&self.llm, request, self.limits.clone());
+        pin!(events);
 
-        let response = try_collect(stream).await?;
+        let mut response = String::new();
+        while let Some(event) = events.try_next().await? {
+            match event {
+                AgentEvent::Text(chunk) => response.push_str(&chunk),
+                AgentEvent::ToolCalls(calls) => {
+                    self.messages.push(Message::assistant_tool_calls(
+                        mem::take(&mut response),
+                        calls,
+                    ));
+                }
+                AgentEvent::ToolResult { call, output } => {
+                    self.messages.push(Message::tool_result(call.id, output));
+                }
+                _ => {}
+            }
+        }
         self.messages.push(Message::assistant(response));
         Ok(())
     }
@@ -0,0 +1,389 @@
+//! Type-erased language models.
+//!
+//! [`LanguageModel`] has generic methods and returns `impl Trait` types, so it cannot be
+//! used as a trait object: there is no `dyn LanguageModel`. Applications holding models
+//! chosen at runtime, such as a registry mapping names to models of different providers,
+//! or plugins loading models, erase their types with [`BoxedLanguageModel`] instead.
+//!
+//! A boxed model is itself a [`LanguageModel`], whose errors are boxed as [`BoxedError`].
+//! Its streams and futures are boxed, at the cost of an allocation per call.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{BoxedLanguageModel, LanguageModel, Request};
+//! use std::collections::HashMap;
+//!
+//! async fn ask(
+//!     models: &HashMap<String, BoxedLanguageModel>,
+//!     name: &str,
+//! ) -> ai_types::Result {
+//!     let model = &models[name];
+//!     Ok(model.respond(Request::oneshot("Be brief", "Hello!")).await?)
+//! }
+//!
+//! fn register(models: &mut HashMap<String, BoxedLanguageModel>, model: impl LanguageModel) {
+//!     models.insert(model.profile().name, model.boxed());
+//! }
+//! ```
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::{fmt, future::Future, pin::Pin, time::Duration};
+
+use async_stream::stream;
+use futures_core::Stream;
+use futures_lite::{StreamExt, pin};
+use schemars::Schema;
+use serde_json::Value;
+
+use crate::{
+    error::{Classify, ErrorKind, ProviderError, RawError},
+    llm::{
+        LanguageModel, Request, TextStream, event::StreamEvent, generate_with_schema,
+        model::Profile, questionnaire::Question, stream::text_stream,
+    },
+    rate_limit::RateLimitInfo,
+};
+
+type BoxStream<'a, T> = Pin<Box<dyn Stream<Item = T> + Send + 'a>>;
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// The object-safe subset of [`LanguageModel`], implemented for every model.
+trait ErasedModel: Send + Sync {
+    fn erased_respond(&self, request: Request) -> BoxStream<'_, Result<String, BoxedError>>;
+
+    fn erased_respond_events(
+        &self,
+        request: Request,
+    ) -> BoxStream<'_, Result<StreamEvent, BoxedError>>;
+
+    fn erased_respond_n(&self, request: Request) -> Vec<BoxStream<'_, Result<String, BoxedError>>>;
+
+    fn erased_generate(
+        &self,
+        request: Request,
+        schema: Schema,
+    ) -> BoxFuture<'_, crate::Result<Value>>;
+
+    fn erased_complete(&self, prefix: String) -> BoxStream<'_, Result<String, BoxedError>>;
+
+    fn erased_summarize(&self, text: String) -> BoxStream<'_, Result<String, BoxedError>>;
+
+    fn erased_generate_questionnaire(
+        &self,
+        spec: String,
+        count: usize,
+    ) -> BoxStream<'_, crate::Result<Question>>;
+
+    fn erased_profile(&self) -> Profile;
+}
+
+impl<M: LanguageModel> ErasedModel for M {
+    fn erased_respond(&self, request: Request) -> BoxStream<'_, Result<String, BoxedError>> {
+        Box::pin(LanguageModel::respond(self, request).map(|chunk| chunk.map_err(BoxedError::new)))
+    }
+
+    fn erased_respond_events(
+        &self,
+        request: Request,
+    ) -> BoxStream<'_, Result<StreamEvent, BoxedError>> {
+        Box::pin(
+            LanguageModel::respond_events(self, request)
+                .map(|event| event.map_err(BoxedError::new)),
+        )
+    }
+
+    fn erased_respond_n(&self, request: Request) -> Vec<BoxStream<'_, Result<String, BoxedError>>> {
+        LanguageModel::respond_n(self, request)
+            .into_iter()
+            .map(|candidate| -> BoxStream<'_, _> {
+                Box::pin(candidate.map(|chunk| chunk.map_err(BoxedError::new)))
+            })
+            .collect()
+    }
+
+    fn erased_generate(
+        &self,
+        request: Request,
+        schema: Schema,
+    ) -> BoxFuture<'_, crate::Result<Value>> {
+        Box::pin(generate_with_schema(self, request, schema))
+    }
+
+    fn erased_complete(&self, prefix: String) -> BoxStream<'_, Result<String, BoxedError>> {
+        Box::pin(stream! {
+            let chunks = LanguageModel::complete(self, &prefix);
+            pin!(chunks);
+            while let Some(chunk) = chunks.next().await {
+                yield chunk.map_err(BoxedError::new);
+            }
+        })
+    }
+
+    fn erased_summarize(&self, text: String) -> BoxStream<'_, Result<String, BoxedError>> {
+        Box::pin(stream! {
+            let chunks = LanguageModel::summarize(self, &text);
+            pin!(chunks);
+            while let Some(chunk) = chunks.next().await {
+                yield chunk.map_err(BoxedError::new);
+            }
+        })
+    }
+
+    fn erased_generate_questionnaire(
+        &self,
+        spec: String,
+        count: usize,
+    ) -> BoxStream<'_, crate::Result<Question>> {
+        Box::pin(stream! {
+            let questions = LanguageModel::generate_questionnaire(self, &spec, count);
+            pin!(questions);
+            while let Some(question) = questions.next().await {
+                yield question;
+            }
+        })
+    }
+
+    fn erased_profile(&self) -> Profile {
+        LanguageModel::profile(self)
+    }
+}
+
+/// A language model of any type.
+///
+/// Create one with [`new`](Self::new) or [`LanguageModel::boxed`]. A blanket `From`
+/// conversion is not possible, because it would conflict with the reflexive `From` of
+/// the boxed model itself.
+///
+/// Methods of [`LanguageModel`] taking no type parameters are forwarded to the wrapped
+/// model, including its own implementations of provided methods. Generic methods, such
+/// as [`generate`](LanguageModel::generate), use their default implementations on top
+/// of the forwarded ones; [`erased_generate`](Self::erased_generate) is a non-generic
+/// alternative forwarded as is.
+///
+/// See the [module documentation](crate::llm::boxed) for an example.
+pub struct BoxedLanguageModel(Box<dyn ErasedModel>);
+
+impl BoxedLanguageModel {
+    /// Erases the type of `model`.
+    #[must_use]
+    pub fn new(model: impl LanguageModel) -> Self {
+        Self(Box::new(model))
+    }
+
+    /// Generates JSON conforming to `schema`, like [`generate`](LanguageModel::generate)
+    /// with a schema known only at runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the model fails, or if its response is not valid JSON.
+    pub fn erased_generate(
+        &self,
+        request: Request,
+        schema: Schema,
+    ) -> impl Future<Output = crate::Result<Value>> + Send + '_ {
+        self.0.erased_generate(request, schema)
+    }
+}
+
+impl fmt::Debug for BoxedLanguageModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("BoxedLanguageModel")
+            .field(&self.0.erased_profile().name)
+            .finish()
+    }
+}
+
+impl LanguageModel for BoxedLanguageModel {
+    type Error = BoxedError;
+
+    fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+        text_stream(self.0.erased_respond(request))
+    }
+
+    fn respond_events(
+        &self,
+        request: Request,
+    ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+        self.0.erased_respond_events(request)
+    }
+
+    fn respond_n(&self, request: Request) -> Vec<impl TextStream<Error = Self::Error> + Send> {
+        self.0
+            .erased_respond_n(request)
+            .into_iter()
+            .map(text_stream)
+            .collect()
+    }
+
+    fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+        text_stream(self.0.erased_complete(prefix.into()))
+    }
+
+    fn summarize(&self, text: &str) -> impl TextStream<Error = Self::Error> + Send {
+        text_stream(self.0.erased_summarize(text.into()))
+    }
+
+    fn generate_questionnaire(
+        &self,
+        spec: &str,
+        count: usize,
+    ) -> impl Stream<Item = crate::Result<Question>> + Send {
+        self.0.erased_generate_questionnaire(spec.into(), count)
+    }
+
+    fn profile(&self) -> Profile {
+        self.0.erased_profile()
+    }
+
+    fn boxed(self) -> BoxedLanguageModel {
+        self
+    }
+}
+
+/// An error of a [`BoxedLanguageModel`], wrapping the error of the underlying model.
+///
+/// It displays as the wrapped error, and can be downcast back to it. If the wrapped
+/// error is a [`ProviderError`], its classification is forwarded.
+pub struct BoxedError(Box<dyn core::error::Error + Send + Sync>);
+
+impl BoxedError {
+    /// Boxes `error`.
+    pub fn new(error: impl core::error::Error + Send + Sync + 'static) -> Self {
+        Self(Box::new(error))
+    }
+
+    /// Returns the wrapped error if it has type `E`.
+    #[must_use]
+    pub fn downcast_ref<E: core::error::Error + 'static>(&self) -> Option<&E> {
+        self.0.downcast_ref()
+    }
+
+    /// Returns the wrapped error.
+    #[must_use]
+    pub fn into_inner(self) -> Box<dyn core::error::Error + Send + Sync> {
+        self.0
+    }
+}
+
+impl fmt::Debug for BoxedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for BoxedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl core::error::Error for BoxedError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
+impl Classify for BoxedError {
+    fn kind(&self) -> ErrorKind {
+        self.downcast_ref::<ProviderError>()
+            .map_or(ErrorKind::Other, Classify::kind)
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        self.downcast_ref::<ProviderError>()
+            .and_then(Classify::retry_after)
+    }
+
+    fn rate_limit(&self) -> Option<RateLimitInfo> {
+        self.downcast_ref::<ProviderError>()
+            .and_then(Classify::rate_limit)
+    }
+
+    fn raw(&self) -> Option<&RawError> {
+        self.downcast_ref::<ProviderError>().and_then(Classify::raw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{Role, model::Parameters};
+    use alloc::{collections::BTreeMap, format, string::ToString, vec, vec::Vec};
+    use schemars::schema_for;
+
+    /// Fails on requests without user messages, and otherwise echoes the last one.
+    struct Echo;
+
+    impl LanguageModel for Echo {
+        type Error = ProviderError;
+
+        fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+            let last = request
+                .messages
+                .iter()
+                .rev()
+                .find(|m| m.role() == Role::User);
+            let chunk = last.map_or_else(
+                || Err(ProviderError::new(ErrorKind::InvalidRequest, "No messages")),
+                |message| Ok(message.content().into_owned()),
+            );
+            text_stream(futures_lite::stream::iter(vec![chunk]))
+        }
+
+        fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+            text_stream(futures_lite::stream::iter(vec![Ok(format!("{prefix}..."))]))
+        }
+
+        fn summarize(&self, _text: &str) -> impl TextStream<Error = Self::Error> + Send {
+            text_stream(futures_lite::stream::iter(vec![Ok("Summary".into())]))
+        }
+
+        fn respond_n(&self, request: Request) -> Vec<impl TextStream<Error = Self::Error> + Send> {
+            (0..request.parameters.n.unwrap_or(1))
+                .map(|index| text_stream(futures_lite::stream::iter(vec![Ok(index.to_string())])))
+                .collect()
+        }
+
+        fn profile(&self) -> Profile {
+            Profile::new("echo", "Echoes the last user message", 1024)
+        }
+    }
+
+    #[tokio::test]
+    async fn forwards_to_the_wrapped_model() {
+        let models: Vec<BoxedLanguageModel> = vec![Echo.boxed(), BoxedLanguageModel::new(Echo)];
+        let model = &models[0];
+
+        assert_eq!(model.profile().name, "echo");
+        let request = Request::oneshot("system", r#"{"answer": 42}"#);
+        assert_eq!(
+            model.respond(request.clone()).await.unwrap(),
+            r#"{"answer": 42}"#
+        );
+        assert_eq!(model.complete("Once").await.unwrap(), "Once...");
+        assert_eq!(model.summarize("Long text").await.unwrap(), "Summary");
+        let mut candidates = Vec::new();
+        let n = request.clone().with_parameters(Parameters::default().n(2));
+        for candidate in model.respond_n(n) {
+            candidates.push(candidate.await.unwrap());
+        }
+        assert_eq!(candidates, ["0", "1"]);
+
+        let value = model
+            .erased_generate(request.clone(), schema_for!(BTreeMap<String, u32>))
+            .await
+            .unwrap();
+        assert_eq!(value["answer"], 42);
+        let typed: BTreeMap<String, u32> = models[1].generate(request).await.unwrap();
+        assert_eq!(typed["answer"], 42);
+    }
+
+    #[tokio::test]
+    async fn errors_keep_their_classification() {
+        let error = Echo.boxed().respond(Request::default()).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidRequest);
+        assert_eq!(error.to_string(), "invalid request: No messages");
+        assert!(error.downcast_ref::<ProviderError>().is_some());
+    }
+}
@@ -0,0 +1,225 @@
+//! Token budget planning between prompt and completion.
+//!
+//! A model's context window is shared by the prompt and the generated completion.
+//! [`BudgetPlanner`] splits it: given the context length, the tokens reserved for the
+//! completion, and the size of the already assembled prompt, it produces a
+//! [`BudgetPlan`] telling a conversation or RAG assembler exactly how many tokens of
+//! retrieved context or history can still be included.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{budget::BudgetPlanner, model::Profile};
+//!
+//! let profile = Profile::new("small-model", "A small model", 8192);
+//! let planner = BudgetPlanner::from_profile(&profile).reserve_completion(1024);
+//!
+//! // The system prompt and user question take 600 tokens.
+//! let plan = planner.plan(600);
+//! assert_eq!(plan.available(), 8192 - 1024 - 600);
+//!
+//! // Retrieved documents, most relevant first, with their token counts.
+//! let documents = [3000, 2500, 2000, 500];
+//! assert_eq!(plan.fit_count(documents), 2);
+//! ```
+
+use crate::llm::model::{Parameters, Profile};
+
+/// Plans how a model's context window is split between prompt and completion.
+///
+/// See the [module documentation](crate::llm::budget) for an example.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetPlanner {
+    context_length: u32,
+    reserved_completion: u32,
+}
+
+impl BudgetPlanner {
+    /// Creates a planner for a model with the given context length.
+    ///
+    /// No tokens are reserved for the completion until
+    /// [`reserve_completion`](Self::reserve_completion) is called.
+    #[must_use]
+    pub const fn new(context_length: u32) -> Self {
+        Self {
+            context_length,
+            reserved_completion: 0,
+        }
+    }
+
+    /// Creates a planner from a model [`Profile`].
+    #[must_use]
+    pub const fn from_profile(profile: &Profile) -> Self {
+        Self::new(profile.context_length)
+    }
+
+    /// Reserves `tokens` of the context window for the completion.
+    #[must_use]
+    pub const fn reserve_completion(mut self, tokens: u32) -> Self {
+        self.reserved_completion = tokens;
+        self
+    }
+
+    /// Reserves the completion budget requested by [`Parameters::max_tokens`].
+    ///
+    /// Leaves the current reservation unchanged when `max_tokens` is not set.
+    #[must_use]
+    pub const fn reserve_from_parameters(self, parameters: &Parameters) -> Self {
+        match parameters.max_tokens {
+            Some(tokens) => self.reserve_completion(tokens),
+            None => self,
+        }
+    }
+
+    /// Returns the context length of the model.
+    #[must_use]
+    pub const fn context_length(&self) -> u32 {
+        self.context_length
+    }
+
+    /// Returns the tokens reserved for the completion.
+    #[must_use]
+    pub const fn reserved_completion(&self) -> u32 {
+        self.reserved_completion
+    }
+
+    /// Returns the largest completion a prompt of `prompt_tokens` tokens leaves room for,
+    /// keeping `margin` tokens free for errors in the prompt's count.
+    ///
+    /// Ignores the completion reservation. Zero when the prompt alone fills the window.
+    #[must_use]
+    pub const fn max_completion(&self, prompt_tokens: u32, margin: u32) -> u32 {
+        self.context_length
+            .saturating_sub(prompt_tokens)
+            .saturating_sub(margin)
+    }
+
+    /// Computes the plan for an assembled prompt of `prompt_tokens` tokens.
+    #[must_use]
+    pub const fn plan(&self, prompt_tokens: u32) -> BudgetPlan {
+        BudgetPlan {
+            context_length: self.context_length,
+            prompt_tokens,
+            completion_tokens: self.reserved_completion,
+        }
+    }
+}
+
+/// The result of [`BudgetPlanner::plan`].
+///
+/// Describes how many tokens are left for additional context once the prompt and the
+/// completion reservation are accounted for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetPlan {
+    context_length: u32,
+    prompt_tokens: u32,
+    completion_tokens: u32,
+}
+
+impl BudgetPlan {
+    /// Returns the context length the plan was computed for.
+    #[must_use]
+    pub const fn context_length(&self) -> u32 {
+        self.context_length
+    }
+
+    /// Returns the tokens used by the assembled prompt.
+    #[must_use]
+    pub const fn prompt_tokens(&self) -> u32 {
+        self.prompt_tokens
+    }
+
+    /// Returns the tokens reserved for the completion.
+    #[must_use]
+    pub const fn completion_tokens(&self) -> u32 {
+        self.completion_tokens
+    }
+
+    /// Returns the tokens still available for retrieved context or history.
+    #[must_use]
+    pub const fn available(&self) -> u32 {
+        self.context_length
+            .saturating_sub(self.completion_tokens)
+            .saturating_sub(self.prompt_tokens)
+    }
+
+    /// Returns by how many tokens the prompt and completion exceed the context window.
+    ///
+    /// Zero when the plan fits.
+    #[must_use]
+    pub const fn overflow(&self) -> u32 {
+        self.prompt_tokens
+            .saturating_add(self.completion_tokens)
+            .saturating_sub(self.context_length)
+    }
+
+    /// Returns whether the prompt and completion fit into the context window.
+    #[must_use]
+    pub const fn fits(&self) -> bool {
+        self.overflow() == 0
+    }
+
+    /// Returns how many items fit into the available budget, in order.
+    ///
+    /// `token_counts` are the sizes of candidate items (retrieved chunks, or history
+    /// messages from newest to oldest) in the order they should be included. Stops at
+    /// the first item that does not fit.
+    pub fn fit_count(&self, token_counts: impl IntoIterator<Item = u32>) -> usize {
+        let mut remaining = self.available();
+        let mut count = 0;
+        for tokens in token_counts {
+            if tokens > remaining {
+                break;
+            }
+            remaining -= tokens;
+            count += 1;
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plan_available_tokens() {
+        let plan = BudgetPlanner::new(4096).reserve_completion(1000).plan(96);
+        assert_eq!(plan.available(), 3000);
+        assert!(plan.fits());
+        assert_eq!(plan.overflow(), 0);
+    }
+
+    #[test]
+    fn plan_overflow() {
+        let plan = BudgetPlanner::new(1000).reserve_completion(500).plan(700);
+        assert_eq!(plan.available(), 0);
+        assert!(!plan.fits());
+        assert_eq!(plan.overflow(), 200);
+    }
+
+    #[test]
+    fn plan_reserves_from_parameters() {
+        let planner =
+            BudgetPlanner::new(2048).reserve_from_parameters(&Parameters::default().max_tokens(48));
+        assert_eq!(planner.reserved_completion(), 48);
+
+        let unchanged = planner.reserve_from_parameters(&Parameters::default());
+        assert_eq!(unchanged.reserved_completion(), 48);
+    }
+
+    #[test]
+    fn max_completion_keeps_margin() {
+        let planner = BudgetPlanner::new(4096).reserve_completion(1000);
+        assert_eq!(planner.max_completion(1000, 96), 3000);
+        assert_eq!(planner.max_completion(4000, 200), 0);
+    }
+
+    #[test]
+    fn fit_count_stops_at_first_overflow() {
+        let plan = BudgetPlanner::new(100).plan(0);
+        assert_eq!(plan.fit_count([40, 40, 30, 10]), 2);
+        assert_eq!(plan.fit_count([]), 0);
+        assert_eq!(plan.fit_count([100]), 1);
+    }
+}
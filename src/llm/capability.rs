@@ -0,0 +1,336 @@
+//! Capability matrices of models across providers.
+//!
+//! A [`CapabilityMatrix`] lists models with their [abilities](Ability), the
+//! [parameters they support](SupportedParameters), their context length and their
+//! [pricing](Pricing), one [`CapabilityRow`] per model. It is plain data, serializable with
+//! the `serde` feature, and renders to [CSV](CapabilityMatrix::to_csv) for dashboards or to
+//! a [Markdown table](CapabilityMatrix::to_markdown) for generated documentation.
+//!
+//! Profiles don't report supported parameters, so they are given alongside each model.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{
+//!     capability::CapabilityMatrix,
+//!     model::{Ability, Profile, SupportedParameters},
+//! };
+//!
+//! let mut parameters = SupportedParameters::default();
+//! parameters.temperature = true;
+//!
+//! let matrix = CapabilityMatrix::new()
+//!     .with_model(
+//!         "openai",
+//!         Profile::new("gpt-4o", "GPT-4o", 128_000).with_ability(Ability::Vision),
+//!         parameters,
+//!     )
+//!     .with_model("local", Profile::new("llama", "Llama", 8192), parameters);
+//!
+//! let csv = matrix.to_csv();
+//! assert!(csv.starts_with("provider,model,context_length,tool_use,vision,"));
+//! assert!(csv.contains("\nopenai,gpt-4o,128000,false,true,"));
+//! ```
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::llm::{
+    LanguageModel, LanguageModelProvider,
+    model::{Ability, Pricing, Profile, SupportedParameters},
+};
+
+/// Every ability, in column order.
+const ABILITIES: [(Ability, &str); 5] = [
+    (Ability::ToolUse, "tool_use"),
+    (Ability::Vision, "vision"),
+    (Ability::Audio, "audio"),
+    (Ability::WebSearch, "web_search"),
+    (Ability::StructuredOutput, "structured_output"),
+];
+
+/// Capabilities of models across providers, one row per model.
+///
+/// See the [module documentation](crate::llm::capability) for an example.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CapabilityMatrix {
+    /// The models, in the order they were added.
+    pub rows: Vec<CapabilityRow>,
+}
+
+/// A model in a [`CapabilityMatrix`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct CapabilityRow {
+    /// The name of the provider serving the model.
+    pub provider: String,
+    /// The model's profile.
+    pub profile: Profile,
+    /// The parameters the model supports.
+    pub parameters: SupportedParameters,
+}
+
+impl CapabilityRow {
+    /// Creates a row for the model described by `profile`, served by `provider`.
+    pub fn new(
+        provider: impl Into<String>,
+        profile: Profile,
+        parameters: SupportedParameters,
+    ) -> Self {
+        Self {
+            provider: provider.into(),
+            profile,
+            parameters,
+        }
+    }
+
+    /// Returns whether the model has `ability`.
+    #[must_use]
+    pub fn supports(&self, ability: Ability) -> bool {
+        self.profile.abilities.contains(&ability)
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+        let mut cells = vec![
+            Cell::Text(self.provider.clone()),
+            Cell::Text(self.profile.name.clone()),
+            Cell::Text(self.profile.context_length.to_string()),
+        ];
+        cells.extend(
+            ABILITIES
+                .iter()
+                .map(|&(ability, _)| Cell::Flag(self.supports(ability))),
+        );
+        cells.extend(
+            parameters(&self.parameters)
+                .into_iter()
+                .map(|(_, supported)| Cell::Flag(supported)),
+        );
+        let price = |price: fn(&Pricing) -> f64| {
+            Cell::Text(
+                self.profile
+                    .pricing
+                    .as_ref()
+                    .map(|pricing| price(pricing).to_string())
+                    .unwrap_or_default(),
+            )
+        };
+        cells.push(price(|pricing| pricing.prompt));
+        cells.push(price(|pricing| pricing.completion));
+        cells
+    }
+}
+
+impl CapabilityMatrix {
+    /// Creates an empty matrix.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { rows: Vec::new() }
+    }
+
+    /// Adds the model described by `profile`, served by `provider`.
+    #[must_use]
+    pub fn with_model(
+        mut self,
+        provider: impl Into<String>,
+        profile: Profile,
+        parameters: SupportedParameters,
+    ) -> Self {
+        self.rows
+            .push(CapabilityRow::new(provider, profile, parameters));
+        self
+    }
+
+    /// Adds every model listed by `provider`.
+    ///
+    /// `parameters` returns the parameters supported by each model, given its profile.
+    pub async fn add_provider<P, F>(&mut self, provider: &P, parameters: F)
+    where
+        P: LanguageModelProvider + Sync,
+        F: Fn(&Profile) -> SupportedParameters + Send,
+    {
+        let name = P::profile().name().to_string();
+        for model in provider.list_models().await {
+            let profile = provider.get_model(&model).await.profile();
+            let supported = parameters(&profile);
+            self.rows
+                .push(CapabilityRow::new(name.clone(), profile, supported));
+        }
+    }
+
+    /// Renders the matrix as CSV, with a header row.
+    ///
+    /// Abilities and parameters are `true` or `false`. Prices are per token and empty
+    /// for models without pricing.
+    #[must_use]
+    pub fn to_csv(&self) -> String {
+        let mut csv = header().join(",");
+        for row in &self.rows {
+            let cells: Vec<String> = row
+                .cells()
+                .into_iter()
+                .map(|cell| match cell {
+                    Cell::Text(text) if text.contains([',', '"', '\n']) => {
+                        format!("\"{}\"", text.replace('"', "\"\""))
+                    }
+                    Cell::Text(text) => text,
+                    Cell::Flag(flag) => flag.to_string(),
+                })
+                .collect();
+            csv.push('\n');
+            csv.push_str(&cells.join(","));
+        }
+        csv
+    }
+
+    /// Renders the matrix as a Markdown table.
+    ///
+    /// Supported abilities and parameters are marked with `✓`.
+    #[must_use]
+    pub fn to_markdown(&self) -> String {
+        let header = header();
+        let mut table = format!("| {} |\n|", header.join(" | "));
+        table.push_str(&"---|".repeat(header.len()));
+        for row in &self.rows {
+            let cells: Vec<String> = row
+                .cells()
+                .into_iter()
+                .map(|cell| match cell {
+                    Cell::Text(text) => text.replace('|', "\\|").replace('\n', " "),
+                    Cell::Flag(true) => "✓".to_string(),
+                    Cell::Flag(false) => String::new(),
+                })
+                .collect();
+            table.push_str("\n| ");
+            table.push_str(&cells.join(" | "));
+            table.push_str(" |");
+        }
+        table
+    }
+}
+
+/// A cell of a rendered matrix.
+enum Cell {
+    Text(String),
+    Flag(bool),
+}
+
+/// Returns the column names.
+fn header() -> Vec<&'static str> {
+    let mut header = vec!["provider", "model", "context_length"];
+    header.extend(ABILITIES.iter().map(|&(_, name)| name));
+    header.extend(
+        parameters(&SupportedParameters::default())
+            .into_iter()
+            .map(|(name, _)| name),
+    );
+    header.extend(["prompt_price", "completion_price"]);
+    header
+}
+
+/// Returns each parameter with whether it is supported, in column order.
+const fn parameters(parameters: &SupportedParameters) -> [(&'static str, bool); 11] {
+    [
+        ("max_tokens", parameters.max_tokens),
+        ("temperature", parameters.temperature),
+        ("top_p", parameters.top_p),
+        ("reasoning", parameters.reasoning),
+        ("include_reasoning", parameters.include_reasoning),
+        ("structured_outputs", parameters.structured_outputs),
+        ("response_format", parameters.response_format),
+        ("stop", parameters.stop),
+        ("frequency_penalty", parameters.frequency_penalty),
+        ("presence_penalty", parameters.presence_penalty),
+        ("seed", parameters.seed),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{llm::provider, test_util::MockLanguageModel};
+    use alloc::{string::String, vec::Vec};
+
+    struct Lab;
+
+    impl LanguageModelProvider for Lab {
+        type Model = MockLanguageModel;
+
+        async fn list_models(&self) -> Vec<String> {
+            vec!["seer".into(), "scribe".into()]
+        }
+
+        async fn get_model(&self, name: &str) -> MockLanguageModel {
+            let profile = Profile::new(name, "", 4096);
+            let profile = if name == "seer" {
+                profile.with_ability(Ability::Vision)
+            } else {
+                profile
+            };
+            MockLanguageModel::new().with_profile(profile)
+        }
+
+        fn profile() -> provider::Profile {
+            provider::Profile::new("lab", "A lab")
+        }
+    }
+
+    #[tokio::test]
+    async fn lists_every_model_of_a_provider() {
+        let mut matrix = CapabilityMatrix::new();
+        matrix
+            .add_provider(&Lab, |profile| SupportedParameters {
+                seed: profile.name == "scribe",
+                ..SupportedParameters::default()
+            })
+            .await;
+
+        let names: Vec<_> = matrix
+            .rows
+            .iter()
+            .map(|row| (row.provider.as_str(), row.profile.name.as_str()))
+            .collect();
+        assert_eq!(names, [("lab", "seer"), ("lab", "scribe")]);
+        assert!(matrix.rows[0].supports(Ability::Vision));
+        assert!(matrix.rows[1].parameters.seed);
+    }
+
+    #[test]
+    fn renders_csv_and_markdown() {
+        let pricing = Pricing {
+            prompt: 0.5,
+            completion: 1.5,
+            ..Pricing::default()
+        };
+        let parameters = SupportedParameters {
+            max_tokens: true,
+            ..SupportedParameters::default()
+        };
+        let matrix = CapabilityMatrix::new().with_model(
+            "acme",
+            Profile::new("fast, \"cheap\" | v2", "", 100)
+                .with_ability(Ability::ToolUse)
+                .with_pricing(pricing),
+            parameters,
+        );
+
+        let csv = matrix.to_csv();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].split(',').count(), header().len());
+        assert!(lines[1].starts_with(r#"acme,"fast, ""cheap"" | v2",100,true,false,"#));
+        assert!(lines[1].ends_with(",0.5,1.5"));
+
+        let markdown = matrix.to_markdown();
+        let lines: Vec<_> = markdown.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("|---|---|"));
+        assert!(lines[2].starts_with(r#"| acme | fast, "cheap" \| v2 | 100 | ✓ |  |"#));
+    }
+}
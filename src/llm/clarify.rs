@@ -0,0 +1,208 @@
+//! Structured generation that may ask clarifying questions first.
+//!
+//! Requests are often underspecified: "book me a flight" lacks a date and a destination.
+//! Instead of guessing, [`LanguageModel::clarify_then_generate`] lets the model reply with
+//! a [`NeedClarification`] rather than the requested type. The question is handed to the
+//! caller, whose answer is appended to the conversation before the model tries again.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{LanguageModel, Request, clarify::NeedClarification};
+//! use schemars::JsonSchema;
+//! use serde::Deserialize;
+//!
+//! #[derive(JsonSchema, Deserialize)]
+//! struct Flight {
+//!     destination: String,
+//!     date: String,
+//! }
+//!
+//! async fn book(model: impl LanguageModel) -> ai_types::Result<Flight> {
+//!     model
+//!         .clarify_then_generate(
+//!             Request::oneshot("Extract the flight to book", "Book me a flight to Tokyo"),
+//!             |clarification: NeedClarification| async move {
+//!                 // Show the question to the user and return their answer.
+//!                 println!("{}", clarification.question);
+//!                 Ok(String::from("Next Friday"))
+//!             },
+//!         )
+//!         .await
+//! }
+//! ```
+
+use alloc::{string::String, vec::Vec};
+use core::future::Future;
+
+use schemars::{JsonSchema, schema_for};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value, json};
+
+use crate::llm::{LanguageModel, Message, Request, extract, prompts, tool, try_collect};
+
+/// The key of the object the model replies with to ask a question.
+const KEY: &str = "need_clarification";
+
+/// A question the model needs answered before it can produce its output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct NeedClarification {
+    /// The question to ask the user.
+    pub question: String,
+}
+
+impl NeedClarification {
+    /// Creates a clarification request.
+    pub fn new(question: impl Into<String>) -> Self {
+        Self {
+            question: question.into(),
+        }
+    }
+}
+
+/// Builds the schema accepting either `T` or a clarification request.
+fn schema<T: JsonSchema>() -> Value {
+    let mut answer = schema_for!(T).as_value().clone();
+    let mut root = Map::new();
+    if let Some(object) = answer.as_object_mut() {
+        object.remove("$schema");
+        // Definitions must stay at the root for `$ref`s to resolve.
+        if let Some(defs) = object.remove("$defs") {
+            root.insert("$defs".into(), defs);
+        }
+    }
+    let question = json!({
+        "type": "object",
+        "properties": {
+            KEY: {
+                "type": "object",
+                "properties": { "question": { "type": "string" } },
+                "required": ["question"],
+            },
+        },
+        "required": [KEY],
+        "additionalProperties": false,
+    });
+    root.insert("anyOf".into(), Value::Array(Vec::from([answer, question])));
+    Value::Object(root)
+}
+
+/// Parses a response as either a clarification request or `T`.
+fn parse<T: DeserializeOwned>(response: &str) -> crate::Result<Result<T, NeedClarification>> {
+    let value: Value = extract::parse_json(response)?;
+    if let Some(object) = value.as_object().filter(|object| object.len() == 1) {
+        if let Some(question) = object
+            .get(KEY)
+            .and_then(|clarification| clarification.get("question"))
+            .and_then(Value::as_str)
+        {
+            return Ok(Err(NeedClarification::new(question)));
+        }
+    }
+    Ok(Ok(serde_json::from_value(value)?))
+}
+
+pub(crate) async fn clarify_then_generate<T, M, F, Fut>(
+    model: &M,
+    request: Request,
+    mut ask: F,
+) -> crate::Result<T>
+where
+    T: JsonSchema + DeserializeOwned,
+    M: LanguageModel,
+    F: FnMut(NeedClarification) -> Fut,
+    Fut: Future<Output = crate::Result<String>>,
+{
+    let prompt = prompts::clarify(&tool::json(&schema::<T>()));
+    let mut exchanges = Vec::new();
+    loop {
+        let mut round = request.clone();
+        round.messages.extend(exchanges.iter().cloned());
+        round.messages.push(Message::system(prompt.clone()));
+
+        let response = try_collect(model.respond(round)).await?;
+        let clarification = match parse(&response)? {
+            Ok(value) => return Ok(value),
+            Err(clarification) => clarification,
+        };
+        exchanges.push(Message::assistant(clarification.question.clone()));
+        let answer = ask(clarification).await?;
+        exchanges.push(Message::user(answer));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::MockLanguageModel;
+    use alloc::string::ToString;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Eq, JsonSchema, Deserialize)]
+    struct Flight {
+        destination: String,
+        date: String,
+    }
+
+    #[tokio::test]
+    async fn asks_before_generating() {
+        let model = MockLanguageModel::new().with_replies([
+            r#"{"need_clarification": {"question": "When?"}}"#,
+            r#"{"destination": "Tokyo", "date": "Friday"}"#,
+        ]);
+        let mut questions = Vec::new();
+        let flight: Flight = model
+            .clarify_then_generate(
+                Request::oneshot("Extract", "Fly to Tokyo"),
+                |clarification| {
+                    questions.push(clarification.question);
+                    core::future::ready(Ok("Friday".to_string()))
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            flight,
+            Flight {
+                destination: "Tokyo".into(),
+                date: "Friday".into(),
+            }
+        );
+        assert_eq!(questions, ["When?"]);
+
+        let requests = model.requests();
+        let retry = &requests[1].messages;
+        assert_eq!(retry[retry.len() - 3].content(), "When?");
+        assert_eq!(retry[retry.len() - 2].content(), "Friday");
+    }
+
+    #[tokio::test]
+    async fn answers_directly_without_asking() {
+        let model =
+            MockLanguageModel::new().with_reply(r#"{"destination": "Oslo", "date": "today"}"#);
+        let flight: Flight = model
+            .clarify_then_generate(Request::oneshot("Extract", "Fly to Oslo today"), |_| {
+                core::future::ready(Err(anyhow::anyhow!("should not ask")))
+            })
+            .await
+            .unwrap();
+        assert_eq!(flight.destination, "Oslo");
+    }
+
+    #[test]
+    fn schema_keeps_definitions_at_root() {
+        #[derive(JsonSchema)]
+        #[allow(dead_code)]
+        struct Outer {
+            inner: Flight,
+        }
+
+        let schema = schema::<Outer>();
+        assert!(schema.get("$defs").is_some());
+        assert!(schema.get("$schema").is_none());
+        assert_eq!(schema["anyOf"].as_array().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,336 @@
+//! Comparing models on the same request.
+//!
+//! Picking a model for a task means weighing quality against speed and price. A
+//! [`Comparison`] runs one request against several models and records, for each, the
+//! output, the time to the first chunk, the total latency, the token usage, and the cost
+//! derived from the model's [`Pricing`](crate::llm::model::Pricing). The report can be
+//! inspected in code, or serialized with the `serde` feature for offline review.
+//!
+//! Models are compared by running them one after another with [`Comparison::run`], or at
+//! once with [`compare`], which takes them [boxed](LanguageModel::boxed) so their types
+//! may differ.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{LanguageModel, Request, compare::Comparison};
+//! use std::time::Instant;
+//!
+//! async fn pick(fast: impl LanguageModel, smart: impl LanguageModel) {
+//!     let start = Instant::now();
+//!     let mut comparison = Comparison::new(
+//!         Request::oneshot("Answer in one word", "What is the capital of France?"),
+//!         move || start.elapsed(),
+//!     );
+//!     comparison.run(&fast).await;
+//!     comparison.run(&smart).await;
+//!
+//!     for result in comparison.results() {
+//!         println!(
+//!             "{}: {:?} in {:?}, ${:.4}",
+//!             result.model,
+//!             result.output,
+//!             result.latency,
+//!             result.cost.unwrap_or_default()
+//!         );
+//!     }
+//!     if let Some(fastest) = comparison.fastest() {
+//!         println!("Fastest: {}", fastest.model);
+//!     }
+//! }
+//! ```
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::time::Duration;
+
+use futures_lite::{StreamExt, pin};
+
+use crate::{
+    llm::{BoxedLanguageModel, LanguageModel, Request, event::StreamEvent, usage::Usage},
+    time::Clock,
+};
+
+/// The outcome of running the compared request against one model.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct ModelResult {
+    /// The name of the model, from its profile.
+    pub model: String,
+    /// The text the model produced, possibly partial if it failed.
+    pub output: String,
+    /// The error the model failed with, if any.
+    pub error: Option<String>,
+    /// The time until the first chunk of text, if any text was produced.
+    pub first_token: Option<Duration>,
+    /// The time until the response completed or failed.
+    pub latency: Duration,
+    /// The token usage reported by the model, if any.
+    pub usage: Option<Usage>,
+    /// The cost of the response in USD, if the model reported usage and has pricing.
+    pub cost: Option<f64>,
+}
+
+impl ModelResult {
+    /// Returns whether the model completed without an error.
+    #[must_use]
+    pub const fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// A comparison of models on one request.
+///
+/// See the [module documentation](crate::llm::compare) for an example.
+#[derive(Debug, Clone)]
+pub struct Comparison<C> {
+    request: Request,
+    clock: C,
+    results: Vec<ModelResult>,
+}
+
+impl<C: Clock> Comparison<C> {
+    /// Creates a comparison of models on `request`, timing them with `clock`.
+    #[must_use]
+    pub const fn new(request: Request, clock: C) -> Self {
+        Self {
+            request,
+            clock,
+            results: Vec::new(),
+        }
+    }
+
+    /// Runs the request against `model` and records the result.
+    pub async fn run<M: LanguageModel>(&mut self, model: &M) -> &ModelResult {
+        let profile = model.profile();
+        let start = self.clock.now();
+        let mut output = String::new();
+        let mut error = None;
+        let mut first_token = None;
+        let mut usage: Option<Usage> = None;
+
+        let events = model.respond_events(self.request.clone());
+        pin!(events);
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(StreamEvent::Text(chunk)) => {
+                    if first_token.is_none() && !chunk.is_empty() {
+                        first_token = Some(self.clock.now().saturating_sub(start));
+                    }
+                    output.push_str(&chunk);
+                }
+                Ok(StreamEvent::Usage(reported)) => {
+                    *usage.get_or_insert_default() += reported;
+                }
+                Ok(StreamEvent::Done) => break,
+                Ok(_) => {}
+                Err(failure) => {
+                    error = Some(failure.to_string());
+                    break;
+                }
+            }
+        }
+
+        let cost = usage
+            .zip(profile.pricing.as_ref())
+            .map(|(usage, pricing)| usage.cost(pricing) + pricing.request);
+        let index = self.results.len();
+        self.results.push(ModelResult {
+            model: profile.name,
+            output,
+            error,
+            first_token,
+            latency: self.clock.now().saturating_sub(start),
+            usage,
+            cost,
+        });
+        &self.results[index]
+    }
+
+    /// Returns the request the models are compared on.
+    #[must_use]
+    pub const fn request(&self) -> &Request {
+        &self.request
+    }
+
+    /// Returns the recorded results, in the order the models were run.
+    #[must_use]
+    pub const fn results(&self) -> &[ModelResult] {
+        self.results.as_slice()
+    }
+
+    /// Consumes the comparison, returning the recorded results.
+    #[must_use]
+    pub fn into_results(self) -> Vec<ModelResult> {
+        self.results
+    }
+
+    /// Returns the successful result with the lowest latency.
+    #[must_use]
+    pub fn fastest(&self) -> Option<&ModelResult> {
+        self.successful().min_by_key(|result| result.latency)
+    }
+
+    /// Returns the successful result with the lowest known cost.
+    #[must_use]
+    pub fn cheapest(&self) -> Option<&ModelResult> {
+        self.successful()
+            .filter_map(|result| result.cost.map(|cost| (cost, result)))
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, result)| result)
+    }
+
+    fn successful(&self) -> impl Iterator<Item = &ModelResult> {
+        self.results.iter().filter(|result| result.is_success())
+    }
+}
+
+/// Runs `request` against each of `models` in turn and returns the comparison.
+pub async fn compare<C: Clock>(
+    models: &[BoxedLanguageModel],
+    request: Request,
+    clock: C,
+) -> Comparison<C> {
+    let mut comparison = Comparison::new(request, clock);
+    for model in models {
+        comparison.run(model).await;
+    }
+    comparison
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::{ErrorKind, ProviderError},
+        llm::{TextStream, model::Pricing, model::Profile, stream::text_stream},
+        test_util::{MockLanguageModel, MockReply},
+    };
+    use alloc::vec;
+    use core::{
+        fmt,
+        sync::atomic::{AtomicU64, Ordering},
+    };
+    use futures_core::Stream;
+
+    /// A clock advanced by the models under test.
+    static NOW: AtomicU64 = AtomicU64::new(0);
+
+    fn clock() -> Duration {
+        Duration::from_millis(NOW.load(Ordering::SeqCst))
+    }
+
+    #[derive(Debug)]
+    struct Failure;
+
+    impl fmt::Display for Failure {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("overloaded")
+        }
+    }
+
+    impl core::error::Error for Failure {}
+
+    /// Takes `millis` per chunk and reports usage, or fails after its first chunk.
+    struct Model {
+        name: &'static str,
+        millis: u64,
+        price: Option<f64>,
+        fails: bool,
+    }
+
+    impl LanguageModel for Model {
+        type Error = Failure;
+
+        fn respond(&self, _request: Request) -> impl TextStream<Error = Self::Error> + Send {
+            text_stream(futures_lite::stream::empty())
+        }
+
+        fn respond_events(
+            &self,
+            _request: Request,
+        ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+            let millis = self.millis;
+            let fails = self.fails;
+            let mut events = vec![Ok(StreamEvent::Text("Paris".into()))];
+            if fails {
+                events.push(Err(Failure));
+            } else {
+                events.push(Ok(StreamEvent::Text(".".into())));
+                events.push(Ok(StreamEvent::Usage(Usage::new(10, 2))));
+                events.push(Ok(StreamEvent::Done));
+            }
+            futures_lite::stream::iter(events).inspect(move |_| {
+                NOW.fetch_add(millis, Ordering::SeqCst);
+            })
+        }
+
+        fn complete(&self, _prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+            text_stream(futures_lite::stream::empty())
+        }
+
+        fn profile(&self) -> Profile {
+            let mut profile = Profile::new(self.name, "A test model", 1024);
+            profile.pricing = self.price.map(|price| Pricing {
+                prompt: price,
+                completion: price,
+                ..Pricing::default()
+            });
+            profile
+        }
+    }
+
+    const fn model(name: &'static str, millis: u64, price: Option<f64>, fails: bool) -> Model {
+        Model {
+            name,
+            millis,
+            price,
+            fails,
+        }
+    }
+
+    #[tokio::test]
+    async fn records_latency_usage_and_cost() {
+        let comparison = compare(
+            &[
+                model("slow", 100, Some(1.0), false).boxed(),
+                model("fast", 10, Some(2.0), false).boxed(),
+                model("free", 50, None, false).boxed(),
+                model("broken", 1, Some(0.0), true).boxed(),
+                MockLanguageModel::new()
+                    .with_reply(MockReply::error(ProviderError::new(
+                        ErrorKind::Timeout,
+                        "no answer",
+                    )))
+                    .boxed(),
+            ],
+            Request::oneshot("Answer", "Capital of France?"),
+            clock,
+        )
+        .await;
+
+        let [slow, fast, free, broken, mock] = comparison.results() else {
+            panic!("expected five results");
+        };
+        assert_eq!(slow.output, "Paris.");
+        assert_eq!(slow.first_token, Some(Duration::from_millis(100)));
+        assert_eq!(slow.latency, Duration::from_millis(400));
+        assert_eq!(slow.usage, Some(Usage::new(10, 2)));
+        assert_eq!(slow.cost, Some(12.0));
+        assert_eq!(fast.cost, Some(24.0));
+        assert_eq!(free.cost, None);
+
+        assert!(!broken.is_success());
+        assert_eq!(broken.output, "Paris");
+        assert_eq!(broken.error.as_deref(), Some("overloaded"));
+        assert_eq!(mock.model, "mock");
+        assert!(!mock.is_success());
+
+        // The broken model is faster and cheaper, but did not succeed.
+        assert_eq!(comparison.fastest().unwrap().model, "fast");
+        assert_eq!(comparison.cheapest().unwrap().model, "slow");
+    }
+}
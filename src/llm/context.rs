@@ -0,0 +1,388 @@
+//! Fitting message histories into a model's context window.
+//!
+//! Long conversations eventually outgrow the context window. A [`ContextManager`] counts
+//! the tokens of a history with a [`TokenCounter`] and, when it exceeds the budget, makes
+//! room according to a [`ContextStrategy`]: dropping the oldest messages, keeping a
+//! sliding window of recent ones, or summarizing what was dropped into a system message.
+//!
+//! Messages protected by the manager's [`TrimProtection`], by default system prompts and
+//! pinned messages, are always kept. Tool results are dropped together with the tool
+//! calls they answer, so the history stays valid for providers.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{
+//!     LanguageModel, Message,
+//!     context::{ContextManager, ContextStrategy},
+//!     token::Estimator,
+//! };
+//!
+//! async fn reply(model: &impl LanguageModel, history: &mut Vec<Message>) -> ai_types::Result<()> {
+//!     let manager = ContextManager::for_profile(Estimator::new(), &model.profile(), 1024)
+//!         .with_strategy(ContextStrategy::Summarize);
+//!     manager.fit(model, history).await?;
+//!     Ok(())
+//! }
+//! ```
+
+use alloc::{format, string::String, vec, vec::Vec};
+use core::fmt::Write;
+
+use crate::llm::{
+    LanguageModel, Message, Role, message::TrimProtection, model::Profile, token::TokenCounter,
+    try_collect,
+};
+
+/// Introduces the system message holding the summary of dropped messages.
+const SUMMARY_PREFIX: &str = "Summary of the earlier conversation:\n";
+
+/// How a [`ContextManager`] makes room when a history exceeds its budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum ContextStrategy {
+    /// Drops the oldest messages until the history fits.
+    #[default]
+    DropOldest,
+    /// Keeps at most the given number of recent unprotected messages, then drops the
+    /// oldest of those if the history still does not fit.
+    SlidingWindow {
+        /// The number of recent unprotected messages to keep.
+        messages: usize,
+    },
+    /// Drops the oldest messages like [`DropOldest`](Self::DropOldest), and replaces
+    /// them with a summary in a system message.
+    ///
+    /// Summarizing requires a model, so it only applies with [`ContextManager::fit`].
+    Summarize,
+}
+
+/// Keeps message histories within a token budget.
+///
+/// See the [module documentation](crate::llm::context) for an example.
+#[derive(Debug, Clone)]
+pub struct ContextManager<C> {
+    counter: C,
+    budget: usize,
+    strategy: ContextStrategy,
+    protection: TrimProtection,
+}
+
+impl<C: TokenCounter> ContextManager<C> {
+    /// Creates a manager keeping histories within `budget` tokens.
+    #[must_use]
+    pub fn new(counter: C, budget: usize) -> Self {
+        Self {
+            counter,
+            budget,
+            strategy: ContextStrategy::default(),
+            protection: TrimProtection::default(),
+        }
+    }
+
+    /// Creates a manager for a model, reserving `completion_tokens` of its context window
+    /// for the response.
+    #[must_use]
+    pub fn for_profile(counter: C, profile: &Profile, completion_tokens: u32) -> Self {
+        Self::new(
+            counter,
+            profile.context_length.saturating_sub(completion_tokens) as usize,
+        )
+    }
+
+    /// Sets the strategy used to make room.
+    #[must_use]
+    pub const fn with_strategy(mut self, strategy: ContextStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sets which messages must never be dropped.
+    #[must_use]
+    pub fn with_protection(mut self, protection: TrimProtection) -> Self {
+        self.protection = protection;
+        self
+    }
+
+    /// Returns the token budget.
+    #[must_use]
+    pub const fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Returns the number of tokens `messages` take up.
+    #[must_use]
+    pub fn count(&self, messages: &[Message]) -> usize {
+        self.counter.count_message_tokens(messages)
+    }
+
+    /// Returns whether `messages` fit within the budget.
+    #[must_use]
+    pub fn fits(&self, messages: &[Message]) -> bool {
+        self.count(messages) <= self.budget
+    }
+
+    /// Drops messages until the history fits, and returns the dropped messages in order.
+    ///
+    /// [`ContextStrategy::Summarize`] drops like [`ContextStrategy::DropOldest`] here; use
+    /// [`fit`](Self::fit) to summarize. The history may still exceed the budget if the
+    /// protected messages alone do.
+    pub fn trim(&self, messages: &mut Vec<Message>) -> Vec<Message> {
+        let mut dropped = Vec::new();
+        if let ContextStrategy::SlidingWindow { messages: window } = self.strategy {
+            let unprotected = messages
+                .iter()
+                .filter(|message| !self.protection.protects(message))
+                .count();
+            for _ in window..unprotected {
+                if !self.drop_oldest(messages, &mut dropped) {
+                    break;
+                }
+            }
+        }
+        while !self.fits(messages) && self.drop_oldest(messages, &mut dropped) {}
+        dropped
+    }
+
+    /// Makes the history fit within the budget using the configured strategy.
+    ///
+    /// With [`ContextStrategy::Summarize`], `model` summarizes the dropped messages, along
+    /// with any previous summary, into a system message placed after the leading system
+    /// prompts.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if summarizing fails. The history is left trimmed, without a
+    /// summary, in that case.
+    pub async fn fit<M: LanguageModel>(
+        &self,
+        model: &M,
+        messages: &mut Vec<Message>,
+    ) -> crate::Result<()>
+    where
+        C: Sync,
+    {
+        if self.strategy != ContextStrategy::Summarize || self.fits(messages) {
+            self.trim(messages);
+            return Ok(());
+        }
+
+        let mut transcript = String::new();
+        if let Some(index) = messages.iter().position(is_summary) {
+            transcript.push_str(&messages.remove(index).content()[SUMMARY_PREFIX.len()..]);
+            transcript.push('\n');
+        }
+
+        // Hold the summary's place while trimming, so room is made for it.
+        let position = messages
+            .iter()
+            .position(|message| message.role() != Role::System)
+            .unwrap_or(messages.len());
+        messages.insert(position, Message::system(SUMMARY_PREFIX));
+        for message in self.trim(messages) {
+            let _ = writeln!(
+                transcript,
+                "{}: {}",
+                role_name(message.role()),
+                message.content()
+            );
+        }
+
+        let summary = try_collect(model.summarize(&transcript)).await;
+        let index = messages.iter().position(is_summary).unwrap_or(position);
+        match summary {
+            Ok(summary) => messages[index] = Message::system(format!("{SUMMARY_PREFIX}{summary}")),
+            Err(error) => {
+                messages.remove(index);
+                return Err(error.into());
+            }
+        }
+        // The summary may be longer than the room made for it.
+        self.trim(messages);
+        Ok(())
+    }
+
+    /// Drops the oldest unprotected message, returning whether there was one.
+    ///
+    /// A tool call and its results are dropped together, and only if none of them is
+    /// protected, so no result outlives its call and no call its results.
+    fn drop_oldest(&self, messages: &mut Vec<Message>, dropped: &mut Vec<Message>) -> bool {
+        let droppable =
+            |message: &Message| !is_summary(message) && !self.protection.protects(message);
+        for index in 0..messages.len() {
+            if !droppable(&messages[index]) {
+                continue;
+            }
+            let unit = tool_unit(messages, index);
+            if !unit.iter().all(|&i| droppable(&messages[i])) {
+                continue;
+            }
+            let mut removed: Vec<Message> =
+                unit.iter().rev().map(|&i| messages.remove(i)).collect();
+            removed.reverse();
+            dropped.append(&mut removed);
+            return true;
+        }
+        false
+    }
+}
+
+/// Returns the indices, in order, of the message at `index` and the messages it must be
+/// dropped with: the tool call it answers, or the results of the tool calls it requested.
+fn tool_unit(messages: &[Message], index: usize) -> Vec<usize> {
+    let call = messages[index]
+        .tool_call_id()
+        .and_then(|id| {
+            messages[..index]
+                .iter()
+                .rposition(|message| message.tool_calls().any(|call| call.id == id))
+        })
+        .unwrap_or(index);
+    let ids: Vec<&str> = messages[call]
+        .tool_calls()
+        .map(|call| call.id.as_str())
+        .collect();
+    let mut unit = vec![call];
+    unit.extend((call + 1..messages.len()).filter(|&i| {
+        messages[i]
+            .tool_call_id()
+            .is_some_and(|id| ids.contains(&id))
+    }));
+    unit
+}
+
+fn is_summary(message: &Message) -> bool {
+    message.role() == Role::System && message.content().starts_with(SUMMARY_PREFIX)
+}
+
+pub(crate) const fn role_name(role: Role) -> &'static str {
+    match role {
+        Role::User => "User",
+        Role::Assistant => "Assistant",
+        Role::System => "System",
+        Role::Tool => "Tool",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{llm::tool::ToolCall, test_util::MockLanguageModel};
+    use alloc::{string::ToString, vec};
+
+    /// Counts one token per message, ignoring overhead.
+    struct PerMessage;
+
+    impl TokenCounter for PerMessage {
+        fn count_tokens(&self, _text: &str) -> usize {
+            1
+        }
+
+        fn count_message_tokens(&self, messages: &[Message]) -> usize {
+            messages.len()
+        }
+    }
+
+    fn history() -> Vec<Message> {
+        vec![
+            Message::system("Be brief"),
+            Message::user("1"),
+            Message::assistant("2"),
+            Message::user("3"),
+            Message::assistant("4"),
+        ]
+    }
+
+    fn contents(messages: &[Message]) -> Vec<String> {
+        messages
+            .iter()
+            .map(|message| message.content().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn drops_oldest_unprotected_messages() {
+        let manager = ContextManager::new(PerMessage, 3);
+        let mut messages = history();
+        let dropped = manager.trim(&mut messages);
+        assert_eq!(contents(&messages), ["Be brief", "3", "4"]);
+        assert_eq!(contents(&dropped), ["1", "2"]);
+    }
+
+    #[test]
+    fn sliding_window_keeps_recent_messages() {
+        let manager = ContextManager::new(PerMessage, 100)
+            .with_strategy(ContextStrategy::SlidingWindow { messages: 1 });
+        let mut messages = history();
+        manager.trim(&mut messages);
+        assert_eq!(contents(&messages), ["Be brief", "4"]);
+    }
+
+    #[test]
+    fn drops_tool_results_with_their_calls() {
+        let manager = ContextManager::new(PerMessage, 2);
+        let mut messages = vec![
+            Message::assistant_tool_calls("", vec![ToolCall::new("a", "search", "{}")]),
+            Message::tool_result("a", "found"),
+            Message::user("thanks"),
+            Message::assistant("welcome"),
+        ];
+        let dropped = manager.trim(&mut messages);
+        assert_eq!(dropped.len(), 2);
+        assert_eq!(contents(&messages), ["thanks", "welcome"]);
+    }
+
+    #[test]
+    fn keeps_tool_calls_with_protected_results() {
+        let manager = ContextManager::new(PerMessage, 3);
+        let mut messages = vec![
+            Message::user("look it up"),
+            Message::assistant_tool_calls("", vec![ToolCall::new("a", "search", "{}")]),
+            Message::tool_result("a", "found").pinned(true),
+            Message::user("thanks"),
+            Message::assistant("welcome"),
+        ];
+        let dropped = manager.trim(&mut messages);
+        assert_eq!(contents(&dropped), ["look it up", "thanks"]);
+        assert_eq!(contents(&messages), ["", "found", "welcome"]);
+        assert_eq!(messages[1].tool_call_id(), Some("a"));
+    }
+
+    #[tokio::test]
+    async fn summarizes_dropped_messages() {
+        let manager = ContextManager::new(PerMessage, 4).with_strategy(ContextStrategy::Summarize);
+        let model = MockLanguageModel::new().with_replies(["1 and 2", "1 to 4"]);
+        let mut messages = history();
+        manager.fit(&model, &mut messages).await.unwrap();
+        assert_eq!(
+            contents(&messages),
+            [
+                "Be brief",
+                "Summary of the earlier conversation:\n1 and 2",
+                "3",
+                "4"
+            ]
+        );
+
+        // A later summary folds in the previous one.
+        messages.push(Message::user("5"));
+        messages.push(Message::assistant("6"));
+        manager.fit(&model, &mut messages).await.unwrap();
+        assert_eq!(
+            contents(&messages),
+            [
+                "Be brief",
+                "Summary of the earlier conversation:\n1 to 4",
+                "5",
+                "6"
+            ]
+        );
+
+        let lines: Vec<_> = model
+            .requests()
+            .iter()
+            .map(|request| request.messages.last().unwrap().content().lines().count())
+            .collect();
+        assert_eq!(lines, [2, 3]);
+    }
+}
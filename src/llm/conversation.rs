@@ -0,0 +1,951 @@
+//! Conversations with a language model.
+//!
+//! A [`Conversation`] owns a message history together with the model it talks to.
+//! [`send`](Conversation::send) appends a user message and streams the model's reply,
+//! which is appended to the history once it completes. Histories can be rolled back with
+//! [`truncate`](Conversation::truncate) and [`undo`](Conversation::undo), or branched with
+//! [`fork`](Conversation::fork) to explore alternative continuations.
+//!
+//! Branches forked from a common history can be reconciled with
+//! [`merge`](Conversation::merge), a three-way merge against the history they were forked
+//! from. Changes made on one branch only are taken as they are. Where both branches changed
+//! the same turn differently, such as two regenerated replies, the merged history holds a
+//! message with conflict markers, and the conflict is returned for the caller to resolve.
+//!
+//! The conversation's [`token_report`](Conversation::token_report) breaks the history down into tokens per
+//! message, per role, and per turn, so applications can show indicators like
+//! "context 72% full" and make trimming decisions they can explain.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use ai_types::llm::{Conversation, LanguageModel};
+//! use futures_lite::StreamExt;
+//!
+//! async fn chat(model: impl LanguageModel) -> ai_types::Result<()> {
+//!     let mut conversation = Conversation::new(model).with_system_prompt("Be brief");
+//!
+//!     let mut reply = conversation.send("What is Rust?");
+//!     while let Some(chunk) = reply.next().await {
+//!         print!("{}", chunk?);
+//!     }
+//!     drop(reply);
+//!
+//!     // The follow-up sees the whole history.
+//!     let answer = conversation.send("Who made it?").await?;
+//!     println!("{answer}");
+//!     assert_eq!(conversation.messages().len(), 5);
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ```rust
+//! use ai_types::llm::{Conversation, LanguageModel, Role};
+//!
+//! fn context_indicator(conversation: &Conversation<impl LanguageModel>) -> String {
+//!     let words = |text: &str| text.split_whitespace().count();
+//!     let report = conversation.token_report(&words);
+//!     format!(
+//!         "context {:.0}% full, {} tokens from the user",
+//!         report.usage_ratio() * 100.0,
+//!         report.role_tokens(Role::User),
+//!     )
+//! }
+//! ```
+
+use alloc::{format, string::String, vec, vec::Vec};
+
+use async_stream::stream;
+use futures_lite::{StreamExt, pin};
+
+use anyhow::bail;
+
+use crate::{
+    Moderation,
+    llm::{
+        LanguageModel, Message, Request, Role, TextStream,
+        anonymize::{Anonymizer, PiiDetector, PiiMap},
+        diff::{self, Step},
+        model::Parameters,
+        stream::text_stream,
+        token::TokenCounter,
+        tool::Tools,
+    },
+    middleware::Guarded,
+};
+
+/// A message history bound to a language model.
+///
+/// See the [module documentation](crate::llm::conversation) for an example.
+#[derive(Debug)]
+pub struct Conversation<M: LanguageModel> {
+    model: M,
+    messages: Vec<Message>,
+    parameters: Parameters,
+    tools: Tools,
+}
+
+impl<M: LanguageModel> Conversation<M> {
+    /// Creates an empty conversation with `model`.
+    #[must_use]
+    pub fn new(model: M) -> Self {
+        Self {
+            model,
+            messages: Vec::new(),
+            parameters: Parameters::default(),
+            tools: Tools::new(),
+        }
+    }
+
+    /// Adds a pinned system prompt to the conversation.
+    #[must_use]
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.messages.push(Message::system(prompt).pinned(true));
+        self
+    }
+
+    /// Sets the generation parameters of every reply.
+    #[must_use]
+    pub fn with_parameters(mut self, parameters: Parameters) -> Self {
+        self.parameters = parameters;
+        self
+    }
+
+    /// Sets the tools the model may call in every reply.
+    #[must_use]
+    pub fn with_tools(mut self, tools: Tools) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Appends a message to the history.
+    pub fn push(&mut self, message: Message) {
+        self.messages.push(message);
+    }
+
+    /// Sends a user message and streams the model's reply.
+    ///
+    /// The user message is appended right away. The reply is appended as an assistant
+    /// message once the stream completes; if the stream fails or is dropped early, the
+    /// history ends with the unanswered user message, so the turn can be retried with
+    /// [`regenerate`](Self::regenerate) or discarded with [`undo`](Self::undo).
+    pub fn send(&mut self, message: impl Into<String>) -> impl TextStream<Error = M::Error> + '_ {
+        self.messages.push(Message::user(message));
+        self.regenerate()
+    }
+
+    /// Streams a new reply to the history as it stands, appending it once it completes.
+    ///
+    /// A trailing assistant reply is discarded first, so this retries the last turn.
+    pub fn regenerate(&mut self) -> impl TextStream<Error = M::Error> + '_ {
+        if self
+            .messages
+            .last()
+            .is_some_and(|message| message.role() == Role::Assistant)
+        {
+            self.messages.pop();
+        }
+        let Self {
+            model,
+            messages,
+            parameters,
+            tools,
+        } = self;
+        let request = Request::new(messages.clone())
+            .with_parameters(parameters.clone())
+            .with_tools(tools.clone());
+        text_stream(stream! {
+            let reply = model.respond(request);
+            pin!(reply);
+            let mut text = String::new();
+            while let Some(chunk) = reply.next().await {
+                match chunk {
+                    Ok(chunk) => {
+                        text.push_str(&chunk);
+                        yield Ok(chunk);
+                    }
+                    Err(error) => {
+                        yield Err(error);
+                        return;
+                    }
+                }
+            }
+            messages.push(Message::assistant(text));
+        })
+    }
+
+    /// Shortens the history to its first `len` messages.
+    ///
+    /// Has no effect if the history is already shorter.
+    pub fn truncate(&mut self, len: usize) {
+        self.messages.truncate(len);
+    }
+
+    /// Removes the last turn, from the last user message on, and returns its messages.
+    ///
+    /// Returns an empty list if the history has no user message.
+    pub fn undo(&mut self) -> Vec<Message> {
+        self.messages
+            .iter()
+            .rposition(|message| message.role() == Role::User)
+            .map(|start| self.messages.split_off(start))
+            .unwrap_or_default()
+    }
+
+    /// Replaces personal data in the history with placeholders, and returns the mapping
+    /// needed to restore it.
+    ///
+    /// See the [`anonymize`](crate::llm::anonymize) module for what is replaced.
+    pub fn anonymize(&mut self, anonymizer: &Anonymizer<impl PiiDetector>) -> PiiMap {
+        anonymizer.anonymize_messages(&mut self.messages)
+    }
+
+    /// Returns an independent copy of the conversation.
+    ///
+    /// Messages sent to the fork do not affect the original, so alternative
+    /// continuations can be explored from the same history.
+    #[must_use]
+    pub fn fork(&self) -> Self
+    where
+        M: Clone,
+    {
+        Self {
+            model: self.model.clone(),
+            messages: self.messages.clone(),
+            parameters: self.parameters.clone(),
+            tools: self.tools.clone(),
+        }
+    }
+
+    /// Returns an independent copy of the first `len` messages of the conversation.
+    ///
+    /// Forking at an earlier message branches off from that point in the history.
+    #[must_use]
+    pub fn fork_at(&self, len: usize) -> Self
+    where
+        M: Clone,
+    {
+        let mut fork = self.fork();
+        fork.truncate(len);
+        fork
+    }
+
+    /// Merges `theirs`, a branch forked from the history `base`, into this conversation,
+    /// and returns the conflicts.
+    ///
+    /// Each conflict is left in the history as an assistant message with conflict markers,
+    /// at [`MergeConflict::index`]. See [`merge_branches`] for how histories are merged.
+    pub fn merge(&mut self, base: &[Message], theirs: &[Message]) -> Vec<MergeConflict> {
+        let merge = merge_branches(base, &self.messages, theirs);
+        self.messages = merge.messages;
+        merge.conflicts
+    }
+
+    /// Returns the message history, oldest message first.
+    #[must_use]
+    pub const fn messages(&self) -> &[Message] {
+        self.messages.as_slice()
+    }
+
+    /// Returns the generation parameters of every reply.
+    #[must_use]
+    pub const fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+
+    /// Returns the model of the conversation.
+    #[must_use]
+    pub const fn model(&self) -> &M {
+        &self.model
+    }
+
+    /// Counts the tokens of the history with `counter`.
+    ///
+    /// The report is measured against the context length of the model's profile.
+    pub fn token_report(&self, counter: &impl TokenCounter) -> TokenReport {
+        TokenReport {
+            messages: self
+                .messages
+                .iter()
+                .map(|message| (message.role(), counter.count_tokens(&message.content())))
+                .collect(),
+            context_length: self.model.profile().context_length as usize,
+        }
+    }
+}
+
+/// The result of a three-way merge of conversation histories, created by
+/// [`merge_branches`].
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct ConversationMerge {
+    /// The merged history, with a conflict marker message in place of each conflict.
+    pub messages: Vec<Message>,
+    /// The conflicts, in the order of the history.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl ConversationMerge {
+    /// Returns whether the histories merged without conflicts.
+    #[must_use]
+    pub const fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+
+    /// Returns the merged history, with each conflict marker replaced by the messages
+    /// `resolve` returns for its conflict.
+    pub fn resolve(self, mut resolve: impl FnMut(&MergeConflict) -> Vec<Message>) -> Vec<Message> {
+        let mut conflicts = self.conflicts.iter().peekable();
+        let mut messages = Vec::with_capacity(self.messages.len());
+        for (index, message) in self.messages.into_iter().enumerate() {
+            match conflicts.next_if(|conflict| conflict.index == index) {
+                Some(conflict) => messages.extend(resolve(conflict)),
+                None => messages.push(message),
+            }
+        }
+        messages
+    }
+}
+
+/// Messages both branches changed differently since they were forked.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct MergeConflict {
+    /// The index of the conflict marker message in the merged history.
+    pub index: usize,
+    /// The messages of the common history the branches replaced.
+    pub base: Vec<Message>,
+    /// The messages of our branch.
+    pub ours: Vec<Message>,
+    /// The messages of their branch.
+    pub theirs: Vec<Message>,
+}
+
+impl MergeConflict {
+    /// Returns the text of the conflict marker message.
+    ///
+    /// A side with a single assistant message shows its text. Other sides show each
+    /// message on its own line, prefixed by its role.
+    #[must_use]
+    pub fn markers(&self) -> String {
+        let side = |messages: &[Message]| match messages {
+            [message] if message.role() == Role::Assistant => message.content().into_owned(),
+            messages => messages
+                .iter()
+                .map(|message| {
+                    format!("{}: {}", diff::role_name(message.role()), message.content())
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        format!(
+            "<<<<<<< ours\n{}\n=======\n{}\n>>>>>>> theirs",
+            side(&self.ours),
+            side(&self.theirs)
+        )
+    }
+}
+
+/// Merges two branches of a conversation, `ours` and `theirs`, forked from the history
+/// `base`.
+///
+/// Both branches are aligned with `base` by their longest common subsequence. A part of
+/// the history changed on one branch only takes that branch's messages, and a part
+/// changed the same way on both takes them once. Where the branches changed the same part
+/// differently, the messages they share at its start and end are kept, and the messages
+/// in between become a [`MergeConflict`], marked in the history by an assistant message
+/// with its [`markers`](MergeConflict::markers).
+#[must_use]
+pub fn merge_branches(base: &[Message], ours: &[Message], theirs: &[Message]) -> ConversationMerge {
+    let in_ours = kept(base, ours);
+    let in_theirs = kept(base, theirs);
+    let mut merge = ConversationMerge::default();
+    // The start of the unmerged part of each history.
+    let mut start = (0, 0, 0);
+    loop {
+        // The next message of the base that both branches kept.
+        let next = (start.0..base.len())
+            .find_map(|index| Some((index, in_ours[index]?, in_theirs[index]?)));
+        let end = next.unwrap_or((base.len(), ours.len(), theirs.len()));
+        merge.push(
+            &base[start.0..end.0],
+            &ours[start.1..end.1],
+            &theirs[start.2..end.2],
+        );
+        if next.is_none() {
+            return merge;
+        }
+        merge.messages.push(ours[end.1].clone());
+        start = (end.0 + 1, end.1 + 1, end.2 + 1);
+    }
+}
+
+impl ConversationMerge {
+    /// Merges a part of the history that at least one branch changed.
+    fn push(&mut self, base: &[Message], ours: &[Message], theirs: &[Message]) {
+        let equal = |a: &[Message], b: &[Message]| {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| diff::same(a, b))
+        };
+        if equal(ours, base) {
+            self.messages.extend_from_slice(theirs);
+            return;
+        }
+        if equal(theirs, base) || equal(ours, theirs) {
+            self.messages.extend_from_slice(ours);
+            return;
+        }
+
+        let prefix = ours
+            .iter()
+            .zip(theirs)
+            .take_while(|(a, b)| diff::same(a, b))
+            .count();
+        let (ours_rest, theirs_rest) = (&ours[prefix..], &theirs[prefix..]);
+        let suffix = ours_rest
+            .iter()
+            .rev()
+            .zip(theirs_rest.iter().rev())
+            .take_while(|(a, b)| diff::same(a, b))
+            .count();
+        self.messages.extend_from_slice(&ours[..prefix]);
+        let conflict = MergeConflict {
+            index: self.messages.len(),
+            base: base.to_vec(),
+            ours: ours_rest[..ours_rest.len() - suffix].to_vec(),
+            theirs: theirs_rest[..theirs_rest.len() - suffix].to_vec(),
+        };
+        self.messages.push(Message::assistant(conflict.markers()));
+        self.conflicts.push(conflict);
+        self.messages
+            .extend_from_slice(&ours_rest[ours_rest.len() - suffix..]);
+    }
+}
+
+/// Returns the index in `branch` of each message of `base` the branch kept.
+fn kept(base: &[Message], branch: &[Message]) -> Vec<Option<usize>> {
+    let mut kept = vec![None; base.len()];
+    let (mut i, mut j) = (0, 0);
+    for step in diff::align(base, branch) {
+        match step {
+            Step::Keep => {
+                kept[i] = Some(j);
+                i += 1;
+                j += 1;
+            }
+            Step::Remove(_) => i += 1,
+            Step::Add(_) => j += 1,
+        }
+    }
+    kept
+}
+
+/// A reusable assistant definition, from which fresh [`Conversation`]s are created.
+///
+/// A template bundles what defines an assistant: its system prompt, generation
+/// parameters, the tools it relies on and its guardrails. With the `serde` feature,
+/// templates can be stored as data, versioned and shared between teams.
+///
+/// Tools and moderation services are code, so a template only names them. They are
+/// supplied when the template is [instantiated](Self::instantiate), which fails if a
+/// required tool is missing.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::llm::{
+///     LanguageModel,
+///     conversation::ConversationTemplate,
+///     model::Parameters,
+///     tool::Tools,
+/// };
+///
+/// async fn support(model: impl LanguageModel, tools: &Tools) -> ai_types::Result<()> {
+///     let template = ConversationTemplate::new("support-agent")
+///         .with_version("2.1.0")
+///         .with_system_prompt("You are a friendly support agent for Acme.")
+///         .with_parameters(Parameters::default().temperature(0.3))
+///         .with_required_tool("lookup_order");
+///
+///     let mut conversation = template.instantiate(model, tools)?;
+///     let answer = conversation.send("Where is my order?").await?;
+///     println!("{answer}");
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[non_exhaustive]
+pub struct ConversationTemplate {
+    /// The name of the assistant.
+    pub name: String,
+    /// The version of the definition, in any format the application chooses.
+    pub version: Option<String>,
+    /// The system prompt every conversation starts with, pinned.
+    pub system_prompt: Option<String>,
+    /// The generation parameters of every reply.
+    pub parameters: Parameters,
+    /// The names of the tools the assistant needs.
+    pub required_tools: Vec<String>,
+    /// The guardrails applied to replies.
+    pub guardrails: Guardrails,
+}
+
+/// The guardrails of a [`ConversationTemplate`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[non_exhaustive]
+pub struct Guardrails {
+    /// Whether replies must be moderated before reaching the user.
+    pub moderate: bool,
+    /// Moderate replies in windows of this many sentences, as by
+    /// [`Guarded::with_sentence_window`], instead of as a whole.
+    pub sentence_window: Option<usize>,
+}
+
+impl ConversationTemplate {
+    /// Creates an empty template named `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Sets the version of the definition.
+    #[must_use]
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Sets the system prompt.
+    #[must_use]
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Sets the generation parameters.
+    #[must_use]
+    pub fn with_parameters(mut self, parameters: Parameters) -> Self {
+        self.parameters = parameters;
+        self
+    }
+
+    /// Adds a tool the assistant needs.
+    #[must_use]
+    pub fn with_required_tool(mut self, name: impl Into<String>) -> Self {
+        self.required_tools.push(name.into());
+        self
+    }
+
+    /// Requires replies to be moderated.
+    #[must_use]
+    pub const fn with_moderation(mut self, sentence_window: Option<usize>) -> Self {
+        self.guardrails = Guardrails {
+            moderate: true,
+            sentence_window,
+        };
+        self
+    }
+
+    /// Creates a conversation with `model`, which may call the tools of `tools`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a required tool is not in `tools`, or if the template requires
+    /// moderation, which needs [`instantiate_guarded`](Self::instantiate_guarded).
+    pub fn instantiate<M: LanguageModel>(
+        &self,
+        model: M,
+        tools: &Tools,
+    ) -> crate::Result<Conversation<M>> {
+        if self.guardrails.moderate {
+            bail!(
+                "Template `{}` requires moderation; instantiate it with a moderation service",
+                self.name
+            );
+        }
+        self.conversation(model, tools)
+    }
+
+    /// Creates a conversation with `model`, whose replies are moderated by `moderation`
+    /// according to the template's guardrails.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a required tool is not in `tools`.
+    pub fn instantiate_guarded<M, G>(
+        &self,
+        model: M,
+        moderation: G,
+        tools: &Tools,
+    ) -> crate::Result<Conversation<Guarded<M, G>>>
+    where
+        M: LanguageModel,
+        G: Moderation + Send + Sync + 'static,
+    {
+        let mut model = Guarded::new(model, moderation);
+        if let Some(sentences) = self.guardrails.sentence_window.filter(|&n| n > 0) {
+            model = model.with_sentence_window(sentences);
+        }
+        self.conversation(model, tools)
+    }
+
+    fn conversation<M: LanguageModel>(
+        &self,
+        model: M,
+        tools: &Tools,
+    ) -> crate::Result<Conversation<M>> {
+        if let Some(missing) = self.required_tools.iter().find(|t| !tools.contains(t)) {
+            bail!(
+                "Template `{}` requires the tool `{missing}`, which is not registered",
+                self.name
+            );
+        }
+        let mut conversation = Conversation::new(model)
+            .with_parameters(self.parameters.clone())
+            .with_tools(tools.clone());
+        if let Some(prompt) = &self.system_prompt {
+            conversation = conversation.with_system_prompt(prompt.clone());
+        }
+        Ok(conversation)
+    }
+}
+
+/// Token accounting of a [`Conversation`], created by [`Conversation::token_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenReport {
+    messages: Vec<(Role, usize)>,
+    context_length: usize,
+}
+
+impl TokenReport {
+    /// Returns the tokens of each message, oldest message first.
+    pub fn message_tokens(&self) -> impl Iterator<Item = usize> + '_ {
+        self.messages.iter().map(|(_, tokens)| *tokens)
+    }
+
+    /// Returns the tokens of all messages with `role`.
+    #[must_use]
+    pub fn role_tokens(&self, role: Role) -> usize {
+        self.messages
+            .iter()
+            .filter(|(message_role, _)| *message_role == role)
+            .map(|(_, tokens)| tokens)
+            .sum()
+    }
+
+    /// Returns the tokens of each turn, oldest turn first.
+    ///
+    /// A turn starts with a user message and includes every following message up to the
+    /// next user message. Messages before the first user message, such as the system
+    /// prompt, form a turn of their own.
+    #[must_use]
+    pub fn turn_tokens(&self) -> Vec<usize> {
+        let mut turns: Vec<usize> = Vec::new();
+        for (role, tokens) in &self.messages {
+            match turns.last_mut() {
+                Some(turn) if *role != Role::User => *turn += tokens,
+                _ => turns.push(*tokens),
+            }
+        }
+        turns
+    }
+
+    /// Returns the running total of tokens after each message, oldest message first.
+    ///
+    /// Shows how the context filled up over the course of the conversation.
+    #[must_use]
+    pub fn cumulative_tokens(&self) -> Vec<usize> {
+        self.message_tokens()
+            .scan(0, |total, tokens| {
+                *total += tokens;
+                Some(*total)
+            })
+            .collect()
+    }
+
+    /// Returns the tokens of the whole history.
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.message_tokens().sum()
+    }
+
+    /// Returns the context length of the model.
+    #[must_use]
+    pub const fn context_length(&self) -> usize {
+        self.context_length
+    }
+
+    /// Returns the tokens left in the context window. Zero when the history overflows.
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.context_length.saturating_sub(self.total())
+    }
+
+    /// Returns the fraction of the context window used by the history.
+    ///
+    /// Exceeds `1.0` when the history overflows the context window.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn usage_ratio(&self) -> f64 {
+        if self.context_length == 0 {
+            return 0.0;
+        }
+        self.total() as f64 / self.context_length as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        llm::{
+            model::Profile,
+            tool::{DynTool, ToolDefinition},
+        },
+        moderation::ModerationResult,
+        test_util::{MockLanguageModel, MockReply},
+    };
+    use alloc::{string::ToString, sync::Arc, vec};
+    use core::convert::Infallible;
+
+    type Model = Arc<MockLanguageModel>;
+
+    /// Answers with "Reply 1", "Reply 2" and "Reply 3", each in two chunks.
+    fn model(context_length: u32) -> Model {
+        let replies = (1..=3).map(|n| MockReply::chunks(["Reply ".to_string(), n.to_string()]));
+        let model = MockLanguageModel::new()
+            .with_profile(Profile::new("model", "A test model", context_length))
+            .with_replies(replies);
+        Arc::new(model)
+    }
+
+    fn words(text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+
+    fn conversation() -> Conversation<Model> {
+        let mut conversation = Conversation::new(model(100)).with_system_prompt("Be brief");
+        conversation.push(Message::user("What is Rust?"));
+        conversation.push(Message::assistant("A systems programming language."));
+        conversation.push(Message::user("Who made it?"));
+        conversation.push(Message::assistant("Mozilla."));
+        conversation
+    }
+
+    #[test]
+    fn counts_per_message_and_role() {
+        let report = conversation().token_report(&words);
+
+        assert_eq!(report.message_tokens().collect::<Vec<_>>(), [2, 3, 4, 3, 1]);
+        assert_eq!(report.role_tokens(Role::System), 2);
+        assert_eq!(report.role_tokens(Role::User), 6);
+        assert_eq!(report.role_tokens(Role::Assistant), 5);
+        assert_eq!(report.role_tokens(Role::Tool), 0);
+        assert_eq!(report.total(), 13);
+    }
+
+    #[test]
+    fn counts_per_turn_and_over_time() {
+        let report = conversation().token_report(&words);
+
+        assert_eq!(report.turn_tokens(), vec![2, 7, 4]);
+        assert_eq!(report.cumulative_tokens(), vec![2, 5, 9, 12, 13]);
+    }
+
+    #[test]
+    fn measures_context_usage() {
+        let mut conversation = Conversation::new(model(20));
+        conversation.push(Message::user("one two three four five"));
+
+        let report = conversation.token_report(&words);
+        assert_eq!(report.turn_tokens(), vec![5]);
+        assert_eq!(report.context_length(), 20);
+        assert_eq!(report.remaining(), 15);
+        assert!((report.usage_ratio() - 0.25).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn send_appends_both_sides_of_the_turn() {
+        let mut conversation = Conversation::new(model(100)).with_system_prompt("Be brief");
+
+        let mut chunks = Vec::new();
+        {
+            let reply = conversation.send("Hi");
+            pin!(reply);
+            while let Some(chunk) = reply.next().await {
+                chunks.push(chunk.unwrap());
+            }
+        }
+        assert_eq!(chunks, ["Reply ", "1"]);
+
+        assert_eq!(conversation.send("Again").await.unwrap(), "Reply 2");
+        let roles: Vec<_> = conversation.messages().iter().map(Message::role).collect();
+        assert_eq!(
+            roles,
+            [
+                Role::System,
+                Role::User,
+                Role::Assistant,
+                Role::User,
+                Role::Assistant
+            ]
+        );
+        assert_eq!(conversation.messages()[4].content(), "Reply 2");
+
+        // Regenerating replaces the last reply instead of adding one.
+        assert_eq!(conversation.regenerate().await.unwrap(), "Reply 3");
+        assert_eq!(conversation.messages().len(), 5);
+        assert_eq!(conversation.messages()[4].content(), "Reply 3");
+
+        let sent: Vec<_> = conversation
+            .model()
+            .requests()
+            .iter()
+            .map(|request| request.messages.len())
+            .collect();
+        assert_eq!(sent, [2, 4, 4]);
+    }
+
+    #[tokio::test]
+    async fn dropped_reply_is_not_appended() {
+        let mut conversation = Conversation::new(model(100));
+        drop(conversation.send("Hi"));
+        assert_eq!(conversation.messages().len(), 1);
+
+        assert_eq!(conversation.undo().len(), 1);
+        assert!(conversation.messages().is_empty());
+        assert!(conversation.undo().is_empty());
+    }
+
+    #[tokio::test]
+    async fn forks_are_independent() {
+        let mut conversation = Conversation::new(model(100)).with_system_prompt("Be brief");
+        conversation.send("One").await.unwrap();
+
+        let mut fork = conversation.fork();
+        fork.send("Two").await.unwrap();
+        assert_eq!(fork.messages().len(), 5);
+        assert_eq!(conversation.messages().len(), 3);
+
+        let branch = fork.fork_at(1);
+        assert_eq!(branch.messages().len(), 1);
+
+        fork.truncate(3);
+        assert_eq!(fork.messages()[2].content(), "Reply 1");
+        assert_eq!(fork.messages().len(), conversation.messages().len());
+    }
+
+    #[test]
+    fn merges_changes_from_both_branches() {
+        let base = [
+            Message::system("Be brief"),
+            Message::user("Hi"),
+            Message::assistant("Hello"),
+        ];
+        let ours = [
+            Message::system("Be detailed"),
+            Message::user("Hi"),
+            Message::assistant("Hello"),
+        ];
+        let theirs = [
+            Message::system("Be brief"),
+            Message::user("Hi"),
+            Message::assistant("Hello"),
+            Message::user("Bye"),
+            Message::assistant("Goodbye"),
+        ];
+
+        let merge = merge_branches(&base, &ours, &theirs);
+        assert!(merge.is_clean());
+        let contents: Vec<_> = merge.messages.iter().map(Message::content).collect();
+        assert_eq!(contents, ["Be detailed", "Hi", "Hello", "Bye", "Goodbye"]);
+    }
+
+    #[test]
+    fn marks_diverging_replies_as_conflicts() {
+        let mut conversation = conversation();
+        let base = conversation.messages().to_vec();
+        let mut theirs = conversation.fork();
+        conversation.push(Message::user("Again"));
+        conversation.push(Message::assistant("Mine"));
+        theirs.push(Message::user("Again"));
+        theirs.push(Message::assistant("Yours"));
+
+        let resolved = merge_branches(&base, conversation.messages(), theirs.messages())
+            .resolve(|conflict| conflict.theirs.clone());
+        assert_eq!(resolved.len(), base.len() + 2);
+        assert_eq!(resolved[base.len() + 1].content(), "Yours");
+
+        let conflicts = conversation.merge(&base, theirs.messages());
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].index, base.len() + 1);
+        assert_eq!(conversation.messages()[base.len()].content(), "Again");
+        assert_eq!(
+            conversation.messages()[base.len() + 1].content(),
+            "<<<<<<< ours\nMine\n=======\nYours\n>>>>>>> theirs"
+        );
+    }
+
+    struct Lookup;
+
+    impl DynTool for Lookup {
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition::from_parts("lookup", "Looks up", schemars::json_schema!(true))
+        }
+
+        async fn call(&mut self, arguments: String) -> crate::Result {
+            Ok(arguments)
+        }
+    }
+
+    struct Allow;
+
+    impl Moderation for Allow {
+        type Error = Infallible;
+
+        async fn moderate(&self, _content: &str) -> Result<ModerationResult, Self::Error> {
+            Ok(ModerationResult::new(false, alloc::vec::Vec::new()))
+        }
+    }
+
+    #[tokio::test]
+    async fn templates_instantiate_configured_conversations() {
+        let template = ConversationTemplate::new("support")
+            .with_version("1.0")
+            .with_system_prompt("Be kind")
+            .with_parameters(Parameters::default().temperature(0.2))
+            .with_required_tool("lookup");
+
+        let mut tools = Tools::new();
+        let error = template.instantiate(model(100), &tools).unwrap_err();
+        assert!(error.to_string().contains("`lookup`"));
+
+        tools.register_dyn(Lookup);
+        let mut first = template.instantiate(model(100), &tools).unwrap();
+        assert_eq!(first.parameters().temperature, Some(0.2));
+        assert_eq!(first.send("Hi").await.unwrap(), "Reply 1");
+        assert_eq!(first.model().requests()[0].messages.len(), 2);
+
+        let second = template.instantiate(model(100), &tools).unwrap();
+        assert_eq!(second.messages().len(), 1);
+        assert_eq!(second.messages()[0].content(), "Be kind");
+        assert!(second.messages()[0].is_pinned());
+    }
+
+    #[tokio::test]
+    async fn moderated_templates_require_a_moderation_service() {
+        let template = ConversationTemplate::new("kids").with_moderation(Some(2));
+        let tools = Tools::new();
+        assert!(template.instantiate(model(100), &tools).is_err());
+
+        let mut conversation = template
+            .instantiate_guarded(model(100), Allow, &tools)
+            .unwrap();
+        assert_eq!(conversation.send("Hi").await.unwrap(), "Reply 1");
+    }
+}
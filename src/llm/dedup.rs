@@ -0,0 +1,230 @@
+//! Removing repeated blocks from prompts.
+//!
+//! Prompts assembled from many sources, such as a system prompt built from fragments and
+//! few-shot examples collected from several templates, often repeat the same instruction
+//! or example word for word. Repeats cost tokens without telling the model anything new.
+//!
+//! A [`Deduplicator`] splits the text of each message into blocks separated by blank
+//! lines, and removes every block already seen earlier in the conversation, or replaces
+//! it with a short [reference](Deduplicator::with_reference). Blocks are compared with
+//! whitespace collapsed, and short blocks are left alone: a repeated "Yes." is part of
+//! the conversation, not boilerplate. Each removal is reported as a [`Duplicate`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{Message, dedup::dedup};
+//!
+//! let rules = "Answer in one word. Use lowercase. Never explain your reasoning to the user.";
+//! let mut messages = [
+//!     Message::system(format!("You classify sentiment.\n\n{rules}")),
+//!     Message::user(format!("{rules}\n\nText: I love it")),
+//! ];
+//!
+//! let duplicates = dedup(&mut messages);
+//! assert_eq!(messages[1].content(), "Text: I love it");
+//! assert_eq!(duplicates[0].original, 0);
+//! ```
+
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
+use crate::llm::{Message, message::Content};
+
+/// The default minimum length of deduplicated blocks, in bytes.
+const MIN_LENGTH: usize = 64;
+
+/// A repeated block removed by a [`Deduplicator`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Duplicate {
+    /// The index of the message the block was removed from.
+    pub message: usize,
+    /// The index of the message where the block first appeared.
+    pub original: usize,
+    /// The length of the removed block, in bytes.
+    pub length: usize,
+}
+
+/// Removes blocks of text repeated across the messages of a conversation.
+///
+/// See the [module documentation](crate::llm::dedup) for details.
+#[derive(Debug, Clone)]
+pub struct Deduplicator {
+    min_length: usize,
+    reference: Option<String>,
+}
+
+impl Default for Deduplicator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deduplicator {
+    /// Creates a deduplicator removing repeated blocks of at least 64 bytes.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            min_length: MIN_LENGTH,
+            reference: None,
+        }
+    }
+
+    /// Sets the minimum length of deduplicated blocks, in bytes, with whitespace
+    /// collapsed.
+    #[must_use]
+    pub const fn with_min_length(mut self, min_length: usize) -> Self {
+        self.min_length = min_length;
+        self
+    }
+
+    /// Replaces repeated blocks with `reference`, such as `"(Same as above.)"`, instead of
+    /// removing them.
+    #[must_use]
+    pub fn with_reference(mut self, reference: impl Into<String>) -> Self {
+        self.reference = Some(reference.into());
+        self
+    }
+
+    /// Deduplicates `messages` in place and returns the removed blocks, in order.
+    ///
+    /// [Pinned](Message::is_pinned) messages are left intact, though their blocks still
+    /// count as seen. A text part made only of repeated blocks keeps its first one, so no
+    /// message is emptied.
+    pub fn dedup(&self, messages: &mut [Message]) -> Vec<Duplicate> {
+        let mut seen = BTreeMap::new();
+        let mut duplicates = Vec::new();
+        for (index, message) in messages.iter_mut().enumerate() {
+            let pinned = message.is_pinned();
+            for part in message.parts_mut() {
+                let Content::Text(text) = part else {
+                    continue;
+                };
+                let found = duplicates.len();
+                let mut blocks = Vec::new();
+                let mut first = None;
+                for block in text.split("\n\n") {
+                    let key = collapse(block);
+                    if key.len() < self.min_length {
+                        blocks.push(block);
+                        continue;
+                    }
+                    match seen.get(&key) {
+                        Some(&original) if !pinned => {
+                            duplicates.push(Duplicate {
+                                message: index,
+                                original,
+                                length: block.len(),
+                            });
+                            first.get_or_insert(block);
+                            blocks.extend(self.reference.as_deref());
+                        }
+                        Some(_) => blocks.push(block),
+                        None => {
+                            seen.insert(key, index);
+                            blocks.push(block);
+                        }
+                    }
+                }
+                if let (true, Some(block)) = (blocks.is_empty(), first) {
+                    duplicates.remove(found);
+                    blocks.push(block);
+                }
+                if duplicates.len() > found {
+                    *text = blocks.join("\n\n");
+                }
+            }
+        }
+        duplicates
+    }
+}
+
+/// Deduplicates `messages` in place with the default [`Deduplicator`] and returns the
+/// removed blocks, in order.
+pub fn dedup(messages: &mut [Message]) -> Vec<Duplicate> {
+    Deduplicator::new().dedup(messages)
+}
+
+/// Collapses runs of whitespace into single spaces and trims the ends.
+fn collapse(block: &str) -> String {
+    let mut collapsed = String::with_capacity(block.len());
+    for word in block.split_whitespace() {
+        if !collapsed.is_empty() {
+            collapsed.push(' ');
+        }
+        collapsed.push_str(word);
+    }
+    collapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str =
+        "Input: The delivery was two days late.\nOutput: {\"sentiment\": \"negative\"}";
+
+    #[test]
+    fn removes_repeated_examples() {
+        let mut messages = [
+            Message::system(alloc::format!("Classify sentiment.\n\n{EXAMPLE}")),
+            Message::user(alloc::format!(
+                "Examples:\n\n{}\n\nYes.\n\nInput: Great service!",
+                EXAMPLE.replace('\n', "\n   ")
+            )),
+            Message::assistant("Yes."),
+            Message::user(alloc::format!("{EXAMPLE}\n\n{EXAMPLE}")),
+        ];
+        let duplicates = dedup(&mut messages);
+
+        assert_eq!(
+            messages[1].content(),
+            "Examples:\n\nYes.\n\nInput: Great service!"
+        );
+        assert_eq!(messages[2].content(), "Yes.");
+        assert_eq!(messages[3].content(), EXAMPLE);
+        assert_eq!(
+            duplicates
+                .iter()
+                .map(|d| (d.message, d.original))
+                .collect::<Vec<_>>(),
+            [(1, 0), (3, 0)]
+        );
+        assert_eq!(duplicates[1].length, EXAMPLE.len());
+    }
+
+    #[test]
+    fn keeps_pinned_messages_intact() {
+        let disclosure = alloc::format!("Hi\n\n{EXAMPLE}");
+        let mut messages = [
+            Message::system(EXAMPLE),
+            Message::user(disclosure.clone()).pinned(true),
+            Message::user(alloc::format!("Thanks\n\n{EXAMPLE}")),
+        ];
+        let duplicates = dedup(&mut messages);
+
+        assert_eq!(messages[1].content(), disclosure);
+        assert_eq!(messages[2].content(), "Thanks");
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].message, 2);
+    }
+
+    #[test]
+    fn references_repeats_when_configured() {
+        let mut messages = [
+            Message::system(EXAMPLE),
+            Message::user(alloc::format!("Hi\n\n{EXAMPLE}")),
+        ];
+        let duplicates = Deduplicator::new()
+            .with_min_length(10)
+            .with_reference("(Same example as above.)")
+            .dedup(&mut messages);
+
+        assert_eq!(messages[1].content(), "Hi\n\n(Same example as above.)");
+        assert_eq!(duplicates.len(), 1);
+
+        let mut unique = [Message::user(EXAMPLE)];
+        assert!(dedup(&mut unique).is_empty());
+    }
+}
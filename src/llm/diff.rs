@@ -0,0 +1,461 @@
+//! Diffing requests to see how middleware changed them.
+//!
+//! Middleware such as context injectors, compressors and routers rewrite requests before
+//! they reach the provider. [`Request::diff`] compares a request before and after such a
+//! step, and returns a [`RequestDiff`] listing every [`RequestChange`]: added, removed or
+//! edited messages, changed parameters, and added, removed or redefined tools.
+//!
+//! Messages are aligned by their longest common subsequence, so a message inserted in the
+//! middle of a history is reported once rather than shifting every later message. A
+//! removed message directly replaced by one with the same role is reported as an edit.
+//!
+//! The diff [displays](core::fmt::Display) as a changelog, one change per line.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{Message, Request, model::Parameters};
+//!
+//! let before = Request::oneshot("You are terse.", "What's the capital of France?");
+//! let after = Request::new([
+//!     Message::system("You are terse."),
+//!     Message::system("Today is Monday."),
+//!     Message::user("What's the capital of France?"),
+//! ])
+//! .with_parameters(Parameters::default().temperature(0.2));
+//!
+//! let diff = before.diff(&after);
+//! assert_eq!(
+//!     diff.to_string(),
+//!     "+ message 1 (system): \"Today is Monday.\"\n~ temperature: unset -> 0.2"
+//! );
+//! ```
+
+use alloc::{
+    borrow::Cow,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt::{self, Debug};
+
+use crate::llm::{Message, Request, Role};
+
+/// The number of characters of a message shown in a changelog line.
+const PREVIEW_CHARS: usize = 60;
+
+/// The changes between two [`Request`]s.
+///
+/// Returned by [`Request::diff`]. See the [module documentation](crate::llm::diff) for
+/// an example.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct RequestDiff {
+    /// The changes, messages first, in the order of the requests.
+    pub changes: Vec<RequestChange>,
+}
+
+impl RequestDiff {
+    /// Returns whether the requests are the same.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// A change between two [`Request`]s.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum RequestChange {
+    /// A message was added.
+    MessageAdded {
+        /// The index of the message in the new request.
+        index: usize,
+        /// The added message.
+        message: Message,
+    },
+    /// A message was removed.
+    MessageRemoved {
+        /// The index of the message in the old request.
+        index: usize,
+        /// The removed message.
+        message: Message,
+    },
+    /// A message was replaced by another with the same role.
+    MessageChanged {
+        /// The index of the message in the new request.
+        index: usize,
+        /// The message in the old request.
+        before: Message,
+        /// The message in the new request.
+        after: Message,
+    },
+    /// A parameter or setting of the request changed.
+    ParameterChanged {
+        /// The name of the parameter, such as `temperature`.
+        name: &'static str,
+        /// The old value, or `unset`.
+        before: String,
+        /// The new value, or `unset`.
+        after: String,
+    },
+    /// A tool was registered.
+    ToolAdded(String),
+    /// A tool was unregistered.
+    ToolRemoved(String),
+    /// A tool's description or argument schema changed.
+    ToolChanged(String),
+}
+
+impl Request {
+    /// Compares this request with `after`, such as the same request after middleware
+    /// rewrote it.
+    ///
+    /// See the [`diff`](crate::llm::diff) module for details.
+    #[must_use]
+    pub fn diff(&self, after: &Self) -> RequestDiff {
+        let mut changes = diff_messages(&self.messages, &after.messages);
+
+        macro_rules! compare {
+            ($($field:ident),* $(,)?) => {
+                $(
+                    if self.parameters.$field != after.parameters.$field {
+                        changes.push(RequestChange::ParameterChanged {
+                            name: stringify!($field),
+                            before: show(self.parameters.$field.as_ref()),
+                            after: show(after.parameters.$field.as_ref()),
+                        });
+                    }
+                )*
+            };
+        }
+        compare!(
+            temperature,
+            top_p,
+            top_k,
+            frequency_penalty,
+            presence_penalty,
+            repetition_penalty,
+            min_p,
+            top_a,
+            seed,
+            max_tokens,
+            n,
+            logit_bias,
+            logprobs,
+            top_logprobs,
+            stop,
+            tool_choice,
+        );
+        if self.response_format != after.response_format {
+            changes.push(RequestChange::ParameterChanged {
+                name: "response_format",
+                before: format!("{:?}", self.response_format),
+                after: format!("{:?}", after.response_format),
+            });
+        }
+        if self.parse_retries != after.parse_retries {
+            changes.push(RequestChange::ParameterChanged {
+                name: "parse_retries",
+                before: self.parse_retries.to_string(),
+                after: after.parse_retries.to_string(),
+            });
+        }
+        if self.idempotency_key != after.idempotency_key {
+            changes.push(RequestChange::ParameterChanged {
+                name: "idempotency_key",
+                before: show(self.idempotency_key.as_ref()),
+                after: show(after.idempotency_key.as_ref()),
+            });
+        }
+
+        let old = self.tools.definitions();
+        let new = after.tools.definitions();
+        for tool in &old {
+            match new.iter().find(|other| other.name == tool.name) {
+                None => changes.push(RequestChange::ToolRemoved(tool.name.to_string())),
+                Some(other)
+                    if other.description != tool.description
+                        || other.arguments != tool.arguments =>
+                {
+                    changes.push(RequestChange::ToolChanged(tool.name.to_string()));
+                }
+                Some(_) => {}
+            }
+        }
+        for tool in &new {
+            if !old.iter().any(|other| other.name == tool.name) {
+                changes.push(RequestChange::ToolAdded(tool.name.to_string()));
+            }
+        }
+
+        RequestDiff { changes }
+    }
+}
+
+/// A step aligning two message lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Step {
+    /// The next messages of both lists are the same.
+    Keep,
+    /// The message at this index of the first list is not in the second.
+    Remove(usize),
+    /// The message at this index of the second list is not in the first.
+    Add(usize),
+}
+
+/// Aligns two message lists by their longest common subsequence.
+pub(crate) fn align(before: &[Message], after: &[Message]) -> Vec<Step> {
+    let (n, m) = (before.len(), after.len());
+    // `common[i][j]` is the length of the longest common subsequence of the suffixes.
+    let mut common = vec![vec![0_usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            common[i][j] = if same(&before[i], &after[j]) {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut steps = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && same(&before[i], &after[j]) {
+            steps.push(Step::Keep);
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && common[i + 1][j] >= common[i][j + 1]) {
+            steps.push(Step::Remove(i));
+            i += 1;
+        } else {
+            steps.push(Step::Add(j));
+            j += 1;
+        }
+    }
+    steps
+}
+
+/// Lists the differences between two message lists.
+fn diff_messages(before: &[Message], after: &[Message]) -> Vec<RequestChange> {
+    let steps = align(before, after);
+    let mut changes = Vec::new();
+    for run in steps.split(|step| matches!(step, Step::Keep)) {
+        let removed: Vec<usize> = run
+            .iter()
+            .filter_map(|step| match step {
+                Step::Remove(index) => Some(*index),
+                _ => None,
+            })
+            .collect();
+        let added: Vec<usize> = run
+            .iter()
+            .filter_map(|step| match step {
+                Step::Add(index) => Some(*index),
+                _ => None,
+            })
+            .collect();
+        for k in 0..removed.len().max(added.len()) {
+            match (removed.get(k), added.get(k)) {
+                (Some(&old), Some(&new)) if before[old].role() == after[new].role() => {
+                    changes.push(RequestChange::MessageChanged {
+                        index: new,
+                        before: before[old].clone(),
+                        after: after[new].clone(),
+                    });
+                }
+                (old, new) => {
+                    if let Some(&index) = old {
+                        changes.push(RequestChange::MessageRemoved {
+                            index,
+                            message: before[index].clone(),
+                        });
+                    }
+                    if let Some(&index) = new {
+                        changes.push(RequestChange::MessageAdded {
+                            index,
+                            message: after[index].clone(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    changes
+}
+
+/// Returns whether two messages are identical.
+pub(crate) fn same(a: &Message, b: &Message) -> bool {
+    a.role() == b.role()
+        && a.parts() == b.parts()
+        && a.attachments() == b.attachments()
+        && a.annotations() == b.annotations()
+        && a.tool_call_id() == b.tool_call_id()
+        && a.is_pinned() == b.is_pinned()
+}
+
+/// Formats an optional value, or `unset`.
+fn show<T: Debug>(value: Option<&T>) -> String {
+    value.map_or_else(|| "unset".into(), |value| format!("{value:?}"))
+}
+
+/// Returns the lowercase name of a role.
+pub(crate) const fn role_name(role: Role) -> &'static str {
+    match role {
+        Role::User => "user",
+        Role::Assistant => "assistant",
+        Role::System => "system",
+        Role::Tool => "tool",
+    }
+}
+
+/// Returns the beginning of a message's text on one line, quoted.
+fn preview(message: &Message) -> String {
+    let content = message.content();
+    let text: Cow<'_, str> = if content.contains('\n') {
+        content.replace('\n', " ").into()
+    } else {
+        content
+    };
+    let mut preview: String = text.chars().take(PREVIEW_CHARS).collect();
+    if text.chars().nth(PREVIEW_CHARS).is_some() {
+        preview.push('…');
+    }
+    format!("{preview:?}")
+}
+
+impl fmt::Display for RequestChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MessageAdded { index, message } => write!(
+                f,
+                "+ message {index} ({}): {}",
+                role_name(message.role()),
+                preview(message)
+            ),
+            Self::MessageRemoved { index, message } => write!(
+                f,
+                "- message {index} ({}): {}",
+                role_name(message.role()),
+                preview(message)
+            ),
+            Self::MessageChanged {
+                index,
+                before,
+                after,
+            } => write!(
+                f,
+                "~ message {index} ({}): {} -> {}",
+                role_name(after.role()),
+                preview(before),
+                preview(after)
+            ),
+            Self::ParameterChanged {
+                name,
+                before,
+                after,
+            } => write!(f, "~ {name}: {before} -> {after}"),
+            Self::ToolAdded(name) => write!(f, "+ tool {name}"),
+            Self::ToolRemoved(name) => write!(f, "- tool {name}"),
+            Self::ToolChanged(name) => write!(f, "~ tool {name}"),
+        }
+    }
+}
+
+impl fmt::Display for RequestDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, change) in self.changes.iter().enumerate() {
+            if index > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{Tool, model::Parameters};
+    use alloc::vec::Vec;
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+
+    #[derive(JsonSchema, Deserialize)]
+    struct Query {
+        #[allow(dead_code)]
+        q: String,
+    }
+
+    struct Search;
+
+    impl Tool for Search {
+        const NAME: &str = "search";
+        const DESCRIPTION: &str = "Searches the web";
+        type Arguments = Query;
+
+        async fn call(&mut self, _arguments: Self::Arguments) -> crate::Result {
+            Ok(String::new())
+        }
+    }
+
+    #[test]
+    fn aligns_messages_and_pairs_edits() {
+        let before = Request::new([
+            Message::system("Be helpful."),
+            Message::user("first question"),
+            Message::assistant("first answer"),
+            Message::user("second question"),
+        ]);
+        let after = Request::new([
+            Message::system("Be helpful."),
+            Message::user("first question"),
+            Message::assistant("summary of the first answer"),
+            Message::tool("note"),
+            Message::user("second question"),
+        ]);
+
+        let lines: Vec<String> = before
+            .diff(&after)
+            .changes
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "~ message 2 (assistant): \"first answer\" -> \"summary of the first answer\"",
+                "+ message 3 (tool): \"note\"",
+            ]
+        );
+        assert!(before.diff(&before.clone()).is_empty());
+        assert_eq!(
+            after.diff(&before).changes.len(),
+            2,
+            "the reverse diff removes the note and restores the answer"
+        );
+    }
+
+    #[test]
+    fn reports_parameters_and_tools() {
+        let long = "word ".repeat(20);
+        let before = Request::new([Message::user(long.as_str())]).with_tool(Search);
+        let after = Request::new([Message::user(long.as_str())])
+            .with_parameters(Parameters::default().max_tokens(100).seed(7))
+            .with_parse_retries(2);
+
+        let diff = before.diff(&after);
+        assert_eq!(
+            diff.to_string(),
+            "~ seed: unset -> 7\n~ max_tokens: unset -> 100\n~ parse_retries: 0 -> 2\n- tool search"
+        );
+
+        let edited = Request::new([Message::user("short")]);
+        let line = before.diff(&edited).changes[0].to_string();
+        assert!(line.starts_with("~ message 0 (user): \"word word"));
+        assert!(line.contains("…\" -> \"short\""));
+    }
+}
@@ -0,0 +1,337 @@
+//! Comparing documents in a typed matrix with cited findings.
+//!
+//! [`LanguageModel::compare_documents`] compares several documents, such as contracts,
+//! vendor proposals or product sheets, in one structured response. Each document is
+//! described by a row of the caller's type `T`, and the similarities and differences
+//! between them are listed with [`Citation`]s pointing back at the documents.
+//!
+//! Documents too long to be compared side by side in the model's context window are
+//! split into chunks at paragraph boundaries, and each chunk is summarized first. The
+//! text the comparison was based on, the document itself or the summary of its chunks,
+//! is kept in [`DocumentComparison::digests`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::LanguageModel;
+//! use schemars::JsonSchema;
+//! use serde::Deserialize;
+//!
+//! #[derive(JsonSchema, Deserialize)]
+//! struct Offer {
+//!     vendor: String,
+//!     price: f64,
+//!     warranty_years: u32,
+//! }
+//!
+//! async fn compare(model: impl LanguageModel, offers: &[&str]) -> ai_types::Result<()> {
+//!     let comparison = model.compare_documents::<Offer>(offers).await?;
+//!     for offer in &comparison.documents {
+//!         println!("{}: ${} with {} years", offer.vendor, offer.price, offer.warranty_years);
+//!     }
+//!     for difference in &comparison.differences {
+//!         println!("{}", difference.statement);
+//!         for citation in &difference.citations {
+//!             println!("  [{}] {}", citation.document + 1, citation.quote);
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use anyhow::bail;
+use schemars::{JsonSchema, Schema, schema_for};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value, json};
+
+use crate::llm::{
+    LanguageModel, Request, generate_with_schema, prompts,
+    token::{Estimator, TokenCounter},
+    try_collect,
+};
+
+/// The smallest token budget of a document before it is summarized.
+const MIN_DOCUMENT_TOKENS: usize = 512;
+
+/// The comparison of several documents.
+///
+/// Returned by [`LanguageModel::compare_documents`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct DocumentComparison<T> {
+    /// One row per document, in the order the documents were given.
+    pub documents: Vec<T>,
+    /// What the documents have in common.
+    pub similarities: Vec<Finding>,
+    /// Where the documents differ.
+    pub differences: Vec<Finding>,
+    /// The text each document was compared by: the document itself, or the summary of
+    /// its chunks if it was too long.
+    pub digests: Vec<String>,
+}
+
+impl<T> DocumentComparison<T> {
+    /// Returns every citation of the document at `index`, in similarities and
+    /// differences.
+    pub fn citations(&self, index: usize) -> impl Iterator<Item = &Citation> {
+        self.similarities
+            .iter()
+            .chain(&self.differences)
+            .flat_map(|finding| &finding.citations)
+            .filter(move |citation| citation.document == index)
+    }
+}
+
+/// A similarity or difference between documents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Finding {
+    /// The similarity or difference, in one sentence.
+    pub statement: String,
+    /// The passages of the documents supporting it.
+    pub citations: Vec<Citation>,
+}
+
+/// A passage of a document supporting a [`Finding`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Citation {
+    /// The zero-based index of the cited document.
+    pub document: usize,
+    /// The cited passage, quoted from the document's digest.
+    pub quote: String,
+}
+
+pub(crate) async fn compare_documents<T, M>(
+    model: &M,
+    documents: &[&str],
+) -> crate::Result<DocumentComparison<T>>
+where
+    T: JsonSchema + DeserializeOwned,
+    M: LanguageModel,
+{
+    let context = usize::try_from(model.profile().context_length).unwrap_or(usize::MAX);
+    let budget = (context / (documents.len() + 1)).max(MIN_DOCUMENT_TOKENS);
+
+    let mut digests = Vec::with_capacity(documents.len());
+    for document in documents {
+        let chunks = chunk(document, budget);
+        if let [only] = chunks.as_slice() {
+            digests.push((*only).to_string());
+            continue;
+        }
+        let mut summaries = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            summaries.push(try_collect(model.summarize(chunk)).await?);
+        }
+        digests.push(summaries.join("\n\n"));
+    }
+
+    let text: Vec<String> = digests
+        .iter()
+        .enumerate()
+        .map(|(index, digest)| format!("Document {}:\n{digest}", index + 1))
+        .collect();
+    let request = Request::oneshot(
+        prompts::compare_documents(documents.len()),
+        text.join("\n\n"),
+    );
+    let response: Value = generate_with_schema(model, request, schema::<T>()).await?;
+    parse(response, digests)
+}
+
+/// Splits `text` at paragraph boundaries into chunks of at most `budget` tokens.
+///
+/// A paragraph longer than the budget becomes a chunk of its own.
+fn chunk(text: &str, budget: usize) -> Vec<&str> {
+    let counter = Estimator::new();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut tokens = 0;
+    let mut end = 0;
+    for (index, _) in text.match_indices("\n\n").chain([(text.len(), "")]) {
+        let paragraph = counter.count_tokens(&text[end..index]);
+        if tokens > 0 && tokens + paragraph > budget {
+            chunks.push(text[start..end].trim());
+            start = end;
+            tokens = 0;
+        }
+        tokens += paragraph;
+        end = index;
+    }
+    chunks.push(text[start..].trim());
+    chunks
+}
+
+/// Builds the schema of the comparison, with `T` as the type of the rows.
+fn schema<T: JsonSchema>() -> Schema {
+    let mut row = schema_for!(T).as_value().clone();
+    let mut root = Map::new();
+    if let Some(object) = row.as_object_mut() {
+        object.remove("$schema");
+        // Definitions must stay at the root for `$ref`s to resolve.
+        if let Some(defs) = object.remove("$defs") {
+            root.insert("$defs".into(), defs);
+        }
+    }
+    let findings = json!({
+        "type": "array",
+        "items": {
+            "type": "object",
+            "properties": {
+                "statement": { "type": "string" },
+                "citations": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "document": { "type": "integer", "minimum": 1 },
+                            "quote": { "type": "string" },
+                        },
+                        "required": ["document", "quote"],
+                    },
+                },
+            },
+            "required": ["statement", "citations"],
+        },
+    });
+    root.insert("type".into(), "object".into());
+    root.insert(
+        "properties".into(),
+        json!({
+            "documents": { "type": "array", "items": row },
+            "similarities": findings,
+            "differences": findings,
+        }),
+    );
+    root.insert(
+        "required".into(),
+        json!(["documents", "similarities", "differences"]),
+    );
+    Schema::from(root)
+}
+
+/// Parses the model's comparison of the documents summarized by `digests`.
+fn parse<T: DeserializeOwned>(
+    mut response: Value,
+    digests: Vec<String>,
+) -> crate::Result<DocumentComparison<T>> {
+    let documents: Vec<T> = serde_json::from_value(response["documents"].take())?;
+    if documents.len() != digests.len() {
+        bail!(
+            "Comparison describes {} documents instead of {}",
+            documents.len(),
+            digests.len()
+        );
+    }
+    let findings = |key: &str| -> Vec<Finding> {
+        let findings = response[key].as_array().into_iter().flatten();
+        findings
+            .filter_map(|finding| {
+                let statement = finding["statement"].as_str()?.to_string();
+                let citations = finding["citations"].as_array().into_iter().flatten();
+                let citations = citations
+                    .filter_map(|citation| {
+                        // The prompt numbers documents from one.
+                        let document = usize::try_from(citation["document"].as_u64()?).ok()?;
+                        Some(Citation {
+                            document: document.checked_sub(1).filter(|&i| i < digests.len())?,
+                            quote: citation["quote"].as_str()?.to_string(),
+                        })
+                    })
+                    .collect();
+                Some(Finding {
+                    statement,
+                    citations,
+                })
+            })
+            .collect()
+    };
+    Ok(DocumentComparison {
+        documents,
+        similarities: findings("similarities"),
+        differences: findings("differences"),
+        digests,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{llm::model::Profile, test_util::MockLanguageModel};
+    use alloc::vec::Vec;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, JsonSchema, Deserialize)]
+    struct Offer {
+        price: u32,
+    }
+
+    #[tokio::test]
+    async fn compares_documents_with_citations() {
+        // The long document is summarized in two chunks before the comparison.
+        let model = MockLanguageModel::new()
+            .with_profile(Profile::new("scripted", "Scripted comparison", 1024))
+            .with_replies([
+                "Lengthy",
+                "Terms",
+                r#"{
+                "documents": [{"price": 100}, {"price": 120}],
+                "similarities": [{"statement": "Both ship free.", "citations": [
+                    {"document": 1, "quote": "free shipping"},
+                    {"document": 2, "quote": "ships free"},
+                    {"document": 3, "quote": "no such document"}
+                ]}],
+                "differences": [{"statement": "A is cheaper.", "citations": [
+                    {"document": 1, "quote": "$100"}
+                ]}]
+            }"#,
+            ]);
+        let long = "Lengthy ".repeat(400) + "\n\n" + &"Terms ".repeat(400);
+        let comparison = model
+            .compare_documents::<Offer>(&["A: $100, free shipping", &long])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            comparison.documents,
+            [Offer { price: 100 }, Offer { price: 120 }]
+        );
+        assert_eq!(
+            comparison.digests,
+            ["A: $100, free shipping", "Lengthy\n\nTerms"]
+        );
+        assert_eq!(comparison.similarities[0].citations.len(), 2);
+        let quotes: Vec<_> = comparison.citations(0).map(|c| c.quote.as_str()).collect();
+        assert_eq!(quotes, ["free shipping", "$100"]);
+
+        let requests = model.requests();
+        let prompt = requests[2].messages[1].content();
+        assert!(prompt.starts_with("Document 1:\nA: $100"));
+        assert!(prompt.ends_with("Document 2:\nLengthy\n\nTerms"));
+    }
+
+    #[tokio::test]
+    async fn rejects_a_row_count_mismatch() {
+        let model = MockLanguageModel::new()
+            .with_reply(r#"{"documents": [{"price": 1}], "similarities": [], "differences": []}"#);
+        let result = model.compare_documents::<Offer>(&["A", "B"]).await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn chunks_at_paragraph_boundaries() {
+        let text = "one two\n\nthree four\n\nfive";
+        assert_eq!(chunk(text, 1000), [text]);
+        assert_eq!(chunk(text, 1), ["one two", "three four", "five"]);
+    }
+}
@@ -0,0 +1,220 @@
+//! Injecting the current date, time, and application facts into prompts.
+//!
+//! Models do not know what day it is, and every deployment formats dates differently.
+//! A [`ContextEnricher`] renders the current date and time, the user's timezone, and
+//! facts declared by the application into one canonical block, and adds it to the
+//! system prompt of a request:
+//!
+//! ```text
+//! Context:
+//! - Current date: 2026-10-16 (Friday)
+//! - Current time: 14:30 (UTC+02:00, Europe/Paris)
+//! - Company: Acme Corp
+//! ```
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{Request, enrich::ContextEnricher};
+//! use std::time::{SystemTime, UNIX_EPOCH};
+//!
+//! let enricher = ContextEnricher::new(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap())
+//!     .with_timezone("Europe/Paris", 120)
+//!     .with_fact("Company", "Acme Corp");
+//!
+//! let request = enricher.enrich(Request::oneshot("You are a support agent.", "When do you open?"));
+//! assert!(request.messages[0].content().contains("- Company: Acme Corp"));
+//! ```
+
+use alloc::{format, string::String, vec::Vec};
+use core::fmt::Write;
+
+use crate::{
+    llm::{Message, Request, Role},
+    time::Clock,
+};
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+/// Adds the current date, time, and application facts to system prompts.
+///
+/// See the [module documentation](crate::llm::enrich) for the format and an example.
+#[derive(Debug, Clone)]
+pub struct ContextEnricher<C> {
+    clock: C,
+    utc_offset: i32,
+    timezone: Option<String>,
+    facts: Vec<(String, String)>,
+}
+
+impl<C: Clock> ContextEnricher<C> {
+    /// Creates an enricher reading the time from `clock`.
+    ///
+    /// Unlike most clocks in this crate, `clock` must return the time elapsed since the
+    /// Unix epoch, such as `SystemTime::now().duration_since(UNIX_EPOCH)`. Times are
+    /// rendered in UTC until a timezone is set.
+    pub const fn new(clock: C) -> Self {
+        Self {
+            clock,
+            utc_offset: 0,
+            timezone: None,
+            facts: Vec::new(),
+        }
+    }
+
+    /// Renders times with a fixed offset from UTC, in minutes.
+    #[must_use]
+    pub const fn with_utc_offset(mut self, minutes: i32) -> Self {
+        self.utc_offset = minutes;
+        self
+    }
+
+    /// Renders times in a named timezone, such as `Europe/Paris`, currently `utc_offset`
+    /// minutes ahead of UTC.
+    ///
+    /// The name is shown to the model; the offset is used to compute the local time.
+    #[must_use]
+    pub fn with_timezone(mut self, name: impl Into<String>, utc_offset: i32) -> Self {
+        self.timezone = Some(name.into());
+        self.utc_offset = utc_offset;
+        self
+    }
+
+    /// Adds a fact about the application, such as the company name or the user's plan.
+    ///
+    /// Facts are listed in the order they are added.
+    #[must_use]
+    pub fn with_fact(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.facts.push((name.into(), value.into()));
+        self
+    }
+
+    /// Renders the context block for the current time.
+    #[must_use]
+    pub fn context(&self) -> String {
+        let local = i64::try_from(self.clock.now().as_secs()).unwrap_or(i64::MAX)
+            + i64::from(self.utc_offset) * 60;
+        let days = local.div_euclid(86_400);
+        let seconds = local.rem_euclid(86_400);
+        let (year, month, day) = civil_from_days(days);
+        let weekday = WEEKDAYS[usize::try_from((days + 4).rem_euclid(7)).unwrap_or(0)];
+
+        let sign = if self.utc_offset < 0 { '-' } else { '+' };
+        let offset = self.utc_offset.unsigned_abs();
+        let mut zone = format!("UTC{sign}{:02}:{:02}", offset / 60, offset % 60);
+        if let Some(name) = &self.timezone {
+            let _ = write!(zone, ", {name}");
+        }
+
+        let mut context = String::from("Context:\n");
+        let _ = writeln!(
+            context,
+            "- Current date: {year:04}-{month:02}-{day:02} ({weekday})"
+        );
+        let _ = write!(
+            context,
+            "- Current time: {:02}:{:02} ({zone})",
+            seconds / 3600,
+            seconds % 3600 / 60
+        );
+        for (name, value) in &self.facts {
+            let _ = write!(context, "\n- {name}: {value}");
+        }
+        context
+    }
+
+    /// Adds the context block to the system prompt of `request`.
+    ///
+    /// The block is appended to the first message if it is a system message, and inserted
+    /// as a new system message at the start otherwise.
+    #[must_use]
+    pub fn enrich(&self, mut request: Request) -> Request {
+        let context = self.context();
+        match request.messages.first_mut() {
+            Some(first) if first.role() == Role::System => {
+                let prompt = core::mem::replace(first, Message::system(""));
+                *first = prompt.with_content(format!("\n\n{context}"));
+            }
+            _ => request.messages.insert(0, Message::system(context)),
+        }
+        request
+    }
+}
+
+/// Converts days since the Unix epoch into a proleptic Gregorian `(year, month, day)`.
+///
+/// Howard Hinnant's `civil_from_days` algorithm.
+const fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::time::Duration;
+
+    /// 2024-02-29 23:30:00 UTC, a Thursday.
+    fn leap_day() -> Duration {
+        Duration::from_secs(1_709_249_400)
+    }
+
+    #[test]
+    fn renders_utc_and_offsets() {
+        let enricher = ContextEnricher::new(leap_day);
+        assert_eq!(
+            enricher.context(),
+            "Context:\n- Current date: 2024-02-29 (Thursday)\n- Current time: 23:30 (UTC+00:00)"
+        );
+
+        let tokyo = ContextEnricher::new(leap_day).with_timezone("Asia/Tokyo", 540);
+        assert!(tokyo.context().contains("2024-03-01 (Friday)"));
+        assert!(tokyo.context().contains("08:30 (UTC+09:00, Asia/Tokyo)"));
+
+        let newfoundland = ContextEnricher::new(leap_day).with_utc_offset(-150);
+        assert!(newfoundland.context().contains("21:00 (UTC-02:30)"));
+    }
+
+    #[test]
+    fn enriches_system_prompt() {
+        let enricher = ContextEnricher::new(leap_day).with_fact("Plan", "Pro");
+
+        let request = enricher.enrich(Request::oneshot("Be helpful.", "Hi"));
+        assert_eq!(request.messages.len(), 2);
+        assert!(
+            request.messages[0]
+                .content()
+                .starts_with("Be helpful.\n\nContext:\n")
+        );
+        assert!(request.messages[0].content().ends_with("\n- Plan: Pro"));
+
+        let request = enricher.enrich(Request::new([Message::user("Hi")]));
+        assert_eq!(request.messages[0].role(), Role::System);
+        assert!(request.messages[0].content().starts_with("Context:\n"));
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(20_742), (2026, 10, 16));
+    }
+}
@@ -0,0 +1,417 @@
+//! Querying several models and keeping the best answer.
+//!
+//! Different models fail on different inputs, so asking several and choosing among their
+//! answers often beats any single one. An [`Ensemble`] sends a request to all of its
+//! models concurrently, then hands the answers to a [`Judge`] that either scores them,
+//! selects one, or merges them into a new answer.
+//!
+//! Any closure scoring an answer is a judge, and [`ModelJudge`] asks a language model to
+//! pick or merge. The [`EnsembleOutcome`] records every candidate with its score, so the
+//! choice can be inspected and logged.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{LanguageModel, Request, ensemble::{Ensemble, ModelJudge}};
+//!
+//! async fn best_answer<M: LanguageModel>(
+//!     models: Vec<M>,
+//!     judge: impl LanguageModel,
+//! ) -> ai_types::Result<String> {
+//!     let ensemble = Ensemble::new(models, ModelJudge::new(judge));
+//!     let outcome = ensemble
+//!         .run(Request::oneshot("Be concise", "Why is the sky blue?"))
+//!         .await?;
+//!     for candidate in &outcome.candidates {
+//!         println!("{}: {:?}", candidate.model, candidate.text);
+//!     }
+//!     Ok(outcome.answer)
+//! }
+//! ```
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt::Write, future::Future};
+
+use crate::{
+    join::join_all,
+    llm::{LanguageModel, Message, Request, context::role_name, prompts, try_collect},
+};
+
+/// A judge's decision about the candidate answers.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Verdict {
+    /// Scores each candidate, in order; the highest weighted score wins.
+    Scores(Vec<f32>),
+    /// Selects the candidate at the given index.
+    Select(usize),
+    /// Replaces the candidates with a merged answer.
+    Merge(String),
+}
+
+/// Decides which of several candidate answers to a request is best.
+///
+/// Any closure scoring a candidate answer to a request is a judge.
+pub trait Judge: Send + Sync {
+    /// Judges `candidates`, the successful answers to `request`.
+    fn judge(
+        &self,
+        request: &Request,
+        candidates: &[&str],
+    ) -> impl Future<Output = crate::Result<Verdict>> + Send;
+}
+
+impl<F: Fn(&Request, &str) -> f32 + Send + Sync> Judge for F {
+    async fn judge(&self, request: &Request, candidates: &[&str]) -> crate::Result<Verdict> {
+        Ok(Verdict::Scores(
+            candidates
+                .iter()
+                .map(|candidate| self(request, candidate))
+                .collect(),
+        ))
+    }
+}
+
+/// A [`Judge`] asking a language model to select or merge candidate answers.
+#[derive(Debug, Clone)]
+pub struct ModelJudge<M> {
+    model: M,
+    merge: bool,
+}
+
+impl<M: LanguageModel> ModelJudge<M> {
+    /// Creates a judge asking `model` to select the best candidate.
+    #[must_use]
+    pub const fn new(model: M) -> Self {
+        Self {
+            model,
+            merge: false,
+        }
+    }
+
+    /// Asks the model to merge the candidates into a new answer instead of selecting one.
+    #[must_use]
+    pub const fn merging(mut self) -> Self {
+        self.merge = true;
+        self
+    }
+
+    /// Returns the judging model.
+    #[must_use]
+    pub const fn model(&self) -> &M {
+        &self.model
+    }
+}
+
+impl<M: LanguageModel> Judge for ModelJudge<M> {
+    async fn judge(&self, request: &Request, candidates: &[&str]) -> crate::Result<Verdict> {
+        let mut transcript = String::new();
+        for message in &request.messages {
+            let _ = writeln!(
+                transcript,
+                "{}: {}",
+                role_name(message.role()),
+                message.content()
+            );
+        }
+        let mut numbered = String::new();
+        for (index, candidate) in candidates.iter().enumerate() {
+            let _ = writeln!(numbered, "[{}] {candidate}", index + 1);
+        }
+
+        if self.merge {
+            let prompt = prompts::merge(&transcript, &numbered);
+            let answer =
+                try_collect(self.model.respond(Request::new([Message::user(prompt)]))).await?;
+            return Ok(Verdict::Merge(answer.trim().to_string()));
+        }
+        let prompt = prompts::judge(&transcript, &numbered);
+        let answer = try_collect(self.model.respond(Request::new([Message::user(prompt)]))).await?;
+        let number: usize = answer
+            .trim_start_matches(|c: char| !c.is_ascii_digit())
+            .split(|c: char| !c.is_ascii_digit())
+            .next()
+            .and_then(|digits| digits.parse().ok())
+            .ok_or_else(|| anyhow::anyhow!("Judge did not answer with a number: `{answer}`"))?;
+        if !(1..=candidates.len()).contains(&number) {
+            anyhow::bail!("Judge selected answer {number} of {}", candidates.len());
+        }
+        Ok(Verdict::Select(number - 1))
+    }
+}
+
+/// One model's answer in an [`EnsembleOutcome`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Candidate {
+    /// The name of the model, from its profile.
+    pub model: String,
+    /// The model's answer, or `None` if it failed.
+    pub text: Option<String>,
+    /// The error the model failed with, if any.
+    pub error: Option<String>,
+    /// The weighted score given by the judge, if it scored the candidates.
+    pub score: Option<f32>,
+    /// Whether this candidate was chosen as the answer.
+    pub selected: bool,
+}
+
+/// The result of an [`Ensemble`] run.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct EnsembleOutcome {
+    /// The chosen or merged answer.
+    pub answer: String,
+    /// Every model's answer, in the order of the ensemble's models.
+    pub candidates: Vec<Candidate>,
+}
+
+impl EnsembleOutcome {
+    /// Returns the chosen candidate, or `None` if the answer was merged.
+    #[must_use]
+    pub fn selected(&self) -> Option<&Candidate> {
+        self.candidates.iter().find(|candidate| candidate.selected)
+    }
+}
+
+/// Sends a request to several models and lets a [`Judge`] choose the answer.
+///
+/// See the [module documentation](crate::llm::ensemble) for an example.
+#[derive(Debug, Clone)]
+pub struct Ensemble<M, J> {
+    models: Vec<M>,
+    weights: Vec<f32>,
+    judge: J,
+}
+
+impl<M: LanguageModel, J: Judge> Ensemble<M, J> {
+    /// Creates an ensemble of `models`, all weighted equally.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `models` is empty.
+    pub fn new(models: impl IntoIterator<Item = M>, judge: J) -> Self {
+        let models: Vec<M> = models.into_iter().collect();
+        assert!(!models.is_empty(), "An ensemble needs at least one model");
+        Self {
+            weights: models.iter().map(|_| 1.0).collect(),
+            models,
+            judge,
+        }
+    }
+
+    /// Sets the weight of each model, in order.
+    ///
+    /// Scores given by the judge are multiplied by the weight of the model that produced
+    /// the candidate. Selections and merges by the judge are not weighted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of weights differs from the number of models.
+    #[must_use]
+    pub fn with_weights(mut self, weights: impl IntoIterator<Item = f32>) -> Self {
+        self.weights = weights.into_iter().collect();
+        assert_eq!(
+            self.weights.len(),
+            self.models.len(),
+            "Each model needs exactly one weight"
+        );
+        self
+    }
+
+    /// Returns the models of the ensemble.
+    #[must_use]
+    pub fn models(&self) -> &[M] {
+        &self.models
+    }
+
+    /// Returns the judge.
+    #[must_use]
+    pub const fn judge(&self) -> &J {
+        &self.judge
+    }
+
+    /// Sends `request` to every model concurrently and returns the judged outcome.
+    ///
+    /// Models that fail are recorded but not judged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if every model fails, or if the judge fails or returns a verdict
+    /// that does not match the candidates.
+    pub async fn run(&self, request: Request) -> crate::Result<EnsembleOutcome> {
+        let answers = join_all(
+            self.models
+                .iter()
+                .map(|model| try_collect(model.respond(request.clone()))),
+        )
+        .await;
+        let mut candidates: Vec<Candidate> = self
+            .models
+            .iter()
+            .zip(answers)
+            .map(|(model, answer)| {
+                let (text, error) = match answer {
+                    Ok(text) => (Some(text), None),
+                    Err(error) => (None, Some(error.to_string())),
+                };
+                Candidate {
+                    model: model.profile().name,
+                    text,
+                    error,
+                    score: None,
+                    selected: false,
+                }
+            })
+            .collect();
+
+        // Only successful candidates are judged; `judged` maps back to their positions.
+        let judged: Vec<usize> = (0..candidates.len())
+            .filter(|index| candidates[*index].text.is_some())
+            .collect();
+        if judged.is_empty() {
+            let errors: Vec<&str> = candidates
+                .iter()
+                .filter_map(|candidate| candidate.error.as_deref())
+                .collect();
+            anyhow::bail!("Every model in the ensemble failed: {}", errors.join("; "));
+        }
+        let texts: Vec<&str> = judged
+            .iter()
+            .filter_map(|index| candidates[*index].text.as_deref())
+            .collect();
+
+        let chosen = match self.judge.judge(&request, &texts).await? {
+            Verdict::Merge(answer) => {
+                return Ok(EnsembleOutcome { answer, candidates });
+            }
+            Verdict::Select(choice) => judged.get(choice).copied().ok_or_else(|| {
+                anyhow::anyhow!("Judge selected candidate {choice} of {}", judged.len())
+            })?,
+            Verdict::Scores(scores) => {
+                if scores.len() != judged.len() {
+                    anyhow::bail!(
+                        "Judge returned {} scores for {} candidates",
+                        scores.len(),
+                        judged.len()
+                    );
+                }
+                for (index, score) in judged.iter().zip(scores) {
+                    candidates[*index].score = Some(score * self.weights[*index]);
+                }
+                judged
+                    .iter()
+                    .copied()
+                    .max_by(|a, b| {
+                        let score = |index: &usize| candidates[*index].score.unwrap_or(f32::MIN);
+                        score(a).total_cmp(&score(b)).then(b.cmp(a))
+                    })
+                    .unwrap_or(judged[0])
+            }
+        };
+        candidates[chosen].selected = true;
+        Ok(EnsembleOutcome {
+            answer: candidates[chosen].text.clone().unwrap_or_default(),
+            candidates,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::{ErrorKind, ProviderError},
+        llm::model::Profile,
+        test_util::{MockLanguageModel, MockReply},
+    };
+
+    /// Answers with `text`, and is named after it.
+    fn answer(text: &str) -> MockLanguageModel {
+        MockLanguageModel::new()
+            .with_profile(Profile::new(text, "A test model", 1024))
+            .with_reply(text)
+    }
+
+    fn unavailable() -> MockLanguageModel {
+        let error = ProviderError::new(ErrorKind::ProviderUnavailable, "down");
+        MockLanguageModel::new()
+            .with_profile(Profile::new("broken", "A test model", 1024))
+            .with_reply(MockReply::error(error))
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn length(_request: &Request, candidate: &str) -> f32 {
+        candidate.len() as f32
+    }
+
+    #[tokio::test]
+    async fn weighted_scores_pick_the_answer() {
+        let models = [answer("short"), unavailable(), answer("longer answer")];
+        let outcome = Ensemble::new(models, length)
+            .run(Request::oneshot("", "Hi"))
+            .await
+            .unwrap();
+        assert_eq!(outcome.answer, "longer answer");
+        assert_eq!(outcome.selected().unwrap().model, "longer answer");
+        assert_eq!(outcome.candidates[0].score, Some(5.0));
+        assert_eq!(
+            outcome.candidates[1].error.as_deref(),
+            Some("provider unavailable: down")
+        );
+        assert_eq!(outcome.candidates[1].score, None);
+
+        let models = [answer("short"), unavailable(), answer("longer answer")];
+        let outcome = Ensemble::new(models, length)
+            .with_weights([10.0, 1.0, 1.0])
+            .run(Request::oneshot("", "Hi"))
+            .await
+            .unwrap();
+        assert_eq!(outcome.answer, "short");
+        assert_eq!(outcome.candidates[0].score, Some(50.0));
+    }
+
+    #[tokio::test]
+    async fn model_judge_selects_and_merges() {
+        let models = [answer("Paris"), answer("Lyon")];
+        let outcome = Ensemble::new(models, ModelJudge::new(answer("Answer [1]")))
+            .run(Request::oneshot("", "Capital of France?"))
+            .await
+            .unwrap();
+        assert_eq!(outcome.answer, "Paris");
+        assert!(outcome.candidates[0].selected);
+
+        let models = [answer("Paris"), answer("Lyon")];
+        let judge = ModelJudge::new(answer(" Paris, not Lyon. ")).merging();
+        let outcome = Ensemble::new(models, judge)
+            .run(Request::oneshot("", "Capital of France?"))
+            .await
+            .unwrap();
+        assert_eq!(outcome.answer, "Paris, not Lyon.");
+        assert!(outcome.selected().is_none());
+
+        let models = [answer("Paris")];
+        let result = Ensemble::new(models, ModelJudge::new(answer("3")))
+            .run(Request::oneshot("", "Capital of France?"))
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn fails_when_every_model_fails() {
+        let error = Ensemble::new([unavailable(), unavailable()], length)
+            .run(Request::oneshot("", "Hi"))
+            .await
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("down; provider unavailable: down")
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! Typed events emitted while a language model responds.
+//!
+//! [`LanguageModel::respond`](crate::LanguageModel::respond) only yields text. Providers that
+//! support function calling surface the tool calls a model makes mid-stream through
+//! [`LanguageModel::respond_events`](crate::LanguageModel::respond_events), which yields
+//! [`StreamEvent`]s. Applications can then intercept and execute the calls themselves.
+//! Providers also report token [`Usage`] through a final [`StreamEvent::Usage`] event, and
+//! details such as the model version through [`StreamEvent::Metadata`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{LanguageModel, Message, Request, event::StreamEvent};
+//! use futures_lite::{StreamExt, pin};
+//!
+//! async fn run(model: impl LanguageModel) -> ai_types::Result {
+//!     let request = Request::new([Message::user("What's the weather in Paris?")]);
+//!     let events = model.respond_events(request.clone());
+//!     pin!(events);
+//!
+//!     let mut text = String::new();
+//!     while let Some(event) = events.try_next().await? {
+//!         match event {
+//!             StreamEvent::Text(chunk) => text.push_str(&chunk),
+//!             StreamEvent::ToolCall(call) => {
+//!                 let output = request.tools.execute(&call).await?;
+//!                 println!("{} returned {output}", call.name);
+//!             }
+//!             StreamEvent::Usage(usage) => println!("{} tokens used", usage.total_tokens),
+//!             StreamEvent::Done => break,
+//!             _ => {}
+//!         }
+//!     }
+//!     Ok(text)
+//! }
+//! ```
+
+use alloc::{string::String, vec::Vec};
+use async_stream::try_stream;
+use futures_core::Stream;
+use futures_lite::StreamExt;
+
+use crate::{
+    llm::{
+        Annotation, TextStream, logprobs::TokenLogprob, metadata::ResponseMetadata, tool::ToolCall,
+        usage::Usage, validation::ValidationError,
+    },
+    provenance::Provenance,
+    rate_limit::RateLimitInfo,
+};
+
+/// An event in a language model's streaming response.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum StreamEvent {
+    /// A chunk of response text.
+    Text(String),
+    /// The model requested a tool call.
+    ToolCall(ToolCall),
+    /// The model requested a tool call whose arguments do not match the tool's schema.
+    ///
+    /// Emitted instead of [`StreamEvent::ToolCall`] by
+    /// [`validate_tool_calls`](crate::llm::validation::validate_tool_calls).
+    InvalidToolCall {
+        /// The invalid tool call.
+        call: ToolCall,
+        /// The problems found in the arguments.
+        errors: Vec<ValidationError>,
+    },
+    /// The log probabilities of the tokens of the preceding text, reported when requested
+    /// with [`Parameters::logprobs`](crate::llm::model::Parameters::logprobs).
+    Logprobs(Vec<TokenLogprob>),
+    /// A citation or other annotation of the response text, such as a source found by
+    /// web search.
+    Annotation(Annotation),
+    /// Token usage of the response, reported before [`StreamEvent::Done`] by providers
+    /// that support it.
+    Usage(Usage),
+    /// Details about how the response was produced, reported by providers that expose
+    /// them. May be reported more than once; see
+    /// [`ResponseMetadata::update`](crate::llm::metadata::ResponseMetadata::update).
+    Metadata(ResponseMetadata),
+    /// The state of the caller's rate limit, reported by providers that expose it.
+    RateLimit(RateLimitInfo),
+    /// Where the response came from, reported before [`StreamEvent::Done`] by
+    /// [`Tagged`](crate::middleware::Tagged).
+    Provenance(Provenance),
+    /// The response is complete.
+    Done,
+}
+
+/// Converts a [`TextStream`] into a stream of [`StreamEvent`]s.
+///
+/// Each chunk becomes a [`StreamEvent::Text`], followed by a final [`StreamEvent::Done`]
+/// once the text stream ends successfully. This is the default behavior of
+/// [`LanguageModel::respond_events`](crate::LanguageModel::respond_events) for models that
+/// cannot report tool calls.
+pub fn text_events<S: TextStream>(stream: S) -> impl Stream<Item = Result<StreamEvent, S::Error>> {
+    try_stream! {
+        let mut stream = stream;
+        while let Some(chunk) = stream.try_next().await? {
+            yield StreamEvent::Text(chunk);
+        }
+        yield StreamEvent::Done;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::stream::text_stream;
+    use alloc::{string::ToString, vec, vec::Vec};
+
+    #[tokio::test]
+    async fn text_events_end_with_done() {
+        let stream = text_stream(futures_lite::stream::iter(vec![
+            Ok::<_, core::convert::Infallible>("Hello".to_string()),
+            Ok(" world".to_string()),
+        ]));
+
+        let events: Vec<_> = text_events(stream).map(Result::unwrap).collect().await;
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::Text("Hello".into()),
+                StreamEvent::Text(" world".into()),
+                StreamEvent::Done,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn text_events_stop_on_error() {
+        #[derive(Debug)]
+        struct Failure;
+
+        let stream = text_stream(futures_lite::stream::iter(vec![
+            Ok("partial".to_string()),
+            Err(Failure),
+        ]));
+
+        let events: Vec<_> = text_events(stream).collect().await;
+        assert_eq!(events.len(), 2);
+        assert!(events[1].is_err());
+    }
+}
@@ -0,0 +1,651 @@
+//! Keeping persisted structured outputs readable as their types change.
+//!
+//! Applications often store the results of [`generate`](crate::LanguageModel::generate):
+//! extracted records, classifications, summaries. When the type `T` evolves between
+//! versions of the application, JSON stored by earlier versions may no longer deserialize:
+//! a field became required, changed type, or lost an enum value.
+//!
+//! A [`SchemaLock`] records the schema of each type used for structured output, and is
+//! persisted alongside the outputs, like a lockfile. On startup, [`check`](SchemaLock::check)
+//! compares the recorded schema of a type with its current one, and reports every
+//! [`Incompatibility`] so it can be logged, or fail a test before release. Stored JSON is
+//! then brought up to date with [`migrate`], which repairs what it can: missing fields get
+//! their default or `null`, single values are wrapped in arrays, and unknown fields are
+//! dropped where the schema forbids them.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::evolution::{SchemaLock, migrate};
+//! use schemars::{JsonSchema, schema_for};
+//! use serde::Deserialize;
+//! use serde_json::json;
+//!
+//! #[derive(JsonSchema, Deserialize)]
+//! struct Contact {
+//!     name: String,
+//!     emails: Vec<String>,
+//!     phone: Option<String>,
+//! }
+//!
+//! // Recorded by the previous version, where `emails` was a single `email`.
+//! let mut lock = SchemaLock::new();
+//! lock.record_schema(
+//!     "Contact",
+//!     schemars::json_schema!({
+//!         "type": "object",
+//!         "properties": { "name": { "type": "string" }, "emails": { "type": "string" } },
+//!         "required": ["name", "emails"]
+//!     }),
+//! );
+//!
+//! let incompatibilities = lock.check::<Contact>();
+//! assert_eq!(incompatibilities.len(), 1);
+//! assert_eq!(incompatibilities[0].path, "$.emails");
+//!
+//! let mut stored = json!({ "name": "Ada", "emails": "ada@example.com" });
+//! assert!(migrate(&mut stored, &schema_for!(Contact)).is_empty());
+//! let contact: Contact = serde_json::from_value(stored).unwrap();
+//! assert_eq!(contact.emails, ["ada@example.com"]);
+//!
+//! lock.record::<Contact>();
+//! assert!(lock.check::<Contact>().is_empty());
+//! ```
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt;
+
+use schemars::{JsonSchema, Schema, schema_for};
+use serde_json::{Map, Value};
+
+use crate::llm::validation::{ValidationError, validate};
+
+/// How deep schemas are followed, which bounds recursive types.
+const MAX_DEPTH: usize = 32;
+
+/// The JSON types, which a schema without `type` accepts.
+const ALL_TYPES: [&str; 6] = ["null", "boolean", "number", "string", "array", "object"];
+
+/// The recorded schemas of the types used for structured output, by name.
+///
+/// With the `serde` feature, a lock serializes as a JSON object mapping names to schemas.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct SchemaLock {
+    schemas: BTreeMap<String, Schema>,
+}
+
+impl SchemaLock {
+    /// Creates an empty lock.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            schemas: BTreeMap::new(),
+        }
+    }
+
+    /// Records the current schema of `T` under its schema name, and returns the schema
+    /// recorded before, if any.
+    pub fn record<T: JsonSchema>(&mut self) -> Option<Schema> {
+        self.record_schema(T::schema_name(), schema_for!(T))
+    }
+
+    /// Records `schema` under `name`, and returns the schema recorded before, if any.
+    pub fn record_schema(&mut self, name: impl Into<String>, schema: Schema) -> Option<Schema> {
+        self.schemas.insert(name.into(), schema)
+    }
+
+    /// Returns the schema recorded under `name`.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Schema> {
+        self.schemas.get(name)
+    }
+
+    /// Returns how JSON conforming to the recorded schema of `T` fails to conform to its
+    /// current schema. Returns an empty vector if the changes are compatible, or if no
+    /// schema was recorded for `T`.
+    #[must_use]
+    pub fn check<T: JsonSchema>(&self) -> Vec<Incompatibility> {
+        self.get(&T::schema_name())
+            .map_or_else(Vec::new, |recorded| compare(recorded, &schema_for!(T)))
+    }
+}
+
+/// A change of schema that breaks JSON conforming to the earlier schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Incompatibility {
+    /// Path of the changed field, e.g. `$.address.city` or `$.items[]`.
+    pub path: String,
+    /// What changed.
+    pub change: Change,
+}
+
+/// What changed in an [`Incompatibility`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum Change {
+    /// The field became required, and earlier JSON may lack it.
+    Required,
+    /// The field no longer accepts some of the types it accepted.
+    Type {
+        /// What the field accepted before, e.g. `string or null`.
+        old: String,
+        /// What the field accepts now.
+        new: String,
+    },
+    /// The field no longer accepts a value it accepted.
+    ValueRemoved(Value),
+    /// The field was removed, and unknown fields are rejected.
+    Removed,
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.path)?;
+        match &self.change {
+            Change::Required => f.write_str("field became required"),
+            Change::Type { old, new } => write!(f, "changed from {old} to {new}"),
+            Change::ValueRemoved(value) => write!(f, "value {value} removed"),
+            Change::Removed => f.write_str("field removed, and unknown fields are rejected"),
+        }
+    }
+}
+
+/// Returns how JSON conforming to `old` fails to conform to `new`, sorted by path.
+///
+/// Supports the subset of JSON Schema generated by [`schemars`], like
+/// [`validate`]. Returns an empty vector if every value valid under `old` stays valid.
+#[must_use]
+pub fn compare(old: &Schema, new: &Schema) -> Vec<Incompatibility> {
+    let comparison = Comparison {
+        old_root: old.as_value(),
+        new_root: new.as_value(),
+    };
+    let mut found = Vec::new();
+    comparison.compare(old.as_value(), new.as_value(), "$", 0, &mut found);
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    found.dedup();
+    found
+}
+
+/// Repairs `value` to conform to `schema` where possible, and returns the problems left.
+///
+/// Missing required fields are filled with their default, or `null` if they accept it.
+/// Single values are wrapped where an array is expected, numbers and strings converted
+/// into each other, and fields unknown to a schema rejecting unknown fields dropped.
+/// Returns an empty vector if `value` now conforms to `schema`.
+pub fn migrate(value: &mut Value, schema: &Schema) -> Vec<ValidationError> {
+    Migration {
+        root: schema.as_value(),
+    }
+    .migrate(schema.as_value(), value, 0);
+    validate(schema.as_value(), value)
+}
+
+struct Comparison<'a> {
+    old_root: &'a Value,
+    new_root: &'a Value,
+}
+
+impl<'a> Comparison<'a> {
+    fn compare(
+        &self,
+        old: &'a Value,
+        new: &'a Value,
+        path: &str,
+        depth: usize,
+        found: &mut Vec<Incompatibility>,
+    ) {
+        if depth > MAX_DEPTH {
+            return;
+        }
+        let old = resolve(self.old_root, old);
+        let new = resolve(self.new_root, new);
+        let (Some(old_object), Some(new_object)) = (old.as_object(), new.as_object()) else {
+            if new == &Value::Bool(false) && old != &Value::Bool(false) {
+                found.push(incompatibility(path, type_change(old, new)));
+            }
+            return;
+        };
+
+        if let Some(branches) = branches(old_object) {
+            for branch in branches {
+                self.compare(branch, new, path, depth + 1, found);
+            }
+            return;
+        }
+        if let Some(branches) = branches(new_object) {
+            let closest = branches
+                .iter()
+                .map(|branch| {
+                    let mut branch_found = Vec::new();
+                    self.compare(old, branch, path, depth + 1, &mut branch_found);
+                    branch_found
+                })
+                .min_by_key(Vec::len);
+            found.extend(closest.unwrap_or_default());
+            return;
+        }
+
+        if let Some(new_types) = new_object.get("type") {
+            let old_types = old_object
+                .get("type")
+                .map_or_else(|| ALL_TYPES.to_vec(), types);
+            let new_types = types(new_types);
+            let accepted = |name: &&str| {
+                new_types.contains(name) || (*name == "integer" && new_types.contains(&"number"))
+            };
+            if !old_types.iter().all(accepted) {
+                found.push(incompatibility(path, type_change(old, new)));
+                return;
+            }
+        }
+
+        if let Some(new_values) = values(new_object) {
+            match values(old_object) {
+                Some(old_values) => found.extend(
+                    old_values
+                        .into_iter()
+                        .filter(|value| !new_values.contains(value))
+                        .map(|value| incompatibility(path, Change::ValueRemoved(value.clone()))),
+                ),
+                None => found.push(incompatibility(path, type_change(old, new))),
+            }
+        }
+
+        self.compare_objects(old_object, new_object, path, depth, found);
+        if let (Some(old_items), Some(new_items)) =
+            (old_object.get("items"), new_object.get("items"))
+        {
+            self.compare(old_items, new_items, &format!("{path}[]"), depth + 1, found);
+        }
+    }
+
+    fn compare_objects(
+        &self,
+        old: &'a Map<String, Value>,
+        new: &'a Map<String, Value>,
+        path: &str,
+        depth: usize,
+        found: &mut Vec<Incompatibility>,
+    ) {
+        let old_properties = old.get("properties").and_then(Value::as_object);
+        let new_properties = new.get("properties").and_then(Value::as_object);
+        let old_required = required(old);
+        for name in required(new) {
+            if !old_required.contains(&name) {
+                found.push(incompatibility(&format!("{path}.{name}"), Change::Required));
+            }
+        }
+
+        if let (Some(old_properties), Some(new_properties)) = (old_properties, new_properties) {
+            for (name, new_property) in new_properties {
+                if let Some(old_property) = old_properties.get(name) {
+                    let path = format!("{path}.{name}");
+                    self.compare(old_property, new_property, &path, depth + 1, found);
+                }
+            }
+        }
+
+        if new.get("additionalProperties") == Some(&Value::Bool(false)) {
+            for name in old_properties.into_iter().flat_map(Map::keys) {
+                if new_properties.is_none_or(|properties| !properties.contains_key(name)) {
+                    found.push(incompatibility(&format!("{path}.{name}"), Change::Removed));
+                }
+            }
+        }
+    }
+}
+
+struct Migration<'a> {
+    root: &'a Value,
+}
+
+impl<'a> Migration<'a> {
+    fn migrate(&self, schema: &'a Value, value: &mut Value, depth: usize) {
+        if depth > MAX_DEPTH {
+            return;
+        }
+        let Some(schema) = resolve(self.root, schema).as_object() else {
+            return;
+        };
+
+        if let Some(branches) = branches(schema) {
+            if let Some(branch) = self.branch_for(branches, value) {
+                self.migrate(branch, value, depth + 1);
+            }
+            return;
+        }
+
+        if let Some(expected) = schema.get("type") {
+            convert(&types(expected), value);
+        }
+        if let Value::Object(object) = value {
+            self.migrate_object(schema, object, depth);
+        }
+        if let (Some(items), Value::Array(array)) = (schema.get("items"), value) {
+            for item in array {
+                self.migrate(items, item, depth + 1);
+            }
+        }
+    }
+
+    fn migrate_object(
+        &self,
+        schema: &'a Map<String, Value>,
+        object: &mut Map<String, Value>,
+        depth: usize,
+    ) {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for name in required(schema) {
+            if object.contains_key(name) {
+                continue;
+            }
+            let property = properties.and_then(|properties| properties.get(name));
+            if let Some(default) = property.and_then(|property| self.default(property)) {
+                object.insert(name.to_string(), default);
+            }
+        }
+
+        if let Some(properties) = properties {
+            for (name, property) in properties {
+                if let Some(value) = object.get_mut(name) {
+                    self.migrate(property, value, depth + 1);
+                }
+            }
+        }
+
+        if schema.get("additionalProperties") == Some(&Value::Bool(false)) {
+            object.retain(|name, _| properties.is_some_and(|p| p.contains_key(name)));
+        }
+    }
+
+    /// Returns the value a missing field gets: its default, or `null` if it accepts it.
+    fn default(&self, property: &'a Value) -> Option<Value> {
+        let property = resolve(self.root, property).as_object()?;
+        if let Some(default) = property.get("default") {
+            return Some(default.clone());
+        }
+        let nullable = property
+            .get("type")
+            .is_some_and(|expected| types(expected).contains(&"null"))
+            || branches(property).is_some_and(|branches| {
+                branches.iter().any(|branch| {
+                    resolve(self.root, branch)
+                        .get("type")
+                        .is_some_and(|expected| types(expected).contains(&"null"))
+                })
+            });
+        nullable.then_some(Value::Null)
+    }
+
+    /// Returns the branch `value` most likely belongs to: the first accepting its type,
+    /// or else the first not accepting only `null`.
+    fn branch_for(&self, branches: &'a [Value], value: &Value) -> Option<&'a Value> {
+        let type_of = |branch: &'a Value| {
+            resolve(self.root, branch)
+                .get("type")
+                .map_or_else(|| ALL_TYPES.to_vec(), types)
+        };
+        branches
+            .iter()
+            .find(|branch| type_of(branch).iter().any(|name| is_type(name, value)))
+            .or_else(|| branches.iter().find(|branch| type_of(branch) != ["null"]))
+    }
+}
+
+/// Converts `value` to one of the `expected` types, if it has none of them.
+fn convert(expected: &[&str], value: &mut Value) {
+    if expected.iter().any(|name| is_type(name, value)) {
+        return;
+    }
+    if expected.contains(&"array") && !value.is_null() {
+        *value = Value::Array(vec![value.take()]);
+    } else if expected.contains(&"string") && (value.is_number() || value.is_boolean()) {
+        *value = Value::String(value.to_string());
+    } else if let Some(text) = value.as_str() {
+        let parsed = if expected.contains(&"integer") {
+            text.trim().parse::<i64>().ok().map(Value::from)
+        } else if expected.contains(&"number") {
+            text.trim().parse::<f64>().ok().map(Value::from)
+        } else {
+            None
+        };
+        if let Some(parsed) = parsed {
+            *value = parsed;
+        }
+    }
+}
+
+fn incompatibility(path: &str, change: Change) -> Incompatibility {
+    Incompatibility {
+        path: path.to_string(),
+        change,
+    }
+}
+
+fn type_change(old: &Value, new: &Value) -> Change {
+    Change::Type {
+        old: describe(old),
+        new: describe(new),
+    }
+}
+
+/// Describes what a schema accepts, for reports.
+fn describe(schema: &Value) -> String {
+    let Some(schema) = schema.as_object() else {
+        return if schema == &Value::Bool(false) {
+            "nothing".to_string()
+        } else {
+            "anything".to_string()
+        };
+    };
+    if let Some(values) = values(schema) {
+        let values: Vec<String> = values.iter().map(ToString::to_string).collect();
+        return format!("one of {}", values.join(", "));
+    }
+    schema.get("type").map_or_else(
+        || "anything".to_string(),
+        |expected| types(expected).join(" or "),
+    )
+}
+
+/// Follows a local `$ref`, if `schema` is one.
+fn resolve<'a>(root: &'a Value, schema: &'a Value) -> &'a Value {
+    schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|target| root.pointer(target.strip_prefix('#')?))
+        .unwrap_or(schema)
+}
+
+fn branches(schema: &Map<String, Value>) -> Option<&[Value]> {
+    schema
+        .get("anyOf")
+        .or_else(|| schema.get("oneOf"))
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+}
+
+fn types(expected: &Value) -> Vec<&str> {
+    match expected {
+        Value::String(name) => vec![name.as_str()],
+        Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+        _ => ALL_TYPES.to_vec(),
+    }
+}
+
+/// Returns the values a schema restricts to, with `enum` or `const`.
+fn values(schema: &Map<String, Value>) -> Option<Vec<&Value>> {
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return Some(values.iter().collect());
+    }
+    schema.get("const").map(|value| vec![value])
+}
+
+fn required(schema: &Map<String, Value>) -> Vec<&str> {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map_or_else(Vec::new, |names| {
+            names.iter().filter_map(Value::as_str).collect()
+        })
+}
+
+fn is_type(name: &str, value: &Value) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(JsonSchema, Deserialize)]
+    #[allow(dead_code)]
+    enum StatusV1 {
+        Draft,
+        Published,
+        Archived,
+    }
+
+    #[derive(JsonSchema, Deserialize)]
+    #[allow(dead_code)]
+    struct ArticleV1 {
+        title: String,
+        views: u32,
+        status: StatusV1,
+        tags: Option<String>,
+    }
+
+    #[derive(JsonSchema, Deserialize)]
+    #[allow(dead_code)]
+    enum StatusV2 {
+        Draft,
+        Published,
+    }
+
+    #[derive(JsonSchema, Deserialize)]
+    #[serde(rename = "Article", deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct ArticleV2 {
+        title: String,
+        views: f64,
+        status: Option<StatusV2>,
+        tags: Vec<String>,
+        author: Option<String>,
+        language: String,
+        #[serde(default)]
+        pinned: bool,
+    }
+
+    #[test]
+    fn reports_incompatible_changes() {
+        let mut lock = SchemaLock::new();
+        assert!(lock.check::<ArticleV2>().is_empty());
+        lock.record_schema("Article", schema_for!(ArticleV1));
+
+        let found = lock.check::<ArticleV2>();
+        let paths: Vec<(&str, &Change)> = found
+            .iter()
+            .map(|incompatibility| (incompatibility.path.as_str(), &incompatibility.change))
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                ("$.language", &Change::Required),
+                ("$.status", &Change::ValueRemoved(json!("Archived"))),
+                ("$.tags", &Change::Required),
+                (
+                    "$.tags",
+                    &Change::Type {
+                        old: "string or null".into(),
+                        new: "array".into()
+                    }
+                ),
+            ]
+        );
+        assert_eq!(
+            found[1].to_string(),
+            r#"$.status: value "Archived" removed"#
+        );
+
+        assert!(compare(&schema_for!(ArticleV2), &schema_for!(ArticleV2)).is_empty());
+        assert_eq!(
+            compare(&schema_for!(ArticleV2), &schema_for!(ArticleV1)).last(),
+            Some(&incompatibility(
+                "$.views",
+                Change::Type {
+                    old: "number".into(),
+                    new: "integer".into()
+                }
+            ))
+        );
+        assert_eq!(lock.record::<ArticleV2>(), Some(schema_for!(ArticleV1)));
+        assert!(lock.check::<ArticleV2>().is_empty());
+    }
+
+    #[test]
+    fn migrates_stored_json() {
+        let schema = schema_for!(ArticleV2);
+        let mut stored = json!({
+            "title": 42,
+            "views": "1200",
+            "status": "Draft",
+            "tags": "rust",
+            "language": "en",
+            "legacy": true,
+        });
+        assert!(migrate(&mut stored, &schema).is_empty());
+        assert_eq!(
+            stored,
+            json!({
+                "title": "42",
+                "views": 1200.0,
+                "status": "Draft",
+                "tags": ["rust"],
+                "language": "en",
+            })
+        );
+        let article: ArticleV2 = serde_json::from_value(stored).unwrap();
+        assert_eq!(article.tags, ["rust"]);
+
+        let mut archived = json!({ "title": "Old", "views": 1, "status": "Archived", "tags": [] });
+        let errors = migrate(&mut archived, &schema);
+        let paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(paths, ["$.language", "$.status"]);
+
+        let schema = schemars::json_schema!({
+            "type": "object",
+            "properties": {
+                "language": { "type": "string", "default": "en" },
+                "note": { "type": ["string", "null"] }
+            },
+            "required": ["language", "note"]
+        });
+        let mut value = json!({});
+        assert!(migrate(&mut value, &schema).is_empty());
+        assert_eq!(value, json!({ "language": "en", "note": null }));
+    }
+}
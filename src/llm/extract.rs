@@ -0,0 +1,273 @@
+//! Lenient extraction of JSON from model responses.
+//!
+//! Even when asked for JSON only, models often wrap it in a markdown code fence, explain
+//! it in a sentence first, or leave a trailing comma after the last field. [`parse_json`]
+//! accepts such responses: it parses the response as is, and falls back to the JSON
+//! objects and arrays found in it, with trailing commas removed.
+//!
+//! [`generate`](crate::LanguageModel::generate) parses responses this way. When parsing
+//! still fails, it can send the error back to the model and ask again, up to the
+//! request's [`parse_retries`](crate::llm::Request::parse_retries).
+//!
+//! [`close_json`] completes JSON still being streamed, which lets
+//! [`generate_stream`](crate::LanguageModel::generate_stream) parse partial objects.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::extract::{extract_json, parse_json};
+//! use serde_json::Value;
+//!
+//! let response = "Sure! Here is the data:\n```json\n{\"city\": \"Paris\", \"days\": [1, 2,],}\n```";
+//! assert_eq!(
+//!     extract_json(response),
+//!     Some("{\"city\": \"Paris\", \"days\": [1, 2,],}")
+//! );
+//!
+//! let value: Value = parse_json(response).unwrap();
+//! assert_eq!(value["days"][1], 2);
+//! ```
+
+use alloc::string::String;
+
+use serde::de::{DeserializeOwned, IgnoredAny};
+
+/// Parses JSON from a model response, tolerating surrounding text and trailing commas.
+///
+/// The response is parsed as is first. If that fails, each JSON object or array found in
+/// it, inside a code fence if there is one, is parsed in order, with trailing commas
+/// removed if needed. The first value that parses wins.
+///
+/// # Errors
+///
+/// Returns the error of parsing the whole response if no value parses.
+pub fn parse_json<T: DeserializeOwned>(response: &str) -> serde_json::Result<T> {
+    let error = match serde_json::from_str(response.trim()) {
+        Ok(value) => return Ok(value),
+        Err(error) => error,
+    };
+    for candidate in candidates(unfence(response)) {
+        if let Ok(value) = serde_json::from_str(candidate) {
+            return Ok(value);
+        }
+        if let Ok(value) = serde_json::from_str(&remove_trailing_commas(candidate)) {
+            return Ok(value);
+        }
+    }
+    Err(error)
+}
+
+/// Returns the first balanced JSON object or array in `response`, inside a code fence if
+/// there is one.
+///
+/// Brackets inside strings are ignored. The returned text is not validated, and may
+/// still contain syntax errors.
+#[must_use]
+pub fn extract_json(response: &str) -> Option<&str> {
+    candidates(unfence(response)).next()
+}
+
+/// Removes commas directly followed, ignoring whitespace, by a closing bracket.
+#[must_use]
+pub fn remove_trailing_commas(json: &str) -> String {
+    let mut repaired = String::with_capacity(json.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in json.char_indices() {
+        if in_string {
+            in_string = escaped || c != '"';
+            escaped = !escaped && c == '\\';
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' && json[index + 1..].trim_start().starts_with(['}', ']']) {
+            continue;
+        }
+        repaired.push(c);
+    }
+    repaired
+}
+
+/// Closes truncated JSON, such as a response still being streamed, so it parses.
+///
+/// Text before the first `{` or `[` is skipped. An unterminated string is closed, and
+/// then the open arrays and objects. When the last value is incomplete in a way closing
+/// cannot fix, like a key without a value or a partial `true`, it is dropped along with
+/// its key. Complete JSON is returned unchanged, without any text after it.
+///
+/// Returns `None` if there is no JSON to close, or if its brackets do not match.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::llm::extract::close_json;
+///
+/// assert_eq!(close_json(r#"{"title": "Hel"#).as_deref(), Some(r#"{"title": "Hel"}"#));
+/// assert_eq!(close_json(r#"{"a": [1, 2], "b": tr"#).as_deref(), Some(r#"{"a": [1, 2]}"#));
+/// ```
+#[must_use]
+pub fn close_json(partial: &str) -> Option<String> {
+    let text = &partial[partial.find(['{', '['])?..];
+    // The closing brackets still needed, innermost last.
+    let mut closers = String::new();
+    // The last point the text can be cut at, with the closers needed there.
+    let mut cut = None;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in text.char_indices() {
+        if in_string {
+            in_string = escaped || c != '"';
+            escaped = !escaped && c == '\\';
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => {
+                closers.push(if c == '{' { '}' } else { ']' });
+                cut = Some((index + 1, closers.clone()));
+            }
+            '}' | ']' => {
+                if closers.pop() != Some(c) {
+                    return None;
+                }
+                if closers.is_empty() {
+                    return Some(text[..=index].into());
+                }
+            }
+            ',' => cut = Some((index, closers.clone())),
+            _ => {}
+        }
+    }
+
+    let mut closed = String::from(text);
+    if in_string {
+        if escaped {
+            closed.pop();
+        }
+        closed.push('"');
+    }
+    closed.extend(closers.chars().rev());
+    if serde_json::from_str::<IgnoredAny>(&closed).is_ok() {
+        return Some(closed);
+    }
+    let (end, closers) = cut?;
+    let mut closed = String::from(&text[..end]);
+    closed.extend(closers.chars().rev());
+    Some(closed)
+}
+
+/// Returns the content of the first code fence in `response`, or `response` if it has
+/// none. An unclosed fence extends to the end.
+fn unfence(response: &str) -> &str {
+    let Some(start) = response.find("```") else {
+        return response;
+    };
+    // Skip the language tag.
+    let content = &response[start + 3..];
+    let content = content.find('\n').map_or("", |end| &content[end + 1..]);
+    content.find("```").map_or(content, |end| &content[..end])
+}
+
+/// Iterates over the balanced objects and arrays of `text`, by starting position.
+fn candidates(text: &str) -> impl Iterator<Item = &str> {
+    text.char_indices()
+        .filter(|&(_, c)| c == '{' || c == '[')
+        .filter_map(|(start, _)| balanced(&text[start..]).map(|end| &text[start..start + end]))
+}
+
+/// Returns the length of the bracketed value `text` starts with, if it is closed.
+fn balanced(text: &str) -> Option<usize> {
+    let mut depth = 0_usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in text.char_indices() {
+        if in_string {
+            in_string = escaped || c != '"';
+            escaped = !escaped && c == '\\';
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(index + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LanguageModel, llm::Request, test_util::MockLanguageModel};
+    use alloc::collections::BTreeMap;
+    use serde_json::Value;
+
+    #[test]
+    fn extracts_json_around_prose() {
+        assert_eq!(
+            extract_json(r#"The answer is {"a": "}", "b": [1]} as requested."#),
+            Some(r#"{"a": "}", "b": [1]}"#)
+        );
+        assert_eq!(extract_json("```\n[1, 2]\n```"), Some("[1, 2]"));
+        assert_eq!(extract_json(r#"{"open": true"#), None);
+
+        let value: Value = parse_json(r#"See [note]: {"escaped": "a\"}",}"#).unwrap();
+        assert_eq!(value["escaped"], "a\"}");
+        assert!(parse_json::<Value>("no JSON here").is_err());
+    }
+
+    #[test]
+    fn closes_truncated_json() {
+        let cases = [
+            ("```json\n[1, 2", Some("[1, 2]")),
+            (r#"{"a": {"b": "x\"#, Some(r#"{"a": {"b": "x"}}"#)),
+            (r#"{"a": "}", "b"#, Some(r#"{"a": "}"}"#)),
+            (r#"{"a": 1, "b": {"c":"#, Some(r#"{"a": 1, "b": {}}"#)),
+            (r#"[{"a": 1}, "#, Some(r#"[{"a": 1}]"#)),
+            (r#"{"a": 1} trailing"#, Some(r#"{"a": 1}"#)),
+            ("no JSON", None),
+            (r#"{"a": [1}"#, None),
+        ];
+        for (partial, closed) in cases {
+            assert_eq!(close_json(partial).as_deref(), closed, "{partial}");
+        }
+    }
+
+    #[test]
+    fn removes_only_trailing_commas() {
+        assert_eq!(
+            remove_trailing_commas(r#"{"a": [1, 2 ,], "b": ",}", }"#),
+            r#"{"a": [1, 2 ], "b": ",}" }"#
+        );
+    }
+
+    #[tokio::test]
+    async fn generate_retries_unparsable_responses() {
+        let replies = ["I cannot do that.", "```json\n{\"ok\": true,}\n```"];
+        let request = Request::oneshot("system", "user");
+
+        let model = MockLanguageModel::new().with_replies(replies);
+        assert!(
+            model
+                .generate::<BTreeMap<String, bool>>(request.clone())
+                .await
+                .is_err()
+        );
+        assert_eq!(model.requests().len(), 1);
+
+        let model = MockLanguageModel::new().with_replies(replies);
+        let value: BTreeMap<String, bool> =
+            model.generate(request.with_parse_retries(1)).await.unwrap();
+        assert!(value["ok"]);
+
+        let requests = model.requests();
+        assert_eq!(requests.len(), 2);
+        let feedback = requests[1].messages.last().unwrap().content();
+        assert!(feedback.contains("could not be parsed"));
+    }
+}
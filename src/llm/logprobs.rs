@@ -0,0 +1,138 @@
+//! Log probabilities of generated tokens.
+//!
+//! With [`Parameters::logprobs`](crate::llm::model::Parameters::logprobs) enabled,
+//! providers return how likely the model considered each token it generated, and with
+//! [`top_logprobs`](crate::llm::model::Parameters::top_logprobs) the most likely
+//! alternatives at each position. They arrive alongside the text, as
+//! [`StreamEvent::Logprobs`](crate::llm::event::StreamEvent::Logprobs) events from
+//! [`LanguageModel::respond_events`](crate::LanguageModel::respond_events), each covering
+//! the tokens of the text event before it.
+//!
+//! Log probabilities are natural logarithms, so `0.0` means the model was certain and
+//! lower values mean less confidence. [`mean_logprob`] averages them into a simple
+//! confidence score of a whole response.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{
+//!     LanguageModel, Message, Request,
+//!     event::StreamEvent,
+//!     logprobs::{TokenLogprob, mean_logprob},
+//!     model::Parameters,
+//! };
+//! use futures_lite::{StreamExt, pin};
+//!
+//! async fn answer_with_confidence(model: impl LanguageModel) -> ai_types::Result<(String, f64)> {
+//!     let request = Request::new([Message::user("Is 7919 prime?")])
+//!         .with_parameters(Parameters::default().logprobs(true).top_logprobs(3));
+//!     let events = model.respond_events(request);
+//!     pin!(events);
+//!
+//!     let mut text = String::new();
+//!     let mut tokens: Vec<TokenLogprob> = Vec::new();
+//!     while let Some(event) = events.try_next().await? {
+//!         match event {
+//!             StreamEvent::Text(chunk) => text.push_str(&chunk),
+//!             StreamEvent::Logprobs(chunk) => tokens.extend(chunk),
+//!             _ => {}
+//!         }
+//!     }
+//!     Ok((text, mean_logprob(&tokens).unwrap_or(0.0)))
+//! }
+//! ```
+
+use alloc::{string::String, vec::Vec};
+
+/// A generated token with its log probability.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct TokenLogprob {
+    /// The text of the token.
+    pub token: String,
+    /// The natural logarithm of the token's probability.
+    pub logprob: f64,
+    /// The most likely tokens at this position, most likely first, if requested with
+    /// [`top_logprobs`](crate::llm::model::Parameters::top_logprobs). Usually includes the
+    /// generated token itself.
+    pub top: Vec<TopLogprob>,
+}
+
+impl TokenLogprob {
+    /// Creates a token without alternatives.
+    pub fn new(token: impl Into<String>, logprob: f64) -> Self {
+        Self {
+            token: token.into(),
+            logprob,
+            top: Vec::new(),
+        }
+    }
+
+    /// Adds a likely token at this position.
+    #[must_use]
+    pub fn with_top(mut self, token: impl Into<String>, logprob: f64) -> Self {
+        self.top.push(TopLogprob {
+            token: token.into(),
+            logprob,
+        });
+        self
+    }
+
+    /// Returns the most likely token at this position other than the generated one.
+    #[must_use]
+    pub fn runner_up(&self) -> Option<&TopLogprob> {
+        self.top
+            .iter()
+            .filter(|top| top.token != self.token)
+            .max_by(|a, b| a.logprob.total_cmp(&b.logprob))
+    }
+}
+
+/// One of the most likely tokens at a position of the response.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct TopLogprob {
+    /// The text of the token.
+    pub token: String,
+    /// The natural logarithm of the token's probability.
+    pub logprob: f64,
+}
+
+/// Returns the average log probability of `tokens`, or `None` if there are none.
+///
+/// The closer to `0.0`, the more confident the model was in its response.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn mean_logprob(tokens: &[TokenLogprob]) -> Option<f64> {
+    if tokens.is_empty() {
+        return None;
+    }
+    let total: f64 = tokens.iter().map(|token| token.logprob).sum();
+    Some(total / tokens.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_tokens_and_alternatives() {
+        let tokens = [
+            TokenLogprob::new("Yes", -0.25)
+                .with_top("Yes", -0.25)
+                .with_top("No", -1.5)
+                .with_top("Maybe", -3.0),
+            TokenLogprob::new(".", -0.75),
+        ];
+
+        assert_eq!(
+            tokens[0].runner_up().map(|top| top.token.as_str()),
+            Some("No")
+        );
+        assert!(tokens[1].runner_up().is_none());
+        assert_eq!(mean_logprob(&tokens), Some(-0.5));
+        assert_eq!(mean_logprob(&[]), None);
+    }
+}
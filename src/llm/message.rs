@@ -20,6 +20,22 @@
 //! let tool_msg = Message::new(Role::Tool, "Tool executed successfully".into());
 //! ```
 //!
+//! ## Multimodal content
+//!
+//! ```rust
+//! use ai_types::llm::{Message, Role, message::Content};
+//!
+//! let message = Message::from_parts(
+//!     Role::User,
+//!     [
+//!         Content::text("What is in this picture?"),
+//!         Content::image_url("https://example.com/cat.png".parse().unwrap()),
+//!     ],
+//! );
+//! assert_eq!(message.parts().len(), 2);
+//! assert_eq!(message.content(), "What is in this picture?");
+//! ```
+//!
 //! ## Adding attachments
 //!
 //! ```rust
@@ -57,15 +73,19 @@
 
 use core::fmt::Debug;
 
-use alloc::{string::String, vec::Vec};
+use alloc::{borrow::Cow, string::String, sync::Arc, vec, vec::Vec};
 use url::Url;
 
+use crate::llm::tool::ToolCall;
+
 /// Conversation participant role.
 ///
 /// Defines the role of a message sender in a conversation.
 /// Each role has specific semantics and is typically handled differently
 /// by AI language models.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Role {
     /// User message.
     ///
@@ -82,14 +102,19 @@ pub enum Role {
     System,
     /// Tool/function call message.
     ///
-    /// Represents output from external tools or function calls.
+    /// Represents output from external tools or function calls. Tool results carry the
+    /// ID of the call they answer; see [`Message::tool_result`].
     Tool,
 }
 
 /// A message in a conversation.
 ///
-/// Contains a [`Role`], text content, and optional attachments and annotations.
+/// Contains a [`Role`], [`Content`] parts, and optional attachments and annotations.
 /// Messages form the building blocks of conversations with AI language models.
+///
+/// Messages are cheap to clone: clones share their content parts until one of them is
+/// modified, so a [`Request`](crate::llm::Request) can be cloned for every retry or
+/// fallback model without copying its history.
 /// # Example
 ///
 /// ```rust
@@ -103,14 +128,25 @@ pub enum Role {
 ///
 /// // Add attachments to a message
 /// let msg_with_attachment = Message::user("Check out this image")
-///     .with_attachment("https://example.com/image.jpg");
+///     .with_attachment("https://example.com/image.jpg".parse::<Url>().unwrap());
 /// ```
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Message {
+    #[cfg_attr(feature = "serde", serde(default))]
     attachments: Vec<Url>,
+    #[cfg_attr(feature = "serde", serde(default))]
     annotation: Vec<Annotation>,
-    content: String,
+    #[cfg_attr(feature = "serde", serde(with = "shared"))]
+    parts: Arc<Vec<Content>>,
     role: Role,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pinned: bool,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    tool_call_id: Option<String>,
 }
 
 impl Message {
@@ -120,9 +156,48 @@ impl Message {
         self.role
     }
     /// Returns the text content of the message.
+    ///
+    /// Text parts are concatenated; non-text parts are skipped. Borrows when the message
+    /// has a single text part.
+    #[must_use]
+    pub fn content(&self) -> Cow<'_, str> {
+        match self.parts.as_slice() {
+            [Content::Text(text)] => Cow::Borrowed(text),
+            parts => Cow::Owned(parts.iter().filter_map(Content::as_text).collect()),
+        }
+    }
+
+    /// Returns the content parts of the message, in order.
     #[must_use]
-    pub const fn content(&self) -> &str {
-        self.content.as_str()
+    pub fn parts(&self) -> &[Content] {
+        self.parts.as_slice()
+    }
+
+    /// Returns the content parts of the message mutably.
+    pub(crate) fn parts_mut(&mut self) -> &mut [Content] {
+        Arc::make_mut(&mut self.parts).as_mut_slice()
+    }
+
+    /// Appends the content, attachments, and annotations of `other` to this message.
+    ///
+    /// Adjacent text parts are joined by a blank line. The merged message is pinned if
+    /// either message was.
+    pub(crate) fn merge(&mut self, other: Self) {
+        let mut parts = Arc::unwrap_or_clone(other.parts).into_iter();
+        let own = Arc::make_mut(&mut self.parts);
+        if let (Some(Content::Text(last)), Some(Content::Text(first))) =
+            (own.last_mut(), parts.as_slice().first())
+        {
+            if !last.is_empty() && !first.is_empty() {
+                last.push_str("\n\n");
+            }
+            last.push_str(first);
+            parts.next();
+        }
+        own.extend(parts);
+        self.attachments.extend(other.attachments);
+        self.annotation.extend(other.annotation);
+        self.pinned |= other.pinned;
     }
 
     /// Returns the attachment URLs associated with the message.
@@ -141,6 +216,165 @@ impl Message {
     pub const fn annotations(&self) -> &[Annotation] {
         self.annotation.as_slice()
     }
+
+    /// Returns the tool calls requested in the message, in order.
+    pub fn tool_calls(&self) -> impl Iterator<Item = &ToolCall> {
+        self.parts.iter().filter_map(|part| match part {
+            Content::ToolCall(call) => Some(call),
+            _ => None,
+        })
+    }
+
+    /// Returns the ID of the tool call this message answers, if it is a tool result.
+    #[must_use]
+    pub fn tool_call_id(&self) -> Option<&str> {
+        self.tool_call_id.as_deref()
+    }
+
+    /// Returns whether the message is pinned.
+    ///
+    /// Pinned messages are never dropped by trimming or compression strategies.
+    /// See [`TrimProtection`].
+    #[must_use]
+    pub const fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+}
+
+/// Decides which messages trimming and compression strategies must keep.
+///
+/// A message is protected when it is [pinned](Message::pinned) or its [`Role`] is one of
+/// the protected roles. By default, system messages are protected.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::llm::{Message, Role, message::TrimProtection};
+///
+/// let protection = TrimProtection::default();
+/// assert!(protection.protects(&Message::system("You are a helpful assistant")));
+/// assert!(protection.protects(&Message::user("Disclosure: ...").pinned(true)));
+/// assert!(!protection.protects(&Message::user("Hello")));
+///
+/// let nothing = TrimProtection::none();
+/// assert!(!nothing.protects(&Message::system("Trimmable")));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrimProtection {
+    roles: Vec<Role>,
+}
+
+impl Default for TrimProtection {
+    fn default() -> Self {
+        Self::roles([Role::System])
+    }
+}
+
+impl TrimProtection {
+    /// Protects only pinned messages.
+    #[must_use]
+    pub const fn none() -> Self {
+        Self { roles: Vec::new() }
+    }
+
+    /// Protects pinned messages and every message with one of the given roles.
+    pub fn roles(roles: impl IntoIterator<Item = Role>) -> Self {
+        Self {
+            roles: roles.into_iter().collect(),
+        }
+    }
+
+    /// Returns whether `message` must be kept.
+    #[must_use]
+    pub fn protects(&self, message: &Message) -> bool {
+        message.is_pinned() || self.roles.contains(&message.role())
+    }
+}
+
+/// A part of a [`Message`]'s content.
+///
+/// Modern providers accept interleaved text, images, and audio in a single message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum Content {
+    /// Plain text.
+    Text(String),
+    /// An image referenced by URL.
+    ImageUrl(Url),
+    /// Inline image data.
+    ImageData {
+        /// Encoded image bytes.
+        data: Vec<u8>,
+        /// MIME type of the image, e.g. `image/png`.
+        mime_type: String,
+    },
+    /// Inline audio data.
+    AudioData {
+        /// Encoded audio bytes.
+        data: Vec<u8>,
+        /// MIME type of the audio, e.g. `audio/wav`.
+        mime_type: String,
+    },
+    /// A tool call requested by the assistant.
+    ToolCall(ToolCall),
+}
+
+impl Content {
+    /// Creates a text part.
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text(text.into())
+    }
+
+    /// Creates an image part referenced by URL.
+    #[must_use]
+    pub const fn image_url(url: Url) -> Self {
+        Self::ImageUrl(url)
+    }
+
+    /// Creates an inline image part.
+    pub fn image_data(data: impl Into<Vec<u8>>, mime_type: impl Into<String>) -> Self {
+        Self::ImageData {
+            data: data.into(),
+            mime_type: mime_type.into(),
+        }
+    }
+
+    /// Creates an inline audio part.
+    pub fn audio_data(data: impl Into<Vec<u8>>, mime_type: impl Into<String>) -> Self {
+        Self::AudioData {
+            data: data.into(),
+            mime_type: mime_type.into(),
+        }
+    }
+
+    /// Returns the text of a [`Content::Text`] part.
+    #[must_use]
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+impl From<ToolCall> for Content {
+    fn from(call: ToolCall) -> Self {
+        Self::ToolCall(call)
+    }
+}
+
+impl From<String> for Content {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<&str> for Content {
+    fn from(text: &str) -> Self {
+        Self::Text(text.into())
+    }
 }
 
 /// URL annotation metadata.
@@ -157,6 +391,7 @@ impl Message {
 /// * `start` - Start character index of the URL in the message content
 /// * `end` - End character index of the URL in the message content
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UrlAnnotation {
     /// The annotated URL.
     pub url: Url,
@@ -226,6 +461,8 @@ impl UrlAnnotation {
 ///
 /// * `Url` - Annotation for a URL mentioned in the message content
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Annotation {
     /// URL annotation. See [`UrlAnnotation`].
     Url(UrlAnnotation),
@@ -239,12 +476,24 @@ impl Message {
     /// * `role` - The role of the message sender
     /// * `content` - The text content of the message
     #[must_use]
-    pub const fn new(role: Role, content: String) -> Self {
+    pub fn new(role: Role, content: String) -> Self {
+        Self::from_parts(role, vec![Content::Text(content)])
+    }
+
+    /// Creates a new message from content parts.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The role of the message sender
+    /// * `parts` - The content parts of the message, in order
+    pub fn from_parts(role: Role, parts: impl IntoIterator<Item = Content>) -> Self {
         Self {
             role,
-            content,
+            parts: Arc::new(parts.into_iter().collect()),
             attachments: Vec::new(),
             annotation: Vec::new(),
+            pinned: false,
+            tool_call_id: None,
         }
     }
 
@@ -284,30 +533,121 @@ impl Message {
         Self::new(Role::Tool, content.into())
     }
 
-    /// Adds an attachment URL to the message.
+    /// Creates an assistant message requesting tool calls.
+    ///
+    /// The text, if not empty, precedes the calls.
     ///
     /// # Arguments
     ///
-    /// * `url` - The URL to attach
+    /// * `content` - The text the assistant produced before calling the tools
+    /// * `calls` - The requested tool calls
     ///
-    /// # Panics
+    /// # Example
+    ///
+    /// ```rust
+    /// use ai_types::llm::{Message, Role, tool::ToolCall};
+    ///
+    /// let call = ToolCall::new("call_1", "weather", r#"{"city":"Paris"}"#);
+    /// let request = Message::assistant_tool_calls("Let me check.", [call.clone()]);
+    /// let result = Message::tool_result(&call.id, "Sunny, 24°C");
     ///
-    /// Panics if the URL conversion fails.
+    /// assert_eq!(request.tool_calls().collect::<Vec<_>>(), [&call]);
+    /// assert_eq!(result.role(), Role::Tool);
+    /// assert_eq!(result.tool_call_id(), Some("call_1"));
+    /// ```
+    pub fn assistant_tool_calls(
+        content: impl Into<String>,
+        calls: impl IntoIterator<Item = ToolCall>,
+    ) -> Self {
+        let content = content.into();
+        let text = (!content.is_empty()).then_some(Content::Text(content));
+        Self::from_parts(
+            Role::Assistant,
+            text.into_iter()
+                .chain(calls.into_iter().map(Content::ToolCall)),
+        )
+    }
+
+    /// Creates a tool message answering the tool call `id`.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the answered [`ToolCall`]
+    /// * `content` - The tool's output
+    pub fn tool_result(id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: Some(id.into()),
+            ..Self::tool(content)
+        }
+    }
+
+    /// Pins or unpins the message.
+    ///
+    /// Pinned messages, such as legally required disclosures, are never dropped from the
+    /// context window by trimming or compression strategies.
     #[must_use]
-    pub fn with_attachment<U: TryInto<Url, Error: Debug>>(mut self, url: U) -> Self {
-        self.attachments.push(url.try_into().unwrap());
+    pub const fn pinned(mut self, pinned: bool) -> Self {
+        self.pinned = pinned;
         self
     }
 
-    /// Adds multiple attachment URLs to the message.
+    /// Appends a content part to the message.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ai_types::llm::{Message, message::Content};
+    ///
+    /// let message = Message::user("Transcribe this recording")
+    ///     .with_content(Content::audio_data(vec![0u8; 16], "audio/wav"));
+    /// assert_eq!(message.parts().len(), 2);
+    /// ```
+    #[must_use]
+    pub fn with_content(mut self, content: impl Into<Content>) -> Self {
+        Arc::make_mut(&mut self.parts).push(content.into());
+        self
+    }
+
+    /// Adds an attachment URL to the message.
+    ///
+    /// To attach a URL that still has to be parsed, use
+    /// [`try_with_attachment`](Self::try_with_attachment).
     ///
     /// # Arguments
     ///
-    /// * `urls` - An iterable of URLs to attach
+    /// * `url` - The URL to attach
+    #[must_use]
+    pub fn with_attachment(mut self, url: Url) -> Self {
+        self.attachments.push(url);
+        self
+    }
+
+    /// Adds an attachment URL to the message, converting it first.
     ///
-    /// # Panics
+    /// # Errors
+    ///
+    /// Returns the conversion error if `url` isn't a valid URL.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ai_types::llm::Message;
+    ///
+    /// let message = Message::user("Check out this image")
+    ///     .try_with_attachment("https://example.com/image.jpg")?;
+    /// assert!(Message::user("Broken").try_with_attachment("not a url").is_err());
+    /// # Ok::<(), url::ParseError>(())
+    /// ```
+    pub fn try_with_attachment<U: TryInto<Url>>(mut self, url: U) -> Result<Self, U::Error> {
+        self.attachments.push(url.try_into()?);
+        Ok(self)
+    }
+
+    /// Adds multiple attachment URLs to the message.
+    ///
+    /// # Arguments
     ///
-    /// Panics if any URL conversion fails.
+    /// * `urls` - An iterable of URLs to attach
     ///
     /// # Example
     ///
@@ -316,19 +656,34 @@ impl Message {
     /// use url::Url;
     ///
     /// let urls = [
-    ///     "https://example.com",
-    ///     "https://example.org",
+    ///     "https://example.com".parse::<Url>().unwrap(),
+    ///     "https://example.org".parse::<Url>().unwrap(),
     /// ];
     /// let message = Message::user("Check these links").with_attachments(urls);
     /// ```
     #[must_use]
-    pub fn with_attachments<U: TryInto<Url, Error: Debug>>(
+    pub fn with_attachments(mut self, urls: impl IntoIterator<Item = Url>) -> Self {
+        self.attachments.extend(urls);
+        self
+    }
+
+    /// Adds multiple attachment URLs to the message, converting each first.
+    ///
+    /// The message is only changed if every URL converts.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first conversion error if any of `urls` isn't a valid URL.
+    pub fn try_with_attachments<U: TryInto<Url>>(
         mut self,
         urls: impl IntoIterator<Item = U>,
-    ) -> Self {
-        self.attachments
-            .extend(urls.into_iter().map(|url| url.try_into().unwrap()));
-        self
+    ) -> Result<Self, U::Error> {
+        let urls = urls
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.attachments.extend(urls);
+        Ok(self)
     }
 
     /// Adds an annotation to the message.
@@ -407,6 +762,27 @@ impl Annotation {
     }
 }
 
+/// Serializes shared content parts as a plain list.
+#[cfg(feature = "serde")]
+mod shared {
+    use alloc::{sync::Arc, vec::Vec};
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T: Serialize, S: Serializer>(
+        value: &Arc<Vec<T>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.as_slice().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Arc<Vec<T>>, D::Error> {
+        Vec::deserialize(deserializer).map(Arc::new)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -426,7 +802,7 @@ mod tests {
     fn message_creation() {
         let message = Message::new(Role::User, "Hello".into());
         assert_eq!(message.role, Role::User);
-        assert_eq!(message.content, "Hello");
+        assert_eq!(message.content(), "Hello");
         assert!(message.attachments.is_empty());
         assert!(message.annotation.is_empty());
     }
@@ -435,19 +811,19 @@ mod tests {
     fn message_convenience_constructors() {
         let user_msg = Message::user("User message");
         assert_eq!(user_msg.role, Role::User);
-        assert_eq!(user_msg.content, "User message");
+        assert_eq!(user_msg.content(), "User message");
 
         let assistant_msg = Message::assistant("Assistant message");
         assert_eq!(assistant_msg.role, Role::Assistant);
-        assert_eq!(assistant_msg.content, "Assistant message");
+        assert_eq!(assistant_msg.content(), "Assistant message");
 
         let system_msg = Message::system("System message");
         assert_eq!(system_msg.role, Role::System);
-        assert_eq!(system_msg.content, "System message");
+        assert_eq!(system_msg.content(), "System message");
 
         let tool_msg = Message::tool("Tool message");
         assert_eq!(tool_msg.role, Role::Tool);
-        assert_eq!(tool_msg.content, "Tool message");
+        assert_eq!(tool_msg.content(), "Tool message");
     }
 
     #[test]
@@ -472,6 +848,25 @@ mod tests {
         assert_eq!(message.attachments, urls);
     }
 
+    #[test]
+    fn invalid_attachments_are_errors() {
+        assert!(
+            Message::user("Hello")
+                .try_with_attachment("not a url")
+                .is_err()
+        );
+
+        let message = Message::user("Hello")
+            .try_with_attachments(["https://example.com", "https://example.org"])
+            .unwrap();
+        assert_eq!(message.attachments.len(), 2);
+        assert!(
+            Message::user("Hello")
+                .try_with_attachments(["https://example.com", "::"])
+                .is_err()
+        );
+    }
+
     #[test]
     fn url_annotation() {
         let url = "https://example.com".parse::<Url>().unwrap();
@@ -525,7 +920,7 @@ mod tests {
         let cloned = original.clone();
 
         assert_eq!(original.role, cloned.role);
-        assert_eq!(original.content, cloned.content);
+        assert_eq!(original.content(), cloned.content());
         assert_eq!(original.attachments.len(), cloned.attachments.len());
         assert_eq!(original.annotation.len(), cloned.annotation.len());
     }
@@ -564,6 +959,99 @@ mod tests {
         assert_eq!(message.annotation.len(), 2);
     }
 
+    #[test]
+    fn message_content_parts() {
+        let url = "https://example.com/cat.png".parse::<Url>().unwrap();
+        let message = Message::from_parts(
+            Role::User,
+            [
+                Content::text("Look: "),
+                Content::image_url(url.clone()),
+                Content::text("what is it?"),
+            ],
+        );
+
+        assert_eq!(message.content(), "Look: what is it?");
+        assert_eq!(message.parts()[1], Content::ImageUrl(url));
+        assert_eq!(message.parts()[2].as_text(), Some("what is it?"));
+    }
+
+    #[test]
+    fn message_single_text_part_is_borrowed() {
+        let message = Message::user("Hello");
+        assert_eq!(message.parts(), [Content::text("Hello")]);
+        assert!(matches!(message.content(), Cow::Borrowed("Hello")));
+
+        let message = message.with_content(Content::image_data(vec![1, 2, 3], "image/png"));
+        assert_eq!(message.content(), "Hello");
+        assert_eq!(message.parts().len(), 2);
+    }
+
+    #[test]
+    fn clones_share_parts_until_modified() {
+        let message = Message::user("A long conversation turn");
+        let clone = message.clone();
+        assert!(core::ptr::eq(message.parts(), clone.parts()));
+
+        let extended = clone.with_content(Content::text("more"));
+        assert_eq!(message.parts().len(), 1);
+        assert_eq!(extended.parts().len(), 2);
+    }
+
+    #[test]
+    fn message_pinning() {
+        let message = Message::user("Disclosure");
+        assert!(!message.is_pinned());
+
+        let pinned = message.pinned(true);
+        assert!(pinned.is_pinned());
+        assert!(!pinned.pinned(false).is_pinned());
+    }
+
+    #[test]
+    fn trim_protection_roles() {
+        let protection = TrimProtection::roles([Role::System, Role::Tool]);
+        assert!(protection.protects(&Message::system("System")));
+        assert!(protection.protects(&Message::tool("Output")));
+        assert!(!protection.protects(&Message::assistant("Reply")));
+        assert!(protection.protects(&Message::assistant("Reply").pinned(true)));
+    }
+
+    #[test]
+    fn tool_call_round_trip_messages() {
+        let calls = [
+            ToolCall::new("call_1", "search", r#"{"q":"rust"}"#),
+            ToolCall::new("call_2", "clock", "{}"),
+        ];
+        let request = Message::assistant_tool_calls("", calls.clone());
+        assert_eq!(request.role(), Role::Assistant);
+        assert_eq!(request.parts().len(), 2);
+        assert_eq!(request.content(), "");
+        assert!(request.tool_calls().eq(calls.iter()));
+        assert_eq!(request.tool_call_id(), None);
+
+        let result = Message::tool_result("call_2", "12:00");
+        assert_eq!(result.role(), Role::Tool);
+        assert_eq!(result.content(), "12:00");
+        assert_eq!(result.tool_call_id(), Some("call_2"));
+        assert_eq!(result.tool_calls().count(), 0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn tool_messages_serde_roundtrip() {
+        let messages = [
+            Message::assistant_tool_calls("Checking", [ToolCall::new("id", "tool", "{}")]),
+            Message::tool_result("id", "done"),
+        ];
+        let json = serde_json::to_string(&messages).unwrap();
+        let decoded: Vec<Message> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded[0].tool_calls().count(), 1);
+        assert_eq!(decoded[0].content(), "Checking");
+        assert_eq!(decoded[1].tool_call_id(), Some("id"));
+    }
+
     #[test]
     fn url_annotation_constructor() {
         let url = "https://example.com".parse::<Url>().unwrap();
@@ -0,0 +1,248 @@
+//! Metadata of a language model's response.
+//!
+//! Besides text, providers report details about how a response was produced: the exact
+//! model version that served it, the fingerprint of the backend configuration, why
+//! generation stopped, and the provider's identifier of the request. A
+//! [`ResponseMetadata`] record collects them, for debugging, reproducibility and audit
+//! logging.
+//!
+//! Providers report metadata through
+//! [`StreamEvent::Metadata`](crate::llm::event::StreamEvent::Metadata) events, emitted by
+//! [`LanguageModel::respond_events`](crate::LanguageModel::respond_events) before the
+//! stream completes. Details known early, such as the request identifier, may be
+//! reported in an earlier event than details known at the end, such as the finish reason;
+//! [`ResponseMetadata::update`] folds them into one record.
+//!
+//! The [`FinishReason`] tells whether the model ended its answer on its own or was cut
+//! off, by the token limit, a stop sequence, a tool call or the provider's content filter,
+//! so applications can warn users about truncated answers.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{
+//!     LanguageModel, Request,
+//!     event::StreamEvent,
+//!     metadata::{FinishReason, ResponseMetadata},
+//! };
+//! use futures_lite::{StreamExt, pin};
+//!
+//! async fn audit(model: impl LanguageModel, request: Request) -> ai_types::Result {
+//!     let events = model.respond_events(request);
+//!     pin!(events);
+//!
+//!     let mut text = String::new();
+//!     let mut metadata = ResponseMetadata::new();
+//!     while let Some(event) = events.try_next().await? {
+//!         match event {
+//!             StreamEvent::Text(chunk) => text.push_str(&chunk),
+//!             StreamEvent::Metadata(reported) => metadata.update(reported),
+//!             _ => {}
+//!         }
+//!     }
+//!     if metadata.finish_reason == Some(FinishReason::Length) {
+//!         text.push_str("\n\n(The answer was cut off.)");
+//!     }
+//!     println!(
+//!         "{} answered request {:?} with fingerprint {:?}",
+//!         metadata.model.as_deref().unwrap_or("unknown model"),
+//!         metadata.request_id,
+//!         metadata.system_fingerprint,
+//!     );
+//!     Ok(text)
+//! }
+//! ```
+
+use alloc::string::String;
+use core::fmt;
+
+/// Details about how a response was produced, as reported by the provider.
+///
+/// Every field is optional, since providers report different subsets.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[non_exhaustive]
+pub struct ResponseMetadata {
+    /// The identifier of the model that actually served the response, such as a dated
+    /// snapshot of the requested model.
+    pub model: Option<String>,
+    /// The fingerprint of the backend configuration that served the response.
+    ///
+    /// Responses to the same request with the same seed are only expected to be
+    /// reproducible while the fingerprint stays the same.
+    pub system_fingerprint: Option<String>,
+    /// Why generation stopped.
+    pub finish_reason: Option<FinishReason>,
+    /// The provider's identifier of the request, for support tickets and log correlation.
+    pub request_id: Option<String>,
+}
+
+impl ResponseMetadata {
+    /// Creates an empty record.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            model: None,
+            system_fingerprint: None,
+            finish_reason: None,
+            request_id: None,
+        }
+    }
+
+    /// Sets the identifier of the model that served the response.
+    #[must_use]
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Sets the fingerprint of the backend configuration.
+    #[must_use]
+    pub fn with_system_fingerprint(mut self, fingerprint: impl Into<String>) -> Self {
+        self.system_fingerprint = Some(fingerprint.into());
+        self
+    }
+
+    /// Sets why generation stopped.
+    #[must_use]
+    pub fn with_finish_reason(mut self, reason: FinishReason) -> Self {
+        self.finish_reason = Some(reason);
+        self
+    }
+
+    /// Sets the provider's identifier of the request.
+    #[must_use]
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    /// Adds the fields reported in a later event, replacing the ones it sets.
+    pub fn update(&mut self, reported: Self) {
+        if reported.model.is_some() {
+            self.model = reported.model;
+        }
+        if reported.system_fingerprint.is_some() {
+            self.system_fingerprint = reported.system_fingerprint;
+        }
+        if reported.finish_reason.is_some() {
+            self.finish_reason = reported.finish_reason;
+        }
+        if reported.request_id.is_some() {
+            self.request_id = reported.request_id;
+        }
+    }
+}
+
+/// Why a model stopped generating.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum FinishReason {
+    /// The model ended its answer on its own.
+    Stop,
+    /// The answer reached the token limit, such as
+    /// [`Parameters::max_tokens`](crate::llm::model::Parameters::max_tokens), and is
+    /// truncated.
+    Length,
+    /// The answer reached one of the request's stop sequences.
+    StopSequence,
+    /// The model stopped to call tools.
+    ToolCalls,
+    /// The provider's content filter withheld the rest of the answer.
+    ContentFilter,
+    /// A reason this crate doesn't know, as reported by the provider.
+    Other(String),
+}
+
+impl FinishReason {
+    /// Parses the finish reason reported by a provider.
+    ///
+    /// Understands the names used by common APIs, such as `length` and `max_tokens` for
+    /// [`Length`](Self::Length) or `tool_calls` and `tool_use` for
+    /// [`ToolCalls`](Self::ToolCalls). Unknown reasons become [`Other`](Self::Other).
+    #[must_use]
+    pub fn parse(reason: &str) -> Self {
+        match reason.to_ascii_lowercase().as_str() {
+            "stop" | "end_turn" | "eos" | "finish_reason_stop" => Self::Stop,
+            "length" | "max_tokens" | "max_output_tokens" | "model_length" => Self::Length,
+            "stop_sequence" => Self::StopSequence,
+            "tool_calls" | "tool_use" | "function_call" => Self::ToolCalls,
+            "content_filter" | "safety" | "recitation" | "blocklist" | "prohibited_content" => {
+                Self::ContentFilter
+            }
+            _ => Self::Other(reason.into()),
+        }
+    }
+
+    /// Returns whether the answer was cut off before the model finished it, by the token
+    /// limit or the content filter.
+    #[must_use]
+    pub const fn is_truncated(&self) -> bool {
+        matches!(self, Self::Length | Self::ContentFilter)
+    }
+
+    /// Returns the reason's name, such as `length`.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Stop => "stop",
+            Self::Length => "length",
+            Self::StopSequence => "stop_sequence",
+            Self::ToolCalls => "tool_calls",
+            Self::ContentFilter => "content_filter",
+            Self::Other(reason) => reason,
+        }
+    }
+}
+
+impl fmt::Display for FinishReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn later_reports_fill_in_and_replace_fields() {
+        let mut metadata = ResponseMetadata::new()
+            .with_model("model-2024-05-13")
+            .with_request_id("req-1");
+        metadata.update(
+            ResponseMetadata::new()
+                .with_finish_reason(FinishReason::Stop)
+                .with_system_fingerprint("fp_44709d6fcb")
+                .with_request_id("req-2"),
+        );
+
+        assert_eq!(
+            metadata,
+            ResponseMetadata::new()
+                .with_model("model-2024-05-13")
+                .with_system_fingerprint("fp_44709d6fcb")
+                .with_finish_reason(FinishReason::Stop)
+                .with_request_id("req-2")
+        );
+    }
+
+    #[test]
+    fn parses_provider_finish_reasons() {
+        assert_eq!(FinishReason::parse("end_turn"), FinishReason::Stop);
+        assert_eq!(FinishReason::parse("MAX_TOKENS"), FinishReason::Length);
+        assert_eq!(FinishReason::parse("tool_use"), FinishReason::ToolCalls);
+        assert_eq!(FinishReason::parse("SAFETY"), FinishReason::ContentFilter);
+        assert_eq!(
+            FinishReason::parse("pause_turn"),
+            FinishReason::Other("pause_turn".into())
+        );
+        assert!(FinishReason::Length.is_truncated());
+        assert!(!FinishReason::ToolCalls.is_truncated());
+        assert_eq!(FinishReason::parse("length").to_string(), "length");
+    }
+}
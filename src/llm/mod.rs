@@ -250,7 +250,7 @@
 //! use url::Url;
 //!
 //! let message = Message::user("Check this documentation")
-//!     .with_attachment("file:///path/to/doc.pdf")
+//!     .with_attachment("file:///path/to/doc.pdf".parse::<Url>().unwrap())
 //!     .with_annotation(
 //!         Annotation::url(
 //!             "https://docs.rs/ai-types",
@@ -261,35 +261,104 @@
 //!         )
 //!    );
 //! ```
+/// Tool-calling agent loop with safety limits.
+pub mod agent;
+/// Replacing personal data in conversations with stable placeholders.
+pub mod anonymize;
+/// Reusable prompt fragments referenced by content hash.
+pub mod asset;
 /// Assistant module for managing assistant-related functionality.
 pub mod assistant;
+/// Type-erased language models.
+pub mod boxed;
+/// Token budget planning between prompt and completion.
+pub mod budget;
+/// Capability matrices of models across providers.
+pub mod capability;
+/// Structured generation that may ask clarifying questions first.
+pub mod clarify;
+/// Comparing models on the same request.
+pub mod compare;
+/// Fitting message histories into a model's context window.
+pub mod context;
+/// Conversations owning their message history.
+pub mod conversation;
+/// Removing repeated blocks from prompts.
+pub mod dedup;
+/// Diffing requests to see how middleware changed them.
+pub mod diff;
+/// Comparing documents in a typed matrix with cited findings.
+pub mod documents;
+/// Injecting the current date, time, and application facts into prompts.
+pub mod enrich;
+/// Querying several models and keeping the best answer.
+pub mod ensemble;
+/// Typed streaming events such as tool calls.
+pub mod event;
+/// Keeping persisted structured outputs readable as their types change.
+pub mod evolution;
+/// Lenient extraction of JSON from model responses.
+pub mod extract;
+/// Log probabilities of generated tokens.
+pub mod logprobs;
 /// Message types and conversation handling.
 pub mod message;
+/// Response metadata such as the model version and finish reason.
+pub mod metadata;
 /// Model profiles and capabilities.
 pub mod model;
-mod provider;
+/// Fixing message lists to satisfy common provider requirements.
+pub mod normalize;
+/// Streaming structured output.
+pub mod partial;
+/// Testing what a model can actually do.
+pub mod probe;
+/// Prompt templates rendering variables, conditional sections and few-shot examples into messages.
+pub mod prompt;
+pub(crate) mod provider;
+/// Generating assessment questions with answer keys.
+pub mod questionnaire;
+/// Requests bundling messages, tools, and parameters.
+pub mod request;
+/// Routing each request to one of several models.
+pub mod router;
+/// Streaming text responses and stream adapters.
+pub mod stream;
+/// Token counting for context-window budgeting.
+pub mod token;
 /// Tool system for function calling.
 pub mod tool;
-use crate::llm::{model::Parameters, tool::Tools};
-use alloc::{boxed::Box, string::String, sync::Arc};
-use async_stream::try_stream;
+/// Render-ready conversation state for chat interfaces.
+pub mod ui;
+/// Token usage reporting and cost tracking.
+pub mod usage;
+/// Validation of tool-call arguments against their schema.
+pub mod validation;
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+pub use boxed::{BoxedError, BoxedLanguageModel};
+pub use conversation::{Conversation, ConversationTemplate};
 use core::future::Future;
+use event::{StreamEvent, text_events};
 use futures_core::Stream;
 use futures_lite::{StreamExt, pin};
 pub use message::{Annotation, Message, Role, UrlAnnotation};
 pub use provider::LanguageModelProvider;
-use schemars::{JsonSchema, schema_for};
+pub use request::{Request, ResponseFormat};
+use schemars::{JsonSchema, Schema, schema_for};
 use serde::de::DeserializeOwned;
+pub use stream::TextStream;
 pub use tool::Tool;
 
-use crate::llm::{model::Profile, tool::json};
-
-/// Creates a two-message conversation with system and user prompts.
-///
-/// Returns an array containing a [`Message`] with [`Role::System`] and a [`Message`] with [`Role::User`].
-fn oneshot(system: impl Into<String>, user: impl Into<String>) -> [Message; 2] {
-    [Message::system(system.into()), Message::user(user.into())]
-}
+use crate::llm::{
+    model::{Ability, Profile},
+    tool::json,
+};
 
 /// Language models for text generation and conversation.
 ///
@@ -299,28 +368,109 @@ pub trait LanguageModel: Sized + Send + Sync + 'static {
     type Error: core::error::Error + Send + Sync + 'static;
 
     /// Generates streaming response to conversation.
-    fn respond(
+    ///
+    /// Returns a [`TextStream`], which can be consumed chunk by chunk or awaited as a whole.
+    fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send;
+
+    /// Generates a streaming response as typed [`StreamEvent`]s.
+    ///
+    /// Unlike [`respond`](LanguageModel::respond), this surfaces the tool calls the model
+    /// makes mid-stream as [`StreamEvent::ToolCall`], so applications can execute them
+    /// themselves. The default implementation wraps [`respond`](LanguageModel::respond)
+    /// and only emits text.
+    fn respond_events(
         &self,
-        messages: &[Message],
-        tools: &mut Tools,
-        parameters: &Parameters,
-    ) -> impl Stream<Item = Result<String, Self::Error>> + Send;
+        request: Request,
+    ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+        text_events(self.respond(request))
+    }
+
+    /// Generates [`n`](model::Parameters::n) candidate responses, for best-of-N reranking
+    /// or self-consistency sampling. The response at index `i` of the returned list is
+    /// candidate `i`.
+    ///
+    /// The default implementation sends the request `n` times, each asking for one
+    /// response. A request with a [seed](model::Parameters::seed) gets a different seed
+    /// per candidate, counting up from its own, so the candidates differ. Providers
+    /// generating several completions natively can override it; boxed models, routers and
+    /// middleware passing requests through forward it, and caches never serve candidates.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ai_types::llm::{LanguageModel, Request, model::Parameters};
+    ///
+    /// async fn vote(model: impl LanguageModel) -> ai_types::Result<Vec<String>> {
+    ///     let request = Request::oneshot("Answer with a number.", "What is 17 * 23?")
+    ///         .with_parameters(Parameters::default().n(5).temperature(1.0));
+    ///     let mut answers = Vec::new();
+    ///     for candidate in model.respond_n(request) {
+    ///         answers.push(candidate.await?);
+    ///     }
+    ///     Ok(answers)
+    /// }
+    /// ```
+    fn respond_n(&self, request: Request) -> Vec<impl TextStream<Error = Self::Error> + Send> {
+        respond_n(self, request)
+    }
 
     /// Generates structured output conforming to JSON schema.
+    ///
+    /// The request's [`ResponseFormat`] is set to the schema of `T`. Models without
+    /// [`Ability::StructuredOutput`](model::Ability::StructuredOutput) are also given the
+    /// schema in a system message asking for JSON.
+    ///
+    /// Responses are parsed leniently with [`extract::parse_json`]. A response that still
+    /// does not parse is sent back to the model with the error, up to
+    /// [`Request::parse_retries`] times. Each retry derives its own
+    /// [idempotency key](Request::idempotency_key) from the request's.
     fn generate<T: JsonSchema + DeserializeOwned>(
         &self,
-        messages: &[Message],
-        tools: &mut Tools,
-        parameters: &Parameters,
+        request: Request,
     ) -> impl Future<Output = crate::Result<T>> + Send {
-        generate(self, messages, tools, parameters)
+        generate(self, request)
+    }
+
+    /// Generates structured output, yielding partial results as the response streams in.
+    ///
+    /// The request is prepared as by [`generate`](LanguageModel::generate). Partial results
+    /// are yielded whenever the JSON received so far deserializes into `T`, and the last
+    /// item is the complete result, or an error if the complete response does not parse.
+    /// Parse failures are not retried.
+    ///
+    /// See the [`partial`] module for an example.
+    fn generate_stream<T: JsonSchema + DeserializeOwned + Send>(
+        &self,
+        request: Request,
+    ) -> impl Stream<Item = crate::Result<partial::Partial<T>>> + Send {
+        partial::generate_stream(self, request)
+    }
+
+    /// Generates structured output, letting the model ask clarifying questions first.
+    ///
+    /// Instead of `T`, the model may reply with a [`NeedClarification`](clarify::NeedClarification).
+    /// Its question is passed to `ask`, and the answer `ask` returns is added to the
+    /// conversation before the model tries again. Return an error from `ask` to give up.
+    ///
+    /// See the [`clarify`] module for an example.
+    fn clarify_then_generate<T, F, Fut>(
+        &self,
+        request: Request,
+        ask: F,
+    ) -> impl Future<Output = crate::Result<T>> + Send
+    where
+        T: JsonSchema + DeserializeOwned,
+        F: FnMut(clarify::NeedClarification) -> Fut + Send,
+        Fut: Future<Output = crate::Result<String>> + Send,
+    {
+        clarify::clarify_then_generate(self, request, ask)
     }
 
     /// Completes given text prefix.
-    fn complete(&self, prefix: &str) -> impl Stream<Item = Result<String, Self::Error>> + Send;
+    fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send;
 
     /// Summarizes text.
-    fn summarize(&self, text: &str) -> impl Stream<Item = Result<String, Self::Error>> + Send {
+    fn summarize(&self, text: &str) -> impl TextStream<Error = Self::Error> + Send {
         summarize(self, text)
     }
 
@@ -332,10 +482,44 @@ pub trait LanguageModel: Sized + Send + Sync + 'static {
         categorize(self, text)
     }
 
+    /// Generates up to `count` assessment questions from a specification, such as a job
+    /// description or a syllabus.
+    ///
+    /// Questions are yielded as soon as the model has written them. A question the model
+    /// wrote incorrectly is yielded as an error, and the stream continues.
+    ///
+    /// See the [`questionnaire`] module for an example.
+    fn generate_questionnaire(
+        &self,
+        spec: &str,
+        count: usize,
+    ) -> impl Stream<Item = crate::Result<questionnaire::Question>> + Send {
+        questionnaire::generate_questionnaire(self, spec, count)
+    }
+
+    /// Compares `documents` in a matrix with one row of `T` per document, listing their
+    /// similarities and differences with citations.
+    ///
+    /// Documents too long to be compared side by side are chunked and summarized first.
+    /// See the [`documents`] module for an example.
+    fn compare_documents<T: JsonSchema + DeserializeOwned>(
+        &self,
+        documents: &[&str],
+    ) -> impl Future<Output = crate::Result<documents::DocumentComparison<T>>> + Send {
+        documents::compare_documents(self, documents)
+    }
+
     /// Returns model profile and capabilities.
     ///
     /// See [`Profile`] for details on model metadata.
     fn profile(&self) -> Profile;
+
+    /// Erases the type of this model, so it can be stored alongside models of other types.
+    ///
+    /// See the [`boxed`] module for details.
+    fn boxed(self) -> BoxedLanguageModel {
+        BoxedLanguageModel::new(self)
+    }
 }
 
 macro_rules! impl_language_model {
@@ -344,35 +528,56 @@ macro_rules! impl_language_model {
             impl<T: LanguageModel> LanguageModel for $name<T> {
                 type Error = T::Error;
 
-                fn respond(
+                fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+                    T::respond(self, request)
+                }
+
+                fn respond_events(
                     &self,
-                    messages: &[Message],
-                    tools: &mut Tools,
-                    parameters: &Parameters,
-                ) -> impl Stream<Item = Result<String, Self::Error>> + Send {
-                    T::respond(self, messages, tools, parameters)
+                    request: Request,
+                ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+                    T::respond_events(self, request)
+                }
+
+                fn respond_n(
+                    &self,
+                    request: Request,
+                ) -> Vec<impl TextStream<Error = Self::Error> + Send> {
+                    T::respond_n(self, request)
                 }
 
                 fn generate<U: JsonSchema + DeserializeOwned>(
                     &self,
-                    messages: &[Message],
-                    tools: &mut Tools,
-                    parameters: &Parameters,
+                    request: Request,
                 ) -> impl Future<Output = crate::Result<U>> + Send {
-                    T::generate(self, messages, tools, parameters)
+                    T::generate(self, request)
                 }
 
-                fn complete(
+                fn generate_stream<U: JsonSchema + DeserializeOwned + Send>(
                     &self,
-                    prefix: &str,
-                ) -> impl Stream<Item = Result<String, Self::Error>> + Send {
-                    T::complete(self, prefix)
+                    request: Request,
+                ) -> impl Stream<Item = crate::Result<partial::Partial<U>>> + Send {
+                    T::generate_stream(self, request)
                 }
 
-                fn summarize(
+                fn clarify_then_generate<U, F, Fut>(
                     &self,
-                    text: &str,
-                ) -> impl Stream<Item = Result<String, Self::Error>> + Send {
+                    request: Request,
+                    ask: F,
+                ) -> impl Future<Output = crate::Result<U>> + Send
+                where
+                    U: JsonSchema + DeserializeOwned,
+                    F: FnMut(clarify::NeedClarification) -> Fut + Send,
+                    Fut: Future<Output = crate::Result<String>> + Send,
+                {
+                    T::clarify_then_generate(self, request, ask)
+                }
+
+                fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+                    T::complete(self, prefix)
+                }
+
+                fn summarize(&self, text: &str) -> impl TextStream<Error = Self::Error> + Send {
                     T::summarize(self, text)
                 }
 
@@ -383,6 +588,21 @@ macro_rules! impl_language_model {
                     T::categorize(self, text)
                 }
 
+                fn generate_questionnaire(
+                    &self,
+                    spec: &str,
+                    count: usize,
+                ) -> impl Stream<Item = crate::Result<questionnaire::Question>> + Send {
+                    T::generate_questionnaire(self, spec, count)
+                }
+
+                fn compare_documents<U: JsonSchema + DeserializeOwned>(
+                    &self,
+                    documents: &[&str],
+                ) -> impl Future<Output = crate::Result<documents::DocumentComparison<U>>> + Send {
+                    T::compare_documents(self, documents)
+                }
+
                 fn profile(&self) -> Profile {
                     T::profile(self)
                 }
@@ -391,7 +611,7 @@ macro_rules! impl_language_model {
     };
 }
 
-mod prompts;
+pub(crate) mod prompts;
 
 impl_language_model!(Arc, Box);
 
@@ -410,50 +630,136 @@ where
         .try_fold(String::new(), |acc, chunk| Ok(acc + &chunk))
         .await
 }
+
+/// Prepares `request` for a response conforming to `schema`.
+fn structured<M: LanguageModel>(model: &M, mut request: Request, schema: Schema) -> Request {
+    // Models without native structured outputs are asked for JSON in the prompt.
+    if !model
+        .profile()
+        .abilities
+        .contains(&Ability::StructuredOutput)
+    {
+        let prompt = prompts::generate(&json(&schema));
+        request.messages.push(Message::system(prompt));
+    }
+    request.response_format = ResponseFormat::JsonSchema(schema);
+    request
+}
+
 async fn generate<T: JsonSchema + DeserializeOwned, M: LanguageModel>(
     model: &M,
-    messages: &[Message],
-    tools: &mut Tools,
-    parameters: &Parameters,
+    request: Request,
 ) -> crate::Result<T> {
-    let schema = json(&schema_for!(T));
-
-    let prompt = prompts::generate(&schema);
-    let mut messages = messages.to_vec();
-    messages.push(Message::system(prompt));
-    let stream = model.respond(&messages, tools, parameters);
-    let response = try_collect(stream).await?;
-
-    let value: T = serde_json::from_str(&response)?;
-
-    Ok(value)
+    generate_with_schema(model, request, schema_for!(T)).await
 }
 
-fn summarize<M: LanguageModel>(
+/// Generates a response conforming to `schema`, parsed as `T`.
+async fn generate_with_schema<T: DeserializeOwned, M: LanguageModel>(
     model: &M,
-    text: &str,
-) -> impl Stream<Item = Result<String, M::Error>> + Send {
-    try_stream! {
-        let messages = oneshot("Summarize text:", text);
-        let mut tools = Tools::new();
-        let parameters = Parameters::default();
-        let stream=model.respond(&messages, &mut tools, &parameters);
-        pin!(stream);
-        while let Some(chunk) = stream.try_next().await? {
-            yield chunk;
+    request: Request,
+    schema: Schema,
+) -> crate::Result<T> {
+    let mut request = structured(model, request, schema);
+    let key = request.idempotency_key.clone();
+    let mut retries = request.parse_retries;
+    loop {
+        let response = try_collect(model.respond(request.clone())).await?;
+        match extract::parse_json(&response) {
+            Ok(value) => return Ok(value),
+            Err(error) if retries > 0 => {
+                retries -= 1;
+                request.messages.push(Message::assistant(response));
+                request
+                    .messages
+                    .push(Message::user(prompts::fix_json(&error.to_string())));
+                // Each retry is a new request, which providers must not deduplicate.
+                let attempt = request.parse_retries - retries;
+                request.idempotency_key = key
+                    .as_ref()
+                    .map(|key| format!("{key}:parse-retry-{attempt}"));
+            }
+            Err(error) => return Err(error.into()),
         }
     }
 }
 
+fn respond_n<M: LanguageModel>(
+    model: &M,
+    mut request: Request,
+) -> Vec<impl TextStream<Error = M::Error> + Send> {
+    let n = request.parameters.n.take().unwrap_or(1).max(1);
+    let seed = request.parameters.seed;
+    (0..n)
+        .map(|index| {
+            let mut candidate = request.clone();
+            candidate.parameters.seed = seed.map(|seed| seed.wrapping_add(index));
+            model.respond(candidate)
+        })
+        .collect()
+}
+
+fn summarize<M: LanguageModel>(model: &M, text: &str) -> impl TextStream<Error = M::Error> + Send {
+    model.respond(Request::oneshot("Summarize text:", text))
+}
+
 async fn categorize<T: JsonSchema + DeserializeOwned, M: LanguageModel>(
     model: &M,
     text: &str,
 ) -> crate::Result<T> {
     model
-        .generate(
-            &oneshot("Categorize text by provided schema", text),
-            &mut Tools::new(),
-            &Parameters::default(),
-        )
+        .generate(Request::oneshot("Categorize text by provided schema", text))
         .await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::MockLanguageModel;
+    use model::Parameters;
+
+    #[tokio::test]
+    async fn respond_n_varies_the_seed_of_each_candidate() {
+        let model = MockLanguageModel::new();
+        let request =
+            Request::oneshot("", "Hi").with_parameters(Parameters::default().n(3).seed(7));
+        for candidate in model.respond_n(request) {
+            candidate.await.unwrap();
+        }
+        let parameters: Vec<_> = model
+            .requests()
+            .iter()
+            .map(|request| (request.parameters.seed, request.parameters.n))
+            .collect();
+        assert_eq!(
+            parameters,
+            [(Some(7), None), (Some(8), None), (Some(9), None)]
+        );
+
+        let single = model.respond_n(Request::oneshot("", "Hi"));
+        assert_eq!(single.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn generate_retries_with_fresh_idempotency_keys() {
+        let model = MockLanguageModel::new().with_replies(["not json", "still not", "42"]);
+        let request = Request::oneshot("", "Pick a number")
+            .with_idempotency_key("key")
+            .with_parse_retries(2);
+        let number: u32 = model.generate(request).await.unwrap();
+        assert_eq!(number, 42);
+
+        let keys: Vec<_> = model
+            .requests()
+            .into_iter()
+            .map(|request| request.idempotency_key)
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                Some("key".into()),
+                Some("key:parse-retry-1".into()),
+                Some("key:parse-retry-2".into()),
+            ]
+        );
+    }
+}
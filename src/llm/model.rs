@@ -12,14 +12,14 @@
 //!
 //! let mut pricing = Pricing::default();
 //!
-//! pricing.prompt = 0.01; // $0.01 per 1K prompt tokens
-//! pricing.completion = 0.03; // $0.03 per 1K completion tokens
+//! pricing.prompt = 0.00001; // $10 per 1M prompt tokens
+//! pricing.completion = 0.00003; // $30 per 1M completion tokens
 //! pricing.request = 0.01; // $0.01 per request
 //! pricing.image = 0.1; // $0.1 per image
 //! pricing.web_search = 0.05; // $0.05 per web search
-//! pricing.internal_reasoning = 0.003; // $0.003 per internal reasoning
-//! pricing.input_cache_read = 0.0005; // $0.0005 per input cache read
-//! pricing.input_cache_write = 0.001; // $0.001 per input cache write
+//! pricing.internal_reasoning = 0.00003; // $30 per 1M reasoning tokens
+//! pricing.input_cache_read = 0.0000025; // $2.50 per 1M cached tokens read
+//! pricing.input_cache_write = 0.0000125; // $12.50 per 1M cached tokens written
 //!
 //! let profile = Profile::new("gpt-4", "GPT-4 model", 8192)
 //!     .with_ability(Ability::ToolUse)
@@ -40,14 +40,17 @@
 //! ```
 
 use alloc::{string::String, vec::Vec};
-
-use crate::llm::tool::Tools;
+use core::{fmt, ops::RangeInclusive};
 
 /// Parameters for configuring the behavior of a language model.
 ///
 /// This struct contains various parameters that can be used to control
 /// how a language model generates responses. All parameters are optional
-/// and use the builder pattern for easy configuration.
+/// and use the builder pattern for easy configuration. The default leaves every
+/// parameter unset, so the provider's own defaults apply.
+///
+/// The setters accept any value. Use [`Parameters::builder`] or [`Parameters::validate`]
+/// to reject out-of-range values before they reach a provider.
 ///
 /// # Examples
 ///
@@ -60,7 +63,9 @@ use crate::llm::tool::Tools;
 ///     .max_tokens(1000)
 ///     .seed(42);
 /// ```
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Parameters {
     /// Sampling temperature.
     ///
@@ -104,6 +109,11 @@ pub struct Parameters {
     ///
     /// Limits the length of the generated response.
     pub max_tokens: Option<u32>,
+    /// Number of candidate completions to generate.
+    ///
+    /// Read by [`LanguageModel::respond_n`](crate::LanguageModel::respond_n); other methods
+    /// generate one completion.
+    pub n: Option<u32>,
     /// Biases for specific logits.
     ///
     /// Each tuple contains a token string and its bias value.
@@ -120,10 +130,6 @@ pub struct Parameters {
     ///
     /// Generation stops when any of these strings are encountered.
     pub stop: Option<Vec<String>>,
-    /// Tools available to the model.
-    ///
-    /// Defines what external functions the model can call.
-    pub tools: Tools,
     /// Tool choices available to the model.
     ///
     /// Specifies which tools the model is allowed to use.
@@ -165,13 +171,249 @@ impl_with_methods! {
         top_a: f32,
         seed: u32,
         max_tokens: u32,
+        n: u32,
         logit_bias: Vec<(String, f32)>,
         logprobs: bool,
         top_logprobs: u8,
         stop: Vec<String>,
+        tool_choice: Vec<String>,
+    }
+}
+
+impl Parameters {
+    /// Creates a builder that validates the parameters when built.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ai_types::llm::model::{ParameterError, Parameters};
+    ///
+    /// let params = Parameters::builder().temperature(0.7).top_p(0.9).build()?;
+    /// assert_eq!(params.temperature, Some(0.7));
+    ///
+    /// let invalid = Parameters::builder().temperature(3.0).build();
+    /// assert!(matches!(invalid, Err(ParameterError::OutOfRange { name: "temperature", .. })));
+    /// # Ok::<(), ParameterError>(())
+    /// ```
+    #[must_use]
+    pub fn builder() -> ParametersBuilder {
+        ParametersBuilder::default()
+    }
+
+    /// Returns these parameters, taking the ones left unset from `defaults`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ai_types::llm::model::Parameters;
+    ///
+    /// let defaults = Parameters::default().temperature(0.2).max_tokens(512);
+    /// let params = Parameters::default().temperature(0.9).or(defaults);
+    /// assert_eq!(params.temperature, Some(0.9));
+    /// assert_eq!(params.max_tokens, Some(512));
+    /// ```
+    #[must_use]
+    pub fn or(self, defaults: Self) -> Self {
+        Self {
+            temperature: self.temperature.or(defaults.temperature),
+            top_p: self.top_p.or(defaults.top_p),
+            top_k: self.top_k.or(defaults.top_k),
+            frequency_penalty: self.frequency_penalty.or(defaults.frequency_penalty),
+            presence_penalty: self.presence_penalty.or(defaults.presence_penalty),
+            repetition_penalty: self.repetition_penalty.or(defaults.repetition_penalty),
+            min_p: self.min_p.or(defaults.min_p),
+            top_a: self.top_a.or(defaults.top_a),
+            seed: self.seed.or(defaults.seed),
+            max_tokens: self.max_tokens.or(defaults.max_tokens),
+            n: self.n.or(defaults.n),
+            logit_bias: self.logit_bias.or(defaults.logit_bias),
+            logprobs: self.logprobs.or(defaults.logprobs),
+            top_logprobs: self.top_logprobs.or(defaults.top_logprobs),
+            stop: self.stop.or(defaults.stop),
+            tool_choice: self.tool_choice.or(defaults.tool_choice),
+        }
+    }
+
+    /// Checks that every parameter is within its valid range and that the parameters
+    /// are consistent with each other.
+    ///
+    /// Ranges follow the widest ones accepted by major providers:
+    ///
+    /// | Parameter | Range |
+    /// |-----------|-------|
+    /// | `temperature` | 0.0 to 2.0 |
+    /// | `top_p`, `min_p`, `top_a` | 0.0 to 1.0 |
+    /// | `frequency_penalty`, `presence_penalty` | -2.0 to 2.0 |
+    /// | `repetition_penalty` | above 0.0, up to 2.0 |
+    /// | `logit_bias` values | -100.0 to 100.0 |
+    /// | `top_logprobs` | 0 to 20 |
+    ///
+    /// `top_k`, `max_tokens` and `n` must be at least 1, stop sequences must not be empty,
+    /// and `top_logprobs` requires `logprobs` to be enabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first invalid parameter found.
+    pub fn validate(&self) -> Result<(), ParameterError> {
+        check_range("temperature", self.temperature, 0.0..=2.0)?;
+        check_range("top_p", self.top_p, 0.0..=1.0)?;
+        check_range("min_p", self.min_p, 0.0..=1.0)?;
+        check_range("top_a", self.top_a, 0.0..=1.0)?;
+        check_range("frequency_penalty", self.frequency_penalty, -2.0..=2.0)?;
+        check_range("presence_penalty", self.presence_penalty, -2.0..=2.0)?;
+        check_range("repetition_penalty", self.repetition_penalty, 0.0..=2.0)?;
+        if self.repetition_penalty == Some(0.0) {
+            return Err(ParameterError::Invalid {
+                name: "repetition_penalty",
+                reason: "must be above 0",
+            });
+        }
+        for (_, bias) in self.logit_bias.iter().flatten() {
+            check_range("logit_bias", Some(*bias), -100.0..=100.0)?;
+        }
+        if self.top_k == Some(0) {
+            return Err(ParameterError::Invalid {
+                name: "top_k",
+                reason: "must be at least 1",
+            });
+        }
+        if self.max_tokens == Some(0) {
+            return Err(ParameterError::Invalid {
+                name: "max_tokens",
+                reason: "must be at least 1",
+            });
+        }
+        if self.n == Some(0) {
+            return Err(ParameterError::Invalid {
+                name: "n",
+                reason: "must be at least 1",
+            });
+        }
+        if let Some(top_logprobs) = self.top_logprobs {
+            check_range("top_logprobs", Some(f32::from(top_logprobs)), 0.0..=20.0)?;
+            if self.logprobs != Some(true) {
+                return Err(ParameterError::Invalid {
+                    name: "top_logprobs",
+                    reason: "requires `logprobs` to be enabled",
+                });
+            }
+        }
+        if self.stop.iter().flatten().any(String::is_empty) {
+            return Err(ParameterError::Invalid {
+                name: "stop",
+                reason: "stop sequences must not be empty",
+            });
+        }
+        Ok(())
+    }
+}
+
+fn check_range(
+    name: &'static str,
+    value: Option<f32>,
+    range: RangeInclusive<f32>,
+) -> Result<(), ParameterError> {
+    match value {
+        Some(value) if !range.contains(&value) => {
+            Err(ParameterError::OutOfRange { name, value, range })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// A builder for [`Parameters`] that validates them when built.
+///
+/// Created by [`Parameters::builder`]. It has the same setters as [`Parameters`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParametersBuilder {
+    parameters: Parameters,
+}
+
+macro_rules! impl_builder_methods {
+    ($($field:ident : $field_ty:ty),* $(,)?) => {
+        impl ParametersBuilder {
+            $(
+                #[doc = concat!("Sets [`Parameters::", stringify!($field), "`].")]
+                #[must_use]
+                pub fn $field(mut self, value: $field_ty) -> Self {
+                    self.parameters = self.parameters.$field(value);
+                    self
+                }
+            )*
+        }
+    };
+}
+
+impl_builder_methods! {
+    temperature: f32,
+    top_p: f32,
+    top_k: u32,
+    frequency_penalty: f32,
+    presence_penalty: f32,
+    repetition_penalty: f32,
+    min_p: f32,
+    top_a: f32,
+    seed: u32,
+    max_tokens: u32,
+    n: u32,
+    logit_bias: Vec<(String, f32)>,
+    logprobs: bool,
+    top_logprobs: u8,
+    stop: Vec<String>,
+    tool_choice: Vec<String>,
+}
+
+impl ParametersBuilder {
+    /// Validates and returns the parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ParameterError`] if a parameter is out of range or the parameters
+    /// conflict. See [`Parameters::validate`] for the rules.
+    pub fn build(self) -> Result<Parameters, ParameterError> {
+        self.parameters.validate()?;
+        Ok(self.parameters)
     }
 }
 
+/// An invalid generation parameter.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ParameterError {
+    /// A parameter is outside its valid range.
+    OutOfRange {
+        /// The name of the parameter.
+        name: &'static str,
+        /// The rejected value.
+        value: f32,
+        /// The valid range.
+        range: RangeInclusive<f32>,
+    },
+    /// A parameter has an invalid value or conflicts with the other parameters.
+    Invalid {
+        /// The name of the parameter.
+        name: &'static str,
+        /// Why the parameter is invalid.
+        reason: &'static str,
+    },
+}
+
+impl fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange { name, value, range } => write!(
+                f,
+                "`{name}` must be between {} and {}, got {value}",
+                range.start(),
+                range.end()
+            ),
+            Self::Invalid { name, reason } => write!(f, "invalid `{name}`: {reason}"),
+        }
+    }
+}
+
+impl core::error::Error for ParameterError {}
+
 /// Represents a language model's profile, including its name, description, abilities, context length, and optional pricing.
 ///
 /// A model profile provides comprehensive information about a language model's
@@ -188,6 +430,7 @@ impl_with_methods! {
 ///     .with_ability(Ability::Vision);
 /// ```
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub struct Profile {
     /// The name of the model.
@@ -215,12 +458,14 @@ pub struct Profile {
 ///
 /// let mut pricing = Pricing::default();
 ///
-/// pricing.prompt = 0.01; // $0.01 per 1K prompt tokens
-/// pricing.completion = 0.03; // $0.03 per 1K completion tokens
+/// pricing.prompt = 0.00001; // $10 per 1M prompt tokens
+/// pricing.completion = 0.00003; // $30 per 1M completion tokens
 /// pricing.image = 0.25; // $0.25 per image
 /// pricing.web_search = 0.005; // $0.005 per search
 /// ```
 #[derive(Debug, Clone, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[non_exhaustive]
 pub struct Pricing {
     /// Price per prompt token.
@@ -261,6 +506,8 @@ pub struct Pricing {
 /// support.seed = true;
 /// ```
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[allow(clippy::struct_excessive_bools)]
 #[non_exhaustive]
 pub struct SupportedParameters {
@@ -371,8 +618,8 @@ impl Profile {
     ///
     /// let mut pricing = Pricing::default();
     ///
-    /// pricing.prompt = 0.01;
-    /// pricing.completion = 0.03;
+    /// pricing.prompt = 0.00001;
+    /// pricing.completion = 0.00003;
     ///
     /// let profile = Profile::new("paid-model", "A paid model", 4096)
     ///     .with_pricing(pricing);
@@ -400,6 +647,8 @@ impl Profile {
 /// let has_vision = abilities.contains(&Ability::Vision);
 /// ```
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Ability {
     /// The model can use external tools/functions.
     ToolUse,
@@ -409,6 +658,9 @@ pub enum Ability {
     Audio,
     /// The model can perform web searches natively.
     WebSearch,
+    /// The model can constrain its output to a JSON schema natively, honoring
+    /// [`ResponseFormat`](crate::llm::request::ResponseFormat).
+    StructuredOutput,
 }
 
 #[cfg(test)]
@@ -655,4 +907,91 @@ mod tests {
         assert!(debug_str.contains("42"));
         assert!(debug_str.contains("1000"));
     }
+
+    #[test]
+    fn builder_rejects_out_of_range_values() {
+        assert!(Parameters::builder().build().is_ok());
+        assert!(
+            Parameters::builder()
+                .temperature(0.0)
+                .top_p(1.0)
+                .presence_penalty(-2.0)
+                .build()
+                .is_ok()
+        );
+
+        let invalid = [
+            Parameters::builder().temperature(-0.1),
+            Parameters::builder().temperature(f32::NAN),
+            Parameters::builder().top_p(1.5),
+            Parameters::builder().frequency_penalty(2.5),
+            Parameters::builder().logit_bias(alloc::vec![("a".into(), 101.0)]),
+        ];
+        for builder in invalid {
+            assert!(matches!(
+                builder.build(),
+                Err(ParameterError::OutOfRange { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn builder_rejects_invalid_values() {
+        for builder in [
+            Parameters::builder().repetition_penalty(0.0),
+            Parameters::builder().top_k(0),
+            Parameters::builder().max_tokens(0),
+        ] {
+            assert!(matches!(
+                builder.build(),
+                Err(ParameterError::Invalid { .. })
+            ));
+        }
+        let error = Parameters::builder().top_logprobs(5).build().unwrap_err();
+        assert_eq!(
+            error,
+            ParameterError::Invalid {
+                name: "top_logprobs",
+                reason: "requires `logprobs` to be enabled",
+            }
+        );
+        assert!(
+            Parameters::builder()
+                .logprobs(true)
+                .top_logprobs(5)
+                .build()
+                .is_ok()
+        );
+        assert!(
+            Parameters::builder()
+                .stop(alloc::vec![String::new()])
+                .build()
+                .is_err()
+        );
+        assert_eq!(
+            alloc::format!(
+                "{}",
+                Parameters::default().top_p(2.0).validate().unwrap_err()
+            ),
+            "`top_p` must be between 0 and 1, got 2"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn profile_serde_roundtrip() {
+        let pricing = Pricing {
+            prompt: 0.01,
+            ..Pricing::default()
+        };
+        let profile = Profile::new("test-model", "A test model", 4096)
+            .with_ability(Ability::ToolUse)
+            .with_pricing(pricing);
+
+        let json = serde_json::to_value(&profile).unwrap();
+        assert_eq!(json["abilities"][0], "tool_use");
+
+        let restored: Profile = serde_json::from_value(json).unwrap();
+        assert_eq!(restored, profile);
+    }
 }
@@ -0,0 +1,374 @@
+//! Fixing message lists to satisfy common provider requirements.
+//!
+//! Providers reject message lists that break their structural rules, usually with an
+//! unhelpful `400 Bad Request`. The rules vary, but most providers share a few:
+//!
+//! - A single system message, at the start.
+//! - User and assistant messages alternate; some providers also require the first
+//!   message after the system message to come from the user.
+//! - Every tool call is answered by a tool result right after the assistant message that
+//!   made it, and every tool result answers such a call.
+//!
+//! Histories assembled by applications, trimmed by context strategies, or edited by users
+//! break these rules easily. A [`Normalizer`] repairs them and reports each [`Fix`] it
+//! applied, so the repairs can be logged.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{Message, Role, normalize::{Fix, normalize}, tool::ToolCall};
+//!
+//! let call = ToolCall::new("call_1", "weather", r#"{"city":"Paris"}"#);
+//! let mut messages = vec![
+//!     Message::user("What's the weather in Paris?"),
+//!     Message::system("You are a helpful assistant."),
+//!     Message::user("Be brief."),
+//!     Message::assistant_tool_calls("", [call]),
+//! ];
+//!
+//! let fixes = normalize(&mut messages);
+//! let roles: Vec<Role> = messages.iter().map(Message::role).collect();
+//! assert_eq!(roles, [Role::System, Role::User, Role::Assistant, Role::Tool]);
+//! assert_eq!(messages[1].content(), "What's the weather in Paris?\n\nBe brief.");
+//! assert!(fixes.contains(&Fix::AddedToolResult { id: "call_1".into() }));
+//! ```
+
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::llm::{Message, Role};
+
+/// The default content of the tool results added for unanswered tool calls.
+const MISSING_RESULT: &str = "The tool call was not executed.";
+
+/// A repair applied by a [`Normalizer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum Fix {
+    /// System messages were moved to the start and merged into one.
+    MergedSystem {
+        /// The number of system messages found.
+        count: usize,
+    },
+    /// A tool result was moved right after the call it answers.
+    MovedToolResult {
+        /// The ID of the answered tool call.
+        id: String,
+    },
+    /// A tool result answering no call, or a call already answered, was removed.
+    DroppedToolResult {
+        /// The ID of the tool call the result claimed to answer.
+        id: String,
+    },
+    /// A placeholder result was added for an unanswered tool call.
+    AddedToolResult {
+        /// The ID of the unanswered tool call.
+        id: String,
+    },
+    /// Consecutive messages with the same role were merged.
+    MergedConsecutive {
+        /// The role of the merged messages.
+        role: Role,
+    },
+    /// A user message was inserted before a conversation starting with the assistant.
+    InsertedUser,
+}
+
+/// Repairs message lists to satisfy common provider requirements.
+///
+/// By default, a normalizer merges system messages into one leading message, pairs tool
+/// results with their calls, and merges consecutive user or assistant messages. Each
+/// repair can be turned off, and [`with_leading_user`](Self::with_leading_user) adds
+/// the stricter requirement of some providers that the conversation starts with the user.
+///
+/// See the [module documentation](crate::llm::normalize) for an example.
+#[derive(Debug, Clone)]
+pub struct Normalizer {
+    merge_system: bool,
+    pair_tool_results: bool,
+    missing_result: Option<String>,
+    merge_consecutive: bool,
+    leading_user: Option<String>,
+}
+
+impl Default for Normalizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Normalizer {
+    /// Creates a normalizer applying the default repairs.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            merge_system: true,
+            pair_tool_results: true,
+            missing_result: Some(MISSING_RESULT.into()),
+            merge_consecutive: true,
+            leading_user: None,
+        }
+    }
+
+    /// Sets whether system messages are moved to the start and merged into one.
+    #[must_use]
+    pub const fn with_merge_system(mut self, enabled: bool) -> Self {
+        self.merge_system = enabled;
+        self
+    }
+
+    /// Sets whether tool results are moved after their calls and orphaned results dropped.
+    #[must_use]
+    pub const fn with_tool_pairing(mut self, enabled: bool) -> Self {
+        self.pair_tool_results = enabled;
+        self
+    }
+
+    /// Sets the content of the results added for unanswered tool calls, or `None` to
+    /// leave unanswered calls alone.
+    ///
+    /// Only applies with [tool pairing](Self::with_tool_pairing).
+    #[must_use]
+    pub fn with_missing_result(mut self, content: Option<impl Into<String>>) -> Self {
+        self.missing_result = content.map(Into::into);
+        self
+    }
+
+    /// Sets whether consecutive user or assistant messages are merged into one.
+    #[must_use]
+    pub const fn with_merge_consecutive(mut self, enabled: bool) -> Self {
+        self.merge_consecutive = enabled;
+        self
+    }
+
+    /// Inserts a user message with `content` when the conversation, after the system
+    /// message, does not start with the user.
+    #[must_use]
+    pub fn with_leading_user(mut self, content: impl Into<String>) -> Self {
+        self.leading_user = Some(content.into());
+        self
+    }
+
+    /// Repairs `messages` in place and returns the fixes applied, in order.
+    pub fn normalize(&self, messages: &mut Vec<Message>) -> Vec<Fix> {
+        let mut fixes = Vec::new();
+        if self.merge_system {
+            merge_system(messages, &mut fixes);
+        }
+        if self.pair_tool_results {
+            self.pair_tool_results(messages, &mut fixes);
+        }
+        if self.merge_consecutive {
+            merge_consecutive(messages, &mut fixes);
+        }
+        if let Some(content) = &self.leading_user {
+            let start = usize::from(messages.first().is_some_and(|m| m.role() == Role::System));
+            if messages.get(start).is_some_and(|m| m.role() != Role::User) {
+                messages.insert(start, Message::user(content.clone()));
+                fixes.push(Fix::InsertedUser);
+            }
+        }
+        fixes
+    }
+
+    fn pair_tool_results(&self, messages: &mut Vec<Message>, fixes: &mut Vec<Fix>) {
+        let calls: Vec<String> = messages
+            .iter()
+            .flat_map(Message::tool_calls)
+            .map(|call| call.id.clone())
+            .collect();
+
+        // Take every tool result out, keeping the first answer to each call.
+        let mut results = BTreeMap::new();
+        let mut rest = Vec::with_capacity(messages.len());
+        for (position, message) in core::mem::take(messages).into_iter().enumerate() {
+            let Some(id) = message.tool_call_id().map(ToString::to_string) else {
+                rest.push((position, message));
+                continue;
+            };
+            if calls.contains(&id) && !results.contains_key(&id) {
+                results.insert(id, (position, message));
+            } else {
+                fixes.push(Fix::DroppedToolResult { id });
+            }
+        }
+
+        // Put them back right after the calls they answer.
+        for (origin, message) in rest {
+            let mut answers = Vec::new();
+            for (offset, call) in message.tool_calls().enumerate() {
+                let id = call.id.clone();
+                if let Some((position, result)) = results.remove(&id) {
+                    if position != origin + 1 + offset {
+                        fixes.push(Fix::MovedToolResult { id });
+                    }
+                    answers.push(result);
+                } else if let Some(content) = &self.missing_result {
+                    answers.push(Message::tool_result(id.clone(), content.clone()));
+                    fixes.push(Fix::AddedToolResult { id });
+                }
+            }
+            messages.push(message);
+            messages.extend(answers);
+        }
+    }
+}
+
+/// Repairs `messages` in place with the default [`Normalizer`] and returns the fixes
+/// applied, in order.
+pub fn normalize(messages: &mut Vec<Message>) -> Vec<Fix> {
+    Normalizer::new().normalize(messages)
+}
+
+fn merge_system(messages: &mut Vec<Message>, fixes: &mut Vec<Fix>) {
+    let count = messages.iter().filter(|m| m.role() == Role::System).count();
+    let leading = messages.first().is_some_and(|m| m.role() == Role::System);
+    if count == 0 || (count == 1 && leading) {
+        return;
+    }
+    let (system, rest): (Vec<Message>, Vec<Message>) = core::mem::take(messages)
+        .into_iter()
+        .partition(|m| m.role() == Role::System);
+    let mut system = system.into_iter();
+    if let Some(mut merged) = system.next() {
+        for message in system {
+            merged.merge(message);
+        }
+        messages.push(merged);
+    }
+    messages.extend(rest);
+    fixes.push(Fix::MergedSystem { count });
+}
+
+fn merge_consecutive(messages: &mut Vec<Message>, fixes: &mut Vec<Fix>) {
+    let mut merged: Vec<Message> = Vec::with_capacity(messages.len());
+    for message in core::mem::take(messages) {
+        let role = message.role();
+        match merged.last_mut() {
+            Some(last)
+                if last.role() == role
+                    && matches!(role, Role::User | Role::Assistant)
+                    && last.tool_calls().next().is_none() =>
+            {
+                last.merge(message);
+                fixes.push(Fix::MergedConsecutive { role });
+            }
+            _ => merged.push(message),
+        }
+    }
+    *messages = merged;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::tool::ToolCall;
+    use alloc::vec;
+
+    fn call(id: &str) -> ToolCall {
+        ToolCall::new(id, "search", "{}")
+    }
+
+    fn summary(messages: &[Message]) -> Vec<(Role, String)> {
+        messages
+            .iter()
+            .map(|m| {
+                let text = m.tool_call_id().map_or_else(
+                    || m.content().into_owned(),
+                    |id| alloc::format!("{id}: {}", m.content()),
+                );
+                (m.role(), text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn valid_conversations_are_unchanged() {
+        let mut messages = vec![
+            Message::system("Be helpful"),
+            Message::user("Hi"),
+            Message::assistant_tool_calls("Searching", [call("1"), call("2")]),
+            Message::tool_result("1", "one"),
+            Message::tool_result("2", "two"),
+            Message::assistant("Done"),
+        ];
+        assert!(normalize(&mut messages).is_empty());
+        assert_eq!(messages.len(), 6);
+    }
+
+    #[test]
+    fn pairs_tool_results_with_their_calls() {
+        let mut messages = vec![
+            Message::user("Search"),
+            Message::assistant_tool_calls("", [call("a"), call("b"), call("c")]),
+            Message::tool_result("b", "second"),
+            Message::user("Well?"),
+            Message::tool_result("a", "first"),
+            Message::tool_result("zzz", "orphan"),
+            Message::tool_result("b", "duplicate"),
+        ];
+        let fixes = normalize(&mut messages);
+
+        assert_eq!(
+            summary(&messages),
+            [
+                (Role::User, "Search".into()),
+                (Role::Assistant, String::new()),
+                (Role::Tool, "a: first".into()),
+                (Role::Tool, "b: second".into()),
+                (Role::Tool, alloc::format!("c: {MISSING_RESULT}")),
+                (Role::User, "Well?".into()),
+            ]
+        );
+        assert_eq!(
+            fixes,
+            [
+                Fix::DroppedToolResult { id: "zzz".into() },
+                Fix::DroppedToolResult { id: "b".into() },
+                Fix::MovedToolResult { id: "a".into() },
+                Fix::MovedToolResult { id: "b".into() },
+                Fix::AddedToolResult { id: "c".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn merges_roles_and_inserts_leading_user() {
+        let mut messages = vec![
+            Message::assistant("Hello!"),
+            Message::system("Rule one"),
+            Message::assistant("How can I help?").pinned(true),
+            Message::system("Rule two"),
+            Message::user("Hi"),
+        ];
+        let fixes = Normalizer::new()
+            .with_leading_user("(conversation start)")
+            .normalize(&mut messages);
+
+        assert_eq!(
+            summary(&messages),
+            [
+                (Role::System, "Rule one\n\nRule two".into()),
+                (Role::User, "(conversation start)".into()),
+                (Role::Assistant, "Hello!\n\nHow can I help?".into()),
+                (Role::User, "Hi".into()),
+            ]
+        );
+        assert!(messages[2].is_pinned());
+        assert_eq!(
+            fixes,
+            [
+                Fix::MergedSystem { count: 2 },
+                Fix::MergedConsecutive {
+                    role: Role::Assistant
+                },
+                Fix::InsertedUser,
+            ]
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! Streaming structured output.
+//!
+//! [`LanguageModel::generate_stream`] parses the JSON of a structured response while it
+//! is being written. Each time a chunk arrives, the JSON received so far is
+//! [closed](crate::llm::extract::close_json) and deserialized, and the result is yielded
+//! as a [`Partial`] if it changed. User interfaces can render a result field by field,
+//! and text fields as they grow, instead of waiting for the whole response.
+//!
+//! Partial objects lack the fields not written yet, so they only deserialize when those
+//! fields are optional. Make the fields of `T` [`Option`]s, or give them defaults with
+//! `#[serde(default)]`, to see them filled in progressively. Otherwise, only the complete
+//! object is yielded.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{LanguageModel, Request};
+//! use futures_lite::StreamExt;
+//! use schemars::{JsonSchema, schema_for};
+//! use serde::Deserialize;
+//!
+//! #[derive(JsonSchema, Deserialize)]
+//! struct Recipe {
+//!     title: Option<String>,
+//!     #[serde(default)]
+//!     steps: Vec<String>,
+//! }
+//!
+//! async fn show(model: impl LanguageModel) -> ai_types::Result<()> {
+//!     let request = Request::oneshot("Write a recipe", "Pancakes");
+//!     let recipes = model.generate_stream::<Recipe>(request);
+//!     futures_lite::pin!(recipes);
+//!     while let Some(recipe) = recipes.next().await {
+//!         let recipe = recipe?;
+//!         println!("{:?}: {} steps so far", recipe.value.title, recipe.value.steps.len());
+//!         if recipe.complete {
+//!             println!("Done!");
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use alloc::string::String;
+
+use async_stream::stream;
+use futures_core::Stream;
+use futures_lite::{StreamExt, pin};
+use schemars::{JsonSchema, schema_for};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::llm::{LanguageModel, Request, extract, structured};
+
+/// A structured result, possibly still being generated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Partial<T> {
+    /// The result parsed from the response received so far.
+    pub value: T,
+    /// Whether the response is complete, making this the final result.
+    pub complete: bool,
+}
+
+pub(crate) fn generate_stream<T, M>(
+    model: &M,
+    request: Request,
+) -> impl Stream<Item = crate::Result<Partial<T>>> + Send
+where
+    T: JsonSchema + DeserializeOwned + Send,
+    M: LanguageModel,
+{
+    let response = model.respond(structured(model, request, schema_for!(T)));
+    stream! {
+        pin!(response);
+        let mut buffer = String::new();
+        let mut last = None;
+        while let Some(chunk) = response.next().await {
+            match chunk {
+                Ok(chunk) => buffer.push_str(&chunk),
+                Err(error) => {
+                    yield Err(error.into());
+                    return;
+                }
+            }
+            let Some(value) = extract::close_json(&buffer)
+                .and_then(|json| serde_json::from_str::<Value>(&json).ok())
+            else {
+                continue;
+            };
+            if last.as_ref() == Some(&value) {
+                continue;
+            }
+            if let Ok(partial) = serde_json::from_value(value.clone()) {
+                last = Some(value);
+                yield Ok(Partial { value: partial, complete: false });
+            }
+        }
+        yield extract::parse_json(&buffer)
+            .map(|value| Partial { value, complete: true })
+            .map_err(Into::into);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockLanguageModel, MockReply};
+    use alloc::{string::ToString, vec::Vec};
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Eq, JsonSchema, Deserialize)]
+    struct Recipe {
+        title: Option<String>,
+        #[serde(default)]
+        steps: Vec<String>,
+    }
+
+    fn recipe(title: Option<&str>, steps: &[&str]) -> Recipe {
+        Recipe {
+            title: title.map(ToString::to_string),
+            steps: steps.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn yields_growing_partial_objects() {
+        let model = MockLanguageModel::new().with_reply(MockReply::chunks([
+            "```json\n{\"tit",
+            "le\": \"Panc",
+            "akes\", \"st",
+            "eps\": [\"Mix\", \"Fr",
+            "y\"]}\n```",
+        ]));
+        let partials: Vec<_> = model
+            .generate_stream::<Recipe>(Request::oneshot("Write a recipe", "Pancakes"))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert_eq!(
+            partials,
+            [
+                Partial {
+                    value: recipe(None, &[]),
+                    complete: false
+                },
+                Partial {
+                    value: recipe(Some("Panc"), &[]),
+                    complete: false
+                },
+                Partial {
+                    value: recipe(Some("Pancakes"), &[]),
+                    complete: false
+                },
+                Partial {
+                    value: recipe(Some("Pancakes"), &["Mix", "Fr"]),
+                    complete: false
+                },
+                Partial {
+                    value: recipe(Some("Pancakes"), &["Mix", "Fry"]),
+                    complete: false
+                },
+                Partial {
+                    value: recipe(Some("Pancakes"), &["Mix", "Fry"]),
+                    complete: true
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn incomplete_responses_end_with_an_error() {
+        let model = MockLanguageModel::new().with_reply(r#"{"title": "Pan"#);
+        let partials: Vec<_> = model
+            .generate_stream::<Recipe>(Request::default())
+            .collect()
+            .await;
+
+        assert_eq!(partials.len(), 2);
+        assert_eq!(
+            partials[0].as_ref().unwrap().value.title.as_deref(),
+            Some("Pan")
+        );
+        assert!(partials[1].is_err());
+    }
+}
@@ -0,0 +1,309 @@
+//! Testing what a model can actually do.
+//!
+//! A model's [`Profile`] is declared by its provider adapter, and is sometimes missing or
+//! wrong: a local model may claim tool use it cannot perform, or an endpoint may truncate
+//! long prompts silently. A [`Prober`] sends a small battery of canned requests and
+//! reports what it observed:
+//!
+//! - **JSON output**: whether [`generate`](LanguageModel::generate) yields valid JSON
+//!   with the requested content.
+//! - **Tool calls**: whether the model calls a tool offered in the request.
+//! - **Long-context recall**: whether the model finds a fact buried in the middle of a
+//!   long prompt.
+//!
+//! Probes make real requests, and the long-context probe sends many tokens, so run them
+//! once per model and keep the report.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{LanguageModel, probe::probe};
+//!
+//! async fn check(model: impl LanguageModel) {
+//!     let report = probe(&model).await;
+//!     if !report.tool_calls.is_passed() {
+//!         println!("{} cannot call tools: {:?}", report.model, report.tool_calls);
+//!     }
+//!     let profile = report.apply_to(model.profile());
+//!     println!("Observed abilities: {:?}", profile.abilities);
+//! }
+//! ```
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+};
+use core::fmt::Write;
+
+use futures_lite::{StreamExt, pin};
+use serde_json::Value;
+
+use crate::llm::{
+    LanguageModel, Request, Tool,
+    event::StreamEvent,
+    model::{Ability, Profile},
+    token::{Estimator, TokenCounter},
+    try_collect,
+};
+
+/// The default size of the long-context probe, in estimated tokens.
+const DEFAULT_CONTEXT_TOKENS: usize = 16_000;
+
+/// The fact hidden in the long-context probe.
+const NEEDLE: &str = "The access code for the archive room is 7319-HERON.";
+
+/// Filler surrounding the fact in the long-context probe.
+const FILLER: &str =
+    "The committee reviewed the quarterly figures and agreed to meet again next week. ";
+
+/// The outcome of a single probe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum ProbeOutcome {
+    /// The model behaved as expected.
+    Passed,
+    /// The model responded, but not as expected.
+    Failed(String),
+    /// The request failed, so nothing was observed.
+    Error(String),
+}
+
+impl ProbeOutcome {
+    /// Returns whether the probe passed.
+    #[must_use]
+    pub const fn is_passed(&self) -> bool {
+        matches!(self, Self::Passed)
+    }
+}
+
+/// The capabilities observed by a [`Prober`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct ProbeReport {
+    /// The name of the probed model, from its profile.
+    pub model: String,
+    /// Whether structured generation produced valid JSON with the requested content.
+    pub json_output: ProbeOutcome,
+    /// Whether the model called a tool offered in the request.
+    pub tool_calls: ProbeOutcome,
+    /// Whether the model recalled a fact from the middle of a long prompt.
+    pub long_context: ProbeOutcome,
+    /// The estimated size of the long-context probe, in tokens.
+    pub context_tokens: usize,
+}
+
+impl ProbeReport {
+    /// Returns `profile` with its abilities corrected by the observations.
+    ///
+    /// [`Ability::ToolUse`] is added if the tool probe passed and removed if it failed.
+    /// Inconclusive probes leave the profile unchanged.
+    #[must_use]
+    pub fn apply_to(&self, mut profile: Profile) -> Profile {
+        let has_tools = profile.abilities.contains(&Ability::ToolUse);
+        match self.tool_calls {
+            ProbeOutcome::Passed if !has_tools => profile.abilities.push(Ability::ToolUse),
+            ProbeOutcome::Failed(_) => profile.abilities.retain(|a| *a != Ability::ToolUse),
+            _ => {}
+        }
+        profile
+    }
+}
+
+/// Runs capability probes against models.
+///
+/// See the [module documentation](crate::llm::probe) for an example.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Prober {
+    context_tokens: Option<usize>,
+}
+
+impl Prober {
+    /// Creates a prober with the default probe sizes.
+    ///
+    /// The long-context probe fills three quarters of the declared context length, up to
+    /// 16,000 tokens.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            context_tokens: None,
+        }
+    }
+
+    /// Sets the size of the long-context probe, in estimated tokens.
+    #[must_use]
+    pub const fn with_context_tokens(mut self, tokens: usize) -> Self {
+        self.context_tokens = Some(tokens);
+        self
+    }
+
+    /// Runs every probe against `model`, one after another.
+    pub async fn run<M: LanguageModel>(&self, model: &M) -> ProbeReport {
+        let profile = model.profile();
+        let context_tokens = self.context_tokens.unwrap_or_else(|| {
+            (profile.context_length as usize / 4 * 3).min(DEFAULT_CONTEXT_TOKENS)
+        });
+        ProbeReport {
+            model: profile.name,
+            json_output: json_output(model).await,
+            tool_calls: tool_calls(model).await,
+            long_context: long_context(model, context_tokens).await,
+            context_tokens,
+        }
+    }
+}
+
+/// Runs every probe against `model` with the default probe sizes.
+pub async fn probe<M: LanguageModel>(model: &M) -> ProbeReport {
+    Prober::new().run(model).await
+}
+
+async fn json_output<M: LanguageModel>(model: &M) -> ProbeOutcome {
+    let request = Request::oneshot(
+        "Answer with a JSON object whose `capital` field holds the answer.",
+        "What is the capital of France?",
+    );
+    match model.generate::<BTreeMap<String, Value>>(request).await {
+        Ok(object) => match object.get("capital").and_then(Value::as_str) {
+            Some(capital) if capital.contains("Paris") => ProbeOutcome::Passed,
+            Some(capital) => ProbeOutcome::Failed(format!("Expected Paris, got `{capital}`")),
+            None => ProbeOutcome::Failed("The object has no `capital` string".into()),
+        },
+        Err(error) if error.is::<serde_json::Error>() => {
+            ProbeOutcome::Failed(format!("Invalid JSON: {error}"))
+        }
+        Err(error) => ProbeOutcome::Error(error.to_string()),
+    }
+}
+
+/// A weather tool offered to the model by the tool-call probe.
+struct Weather;
+
+impl Tool for Weather {
+    const NAME: &str = "get_weather";
+    const DESCRIPTION: &str =
+        "Returns the current weather in a city. Takes an object with a `city` string.";
+    type Arguments = BTreeMap<String, Value>;
+
+    async fn call(&mut self, _arguments: Self::Arguments) -> crate::Result {
+        Ok("Sunny, 22°C".into())
+    }
+}
+
+async fn tool_calls<M: LanguageModel>(model: &M) -> ProbeOutcome {
+    let request = Request::oneshot(
+        "Use the available tools to answer.",
+        "What is the weather in Paris right now?",
+    )
+    .with_tool(Weather);
+    let events = model.respond_events(request);
+    pin!(events);
+    while let Some(event) = events.next().await {
+        match event {
+            Ok(StreamEvent::ToolCall(call) | StreamEvent::InvalidToolCall { call, .. })
+                if call.name == Weather::NAME =>
+            {
+                return ProbeOutcome::Passed;
+            }
+            Ok(StreamEvent::Done) => break,
+            Ok(_) => {}
+            Err(error) => return ProbeOutcome::Error(error.to_string()),
+        }
+    }
+    ProbeOutcome::Failed("The model answered without calling the tool".into())
+}
+
+async fn long_context<M: LanguageModel>(model: &M, tokens: usize) -> ProbeOutcome {
+    let response = try_collect(model.respond(Request::oneshot(
+        "Answer from the document the user provides.",
+        haystack(tokens),
+    )))
+    .await;
+    match response {
+        Ok(answer) if answer.contains("7319-HERON") => ProbeOutcome::Passed,
+        Ok(_) => ProbeOutcome::Failed("The model did not recall the access code".into()),
+        Err(error) => ProbeOutcome::Error(error.to_string()),
+    }
+}
+
+/// Builds a document of about `tokens` tokens with the needle in the middle, followed by
+/// the question.
+fn haystack(tokens: usize) -> String {
+    let filler = Estimator::new().count_tokens(FILLER);
+    let sentences = tokens / filler;
+    let mut document = String::from("Document:\n");
+    for index in 0..sentences.max(1) {
+        if index == sentences / 2 {
+            let _ = write!(document, "{NEEDLE} ");
+        }
+        document.push_str(FILLER);
+    }
+    document.push_str("\n\nWhat is the access code for the archive room?");
+    document
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        llm::tool::ToolCall,
+        test_util::{MockLanguageModel, MockReply},
+    };
+
+    /// Answers the JSON, tool-call and long-context probes, in that order, with `replies`.
+    fn model(replies: [MockReply; 3]) -> MockLanguageModel {
+        MockLanguageModel::new()
+            .with_profile(Profile::new("model", "A test model", 4000))
+            .with_replies(replies)
+    }
+
+    #[tokio::test]
+    async fn capable_model_passes() {
+        let weather = ToolCall::new("1", "get_weather", r#"{"city":"Paris"}"#);
+        let model = model([
+            r#"{"capital": "Paris"}"#.into(),
+            MockReply::default().with_tool_call(weather),
+            "7319-HERON".into(),
+        ]);
+        let report = probe(&model).await;
+        assert_eq!(report.json_output, ProbeOutcome::Passed);
+        assert_eq!(report.tool_calls, ProbeOutcome::Passed);
+        assert_eq!(report.long_context, ProbeOutcome::Passed);
+        assert_eq!(report.context_tokens, 3000);
+
+        let profile = report.apply_to(Profile::new("model", "", 4000));
+        assert_eq!(profile.abilities, [Ability::ToolUse]);
+    }
+
+    #[tokio::test]
+    async fn limited_model_fails() {
+        let report = Prober::new()
+            .with_context_tokens(500)
+            .run(&model([
+                "Paris".into(),
+                "It is sunny.".into(),
+                "I don't know".into(),
+            ]))
+            .await;
+        assert!(matches!(report.json_output, ProbeOutcome::Failed(_)));
+        assert!(matches!(report.tool_calls, ProbeOutcome::Failed(_)));
+        assert!(matches!(report.long_context, ProbeOutcome::Failed(_)));
+
+        let declared = Profile::new("model", "", 4000).with_ability(Ability::ToolUse);
+        assert!(report.apply_to(declared).abilities.is_empty());
+    }
+
+    #[test]
+    fn haystack_hides_needle_in_the_middle() {
+        let document = haystack(2000);
+        let tokens = Estimator::new().count_tokens(&document);
+        assert!((1900..2100).contains(&tokens), "{tokens} tokens");
+
+        let needle = document.find(NEEDLE).unwrap();
+        let middle = document.len() / 2;
+        assert!(needle.abs_diff(middle) < document.len() / 10);
+    }
+}
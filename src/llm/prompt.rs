@@ -0,0 +1,361 @@
+//! Prompt templates rendering variables, conditional sections and few-shot examples.
+//!
+//! A [`PromptTemplate`] is a list of messages whose text contains placeholders. It is
+//! plain data, serializable with the `serde` feature, so prompts can be stored in files
+//! or databases and shared across models, then [rendered](PromptTemplate::render) into
+//! the [`Message`]s of a request.
+//!
+//! The syntax is a small subset of Mustache:
+//!
+//! - `{{name}}` is replaced with the value of the variable `name`.
+//! - `{{#name}}…{{/name}}` is kept only if `name` is set to a non-empty value.
+//! - `{{^name}}…{{/name}}` is kept only if `name` is unset or empty.
+//!
+//! Names consist of ASCII letters, digits, `_`, `-` and `.`. Other tags, such as
+//! [prompt asset](super::asset) references, are left as they are. Messages that render
+//! to nothing are dropped, so a whole message can be made conditional.
+//!
+//! Few-shot [examples](PromptTemplate::with_example) are inserted as user and assistant
+//! message pairs after the leading system messages.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{Role, prompt::PromptTemplate};
+//!
+//! let template = PromptTemplate::new()
+//!     .with_system("Translate into {{language}}.{{#tone}} Use a {{tone}} tone.{{/tone}}")
+//!     .with_example("Good morning", "Bonjour")
+//!     .with_user("{{text}}");
+//!
+//! let messages = template.render([("language", "French"), ("text", "Thank you")])?;
+//! assert_eq!(messages.len(), 4);
+//! assert_eq!(messages[0].content(), "Translate into French.");
+//! assert_eq!(messages[2].role(), Role::Assistant);
+//! assert_eq!(messages[3].content(), "Thank you");
+//! # Ok::<(), ai_types::llm::prompt::TemplateError>(())
+//! ```
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+
+use crate::llm::{Message, Role};
+
+const OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+
+/// A message of a [`PromptTemplate`], with placeholders in its text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MessageTemplate {
+    /// The role of the rendered message.
+    pub role: Role,
+    /// The text of the message, with placeholders.
+    pub text: String,
+}
+
+/// A few-shot example, rendered as a user message and the assistant's answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Example {
+    /// The user's input, with placeholders.
+    pub input: String,
+    /// The expected answer, with placeholders.
+    pub output: String,
+}
+
+/// A chat prompt stored as data and rendered into messages.
+///
+/// See the [module documentation](crate::llm::prompt) for the syntax and an example.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PromptTemplate {
+    /// The messages of the prompt.
+    pub messages: Vec<MessageTemplate>,
+    /// The few-shot examples, inserted after the leading system messages.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub examples: Vec<Example>,
+}
+
+impl PromptTemplate {
+    /// Creates an empty template.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            messages: Vec::new(),
+            examples: Vec::new(),
+        }
+    }
+
+    /// Appends a message with the given role.
+    #[must_use]
+    pub fn with_message(mut self, role: Role, text: impl Into<String>) -> Self {
+        self.messages.push(MessageTemplate {
+            role,
+            text: text.into(),
+        });
+        self
+    }
+
+    /// Appends a system message.
+    #[must_use]
+    pub fn with_system(self, text: impl Into<String>) -> Self {
+        self.with_message(Role::System, text)
+    }
+
+    /// Appends a user message.
+    #[must_use]
+    pub fn with_user(self, text: impl Into<String>) -> Self {
+        self.with_message(Role::User, text)
+    }
+
+    /// Appends an assistant message.
+    #[must_use]
+    pub fn with_assistant(self, text: impl Into<String>) -> Self {
+        self.with_message(Role::Assistant, text)
+    }
+
+    /// Adds a few-shot example of `input` answered with `output`.
+    #[must_use]
+    pub fn with_example(mut self, input: impl Into<String>, output: impl Into<String>) -> Self {
+        self.examples.push(Example {
+            input: input.into(),
+            output: output.into(),
+        });
+        self
+    }
+
+    /// Returns the names of all variables referenced by the template, in sections or not.
+    #[must_use]
+    pub fn variables(&self) -> BTreeSet<String> {
+        let texts = self.messages.iter().map(|message| message.text.as_str());
+        let examples = self
+            .examples
+            .iter()
+            .flat_map(|example| [example.input.as_str(), example.output.as_str()]);
+        texts
+            .chain(examples)
+            .flat_map(tags)
+            .map(|tag| tag.trim_start_matches(['#', '^', '/']).trim().to_string())
+            .collect()
+    }
+
+    /// Renders the template with the given variables.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TemplateError`] if a variable outside a skipped section is missing, or
+    /// if the sections are not properly nested.
+    pub fn render<K, V>(
+        &self,
+        variables: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<Vec<Message>, TemplateError>
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let variables: BTreeMap<String, String> = variables
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .collect();
+
+        let system = self
+            .messages
+            .iter()
+            .take_while(|message| message.role == Role::System)
+            .count();
+        let examples = self.examples.iter().flat_map(|example| {
+            [
+                (Role::User, example.input.as_str()),
+                (Role::Assistant, example.output.as_str()),
+            ]
+        });
+        let templates = self.messages[..system]
+            .iter()
+            .map(|message| (message.role, message.text.as_str()))
+            .chain(examples)
+            .chain(
+                self.messages[system..]
+                    .iter()
+                    .map(|message| (message.role, message.text.as_str())),
+            );
+
+        let mut messages = Vec::new();
+        for (role, text) in templates {
+            let text = render_text(text, &variables)?;
+            if !text.trim().is_empty() {
+                messages.push(Message::new(role, text));
+            }
+        }
+        Ok(messages)
+    }
+}
+
+/// An error rendering a [`PromptTemplate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// A variable used outside a skipped section has no value.
+    MissingVariable(String),
+    /// A section was opened but never closed.
+    UnclosedSection(String),
+    /// A section was closed without being opened, or out of order.
+    UnexpectedClose(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingVariable(name) => write!(f, "Missing template variable `{name}`"),
+            Self::UnclosedSection(name) => write!(f, "Unclosed template section `{name}`"),
+            Self::UnexpectedClose(name) => {
+                write!(f, "Unexpected close of template section `{name}`")
+            }
+        }
+    }
+}
+
+impl core::error::Error for TemplateError {}
+
+/// Returns whether `name` is a valid variable name.
+fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'-' | b'.'))
+}
+
+/// Returns the contents of the template tags in `text`, skipping non-template tags.
+fn tags(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    core::iter::from_fn(move || {
+        loop {
+            let start = rest.find(OPEN)?;
+            let after = &rest[start + OPEN.len()..];
+            let end = after.find(CLOSE)?;
+            rest = &after[end + CLOSE.len()..];
+            let tag = after[..end].trim();
+            if is_name(tag.trim_start_matches(['#', '^', '/']).trim()) {
+                return Some(tag);
+            }
+        }
+    })
+}
+
+fn render_text(text: &str, variables: &BTreeMap<String, String>) -> Result<String, TemplateError> {
+    let is_set = |name: &str| variables.get(name).is_some_and(|value| !value.is_empty());
+    let mut output = String::new();
+    // The open sections, innermost last, with whether their content is kept.
+    let mut sections: Vec<(&str, bool)> = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(OPEN) {
+        let visible = sections.last().is_none_or(|(_, visible)| *visible);
+        let after = &rest[start + OPEN.len()..];
+        let Some(end) = after.find(CLOSE) else {
+            break;
+        };
+        let raw = &rest[..start + OPEN.len() + end + CLOSE.len()];
+        let tag = after[..end].trim();
+        rest = &after[end + CLOSE.len()..];
+        if visible {
+            output.push_str(&raw[..start]);
+        }
+
+        let (sigil, name) = match tag.as_bytes().first() {
+            Some(b'#' | b'^' | b'/') => (tag.as_bytes()[0], tag[1..].trim()),
+            _ => (0, tag),
+        };
+        if !is_name(name) {
+            if visible {
+                output.push_str(&raw[start..]);
+            }
+            continue;
+        }
+        match sigil {
+            b'#' => sections.push((name, visible && is_set(name))),
+            b'^' => sections.push((name, visible && !is_set(name))),
+            b'/' => match sections.pop() {
+                Some((open, _)) if open == name => {}
+                _ => return Err(TemplateError::UnexpectedClose(name.to_string())),
+            },
+            _ if visible => {
+                let value = variables
+                    .get(name)
+                    .ok_or_else(|| TemplateError::MissingVariable(name.to_string()))?;
+                output.push_str(value);
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((name, _)) = sections.pop() {
+        return Err(TemplateError::UnclosedSection(name.to_string()));
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn renders_sections_and_examples() {
+        let template = PromptTemplate::new()
+            .with_system("You are {{persona}}.{{^formal}} Be casual.{{/formal}}")
+            .with_system("{{#rules}}Rules: {{rules}}{{/rules}}")
+            .with_example("Hi {{persona}}", "Hello!")
+            .with_user("{{question}} {{asset:0123456789abcdef}}");
+
+        let names: Vec<_> = template.variables().into_iter().collect();
+        assert_eq!(names, ["formal", "persona", "question", "rules"]);
+
+        let messages = template
+            .render([("persona", "a pirate"), ("question", "Where?")])
+            .unwrap();
+        let rendered: Vec<_> = messages
+            .iter()
+            .map(|message| (message.role(), message.content().into_owned()))
+            .collect();
+        let expected = [
+            (Role::System, "You are a pirate. Be casual."),
+            (Role::User, "Hi a pirate"),
+            (Role::Assistant, "Hello!"),
+            (Role::User, "Where? {{asset:0123456789abcdef}}"),
+        ];
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(role, text)| (role, text.to_string()))
+            .collect();
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn reports_template_errors() {
+        let render = |text: &str| {
+            PromptTemplate::new()
+                .with_user(text)
+                .render([("a", "1")])
+                .err()
+        };
+
+        assert_eq!(
+            render("{{b}}"),
+            Some(TemplateError::MissingVariable("b".to_string()))
+        );
+        assert_eq!(render("{{#b}}{{c}}{{/b}}"), None);
+        assert_eq!(
+            render("{{#a}}"),
+            Some(TemplateError::UnclosedSection("a".to_string()))
+        );
+        assert_eq!(
+            render("{{#a}}{{^b}}{{/a}}{{/b}}"),
+            Some(TemplateError::UnexpectedClose("a".to_string()))
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Generating assessment questions with answer keys.
+//!
+//! [`LanguageModel::generate_questionnaire`] turns a specification, such as a job
+//! description, a syllabus or a survey brief, into typed [`Question`]s with an
+//! [`AnswerKey`] each. Questions are streamed as the model writes them, one JSON object
+//! per line, so the first ones can be shown before the last is generated.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{LanguageModel, questionnaire::AnswerKey};
+//! use futures_lite::StreamExt;
+//!
+//! async fn interview(model: impl LanguageModel, job: &str) -> ai_types::Result<()> {
+//!     let questions = model.generate_questionnaire(job, 5);
+//!     futures_lite::pin!(questions);
+//!     while let Some(question) = questions.next().await {
+//!         let question = question?;
+//!         println!("{}", question.text);
+//!         if let AnswerKey::Choice { options, .. } = &question.answer_key {
+//!             for (index, option) in options.iter().enumerate() {
+//!                 println!("  {}. {option}", index + 1);
+//!             }
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use anyhow::bail;
+use async_stream::stream;
+use futures_core::Stream;
+use futures_lite::{StreamExt, pin};
+use serde_json::Value;
+
+use crate::llm::{LanguageModel, Request, prompts};
+
+/// A generated assessment question.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Question {
+    /// The question to ask.
+    pub text: String,
+    /// The skill or topic the question assesses.
+    pub topic: Option<String>,
+    /// How to grade answers.
+    pub answer_key: AnswerKey,
+}
+
+impl Question {
+    /// Creates a question.
+    pub fn new(text: impl Into<String>, answer_key: AnswerKey) -> Self {
+        Self {
+            text: text.into(),
+            topic: None,
+            answer_key,
+        }
+    }
+
+    /// Sets the skill or topic the question assesses.
+    #[must_use]
+    pub fn with_topic(mut self, topic: impl Into<String>) -> Self {
+        self.topic = Some(topic.into());
+        self
+    }
+
+    /// Returns the correct answer, or `None` for open questions graded by a rubric.
+    #[must_use]
+    pub fn correct_answer(&self) -> Option<&str> {
+        match &self.answer_key {
+            AnswerKey::Choice { options, correct } => options.get(*correct).map(String::as_str),
+            AnswerKey::Expected(answer) => Some(answer),
+            AnswerKey::Rubric(_) => None,
+        }
+    }
+}
+
+/// How to grade answers to a [`Question`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum AnswerKey {
+    /// A multiple-choice question.
+    Choice {
+        /// The options to choose from.
+        options: Vec<String>,
+        /// The index of the correct option.
+        correct: usize,
+    },
+    /// A short-answer question with the expected answer.
+    Expected(String),
+    /// An open question, with the points a good answer covers.
+    Rubric(Vec<String>),
+}
+
+/// Streams up to `count` questions generated from `spec`.
+pub(crate) fn generate_questionnaire<M: LanguageModel>(
+    model: &M,
+    spec: &str,
+    count: usize,
+) -> impl Stream<Item = crate::Result<Question>> + Send {
+    let request = Request::oneshot(prompts::questionnaire(count), spec);
+    let response = model.respond(request);
+    stream! {
+        pin!(response);
+        let mut buffer = String::new();
+        let mut remaining = count;
+        let mut done = false;
+        while !done && remaining > 0 {
+            match response.next().await {
+                Some(Ok(chunk)) => buffer.push_str(&chunk),
+                Some(Err(error)) => {
+                    yield Err(error.into());
+                    return;
+                }
+                None => {
+                    // Parse the last line, which may lack a newline.
+                    buffer.push('\n');
+                    done = true;
+                }
+            }
+            while let Some(end) = buffer.find('\n') {
+                let line: String = buffer.drain(..=end).collect();
+                let line = line.trim();
+                if line.is_empty() || line.starts_with("```") || remaining == 0 {
+                    continue;
+                }
+                remaining -= 1;
+                yield parse(line);
+            }
+        }
+    }
+}
+
+/// Parses one line of the model's response.
+fn parse(line: &str) -> crate::Result<Question> {
+    let value: Value = serde_json::from_str(line)?;
+    let Some(text) = value["question"].as_str() else {
+        bail!("Question without text: {line}");
+    };
+    let strings = |key: &str| -> Vec<String> {
+        value[key]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .map(ToString::to_string)
+            .collect()
+    };
+
+    let answer_key = match value["type"].as_str() {
+        Some("choice") => {
+            let options = strings("options");
+            let correct = value["correct"]
+                .as_u64()
+                .and_then(|index| usize::try_from(index).ok())
+                .filter(|&index| index < options.len());
+            let Some(correct) = correct else {
+                bail!("Multiple-choice question without a valid correct option: {line}");
+            };
+            AnswerKey::Choice { options, correct }
+        }
+        Some("short") => match value["answer"].as_str() {
+            Some(answer) => AnswerKey::Expected(answer.to_string()),
+            None => bail!("Short-answer question without an answer: {line}"),
+        },
+        Some("open") => AnswerKey::Rubric(strings("rubric")),
+        _ => bail!("Question of unknown type: {line}"),
+    };
+
+    let question = Question::new(text, answer_key);
+    Ok(match value["topic"].as_str() {
+        Some(topic) => question.with_topic(topic),
+        None => question,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockLanguageModel, MockReply};
+    use alloc::vec;
+
+    #[tokio::test]
+    async fn streams_questions_line_by_line() {
+        let model = MockLanguageModel::new().with_reply(MockReply::chunks([
+            "```json\n{\"question\": \"Which keyword declares an immutable binding?\", \"topic\": \"Rust\", ",
+            "\"type\": \"choice\", \"options\": [\"let\", \"mut\"], \"correct\": 0}\n",
+            "{\"question\": \"What does HTTP 404 mean?\", \"type\": \"short\", \"answer\": \"Not found\"}\n",
+            "{\"question\": \"Broken\", \"type\": \"choice\", \"options\": [], \"correct\": 3}\n",
+            "{\"question\": \"Design a cache.\", \"type\": \"open\", \"rubric\": [\"Eviction\"]}\n",
+            "{\"question\": \"One too many\", \"type\": \"short\", \"answer\": \"-\"}",
+        ]));
+        let questions: Vec<_> = model
+            .generate_questionnaire("Backend engineer", 4)
+            .collect()
+            .await;
+
+        assert_eq!(questions.len(), 4);
+        let first = questions[0].as_ref().unwrap();
+        assert_eq!(first.topic.as_deref(), Some("Rust"));
+        assert_eq!(first.correct_answer(), Some("let"));
+        assert_eq!(
+            questions[1].as_ref().unwrap().answer_key,
+            AnswerKey::Expected("Not found".into())
+        );
+        assert!(questions[2].is_err());
+        assert_eq!(
+            questions[3].as_ref().unwrap().answer_key,
+            AnswerKey::Rubric(vec!["Eviction".into()])
+        );
+    }
+}
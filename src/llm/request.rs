@@ -0,0 +1,269 @@
+//! Requests sent to language models.
+//!
+//! A [`Request`] bundles the conversation [`Message`]s, the [`Tools`](crate::llm::tool::Tools)
+//! the model may call, and the generation [`Parameters`](crate::llm::model::Parameters).
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{Message, Request, model::Parameters};
+//!
+//! let request = Request::new([
+//!     Message::system("You are a helpful assistant"),
+//!     Message::user("Write a haiku about Rust"),
+//! ])
+//! .with_parameters(Parameters::default().temperature(0.7));
+//!
+//! assert_eq!(request.messages.len(), 2);
+//! ```
+
+use alloc::{string::String, vec::Vec};
+
+use schemars::Schema;
+
+use crate::llm::{Message, Tool, model::Parameters, tool::Tools};
+
+/// A request to a language model.
+///
+/// Contains the conversation history, registered tools, and generation parameters.
+/// Requests are cheap to clone: tools and the content of messages are shared between
+/// clones, so retries and fallback models can each take their own copy.
+///
+/// With the `serde` feature, requests can be serialized for logging and replay. Tools are
+/// skipped: only their implementations can call them, so they must be registered again.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[non_exhaustive]
+pub struct Request {
+    /// Conversation history, oldest message first.
+    pub messages: Vec<Message>,
+    /// Tools available to the model.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub tools: Tools,
+    /// Generation parameters.
+    pub parameters: Parameters,
+    /// Key identifying this logical request across retries.
+    ///
+    /// Providers that support idempotency forward it so a retried request is not
+    /// executed, or billed, twice.
+    pub idempotency_key: Option<String>,
+    /// The format the response must have.
+    pub response_format: ResponseFormat,
+    /// How many times [`generate`](crate::LanguageModel::generate) asks the model again
+    /// after a response that does not parse, with the parse error. Defaults to `0`.
+    pub parse_retries: u32,
+}
+
+/// The format of a model's response.
+///
+/// Providers supporting structured outputs natively, as declared by
+/// [`Ability::StructuredOutput`](crate::llm::model::Ability::StructuredOutput), enforce
+/// the format while generating. Others ignore it, so [`generate`](crate::LanguageModel::generate)
+/// also describes the schema in the prompt for them.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum ResponseFormat {
+    /// Free-form text.
+    #[default]
+    Text,
+    /// Any valid JSON object.
+    JsonObject,
+    /// JSON conforming to the schema.
+    JsonSchema(Schema),
+}
+
+impl Request {
+    /// Creates a request from a sequence of messages.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ai_types::llm::{Message, Request};
+    ///
+    /// let request = Request::new([Message::user("Hello!")]);
+    /// ```
+    pub fn new(messages: impl IntoIterator<Item = Message>) -> Self {
+        Self {
+            messages: messages.into_iter().collect(),
+            tools: Tools::new(),
+            parameters: Parameters::default(),
+            idempotency_key: None,
+            response_format: ResponseFormat::Text,
+            parse_retries: 0,
+        }
+    }
+
+    /// Creates a two-message request with system and user prompts.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ai_types::llm::{Request, Role};
+    ///
+    /// let request = Request::oneshot("You are a translator", "Translate 'hello' to French");
+    /// assert_eq!(request.messages[0].role(), Role::System);
+    /// assert_eq!(request.messages[1].role(), Role::User);
+    /// ```
+    pub fn oneshot(system: impl Into<String>, user: impl Into<String>) -> Self {
+        Self::new([Message::system(system), Message::user(user)])
+    }
+
+    /// Registers a tool the model may call.
+    #[must_use]
+    pub fn with_tool(mut self, tool: impl Tool) -> Self {
+        self.tools.register(tool);
+        self
+    }
+
+    /// Replaces the tool registry.
+    #[must_use]
+    pub fn with_tools(mut self, tools: Tools) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Sets the generation parameters.
+    #[must_use]
+    pub fn with_parameters(mut self, parameters: Parameters) -> Self {
+        self.parameters = parameters;
+        self
+    }
+
+    /// Sets the idempotency key forwarded to providers that support it.
+    ///
+    /// Retries of the same request must reuse the key, which they do naturally when the
+    /// request is cloned.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ai_types::llm::{Message, Request};
+    ///
+    /// let request = Request::new([Message::user("Hello!")]).with_idempotency_key("order-42");
+    /// assert_eq!(request.idempotency_key.as_deref(), Some("order-42"));
+    /// ```
+    #[must_use]
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+
+    /// Sets the format the response must have.
+    #[must_use]
+    pub fn with_response_format(mut self, format: ResponseFormat) -> Self {
+        self.response_format = format;
+        self
+    }
+
+    /// Sets how many times [`generate`](crate::LanguageModel::generate) asks the model
+    /// again after a response that does not parse.
+    ///
+    /// Each retry sends the unparsable response and the parse error back to the model.
+    #[must_use]
+    pub const fn with_parse_retries(mut self, retries: u32) -> Self {
+        self.parse_retries = retries;
+        self
+    }
+
+    /// Appends a message to the conversation.
+    #[must_use]
+    pub fn with_message(mut self, message: Message) -> Self {
+        self.messages.push(message);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        LanguageModel,
+        llm::{
+            Role,
+            model::{Ability, Profile},
+        },
+        test_util::MockLanguageModel,
+    };
+    use alloc::collections::BTreeMap;
+
+    #[test]
+    fn request_new() {
+        let request = Request::new([Message::user("Hi"), Message::assistant("Hello")]);
+        assert_eq!(request.messages.len(), 2);
+        assert!(request.tools.definitions().is_empty());
+        assert!(request.parameters.temperature.is_none());
+    }
+
+    #[test]
+    fn request_oneshot() {
+        let request = Request::oneshot("system", "user");
+        assert_eq!(request.messages[0].role(), Role::System);
+        assert_eq!(request.messages[0].content(), "system");
+        assert_eq!(request.messages[1].role(), Role::User);
+        assert_eq!(request.messages[1].content(), "user");
+    }
+
+    #[test]
+    fn request_builder() {
+        let request = Request::default()
+            .with_message(Message::user("Hi"))
+            .with_parameters(Parameters::default().max_tokens(10));
+        assert_eq!(request.messages.len(), 1);
+        assert_eq!(request.parameters.max_tokens, Some(10));
+        assert!(request.idempotency_key.is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn request_serde_roundtrip() {
+        let request = Request::oneshot("system", "user")
+            .with_parameters(Parameters::default().temperature(0.5))
+            .with_idempotency_key("key-1");
+
+        let json = serde_json::to_string(&request).unwrap();
+        let restored: Request = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.messages.len(), 2);
+        assert_eq!(restored.messages[0].role(), Role::System);
+        assert_eq!(restored.messages[1].content(), "user");
+        assert_eq!(restored.parameters, request.parameters);
+        assert_eq!(restored.idempotency_key.as_deref(), Some("key-1"));
+    }
+
+    #[tokio::test]
+    async fn generate_uses_native_structured_output() {
+        let structured =
+            Profile::new("model", "A test model", 1024).with_ability(Ability::StructuredOutput);
+        let native = MockLanguageModel::new()
+            .with_profile(structured)
+            .with_reply("{}");
+        let prompted = MockLanguageModel::new().with_reply("{}");
+
+        let request = Request::oneshot("system", "user");
+        let _: BTreeMap<String, u32> = native.generate(request.clone()).await.unwrap();
+        let _: BTreeMap<String, u32> = prompted.generate(request).await.unwrap();
+
+        let sent = &native.requests()[0];
+        assert_eq!(sent.messages.len(), 2);
+        assert!(matches!(
+            sent.response_format,
+            ResponseFormat::JsonSchema(_)
+        ));
+        let sent = &prompted.requests()[0];
+        assert_eq!(sent.messages.len(), 3);
+        assert!(matches!(
+            sent.response_format,
+            ResponseFormat::JsonSchema(_)
+        ));
+    }
+
+    #[test]
+    fn idempotency_key_survives_clone() {
+        let request = Request::oneshot("system", "user").with_idempotency_key("key-1");
+        let retry = request.clone();
+        assert_eq!(retry.idempotency_key, request.idempotency_key);
+    }
+}
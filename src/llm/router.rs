@@ -0,0 +1,959 @@
+//! Routing each request to one of several models.
+//!
+//! No single model is the best choice for every request: a short question does not need
+//! the most capable model, a screenshot needs one with vision, and a long document needs
+//! a large context window. A [`Router`] owns several models and sends each request to
+//! one of them, chosen in two steps.
+//!
+//! First, the [`Requirements`] of the request rule out the models unable to serve it. The
+//! abilities a request needs are inferred from its content: tools need
+//! [`Ability::ToolUse`], images [`Ability::Vision`] and audio [`Ability::Audio`]. Its
+//! prompt, estimated with [`Estimator`], plus its `max_tokens` must fit in the context
+//! window. Requests can also ask for explicit tags, which the chosen model's [`Route`]
+//! must carry.
+//!
+//! Then a [`RoutingPolicy`] picks one of the remaining models. The default policy,
+//! [`Cheapest`], picks the one with the lowest price per token; [`InOrder`] picks the
+//! first, [`SmallestFit`] the one with the smallest context window the prompt fits in, and
+//! any closure can decide instead. [`Weighted`] spreads requests over the
+//! models in proportion to their weights, and [`Sticky`] wraps another policy to keep
+//! each conversation on the model that served its first turn, so the provider's prompt
+//! cache keeps hitting.
+//!
+//! A router is itself a [`LanguageModel`]. Its models share a type, so models of
+//! different types are [boxed](LanguageModel::boxed) first.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{
+//!     LanguageModel, Request, Role,
+//!     router::{Route, Router},
+//! };
+//!
+//! async fn answer(
+//!     small: impl LanguageModel,
+//!     large: impl LanguageModel,
+//!     private: impl LanguageModel,
+//! ) -> ai_types::Result {
+//!     let router = Router::new([
+//!         Route::new(small.boxed()),
+//!         Route::new(large.boxed()),
+//!         Route::new(private.boxed()).with_tag("on-premises"),
+//!     ])
+//!     // The application marks confidential conversations in their system prompt.
+//!     .with_tags(|request: &Request| {
+//!         let confidential = request.messages.iter().any(|message| {
+//!             message.role() == Role::System && message.content().contains("[confidential]")
+//!         });
+//!         confidential.then(|| "on-premises".into()).into_iter().collect()
+//!     });
+//!
+//!     Ok(router.respond(Request::oneshot("Be brief", "Hello!")).await?)
+//! }
+//! ```
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    format,
+    string::String,
+    sync::Arc,
+    vec::Vec,
+};
+use core::{fmt, hash::Hasher};
+
+use async_stream::stream;
+use futures_core::Stream;
+use futures_lite::{StreamExt, pin};
+use spin::Mutex;
+
+use crate::{
+    LanguageModel,
+    error::{Classify, ErrorKind, RawError},
+    hash::Fnv1a,
+    llm::{
+        Message, Request, Role, TextStream,
+        event::StreamEvent,
+        message::Content,
+        model::{Ability, Profile},
+        stream::text_stream,
+        token::{Estimator, TokenCounter},
+    },
+    rate_limit::RateLimitInfo,
+};
+
+type Tags = dyn Fn(&Request) -> Vec<String> + Send + Sync;
+type SessionKey = dyn Fn(&Request) -> Option<String> + Send + Sync;
+
+/// What a model needs to serve a request.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[non_exhaustive]
+pub struct Requirements {
+    /// The abilities the model must have.
+    pub abilities: Vec<Ability>,
+    /// The tokens the request takes up in the context window, including its completion.
+    pub context_length: u32,
+    /// The tags the model's route must carry.
+    pub tags: Vec<String>,
+}
+
+impl Requirements {
+    /// Infers the requirements of `request` from its content.
+    ///
+    /// See the [module documentation](crate::llm::router) for the rules. No tags are
+    /// required.
+    #[must_use]
+    pub fn of(request: &Request) -> Self {
+        let mut abilities = Vec::new();
+        if !request.tools.is_empty() {
+            abilities.push(Ability::ToolUse);
+        }
+        let parts = || request.messages.iter().flat_map(Message::parts);
+        if parts().any(|part| matches!(part, Content::ImageUrl(_) | Content::ImageData { .. })) {
+            abilities.push(Ability::Vision);
+        }
+        if parts().any(|part| matches!(part, Content::AudioData { .. })) {
+            abilities.push(Ability::Audio);
+        }
+
+        let prompt = Estimator::new().count_message_tokens(&request.messages);
+        let prompt = u32::try_from(prompt).unwrap_or(u32::MAX);
+        let completion = request.parameters.max_tokens.unwrap_or(0);
+        Self {
+            abilities,
+            context_length: prompt.saturating_add(completion),
+            tags: Vec::new(),
+        }
+    }
+
+    /// Requires the model's route to carry `tag`.
+    #[must_use]
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Returns whether a model with `profile`, routed with `tags`, meets the requirements.
+    #[must_use]
+    pub fn are_met_by(&self, profile: &Profile, tags: &[String]) -> bool {
+        self.abilities
+            .iter()
+            .all(|ability| profile.abilities.contains(ability))
+            && self.context_length <= profile.context_length
+            && self.tags.iter().all(|tag| tags.contains(tag))
+    }
+}
+
+/// A model of a [`Router`], with its profile and tags.
+#[derive(Debug, Clone)]
+pub struct Route<M> {
+    model: M,
+    profile: Profile,
+    tags: Vec<String>,
+}
+
+impl<M: LanguageModel> Route<M> {
+    /// Creates a route to `model`, reading its profile once.
+    #[must_use]
+    pub fn new(model: M) -> Self {
+        let profile = model.profile();
+        Self {
+            model,
+            profile,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Routes with `profile` instead of the model's own, such as one completed with
+    /// pricing or [probed](crate::llm::probe) abilities.
+    #[must_use]
+    pub fn with_profile(mut self, profile: Profile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Adds a tag that requests can require.
+    #[must_use]
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Returns the model.
+    #[must_use]
+    pub const fn model(&self) -> &M {
+        &self.model
+    }
+
+    /// Returns the profile the route is chosen by.
+    #[must_use]
+    pub const fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// Returns the tags of the route.
+    #[must_use]
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+}
+
+/// Picks which of the models able to serve a request serves it.
+///
+/// Any closure taking the request and the candidates' profiles is a policy.
+pub trait RoutingPolicy: Send + Sync {
+    /// Returns the index of the chosen model in `candidates`, the profiles of the models
+    /// meeting the requirements of `request`, in the router's order.
+    ///
+    /// `candidates` is never empty. An index out of range fails the request.
+    fn select(&self, request: &Request, candidates: &[&Profile]) -> usize;
+}
+
+impl<F: Fn(&Request, &[&Profile]) -> usize + Send + Sync> RoutingPolicy for F {
+    fn select(&self, request: &Request, candidates: &[&Profile]) -> usize {
+        self(request, candidates)
+    }
+}
+
+/// A [`RoutingPolicy`] picking the model with the lowest price per prompt and completion
+/// token.
+///
+/// Models without pricing, such as local ones, count as free. Ties go to the earlier
+/// model.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cheapest;
+
+impl RoutingPolicy for Cheapest {
+    fn select(&self, _request: &Request, candidates: &[&Profile]) -> usize {
+        candidates
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| price(a).total_cmp(&price(b)))
+            .map_or(0, |(index, _)| index)
+    }
+}
+
+/// Returns the price per prompt and completion token of a model, zero without pricing.
+fn price(profile: &Profile) -> f64 {
+    profile
+        .pricing
+        .as_ref()
+        .map_or(0.0, |pricing| pricing.prompt + pricing.completion)
+}
+
+/// A [`RoutingPolicy`] picking the first model, so routes are listed by preference.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InOrder;
+
+impl RoutingPolicy for InOrder {
+    fn select(&self, _request: &Request, _candidates: &[&Profile]) -> usize {
+        0
+    }
+}
+
+/// A [`RoutingPolicy`] picking the model with the smallest context window the request fits
+/// in, such as an 8k model for short prompts and a 128k model for long ones.
+///
+/// The prompt is counted with a [`TokenCounter`], [`Estimator`] by default, to which the
+/// request's `max_tokens` and a [headroom](Self::with_headroom) are added. Among models
+/// with the same context window, the cheapest is picked, as by [`Cheapest`]. A request
+/// fitting none of the candidates by this count goes to the one with the largest window.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::llm::{LanguageModel, router::{Route, Router, SmallestFit}};
+///
+/// fn by_length(short: impl LanguageModel, long: impl LanguageModel) -> impl LanguageModel {
+///     let policy = SmallestFit::new().with_headroom(512);
+///     Router::new([Route::new(short.boxed()), Route::new(long.boxed())]).with_policy(policy)
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct SmallestFit<C = Estimator> {
+    counter: C,
+    headroom: u32,
+}
+
+impl SmallestFit {
+    /// Creates a policy counting prompts with [`Estimator`], without headroom.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            counter: Estimator::new(),
+            headroom: 0,
+        }
+    }
+}
+
+impl Default for SmallestFit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: TokenCounter> SmallestFit<C> {
+    /// Counts prompts with `counter`, such as the tokenizer of the models.
+    #[must_use]
+    pub fn with_counter<D: TokenCounter>(self, counter: D) -> SmallestFit<D> {
+        SmallestFit {
+            counter,
+            headroom: self.headroom,
+        }
+    }
+
+    /// Sets the tokens kept free in the context window on top of the prompt and its
+    /// `max_tokens`, to make up for counting errors.
+    #[must_use]
+    pub const fn with_headroom(mut self, tokens: u32) -> Self {
+        self.headroom = tokens;
+        self
+    }
+
+    /// Returns the tokens `request` needs in the context window.
+    #[must_use]
+    pub fn tokens(&self, request: &Request) -> u32 {
+        let prompt = self.counter.count_message_tokens(&request.messages);
+        u32::try_from(prompt)
+            .unwrap_or(u32::MAX)
+            .saturating_add(request.parameters.max_tokens.unwrap_or(0))
+            .saturating_add(self.headroom)
+    }
+}
+
+impl<C: TokenCounter + Send + Sync> RoutingPolicy for SmallestFit<C> {
+    fn select(&self, request: &Request, candidates: &[&Profile]) -> usize {
+        let tokens = self.tokens(request);
+        let fitting = candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, profile)| profile.context_length >= tokens)
+            .min_by(|(_, a), (_, b)| {
+                a.context_length
+                    .cmp(&b.context_length)
+                    .then(price(a).total_cmp(&price(b)))
+            });
+        // Reversed so ties go to the earlier model, as `max_by_key` keeps the last maximum.
+        let largest = || {
+            candidates
+                .iter()
+                .enumerate()
+                .rev()
+                .max_by_key(|(_, profile)| profile.context_length)
+        };
+        fitting.or_else(largest).map_or(0, |(index, _)| index)
+    }
+}
+
+/// A [`RoutingPolicy`] spreading requests over models in proportion to their weights.
+///
+/// Models are identified by their profile name; models without a weight count as
+/// weighing 1, and a weight of 0 only gets requests no other candidate can serve. The
+/// distribution is a smooth weighted round-robin: with weights 3 and 1, every four
+/// requests go to the models as `A A B A`, not `A A A B`.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::llm::{LanguageModel, router::{Route, Router, Weighted}};
+///
+/// fn balance(primary: impl LanguageModel, secondary: impl LanguageModel) -> impl LanguageModel {
+///     let weights = Weighted::new([(primary.profile().name, 3), (secondary.profile().name, 1)]);
+///     Router::new([Route::new(primary.boxed()), Route::new(secondary.boxed())]).with_policy(weights)
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Weighted {
+    weights: BTreeMap<String, u32>,
+    current: Mutex<BTreeMap<String, i64>>,
+}
+
+impl Weighted {
+    /// Creates a policy with the weight of each model, by profile name.
+    pub fn new<N: Into<String>>(weights: impl IntoIterator<Item = (N, u32)>) -> Self {
+        Self {
+            weights: weights
+                .into_iter()
+                .map(|(name, weight)| (name.into(), weight))
+                .collect(),
+            current: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Returns the weight of the model named `name`.
+    #[must_use]
+    pub fn weight(&self, name: &str) -> u32 {
+        self.weights.get(name).copied().unwrap_or(1)
+    }
+}
+
+impl RoutingPolicy for Weighted {
+    fn select(&self, _request: &Request, candidates: &[&Profile]) -> usize {
+        let mut current = self.current.lock();
+        let mut total = 0;
+        let mut best: Option<(usize, i64)> = None;
+        for (index, profile) in candidates.iter().enumerate() {
+            let weight = i64::from(self.weight(&profile.name));
+            total += weight;
+            let score = current.entry(profile.name.clone()).or_insert(0);
+            *score += weight;
+            if best.is_none_or(|(_, best)| *score > best) {
+                best = Some((index, *score));
+            }
+        }
+        let index = best.map_or(0, |(index, _)| index);
+        if let Some(score) = current.get_mut(&candidates[index].name) {
+            *score -= total;
+        }
+        index
+    }
+}
+
+/// A [`RoutingPolicy`] sending every turn of a conversation to the same model.
+///
+/// Providers cache the prompts they have seen, and a cache hit is cheaper and faster, but
+/// only on the model that served the previous turns. The first request of a session is
+/// routed by the wrapped policy, and later requests of the session go to the same model
+/// as long as it can serve them.
+///
+/// Sessions are identified by a [key](Self::with_key). The default key hashes the
+/// messages up to the first user message, which stay the same as a conversation grows.
+/// The policy remembers the most recent sessions, up to a [capacity](Self::with_capacity).
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::llm::router::{Sticky, Weighted};
+///
+/// // Conversations are keyed by their opening messages, so each stays in one region.
+/// let policy = Sticky::new(Weighted::new([("eu", 1), ("us", 1)])).with_capacity(1000);
+/// ```
+pub struct Sticky<P = Cheapest> {
+    policy: P,
+    key: Arc<SessionKey>,
+    capacity: usize,
+    sessions: Mutex<Sessions>,
+}
+
+/// The model each session is pinned to, and the sessions from oldest to newest.
+#[derive(Debug, Default)]
+struct Sessions {
+    models: BTreeMap<String, String>,
+    order: VecDeque<String>,
+}
+
+impl<P: fmt::Debug> fmt::Debug for Sticky<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sticky")
+            .field("policy", &self.policy)
+            .field("capacity", &self.capacity)
+            .field("sessions", &self.sessions.lock().order.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P: RoutingPolicy> Sticky<P> {
+    /// Wraps `policy`, which routes the first request of each session.
+    ///
+    /// Remembers up to 10,000 sessions.
+    pub fn new(policy: P) -> Self {
+        Self {
+            policy,
+            key: Arc::new(conversation_key),
+            capacity: 10_000,
+            sessions: Mutex::new(Sessions::default()),
+        }
+    }
+
+    /// Identifies the session of each request with `key`, such as a user or conversation
+    /// ID. Requests without a key are routed by the wrapped policy alone.
+    #[must_use]
+    pub fn with_key(
+        mut self,
+        key: impl Fn(&Request) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.key = Arc::new(key);
+        self
+    }
+
+    /// Sets how many sessions are remembered; the oldest is forgotten first.
+    #[must_use]
+    pub const fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Returns the name of the model the session `key` is pinned to, if any.
+    #[must_use]
+    pub fn model_of(&self, key: &str) -> Option<String> {
+        self.sessions.lock().models.get(key).cloned()
+    }
+}
+
+impl<P: RoutingPolicy> RoutingPolicy for Sticky<P> {
+    fn select(&self, request: &Request, candidates: &[&Profile]) -> usize {
+        let Some(key) = (self.key)(request) else {
+            return self.policy.select(request, candidates);
+        };
+        let mut sessions = self.sessions.lock();
+        if let Some(model) = sessions.models.get(&key) {
+            if let Some(index) = candidates.iter().position(|profile| profile.name == *model) {
+                return index;
+            }
+        }
+
+        let index = self.policy.select(request, candidates);
+        if let Some(profile) = candidates.get(index) {
+            if sessions
+                .models
+                .insert(key.clone(), profile.name.clone())
+                .is_none()
+            {
+                sessions.order.push_back(key);
+            }
+            while sessions.order.len() > self.capacity {
+                if let Some(oldest) = sessions.order.pop_front() {
+                    sessions.models.remove(&oldest);
+                }
+            }
+        }
+        index
+    }
+}
+
+/// Hashes the messages up to the first user message, or `None` if there is none.
+fn conversation_key(request: &Request) -> Option<String> {
+    let first = request
+        .messages
+        .iter()
+        .position(|message| message.role() == Role::User)?;
+    let mut hasher = Fnv1a::new();
+    for message in &request.messages[..=first] {
+        hasher.write(message.content().as_bytes());
+        hasher.write_u8(0);
+    }
+    Some(format!("{:016x}", hasher.finish()))
+}
+
+/// A language model sending each request to one of several models.
+///
+/// See the [module documentation](crate::llm::router) for details.
+pub struct Router<M, P = Cheapest> {
+    routes: Vec<Route<M>>,
+    policy: P,
+    tags: Option<Arc<Tags>>,
+}
+
+impl<M: fmt::Debug, P: fmt::Debug> fmt::Debug for Router<M, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Router")
+            .field("routes", &self.routes)
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M: LanguageModel> Router<M> {
+    /// Creates a router between `routes`, picking the cheapest model able to serve each
+    /// request.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `routes` is empty.
+    #[must_use]
+    pub fn new(routes: impl IntoIterator<Item = Route<M>>) -> Self {
+        let routes: Vec<Route<M>> = routes.into_iter().collect();
+        assert!(!routes.is_empty(), "A router needs at least one route");
+        Self {
+            routes,
+            policy: Cheapest,
+            tags: None,
+        }
+    }
+}
+
+impl<M: LanguageModel, P: RoutingPolicy> Router<M, P> {
+    /// Picks among the models able to serve a request with `policy`.
+    #[must_use]
+    pub fn with_policy<Q: RoutingPolicy>(self, policy: Q) -> Router<M, Q> {
+        Router {
+            routes: self.routes,
+            policy,
+            tags: self.tags,
+        }
+    }
+
+    /// Derives the tags each request requires with `tags`.
+    #[must_use]
+    pub fn with_tags(
+        mut self,
+        tags: impl Fn(&Request) -> Vec<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.tags = Some(Arc::new(tags));
+        self
+    }
+
+    /// Returns the routes, in order.
+    #[must_use]
+    pub fn routes(&self) -> &[Route<M>] {
+        &self.routes
+    }
+
+    /// Returns the requirements of `request`, including the tags it requires.
+    #[must_use]
+    pub fn requirements(&self, request: &Request) -> Requirements {
+        let mut requirements = Requirements::of(request);
+        if let Some(tags) = &self.tags {
+            requirements.tags = tags(request);
+        }
+        requirements
+    }
+
+    /// Returns the route `request` is sent to, or `None` if no model can serve it.
+    #[must_use]
+    pub fn select(&self, request: &Request) -> Option<&Route<M>> {
+        let requirements = self.requirements(request);
+        let candidates: Vec<&Route<M>> = self
+            .routes
+            .iter()
+            .filter(|route| requirements.are_met_by(&route.profile, &route.tags))
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        let profiles: Vec<&Profile> = candidates.iter().map(|route| &route.profile).collect();
+        candidates
+            .get(self.policy.select(request, &profiles))
+            .copied()
+    }
+
+    fn routed<'a, I, S>(
+        route: Option<&'a Route<M>>,
+        attempt: impl FnOnce(&'a M) -> S + Send,
+    ) -> impl Stream<Item = Result<I, RouterError<M::Error>>> + Send
+    where
+        I: Send,
+        S: Stream<Item = Result<I, M::Error>> + Send,
+    {
+        stream! {
+            let Some(route) = route else {
+                yield Err(RouterError::NoRoute);
+                return;
+            };
+            let items = attempt(&route.model);
+            pin!(items);
+            while let Some(item) = items.next().await {
+                yield item.map_err(RouterError::Model);
+            }
+        }
+    }
+}
+
+impl<M: LanguageModel, P: RoutingPolicy + 'static> LanguageModel for Router<M, P> {
+    type Error = RouterError<M::Error>;
+
+    fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+        let route = self.select(&request);
+        text_stream(Self::routed(route, move |model| model.respond(request)))
+    }
+
+    fn respond_events(
+        &self,
+        request: Request,
+    ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+        let route = self.select(&request);
+        Self::routed(route, move |model| model.respond_events(request))
+    }
+
+    /// Routes the request once, and asks the chosen model for every candidate.
+    fn respond_n(&self, request: Request) -> Vec<impl TextStream<Error = Self::Error> + Send> {
+        let candidates: Vec<_> = self.select(&request).map_or_else(
+            || alloc::vec![None],
+            |route| {
+                route
+                    .model
+                    .respond_n(request)
+                    .into_iter()
+                    .map(Some)
+                    .collect()
+            },
+        );
+        candidates
+            .into_iter()
+            .map(|candidate| {
+                text_stream(stream! {
+                    let Some(chunks) = candidate else {
+                        yield Err(RouterError::NoRoute);
+                        return;
+                    };
+                    pin!(chunks);
+                    while let Some(chunk) = chunks.next().await {
+                        yield chunk.map_err(RouterError::Model);
+                    }
+                })
+            })
+            .collect()
+    }
+
+    fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+        let route = self.select(&Request::new([Message::user(prefix)]));
+        text_stream(Self::routed(route, move |model| model.complete(prefix)))
+    }
+
+    /// Returns a profile combining the routes: every ability of any model, and the
+    /// largest context window.
+    fn profile(&self) -> Profile {
+        let names: Vec<&str> = self
+            .routes
+            .iter()
+            .map(|route| route.profile.name.as_str())
+            .collect();
+        let abilities: BTreeSet<Ability> = self
+            .routes
+            .iter()
+            .flat_map(|route| route.profile.abilities.iter().copied())
+            .collect();
+        let context_length = self
+            .routes
+            .iter()
+            .map(|route| route.profile.context_length)
+            .max()
+            .unwrap_or(0);
+        Profile::new(
+            "router",
+            alloc::format!("Routes between {}", names.join(", ")),
+            context_length,
+        )
+        .with_abilities(abilities)
+    }
+}
+
+/// An error returned by [`Router`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouterError<E> {
+    /// No model meets the requirements of the request.
+    NoRoute,
+    /// The chosen model failed.
+    Model(E),
+}
+
+impl<E: fmt::Display> fmt::Display for RouterError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoRoute => f.write_str("No model can serve the request"),
+            Self::Model(error) => error.fmt(f),
+        }
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for RouterError<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::NoRoute => None,
+            Self::Model(error) => Some(error),
+        }
+    }
+}
+
+impl<E: Classify> Classify for RouterError<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::NoRoute => ErrorKind::InvalidRequest,
+            Self::Model(error) => error.kind(),
+        }
+    }
+
+    fn retry_after(&self) -> Option<core::time::Duration> {
+        match self {
+            Self::NoRoute => None,
+            Self::Model(error) => error.retry_after(),
+        }
+    }
+
+    fn rate_limit(&self) -> Option<RateLimitInfo> {
+        match self {
+            Self::NoRoute => None,
+            Self::Model(error) => error.rate_limit(),
+        }
+    }
+
+    fn raw(&self) -> Option<&RawError> {
+        match self {
+            Self::NoRoute => None,
+            Self::Model(error) => error.raw(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        llm::{
+            model::Pricing,
+            tool::{DynTool, ToolDefinition, Tools},
+        },
+        test_util::MockLanguageModel,
+    };
+    use alloc::vec;
+
+    struct Clock;
+
+    impl DynTool for Clock {
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition::from_parts("clock", "Tells the time", schemars::json_schema!(true))
+        }
+
+        async fn call(&mut self, _arguments: String) -> crate::Result {
+            Ok("noon".into())
+        }
+    }
+
+    /// Answers with its name.
+    fn model(
+        name: &str,
+        context_length: u32,
+        price: f64,
+        abilities: &[Ability],
+    ) -> MockLanguageModel {
+        let pricing = Pricing {
+            prompt: price,
+            ..Pricing::default()
+        };
+        let profile = Profile::new(name, "", context_length)
+            .with_abilities(abilities.iter().copied())
+            .with_pricing(pricing);
+        MockLanguageModel::new()
+            .with_profile(profile)
+            .with_reply(name)
+    }
+
+    fn router() -> Router<MockLanguageModel> {
+        Router::new([
+            Route::new(model(
+                "large",
+                100_000,
+                3.0,
+                &[Ability::ToolUse, Ability::Vision],
+            )),
+            Route::new(model("small", 100, 1.0, &[])),
+            Route::new(model("tools", 1000, 2.0, &[Ability::ToolUse])),
+        ])
+    }
+
+    #[tokio::test]
+    async fn routes_to_the_cheapest_capable_model() {
+        let router = router();
+        let short = Request::oneshot("Be brief", "Hi");
+        assert_eq!(router.respond(short.clone()).await.unwrap(), "small");
+
+        let mut tools = Tools::new();
+        tools.register_dyn(Clock);
+        let with_tools = short.clone().with_tools(tools);
+        assert_eq!(router.respond(with_tools).await.unwrap(), "tools");
+
+        let long = Request::oneshot("Be brief", "word ".repeat(1000));
+        assert_eq!(router.respond(long).await.unwrap(), "large");
+
+        let image =
+            Request::new([Message::user("What is it?")
+                .with_content(Content::image_data(vec![0], "image/png"))]);
+        assert_eq!(Requirements::of(&image).abilities, [Ability::Vision]);
+        assert_eq!(router.respond(image).await.unwrap(), "large");
+
+        let profile = router.profile();
+        assert_eq!(profile.context_length, 100_000);
+        assert_eq!(profile.abilities, [Ability::ToolUse, Ability::Vision]);
+    }
+
+    fn pair() -> Vec<Route<MockLanguageModel>> {
+        vec![
+            Route::new(model("a", 1000, 1.0, &[])),
+            Route::new(model("b", 1000, 1.0, &[])),
+        ]
+    }
+
+    #[tokio::test]
+    async fn spreads_requests_by_weight() {
+        let router = Router::new(pair()).with_policy(Weighted::new([("a", 3), ("b", 1)]));
+        let mut served = Vec::new();
+        for _ in 0..8 {
+            let request = Request::oneshot("Be brief", "Hi");
+            served.push(router.respond(request).await.unwrap());
+        }
+        assert_eq!(served, ["a", "a", "b", "a", "a", "a", "b", "a"]);
+    }
+
+    #[tokio::test]
+    async fn keeps_conversations_on_one_model() {
+        let policy = Sticky::new(Weighted::new([("a", 1), ("b", 1)])).with_capacity(1);
+        let router = Router::new(pair()).with_policy(policy);
+
+        let first = Request::oneshot("Be brief", "Hi");
+        let second = Request::oneshot("Be brief", "Hello");
+        assert_eq!(router.respond(first.clone()).await.unwrap(), "a");
+        let next_turn = first
+            .clone()
+            .with_message(Message::assistant("Hey"))
+            .with_message(Message::user("How are you?"));
+        assert_eq!(router.respond(next_turn.clone()).await.unwrap(), "a");
+
+        // Only one session is remembered, so the first is forgotten.
+        assert_eq!(router.respond(second).await.unwrap(), "b");
+        assert_eq!(router.respond(next_turn).await.unwrap(), "a");
+        let key = conversation_key(&first).unwrap();
+        assert_eq!(router.policy.model_of(&key).as_deref(), Some("a"));
+    }
+
+    #[tokio::test]
+    async fn picks_the_smallest_context_window_that_fits() {
+        let router = Router::new([
+            Route::new(model("long", 128_000, 0.5, &[])),
+            Route::new(model("short-pricey", 8000, 2.0, &[])),
+            Route::new(model("short", 8000, 1.0, &[])),
+        ])
+        .with_policy(SmallestFit::new());
+
+        let short = Request::oneshot("Be brief", "Hi");
+        assert_eq!(router.respond(short.clone()).await.unwrap(), "short");
+        let long = Request::oneshot("Be brief", "word ".repeat(10_000));
+        assert_eq!(router.respond(long).await.unwrap(), "long");
+
+        let router = router.with_policy(SmallestFit::new().with_headroom(7990));
+        assert_eq!(router.respond(short.clone()).await.unwrap(), "long");
+
+        // A counter seeing more tokens than fit anywhere falls back to the largest window.
+        let pessimistic = |_: &str| 1_000_000;
+        let router = router.with_policy(SmallestFit::new().with_counter(pessimistic));
+        assert_eq!(router.respond(short).await.unwrap(), "long");
+    }
+
+    #[tokio::test]
+    async fn honors_tags_and_custom_policies() {
+        let router = Router::new([
+            Route::new(model("cloud", 1000, 1.0, &[])),
+            Route::new(model("local", 1000, 5.0, &[])).with_tag("private"),
+        ])
+        .with_tags(|request: &Request| {
+            let system = request.messages.iter().filter(|m| m.role() == Role::System);
+            system
+                .filter_map(|message| message.content().strip_prefix("Needs ").map(String::from))
+                .collect()
+        });
+
+        let request = Request::oneshot("Be brief", "Hi");
+        assert_eq!(router.respond(request.clone()).await.unwrap(), "cloud");
+        let private = Request::oneshot("Needs private", "Hi");
+        assert_eq!(router.respond(private).await.unwrap(), "local");
+        let unknown = Request::oneshot("Needs gpu", "Hi");
+        assert_eq!(
+            router.respond(unknown).await.unwrap_err(),
+            RouterError::NoRoute
+        );
+
+        let last = router.with_policy(|_: &Request, candidates: &[&Profile]| candidates.len() - 1);
+        assert_eq!(last.respond(request).await.unwrap(), "local");
+    }
+}
@@ -0,0 +1,1458 @@
+//! Streaming text responses.
+//!
+//! Language models deliver their output incrementally. This module provides the
+//! [`TextStream`] trait, which unifies chunk-by-chunk processing ([`Stream`]) and
+//! collecting the complete response ([`IntoFuture`]), together with adapters that
+//! operate on any text stream.
+//!
+//! # Examples
+//!
+//! ## Wrapping an existing stream
+//!
+//! ```rust
+//! use ai_types::llm::stream::text_stream;
+//! use futures_lite::{StreamExt, stream};
+//!
+//! # tokio_test::block_on(async {
+//! let chunks = stream::iter(["Hello, ", "world!"]).map(|s| Ok::<_, std::io::Error>(s.to_string()));
+//! let text = text_stream(chunks).await.unwrap();
+//! assert_eq!(text, "Hello, world!");
+//! # });
+//! ```
+//!
+//! ## Fanning out one response to several consumers
+//!
+//! ```rust
+//! use ai_types::llm::{TextStream, stream::text_stream};
+//! use core::future::IntoFuture;
+//! use futures_lite::{StreamExt, stream};
+//!
+//! # tokio_test::block_on(async {
+//! let chunks = stream::iter(["Hello, ", "world!"]).map(|s| Ok::<_, std::io::Error>(s.to_string()));
+//! let broadcast = text_stream(chunks).broadcast(8);
+//!
+//! let ui = broadcast.subscribe();
+//! let logger = broadcast.subscribe();
+//! drop(broadcast);
+//!
+//! let (shown, logged) =
+//!     futures_lite::future::zip(ui.into_future(), logger.into_future()).await;
+//! assert_eq!(shown.unwrap(), "Hello, world!");
+//! assert_eq!(logged.unwrap(), "Hello, world!");
+//! # });
+//! ```
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    string::String,
+    sync::Arc,
+    task::Wake,
+    vec::Vec,
+};
+use core::{
+    future::{Future, IntoFuture},
+    ops::Range,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+use futures_core::Stream;
+use futures_lite::{StreamExt, future, pin};
+use spin::Mutex;
+
+use crate::time::Timer;
+
+/// A stream of text chunks that can also be awaited as a complete response.
+///
+/// Every text stream implements both [`Stream<Item = Result<String, Error>>`](Stream) for
+/// chunk-by-chunk processing and [`IntoFuture<Output = Result<String, Error>>`](IntoFuture)
+/// for collecting the complete response.
+///
+/// Use [`text_stream`] to turn any compatible [`Stream`] into a `TextStream`.
+pub trait TextStream:
+    Stream<Item = Result<String, <Self as TextStream>::Error>>
+    + IntoFuture<Output = Result<String, <Self as TextStream>::Error>>
+    + Unpin
+{
+    /// The error type yielded by this stream.
+    type Error;
+
+    /// Shares this stream between several consumers.
+    ///
+    /// See [`Broadcast`] for details.
+    fn broadcast(self, capacity: usize) -> Broadcast<Self>
+    where
+        Self: Sized,
+    {
+        Broadcast::new(self, capacity)
+    }
+
+    /// Annotates each chunk with its position in the accumulated response.
+    ///
+    /// See [`Offsets`] for details.
+    fn with_offsets(self) -> Offsets<Self>
+    where
+        Self: Sized,
+    {
+        Offsets::new(self)
+    }
+
+    /// Re-chunks the stream into complete sentences.
+    ///
+    /// See [`Sentences`] for details.
+    fn sentences(self) -> Sentences<Self>
+    where
+        Self: Sized,
+    {
+        Sentences::new(self, Boundary::Sentence)
+    }
+
+    /// Re-chunks the stream into complete paragraphs.
+    ///
+    /// See [`Sentences`] for details.
+    fn paragraphs(self) -> Sentences<Self>
+    where
+        Self: Sized,
+    {
+        Sentences::new(self, Boundary::Paragraph)
+    }
+
+    /// Collects the response, keeping the text received before an error or cancellation.
+    ///
+    /// See [`CollectPartial`] for details.
+    fn collect_partial(self) -> CollectPartial<Self>
+    where
+        Self: Sized,
+    {
+        CollectPartial::new(self)
+    }
+}
+
+/// Converts a stream of text chunks into a [`TextStream`].
+///
+/// The returned stream yields the same chunks and can be awaited to collect them.
+pub fn text_stream<S, E>(stream: S) -> TextChunks<S>
+where
+    S: Stream<Item = Result<String, E>>,
+{
+    TextChunks {
+        stream: alloc::boxed::Box::pin(stream),
+    }
+}
+
+/// A [`TextStream`] backed by an arbitrary chunk stream.
+///
+/// Created by [`text_stream`].
+pub struct TextChunks<S> {
+    stream: Pin<alloc::boxed::Box<S>>,
+}
+
+impl<S> core::fmt::Debug for TextChunks<S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TextChunks").finish_non_exhaustive()
+    }
+}
+
+impl<S, E> Stream for TextChunks<S>
+where
+    S: Stream<Item = Result<String, E>>,
+{
+    type Item = Result<String, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.stream.as_mut().poll_next(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+impl<S, E> IntoFuture for TextChunks<S>
+where
+    S: Stream<Item = Result<String, E>>,
+{
+    type Output = Result<String, E>;
+    type IntoFuture = Collect<Self>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Collect::new(self)
+    }
+}
+
+impl<S, E> TextStream for TextChunks<S>
+where
+    S: Stream<Item = Result<String, E>>,
+{
+    type Error = E;
+}
+
+/// Future collecting every chunk of a text stream into a single [`String`].
+///
+/// Returned by the [`IntoFuture`] implementation of the text streams in this crate.
+#[derive(Debug)]
+pub struct Collect<S> {
+    stream: S,
+    text: String,
+}
+
+impl<S> Collect<S> {
+    /// Creates a future collecting `stream`.
+    pub const fn new(stream: S) -> Self {
+        Self {
+            stream,
+            text: String::new(),
+        }
+    }
+}
+
+impl<S, E> Future for Collect<S>
+where
+    S: Stream<Item = Result<String, E>> + Unpin,
+{
+    type Output = Result<String, E>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        loop {
+            match Pin::new(&mut this.stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => this.text.push_str(&chunk),
+                Poll::Ready(Some(Err(error))) => return Poll::Ready(Err(error)),
+                Poll::Ready(None) => return Poll::Ready(Ok(core::mem::take(&mut this.text))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Future collecting a text stream, keeping the partial response if it is interrupted.
+///
+/// Awaiting a [`TextStream`] discards the text received so far when the stream fails.
+/// This future instead returns it in a [`PartialResponse`] together with the error, so
+/// an application can still show the partial answer.
+///
+/// It is also safe to stop: the future is [`Unpin`] and can be polled by reference, and
+/// [`text`](Self::text) and [`cancel`](Self::cancel) give access to the text received
+/// so far after a race with a timeout or a stop button. [`until`](Self::until) does
+/// this for a given stop signal.
+///
+/// Created by [`TextStream::collect_partial`].
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::llm::{TextStream, stream::{Interruption, text_stream}};
+/// use futures_lite::{StreamExt, stream};
+///
+/// # tokio_test::block_on(async {
+/// let chunks = stream::iter(["The answer ", "is"])
+///     .map(|s| Ok::<_, std::io::Error>(s.to_string()))
+///     .chain(stream::pending());
+/// let stop = async {};
+///
+/// let partial = text_stream(chunks).collect_partial().until(stop).await.unwrap_err();
+/// assert_eq!(partial.text, "The answer is");
+/// assert!(matches!(partial.cause, Interruption::Cancelled));
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct CollectPartial<S> {
+    stream: S,
+    text: String,
+}
+
+impl<S: TextStream> CollectPartial<S> {
+    /// Creates a future collecting `stream`.
+    pub const fn new(stream: S) -> Self {
+        Self {
+            stream,
+            text: String::new(),
+        }
+    }
+
+    /// Returns the text received so far.
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Stops collecting, returning the text received so far.
+    #[must_use]
+    pub fn cancel(self) -> PartialResponse<S::Error> {
+        PartialResponse {
+            text: self.text,
+            cause: Interruption::Cancelled,
+        }
+    }
+
+    /// Collects the response until `signal` completes, then cancels.
+    ///
+    /// # Errors
+    ///
+    /// Returns the partial response if the stream fails or `signal` completes first.
+    pub async fn until(
+        mut self,
+        signal: impl Future<Output = ()>,
+    ) -> Result<String, PartialResponse<S::Error>> {
+        let finished = future::or(async { Some((&mut self).await) }, async {
+            signal.await;
+            None
+        })
+        .await;
+        finished.unwrap_or_else(|| Err(self.cancel()))
+    }
+}
+
+impl<S: TextStream> Future for CollectPartial<S> {
+    type Output = Result<String, PartialResponse<S::Error>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        loop {
+            match Pin::new(&mut this.stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => this.text.push_str(&chunk),
+                Poll::Ready(Some(Err(error))) => {
+                    return Poll::Ready(Err(PartialResponse {
+                        text: core::mem::take(&mut this.text),
+                        cause: Interruption::Failed(error),
+                    }));
+                }
+                Poll::Ready(None) => return Poll::Ready(Ok(core::mem::take(&mut this.text))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Why a response collected by [`CollectPartial`] is incomplete.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Interruption<E> {
+    /// Collection was stopped before the stream ended.
+    Cancelled,
+    /// The stream failed with this error.
+    Failed(E),
+}
+
+/// The text received before a response was interrupted, and why.
+///
+/// Returned by [`CollectPartial`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialResponse<E> {
+    /// The text received before the interruption.
+    pub text: String,
+    /// Why the response is incomplete.
+    pub cause: Interruption<E>,
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for PartialResponse<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.cause {
+            Interruption::Cancelled => {
+                write!(f, "Response cancelled after {} bytes", self.text.len())
+            }
+            Interruption::Failed(error) => {
+                write!(
+                    f,
+                    "Response failed after {} bytes: {error}",
+                    self.text.len()
+                )
+            }
+        }
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for PartialResponse<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match &self.cause {
+            Interruption::Cancelled => None,
+            Interruption::Failed(error) => Some(error),
+        }
+    }
+}
+
+/// Shares one [`TextStream`] between several consumers.
+///
+/// Each [`Subscriber`] receives every chunk produced after it subscribed. Chunks are kept
+/// in a single shared buffer of at most `capacity` entries and released as soon as every
+/// subscriber has seen them, so a long response is never buffered once per consumer.
+///
+/// When the buffer is full, faster subscribers wait for the slowest one to catch up, so
+/// backpressure from any consumer propagates to the underlying stream. Dropping a
+/// subscriber releases its hold on the buffer.
+///
+/// Errors are shared between subscribers through an [`Arc`].
+///
+/// Created by [`TextStream::broadcast`].
+pub struct Broadcast<S: TextStream> {
+    shared: Arc<Shared<S>>,
+}
+
+impl<S: TextStream> core::fmt::Debug for Broadcast<S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Broadcast")
+            .field("capacity", &self.shared.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+type Chunk<E> = Result<String, Arc<E>>;
+
+struct Shared<S: TextStream> {
+    capacity: usize,
+    state: Mutex<State<S>>,
+    wakers: Arc<WakerSet>,
+}
+
+struct State<S: TextStream> {
+    source: S,
+    buffer: VecDeque<Chunk<S::Error>>,
+    /// Absolute index of the first buffered chunk.
+    offset: usize,
+    /// Absolute index of the next chunk each subscriber will read.
+    cursors: BTreeMap<usize, usize>,
+    next_id: usize,
+    finished: bool,
+}
+
+impl<S: TextStream> State<S> {
+    fn end(&self) -> usize {
+        self.offset + self.buffer.len()
+    }
+
+    fn release(&mut self) {
+        let oldest = self
+            .cursors
+            .values()
+            .copied()
+            .min()
+            .unwrap_or_else(|| self.end());
+        while self.offset < oldest && self.buffer.pop_front().is_some() {
+            self.offset += 1;
+        }
+    }
+}
+
+#[derive(Default)]
+struct WakerSet {
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl WakerSet {
+    fn register(&self, waker: &Waker) {
+        let mut wakers = self.wakers.lock();
+        if !wakers.iter().any(|registered| registered.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+
+    fn wake_all(&self) {
+        let wakers = core::mem::take(&mut *self.wakers.lock());
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+impl Wake for WakerSet {
+    fn wake(self: Arc<Self>) {
+        self.wake_all();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.wake_all();
+    }
+}
+
+impl<S: TextStream> Broadcast<S> {
+    /// Creates a broadcast over `source` buffering at most `capacity` chunks.
+    ///
+    /// A `capacity` of zero is treated as one.
+    pub fn new(source: S, capacity: usize) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                capacity: capacity.max(1),
+                state: Mutex::new(State {
+                    source,
+                    buffer: VecDeque::new(),
+                    offset: 0,
+                    cursors: BTreeMap::new(),
+                    next_id: 0,
+                    finished: false,
+                }),
+                wakers: Arc::default(),
+            }),
+        }
+    }
+
+    /// Creates a new subscriber.
+    ///
+    /// The subscriber receives every chunk produced from now on; chunks already
+    /// released from the buffer are not replayed.
+    #[must_use]
+    pub fn subscribe(&self) -> Subscriber<S> {
+        let mut state = self.shared.state.lock();
+        let id = state.next_id;
+        state.next_id += 1;
+        let end = state.end();
+        state.cursors.insert(id, end);
+        drop(state);
+
+        Subscriber {
+            id,
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+/// One consumer of a [`Broadcast`].
+///
+/// Implements [`TextStream`], so it can be iterated chunk by chunk or awaited.
+pub struct Subscriber<S: TextStream> {
+    id: usize,
+    shared: Arc<Shared<S>>,
+}
+
+impl<S: TextStream> core::fmt::Debug for Subscriber<S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Subscriber")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: TextStream> Stream for Subscriber<S> {
+    type Item = Chunk<S::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let shared = &self.shared;
+        let mut state = shared.state.lock();
+        let Some(&cursor) = state.cursors.get(&self.id) else {
+            return Poll::Ready(None);
+        };
+
+        if cursor < state.end() {
+            let chunk = state.buffer[cursor - state.offset].clone();
+            state.cursors.insert(self.id, cursor + 1);
+            state.release();
+            drop(state);
+            shared.wakers.wake_all();
+            return Poll::Ready(Some(chunk));
+        }
+
+        if state.finished {
+            return Poll::Ready(None);
+        }
+
+        if state.buffer.len() >= shared.capacity {
+            shared.wakers.register(cx.waker());
+            return Poll::Pending;
+        }
+
+        shared.wakers.register(cx.waker());
+        let waker = Waker::from(shared.wakers.clone());
+        let mut source_cx = Context::from_waker(&waker);
+        match Pin::new(&mut state.source).poll_next(&mut source_cx) {
+            Poll::Ready(Some(item)) => {
+                let chunk = item.map_err(Arc::new);
+                state.buffer.push_back(chunk.clone());
+                state.cursors.insert(self.id, cursor + 1);
+                state.release();
+                drop(state);
+                shared.wakers.wake_all();
+                Poll::Ready(Some(chunk))
+            }
+            Poll::Ready(None) => {
+                state.finished = true;
+                drop(state);
+                shared.wakers.wake_all();
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S: TextStream> Drop for Subscriber<S> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock();
+        state.cursors.remove(&self.id);
+        state.release();
+        drop(state);
+        self.shared.wakers.wake_all();
+    }
+}
+
+impl<S: TextStream> IntoFuture for Subscriber<S> {
+    type Output = Chunk<S::Error>;
+    type IntoFuture = Collect<Self>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Collect::new(self)
+    }
+}
+
+impl<S: TextStream> TextStream for Subscriber<S> {
+    type Error = Arc<S::Error>;
+}
+
+/// The position of a chunk in the accumulated response.
+///
+/// Every range is half-open and measured from the start of the response. Byte offsets
+/// index the Rust string; UTF-16 offsets match JavaScript string indices, so frontends
+/// can highlight or select chunks without recomputing positions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct ChunkSpan {
+    /// The zero-based position of the chunk in the stream.
+    pub index: usize,
+    /// The range of the chunk in bytes.
+    pub bytes: Range<usize>,
+    /// The range of the chunk in Unicode scalar values.
+    pub chars: Range<usize>,
+    /// The range of the chunk in UTF-16 code units.
+    pub utf16: Range<usize>,
+}
+
+/// A chunk of text together with its [`ChunkSpan`].
+///
+/// Yielded by [`Offsets`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct OffsetChunk {
+    /// The text of the chunk.
+    pub text: String,
+    /// The position of the chunk in the accumulated response.
+    pub span: ChunkSpan,
+}
+
+/// Annotates the chunks of a [`TextStream`] with their positions in the response.
+///
+/// The adapter keeps the accumulated text and the span of every chunk seen so far, so
+/// the chunk under a cursor can be found with [`span_at`](Self::span_at).
+///
+/// Created by [`TextStream::with_offsets`].
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::llm::{TextStream, stream::text_stream};
+/// use futures_lite::{StreamExt, stream};
+///
+/// # tokio_test::block_on(async {
+/// let chunks = stream::iter(["Héllo, ", "wörld!"]).map(|s| Ok::<_, std::io::Error>(s.to_string()));
+/// let mut offsets = text_stream(chunks).with_offsets();
+///
+/// let first = offsets.next().await.unwrap().unwrap();
+/// assert_eq!(first.span.bytes, 0..8);
+/// let second = offsets.next().await.unwrap().unwrap();
+/// assert_eq!(second.span.bytes, 8..15);
+/// assert_eq!(second.span.chars, 7..13);
+///
+/// assert_eq!(offsets.text(), "Héllo, wörld!");
+/// assert_eq!(offsets.span_at(9).unwrap().index, 1);
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct Offsets<S> {
+    stream: S,
+    text: String,
+    spans: Vec<ChunkSpan>,
+    chars: usize,
+    utf16: usize,
+}
+
+impl<S> Offsets<S> {
+    /// Creates an adapter annotating the chunks of `stream`.
+    pub const fn new(stream: S) -> Self {
+        Self {
+            stream,
+            text: String::new(),
+            spans: Vec::new(),
+            chars: 0,
+            utf16: 0,
+        }
+    }
+
+    /// Returns the text accumulated so far.
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the spans of the chunks yielded so far, in order.
+    #[must_use]
+    pub fn spans(&self) -> &[ChunkSpan] {
+        &self.spans
+    }
+
+    /// Returns the span of the chunk containing the byte at `offset`.
+    #[must_use]
+    pub fn span_at(&self, offset: usize) -> Option<&ChunkSpan> {
+        let index = self.spans.partition_point(|span| span.bytes.end <= offset);
+        self.spans
+            .get(index)
+            .filter(|span| span.bytes.contains(&offset))
+    }
+
+    /// Returns the accumulated text and the spans of its chunks.
+    #[must_use]
+    pub fn into_parts(self) -> (String, Vec<ChunkSpan>) {
+        (self.text, self.spans)
+    }
+}
+
+impl<S, E> Stream for Offsets<S>
+where
+    S: Stream<Item = Result<String, E>> + Unpin,
+{
+    type Item = Result<OffsetChunk, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let chunk = match Pin::new(&mut this.stream).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => chunk,
+            Poll::Ready(Some(Err(error))) => return Poll::Ready(Some(Err(error))),
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        let chars = chunk.chars().count();
+        let utf16 = chunk.encode_utf16().count();
+        let span = ChunkSpan {
+            index: this.spans.len(),
+            bytes: this.text.len()..this.text.len() + chunk.len(),
+            chars: this.chars..this.chars + chars,
+            utf16: this.utf16..this.utf16 + utf16,
+        };
+        this.text.push_str(&chunk);
+        this.chars += chars;
+        this.utf16 += utf16;
+        this.spans.push(span.clone());
+        Poll::Ready(Some(Ok(OffsetChunk { text: chunk, span })))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+/// Where [`Sentences`] splits a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Boundary {
+    /// After terminal punctuation followed by whitespace, or after CJK terminal
+    /// punctuation.
+    Sentence,
+    /// After a blank line.
+    Paragraph,
+}
+
+impl Boundary {
+    /// Returns the byte index just past the first complete unit in `text`, including
+    /// the whitespace that follows it.
+    fn end(self, text: &str) -> Option<usize> {
+        let end = match self {
+            Self::Sentence => sentence_end(text)?,
+            Self::Paragraph => paragraph_end(text)?,
+        };
+        let whitespace = text[end..].len() - text[end..].trim_start().len();
+        Some(end + whitespace)
+    }
+}
+
+/// Re-chunks a [`TextStream`] into complete sentences or paragraphs.
+///
+/// Providers split their output at arbitrary token boundaries, while text-to-speech
+/// engines and translation need whole sentences. This adapter buffers chunks and yields
+/// each sentence, or paragraph, as soon as it is complete, together with the whitespace
+/// following it, so the chunks still add up to the original response. The rest is
+/// yielded when the stream ends, and before any error.
+///
+/// A sentence ends with `.`, `!`, `?` or a newline followed by whitespace, so decimal
+/// numbers are not split, or with CJK terminal punctuation. A paragraph ends with a
+/// blank line.
+///
+/// Created by [`TextStream::sentences`] and [`TextStream::paragraphs`].
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::llm::{TextStream, stream::text_stream};
+/// use futures_lite::{StreamExt, stream};
+///
+/// # tokio_test::block_on(async {
+/// let chunks = stream::iter(["It costs 3", ".50 today. Wa", "nt one? Sure", "!"])
+///     .map(|s| Ok::<_, std::io::Error>(s.to_string()));
+/// let sentences: Vec<String> = text_stream(chunks).sentences().map(Result::unwrap).collect().await;
+/// assert_eq!(sentences, ["It costs 3.50 today. ", "Want one? ", "Sure!"]);
+/// # });
+/// ```
+pub struct Sentences<S: TextStream> {
+    stream: S,
+    boundary: Boundary,
+    buffer: String,
+    /// An error to yield once the buffer has been flushed.
+    error: Option<S::Error>,
+    finished: bool,
+}
+
+impl<S: TextStream> core::fmt::Debug for Sentences<S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Sentences")
+            .field("boundary", &self.boundary)
+            .field("buffer", &self.buffer)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: TextStream> Sentences<S> {
+    /// Creates an adapter splitting `stream` at every `boundary`.
+    pub const fn new(stream: S, boundary: Boundary) -> Self {
+        Self {
+            stream,
+            boundary,
+            buffer: String::new(),
+            error: None,
+            finished: false,
+        }
+    }
+}
+
+// No field is ever pinned: the stream is `Unpin`, and the error is only moved out.
+impl<S: TextStream> Unpin for Sentences<S> {}
+
+impl<S: TextStream> Stream for Sentences<S> {
+    type Item = Result<String, S::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if let Some(error) = this.error.take() {
+                return Poll::Ready(Some(Err(error)));
+            }
+            if let Some(end) = this.boundary.end(&this.buffer) {
+                let rest = this.buffer.split_off(end);
+                return Poll::Ready(Some(Ok(core::mem::replace(&mut this.buffer, rest))));
+            }
+            if this.finished {
+                return Poll::Ready(
+                    (!this.buffer.is_empty()).then(|| Ok(core::mem::take(&mut this.buffer))),
+                );
+            }
+            match Pin::new(&mut this.stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => this.buffer.push_str(&chunk),
+                Poll::Ready(Some(Err(error))) => {
+                    if this.buffer.is_empty() {
+                        return Poll::Ready(Some(Err(error)));
+                    }
+                    this.error = Some(error);
+                    return Poll::Ready(Some(Ok(core::mem::take(&mut this.buffer))));
+                }
+                Poll::Ready(None) => this.finished = true,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<S: TextStream> IntoFuture for Sentences<S> {
+    type Output = Result<String, S::Error>;
+    type IntoFuture = Collect<Self>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Collect::new(self)
+    }
+}
+
+impl<S: TextStream> TextStream for Sentences<S> {
+    type Error = S::Error;
+}
+
+/// Returns the byte index just past the terminator of the first complete sentence in
+/// `text`.
+pub(crate) fn sentence_end(text: &str) -> Option<usize> {
+    text.char_indices().find_map(|(index, c)| {
+        let end = index + c.len_utf8();
+        let terminated =
+            matches!(c, '.' | '!' | '?' | '\n') && text[end..].starts_with(char::is_whitespace);
+        (terminated || matches!(c, '。' | '！' | '？')).then_some(end)
+    })
+}
+
+/// Returns the byte index just past the blank line ending the first paragraph in `text`.
+fn paragraph_end(text: &str) -> Option<usize> {
+    text.match_indices('\n').find_map(|(index, _)| {
+        let rest = &text[index + 1..];
+        let blank = rest.trim_start_matches([' ', '\t', '\r']);
+        blank
+            .starts_with('\n')
+            .then(|| text.len() - blank.len() + 1)
+    })
+}
+
+/// The minimum size of the chunks produced by [`coalesce`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChunkSize {
+    /// At least this many bytes.
+    Bytes(usize),
+    /// At least this many characters. Binary chunks count bytes instead.
+    Chars(usize),
+}
+
+/// A chunk that [`coalesce`] can merge with the next one.
+///
+/// Implemented for text ([`String`]) and binary ([`Vec<u8>`]) chunks.
+pub trait Coalescible: Default {
+    /// Appends `other` to this chunk.
+    fn append(&mut self, other: Self);
+
+    /// Returns whether this chunk is at least `size` large.
+    fn reaches(&self, size: ChunkSize) -> bool;
+
+    /// Returns whether this chunk is empty.
+    fn is_empty(&self) -> bool;
+}
+
+impl Coalescible for String {
+    fn append(&mut self, other: Self) {
+        if self.is_empty() {
+            *self = other;
+        } else {
+            self.push_str(&other);
+        }
+    }
+
+    fn reaches(&self, size: ChunkSize) -> bool {
+        match size {
+            ChunkSize::Bytes(bytes) => self.len() >= bytes,
+            ChunkSize::Chars(chars) => self.chars().nth(chars.saturating_sub(1)).is_some(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        Self::is_empty(self)
+    }
+}
+
+impl Coalescible for Vec<u8> {
+    fn append(&mut self, mut other: Self) {
+        if self.is_empty() {
+            *self = other;
+        } else {
+            Self::append(self, &mut other);
+        }
+    }
+
+    fn reaches(&self, size: ChunkSize) -> bool {
+        match size {
+            ChunkSize::Bytes(bytes) | ChunkSize::Chars(bytes) => self.len() >= bytes,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        Self::is_empty(self)
+    }
+}
+
+/// Merges tiny chunks of `stream` into chunks of at least `size`.
+///
+/// Providers often stream a token or two per chunk, and servers fanning a response out
+/// over websockets or server-sent events pay a framing cost per chunk. This adapter
+/// buffers chunks until the buffer reaches `size`, or until `max_latency` has passed
+/// since the oldest buffered chunk arrived, so a slow stream is never held back for
+/// long. The remaining buffer is emitted when the stream ends, and before any error.
+///
+/// Works with text and binary chunks. Wrap the result in [`text_stream`] to get a
+/// [`TextStream`] back:
+///
+/// ```rust
+/// use ai_types::{llm::stream::{ChunkSize, coalesce, text_stream}, time::NoDelay};
+/// use core::time::Duration;
+/// use futures_lite::{StreamExt, stream};
+///
+/// # tokio_test::block_on(async {
+/// let chunks = stream::iter(["He", "ll", "o, ", "wor", "ld", "!"])
+///     .map(|s| Ok::<_, std::io::Error>(s.to_string()));
+/// let merged = coalesce(chunks, ChunkSize::Chars(5), Duration::from_millis(50), NoDelay);
+/// let chunks: Vec<String> = text_stream(merged).map(Result::unwrap).collect().await;
+/// assert_eq!(chunks, ["Hello, ", "world", "!"]);
+/// # });
+/// ```
+pub fn coalesce<S, B, E>(
+    stream: S,
+    size: ChunkSize,
+    max_latency: Duration,
+    timer: impl Timer,
+) -> impl Stream<Item = Result<B, E>>
+where
+    S: Stream<Item = Result<B, E>>,
+    B: Coalescible,
+{
+    async_stream::stream! {
+        pin!(stream);
+        let mut buffer = B::default();
+        let mut deadline = None;
+        loop {
+            let next = match deadline.as_mut() {
+                Some(deadline) => {
+                    future::or(async { Some(stream.next().await) }, async {
+                        Pin::as_mut(deadline).await;
+                        None
+                    })
+                    .await
+                }
+                None => Some(stream.next().await),
+            };
+            match next {
+                Some(Some(Ok(chunk))) => {
+                    if deadline.is_none() {
+                        deadline = Some(alloc::boxed::Box::pin(timer.sleep(max_latency)));
+                    }
+                    buffer.append(chunk);
+                    if buffer.reaches(size) {
+                        deadline = None;
+                        yield Ok(core::mem::take(&mut buffer));
+                    }
+                }
+                Some(Some(Err(error))) => {
+                    deadline = None;
+                    if !buffer.is_empty() {
+                        yield Ok(core::mem::take(&mut buffer));
+                    }
+                    yield Err(error);
+                }
+                Some(None) => {
+                    if !buffer.is_empty() {
+                        yield Ok(buffer);
+                    }
+                    return;
+                }
+                // The oldest buffered chunk has waited long enough.
+                None => {
+                    deadline = None;
+                    yield Ok(core::mem::take(&mut buffer));
+                }
+            }
+        }
+    }
+}
+
+/// Ends `stream` just before the first occurrence of any of the stop `sequences`.
+///
+/// Finds the sequences even when the provider splits them across chunks: text that
+/// could be the start of a stop sequence is held back until the next chunk tells. The
+/// stop sequence itself is not yielded, and the underlying stream is dropped as soon as
+/// it is found. Held back text is yielded when the stream ends, and before any error.
+/// Empty sequences are ignored.
+///
+/// ```rust
+/// use ai_types::llm::stream::{take_until_stop, text_stream};
+/// use futures_lite::{StreamExt, stream};
+///
+/// # tokio_test::block_on(async {
+/// let chunks = stream::iter(["Answer: 42\nUs", "er: and", " then?"])
+///     .map(|s| Ok::<_, std::io::Error>(s.to_string()));
+/// let text = take_until_stop(chunks, ["\nUser:"]).await.unwrap();
+/// assert_eq!(text, "Answer: 42");
+/// # });
+/// ```
+pub fn take_until_stop<S, E>(
+    stream: S,
+    sequences: impl IntoIterator<Item = impl Into<String>>,
+) -> impl TextStream<Error = E>
+where
+    S: Stream<Item = Result<String, E>>,
+{
+    let sequences: Vec<String> = sequences
+        .into_iter()
+        .map(Into::into)
+        .filter(|sequence| !sequence.is_empty())
+        .collect();
+    text_stream(async_stream::stream! {
+        pin!(stream);
+        let mut pending = String::new();
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(chunk) => pending.push_str(&chunk),
+                Err(error) => {
+                    if !pending.is_empty() {
+                        yield Ok(core::mem::take(&mut pending));
+                    }
+                    yield Err(error);
+                    continue;
+                }
+            }
+            let stop = sequences
+                .iter()
+                .filter_map(|sequence| pending.find(sequence.as_str()))
+                .min();
+            if let Some(stop) = stop {
+                pending.truncate(stop);
+                if !pending.is_empty() {
+                    yield Ok(pending);
+                }
+                return;
+            }
+            let held = sequences
+                .iter()
+                .map(|sequence| partial_match(&pending, sequence))
+                .max()
+                .unwrap_or(0);
+            if held < pending.len() {
+                let rest = pending.split_off(pending.len() - held);
+                yield Ok(core::mem::replace(&mut pending, rest));
+            }
+        }
+        if !pending.is_empty() {
+            yield Ok(pending);
+        }
+    })
+}
+
+/// Returns the length of the longest end of `text` that starts `sequence`.
+fn partial_match(text: &str, sequence: &str) -> usize {
+    (1..sequence.len().min(text.len() + 1))
+        .rev()
+        .find(|&len| {
+            text.is_char_boundary(text.len() - len)
+                && sequence.starts_with(&text[text.len() - len..])
+        })
+        .unwrap_or(0)
+}
+
+/// Removes the whitespace at the start of the response in `stream`.
+///
+/// Models often open with a newline or a space. Chunks that are only whitespace are
+/// skipped until the first one with visible text, which is trimmed; the rest of the
+/// response is passed through unchanged.
+///
+/// ```rust
+/// use ai_types::llm::stream::{strip_prefix_whitespace, text_stream};
+/// use futures_lite::{StreamExt, stream};
+///
+/// # tokio_test::block_on(async {
+/// let chunks = stream::iter(["\n", "  Hello", " world"])
+///     .map(|s| Ok::<_, std::io::Error>(s.to_string()));
+/// let text = strip_prefix_whitespace(chunks).await.unwrap();
+/// assert_eq!(text, "Hello world");
+/// # });
+/// ```
+pub fn strip_prefix_whitespace<S, E>(stream: S) -> impl TextStream<Error = E>
+where
+    S: Stream<Item = Result<String, E>>,
+{
+    text_stream(async_stream::stream! {
+        pin!(stream);
+        let mut started = false;
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(chunk) if started => yield Ok(chunk),
+                Ok(chunk) => {
+                    let trimmed = chunk.trim_start();
+                    if !trimmed.is_empty() {
+                        started = true;
+                        yield Ok(trimmed.into());
+                    }
+                }
+                Err(error) => yield Err(error),
+            }
+        }
+    })
+}
+
+/// Regroups the chunks of `stream` so that no word is split between two chunks.
+///
+/// Each chunk ends after whitespace, holding back a trailing partial word until the
+/// chunk completing it arrives, so downstream consumers such as speech synthesis or
+/// word-by-word rendering only ever see whole words. Held back text is yielded when the
+/// stream ends, and before any error.
+///
+/// ```rust
+/// use ai_types::llm::stream::buffer_words;
+/// use futures_lite::{StreamExt, stream};
+///
+/// # tokio_test::block_on(async {
+/// let chunks = stream::iter(["Hel", "lo wor", "ld, how", " are you?"])
+///     .map(|s| Ok::<_, std::io::Error>(s.to_string()));
+/// let words: Vec<String> = buffer_words(chunks).map(Result::unwrap).collect().await;
+/// assert_eq!(words, ["Hello ", "world, ", "how are ", "you?"]);
+/// # });
+/// ```
+pub fn buffer_words<S, E>(stream: S) -> impl TextStream<Error = E>
+where
+    S: Stream<Item = Result<String, E>>,
+{
+    text_stream(async_stream::stream! {
+        pin!(stream);
+        let mut pending = String::new();
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(chunk) => {
+                    pending.push_str(&chunk);
+                    let end = pending
+                        .char_indices()
+                        .rev()
+                        .find(|(_, c)| c.is_whitespace())
+                        .map(|(index, c)| index + c.len_utf8());
+                    if let Some(end) = end {
+                        let rest = pending.split_off(end);
+                        yield Ok(core::mem::replace(&mut pending, rest));
+                    }
+                }
+                Err(error) => {
+                    if !pending.is_empty() {
+                        yield Ok(core::mem::take(&mut pending));
+                    }
+                    yield Err(error);
+                }
+            }
+        }
+        if !pending.is_empty() {
+            yield Ok(pending);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{string::ToString, vec};
+    use futures_lite::StreamExt;
+
+    fn chunks(items: &[&str]) -> impl TextStream<Error = core::convert::Infallible> + use<> {
+        let items: Vec<String> = items.iter().map(ToString::to_string).collect();
+        text_stream(futures_lite::stream::iter(items).map(Ok))
+    }
+
+    #[tokio::test]
+    async fn text_stream_collects() {
+        let text = chunks(&["Hello, ", "streaming ", "world!"]).await.unwrap();
+        assert_eq!(text, "Hello, streaming world!");
+    }
+
+    #[tokio::test]
+    async fn text_stream_yields_chunks() {
+        let collected: Vec<String> = chunks(&["a", "b", "c"]).map(Result::unwrap).collect().await;
+        assert_eq!(collected, vec!["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn broadcast_delivers_to_every_subscriber() {
+        let broadcast = chunks(&["one ", "two ", "three"]).broadcast(1);
+        let first = broadcast.subscribe();
+        let second = broadcast.subscribe();
+        let third = broadcast.subscribe();
+
+        let (first, (second, third)) = futures_lite::future::zip(
+            first.into_future(),
+            futures_lite::future::zip(second.into_future(), third.into_future()),
+        )
+        .await;
+
+        assert_eq!(first.unwrap(), "one two three");
+        assert_eq!(second.unwrap(), "one two three");
+        assert_eq!(third.unwrap(), "one two three");
+    }
+
+    #[tokio::test]
+    async fn broadcast_applies_backpressure() {
+        let broadcast = chunks(&["a", "b", "c", "d"]).broadcast(2);
+        let mut fast = broadcast.subscribe();
+        let slow = broadcast.subscribe();
+
+        assert_eq!(fast.next().await.unwrap().unwrap(), "a");
+        assert_eq!(fast.next().await.unwrap().unwrap(), "b");
+        // The buffer is full until the slow subscriber catches up.
+        assert!(futures_lite::future::poll_once(fast.next()).await.is_none());
+
+        drop(slow);
+        assert_eq!(fast.await.unwrap(), "cd");
+    }
+
+    #[tokio::test]
+    async fn broadcast_releases_consumed_chunks() {
+        let broadcast = chunks(&["a", "b", "c"]).broadcast(8);
+        let mut first = broadcast.subscribe();
+        let mut second = broadcast.subscribe();
+
+        first.next().await;
+        second.next().await;
+
+        assert!(broadcast.shared.state.lock().buffer.is_empty());
+    }
+
+    #[tokio::test]
+    async fn broadcast_shares_errors() {
+        #[derive(Debug)]
+        struct Failure;
+
+        let source = text_stream(futures_lite::stream::iter([
+            Ok("partial".to_string()),
+            Err(Failure),
+        ]));
+        let broadcast = source.broadcast(4);
+        let first = broadcast.subscribe();
+        let second = broadcast.subscribe();
+
+        let (first, second) =
+            futures_lite::future::zip(first.into_future(), second.into_future()).await;
+        assert!(first.is_err());
+        assert!(second.is_err());
+    }
+
+    #[tokio::test]
+    async fn coalesce_flushes_after_max_latency() {
+        let chunks = async_stream::stream! {
+            yield Ok(vec![1, 2]);
+            yield Ok(vec![3, 4, 5]);
+            yield Ok(vec![6]);
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            yield Ok(vec![7]);
+            yield Err(());
+        };
+        let merged = coalesce(
+            chunks,
+            ChunkSize::Bytes(4),
+            Duration::from_millis(20),
+            tokio::time::sleep,
+        );
+        pin!(merged);
+
+        assert_eq!(merged.next().await, Some(Ok(vec![1, 2, 3, 4, 5])));
+        // A lone small chunk is released once it has waited `max_latency`.
+        assert_eq!(merged.next().await, Some(Ok(vec![6])));
+        // Buffered chunks are released before an error.
+        assert_eq!(merged.next().await, Some(Ok(vec![7])));
+        assert_eq!(merged.next().await, Some(Err(())));
+        assert_eq!(merged.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn collect_partial_keeps_text_before_an_error() {
+        let source = text_stream(futures_lite::stream::iter([
+            Ok("partial ".to_string()),
+            Ok("answer".to_string()),
+            Err("disconnected"),
+        ]));
+        let partial = source.collect_partial().await.unwrap_err();
+        assert_eq!(partial.text, "partial answer");
+        assert_eq!(partial.cause, Interruption::Failed("disconnected"));
+        assert_eq!(
+            partial.to_string(),
+            "Response failed after 14 bytes: disconnected"
+        );
+
+        let complete = chunks(&["a", "b"]).collect_partial().await;
+        assert_eq!(complete.unwrap(), "ab");
+    }
+
+    #[tokio::test]
+    async fn collect_partial_can_be_cancelled() {
+        let source = chunks(&["a", "b"]).chain(futures_lite::stream::pending());
+        let mut collect = text_stream(source).collect_partial();
+        assert!(
+            futures_lite::future::poll_once(&mut collect)
+                .await
+                .is_none()
+        );
+        assert_eq!(collect.text(), "ab");
+
+        let partial = collect.cancel();
+        assert_eq!(partial.text, "ab");
+        assert_eq!(partial.cause, Interruption::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn take_until_stop_finds_split_sequences() {
+        let stopped: Vec<String> = take_until_stop(
+            chunks(&["Hi <", "b>x", "</", "end> more"]),
+            ["</end>", "<stop>"],
+        )
+        .map(Result::unwrap)
+        .collect()
+        .await;
+        // "<" may start "<stop>" and is held back until "b>" rules it out.
+        assert_eq!(stopped, ["Hi ", "<b>x"]);
+
+        let unstopped = take_until_stop(chunks(&["a <", "st"]), ["<stop>", ""]).await;
+        assert_eq!(unstopped.unwrap(), "a <st");
+        assert_eq!(partial_match("é<s", "<stop>"), 2);
+    }
+
+    #[tokio::test]
+    async fn whitespace_and_words_are_regrouped() {
+        let stripped: Vec<String> = strip_prefix_whitespace(chunks(&[" ", "\n", "\t a", " b"]))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(stripped, ["a", " b"]);
+
+        let words: Vec<String> = buffer_words(chunks(&["na", "ïve ", "wo", "rds ", "", "end"]))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(words, ["naïve ", "words ", "end"]);
+    }
+
+    #[test]
+    fn sentence_boundaries() {
+        assert_eq!(sentence_end("Hi. There"), Some(3));
+        assert_eq!(sentence_end("3.14 is pi"), None);
+        assert_eq!(sentence_end("No end yet."), None);
+        assert_eq!(sentence_end("你好。再见"), Some(9));
+        assert_eq!(paragraph_end("One.\n \nTwo"), Some(7));
+        assert_eq!(paragraph_end("One.\nTwo"), None);
+    }
+
+    #[tokio::test]
+    async fn sentences_and_paragraphs_rechunk_the_stream() {
+        let sentences: Vec<String> = chunks(&["你好。再", "见 Bye.", "\n\nNext"])
+            .sentences()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(sentences, ["你好。", "再见 Bye.\n\n", "Next"]);
+
+        let paragraphs: Vec<String> = chunks(&["A. B.\n", "\nC.\n\n", "D"])
+            .paragraphs()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(paragraphs, ["A. B.\n\n", "C.\n\n", "D"]);
+
+        let source = text_stream(futures_lite::stream::iter([
+            Ok("Done. Part".to_string()),
+            Err(()),
+        ]));
+        let items: Vec<_> = source.sentences().collect().await;
+        assert_eq!(
+            items,
+            [Ok("Done. ".to_string()), Ok("Part".to_string()), Err(())]
+        );
+    }
+
+    #[tokio::test]
+    async fn offsets_track_every_unit() {
+        let mut offsets = chunks(&["a😀", "", "bé"]).with_offsets();
+        let mut spans = Vec::new();
+        while let Some(chunk) = offsets.next().await {
+            spans.push(chunk.unwrap().span);
+        }
+
+        let ranges: Vec<_> = spans
+            .iter()
+            .map(|span| (span.bytes.clone(), span.chars.clone(), span.utf16.clone()))
+            .collect();
+        assert_eq!(
+            ranges,
+            [(0..5, 0..2, 0..3), (5..5, 2..2, 3..3), (5..8, 2..4, 3..5)]
+        );
+        // Empty chunks contain no bytes, so lookups skip them.
+        assert_eq!(offsets.span_at(5).unwrap().index, 2);
+        assert!(offsets.span_at(8).is_none());
+        assert_eq!(offsets.into_parts().0, "a😀bé");
+    }
+}
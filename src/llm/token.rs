@@ -0,0 +1,160 @@
+//! Token counting.
+//!
+//! Context-window budgeting needs token counts, but tokenizers are model specific. The
+//! [`TokenCounter`] trait lets applications plug in the tokenizer of their provider.
+//! Any closure counting the tokens of a string is a counter:
+//!
+//! ```rust
+//! use ai_types::llm::token::TokenCounter;
+//!
+//! let words = |text: &str| text.split_whitespace().count();
+//! assert_eq!(words.count_tokens("Hello there, world"), 3);
+//! ```
+//!
+//! When no tokenizer is at hand, [`Estimator`] approximates counts from the text length.
+
+use crate::llm::Message;
+
+/// Tokens added to every message for its role and formatting, on top of its content.
+///
+/// Chat formats wrap each message in special tokens; 4 matches the common formats
+/// closely enough for budgeting.
+pub const MESSAGE_OVERHEAD: usize = 4;
+
+/// Counts the tokens of text for a specific model.
+pub trait TokenCounter {
+    /// Returns the number of tokens in `text`.
+    fn count_tokens(&self, text: &str) -> usize;
+
+    /// Returns the number of tokens `messages` take up in a prompt.
+    ///
+    /// The default implementation counts the text and tool calls of each message, plus
+    /// [`MESSAGE_OVERHEAD`] per message. Counters that know their model's chat format
+    /// can count it exactly.
+    fn count_message_tokens(&self, messages: &[Message]) -> usize {
+        messages
+            .iter()
+            .map(|message| {
+                let calls: usize = message
+                    .tool_calls()
+                    .map(|call| self.count_tokens(&call.name) + self.count_tokens(&call.arguments))
+                    .sum();
+                MESSAGE_OVERHEAD + self.count_tokens(&message.content()) + calls
+            })
+            .sum()
+    }
+}
+
+impl<F: Fn(&str) -> usize> TokenCounter for F {
+    fn count_tokens(&self, text: &str) -> usize {
+        self(text)
+    }
+}
+
+/// A [`TokenCounter`] estimating counts from the number of characters.
+///
+/// English prose averages about four characters per token with common tokenizers, which
+/// is the default. Estimates are rounded up, so budgets stay on the safe side.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::llm::{Message, token::{Estimator, TokenCounter}};
+///
+/// let estimator = Estimator::new();
+/// assert_eq!(estimator.count_tokens("Hello, world!"), 4);
+/// assert_eq!(estimator.count_message_tokens(&[Message::user("Hello, world!")]), 8);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimator {
+    chars_per_token: f32,
+}
+
+impl Default for Estimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Estimator {
+    /// Creates an estimator assuming four characters per token.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            chars_per_token: 4.0,
+        }
+    }
+
+    /// Sets the average number of characters per token.
+    ///
+    /// Code and non-English text usually have fewer characters per token than prose.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chars_per_token` is not positive.
+    #[must_use]
+    pub const fn with_chars_per_token(mut self, chars_per_token: f32) -> Self {
+        assert!(
+            chars_per_token > 0.0,
+            "Characters per token must be positive"
+        );
+        self.chars_per_token = chars_per_token;
+        self
+    }
+}
+
+impl TokenCounter for Estimator {
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn count_tokens(&self, text: &str) -> usize {
+        let tokens = text.chars().count() as f32 / self.chars_per_token;
+        let whole = tokens as usize;
+        if (whole as f32) < tokens {
+            whole + 1
+        } else {
+            whole
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::tool::ToolCall;
+    use alloc::vec;
+
+    #[test]
+    fn messages_include_overhead_and_tool_calls() {
+        let words = |text: &str| text.split_whitespace().count();
+        let messages = [
+            Message::user("What's the weather?"),
+            Message::assistant_tool_calls(
+                "",
+                vec![ToolCall::new("1", "weather", r#"{"city": "Paris"}"#)],
+            ),
+        ];
+        assert_eq!(
+            words.count_message_tokens(&messages),
+            (MESSAGE_OVERHEAD + 3) + (MESSAGE_OVERHEAD + 1 + 2)
+        );
+        assert_eq!(words.count_message_tokens(&[]), 0);
+    }
+
+    #[test]
+    fn estimator_rounds_up() {
+        let estimator = Estimator::new();
+        assert_eq!(estimator.count_tokens(""), 0);
+        assert_eq!(estimator.count_tokens("abcd"), 1);
+        assert_eq!(estimator.count_tokens("abcde"), 2);
+        assert_eq!(estimator.count_tokens("日本語"), 1);
+        assert_eq!(
+            Estimator::new()
+                .with_chars_per_token(2.5)
+                .count_tokens("abcde"),
+            2
+        );
+    }
+}
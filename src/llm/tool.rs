@@ -11,8 +11,10 @@
 //! ## Core Components
 //!
 //! - [`Tool`] - Trait for defining executable tools
+//! - [`DynTool`] - Trait for tools defined at runtime
 //! - [`Tools`] - Registry for managing multiple tools  
 //! - [`ToolDefinition`] - Metadata and schema for LLM consumption
+//! - [`ToolError`] - Recoverable and internal tool failures
 //!
 //! ## Quick Start
 //!
@@ -52,6 +54,50 @@
 //! }
 //! ```
 //!
+//! ## Deriving Tools from Functions
+//!
+//! With the `derive` feature, the [`tool`] attribute generates the [`Tool`] implementation
+//! from an async function. The tool is named after the function and described by its doc
+//! comment; parameter doc comments describe the arguments:
+//!
+//! ```rust
+//! use ai_types::llm::{Tool, tool::{ToolDefinition, tool}};
+//!
+//! /// Performs basic math operations
+//! #[tool]
+//! async fn calculator(
+//!     /// Operation: "add" or "subtract"
+//!     operation: String,
+//!     /// First number
+//!     a: f64,
+//!     /// Second number
+//!     b: f64,
+//! ) -> ai_types::Result<f64> {
+//!     match operation.as_str() {
+//!         "add" => Ok(a + b),
+//!         "subtract" => Ok(a - b),
+//!         _ => Err(anyhow::Error::msg("Unknown operation")),
+//!     }
+//! }
+//!
+//! assert_eq!(Calculator::NAME, "calculator");
+//! assert_eq!(Calculator::DESCRIPTION, "Performs basic math operations");
+//! let schema = serde_json::to_string(&ToolDefinition::new::<Calculator>().arguments).unwrap();
+//! assert!(schema.contains("First number"));
+//!
+//! /// Looks up a word in the dictionary
+//! #[tool]
+//! async fn define(
+//!     /// The word to define
+//!     word: String,
+//! ) -> ai_types::Result<String> {
+//!     Ok(format!("{word}: a unit of language"))
+//! }
+//!
+//! let schema = serde_json::to_string(&ToolDefinition::new::<Define>().arguments).unwrap();
+//! assert!(schema.contains("The word to define"));
+//! ```
+//!
 //! ## Schema Design Best Practices
 //!
 //! ### 1. Use Clear Documentation Comments
@@ -144,12 +190,17 @@
 pub use ai_types_derive::tool;
 
 use crate::Result;
+use crate::join::join_all;
+use crate::llm::validation::{self, ValidationError};
+use crate::time::Timer;
 use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
-use alloc::{boxed::Box, collections::BTreeMap};
+use alloc::{borrow::Cow, boxed::Box, collections::BTreeMap, sync::Arc};
+use async_lock::Mutex;
 use core::fmt::Debug;
-use core::{future::Future, pin::Pin};
+use core::{any::Any, future::Future, panic::AssertUnwindSafe, pin::Pin, time::Duration};
+use futures_lite::{FutureExt, future};
 use schemars::{JsonSchema, Schema, schema_for};
 use serde::{Serialize, de::DeserializeOwned};
 
@@ -204,10 +255,62 @@ pub trait Tool: Send + Sync + 'static {
 
     /// Executes the tool with the provided arguments.
     ///
-    /// Returns a [`crate::Result`] containing the tool's output.
+    /// Returns a [`crate::Result`] containing the tool's output. Return a
+    /// [`ToolError::Recoverable`] when the model should retry with different arguments.
     fn call(&mut self, arguments: Self::Arguments) -> impl Future<Output = Result> + Send;
 }
 
+/// Tools whose name, description and schema are only known at runtime.
+///
+/// [`Tool`] fixes its name and description at compile time, which rules out tools proxied
+/// from external systems, such as MCP servers or plugin registries. A dynamic tool
+/// describes itself with a [`ToolDefinition`] instead, and receives its arguments as raw
+/// JSON. Register it with [`Tools::register_dyn`].
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::llm::tool::{DynTool, ToolDefinition, Tools};
+/// use schemars::json_schema;
+///
+/// /// A tool served by a remote plugin.
+/// struct Remote {
+///     name: String,
+/// }
+///
+/// impl DynTool for Remote {
+///     fn definition(&self) -> ToolDefinition {
+///         let schema = json_schema!({
+///             "type": "object",
+///             "properties": { "query": { "type": "string" } },
+///             "required": ["query"]
+///         });
+///         ToolDefinition::from_parts(self.name.clone(), "Queries the plugin", schema)
+///     }
+///
+///     async fn call(&mut self, arguments: String) -> ai_types::Result {
+///         // Forward `arguments` to the plugin.
+///         Ok(arguments)
+///     }
+/// }
+///
+/// let mut tools = Tools::new();
+/// tools.register_dyn(Remote { name: "wiki_search".into() });
+/// assert_eq!(tools.definitions()[0].name, "wiki_search");
+/// ```
+pub trait DynTool: Send + Sync + 'static {
+    /// Returns the name, description and argument schema of the tool.
+    ///
+    /// Called once, when the tool is registered.
+    fn definition(&self) -> ToolDefinition;
+
+    /// Executes the tool with arguments as a JSON string.
+    ///
+    /// Arguments are passed as the model produced them; validate them against the schema
+    /// with [`Tools::validate`] beforehand if needed.
+    fn call(&mut self, arguments: String) -> impl Future<Output = Result> + Send;
+}
+
 /// Serializes a value to JSON string.
 ///
 /// Convenience function for tools that need to return JSON responses.
@@ -224,30 +327,213 @@ pub fn json<T: Serialize>(value: &T) -> String {
         .map_or_else(|| format!("{value:#}"), ToString::to_string)
 }
 
+/// Serializes a JSON value canonically, such as a tool's argument schema.
+///
+/// Object keys are sorted and no whitespace is written, so equal values always produce
+/// the same bytes, whatever order their keys were inserted in. Use it to snapshot-test
+/// function-calling payloads across versions.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::llm::tool::canonical_json;
+/// use serde_json::json;
+///
+/// let schema = json!({"type": "object", "properties": {"b": {}, "a": {}}});
+/// assert_eq!(
+///     canonical_json(&schema),
+///     r#"{"properties":{"a":{},"b":{}},"type":"object"}"#
+/// );
+/// ```
+#[must_use]
+pub fn canonical_json(value: &serde_json::Value) -> String {
+    let mut json = String::new();
+    write_canonical(value, &mut json);
+    json
+}
+
+fn write_canonical(value: &serde_json::Value, json: &mut String) {
+    use serde_json::Value;
+    match value {
+        Value::Array(items) => {
+            json.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    json.push(',');
+                }
+                write_canonical(item, json);
+            }
+            json.push(']');
+        }
+        Value::Object(object) => {
+            // Sorted explicitly, as `serde_json` keeps insertion order with `preserve_order`.
+            let mut entries: Vec<_> = object.iter().collect();
+            entries.sort_unstable_by_key(|(key, _)| *key);
+            json.push('{');
+            for (index, (key, value)) in entries.into_iter().enumerate() {
+                if index > 0 {
+                    json.push(',');
+                }
+                write_canonical(&Value::String(key.clone()), json);
+                json.push(':');
+                write_canonical(value, json);
+            }
+            json.push('}');
+        }
+        scalar => json.push_str(&scalar.to_string()),
+    }
+}
+
 trait ToolImpl: Send + Sync {
-    fn call(&mut self, args: String) -> Pin<Box<dyn Future<Output = Result> + Send + '_>>;
+    fn call(&self, args: String) -> Pin<Box<dyn Future<Output = Result> + Send + '_>>;
     fn definition(&self) -> ToolDefinition;
 }
 
-impl<T: Tool> ToolImpl for T {
-    fn call(&mut self, args: String) -> Pin<Box<dyn Future<Output = Result> + Send + '_>> {
+impl<T: Tool> ToolImpl for Mutex<T> {
+    fn call(&self, args: String) -> Pin<Box<dyn Future<Output = Result> + Send + '_>> {
         Box::pin(async move {
+            let mut tool = self.lock().await;
             let arguments: T::Arguments = serde_json::from_str(&args)?;
-            self.call(arguments).await
+            tool.call(arguments).await
         })
     }
 
     fn definition(&self) -> ToolDefinition {
-        ToolDefinition {
-            name: Self::NAME,
-            description: Self::DESCRIPTION,
-            arguments: schema_for!(T::Arguments),
+        ToolDefinition::new::<T>()
+    }
+}
+
+struct Dynamic<T> {
+    definition: ToolDefinition,
+    tool: Mutex<T>,
+}
+
+impl<T: DynTool> ToolImpl for Dynamic<T> {
+    fn call(&self, args: String) -> Pin<Box<dyn Future<Output = Result> + Send + '_>> {
+        Box::pin(async move { self.tool.lock().await.call(args).await })
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+}
+
+/// An error returned by a [`Tool`], classified by how a tool-calling loop should react.
+///
+/// Tools return it through [`crate::Result`]. [`Tools::call`] turns recoverable errors into
+/// a tool result the model can read and react to, while internal errors, like any other
+/// error a tool returns, are propagated and should abort the loop.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::llm::{Tool, tool::{ToolError, Tools}};
+/// use schemars::JsonSchema;
+/// use serde::Deserialize;
+///
+/// #[derive(JsonSchema, Deserialize)]
+/// struct LookupArgs {
+///     /// Order identifier, e.g. "A-1234"
+///     id: String,
+/// }
+///
+/// struct OrderLookup;
+///
+/// impl Tool for OrderLookup {
+///     const NAME: &str = "order_lookup";
+///     const DESCRIPTION: &str = "Looks up an order by id";
+///     type Arguments = LookupArgs;
+///
+///     async fn call(&mut self, args: Self::Arguments) -> ai_types::Result {
+///         if !args.id.starts_with("A-") {
+///             return Err(ToolError::recoverable("order ids start with 'A-'").into());
+///         }
+///         Ok("shipped".into())
+///     }
+/// }
+///
+/// # tokio_test::block_on(async {
+/// let mut tools = Tools::new();
+/// tools.register(OrderLookup);
+///
+/// let output = tools.call("order_lookup", r#"{"id": "1234"}"#.into()).await.unwrap();
+/// assert!(output.contains("order ids start with 'A-'"));
+/// # });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ToolError {
+    /// The call failed because of its arguments or the current state of the world.
+    ///
+    /// The message is shown to the model, which may retry with different arguments.
+    Recoverable(String),
+    /// The tool itself failed. The tool-calling loop should abort.
+    Internal(String),
+    /// The tool did not finish within the [timeout](Tools::with_timeout).
+    Timeout(Duration),
+    /// The tool panicked. Holds the panic message, if it had one.
+    Panicked(String),
+}
+
+impl ToolError {
+    /// Creates a [`ToolError::Recoverable`] error with a message for the model.
+    pub fn recoverable(message: impl Into<String>) -> Self {
+        Self::Recoverable(message.into())
+    }
+
+    /// Creates a [`ToolError::Internal`] error.
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::Internal(message.into())
+    }
+
+    /// Returns whether the model may retry after this error.
+    #[must_use]
+    pub const fn is_recoverable(&self) -> bool {
+        matches!(self, Self::Recoverable(_))
+    }
+
+    /// Formats the error as a tool result message for the model.
+    #[must_use]
+    pub fn to_tool_result(&self, tool: &str) -> String {
+        match self {
+            Self::Recoverable(message) => format!(
+                "Error: tool '{tool}' failed: {message}. Adjust the arguments and try again."
+            ),
+            Self::Internal(message) => {
+                format!("Error: tool '{tool}' failed with an internal error: {message}.")
+            }
+            Self::Timeout(timeout) => {
+                format!("Error: tool '{tool}' timed out after {timeout:?}.")
+            }
+            Self::Panicked(message) => format!("Error: tool '{tool}' crashed: {message}."),
         }
     }
 }
 
+impl core::fmt::Display for ToolError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Recoverable(message) => write!(f, "Recoverable tool error: {message}"),
+            Self::Internal(message) => write!(f, "Internal tool error: {message}"),
+            Self::Timeout(timeout) => write!(f, "Tool timed out after {timeout:?}"),
+            Self::Panicked(message) => write!(f, "Tool panicked: {message}"),
+        }
+    }
+}
+
+impl core::error::Error for ToolError {}
+
 /// Tool registry for managing and calling tools by name.
 ///
+/// Cloning a registry is cheap: clones share the registered tool instances, and calls
+/// to the same tool are serialized.
+///
+/// Calls are isolated from misbehaving tools: a panicking tool fails with
+/// [`ToolError::Panicked`], and with a [timeout](Self::with_timeout), a tool that does not
+/// finish in time fails with [`ToolError::Timeout`].
+///
+/// In [dry-run mode](Self::with_dry_run), calls are resolved and validated but no tool
+/// runs, so agents can be tested against a production registry without side effects.
 ///
 /// # Example
 ///
@@ -259,27 +545,105 @@ impl<T: Tool> ToolImpl for T {
 /// let definitions = tools.definitions();
 /// // let result = tools.call("calculator", r#"{"operation": "add", "a": 5, "b": 3}"#).await;
 /// ```
+#[derive(Clone)]
+#[allow(clippy::struct_field_names)]
 pub struct Tools {
-    tools: BTreeMap<String, Box<dyn ToolImpl>>,
+    tools: BTreeMap<String, Arc<dyn ToolImpl>>,
+    timeouts: Timeouts,
+    dry_run: bool,
+}
+
+/// How long tools may run.
+#[derive(Clone)]
+struct Timeouts {
+    timer: Option<Arc<dyn DynTimer>>,
+    default: Option<Duration>,
+    per_tool: BTreeMap<String, Duration>,
 }
 
 impl Debug for Tools {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("Tools")
             .field("tools", &self.tools.keys().collect::<Vec<_>>())
-            .finish()
+            .field("timeout", &self.timeouts.default)
+            .field("tool_timeouts", &self.timeouts.per_tool)
+            .field("dry_run", &self.dry_run)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An object-safe [`Timer`], so registries stay cheap to clone and free of type parameters.
+trait DynTimer: Send + Sync {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + '_>>;
+}
+
+impl<T: Timer> DynTimer for T {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(Timer::sleep(self, duration))
+    }
+}
+
+/// Returns the message of a panic payload.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(ToString::to_string)
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".into())
+}
+
+/// A tool invocation requested by a language model.
+///
+/// Emitted as [`StreamEvent::ToolCall`](crate::llm::event::StreamEvent::ToolCall) so
+/// applications can run the call themselves, e.g. with [`Tools::execute`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ToolCall {
+    /// Provider-assigned identifier used to match the call with its result.
+    pub id: String,
+    /// Name of the tool to call.
+    pub name: String,
+    /// Tool arguments as a JSON string.
+    pub arguments: String,
+}
+
+impl ToolCall {
+    /// Creates a new tool call.
+    pub fn new(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        arguments: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            arguments: arguments.into(),
+        }
     }
 }
 
+/// A tool call [planned](Tools::plan) without being executed.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct PlannedCall {
+    /// The planned call.
+    pub call: ToolCall,
+    /// The definition of the tool that would be called.
+    pub definition: ToolDefinition,
+    /// The parsed arguments, valid against the tool's schema.
+    pub arguments: serde_json::Value,
+}
+
 /// Tool definition including schema for language models.
 ///
 /// Used to provide language models with information about available [`Tool`]s.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ToolDefinition {
     /// Tool name.
-    pub name: &'static str,
+    pub name: Cow<'static, str>,
     /// Tool description.
-    pub description: &'static str,
+    pub description: Cow<'static, str>,
     /// JSON schema for tool arguments.
     pub arguments: Schema,
 }
@@ -289,11 +653,36 @@ impl ToolDefinition {
     #[must_use]
     pub fn new<T: Tool>() -> Self {
         Self {
-            name: T::NAME,
-            description: T::DESCRIPTION,
+            name: Cow::Borrowed(T::NAME),
+            description: Cow::Borrowed(T::DESCRIPTION),
             arguments: schema_for!(T::Arguments),
         }
     }
+
+    /// Creates a tool definition from a name, description and argument schema known at
+    /// runtime, for [`DynTool`]s.
+    pub fn from_parts(
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        arguments: Schema,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            arguments,
+        }
+    }
+
+    /// Serializes the definition as [canonical JSON](canonical_json), with the keys
+    /// `arguments`, `description` and `name`.
+    #[must_use]
+    pub fn canonical_json(&self) -> String {
+        canonical_json(&serde_json::json!({
+            "name": self.name,
+            "description": self.description,
+            "arguments": self.arguments.as_value(),
+        }))
+    }
 }
 
 impl Default for Tools {
@@ -308,21 +697,116 @@ impl Tools {
     pub const fn new() -> Self {
         Self {
             tools: BTreeMap::new(),
+            timeouts: Timeouts {
+                timer: None,
+                default: None,
+                per_tool: BTreeMap::new(),
+            },
+            dry_run: false,
         }
     }
 
+    /// Sets the timer used to enforce timeouts.
+    ///
+    /// Calls to a tool with a timeout fail while no timer is set.
+    #[must_use]
+    pub fn with_timer(mut self, timer: impl Timer + 'static) -> Self {
+        self.timeouts.timer = Some(Arc::new(timer));
+        self
+    }
+
+    /// Sets how long any tool may run before its call fails with [`ToolError::Timeout`].
+    ///
+    /// Requires a [timer](Self::with_timer): calls fail without one.
+    #[must_use]
+    pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.default = Some(timeout);
+        self
+    }
+
+    /// Sets how long the tool called `name` may run, overriding the
+    /// [default timeout](Self::with_timeout).
+    ///
+    /// Requires a [timer](Self::with_timer): calls fail without one.
+    #[must_use]
+    pub fn with_tool_timeout(mut self, name: impl Into<String>, timeout: Duration) -> Self {
+        self.timeouts.per_tool.insert(name.into(), timeout);
+        self
+    }
+
+    /// Switches the registry to dry-run mode.
+    ///
+    /// [`call`](Self::call) then [plans](Self::plan) each call without running the tool,
+    /// and returns a placeholder output saying the tool was not executed. Calls to unknown
+    /// tools still fail, and invalid arguments are reported like a
+    /// [`ToolError::Recoverable`] error.
+    ///
+    /// The [agent loop](crate::llm::agent::run) executes calls with the request's tools,
+    /// so a request with dry-run tools runs the whole loop without side effects, its
+    /// events reporting every call that would have been made.
+    #[must_use]
+    pub const fn with_dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    /// Returns whether the registry is in [dry-run mode](Self::with_dry_run).
+    #[must_use]
+    pub const fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
     /// Returns definitions of all registered tools.
     #[must_use]
     pub fn definitions(&self) -> Vec<ToolDefinition> {
         self.tools.values().map(|tool| tool.definition()).collect()
     }
 
+    /// Serializes the definitions of the registered tools as a [canonical
+    /// JSON](canonical_json) array, sorted by name.
+    #[must_use]
+    pub fn canonical_json(&self) -> String {
+        let definitions: Vec<String> = self
+            .definitions()
+            .iter()
+            .map(ToolDefinition::canonical_json)
+            .collect();
+        format!("[{}]", definitions.join(","))
+    }
+
+    /// Returns whether no tool is registered.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    /// Returns whether a tool named `name` is registered.
+    #[must_use]
+    pub fn contains(&self, name: &str) -> bool {
+        self.tools.contains_key(name)
+    }
+
     /// Registers a new tool. Replaces existing tool with same name.
     ///
     /// The tool must implement [`Tool`] and be `'static`.
     pub fn register<T: Tool + 'static>(&mut self, tool: T) {
-        self.tools
-            .insert(T::NAME.to_string(), Box::new(tool) as Box<dyn ToolImpl>);
+        self.tools.insert(
+            T::NAME.to_string(),
+            Arc::new(Mutex::new(tool)) as Arc<dyn ToolImpl>,
+        );
+    }
+
+    /// Registers a [`DynTool`] under the name from its definition. Replaces existing tool
+    /// with same name.
+    pub fn register_dyn<T: DynTool>(&mut self, tool: T) {
+        let definition = tool.definition();
+        self.tools.insert(
+            definition.name.to_string(),
+            Arc::new(Dynamic {
+                definition,
+                tool: Mutex::new(tool),
+            }) as Arc<dyn ToolImpl>,
+        );
     }
 
     /// Removes a tool from the registry.
@@ -332,16 +816,128 @@ impl Tools {
 
     /// Calls a tool by name with JSON arguments.
     ///
+    /// A [`ToolError::Recoverable`] returned by the tool is not treated as a failure: it is
+    /// formatted with [`ToolError::to_tool_result`] and returned as the tool's output, so
+    /// the model can correct itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tool is not found, has a timeout but no
+    /// [timer](Self::with_timer) is set, arguments cannot be parsed, tool execution fails
+    /// with an internal error, the tool panics ([`ToolError::Panicked`]), or it times out
+    /// ([`ToolError::Timeout`]).
+    pub async fn call(&self, name: &str, args: String) -> Result {
+        let Some(tool) = self.tools.get(name) else {
+            return Err(anyhow::Error::msg(format!("Tool '{name}' not found")));
+        };
+        if self.dry_run {
+            return match self.plan(&ToolCall::new("", name, args)) {
+                Ok(_) => Ok(format!("Dry run: tool '{name}' was not executed.")),
+                Err(error) => Ok(error.to_tool_result(name)),
+            };
+        }
+
+        let timeout = self
+            .timeouts
+            .per_tool
+            .get(name)
+            .copied()
+            .or(self.timeouts.default);
+        let call = AssertUnwindSafe(tool.call(args)).catch_unwind();
+        let outcome = match (&self.timeouts.timer, timeout) {
+            (Some(timer), Some(timeout)) => {
+                future::or(async { Some(call.await) }, async {
+                    timer.sleep(timeout).await;
+                    None
+                })
+                .await
+            }
+            (None, Some(_)) => {
+                return Err(anyhow::Error::msg(format!(
+                    "Tool '{name}' has a timeout, but no timer is set to enforce it"
+                )));
+            }
+            (_, None) => Some(call.await),
+        };
+        let result = match outcome {
+            Some(Ok(result)) => result,
+            Some(Err(panic)) => Err(ToolError::Panicked(panic_message(&*panic)).into()),
+            None => Err(ToolError::Timeout(timeout.unwrap_or_default()).into()),
+        };
+
+        match result {
+            Err(error) => match error.downcast_ref::<ToolError>() {
+                Some(tool_error) if tool_error.is_recoverable() => {
+                    Ok(tool_error.to_tool_result(name))
+                }
+                _ => Err(error),
+            },
+            result => result,
+        }
+    }
+
+    /// Validates the arguments of a [`ToolCall`] against the schema of its tool.
+    ///
+    /// Returns one [`ValidationError`] per offending field, or a single error when the
+    /// arguments are not valid JSON. Calls to unknown tools are not validated.
+    #[must_use]
+    pub fn validate(&self, call: &ToolCall) -> Vec<ValidationError> {
+        let Some(tool) = self.tools.get(call.name.as_str()) else {
+            return Vec::new();
+        };
+        match serde_json::from_str(&call.arguments) {
+            Ok(arguments) => {
+                validation::validate(tool.definition().arguments.as_value(), &arguments)
+            }
+            Err(error) => alloc::vec![ValidationError::new("$", "valid JSON", error.to_string())],
+        }
+    }
+
+    /// Resolves and validates a [`ToolCall`] without executing it.
+    ///
+    /// Returns what would be called: the tool's definition and the parsed arguments.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ToolError::Recoverable`] if the tool is not found or the arguments do not
+    /// match its schema, with a message for the model.
+    pub fn plan(&self, call: &ToolCall) -> core::result::Result<PlannedCall, ToolError> {
+        let Some(tool) = self.tools.get(call.name.as_str()) else {
+            return Err(ToolError::recoverable(format!(
+                "no tool named '{}'",
+                call.name
+            )));
+        };
+        let errors = self.validate(call);
+        if !errors.is_empty() {
+            let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            return Err(ToolError::recoverable(errors.join("; ")));
+        }
+        Ok(PlannedCall {
+            call: call.clone(),
+            definition: tool.definition(),
+            arguments: serde_json::from_str(&call.arguments).unwrap_or_default(),
+        })
+    }
+
+    /// Executes a [`ToolCall`] emitted by a language model.
+    ///
     /// # Errors
     ///
     /// Returns an error if the tool is not found, arguments cannot be parsed,
     /// or tool execution fails.
-    pub async fn call(&mut self, name: &str, args: String) -> Result {
-        if let Some(tool) = self.tools.get_mut(name) {
-            tool.call(args).await
-        } else {
-            Err(anyhow::Error::msg(format!("Tool '{name}' not found")))
-        }
+    pub async fn execute(&self, call: &ToolCall) -> Result {
+        self.call(&call.name, call.arguments.clone()).await
+    }
+
+    /// Executes several [`ToolCall`]s concurrently, such as the parallel tool calls of a
+    /// single model turn.
+    ///
+    /// Returns one result per call, in the order of `calls`, so each result can be
+    /// matched with the ID of its call. Calls to the same tool still run one at a time.
+    /// Each result is what [`execute`](Self::execute) would return for that call.
+    pub async fn call_many(&self, calls: &[ToolCall]) -> Vec<Result> {
+        join_all(calls.iter().map(|call| self.execute(call))).await
     }
 }
 
@@ -415,6 +1011,34 @@ mod tests {
         assert!(json_str.contains("\"value\": 42"));
     }
 
+    #[test]
+    fn canonical_json_is_independent_of_key_order() {
+        let mut forward = serde_json::Map::new();
+        forward.insert("type".into(), "object".into());
+        forward.insert("required".into(), serde_json::json!(["b", "a"]));
+        forward.insert("description".into(), "Quote \"and\" newline\n".into());
+        let mut backward = serde_json::Map::new();
+        for (key, value) in forward.iter().rev() {
+            backward.insert(key.clone(), value.clone());
+        }
+
+        let forward = ToolDefinition::from_parts("echo", "Echoes", Schema::from(forward));
+        let backward = ToolDefinition::from_parts("echo", "Echoes", Schema::from(backward));
+        assert_eq!(forward.canonical_json(), backward.canonical_json());
+        assert_eq!(
+            forward.canonical_json(),
+            r#"{"arguments":{"description":"Quote \"and\" newline\n","required":["b","a"],"type":"object"},"description":"Echoes","name":"echo"}"#
+        );
+
+        let mut tools = Tools::new();
+        tools.register(Greeter);
+        tools.register(Calculator);
+        let json = tools.canonical_json();
+        assert!(json.starts_with(r#"[{"arguments":{"$schema":"#));
+        assert!(json.contains(r#""name":"calculator"},{"arguments":"#));
+        assert!(json.ends_with(r#""name":"greeter"}]"#));
+    }
+
     #[test]
     fn tool_definition_creation() {
         let definition = ToolDefinition::new::<Calculator>();
@@ -572,7 +1196,7 @@ mod tests {
 
     #[tokio::test]
     async fn tool_not_found() {
-        let mut tools = Tools::new();
+        let tools = Tools::new();
 
         let result = tools.call("nonexistent", "{}".to_string()).await;
         assert!(result.is_err());
@@ -584,6 +1208,32 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn dry_run_plans_without_executing() {
+        let mut tools = Tools::new().with_dry_run();
+        tools.register(Calculator);
+        assert!(tools.is_dry_run());
+
+        let divide = ToolCall::new(
+            "1",
+            "calculator",
+            r#"{"operation": "divide", "a": 1, "b": 0}"#,
+        );
+        let output = tools.execute(&divide).await.unwrap();
+        assert_eq!(output, "Dry run: tool 'calculator' was not executed.");
+        let planned = tools.plan(&divide).unwrap();
+        assert_eq!(planned.definition.name, "calculator");
+        assert_eq!(planned.arguments["b"], 0);
+
+        let invalid = ToolCall::new("2", "calculator", r#"{"operation": "add", "a": "1"}"#);
+        let output = tools.execute(&invalid).await.unwrap();
+        assert!(output.starts_with("Error: tool 'calculator' failed: $.a: expected number"));
+        assert!(tools.plan(&invalid).unwrap_err().is_recoverable());
+
+        assert!(tools.call("missing", "{}".to_string()).await.is_err());
+        assert!(tools.plan(&ToolCall::new("3", "missing", "{}")).is_err());
+    }
+
     #[tokio::test]
     async fn invalid_json() {
         let mut tools = Tools::new();
@@ -633,6 +1283,196 @@ mod tests {
         assert!(debug_str.contains("Performs basic mathematical operations"));
     }
 
+    #[tokio::test]
+    async fn tools_execute_call() {
+        let mut tools = Tools::new();
+        tools.register(Greeter);
+
+        let call = ToolCall::new("call_1", "greeter", r#"{"name": "Bob"}"#);
+        assert_eq!(tools.execute(&call).await.unwrap(), "Hello, Bob!");
+    }
+
+    struct Lookup;
+
+    impl Tool for Lookup {
+        const NAME: &str = "lookup";
+        const DESCRIPTION: &str = "Looks up a record";
+        type Arguments = GreetArgs;
+
+        async fn call(&mut self, args: Self::Arguments) -> Result {
+            match args.name.as_str() {
+                "missing" => Err(ToolError::recoverable("no record named 'missing'").into()),
+                "broken" => Err(ToolError::internal("database unavailable").into()),
+                name => Ok(format!("record {name}")),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn recoverable_error_becomes_tool_result() {
+        let mut tools = Tools::new();
+        tools.register(Lookup);
+
+        let output = tools
+            .call("lookup", r#"{"name": "missing"}"#.to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            output,
+            "Error: tool 'lookup' failed: no record named 'missing'. Adjust the arguments and try again."
+        );
+    }
+
+    #[tokio::test]
+    async fn internal_error_is_propagated() {
+        let mut tools = Tools::new();
+        tools.register(Lookup);
+
+        let error = tools
+            .call("lookup", r#"{"name": "broken"}"#.to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ToolError>(),
+            Some(&ToolError::internal("database unavailable"))
+        );
+    }
+
+    struct Sleeper(&'static str);
+
+    impl DynTool for Sleeper {
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition::from_parts(self.0, "Sleeps", schemars::json_schema!(true))
+        }
+
+        async fn call(&mut self, arguments: String) -> Result {
+            let millis = arguments.parse()?;
+            tokio::time::sleep(core::time::Duration::from_millis(millis)).await;
+            Ok(format!("{} slept {millis}ms", self.0))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn call_many_runs_calls_concurrently_in_order() {
+        let mut tools = Tools::new();
+        tools.register_dyn(Sleeper("slow"));
+        tools.register_dyn(Sleeper("fast"));
+        let calls = [
+            ToolCall::new("call_1", "slow", "200"),
+            ToolCall::new("call_2", "fast", "100"),
+            ToolCall::new("call_3", "missing", "0"),
+        ];
+
+        // The clock is paused, so the calls take exactly as long as the slowest one.
+        let start = tokio::time::Instant::now();
+        let results = tools.call_many(&calls).await;
+        assert_eq!(start.elapsed(), core::time::Duration::from_millis(200));
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap(), "slow slept 200ms");
+        assert_eq!(results[1].as_ref().unwrap(), "fast slept 100ms");
+        assert!(results[2].is_err());
+    }
+
+    struct Panicky;
+
+    impl DynTool for Panicky {
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition::from_parts("panicky", "Panics", schemars::json_schema!(true))
+        }
+
+        async fn call(&mut self, _arguments: String) -> Result {
+            panic!("out of cheese")
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn panics_and_timeouts_become_tool_errors() {
+        let mut tools = Tools::new()
+            .with_timer(tokio::time::sleep)
+            .with_timeout(core::time::Duration::from_millis(50))
+            .with_tool_timeout("slow", core::time::Duration::from_millis(500));
+        tools.register_dyn(Panicky);
+        tools.register_dyn(Sleeper("slow"));
+        tools.register_dyn(Sleeper("stuck"));
+
+        let error = tools.call("panicky", "{}".to_string()).await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ToolError>(),
+            Some(&ToolError::Panicked("out of cheese".into()))
+        );
+        // The registry is still usable after a panic.
+        let error = tools.call("panicky", "{}".to_string()).await.unwrap_err();
+        assert!(error.is::<ToolError>());
+
+        let error = tools.call("stuck", "100".to_string()).await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ToolError>(),
+            Some(&ToolError::Timeout(core::time::Duration::from_millis(50)))
+        );
+        let output = tools.call("slow", "100".to_string()).await.unwrap();
+        assert_eq!(output, "slow slept 100ms");
+
+        let mut untimed = Tools::new().with_timeout(core::time::Duration::from_millis(50));
+        untimed.register_dyn(Sleeper("stuck"));
+        let error = untimed.call("stuck", "0".to_string()).await.unwrap_err();
+        assert!(error.to_string().contains("no timer"));
+    }
+
+    struct Proxy {
+        name: &'static str,
+        calls: usize,
+    }
+
+    impl DynTool for Proxy {
+        fn definition(&self) -> ToolDefinition {
+            let schema = schemars::json_schema!({
+                "type": "object",
+                "properties": { "name": { "type": "string" } },
+                "required": ["name"]
+            });
+            ToolDefinition::from_parts(self.name, "Proxies a remote tool", schema)
+        }
+
+        async fn call(&mut self, arguments: String) -> Result {
+            self.calls += 1;
+            Ok(format!("{} call #{}: {arguments}", self.name, self.calls))
+        }
+    }
+
+    #[tokio::test]
+    async fn dynamic_tools_register_under_runtime_names() {
+        let mut tools = Tools::new();
+        tools.register(Greeter);
+        tools.register_dyn(Proxy {
+            name: "remote_search",
+            calls: 0,
+        });
+
+        let names: Vec<_> = tools.definitions().into_iter().map(|d| d.name).collect();
+        assert_eq!(names, ["greeter", "remote_search"]);
+
+        let output = tools.call("remote_search", "{}".to_string()).await.unwrap();
+        assert_eq!(output, "remote_search call #1: {}");
+        let output = tools.call("remote_search", "{}".to_string()).await.unwrap();
+        assert_eq!(output, "remote_search call #2: {}");
+
+        let call = ToolCall::new("call_1", "remote_search", "{}");
+        assert_eq!(tools.validate(&call).len(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn tool_definition_serde_roundtrip() {
+        let definition = ToolDefinition::new::<Calculator>();
+        let json = serde_json::to_string(&definition).unwrap();
+        let restored: ToolDefinition = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.name, "calculator");
+        assert_eq!(restored.description, definition.description);
+        assert_eq!(restored.arguments, definition.arguments);
+    }
+
     #[test]
     fn tool_definition_clone() {
         let original = ToolDefinition::new::<Calculator>();
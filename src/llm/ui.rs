@@ -0,0 +1,349 @@
+//! Render-ready conversation state for chat interfaces.
+//!
+//! Streaming responses arrive as a sequence of [`StreamEvent`]s: text deltas, tool calls,
+//! citations and so on. A [`ChatView`] folds them, along with the user's messages and
+//! tool results, into a list of [`Turn`]s that a user interface can render directly.
+//!
+//! [`ChatView::reduce`] is a pure reducer in the style of Elm or Redux: it takes the
+//! current view and an [`Action`], and returns the next view. It performs no I/O, so it
+//! fits the state management of any GUI framework and is easy to test.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{
+//!     event::StreamEvent,
+//!     tool::ToolCall,
+//!     ui::{Action, ChatView, ToolCallStatus, TurnStatus},
+//! };
+//!
+//! let mut view = ChatView::new();
+//! for action in [
+//!     Action::UserMessage("What's the weather in Paris?".into()),
+//!     Action::Event(StreamEvent::Text("Let me ".into())),
+//!     Action::Event(StreamEvent::Text("check.".into())),
+//!     Action::Event(StreamEvent::ToolCall(ToolCall::new("1", "weather", "{}"))),
+//!     Action::ToolResult { id: "1".into(), output: "Sunny".into() },
+//!     Action::Event(StreamEvent::Done),
+//! ] {
+//!     view = view.reduce(action);
+//! }
+//!
+//! let answer = &view.turns[1];
+//! assert_eq!(answer.text, "Let me check.");
+//! assert_eq!(answer.tool_calls[0].status, ToolCallStatus::Completed("Sunny".into()));
+//! assert_eq!(answer.status, TurnStatus::Done);
+//! ```
+
+use alloc::{string::String, vec::Vec};
+
+use crate::{
+    llm::{
+        Annotation, Role,
+        event::StreamEvent,
+        metadata::{FinishReason, ResponseMetadata},
+        tool::ToolCall,
+        usage::Usage,
+        validation::ValidationError,
+    },
+    provenance::Provenance,
+};
+
+/// An input to [`ChatView::reduce`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Action {
+    /// The user sent a message.
+    UserMessage(String),
+    /// The model's response emitted an event.
+    Event(StreamEvent),
+    /// A tool call of the current response returned.
+    ToolResult {
+        /// The ID of the tool call.
+        id: String,
+        /// The tool's output.
+        output: String,
+    },
+    /// A tool call of the current response failed.
+    ToolFailed {
+        /// The ID of the tool call.
+        id: String,
+        /// A description of the failure.
+        error: String,
+    },
+    /// The model's response failed.
+    Error(String),
+}
+
+/// The state of a conversation, as shown to the user.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct ChatView {
+    /// The turns of the conversation, oldest first.
+    pub turns: Vec<Turn>,
+}
+
+impl ChatView {
+    /// Creates an empty view.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { turns: Vec::new() }
+    }
+
+    /// Returns the view after `action`.
+    #[must_use]
+    pub fn reduce(mut self, action: Action) -> Self {
+        self.apply(action);
+        self
+    }
+
+    /// Updates the view in place with `action`.
+    ///
+    /// Events start a new assistant turn unless the last turn is an assistant turn still
+    /// streaming. Tool results are matched to calls of the last assistant turn by ID, and
+    /// ignored if no call matches.
+    pub fn apply(&mut self, action: Action) {
+        match action {
+            Action::UserMessage(text) => self.turns.push(Turn::new(Role::User, text)),
+            Action::Event(event) => self.event(event),
+            Action::ToolResult { id, output } => {
+                if let Some(call) = self.tool_call(&id) {
+                    call.status = ToolCallStatus::Completed(output);
+                }
+            }
+            Action::ToolFailed { id, error } => {
+                if let Some(call) = self.tool_call(&id) {
+                    call.status = ToolCallStatus::Failed(error);
+                }
+            }
+            Action::Error(error) => self.streaming().status = TurnStatus::Failed(error),
+        }
+    }
+
+    /// Returns whether the model is still responding.
+    #[must_use]
+    pub fn is_streaming(&self) -> bool {
+        self.turns
+            .last()
+            .is_some_and(|turn| turn.status == TurnStatus::Streaming)
+    }
+
+    fn event(&mut self, event: StreamEvent) {
+        let turn = self.streaming();
+        match event {
+            StreamEvent::Text(text) => turn.text.push_str(&text),
+            StreamEvent::ToolCall(call) => turn.tool_calls.push(ToolCallView {
+                call,
+                status: ToolCallStatus::Pending,
+            }),
+            StreamEvent::InvalidToolCall { call, errors } => turn.tool_calls.push(ToolCallView {
+                call,
+                status: ToolCallStatus::Invalid(errors),
+            }),
+            StreamEvent::Annotation(annotation) => turn.citations.push(annotation),
+            StreamEvent::Usage(usage) => turn.usage = Some(usage),
+            StreamEvent::Provenance(provenance) => turn.provenance = Some(provenance),
+            StreamEvent::Metadata(metadata) => turn
+                .metadata
+                .get_or_insert_with(ResponseMetadata::new)
+                .update(metadata),
+            StreamEvent::Done => turn.status = TurnStatus::Done,
+            _ => {}
+        }
+    }
+
+    /// Returns the assistant turn receiving events, starting one if needed.
+    fn streaming(&mut self) -> &mut Turn {
+        if !self.is_streaming() {
+            self.turns.push(Turn::new(Role::Assistant, String::new()));
+        }
+        let last = self.turns.len() - 1;
+        &mut self.turns[last]
+    }
+
+    fn tool_call(&mut self, id: &str) -> Option<&mut ToolCallView> {
+        self.turns
+            .iter_mut()
+            .rev()
+            .find(|turn| turn.role == Role::Assistant)?
+            .tool_calls
+            .iter_mut()
+            .find(|view| view.call.id == id)
+    }
+}
+
+/// A message of the conversation, with everything shown alongside it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Turn {
+    /// Who wrote the message.
+    pub role: Role,
+    /// The text received so far.
+    pub text: String,
+    /// The tool calls made in this turn, in order.
+    pub tool_calls: Vec<ToolCallView>,
+    /// The citations of the text.
+    pub citations: Vec<Annotation>,
+    /// Token usage of the response, once reported.
+    pub usage: Option<Usage>,
+    /// Where the response came from, once reported.
+    pub provenance: Option<Provenance>,
+    /// Details about how the response was produced, once reported.
+    pub metadata: Option<ResponseMetadata>,
+    /// Whether the turn is complete.
+    pub status: TurnStatus,
+}
+
+impl Turn {
+    /// Returns whether the response was cut off before the model finished it, such as by
+    /// the token limit, according to the reported [`FinishReason`].
+    #[must_use]
+    pub fn is_truncated(&self) -> bool {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.finish_reason.as_ref())
+            .is_some_and(FinishReason::is_truncated)
+    }
+
+    fn new(role: Role, text: String) -> Self {
+        Self {
+            role,
+            text,
+            tool_calls: Vec::new(),
+            citations: Vec::new(),
+            usage: None,
+            provenance: None,
+            metadata: None,
+            status: if role == Role::User {
+                TurnStatus::Done
+            } else {
+                TurnStatus::Streaming
+            },
+        }
+    }
+}
+
+/// Whether a [`Turn`] is complete.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TurnStatus {
+    /// The model is still responding.
+    Streaming,
+    /// The turn is complete.
+    Done,
+    /// The response failed, with a description of the failure.
+    Failed(String),
+}
+
+/// A tool call, as shown to the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ToolCallView {
+    /// The call requested by the model.
+    pub call: ToolCall,
+    /// What happened to the call.
+    pub status: ToolCallStatus,
+}
+
+/// What happened to a tool call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ToolCallStatus {
+    /// The call is waiting for its result.
+    Pending,
+    /// The call's arguments do not match the tool's schema.
+    Invalid(Vec<ValidationError>),
+    /// The tool returned this output.
+    Completed(String),
+    /// The tool failed, with a description of the failure.
+    Failed(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::UrlAnnotation;
+    use alloc::vec;
+
+    fn reduce_all(actions: impl IntoIterator<Item = Action>) -> ChatView {
+        actions.into_iter().fold(ChatView::new(), ChatView::reduce)
+    }
+
+    #[test]
+    fn events_build_assistant_turns() {
+        let citation = Annotation::Url(UrlAnnotation::new(
+            "https://example.com",
+            "Example",
+            "An example",
+            0,
+            5,
+        ));
+        let view = reduce_all([
+            Action::UserMessage("Hi".into()),
+            Action::Event(StreamEvent::Text("Hello".into())),
+            Action::Event(StreamEvent::Annotation(citation.clone())),
+            Action::Event(StreamEvent::Usage(Usage::new(10, 2))),
+            Action::Event(StreamEvent::Metadata(
+                ResponseMetadata::new().with_request_id("req-1"),
+            )),
+            Action::Event(StreamEvent::Metadata(
+                ResponseMetadata::new().with_finish_reason(FinishReason::Stop),
+            )),
+            Action::Event(StreamEvent::Done),
+            Action::UserMessage("Again".into()),
+            Action::Event(StreamEvent::Text("Hel".into())),
+        ]);
+
+        assert_eq!(view.turns.len(), 4);
+        assert_eq!(view.turns[1].text, "Hello");
+        assert_eq!(view.turns[1].citations, [citation]);
+        assert_eq!(view.turns[1].usage, Some(Usage::new(10, 2)));
+        assert_eq!(
+            view.turns[1].metadata,
+            Some(
+                ResponseMetadata::new()
+                    .with_request_id("req-1")
+                    .with_finish_reason(FinishReason::Stop)
+            )
+        );
+        assert_eq!(view.turns[1].status, TurnStatus::Done);
+        assert!(!view.turns[1].is_truncated());
+        assert_eq!(view.turns[3].text, "Hel");
+        assert!(view.is_streaming());
+    }
+
+    #[test]
+    fn tool_results_update_matching_calls() {
+        let error = ValidationError::new("$.city", "string", "missing");
+        let view = reduce_all([
+            Action::Event(StreamEvent::ToolCall(ToolCall::new("a", "search", "{}"))),
+            Action::Event(StreamEvent::ToolCall(ToolCall::new("b", "search", "{}"))),
+            Action::Event(StreamEvent::InvalidToolCall {
+                call: ToolCall::new("c", "weather", "{}"),
+                errors: vec![error.clone()],
+            }),
+            Action::ToolFailed {
+                id: "b".into(),
+                error: "timeout".into(),
+            },
+            Action::ToolResult {
+                id: "unknown".into(),
+                output: "ignored".into(),
+            },
+            Action::Error("connection reset".into()),
+        ]);
+
+        let turn = &view.turns[0];
+        let statuses: Vec<_> = turn.tool_calls.iter().map(|v| v.status.clone()).collect();
+        assert_eq!(
+            statuses,
+            [
+                ToolCallStatus::Pending,
+                ToolCallStatus::Failed("timeout".into()),
+                ToolCallStatus::Invalid(vec![error]),
+            ]
+        );
+        assert_eq!(turn.status, TurnStatus::Failed("connection reset".into()));
+        assert!(!view.is_streaming());
+    }
+}
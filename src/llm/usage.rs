@@ -0,0 +1,138 @@
+//! Token usage reported by language models.
+//!
+//! Providers report how many tokens a response consumed through a
+//! [`StreamEvent::Usage`](crate::llm::event::StreamEvent::Usage) event, emitted by
+//! [`LanguageModel::respond_events`](crate::LanguageModel::respond_events) before the stream
+//! completes. Combined with a model's [`Pricing`], this enables cost tracking.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{model::Pricing, usage::Usage};
+//!
+//! let mut total = Usage::default();
+//! total += Usage::new(1200, 300);
+//! total += Usage::new(800, 200).with_cached_tokens(500);
+//!
+//! assert_eq!(total.prompt_tokens, 2000);
+//! assert_eq!(total.total_tokens, 2500);
+//!
+//! let mut pricing = Pricing::default();
+//! pricing.prompt = 0.000_01;
+//! pricing.completion = 0.000_03;
+//! let cost = total.cost(&pricing);
+//! assert!(cost > 0.0);
+//! ```
+
+use core::ops::{Add, AddAssign};
+
+use crate::llm::model::Pricing;
+
+/// Token usage of a single response, or an accumulated total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[non_exhaustive]
+pub struct Usage {
+    /// Tokens in the prompt, including cached tokens.
+    pub prompt_tokens: u32,
+    /// Tokens generated in the completion.
+    pub completion_tokens: u32,
+    /// Total tokens consumed, usually `prompt_tokens + completion_tokens`.
+    pub total_tokens: u32,
+    /// Prompt tokens served from the provider's input cache.
+    pub cached_tokens: u32,
+}
+
+impl Usage {
+    /// Creates a usage record, computing `total_tokens` from the prompt and completion.
+    #[must_use]
+    pub const fn new(prompt_tokens: u32, completion_tokens: u32) -> Self {
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens.saturating_add(completion_tokens),
+            cached_tokens: 0,
+        }
+    }
+
+    /// Sets the number of prompt tokens served from the input cache.
+    #[must_use]
+    pub const fn with_cached_tokens(mut self, cached_tokens: u32) -> Self {
+        self.cached_tokens = cached_tokens;
+        self
+    }
+
+    /// Sets the total token count, for providers that count additional tokens
+    /// (such as internal reasoning) in the total.
+    #[must_use]
+    pub const fn with_total_tokens(mut self, total_tokens: u32) -> Self {
+        self.total_tokens = total_tokens;
+        self
+    }
+
+    /// Computes the cost of this usage in USD.
+    ///
+    /// Uncached prompt tokens are charged at [`Pricing::prompt`], cached ones at
+    /// [`Pricing::input_cache_read`], and completion tokens at [`Pricing::completion`].
+    #[must_use]
+    pub fn cost(&self, pricing: &Pricing) -> f64 {
+        let cached = self.cached_tokens.min(self.prompt_tokens);
+        let uncached = self.prompt_tokens - cached;
+        f64::from(uncached) * pricing.prompt
+            + f64::from(cached) * pricing.input_cache_read
+            + f64::from(self.completion_tokens) * pricing.completion
+    }
+}
+
+impl Add for Usage {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            prompt_tokens: self.prompt_tokens.saturating_add(rhs.prompt_tokens),
+            completion_tokens: self.completion_tokens.saturating_add(rhs.completion_tokens),
+            total_tokens: self.total_tokens.saturating_add(rhs.total_tokens),
+            cached_tokens: self.cached_tokens.saturating_add(rhs.cached_tokens),
+        }
+    }
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_new_computes_total() {
+        let usage = Usage::new(10, 5);
+        assert_eq!(usage.total_tokens, 15);
+        assert_eq!(usage.cached_tokens, 0);
+        assert_eq!(usage.with_total_tokens(20).total_tokens, 20);
+    }
+
+    #[test]
+    fn usage_accumulates() {
+        let mut usage = Usage::new(10, 5).with_cached_tokens(4);
+        usage += Usage::new(1, 2);
+        assert_eq!(usage, Usage::new(11, 7).with_cached_tokens(4));
+    }
+
+    #[test]
+    fn usage_cost() {
+        let pricing = Pricing {
+            prompt: 2.0,
+            completion: 3.0,
+            input_cache_read: 1.0,
+            ..Pricing::default()
+        };
+
+        let usage = Usage::new(10, 5).with_cached_tokens(4);
+        assert!((usage.cost(&pricing) - (6.0 * 2.0 + 4.0 + 5.0 * 3.0)).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,568 @@
+//! Validation of tool-call arguments against their JSON schema.
+//!
+//! Models sometimes call tools with malformed arguments: a missing field, a number where a
+//! string belongs. Instead of a generic parse error, [`validate`] reports each problem as a
+//! [`ValidationError`] with the path of the offending field and the expected type, which
+//! an agent loop can send back so the model corrects exactly those fields.
+//!
+//! [`validate_tool_calls`] applies this to a stream of events, turning invalid calls into
+//! [`StreamEvent::InvalidToolCall`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::llm::{Tool, tool::{ToolCall, Tools}};
+//! use schemars::JsonSchema;
+//! use serde::Deserialize;
+//!
+//! #[derive(JsonSchema, Deserialize)]
+//! struct WeatherArgs {
+//!     city: String,
+//!     days: u8,
+//! }
+//!
+//! struct Weather;
+//!
+//! impl Tool for Weather {
+//!     const NAME: &str = "weather";
+//!     const DESCRIPTION: &str = "Forecasts the weather";
+//!     type Arguments = WeatherArgs;
+//!
+//!     async fn call(&mut self, args: Self::Arguments) -> ai_types::Result {
+//!         Ok(format!("Sunny in {} for {} days", args.city, args.days))
+//!     }
+//! }
+//!
+//! let mut tools = Tools::new();
+//! tools.register(Weather);
+//!
+//! let call = ToolCall::new("call_1", "weather", r#"{"city": 42}"#);
+//! let errors = tools.validate(&call);
+//! assert_eq!(errors.len(), 2);
+//! assert_eq!(errors[0].path, "$.city");
+//! assert_eq!(errors[0].expected, "string");
+//! assert_eq!(errors[1].path, "$.days");
+//! ```
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{self, Write};
+
+use async_stream::stream;
+use futures_core::Stream;
+use futures_lite::{StreamExt, pin};
+use serde_json::{Map, Value};
+
+use crate::llm::{event::StreamEvent, tool::Tools};
+
+/// A tool-call argument that does not match the tool's schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct ValidationError {
+    /// Path of the offending field, e.g. `$.address.city` or `$.items[2]`.
+    pub path: String,
+    /// What the schema expects at the path, e.g. `string` or `one of "c", "f"`.
+    pub expected: String,
+    /// What the arguments contain at the path, e.g. `number` or `missing`.
+    pub found: String,
+}
+
+impl ValidationError {
+    /// Creates a validation error.
+    pub fn new(
+        path: impl Into<String>,
+        expected: impl Into<String>,
+        found: impl Into<String>,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            expected: expected.into(),
+            found: found.into(),
+        }
+    }
+
+    /// Formats errors of a call to `tool` as a tool result asking the model to fix them.
+    #[must_use]
+    pub fn to_tool_result(tool: &str, errors: &[Self]) -> String {
+        let mut message = format!("Error: invalid arguments for tool '{tool}':");
+        for error in errors {
+            let _ = write!(message, "\n- {error}");
+        }
+        message.push_str("\nCorrect these fields and call the tool again.");
+        message
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: expected {}, found {}",
+            self.path, self.expected, self.found
+        )
+    }
+}
+
+impl core::error::Error for ValidationError {}
+
+/// Validates `arguments` against a JSON schema.
+///
+/// Supports the subset of JSON Schema generated by [`schemars`] for tool arguments:
+/// `type`, `enum`, `const`, `properties`, `required`, `items`, `anyOf`, `oneOf`, `allOf`,
+/// and local `$ref`s. Errors are sorted by path. Returns an empty vector when the arguments
+/// are valid.
+#[must_use]
+pub fn validate(schema: &Value, arguments: &Value) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    Validator { root: schema }.check(schema, arguments, "$", &[], &mut errors);
+    errors.sort_by(|a, b| a.path.cmp(&b.path));
+    errors
+}
+
+/// Checks the tool calls in a stream of events against the schemas of `tools`.
+///
+/// Calls whose arguments are not valid JSON or do not match their tool's schema are
+/// replaced by [`StreamEvent::InvalidToolCall`]. Calls to unknown tools and all other
+/// events pass through unchanged.
+pub fn validate_tool_calls<'a, S, E>(
+    events: S,
+    tools: &'a Tools,
+) -> impl Stream<Item = Result<StreamEvent, E>> + Send + 'a
+where
+    S: Stream<Item = Result<StreamEvent, E>> + Send + 'a,
+    E: Send + 'a,
+{
+    stream! {
+        pin!(events);
+        while let Some(event) = events.next().await {
+            yield match event {
+                Ok(StreamEvent::ToolCall(call)) => {
+                    let errors = tools.validate(&call);
+                    if errors.is_empty() {
+                        Ok(StreamEvent::ToolCall(call))
+                    } else {
+                        Ok(StreamEvent::InvalidToolCall { call, errors })
+                    }
+                }
+                other => other,
+            };
+        }
+    }
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+}
+
+impl<'a> Validator<'a> {
+    /// Checks `value` against `schema`.
+    ///
+    /// `refs` are the `$ref`s followed since the last step into a field or item of `value`.
+    /// Following one of them again would loop forever without checking anything new.
+    fn check(
+        &self,
+        schema: &'a Value,
+        value: &Value,
+        path: &str,
+        refs: &[&'a str],
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let Some(schema) = schema.as_object() else {
+            if schema == &Value::Bool(false) {
+                errors.push(ValidationError::new(path, "no value", type_name(value)));
+            }
+            return;
+        };
+
+        if let Some(target) = schema.get("$ref").and_then(Value::as_str) {
+            if refs.contains(&target) {
+                errors.push(ValidationError::new(
+                    path,
+                    "a schema without circular references",
+                    format!("a cycle through $ref {target:?}"),
+                ));
+                return;
+            }
+            if let Some(resolved) = self.resolve(target) {
+                let mut refs = refs.to_vec();
+                refs.push(target);
+                self.check(resolved, value, path, &refs, errors);
+            }
+        }
+
+        if let Some(expected) = schema.get("type") {
+            if !matches_type(expected, value) {
+                errors.push(ValidationError::new(
+                    path,
+                    describe_type(expected),
+                    type_name(value),
+                ));
+                return;
+            }
+        }
+
+        if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+            if !options.contains(value) {
+                errors.push(ValidationError::new(
+                    path,
+                    format!("one of {}", join(options)),
+                    value.to_string(),
+                ));
+            }
+        }
+        if let Some(constant) = schema.get("const") {
+            if constant != value {
+                errors.push(ValidationError::new(
+                    path,
+                    constant.to_string(),
+                    value.to_string(),
+                ));
+            }
+        }
+
+        for key in ["anyOf", "oneOf"] {
+            if let Some(branches) = schema.get(key).and_then(Value::as_array) {
+                self.check_any(branches, value, path, refs, errors);
+            }
+        }
+        if let Some(branches) = schema.get("allOf").and_then(Value::as_array) {
+            for branch in branches {
+                self.check(branch, value, path, refs, errors);
+            }
+        }
+
+        if let Value::Object(object) = value {
+            self.check_object(schema, object, path, errors);
+        }
+        if let (Some(items), Value::Array(array)) = (schema.get("items"), value) {
+            for (index, item) in array.iter().enumerate() {
+                self.check(items, item, &format!("{path}[{index}]"), &[], errors);
+            }
+        }
+    }
+
+    fn check_object(
+        &self,
+        schema: &'a Map<String, Value>,
+        object: &Map<String, Value>,
+        path: &str,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    let expected = properties
+                        .and_then(|properties| properties.get(name))
+                        .and_then(|property| self.expected(property))
+                        .unwrap_or_else(|| "a value".to_string());
+                    errors.push(ValidationError::new(
+                        format!("{path}.{name}"),
+                        expected,
+                        "missing",
+                    ));
+                }
+            }
+        }
+        if let Some(properties) = properties {
+            for (name, property) in properties {
+                if let Some(value) = object.get(name) {
+                    self.check(property, value, &format!("{path}.{name}"), &[], errors);
+                }
+            }
+        }
+    }
+
+    fn check_any(
+        &self,
+        branches: &'a [Value],
+        value: &Value,
+        path: &str,
+        refs: &[&'a str],
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let mut closest: Option<Vec<ValidationError>> = None;
+        for branch in branches {
+            let mut branch_errors = Vec::new();
+            self.check(branch, value, path, refs, &mut branch_errors);
+            if branch_errors.is_empty() {
+                return;
+            }
+            if closest
+                .as_ref()
+                .is_none_or(|closest| branch_errors.len() < closest.len())
+            {
+                closest = Some(branch_errors);
+            }
+        }
+
+        // A single error at the path itself means no branch matched the value's shape.
+        match closest {
+            Some(closest) if closest.iter().any(|error| error.path != path) => {
+                errors.extend(closest);
+            }
+            Some(_) => {
+                let expected: Vec<String> = branches
+                    .iter()
+                    .filter_map(|branch| self.expected(branch))
+                    .collect();
+                errors.push(ValidationError::new(
+                    path,
+                    expected.join(" or "),
+                    type_name(value),
+                ));
+            }
+            None => {}
+        }
+    }
+
+    /// Describes what a schema expects, for error messages.
+    fn expected(&self, mut schema: &'a Value) -> Option<String> {
+        let mut refs = Vec::new();
+        let schema = loop {
+            let object = schema.as_object()?;
+            match object.get("$ref").and_then(Value::as_str) {
+                Some(target) if refs.contains(&target) => return None,
+                Some(target) => {
+                    refs.push(target);
+                    schema = self.resolve(target)?;
+                }
+                None => break object,
+            }
+        };
+        if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+            return Some(format!("one of {}", join(options)));
+        }
+        if let Some(constant) = schema.get("const") {
+            return Some(constant.to_string());
+        }
+        schema.get("type").map(describe_type)
+    }
+
+    fn resolve(&self, target: &str) -> Option<&'a Value> {
+        let pointer = target.strip_prefix('#')?;
+        self.root.pointer(pointer)
+    }
+}
+
+fn matches_type(expected: &Value, value: &Value) -> bool {
+    match expected {
+        Value::String(name) => is_type(name, value),
+        Value::Array(names) => names
+            .iter()
+            .filter_map(Value::as_str)
+            .any(|name| is_type(name, value)),
+        _ => true,
+    }
+}
+
+fn is_type(name: &str, value: &Value) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    }
+}
+
+fn describe_type(expected: &Value) -> String {
+    match expected {
+        Value::Array(names) => {
+            let names: Vec<&str> = names.iter().filter_map(Value::as_str).collect();
+            names.join(" or ")
+        }
+        other => other.as_str().unwrap_or("a value").to_string(),
+    }
+}
+
+const fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn join(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{Tool, tool::ToolCall};
+    use alloc::vec;
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(JsonSchema, Deserialize)]
+    #[allow(dead_code)]
+    enum Unit {
+        Celsius,
+        Fahrenheit,
+    }
+
+    #[derive(JsonSchema, Deserialize)]
+    #[allow(dead_code)]
+    struct Location {
+        city: String,
+        country: Option<String>,
+    }
+
+    #[derive(JsonSchema, Deserialize)]
+    #[allow(dead_code)]
+    struct ForecastArgs {
+        location: Location,
+        days: u8,
+        unit: Unit,
+        hours: Vec<u8>,
+    }
+
+    struct Forecast;
+
+    impl Tool for Forecast {
+        const NAME: &str = "forecast";
+        const DESCRIPTION: &str = "Forecasts the weather";
+        type Arguments = ForecastArgs;
+
+        async fn call(&mut self, _args: Self::Arguments) -> crate::Result {
+            Ok("Sunny".into())
+        }
+    }
+
+    fn tools() -> Tools {
+        let mut tools = Tools::new();
+        tools.register(Forecast);
+        tools
+    }
+
+    fn errors(arguments: &Value) -> Vec<ValidationError> {
+        tools().validate(&ToolCall::new("1", "forecast", arguments.to_string()))
+    }
+
+    #[test]
+    fn accepts_valid_arguments() {
+        let arguments = json!({
+            "location": {"city": "Paris", "country": null},
+            "days": 3,
+            "unit": "Celsius",
+            "hours": [8, 12],
+        });
+        assert_eq!(errors(&arguments), []);
+    }
+
+    #[test]
+    fn reports_nested_paths_and_types() {
+        let arguments = json!({
+            "location": {"city": 75},
+            "days": "three",
+            "unit": "Kelvin",
+            "hours": [8, "noon"],
+        });
+        assert_eq!(
+            errors(&arguments),
+            [
+                ValidationError::new("$.days", "integer", "string"),
+                ValidationError::new("$.hours[1]", "integer", "string"),
+                ValidationError::new("$.location.city", "string", "number"),
+                ValidationError::new("$.unit", r#"one of "Celsius", "Fahrenheit""#, r#""Kelvin""#),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_missing_fields_and_invalid_json() {
+        assert_eq!(
+            errors(&json!({"location": {}, "days": 1, "unit": "Celsius", "hours": []})),
+            [ValidationError::new("$.location.city", "string", "missing")]
+        );
+
+        let call = ToolCall::new("1", "forecast", "{not json");
+        let errors = tools().validate(&call);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].expected, "valid JSON");
+    }
+
+    #[tokio::test]
+    async fn stream_marks_invalid_calls() {
+        let tools = tools();
+        let invalid = ToolCall::new("1", "forecast", "{}");
+        let unknown = ToolCall::new("2", "unknown", "{}");
+        let events = futures_lite::stream::iter(vec![
+            Ok::<_, core::convert::Infallible>(StreamEvent::ToolCall(invalid.clone())),
+            Ok(StreamEvent::ToolCall(unknown.clone())),
+        ]);
+
+        let events: Vec<_> = validate_tool_calls(events, &tools)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert!(matches!(
+            &events[0],
+            StreamEvent::InvalidToolCall { call, errors } if *call == invalid && errors.len() == 4
+        ));
+        assert_eq!(events[1], StreamEvent::ToolCall(unknown));
+    }
+
+    #[test]
+    fn stops_at_circular_references() {
+        let cycle = ValidationError::new(
+            "$",
+            "a schema without circular references",
+            r##"a cycle through $ref "#""##,
+        );
+        assert_eq!(validate(&json!({"$ref": "#"}), &json!(1)), [cycle]);
+
+        let schema = json!({
+            "$ref": "#/$defs/a",
+            "$defs": {"a": {"anyOf": [{"$ref": "#/$defs/a"}]}},
+        });
+        assert_eq!(validate(&schema, &json!(1)).len(), 1);
+
+        let tree = json!({
+            "$ref": "#/$defs/node",
+            "$defs": {"node": {
+                "type": "object",
+                "properties": {"value": {"type": "integer"}, "child": {"$ref": "#/$defs/node"}},
+            }},
+        });
+        assert_eq!(
+            validate(&tree, &json!({"child": {"child": {"value": "x"}}})),
+            [ValidationError::new(
+                "$.child.child.value",
+                "integer",
+                "string"
+            )]
+        );
+    }
+
+    #[test]
+    fn formats_tool_result() {
+        let message = ValidationError::to_tool_result(
+            "forecast",
+            &[ValidationError::new("$.days", "integer", "string")],
+        );
+        assert_eq!(
+            message,
+            "Error: invalid arguments for tool 'forecast':\n\
+             - $.days: expected integer, found string\n\
+             Correct these fields and call the tool again."
+        );
+    }
+}
@@ -0,0 +1,651 @@
+use alloc::{string::String, sync::Arc};
+use core::{fmt, time::Duration};
+
+use async_stream::stream;
+use futures_core::Stream;
+use futures_lite::{StreamExt, pin};
+use spin::Mutex;
+
+use crate::{
+    LanguageModel,
+    error::{Classify, ErrorKind, RawError},
+    llm::{
+        Message, Request, TextStream,
+        event::StreamEvent,
+        model::{Pricing, Profile},
+        stream::text_stream,
+        token::{Estimator, TokenCounter},
+        usage::Usage,
+    },
+    rate_limit::RateLimitInfo,
+};
+
+type Reporter = dyn Fn(&SpendReport) + Send + Sync;
+
+/// A language model enforcing a spending limit.
+///
+/// Before each request, its cost is estimated from the model's [`Pricing`]: the prompt,
+/// counted with [`Estimator`], plus `max_tokens` of completion if set. If the spend so far
+/// plus the estimate exceeds the limit, the request is rejected with
+/// [`BudgetError::Exceeded`], or sent to the [downgrade](Self::with_downgrade) model if
+/// there is one. Downgraded requests are not limited, so the downgrade should be cheap or
+/// free, such as a local model.
+///
+/// When a response ends, its actual cost is added to the spend: from the
+/// [`Usage`](StreamEvent::Usage) the provider reported, or else from the estimated tokens
+/// of the prompt and the streamed text. Each charge is reported to the
+/// [reporter](Self::with_reporter). Models without pricing cost nothing.
+///
+/// Responses are charged when they end, or when the caller drops them early, since the
+/// provider bills the tokens generated so far. Requests failing before any output, such
+/// as on an authentication error, are not charged. Until it is charged, an admitted
+/// request reserves its estimated cost, so concurrent requests cannot all be admitted
+/// against the same spend; only responses costing more than estimated overshoot the
+/// limit. Start a new budget period with [`reset`](Self::reset).
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{LanguageModel, llm::Request, middleware::Budget};
+///
+/// async fn answer(model: impl LanguageModel, local: impl LanguageModel) -> ai_types::Result {
+///     let model = Budget::new(model, 5.0)
+///         .with_downgrade(local)
+///         .with_reporter(|report| {
+///             println!("{}: ${:.4}, ${:.2} spent", report.model, report.cost, report.spent);
+///         });
+///
+///     Ok(model.respond(Request::oneshot("Be brief", "Hello!")).await?)
+/// }
+/// ```
+pub struct Budget<M, D = M> {
+    model: M,
+    downgrade: Option<D>,
+    limit: f64,
+    ledger: Mutex<Ledger>,
+    reporter: Option<Arc<Reporter>>,
+}
+
+/// The spend of a [`Budget`], and the estimates reserved by requests in flight.
+#[derive(Debug)]
+struct Ledger {
+    spent: f64,
+    reserved: f64,
+}
+
+impl<M: fmt::Debug, D: fmt::Debug> fmt::Debug for Budget<M, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Budget")
+            .field("model", &self.model)
+            .field("downgrade", &self.downgrade)
+            .field("limit", &self.limit)
+            .field("ledger", &*self.ledger.lock())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M: LanguageModel> Budget<M> {
+    /// Wraps `model`, limiting its spend to `limit` USD.
+    #[must_use]
+    pub const fn new(model: M, limit: f64) -> Self {
+        Self {
+            model,
+            downgrade: None,
+            limit,
+            ledger: Mutex::new(Ledger {
+                spent: 0.0,
+                reserved: 0.0,
+            }),
+            reporter: None,
+        }
+    }
+}
+
+impl<M: LanguageModel, D: LanguageModel> Budget<M, D> {
+    /// Sends requests exceeding the budget to `model` instead of rejecting them.
+    #[must_use]
+    pub fn with_downgrade<N: LanguageModel>(self, model: N) -> Budget<M, N> {
+        Budget {
+            model: self.model,
+            downgrade: Some(model),
+            limit: self.limit,
+            ledger: self.ledger,
+            reporter: self.reporter,
+        }
+    }
+
+    /// Calls `reporter` with a [`SpendReport`] every time a response is charged.
+    #[must_use]
+    pub fn with_reporter(
+        mut self,
+        reporter: impl Fn(&SpendReport) + Send + Sync + 'static,
+    ) -> Self {
+        self.reporter = Some(Arc::new(reporter));
+        self
+    }
+
+    /// Returns the wrapped model.
+    #[must_use]
+    pub const fn model(&self) -> &M {
+        &self.model
+    }
+
+    /// Returns the spending limit, in USD.
+    #[must_use]
+    pub const fn limit(&self) -> f64 {
+        self.limit
+    }
+
+    /// Returns the spend so far, in USD.
+    #[must_use]
+    pub fn spent(&self) -> f64 {
+        self.ledger.lock().spent
+    }
+
+    /// Returns the budget left, in USD, or zero once it is exceeded.
+    #[must_use]
+    pub fn remaining(&self) -> f64 {
+        (self.limit - self.spent()).max(0.0)
+    }
+
+    /// Resets the spend to zero, starting a new budget period.
+    ///
+    /// Requests in flight keep their reservations until they are charged.
+    pub fn reset(&self) {
+        self.ledger.lock().spent = 0.0;
+    }
+
+    /// Estimates the cost of `request` on the wrapped model, in USD.
+    #[must_use]
+    pub fn estimate(&self, request: &Request) -> f64 {
+        let usage = Usage::new(
+            prompt_tokens(&request.messages),
+            request.parameters.max_tokens.unwrap_or(0),
+        );
+        cost(self.model.profile().pricing.as_ref(), usage)
+    }
+
+    /// Reserves `estimate` for a request if it fits in the budget.
+    fn admit(&self, estimate: f64) -> Result<(), BudgetExceeded> {
+        let mut ledger = self.ledger.lock();
+        let committed = ledger.spent + ledger.reserved;
+        if committed + estimate > self.limit {
+            return Err(BudgetExceeded {
+                limit: self.limit,
+                spent: committed,
+                estimate,
+            });
+        }
+        ledger.reserved += estimate;
+        Ok(())
+    }
+
+    /// Forwards `items`, charging their cost once they end or are dropped.
+    fn metered<I, E, S>(
+        &self,
+        profile: Profile,
+        meter: Meter,
+        reserved: f64,
+        downgraded: bool,
+        items: S,
+        observe: impl Fn(&mut Meter, &I) + Send,
+    ) -> impl Stream<Item = Result<I, E>> + Send
+    where
+        I: Send,
+        E: Send,
+        S: Stream<Item = Result<I, E>> + Send,
+    {
+        stream! {
+            let mut charge = Charge {
+                budget: self,
+                profile,
+                meter,
+                reserved,
+                downgraded,
+            };
+            pin!(items);
+            while let Some(item) = items.next().await {
+                match item {
+                    Ok(item) => {
+                        charge.meter.streamed = true;
+                        observe(&mut charge.meter, &item);
+                        yield Ok(item);
+                    }
+                    Err(error) => {
+                        yield Err(error);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sends a request to the wrapped model if it fits in the budget, or else to the
+    /// downgrade model.
+    fn budgeted<'a, I, P, Q>(
+        &'a self,
+        prompt_tokens: u32,
+        max_tokens: Option<u32>,
+        primary: impl FnOnce(&'a M) -> P + Send,
+        downgrade: impl FnOnce(&'a D) -> Q + Send,
+        observe: fn(&mut Meter, &I),
+    ) -> impl Stream<Item = Result<I, BudgetError<M::Error, D::Error>>> + Send
+    where
+        I: Send,
+        P: Stream<Item = Result<I, M::Error>> + Send,
+        Q: Stream<Item = Result<I, D::Error>> + Send,
+    {
+        let meter = Meter::new(prompt_tokens);
+        let profile = self.model.profile();
+        let estimate = cost(
+            profile.pricing.as_ref(),
+            Usage::new(meter.prompt_tokens, max_tokens.unwrap_or(0)),
+        );
+        stream! {
+            // The reservation is released by the charge of `metered`, which takes it over
+            // before yielding.
+            match (self.admit(estimate), &self.downgrade) {
+                (Ok(()), _) => {
+                    let items = self.metered(
+                        profile,
+                        meter,
+                        estimate,
+                        false,
+                        primary(&self.model),
+                        observe,
+                    );
+                    pin!(items);
+                    while let Some(item) = items.next().await {
+                        yield item.map_err(BudgetError::Model);
+                    }
+                }
+                (Err(_), Some(model)) => {
+                    let profile = model.profile();
+                    let items = self.metered(profile, meter, 0.0, true, downgrade(model), observe);
+                    pin!(items);
+                    while let Some(item) = items.next().await {
+                        yield item.map_err(BudgetError::Downgrade);
+                    }
+                }
+                (Err(exceeded), None) => yield Err(BudgetError::Exceeded(exceeded)),
+            }
+        }
+    }
+}
+
+impl<M: LanguageModel, D: LanguageModel> LanguageModel for Budget<M, D> {
+    type Error = BudgetError<M::Error, D::Error>;
+
+    fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+        let prompt_tokens = prompt_tokens(&request.messages);
+        let max_tokens = request.parameters.max_tokens;
+        let downgraded = request.clone();
+        text_stream(self.budgeted(
+            prompt_tokens,
+            max_tokens,
+            move |model| model.respond(request),
+            move |model| model.respond(downgraded),
+            |meter, chunk: &String| meter.text(chunk),
+        ))
+    }
+
+    fn respond_events(
+        &self,
+        request: Request,
+    ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+        let prompt_tokens = prompt_tokens(&request.messages);
+        let max_tokens = request.parameters.max_tokens;
+        let downgraded = request.clone();
+        self.budgeted(
+            prompt_tokens,
+            max_tokens,
+            move |model| model.respond_events(request),
+            move |model| model.respond_events(downgraded),
+            |meter, event| match event {
+                StreamEvent::Text(chunk) => meter.text(chunk),
+                StreamEvent::Usage(usage) => meter.reported = Some(*usage),
+                _ => {}
+            },
+        )
+    }
+
+    fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+        text_stream(self.budgeted(
+            prompt_tokens(&[Message::user(prefix)]),
+            None,
+            move |model| model.complete(prefix),
+            move |model| model.complete(prefix),
+            |meter, chunk: &String| meter.text(chunk),
+        ))
+    }
+
+    fn profile(&self) -> Profile {
+        self.model.profile()
+    }
+}
+
+impl<M, D> Budget<M, D> {
+    /// Adds the cost of a response to the spend in place of its `reserved` estimate, and
+    /// reports it. Responses failing before any output cost nothing.
+    fn charge(&self, profile: &Profile, meter: &Meter, reserved: f64, downgraded: bool) {
+        if !meter.streamed && meter.reported.is_none() {
+            let mut ledger = self.ledger.lock();
+            ledger.reserved = (ledger.reserved - reserved).max(0.0);
+            return;
+        }
+        let estimated = meter.reported.is_none();
+        let usage = meter
+            .reported
+            .unwrap_or_else(|| Usage::new(meter.prompt_tokens, meter.completion_tokens));
+        let cost = cost(profile.pricing.as_ref(), usage);
+        let spent = {
+            let mut ledger = self.ledger.lock();
+            ledger.reserved = (ledger.reserved - reserved).max(0.0);
+            ledger.spent += cost;
+            ledger.spent
+        };
+        if let Some(reporter) = &self.reporter {
+            reporter(&SpendReport {
+                model: profile.name.clone(),
+                usage,
+                estimated,
+                cost,
+                spent,
+                limit: self.limit,
+                downgraded,
+            });
+        }
+    }
+}
+
+/// Charges a response when dropped, whether it ended or was abandoned by the caller.
+struct Charge<'a, M, D> {
+    budget: &'a Budget<M, D>,
+    profile: Profile,
+    meter: Meter,
+    reserved: f64,
+    downgraded: bool,
+}
+
+impl<M, D> Drop for Charge<'_, M, D> {
+    fn drop(&mut self) {
+        self.budget
+            .charge(&self.profile, &self.meter, self.reserved, self.downgraded);
+    }
+}
+
+/// Tokens of a response, counted while it streams.
+#[derive(Debug)]
+struct Meter {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+    reported: Option<Usage>,
+    /// Whether the response produced any item.
+    streamed: bool,
+}
+
+impl Meter {
+    const fn new(prompt_tokens: u32) -> Self {
+        Self {
+            prompt_tokens,
+            completion_tokens: 0,
+            reported: None,
+            streamed: false,
+        }
+    }
+
+    fn text(&mut self, chunk: &str) {
+        let tokens = u32::try_from(Estimator::new().count_tokens(chunk)).unwrap_or(u32::MAX);
+        self.completion_tokens = self.completion_tokens.saturating_add(tokens);
+    }
+}
+
+fn prompt_tokens(messages: &[Message]) -> u32 {
+    u32::try_from(Estimator::new().count_message_tokens(messages)).unwrap_or(u32::MAX)
+}
+
+/// Returns the cost of `usage`, plus the price per request.
+fn cost(pricing: Option<&Pricing>, usage: Usage) -> f64 {
+    pricing.map_or(0.0, |pricing| usage.cost(pricing) + pricing.request)
+}
+
+/// The cost of a response charged by a [`Budget`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct SpendReport {
+    /// The name of the model that responded.
+    pub model: String,
+    /// The tokens charged.
+    pub usage: Usage,
+    /// Whether the usage was estimated, because the model did not report it.
+    pub estimated: bool,
+    /// The cost of the response, in USD.
+    pub cost: f64,
+    /// The spend so far, including this response, in USD.
+    pub spent: f64,
+    /// The spending limit, in USD.
+    pub limit: f64,
+    /// Whether the request was sent to the downgrade model.
+    pub downgraded: bool,
+}
+
+/// The error of a request rejected by a [`Budget`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct BudgetExceeded {
+    /// The spending limit, in USD.
+    pub limit: f64,
+    /// The spend when the request was rejected, including the estimates reserved by
+    /// requests in flight, in USD.
+    pub spent: f64,
+    /// The estimated cost of the request, in USD.
+    pub estimate: f64,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Budget of ${} exceeded: ${} spent, request estimated at ${}",
+            self.limit, self.spent, self.estimate
+        )
+    }
+}
+
+impl core::error::Error for BudgetExceeded {}
+
+/// An error returned by [`Budget`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum BudgetError<E, DE> {
+    /// The request would exceed the budget, and there is no downgrade model.
+    Exceeded(BudgetExceeded),
+    /// The wrapped model failed.
+    Model(E),
+    /// The downgrade model failed.
+    Downgrade(DE),
+}
+
+impl<E: fmt::Display, DE: fmt::Display> fmt::Display for BudgetError<E, DE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exceeded(exceeded) => exceeded.fmt(f),
+            Self::Model(error) => error.fmt(f),
+            Self::Downgrade(error) => write!(f, "Downgrade model failed: {error}"),
+        }
+    }
+}
+
+impl<E, DE> core::error::Error for BudgetError<E, DE>
+where
+    E: core::error::Error + 'static,
+    DE: core::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Exceeded(exceeded) => Some(exceeded),
+            Self::Model(error) => Some(error),
+            Self::Downgrade(error) => Some(error),
+        }
+    }
+}
+
+impl<E: Classify, DE: Classify> Classify for BudgetError<E, DE> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Exceeded(_) => ErrorKind::Other,
+            Self::Model(error) => error.kind(),
+            Self::Downgrade(error) => error.kind(),
+        }
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Exceeded(_) => None,
+            Self::Model(error) => error.retry_after(),
+            Self::Downgrade(error) => error.retry_after(),
+        }
+    }
+
+    fn rate_limit(&self) -> Option<RateLimitInfo> {
+        match self {
+            Self::Exceeded(_) => None,
+            Self::Model(error) => error.rate_limit(),
+            Self::Downgrade(error) => error.rate_limit(),
+        }
+    }
+
+    fn raw(&self) -> Option<&RawError> {
+        match self {
+            Self::Exceeded(_) => None,
+            Self::Model(error) => error.raw(),
+            Self::Downgrade(error) => error.raw(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::ProviderError,
+        test_util::{MockLanguageModel, MockReply},
+    };
+    use alloc::vec::Vec;
+
+    /// Prices prompt and completion tokens at one dollar each.
+    fn priced() -> Profile {
+        Profile::new("mock", "", 1024).with_pricing(Pricing {
+            prompt: 1.0,
+            completion: 1.0,
+            ..Pricing::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_over_budget() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let model = MockLanguageModel::new()
+            .with_profile(priced())
+            .with_reply(MockReply::text("four").with_usage(Usage::new(10, 5)));
+        let model =
+            Budget::new(model, 20.0).with_reporter(move |report| sink.lock().push(report.clone()));
+
+        // 4 + 1 tokens of prompt, 1 of completion.
+        let request = Request::new([Message::user("Hi")]);
+        assert!((model.estimate(&request) - 5.0).abs() < f64::EPSILON);
+        assert_eq!(model.respond(request.clone()).await.unwrap(), "four");
+        assert!((model.spent() - 6.0).abs() < f64::EPSILON);
+
+        let events: Vec<_> = model.respond_events(request.clone()).collect().await;
+        assert!(events.iter().all(Result::is_ok));
+        assert!((model.spent() - 21.0).abs() < f64::EPSILON);
+        assert!(model.remaining() < f64::EPSILON);
+
+        let error = model.respond(request.clone()).await.unwrap_err();
+        assert!(matches!(error, BudgetError::Exceeded(e) if (e.spent - 21.0).abs() < f64::EPSILON));
+
+        let reports = reports.lock();
+        assert_eq!(reports.len(), 2);
+        assert!(reports[0].estimated);
+        assert_eq!(reports[1].usage, Usage::new(10, 5));
+        assert!(!reports[1].estimated);
+        drop(reports);
+
+        model.reset();
+        assert_eq!(model.respond(request).await.unwrap(), "four");
+    }
+
+    #[tokio::test]
+    async fn charges_responses_dropped_early() {
+        let model = MockLanguageModel::new()
+            .with_profile(priced())
+            .with_reply(MockReply::text("four").with_usage(Usage::new(10, 5)));
+        let model = Budget::new(model, 100.0);
+
+        let mut events = model
+            .respond_events(Request::new([Message::user("Hi")]))
+            .boxed();
+        assert!(matches!(
+            events.next().await,
+            Some(Ok(StreamEvent::Text(_)))
+        ));
+        assert!(matches!(
+            events.next().await,
+            Some(Ok(StreamEvent::Usage(_)))
+        ));
+        assert!(model.spent() < f64::EPSILON);
+        drop(events);
+        assert!((model.spent() - 15.0).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn reserves_estimates_of_requests_in_flight() {
+        let model = MockLanguageModel::new()
+            .with_profile(priced())
+            .with_reply("four");
+        // Each request is estimated at 5: 4 + 1 tokens of prompt, 1 of completion.
+        let model = Budget::new(model, 7.0);
+        let request = || Request::new([Message::user("Hi")]);
+
+        let mut first = model.respond(request()).boxed();
+        assert_eq!(first.next().await.unwrap().unwrap(), "four");
+        let error = model.respond(request()).await.unwrap_err();
+        assert!(matches!(error, BudgetError::Exceeded(e) if (e.spent - 5.0).abs() < f64::EPSILON));
+
+        drop(first);
+        assert!((model.spent() - 6.0).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn does_not_charge_requests_failing_before_output() {
+        let failure = ProviderError::new(ErrorKind::Authentication, "bad key");
+        let model = MockLanguageModel::new()
+            .with_profile(priced())
+            .with_replies([MockReply::error(failure), "four".into()]);
+        let model = Budget::new(model, 7.0);
+        let request = || Request::new([Message::user("Hi")]);
+
+        assert!(model.respond(request()).await.is_err());
+        assert!(model.spent() < f64::EPSILON);
+        // The failed request's reservation was released.
+        assert_eq!(model.respond(request()).await.unwrap(), "four");
+    }
+
+    #[tokio::test]
+    async fn downgrades_requests_over_budget() {
+        let primary = MockLanguageModel::new()
+            .with_profile(priced())
+            .with_reply("primary");
+        let local = MockLanguageModel::new()
+            .with_profile(priced())
+            .with_reply("local");
+        let model = Budget::new(primary, 7.0).with_downgrade(local);
+
+        let request = Request::new([Message::user("Hi")]);
+        assert_eq!(model.respond(request.clone()).await.unwrap(), "primary");
+        assert_eq!(model.respond(request.clone()).await.unwrap(), "local");
+        assert_eq!(model.complete("Once").await.unwrap(), "local");
+    }
+}
@@ -0,0 +1,426 @@
+use alloc::{collections::BTreeMap, format, string::String, sync::Arc, vec::Vec};
+use core::{future::Future, hash::Hasher};
+
+use async_stream::stream;
+use futures_core::Stream;
+use futures_lite::{StreamExt, pin};
+use spin::Mutex;
+
+use crate::{
+    LanguageModel,
+    hash::Fnv1a,
+    llm::{Request, TextStream, event::StreamEvent, model::Profile, stream::text_stream},
+};
+
+/// A language model serving repeated requests from a cache.
+///
+/// Requests are identified by a [`CacheKey`] made of their messages, parameters and tools.
+/// Only complete, successful responses are cached; a failed response is never stored.
+///
+/// Caching pays off for deterministic workloads, e.g. with a temperature of zero. For
+/// sampled responses, a cache hit replays one of the possible responses.
+///
+/// [`respond_events`](LanguageModel::respond_events) is not cached, because tool calls
+/// must reach the caller every time, and neither is [`respond_n`](LanguageModel::respond_n),
+/// whose candidates are meant to differ.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{LanguageModel, llm::Request, middleware::Cached};
+///
+/// async fn answer(model: impl LanguageModel) -> ai_types::Result {
+///     let model = Cached::new(model, 1000);
+///
+///     let first = model.respond(Request::oneshot("Be brief", "What is 2 + 2?")).await?;
+///     // Served from the cache, without calling the model.
+///     let second = model.respond(Request::oneshot("Be brief", "What is 2 + 2?")).await?;
+///     assert_eq!(first, second);
+///     Ok(first)
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Cached<M, C = LruCache> {
+    model: M,
+    cache: C,
+}
+
+impl<M: LanguageModel> Cached<M> {
+    /// Wraps `model` with an in-memory [`LruCache`] holding up to `capacity` responses.
+    #[must_use]
+    pub fn new(model: M, capacity: usize) -> Self {
+        Self::with_cache(model, LruCache::new(capacity))
+    }
+}
+
+impl<M: LanguageModel, C: Cache> Cached<M, C> {
+    /// Wraps `model` with a custom cache backend.
+    #[must_use]
+    pub const fn with_cache(model: M, cache: C) -> Self {
+        Self { model, cache }
+    }
+
+    /// Returns the wrapped model.
+    #[must_use]
+    pub const fn model(&self) -> &M {
+        &self.model
+    }
+
+    /// Returns the cache backend.
+    #[must_use]
+    pub const fn cache(&self) -> &C {
+        &self.cache
+    }
+
+    fn cached<S>(
+        &self,
+        key: CacheKey,
+        response: impl FnOnce() -> S + Send,
+    ) -> impl TextStream<Error = M::Error> + Send
+    where
+        S: Stream<Item = Result<String, M::Error>> + Send,
+    {
+        text_stream(stream! {
+            if let Some(hit) = self.cache.get(key.clone()).await {
+                yield Ok(hit);
+                return;
+            }
+
+            let chunks = response();
+            pin!(chunks);
+            let mut text = String::new();
+            while let Some(chunk) = chunks.next().await {
+                match chunk {
+                    Ok(chunk) => {
+                        text.push_str(&chunk);
+                        yield Ok(chunk);
+                    }
+                    Err(error) => {
+                        yield Err(error);
+                        return;
+                    }
+                }
+            }
+            self.cache.insert(key, text).await;
+        })
+    }
+}
+
+impl<M: LanguageModel, C: Cache + 'static> LanguageModel for Cached<M, C> {
+    type Error = M::Error;
+
+    fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+        let key = CacheKey::from_request(&request);
+        self.cached(key, move || self.model.respond(request))
+    }
+
+    fn respond_events(
+        &self,
+        request: Request,
+    ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+        self.model.respond_events(request)
+    }
+
+    fn respond_n(&self, request: Request) -> Vec<impl TextStream<Error = Self::Error> + Send> {
+        self.model.respond_n(request)
+    }
+
+    fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+        let key = CacheKey::from_prefix(prefix);
+        self.cached(key, move || self.model.complete(prefix))
+    }
+
+    fn profile(&self) -> Profile {
+        self.model.profile()
+    }
+}
+
+/// Identifies a request in a [`Cache`].
+///
+/// The request's messages, parameters, tool definitions and response format, serialized,
+/// along with a 64-bit FNV-1a hash of them. Keys compare by their full text, so different
+/// requests never share a key even if their hashes collide. Keys are stable within a build of this
+/// crate, but may change between versions; persistent caches should be invalidated on
+/// upgrade.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CacheKey {
+    hash: u64,
+    text: Arc<str>,
+}
+
+impl CacheKey {
+    /// Computes the key of a chat request.
+    ///
+    /// The idempotency key is not part of the key.
+    #[must_use]
+    pub fn from_request(request: &Request) -> Self {
+        Self::new(&format!(
+            "respond\0{:?}\0{:?}\0{}\0{:?}",
+            request.messages,
+            request.parameters,
+            request.tools.canonical_json(),
+            request.response_format
+        ))
+    }
+
+    /// Computes the key of a completion request.
+    #[must_use]
+    pub fn from_prefix(prefix: &str) -> Self {
+        Self::new(&format!("complete\0{prefix}"))
+    }
+
+    fn new(text: &str) -> Self {
+        let mut hasher = Fnv1a::new();
+        hasher.write(text.as_bytes());
+        Self {
+            hash: hasher.finish(),
+            text: text.into(),
+        }
+    }
+
+    /// Returns the hash of the key, e.g. to index an external store.
+    ///
+    /// Different keys may share a hash, so a store indexed by it must compare
+    /// [`as_str`](Self::as_str) on a hit.
+    #[must_use]
+    pub const fn as_u64(&self) -> u64 {
+        self.hash
+    }
+
+    /// Returns the full text of the key.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+/// A storage backend for [`Cached`].
+///
+/// Implement it to share responses through an external store such as Redis. Storage
+/// failures should be treated as cache misses, and so should entries stored under a
+/// different key with the same [hash](CacheKey::as_u64).
+pub trait Cache: Send + Sync {
+    /// Returns the response cached under `key`.
+    fn get(&self, key: CacheKey) -> impl Future<Output = Option<String>> + Send;
+
+    /// Stores `response` under `key`.
+    fn insert(&self, key: CacheKey, response: String) -> impl Future<Output = ()> + Send;
+}
+
+/// An in-memory cache evicting the least recently used response.
+#[derive(Debug)]
+pub struct LruCache {
+    capacity: usize,
+    state: Mutex<LruState>,
+}
+
+#[derive(Debug, Default)]
+struct LruState {
+    entries: BTreeMap<CacheKey, (String, u64)>,
+    recency: BTreeMap<u64, CacheKey>,
+    tick: u64,
+}
+
+impl LruState {
+    fn touch(&mut self, key: &CacheKey) -> Option<&mut String> {
+        let tick = self.tick;
+        let (response, used) = self.entries.get_mut(key)?;
+        self.recency.remove(used);
+        self.recency.insert(tick, key.clone());
+        *used = tick;
+        self.tick += 1;
+        Some(response)
+    }
+}
+
+impl LruCache {
+    /// Creates a cache holding up to `capacity` responses.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(LruState::default()),
+        }
+    }
+
+    /// Returns the capacity of the cache.
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of cached responses.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.state.lock().entries.len()
+    }
+
+    /// Returns whether the cache is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes every cached response.
+    pub fn clear(&self) {
+        *self.state.lock() = LruState::default();
+    }
+}
+
+impl Cache for LruCache {
+    fn get(&self, key: CacheKey) -> impl Future<Output = Option<String>> + Send {
+        let hit = self.state.lock().touch(&key).cloned();
+        core::future::ready(hit)
+    }
+
+    fn insert(&self, key: CacheKey, response: String) -> impl Future<Output = ()> + Send {
+        if self.capacity > 0 {
+            let mut state = self.state.lock();
+            if let Some(cached) = state.touch(&key) {
+                *cached = response;
+            } else {
+                if state.entries.len() >= self.capacity {
+                    if let Some((_, oldest)) = state.recency.pop_first() {
+                        state.entries.remove(&oldest);
+                    }
+                }
+                let tick = state.tick;
+                state.recency.insert(tick, key.clone());
+                state.tick += 1;
+                state.entries.insert(key, (response, tick));
+            }
+        }
+        core::future::ready(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::{ErrorKind, ProviderError},
+        llm::{
+            Message,
+            model::{Ability, Parameters},
+        },
+        test_util::{MockLanguageModel, MockReply},
+    };
+    use alloc::string::ToString;
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+
+    /// Answers the `n`th request with "call n", in two chunks.
+    fn counting() -> MockLanguageModel {
+        MockLanguageModel::new().with_replies(
+            (0..5).map(|call| MockReply::chunks(["call ".to_string(), call.to_string()])),
+        )
+    }
+
+    #[tokio::test]
+    async fn serves_repeated_requests_from_cache() {
+        let model = Cached::new(counting(), 10);
+        let request = || Request::new([Message::user("Hi")]);
+
+        assert_eq!(model.respond(request()).await.unwrap(), "call 0");
+        assert_eq!(model.respond(request()).await.unwrap(), "call 0");
+        assert_eq!(model.model().requests().len(), 1);
+
+        let other = request().with_parameters(Parameters::default().temperature(0.0));
+        assert_eq!(model.respond(other).await.unwrap(), "call 1");
+        assert_eq!(model.complete("Hi").await.unwrap(), "call 2");
+        assert_eq!(model.cache().len(), 3);
+
+        let candidates = request().with_parameters(Parameters::default().n(2));
+        let mut answers = Vec::new();
+        for candidate in model.respond_n(candidates) {
+            answers.push(candidate.await.unwrap());
+        }
+        assert_eq!(answers, ["call 3", "call 4"]);
+    }
+
+    #[tokio::test]
+    async fn does_not_cache_failures() {
+        let flaky = MockReply::text("partial")
+            .then_fail(ProviderError::new(ErrorKind::ProviderUnavailable, "flaky"));
+        let model = Cached::new(MockLanguageModel::new().with_reply(flaky), 10);
+        let request = || Request::new([Message::user("Hi")]);
+
+        assert!(model.respond(request()).await.is_err());
+        assert!(model.respond(request()).await.is_err());
+        assert_eq!(model.model().requests().len(), 2);
+        assert!(model.cache().is_empty());
+    }
+
+    #[tokio::test]
+    async fn evicts_least_recently_used() {
+        let cache = LruCache::new(2);
+        let key = |n: &str| CacheKey::from_prefix(n);
+
+        cache.insert(key("a"), "A".into()).await;
+        cache.insert(key("b"), "B".into()).await;
+        assert_eq!(cache.get(key("a")).await.as_deref(), Some("A"));
+        cache.insert(key("c"), "C".into()).await;
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(key("b")).await, None);
+        assert_eq!(cache.get(key("a")).await.as_deref(), Some("A"));
+        assert_eq!(cache.get(key("c")).await.as_deref(), Some("C"));
+    }
+
+    struct Echo;
+
+    impl crate::llm::Tool for Echo {
+        const NAME: &str = "echo";
+        const DESCRIPTION: &str = "Echoes its argument";
+        type Arguments = String;
+
+        async fn call(&mut self, args: Self::Arguments) -> crate::Result {
+            Ok(args)
+        }
+    }
+
+    #[tokio::test]
+    async fn keys_compare_by_full_text() {
+        let request = || Request::new([Message::user("Hi")]);
+        let mut with_tools = request();
+        with_tools.tools.register(Echo);
+        assert_ne!(
+            CacheKey::from_request(&request()),
+            CacheKey::from_request(&with_tools)
+        );
+
+        let cache = LruCache::new(2);
+        let colliding = |text: &str| CacheKey {
+            hash: 7,
+            text: text.into(),
+        };
+        cache.insert(colliding("a"), "A".into()).await;
+        assert_eq!(cache.get(colliding("b")).await, None);
+        assert_eq!(cache.get(colliding("a")).await.as_deref(), Some("A"));
+    }
+
+    #[derive(Debug, PartialEq, JsonSchema, Deserialize)]
+    struct City {
+        city: String,
+    }
+
+    #[derive(Debug, PartialEq, JsonSchema, Deserialize)]
+    struct Country {
+        country: String,
+    }
+
+    #[tokio::test]
+    async fn keys_include_the_response_format() {
+        let model = MockLanguageModel::new()
+            .with_profile(Profile::new("mock", "", 4096).with_ability(Ability::StructuredOutput))
+            .with_replies([r#"{"city": "Paris"}"#, r#"{"country": "France"}"#]);
+        let model = Cached::new(model, 10);
+        let request = || Request::new([Message::user("Where is the Louvre?")]);
+
+        let city: City = model.generate(request()).await.unwrap();
+        assert_eq!(city.city, "Paris");
+        let country: Country = model.generate(request()).await.unwrap();
+        assert_eq!(country.country, "France");
+        assert_eq!(model.cache().len(), 2);
+    }
+}
@@ -0,0 +1,217 @@
+use alloc::{string::String, vec::Vec};
+use core::future::Future;
+
+use futures_core::Stream;
+
+use crate::{
+    LanguageModel,
+    llm::{
+        LanguageModelProvider, Message, Request, Role, TextStream,
+        event::StreamEvent,
+        model::{Parameters, Profile},
+        provider::Profile as ProviderProfile,
+    },
+};
+
+/// Application-wide defaults applied to every request.
+///
+/// Parameters set on a request take precedence over the defaults, so individual requests
+/// can still override them. The system prefix is put before the request's system
+/// message, or becomes the system message if the request has none.
+///
+/// Attach defaults to a model, or to a provider so every model it returns shares them,
+/// with [`Defaulted`].
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct Defaults {
+    /// Parameters used when a request leaves them unset.
+    pub parameters: Parameters,
+    /// Text put before the system prompt of every request.
+    pub system_prefix: Option<String>,
+}
+
+impl Defaults {
+    /// Creates empty defaults, which leave requests unchanged.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the default parameters.
+    #[must_use]
+    pub fn with_parameters(mut self, parameters: Parameters) -> Self {
+        self.parameters = parameters;
+        self
+    }
+
+    /// Sets the default sampling temperature.
+    #[must_use]
+    pub const fn with_temperature(mut self, temperature: f32) -> Self {
+        self.parameters.temperature = Some(temperature);
+        self
+    }
+
+    /// Sets the default maximum number of tokens to generate.
+    #[must_use]
+    pub const fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.parameters.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Sets the text put before the system prompt of every request.
+    #[must_use]
+    pub fn with_system_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.system_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Applies the defaults to `request`.
+    #[must_use]
+    pub fn apply(&self, mut request: Request) -> Request {
+        request.parameters = request.parameters.or(self.parameters.clone());
+        if let Some(prefix) = &self.system_prefix {
+            let mut system = Message::system(prefix.clone());
+            if request
+                .messages
+                .first()
+                .is_some_and(|m| m.role() == Role::System)
+            {
+                system.merge(request.messages.remove(0));
+            }
+            request.messages.insert(0, system);
+        }
+        request
+    }
+}
+
+/// A model or provider applying [`Defaults`] to every request.
+///
+/// Wrapping a [`LanguageModelProvider`] wraps every model it returns with the same
+/// defaults.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{LanguageModel, llm::Request, middleware::{Defaulted, Defaults}};
+///
+/// async fn answer(model: impl LanguageModel) -> ai_types::Result {
+///     let defaults = Defaults::new()
+///         .with_temperature(0.2)
+///         .with_system_prefix("Answer in British English.");
+///     let model = Defaulted::new(model, defaults);
+///
+///     // Uses temperature 0.2, and the prefixed system prompt.
+///     let answer = model.respond(Request::oneshot("Be brief", "Hello!")).await?;
+///     Ok(answer)
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Defaulted<M> {
+    inner: M,
+    defaults: Defaults,
+}
+
+impl<M> Defaulted<M> {
+    /// Wraps `inner`, applying `defaults` to its requests.
+    #[must_use]
+    pub const fn new(inner: M, defaults: Defaults) -> Self {
+        Self { inner, defaults }
+    }
+
+    /// Returns the wrapped model or provider.
+    #[must_use]
+    pub const fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    /// Returns the applied defaults.
+    #[must_use]
+    pub const fn defaults(&self) -> &Defaults {
+        &self.defaults
+    }
+}
+
+impl<M: LanguageModel> LanguageModel for Defaulted<M> {
+    type Error = M::Error;
+
+    fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+        self.inner.respond(self.defaults.apply(request))
+    }
+
+    fn respond_events(
+        &self,
+        request: Request,
+    ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+        self.inner.respond_events(self.defaults.apply(request))
+    }
+
+    fn respond_n(&self, request: Request) -> Vec<impl TextStream<Error = Self::Error> + Send> {
+        self.inner.respond_n(self.defaults.apply(request))
+    }
+
+    fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+        self.inner.complete(prefix)
+    }
+
+    fn profile(&self) -> Profile {
+        self.inner.profile()
+    }
+}
+
+impl<P> LanguageModelProvider for Defaulted<P>
+where
+    P: LanguageModelProvider + Sync,
+    P::Model: Sync,
+{
+    type Model = Defaulted<P::Model>;
+
+    fn list_models(&self) -> impl Future<Output = Vec<String>> + Send {
+        self.inner.list_models()
+    }
+
+    async fn get_model(&self, name: &str) -> Self::Model {
+        Defaulted::new(self.inner.get_model(name).await, self.defaults.clone())
+    }
+
+    fn profile() -> ProviderProfile {
+        P::profile()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::MockLanguageModel;
+
+    #[tokio::test]
+    async fn requests_override_defaults() {
+        let defaults = Defaults::new()
+            .with_temperature(0.2)
+            .with_max_tokens(64)
+            .with_system_prefix("Be polite.");
+        let model = Defaulted::new(MockLanguageModel::new(), defaults);
+
+        model
+            .respond(Request::oneshot("Be brief.", "Hi"))
+            .await
+            .unwrap();
+        let request = Request::new([Message::user("Hi")])
+            .with_parameters(Parameters::default().temperature(0.9));
+        model.respond(request).await.unwrap();
+
+        let requests = model.inner().requests();
+        assert_eq!(requests[0].messages[0].content(), "Be polite.\n\nBe brief.");
+        assert_eq!(requests[0].parameters.temperature, Some(0.2));
+        assert_eq!(requests[0].parameters.max_tokens, Some(64));
+        assert_eq!(requests[1].messages[0].content(), "Be polite.");
+        assert_eq!(requests[1].parameters.temperature, Some(0.9));
+    }
+
+    #[test]
+    fn empty_defaults_leave_requests_unchanged() {
+        let request = Defaults::new().apply(Request::oneshot("System", "User"));
+        assert_eq!(request.messages.len(), 2);
+        assert_eq!(request.parameters, Parameters::default());
+        assert_eq!(request.messages[0].content(), "System");
+    }
+}
@@ -0,0 +1,473 @@
+use alloc::vec::Vec;
+use core::{fmt, pin::Pin, time::Duration};
+
+use async_stream::stream;
+use futures_core::Stream;
+use futures_lite::{StreamExt, future, pin};
+
+use crate::{
+    LanguageModel,
+    error::{Classify, ErrorKind, RawError},
+    llm::{Request, TextStream, event::StreamEvent, model::Profile, stream::text_stream},
+    rate_limit::RateLimitInfo,
+    time::{NoDelay, Timer},
+};
+
+/// A language model falling back to a secondary model when the primary one fails.
+///
+/// The primary model is tried first. If it fails, or does not produce its first chunk
+/// within the [timeout](Self::with_timeout), the request is sent to the secondary model
+/// instead. Once the primary model has started responding, a later failure is passed
+/// through: falling back would repeat the text already streamed. A primary response that
+/// ends without any chunk, such as an empty completion, is passed through as well.
+///
+/// Fallbacks nest, but for more than two models of the same type, [`ModelChain`] is
+/// simpler.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{LanguageModel, llm::Request, middleware::Fallback};
+/// use core::time::Duration;
+///
+/// async fn answer(primary: impl LanguageModel, local: impl LanguageModel) -> ai_types::Result {
+///     let model = Fallback::new(primary, local)
+///         .with_timeout(Duration::from_secs(10), tokio::time::sleep);
+///
+///     Ok(model.respond(Request::oneshot("Be brief", "Hello!")).await?)
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Fallback<A, B, T = NoDelay> {
+    primary: A,
+    secondary: B,
+    timeout: Option<Duration>,
+    timer: T,
+}
+
+impl<A: LanguageModel, B: LanguageModel> Fallback<A, B> {
+    /// Creates a model trying `primary` first and `secondary` if it fails.
+    #[must_use]
+    pub const fn new(primary: A, secondary: B) -> Self {
+        Self {
+            primary,
+            secondary,
+            timeout: None,
+            timer: NoDelay,
+        }
+    }
+}
+
+impl<A: LanguageModel, B: LanguageModel, T: Timer> Fallback<A, B, T> {
+    /// Falls back when the primary model takes longer than `timeout` to produce its first
+    /// chunk, measured with `timer`.
+    #[must_use]
+    pub fn with_timeout<U: Timer>(self, timeout: Duration, timer: U) -> Fallback<A, B, U> {
+        Fallback {
+            primary: self.primary,
+            secondary: self.secondary,
+            timeout: Some(timeout),
+            timer,
+        }
+    }
+
+    /// Returns the primary model.
+    #[must_use]
+    pub const fn primary(&self) -> &A {
+        &self.primary
+    }
+
+    /// Returns the secondary model.
+    #[must_use]
+    pub const fn secondary(&self) -> &B {
+        &self.secondary
+    }
+
+    fn falling_back<I, P, S>(
+        &self,
+        primary: impl FnOnce() -> P + Send,
+        secondary: impl FnOnce() -> S + Send,
+    ) -> impl Stream<Item = Result<I, FallbackError<A::Error, B::Error>>> + Send
+    where
+        I: Send,
+        P: Stream<Item = Result<I, A::Error>> + Send,
+        S: Stream<Item = Result<I, B::Error>> + Send,
+    {
+        stream! {
+            let items = primary();
+            pin!(items);
+            match first_item(items.as_mut(), &self.timer, self.timeout).await {
+                First::Item(Ok(first)) => {
+                    yield Ok(first);
+                    while let Some(item) = items.next().await {
+                        yield item.map_err(FallbackError::Primary);
+                    }
+                    return;
+                }
+                // An empty response is still a response, which must not be sent twice.
+                First::Ended => return,
+                First::Item(Err(_)) | First::TimedOut => {}
+            }
+
+            let items = secondary();
+            pin!(items);
+            while let Some(item) = items.next().await {
+                yield item.map_err(FallbackError::Secondary);
+            }
+        }
+    }
+}
+
+impl<A, B, T> LanguageModel for Fallback<A, B, T>
+where
+    A: LanguageModel,
+    B: LanguageModel,
+    T: Timer + 'static,
+{
+    type Error = FallbackError<A::Error, B::Error>;
+
+    fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+        let secondary = request.clone();
+        text_stream(self.falling_back(
+            move || self.primary.respond(request),
+            move || self.secondary.respond(secondary),
+        ))
+    }
+
+    fn respond_events(
+        &self,
+        request: Request,
+    ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+        let secondary = request.clone();
+        self.falling_back(
+            move || self.primary.respond_events(request),
+            move || self.secondary.respond_events(secondary),
+        )
+    }
+
+    fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+        text_stream(self.falling_back(
+            move || self.primary.complete(prefix),
+            move || self.secondary.complete(prefix),
+        ))
+    }
+
+    /// Returns the profile of the primary model.
+    fn profile(&self) -> Profile {
+        self.primary.profile()
+    }
+}
+
+/// An error returned by [`Fallback`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FallbackError<P, S> {
+    /// The primary model failed after it started responding, so it could not fall back.
+    Primary(P),
+    /// The secondary model failed after the primary model failed or timed out.
+    Secondary(S),
+}
+
+impl<P: fmt::Display, S: fmt::Display> fmt::Display for FallbackError<P, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Primary(error) => write!(f, "Primary model failed: {error}"),
+            Self::Secondary(error) => write!(f, "Fallback model failed: {error}"),
+        }
+    }
+}
+
+impl<P, S> core::error::Error for FallbackError<P, S>
+where
+    P: core::error::Error + 'static,
+    S: core::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Primary(error) => Some(error),
+            Self::Secondary(error) => Some(error),
+        }
+    }
+}
+
+impl<P: Classify, S: Classify> Classify for FallbackError<P, S> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Primary(error) => error.kind(),
+            Self::Secondary(error) => error.kind(),
+        }
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Primary(error) => error.retry_after(),
+            Self::Secondary(error) => error.retry_after(),
+        }
+    }
+
+    fn rate_limit(&self) -> Option<RateLimitInfo> {
+        match self {
+            Self::Primary(error) => error.rate_limit(),
+            Self::Secondary(error) => error.rate_limit(),
+        }
+    }
+
+    fn raw(&self) -> Option<&RawError> {
+        match self {
+            Self::Primary(error) => error.raw(),
+            Self::Secondary(error) => error.raw(),
+        }
+    }
+}
+
+/// A language model trying a list of models in order until one responds.
+///
+/// Behaves like nested [`Fallback`]s over models of the same type: each model is tried
+/// in turn when the previous one fails or exceeds the timeout before its first chunk.
+/// The last model is never timed out. If every model fails, the error of the last one
+/// is returned.
+///
+/// To chain models of different types, box them into a common type first.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{LanguageModel, llm::Request, middleware::ModelChain};
+///
+/// async fn answer<M: LanguageModel>(models: Vec<M>) -> ai_types::Result {
+///     let chain = ModelChain::new(models);
+///     Ok(chain.respond(Request::oneshot("Be brief", "Hello!")).await?)
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ModelChain<M, T = NoDelay> {
+    models: Vec<M>,
+    timeout: Option<Duration>,
+    timer: T,
+}
+
+impl<M: LanguageModel> ModelChain<M> {
+    /// Creates a chain trying `models` in order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `models` is empty.
+    #[must_use]
+    pub fn new(models: impl IntoIterator<Item = M>) -> Self {
+        let models: Vec<M> = models.into_iter().collect();
+        assert!(!models.is_empty(), "A model chain needs at least one model");
+        Self {
+            models,
+            timeout: None,
+            timer: NoDelay,
+        }
+    }
+}
+
+impl<M: LanguageModel, T: Timer> ModelChain<M, T> {
+    /// Moves on to the next model when one takes longer than `timeout` to produce its
+    /// first chunk, measured with `timer`.
+    #[must_use]
+    pub fn with_timeout<U: Timer>(self, timeout: Duration, timer: U) -> ModelChain<M, U> {
+        ModelChain {
+            models: self.models,
+            timeout: Some(timeout),
+            timer,
+        }
+    }
+
+    /// Returns the models of the chain, in the order they are tried.
+    #[must_use]
+    pub fn models(&self) -> &[M] {
+        &self.models
+    }
+
+    fn chained<I, S>(
+        &self,
+        attempt: impl Fn(usize) -> S + Send,
+    ) -> impl Stream<Item = Result<I, M::Error>> + Send
+    where
+        I: Send,
+        S: Stream<Item = Result<I, M::Error>> + Send,
+    {
+        stream! {
+            let last = self.models.len() - 1;
+            for index in 0..last {
+                let items = attempt(index);
+                pin!(items);
+                match first_item(items.as_mut(), &self.timer, self.timeout).await {
+                    First::Item(Ok(first)) => {
+                        yield Ok(first);
+                        while let Some(item) = items.next().await {
+                            yield item;
+                        }
+                        return;
+                    }
+                    First::Ended => return,
+                    First::Item(Err(_)) | First::TimedOut => {}
+                }
+            }
+
+            let items = attempt(last);
+            pin!(items);
+            while let Some(item) = items.next().await {
+                yield item;
+            }
+        }
+    }
+}
+
+impl<M: LanguageModel, T: Timer + 'static> LanguageModel for ModelChain<M, T> {
+    type Error = M::Error;
+
+    fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+        text_stream(self.chained(move |index| self.models[index].respond(request.clone())))
+    }
+
+    fn respond_events(
+        &self,
+        request: Request,
+    ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+        self.chained(move |index| self.models[index].respond_events(request.clone()))
+    }
+
+    fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+        text_stream(self.chained(move |index| self.models[index].complete(prefix)))
+    }
+
+    /// Returns the profile of the first model.
+    fn profile(&self) -> Profile {
+        self.models[0].profile()
+    }
+}
+
+/// How a stream started, as awaited by [`first_item`].
+enum First<T> {
+    /// The stream produced its first item.
+    Item(T),
+    /// The stream ended without producing any item.
+    Ended,
+    /// The timeout elapsed before the first item.
+    TimedOut,
+}
+
+/// Waits for the first item of `items`, or until it ends or `timeout` elapses first.
+async fn first_item<S: Stream>(
+    mut items: Pin<&mut S>,
+    timer: &impl Timer,
+    timeout: Option<Duration>,
+) -> First<S::Item> {
+    let first = async { items.next().await.map_or(First::Ended, First::Item) };
+    match timeout {
+        Some(timeout) => {
+            future::or(first, async {
+                timer.sleep(timeout).await;
+                First::TimedOut
+            })
+            .await
+        }
+        None => first.await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::ProviderError,
+        test_util::{MockLanguageModel, MockReply},
+    };
+    use core::future::{Pending, pending, ready};
+
+    /// A mock model whose latency never elapses.
+    type Mock = MockLanguageModel<fn(Duration) -> Pending<()>>;
+
+    fn mock(reply: impl Into<MockReply>) -> Mock {
+        MockLanguageModel::new()
+            .with_reply(reply)
+            .with_timer(|_| pending())
+    }
+
+    fn answer(text: &str) -> Mock {
+        mock(text)
+    }
+
+    fn failure() -> ProviderError {
+        ProviderError::new(ErrorKind::ProviderUnavailable, "down")
+    }
+
+    fn fail() -> Mock {
+        mock(MockReply::error(failure()))
+    }
+
+    fn fail_midway() -> Mock {
+        mock(MockReply::text("partial").then_fail(failure()))
+    }
+
+    fn hang() -> Mock {
+        mock("late").with_latency(Duration::from_secs(60))
+    }
+
+    fn empty() -> Mock {
+        mock(MockReply::default())
+    }
+
+    #[tokio::test]
+    async fn falls_back_on_failure() {
+        let model = Fallback::new(answer("primary"), answer("secondary"));
+        assert_eq!(model.respond(Request::default()).await.unwrap(), "primary");
+
+        let model = Fallback::new(fail(), answer("secondary"));
+        assert_eq!(
+            model.respond(Request::default()).await.unwrap(),
+            "secondary"
+        );
+
+        let model = Fallback::new(fail(), fail());
+        assert!(matches!(
+            model.respond(Request::default()).await,
+            Err(FallbackError::Secondary(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn does_not_fall_back_after_streaming_started() {
+        let model = Fallback::new(fail_midway(), answer("secondary"));
+        assert!(matches!(
+            model.respond(Request::default()).await,
+            Err(FallbackError::Primary(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn falls_back_on_timeout() {
+        let model = Fallback::new(hang(), answer("secondary"))
+            .with_timeout(Duration::from_secs(1), |_| ready(()));
+        assert_eq!(
+            model.respond(Request::default()).await.unwrap(),
+            "secondary"
+        );
+    }
+
+    #[tokio::test]
+    async fn chain_tries_models_in_order() {
+        let chain = ModelChain::new([fail(), hang(), answer("third")])
+            .with_timeout(Duration::from_secs(1), |_| ready(()));
+        assert_eq!(chain.respond(Request::default()).await.unwrap(), "third");
+        assert_eq!(chain.complete("Hi").await.unwrap(), "third");
+
+        let chain = ModelChain::new([fail(), fail()]);
+        assert_eq!(
+            chain.respond(Request::default()).await.unwrap_err().kind,
+            ErrorKind::ProviderUnavailable
+        );
+    }
+
+    #[tokio::test]
+    async fn empty_responses_do_not_fall_back() {
+        let model = Fallback::new(empty(), answer("secondary"));
+        assert_eq!(model.respond(Request::default()).await.unwrap(), "");
+
+        let chain = ModelChain::new([empty(), answer("second")]);
+        assert_eq!(chain.respond(Request::default()).await.unwrap(), "");
+    }
+}
@@ -0,0 +1,419 @@
+use alloc::{string::String, vec::Vec};
+use core::{fmt, mem};
+
+use async_stream::stream;
+use futures_core::Stream;
+use futures_lite::{StreamExt, pin};
+
+use crate::{
+    LanguageModel,
+    error::{Classify, ErrorKind, RawError},
+    llm::{
+        Request, TextStream,
+        event::{StreamEvent, text_events},
+        model::Profile,
+        stream::text_stream,
+    },
+    moderation::{Moderation, ModerationCategory},
+    rate_limit::RateLimitInfo,
+};
+
+/// A language model whose output is checked by a [`Moderation`] service before delivery.
+///
+/// By default the whole response is buffered and moderated once, so nothing reaches the
+/// caller unless the complete response passes. With
+/// [`with_sentence_window`](Self::with_sentence_window), the response is released in
+/// windows of a few sentences instead, each moderated before it is emitted. This keeps
+/// streaming responsive at the cost of moderating each window without the rest of the
+/// response as context.
+///
+/// When a window is flagged, the stream ends with [`GuardError::StoppedByPolicy`] and
+/// nothing after it is emitted. Tool calls and other events pass through unmoderated;
+/// pending text is moderated and emitted before them.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{LanguageModel, Moderation, llm::Request, middleware::{GuardError, Guarded}};
+/// use futures_lite::StreamExt;
+///
+/// async fn answer(model: impl LanguageModel, moderation: impl Moderation + Send + Sync + 'static) {
+///     let model = Guarded::new(model, moderation).with_sentence_window(2);
+///     let mut stream = model.respond(Request::oneshot("Be helpful", "Tell me a story"));
+///     while let Some(chunk) = stream.next().await {
+///         match chunk {
+///             Ok(text) => print!("{text}"),
+///             Err(GuardError::StoppedByPolicy(stop)) => {
+///                 println!("\n[Response withheld: {} policy violations]", stop.categories.len());
+///             }
+///             Err(error) => println!("\n[Error: {error}]"),
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Guarded<M, G> {
+    model: M,
+    moderation: G,
+    window: Option<usize>,
+}
+
+impl<M: LanguageModel, G: Moderation> Guarded<M, G> {
+    /// Creates a model whose complete responses are moderated before delivery.
+    #[must_use]
+    pub const fn new(model: M, moderation: G) -> Self {
+        Self {
+            model,
+            moderation,
+            window: None,
+        }
+    }
+
+    /// Releases the response in windows of `sentences` sentences, moderating each one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sentences` is zero.
+    #[must_use]
+    pub const fn with_sentence_window(mut self, sentences: usize) -> Self {
+        assert!(
+            sentences > 0,
+            "A sentence window needs at least one sentence"
+        );
+        self.window = Some(sentences);
+        self
+    }
+
+    /// Returns the wrapped model.
+    #[must_use]
+    pub const fn model(&self) -> &M {
+        &self.model
+    }
+
+    /// Returns the moderation service.
+    #[must_use]
+    pub const fn moderation(&self) -> &G {
+        &self.moderation
+    }
+}
+
+impl<M: LanguageModel, G: Moderation + Sync> Guarded<M, G> {
+    async fn check(&self, text: &str) -> Result<(), GuardError<M::Error, G::Error>> {
+        let result = self
+            .moderation
+            .moderate(text)
+            .await
+            .map_err(GuardError::Moderation)?;
+        if result.is_flagged() || result.has_violations() {
+            return Err(GuardError::StoppedByPolicy(StoppedByPolicy {
+                categories: result.categories().to_vec(),
+            }));
+        }
+        Ok(())
+    }
+
+    fn guarded(
+        &self,
+        events: impl Stream<Item = Result<StreamEvent, M::Error>> + Send,
+    ) -> impl Stream<Item = Result<StreamEvent, GuardError<M::Error, G::Error>>> + Send {
+        stream! {
+            pin!(events);
+            let mut buffer = SentenceBuffer::new(self.window);
+            loop {
+                let event = match events.next().await {
+                    Some(Ok(StreamEvent::Text(chunk))) => {
+                        buffer.push(&chunk);
+                        None
+                    }
+                    Some(Ok(event)) => Some(event),
+                    Some(Err(error)) => {
+                        yield Err(GuardError::Model(error));
+                        return;
+                    }
+                    None => Some(StreamEvent::Done),
+                };
+                let flush = event.is_some();
+                while let Some(text) = buffer.next_window(flush) {
+                    if let Err(error) = self.check(&text).await {
+                        yield Err(error);
+                        return;
+                    }
+                    yield Ok(StreamEvent::Text(text));
+                }
+                match event {
+                    Some(StreamEvent::Done) => {
+                        yield Ok(StreamEvent::Done);
+                        return;
+                    }
+                    Some(event) => yield Ok(event),
+                    None => {}
+                }
+            }
+        }
+    }
+}
+
+impl<M, G> LanguageModel for Guarded<M, G>
+where
+    M: LanguageModel,
+    G: Moderation + Send + Sync + 'static,
+{
+    type Error = GuardError<M::Error, G::Error>;
+
+    fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+        text_stream(only_text(
+            self.guarded(text_events(self.model.respond(request))),
+        ))
+    }
+
+    fn respond_events(
+        &self,
+        request: Request,
+    ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+        self.guarded(self.model.respond_events(request))
+    }
+
+    fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+        text_stream(only_text(
+            self.guarded(text_events(self.model.complete(prefix))),
+        ))
+    }
+
+    fn profile(&self) -> Profile {
+        self.model.profile()
+    }
+}
+
+fn only_text<E>(
+    events: impl Stream<Item = Result<StreamEvent, E>>,
+) -> impl Stream<Item = Result<String, E>> {
+    events.filter_map(|event| match event {
+        Ok(StreamEvent::Text(text)) => Some(Ok(text)),
+        Ok(_) => None,
+        Err(error) => Some(Err(error)),
+    })
+}
+
+/// Accumulates text and releases it in windows of whole sentences.
+struct SentenceBuffer {
+    text: String,
+    window: Option<usize>,
+}
+
+impl SentenceBuffer {
+    const fn new(window: Option<usize>) -> Self {
+        Self {
+            text: String::new(),
+            window,
+        }
+    }
+
+    fn push(&mut self, chunk: &str) {
+        self.text.push_str(chunk);
+    }
+
+    /// Takes the next complete window, or all remaining text when `flush` is set.
+    fn next_window(&mut self, flush: bool) -> Option<String> {
+        if let Some(end) = self
+            .window
+            .and_then(|sentences| sentence_end(&self.text, sentences))
+        {
+            return Some(self.text.drain(..end).collect());
+        }
+        (flush && !self.text.is_empty()).then(|| mem::take(&mut self.text))
+    }
+}
+
+/// Returns the byte offset just past the `count`-th complete sentence of `text`.
+///
+/// A sentence ends with terminal punctuation followed by whitespace, which is included.
+fn sentence_end(text: &str, count: usize) -> Option<usize> {
+    let mut found = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        if !matches!(c, '.' | '!' | '?' | '。' | '！' | '？') {
+            continue;
+        }
+        if let Some(&(index, next)) = chars.peek() {
+            if next.is_whitespace() {
+                found += 1;
+                if found == count {
+                    return Some(index + next.len_utf8());
+                }
+            }
+        }
+    }
+    None
+}
+
+/// The termination of a response that violated the moderation policy.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct StoppedByPolicy {
+    /// The violations detected in the withheld text.
+    pub categories: Vec<ModerationCategory>,
+}
+
+impl fmt::Display for StoppedByPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Response stopped by content policy ({} violations)",
+            self.categories.len()
+        )
+    }
+}
+
+impl core::error::Error for StoppedByPolicy {}
+
+/// An error returned by [`Guarded`].
+#[derive(Debug)]
+pub enum GuardError<E, ME> {
+    /// The wrapped model failed.
+    Model(E),
+    /// The moderation service failed.
+    Moderation(ME),
+    /// The response was stopped because it violated the moderation policy.
+    StoppedByPolicy(StoppedByPolicy),
+}
+
+impl<E: fmt::Display, ME: fmt::Display> fmt::Display for GuardError<E, ME> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Model(error) => error.fmt(f),
+            Self::Moderation(error) => write!(f, "Moderation failed: {error}"),
+            Self::StoppedByPolicy(stop) => stop.fmt(f),
+        }
+    }
+}
+
+impl<E, ME> core::error::Error for GuardError<E, ME>
+where
+    E: core::error::Error + 'static,
+    ME: core::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Model(error) => Some(error),
+            Self::Moderation(error) => Some(error),
+            Self::StoppedByPolicy(_) => None,
+        }
+    }
+}
+
+impl<E: Classify, ME> Classify for GuardError<E, ME> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Model(error) => error.kind(),
+            Self::Moderation(_) => ErrorKind::Other,
+            Self::StoppedByPolicy(_) => ErrorKind::ContentFiltered,
+        }
+    }
+
+    fn retry_after(&self) -> Option<core::time::Duration> {
+        match self {
+            Self::Model(error) => error.retry_after(),
+            _ => None,
+        }
+    }
+
+    fn rate_limit(&self) -> Option<RateLimitInfo> {
+        match self {
+            Self::Model(error) => error.rate_limit(),
+            _ => None,
+        }
+    }
+
+    fn raw(&self) -> Option<&RawError> {
+        match self {
+            Self::Model(error) => error.raw(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::ProviderError,
+        moderation::ModerationResult,
+        test_util::{MockLanguageModel, MockReply},
+    };
+    use alloc::vec;
+    use core::convert::Infallible;
+
+    /// Streams `text` word by word.
+    fn words(text: &str) -> MockLanguageModel {
+        MockLanguageModel::new().with_reply(MockReply::chunks(text.split_inclusive(' ')))
+    }
+
+    /// Flags text mentioning violence.
+    struct Keyword;
+
+    impl Moderation for Keyword {
+        type Error = Infallible;
+
+        async fn moderate(&self, content: &str) -> Result<ModerationResult, Self::Error> {
+            let categories = if content.contains("violence") {
+                vec![ModerationCategory::Violence { score: 0.9 }]
+            } else {
+                Vec::new()
+            };
+            Ok(ModerationResult::new(false, categories))
+        }
+    }
+
+    async fn chunks(
+        model: &impl LanguageModel<Error = GuardError<ProviderError, Infallible>>,
+    ) -> (Vec<String>, Option<StoppedByPolicy>) {
+        let stream = model.respond(Request::default());
+        pin!(stream);
+        let mut chunks = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(text) => chunks.push(text),
+                Err(GuardError::StoppedByPolicy(stop)) => return (chunks, Some(stop)),
+                Err(_) => unreachable!(),
+            }
+        }
+        (chunks, None)
+    }
+
+    #[tokio::test]
+    async fn releases_sentence_windows() {
+        let model = Guarded::new(words("One. Two! Three? Four"), Keyword).with_sentence_window(2);
+        let (chunks, stop) = chunks(&model).await;
+        assert_eq!(chunks, ["One. Two! ", "Three? Four"]);
+        assert!(stop.is_none());
+    }
+
+    #[tokio::test]
+    async fn stops_at_flagged_window() {
+        let model = Guarded::new(
+            words("Hello there. Some violence here. More text."),
+            Keyword,
+        )
+        .with_sentence_window(1);
+        let (chunks, stop) = chunks(&model).await;
+        assert_eq!(chunks, ["Hello there. "]);
+        assert_eq!(
+            stop.unwrap().categories,
+            [ModerationCategory::Violence { score: 0.9 }]
+        );
+    }
+
+    #[tokio::test]
+    async fn withholds_whole_response_by_default() {
+        let model = Guarded::new(words("Fine. Then violence."), Keyword);
+        let (chunks, stop) = chunks(&model).await;
+        assert!(chunks.is_empty());
+        assert!(stop.is_some());
+
+        let model = Guarded::new(words("All fine. Really."), Keyword);
+        assert_eq!(
+            model.respond(Request::default()).await.unwrap(),
+            "All fine. Really."
+        );
+    }
+}
@@ -0,0 +1,652 @@
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::{future::Future, time::Duration};
+
+use async_stream::stream;
+use futures_core::Stream;
+use futures_lite::{StreamExt, pin};
+
+use crate::{
+    EmbeddingModel, ImageGenerator, LanguageModel, Moderation, Reranker,
+    embedding::EmbeddingTask,
+    image::{self, Data, Prompt, Size},
+    llm::{
+        Message, Request, TextStream,
+        event::StreamEvent,
+        model::Profile,
+        stream::text_stream,
+        token::{Estimator, TokenCounter},
+        usage::Usage,
+    },
+    moderation::ModerationResult,
+    rerank::RankedResult,
+    time::Clock,
+};
+
+use super::JailbreakAttempt;
+
+type Error = dyn core::error::Error + 'static;
+
+/// A model reporting the latency, tokens and errors of every call to [`Hooks`].
+///
+/// Every call is reported to [`Hooks::on_request`] when it is made, to
+/// [`Hooks::on_first_token`] when the first output arrives, and to
+/// [`Hooks::on_complete`] or [`Hooks::on_error`] with its [`Metrics`] when it ends.
+/// Token counts come from the [`Usage`] the provider reported, or else are estimated with
+/// [`Estimator`]. Responses dropped before they end are not reported.
+///
+/// The wrapper implements every model trait its model implements: [`LanguageModel`],
+/// [`EmbeddingModel`], [`ImageGenerator`], [`Moderation`] and [`Reranker`].
+///
+/// With the `tracing` feature, each call is also recorded as a `model_call` span,
+/// carrying the model, the operation, the measurements and the error, if any.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{
+///     LanguageModel,
+///     llm::Request,
+///     middleware::{Call, Hooks, Instrumented, Metrics},
+/// };
+/// use std::time::Instant;
+///
+/// struct Log;
+///
+/// impl Hooks for Log {
+///     fn on_complete(&self, call: &Call, metrics: &Metrics) {
+///         println!("{}: {:?}, {} tokens", call.model, metrics.latency, metrics.usage.total_tokens);
+///     }
+/// }
+///
+/// async fn answer(model: impl LanguageModel) -> ai_types::Result {
+///     let start = Instant::now();
+///     let model = Instrumented::new(model, move || start.elapsed()).with_hooks(Log);
+///
+///     Ok(model.respond(Request::oneshot("Be brief", "Hello!")).await?)
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Instrumented<M, C, H = ()> {
+    model: M,
+    clock: C,
+    hooks: H,
+    name: Option<String>,
+}
+
+impl<M, C: Clock> Instrumented<M, C> {
+    /// Wraps `model`, timing calls with `clock`.
+    #[must_use]
+    pub const fn new(model: M, clock: C) -> Self {
+        Self {
+            model,
+            clock,
+            hooks: (),
+            name: None,
+        }
+    }
+}
+
+impl<M, C: Clock, H: Hooks> Instrumented<M, C, H> {
+    /// Reports calls to `hooks`.
+    #[must_use]
+    pub fn with_hooks<G: Hooks>(self, hooks: G) -> Instrumented<M, C, G> {
+        Instrumented {
+            model: self.model,
+            clock: self.clock,
+            hooks,
+            name: self.name,
+        }
+    }
+
+    /// Names the model in reports, instead of the name from its profile.
+    ///
+    /// Models without a profile, such as embedding models, are unnamed otherwise.
+    #[must_use]
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Returns the wrapped model.
+    #[must_use]
+    pub const fn model(&self) -> &M {
+        &self.model
+    }
+
+    /// Returns the hooks calls are reported to.
+    #[must_use]
+    pub const fn hooks(&self) -> &H {
+        &self.hooks
+    }
+}
+
+impl<M: Sync, C: Clock, H: Hooks> Instrumented<M, C, H> {
+    /// Starts recording a call.
+    fn start(&self, operation: Operation, profile: impl FnOnce() -> String) -> Recording {
+        let call = Call {
+            model: self.name.clone().unwrap_or_else(profile),
+            operation,
+        };
+        self.hooks.on_request(&call);
+        Recording {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "model_call",
+                model = %call.model,
+                operation = operation.as_str(),
+                latency_ms = tracing::field::Empty,
+                time_to_first_token_ms = tracing::field::Empty,
+                prompt_tokens = tracing::field::Empty,
+                completion_tokens = tracing::field::Empty,
+                error = tracing::field::Empty,
+            ),
+            call,
+            started: self.clock.now(),
+            first_token: None,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            reported: None,
+        }
+    }
+
+    /// Records the arrival of the first output of a call.
+    fn first_token(&self, recording: &mut Recording) {
+        if recording.first_token.is_some() {
+            return;
+        }
+        let latency = self.clock.now().saturating_sub(recording.started);
+        recording.first_token = Some(latency);
+        self.hooks.on_first_token(&recording.call, latency);
+    }
+
+    /// Ends a call, reporting its metrics.
+    fn finish(&self, recording: &Recording, error: Option<&Error>) {
+        let metrics = Metrics {
+            latency: self.clock.now().saturating_sub(recording.started),
+            time_to_first_token: recording.first_token,
+            usage: recording.reported.unwrap_or_else(|| {
+                Usage::new(recording.prompt_tokens, recording.completion_tokens)
+            }),
+            estimated: recording.reported.is_none(),
+        };
+
+        #[cfg(feature = "tracing")]
+        {
+            let span = &recording.span;
+            span.record("latency_ms", millis(metrics.latency));
+            if let Some(first_token) = metrics.time_to_first_token {
+                span.record("time_to_first_token_ms", millis(first_token));
+            }
+            span.record("prompt_tokens", metrics.usage.prompt_tokens);
+            span.record("completion_tokens", metrics.usage.completion_tokens);
+            if let Some(error) = error {
+                span.record("error", tracing::field::display(error));
+            }
+        }
+
+        match error {
+            Some(error) => self.hooks.on_error(&recording.call, &metrics, error),
+            None => self.hooks.on_complete(&recording.call, &metrics),
+        }
+    }
+
+    /// Forwards `items`, recording them with `observe`, which returns whether an item is
+    /// output.
+    fn observed<I, E, S>(
+        &self,
+        mut recording: Recording,
+        items: S,
+        observe: fn(&mut Recording, &I) -> bool,
+    ) -> impl Stream<Item = Result<I, E>> + Send
+    where
+        I: Send,
+        E: core::error::Error + Send + 'static,
+        S: Stream<Item = Result<I, E>> + Send,
+    {
+        stream! {
+            pin!(items);
+            while let Some(item) = items.next().await {
+                match item {
+                    Ok(item) => {
+                        if observe(&mut recording, &item) {
+                            self.first_token(&mut recording);
+                        }
+                        yield Ok(item);
+                    }
+                    Err(error) => {
+                        self.finish(&recording, Some(&error));
+                        yield Err(error);
+                        return;
+                    }
+                }
+            }
+            self.finish(&recording, None);
+        }
+    }
+
+    /// Awaits `result`, recording it as a call with `prompt_tokens` of input.
+    async fn timed<T, E>(
+        &self,
+        mut recording: Recording,
+        prompt_tokens: u32,
+        result: impl Future<Output = Result<T, E>>,
+        error: fn(&E) -> &Error,
+    ) -> Result<T, E> {
+        recording.prompt_tokens = prompt_tokens;
+        let result = result.await;
+        self.finish(&recording, result.as_ref().err().map(error));
+        result
+    }
+}
+
+impl<M, C, H> LanguageModel for Instrumented<M, C, H>
+where
+    M: LanguageModel,
+    C: Clock + 'static,
+    H: Hooks + 'static,
+{
+    type Error = M::Error;
+
+    fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+        let mut recording = self.start(Operation::Respond, || self.model.profile().name);
+        recording.prompt_tokens = tokens(&request.messages);
+        let chunks = self.model.respond(request);
+        text_stream(
+            self.observed(recording, chunks, |recording, chunk: &String| {
+                recording.text(chunk)
+            }),
+        )
+    }
+
+    fn respond_events(
+        &self,
+        request: Request,
+    ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+        let mut recording = self.start(Operation::Respond, || self.model.profile().name);
+        recording.prompt_tokens = tokens(&request.messages);
+        let events = self.model.respond_events(request);
+        self.observed(recording, events, |recording, event| match event {
+            StreamEvent::Text(chunk) => recording.text(chunk),
+            StreamEvent::ToolCall(_) | StreamEvent::InvalidToolCall { .. } => true,
+            StreamEvent::Usage(usage) => {
+                recording.reported = Some(*usage);
+                false
+            }
+            _ => false,
+        })
+    }
+
+    fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+        let mut recording = self.start(Operation::Complete, || self.model.profile().name);
+        recording.prompt_tokens = tokens(&[Message::user(prefix)]);
+        let chunks = self.model.complete(prefix);
+        text_stream(
+            self.observed(recording, chunks, |recording, chunk: &String| {
+                recording.text(chunk)
+            }),
+        )
+    }
+
+    fn profile(&self) -> Profile {
+        self.model.profile()
+    }
+}
+
+impl<M, C, H> EmbeddingModel for Instrumented<M, C, H>
+where
+    M: EmbeddingModel + Sync,
+    C: Clock,
+    H: Hooks,
+{
+    fn dim(&self) -> usize {
+        self.model.dim()
+    }
+
+    fn embed(&self, text: &str) -> impl Future<Output = crate::Result<Vec<f32>>> + Send {
+        let recording = self.start(Operation::Embed, String::new);
+        let embedding = self.model.embed(text);
+        self.timed(recording, count(text), embedding, AsRef::as_ref)
+    }
+
+    fn supported_dims(&self) -> Vec<usize> {
+        self.model.supported_dims()
+    }
+
+    fn embed_with_dim(
+        &self,
+        text: &str,
+        dim: usize,
+    ) -> impl Future<Output = crate::Result<Vec<f32>>> + Send {
+        let recording = self.start(Operation::Embed, String::new);
+        let embedding = self.model.embed_with_dim(text, dim);
+        self.timed(recording, count(text), embedding, AsRef::as_ref)
+    }
+
+    fn embed_for(
+        &self,
+        text: &str,
+        task: EmbeddingTask,
+    ) -> impl Future<Output = crate::Result<Vec<f32>>> + Send {
+        let recording = self.start(Operation::Embed, String::new);
+        let embedding = self.model.embed_for(text, task);
+        self.timed(recording, count(text), embedding, AsRef::as_ref)
+    }
+}
+
+impl<M, C, H> Moderation for Instrumented<M, C, H>
+where
+    M: Moderation + Sync,
+    C: Clock,
+    H: Hooks,
+{
+    type Error = M::Error;
+
+    fn moderate(
+        &self,
+        content: &str,
+    ) -> impl Future<Output = Result<ModerationResult, Self::Error>> + Send {
+        let recording = self.start(Operation::Moderate, String::new);
+        let result = self.model.moderate(content);
+        self.timed(recording, count(content), result, |error| error)
+    }
+}
+
+impl<M, C, H> Reranker for Instrumented<M, C, H>
+where
+    M: Reranker + Sync,
+    C: Clock,
+    H: Hooks,
+{
+    fn rerank(
+        &self,
+        query: &str,
+        documents: &[&str],
+    ) -> impl Future<Output = crate::Result<Vec<RankedResult>>> + Send {
+        let recording = self.start(Operation::Rerank, String::new);
+        let prompt_tokens = documents.iter().fold(count(query), |sum, document| {
+            sum.saturating_add(count(document))
+        });
+        let ranking = self.model.rerank(query, documents);
+        self.timed(recording, prompt_tokens, ranking, AsRef::as_ref)
+    }
+}
+
+impl<M, C, H> ImageGenerator for Instrumented<M, C, H>
+where
+    M: ImageGenerator + Sync,
+    C: Clock,
+    H: Hooks,
+{
+    type Error = M::Error;
+
+    fn create(
+        &self,
+        prompt: Prompt,
+        size: Size,
+    ) -> impl Stream<Item = Result<Data, Self::Error>> + Unpin + Send {
+        let mut recording = self.start(Operation::CreateImage, || self.model.profile().name);
+        recording.prompt_tokens = count(prompt.text());
+        let images = self.model.create(prompt, size);
+        Box::pin(self.observed(recording, images, |_, _| true))
+    }
+
+    fn edit(
+        &self,
+        prompt: Prompt,
+        mask: &[u8],
+    ) -> impl Stream<Item = Result<Data, Self::Error>> + Unpin + Send {
+        let mut recording = self.start(Operation::EditImage, || self.model.profile().name);
+        recording.prompt_tokens = count(prompt.text());
+        let images = self.model.edit(prompt, mask);
+        Box::pin(self.observed(recording, images, |_, _| true))
+    }
+
+    fn profile(&self) -> image::Profile {
+        self.model.profile()
+    }
+}
+
+/// An in-flight call of an [`Instrumented`] model.
+struct Recording {
+    call: Call,
+    started: Duration,
+    first_token: Option<Duration>,
+    prompt_tokens: u32,
+    completion_tokens: u32,
+    reported: Option<Usage>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl Recording {
+    /// Counts a chunk of text, returning whether it is output.
+    fn text(&mut self, chunk: &str) -> bool {
+        self.completion_tokens = self.completion_tokens.saturating_add(count(chunk));
+        !chunk.is_empty()
+    }
+}
+
+fn count(text: &str) -> u32 {
+    u32::try_from(Estimator::new().count_tokens(text)).unwrap_or(u32::MAX)
+}
+
+fn tokens(messages: &[Message]) -> u32 {
+    u32::try_from(Estimator::new().count_message_tokens(messages)).unwrap_or(u32::MAX)
+}
+
+#[cfg(feature = "tracing")]
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Observes the calls of an [`Instrumented`] model, and the prompts flagged by a
+/// [`JailbreakMonitor`](super::JailbreakMonitor).
+///
+/// Every method does nothing by default, so implementations only override the events
+/// they need. `()` is the hooks ignoring every event.
+pub trait Hooks: Send + Sync {
+    /// Called when a call is made.
+    fn on_request(&self, _call: &Call) {}
+
+    /// Called when the first output of a streaming call arrives, such as a chunk of text,
+    /// a tool call or an image, `latency` after the call was made.
+    fn on_first_token(&self, _call: &Call, _latency: Duration) {}
+
+    /// Called when a call succeeds.
+    fn on_complete(&self, _call: &Call, _metrics: &Metrics) {}
+
+    /// Called when a call fails with `error`.
+    fn on_error(
+        &self,
+        _call: &Call,
+        _metrics: &Metrics,
+        _error: &(dyn core::error::Error + 'static),
+    ) {
+    }
+    /// Called when a [`JailbreakMonitor`](super::JailbreakMonitor) flags a prompt.
+    fn on_jailbreak_attempt(&self, _attempt: &JailbreakAttempt) {}
+}
+
+impl Hooks for () {}
+
+/// What an [`Instrumented`] model was called for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Operation {
+    /// A language model response, as text or events.
+    Respond,
+    /// A language model completion.
+    Complete,
+    /// An embedding.
+    Embed,
+    /// An image generation.
+    CreateImage,
+    /// An image edit.
+    EditImage,
+    /// A moderation check.
+    Moderate,
+    /// A reranking.
+    Rerank,
+}
+
+impl Operation {
+    /// Returns the name of the operation in `snake_case`.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Respond => "respond",
+            Self::Complete => "complete",
+            Self::Embed => "embed",
+            Self::CreateImage => "create_image",
+            Self::EditImage => "edit_image",
+            Self::Moderate => "moderate",
+            Self::Rerank => "rerank",
+        }
+    }
+}
+
+/// A call to an [`Instrumented`] model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Call {
+    /// The name of the model, from its profile or [`Instrumented::with_name`].
+    pub model: String,
+    /// What the model was called for.
+    pub operation: Operation,
+}
+
+/// Measurements of a finished call of an [`Instrumented`] model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Metrics {
+    /// The time from the call to its end.
+    pub latency: Duration,
+    /// The time from the call to its first output, for streaming calls that produced any.
+    pub time_to_first_token: Option<Duration>,
+    /// The tokens of the call. Calls other than language model responses only count
+    /// their input.
+    pub usage: Usage,
+    /// Whether the usage was estimated, because the model did not report it.
+    pub estimated: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::{ErrorKind, ProviderError},
+        test_util::{MockLanguageModel, MockReply},
+    };
+    use alloc::{sync::Arc, vec};
+    use core::sync::atomic::{AtomicU64, Ordering};
+    use spin::Mutex;
+
+    /// Answers in two chunks, then fails after the first.
+    fn model() -> MockLanguageModel {
+        MockLanguageModel::new()
+            .with_profile(Profile::new("model", "Says hello", 1024))
+            .with_replies([
+                MockReply::chunks(["hello", " world"]),
+                MockReply::text("hello")
+                    .then_fail(ProviderError::new(ErrorKind::Timeout, "no answer")),
+            ])
+    }
+
+    struct Embedder;
+
+    impl EmbeddingModel for Embedder {
+        fn dim(&self) -> usize {
+            2
+        }
+
+        async fn embed(&self, _text: &str) -> crate::Result<Vec<f32>> {
+            Ok(vec![1.0, 0.0])
+        }
+    }
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl Hooks for Arc<Recorder> {
+        fn on_request(&self, call: &Call) {
+            self.0.lock().push(alloc::format!("request {}", call.model));
+        }
+
+        fn on_first_token(&self, _call: &Call, latency: Duration) {
+            self.0
+                .lock()
+                .push(alloc::format!("first token {latency:?}"));
+        }
+
+        fn on_complete(&self, call: &Call, metrics: &Metrics) {
+            self.0.lock().push(alloc::format!(
+                "{} complete {:?} {}",
+                call.operation.as_str(),
+                metrics.latency,
+                metrics.usage.completion_tokens
+            ));
+        }
+
+        fn on_error(
+            &self,
+            _call: &Call,
+            _metrics: &Metrics,
+            error: &(dyn core::error::Error + 'static),
+        ) {
+            self.0.lock().push(alloc::format!("error {error}"));
+        }
+    }
+
+    /// A clock advancing one millisecond per reading.
+    fn ticking() -> impl Fn() -> Duration + Send + Sync + 'static {
+        let millis = AtomicU64::new(0);
+        move || Duration::from_millis(millis.fetch_add(1, Ordering::Relaxed))
+    }
+
+    #[tokio::test]
+    async fn reports_latency_and_tokens() {
+        let recorder = Arc::new(Recorder::default());
+        let model = Instrumented::new(model(), ticking()).with_hooks(recorder.clone());
+
+        let response = model.respond(Request::oneshot("Be brief", "Hi")).await;
+        assert_eq!(response.unwrap(), "hello world");
+        assert_eq!(
+            *recorder.0.lock(),
+            ["request model", "first token 1ms", "respond complete 2ms 4"]
+        );
+
+        recorder.0.lock().clear();
+        assert!(
+            model
+                .respond(Request::new([Message::user("Hi again")]))
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            *recorder.0.lock(),
+            [
+                "request model",
+                "first token 1ms",
+                "error timed out: no answer"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn instruments_other_model_types() {
+        let recorder = Arc::new(Recorder::default());
+        let embedder = Instrumented::new(Embedder, ticking())
+            .with_hooks(recorder.clone())
+            .with_name("embedder");
+
+        assert_eq!(embedder.dim(), 2);
+        assert_eq!(embedder.embed("Hello").await.unwrap(), [1.0, 0.0]);
+        assert_eq!(
+            *recorder.0.lock(),
+            ["request embedder", "embed complete 1ms 0"]
+        );
+    }
+}
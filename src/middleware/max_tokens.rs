@@ -0,0 +1,242 @@
+use alloc::vec::Vec;
+
+use futures_core::Stream;
+
+use crate::{
+    LanguageModel,
+    llm::{
+        Request, TextStream,
+        budget::BudgetPlanner,
+        event::StreamEvent,
+        model::Profile,
+        token::{Estimator, TokenCounter},
+    },
+};
+
+/// Tokens kept free by default for errors in the prompt's count.
+const DEFAULT_MARGIN: u32 = 256;
+
+/// A language model fitting `max_tokens` of every request into its context window.
+///
+/// The largest safe completion is the model's context length, minus the prompt and tool
+/// definitions counted with the token counter, minus a margin for counting errors. Requests without
+/// `max_tokens` get it, and requests asking for more are lowered to it, so the provider
+/// never rejects them for exceeding the context window. [`with_limit`](Self::with_limit)
+/// caps the result at the model's maximum output length.
+///
+/// Requests whose prompt alone fills the window, and models without a known context
+/// length, are passed through unchanged.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{LanguageModel, llm::Request, middleware::FitMaxTokens};
+///
+/// async fn answer(model: impl LanguageModel) -> ai_types::Result {
+///     let model = FitMaxTokens::new(model).with_margin(512).with_limit(8192);
+///
+///     Ok(model.respond(Request::oneshot("Be brief", "Hello!")).await?)
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FitMaxTokens<M, C = Estimator> {
+    model: M,
+    counter: C,
+    margin: u32,
+    limit: Option<u32>,
+}
+
+impl<M: LanguageModel> FitMaxTokens<M> {
+    /// Wraps `model`, counting prompts with [`Estimator`].
+    #[must_use]
+    pub const fn new(model: M) -> Self {
+        Self {
+            model,
+            counter: Estimator::new(),
+            margin: DEFAULT_MARGIN,
+            limit: None,
+        }
+    }
+}
+
+impl<M: LanguageModel, C: TokenCounter> FitMaxTokens<M, C> {
+    /// Counts prompts with `counter`, such as the model's own tokenizer.
+    #[must_use]
+    pub fn with_counter<D: TokenCounter>(self, counter: D) -> FitMaxTokens<M, D> {
+        FitMaxTokens {
+            model: self.model,
+            counter,
+            margin: self.margin,
+            limit: self.limit,
+        }
+    }
+
+    /// Keeps `margin` tokens of the context window free, 256 by default.
+    #[must_use]
+    pub const fn with_margin(mut self, margin: u32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Never sets `max_tokens` above `limit`.
+    #[must_use]
+    pub const fn with_limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns the wrapped model.
+    #[must_use]
+    pub const fn model(&self) -> &M {
+        &self.model
+    }
+
+    /// Returns the largest safe `max_tokens` for `request`, or `None` if no completion
+    /// fits.
+    #[must_use]
+    pub fn max_tokens(&self, request: &Request) -> Option<u32> {
+        let profile = self.model.profile();
+        let mut prompt_tokens = self.counter.count_message_tokens(&request.messages);
+        if !request.tools.is_empty() {
+            prompt_tokens += self.counter.count_tokens(&request.tools.canonical_json());
+        }
+        let prompt_tokens = u32::try_from(prompt_tokens).unwrap_or(u32::MAX);
+        let max = BudgetPlanner::from_profile(&profile).max_completion(prompt_tokens, self.margin);
+        let max = self.limit.map_or(max, |limit| max.min(limit));
+        (max > 0).then_some(max)
+    }
+
+    /// Fits `max_tokens` of `request` into the context window.
+    #[must_use]
+    pub fn apply(&self, mut request: Request) -> Request {
+        if let Some(max) = self.max_tokens(&request) {
+            let requested = request.parameters.max_tokens.get_or_insert(max);
+            *requested = (*requested).min(max);
+        }
+        request
+    }
+}
+
+impl<M, C> LanguageModel for FitMaxTokens<M, C>
+where
+    M: LanguageModel,
+    C: TokenCounter + Send + Sync + 'static,
+{
+    type Error = M::Error;
+
+    fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+        self.model.respond(self.apply(request))
+    }
+
+    fn respond_events(
+        &self,
+        request: Request,
+    ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+        self.model.respond_events(self.apply(request))
+    }
+
+    fn respond_n(&self, request: Request) -> Vec<impl TextStream<Error = Self::Error> + Send> {
+        self.model.respond_n(self.apply(request))
+    }
+
+    fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+        self.model.complete(prefix)
+    }
+
+    fn profile(&self) -> Profile {
+        self.model.profile()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        llm::{Message, Tool, model::Parameters},
+        test_util::MockLanguageModel,
+    };
+    use alloc::string::String;
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+
+    fn model() -> MockLanguageModel {
+        MockLanguageModel::new().with_profile(Profile::new("model", "", 1000))
+    }
+
+    /// Returns the `max_tokens` of the last request `model` received.
+    fn sent(model: &MockLanguageModel) -> Option<u32> {
+        model.requests().last().unwrap().parameters.max_tokens
+    }
+
+    #[derive(JsonSchema, Deserialize)]
+    struct Query {
+        /// The words to search the documentation for.
+        #[allow(dead_code)]
+        query: String,
+    }
+
+    struct Search;
+
+    impl Tool for Search {
+        const NAME: &str = "search";
+        const DESCRIPTION: &str = "Searches the documentation of the project";
+        type Arguments = Query;
+
+        async fn call(&mut self, _arguments: Self::Arguments) -> crate::Result {
+            Ok(String::new())
+        }
+    }
+
+    /// Counts one token per word.
+    fn words(text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+
+    #[tokio::test]
+    async fn fills_in_and_lowers_max_tokens() {
+        let model = FitMaxTokens::new(model())
+            .with_counter(words)
+            .with_margin(100);
+        // 4 tokens of overhead plus 96 words.
+        let request = Request::new([Message::user("word ".repeat(96))]);
+        assert_eq!(model.max_tokens(&request), Some(800));
+
+        model.respond(request.clone()).await.unwrap();
+        assert_eq!(sent(model.model()), Some(800));
+        let greedy = request
+            .clone()
+            .with_parameters(Parameters::default().max_tokens(5000));
+        model.respond(greedy).await.unwrap();
+        assert_eq!(sent(model.model()), Some(800));
+        let modest = request
+            .clone()
+            .with_parameters(Parameters::default().max_tokens(50));
+        model.respond(modest).await.unwrap();
+        assert_eq!(sent(model.model()), Some(50));
+
+        let limited = model.with_limit(300);
+        limited.respond(request).await.unwrap();
+        assert_eq!(sent(limited.model()), Some(300));
+    }
+
+    #[tokio::test]
+    async fn leaves_overflowing_prompts_unchanged() {
+        let model = FitMaxTokens::new(model()).with_counter(words);
+        let request = Request::new([Message::user("word ".repeat(2000))]);
+        assert_eq!(model.max_tokens(&request), None);
+        model.respond(request).await.unwrap();
+        assert_eq!(sent(model.model()), None);
+    }
+
+    #[test]
+    fn counts_tool_definitions() {
+        let model = FitMaxTokens::new(model())
+            .with_counter(words)
+            .with_margin(100);
+        let request = Request::new([Message::user("word ".repeat(96))]).with_tool(Search);
+        let definitions = words(&request.tools.canonical_json());
+        assert!(definitions > 0);
+        let max = u32::try_from(800 - definitions).unwrap();
+        assert_eq!(model.max_tokens(&request), Some(max));
+    }
+}
@@ -0,0 +1,43 @@
+//! Wrappers adding behavior to any model.
+//!
+//! A middleware wraps a model and implements the same trait, so it can be used wherever
+//! the wrapped model could, and middlewares can be stacked.
+//!
+//! - [`Cached`] serves repeated requests from a [`Cache`].
+//! - [`SemanticCache`] serves responses to similar prompts, compared by embedding.
+//! - [`Retry`] retries transient failures with exponential backoff.
+//! - [`Fallback`] and [`ModelChain`] fail over to other models.
+//! - [`Guarded`] moderates responses before they reach the caller.
+//! - [`Tagged`] reports the provenance of every response.
+//! - [`Defaulted`] applies application-wide [`Defaults`] to every request.
+//! - [`FitMaxTokens`] fits `max_tokens` of every request into the context window.
+//! - [`Budget`] enforces a spending limit, rejecting or downgrading requests beyond it.
+//! - [`Instrumented`] reports the latency, tokens and errors of every call to [`Hooks`].
+//! - [`JailbreakMonitor`] reports prompts matching known jailbreak techniques to [`Hooks`].
+//! - [`RateLimited`] queues requests to stay within requests and tokens per minute.
+
+mod budget;
+mod cache;
+mod defaults;
+mod fallback;
+mod guard;
+mod instrument;
+mod jailbreak;
+mod max_tokens;
+mod rate_limit;
+mod retry;
+mod semantic;
+mod tag;
+
+pub use budget::{Budget, BudgetError, BudgetExceeded, SpendReport};
+pub use cache::{Cache, CacheKey, Cached, LruCache};
+pub use defaults::{Defaulted, Defaults};
+pub use fallback::{Fallback, FallbackError, ModelChain};
+pub use guard::{GuardError, Guarded, StoppedByPolicy};
+pub use instrument::{Call, Hooks, Instrumented, Metrics, Operation};
+pub use jailbreak::{JailbreakAttempt, JailbreakMonitor, JailbreakPattern};
+pub use max_tokens::FitMaxTokens;
+pub use rate_limit::RateLimited;
+pub use retry::{Retry, RetryPolicy, RetryStrategy};
+pub use semantic::SemanticCache;
+pub use tag::Tagged;
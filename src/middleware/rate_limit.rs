@@ -0,0 +1,518 @@
+use alloc::{collections::VecDeque, string::String};
+use core::time::Duration;
+
+use async_stream::stream;
+use futures_core::Stream;
+use futures_lite::{StreamExt, pin};
+use spin::Mutex;
+
+use crate::{
+    LanguageModel,
+    llm::{
+        Message, Request, TextStream,
+        event::StreamEvent,
+        model::Profile,
+        stream::text_stream,
+        token::{Estimator, TokenCounter},
+    },
+    time::{Clock, Timer},
+};
+
+/// The window the limits apply to.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// The milliseconds in [`WINDOW`], the units of a token in a [`Bucket`].
+const MILLIS_PER_TOKEN: u64 = 60_000;
+
+/// A language model sending no more requests and tokens per minute than its limits allow.
+///
+/// Each request is counted when it is sent, against a sliding window of the last minute.
+/// Its tokens are the prompt, counted with [`Estimator`], plus `max_tokens` if set, the
+/// way most providers count them against their own limits. A request that would exceed a
+/// limit waits in a queue until enough of the window has expired; queued requests are
+/// sent in the order they arrived. A single request with more tokens than the limit is
+/// sent alone, once the window is empty.
+///
+/// Providers such as Anthropic instead limit input and output tokens separately, with
+/// token buckets that hold a minute's worth of tokens and replenish continuously.
+/// [`with_input_tokens_per_minute`](RateLimited::with_input_tokens_per_minute) and
+/// [`with_output_tokens_per_minute`](RateLimited::with_output_tokens_per_minute) model
+/// them: the prompt is taken from the input bucket when the request is sent, and the
+/// completion from the output bucket as it streams in, corrected by the provider's
+/// reported usage if any. A request waits until the input bucket holds its prompt and
+/// the output bucket is not empty, so it waits only as long as the buckets need to
+/// replenish rather than for a whole window.
+///
+/// Without limits, requests pass straight through. Errors, including the provider's own
+/// rate limit errors, are passed through too; stack a [`Retry`](super::Retry) on top to
+/// retry them.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{LanguageModel, llm::Request, middleware::RateLimited};
+/// use std::time::Instant;
+///
+/// async fn answer(model: impl LanguageModel) -> ai_types::Result {
+///     let start = Instant::now();
+///     let model = RateLimited::new(model, move || start.elapsed(), tokio::time::sleep)
+///         .with_requests_per_minute(500)
+///         .with_input_tokens_per_minute(30_000)
+///         .with_output_tokens_per_minute(8_000);
+///
+///     Ok(model.respond(Request::oneshot("Be brief", "Hello!")).await?)
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RateLimited<M, C, T> {
+    model: M,
+    clock: C,
+    timer: T,
+    requests_per_minute: Option<u32>,
+    tokens_per_minute: Option<u32>,
+    input_tokens_per_minute: Option<u32>,
+    output_tokens_per_minute: Option<u32>,
+    /// The time and tokens of each request sent in the window, oldest first.
+    window: Mutex<VecDeque<(Duration, u32)>>,
+    input: Mutex<Bucket>,
+    output: Mutex<Bucket>,
+    /// Held by the request at the head of the queue while it waits.
+    queue: async_lock::Mutex<()>,
+}
+
+impl<M: LanguageModel, C: Clock, T: Timer> RateLimited<M, C, T> {
+    /// Wraps `model`, reading the time with `clock` and waiting with `timer`.
+    #[must_use]
+    pub const fn new(model: M, clock: C, timer: T) -> Self {
+        Self {
+            model,
+            clock,
+            timer,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            input_tokens_per_minute: None,
+            output_tokens_per_minute: None,
+            window: Mutex::new(VecDeque::new()),
+            input: Mutex::new(Bucket::new()),
+            output: Mutex::new(Bucket::new()),
+            queue: async_lock::Mutex::new(()),
+        }
+    }
+
+    /// Limits the number of requests sent per minute, at least one.
+    #[must_use]
+    pub const fn with_requests_per_minute(mut self, limit: u32) -> Self {
+        self.requests_per_minute = Some(if limit == 0 { 1 } else { limit });
+        self
+    }
+
+    /// Limits the number of tokens sent per minute.
+    #[must_use]
+    pub const fn with_tokens_per_minute(mut self, limit: u32) -> Self {
+        self.tokens_per_minute = Some(limit);
+        self
+    }
+
+    /// Limits the prompt tokens sent per minute with a token bucket, at least one.
+    #[must_use]
+    pub const fn with_input_tokens_per_minute(mut self, limit: u32) -> Self {
+        self.input_tokens_per_minute = Some(if limit == 0 { 1 } else { limit });
+        self
+    }
+
+    /// Limits the completion tokens received per minute with a token bucket, at least
+    /// one.
+    #[must_use]
+    pub const fn with_output_tokens_per_minute(mut self, limit: u32) -> Self {
+        self.output_tokens_per_minute = Some(if limit == 0 { 1 } else { limit });
+        self
+    }
+
+    /// Returns the wrapped model.
+    #[must_use]
+    pub const fn model(&self) -> &M {
+        &self.model
+    }
+
+    /// Returns the limit on requests per minute, if any.
+    #[must_use]
+    pub const fn requests_per_minute(&self) -> Option<u32> {
+        self.requests_per_minute
+    }
+
+    /// Returns the limit on tokens per minute, if any.
+    #[must_use]
+    pub const fn tokens_per_minute(&self) -> Option<u32> {
+        self.tokens_per_minute
+    }
+
+    /// Returns the limit on prompt tokens per minute, if any.
+    #[must_use]
+    pub const fn input_tokens_per_minute(&self) -> Option<u32> {
+        self.input_tokens_per_minute
+    }
+
+    /// Returns the limit on completion tokens per minute, if any.
+    #[must_use]
+    pub const fn output_tokens_per_minute(&self) -> Option<u32> {
+        self.output_tokens_per_minute
+    }
+
+    /// Returns the prompt tokens left in the input bucket, if it is limited.
+    #[must_use]
+    pub fn input_tokens_available(&self) -> Option<u32> {
+        let limit = self.input_tokens_per_minute?;
+        let mut bucket = self.input.lock();
+        bucket.refill(self.clock.now(), limit);
+        Some(bucket.available(limit))
+    }
+
+    /// Returns the completion tokens left in the output bucket, if it is limited.
+    #[must_use]
+    pub fn output_tokens_available(&self) -> Option<u32> {
+        let limit = self.output_tokens_per_minute?;
+        let mut bucket = self.output.lock();
+        bucket.refill(self.clock.now(), limit);
+        Some(bucket.available(limit))
+    }
+
+    /// Returns the number of requests and tokens counted in the last minute.
+    #[must_use]
+    pub fn usage(&self) -> (u32, u32) {
+        let mut window = self.window.lock();
+        expire(&mut window, self.clock.now());
+        let requests = u32::try_from(window.len()).unwrap_or(u32::MAX);
+        let tokens = window
+            .iter()
+            .fold(0u32, |sum, (_, tokens)| sum.saturating_add(*tokens));
+        (requests, tokens)
+    }
+
+    /// Counts a request of `tokens`, `prompt` of them in the prompt, against the limits
+    /// if it fits, or returns how long to wait before trying again.
+    fn try_admit(&self, prompt: u32, tokens: u32) -> Result<(), Duration> {
+        let now = self.clock.now();
+        let mut window = self.window.lock();
+        expire(&mut window, now);
+        let mut input = self.input.lock();
+        let mut output = self.output.lock();
+        let input_wait = self.input_tokens_per_minute.and_then(|limit| {
+            input.refill(now, limit);
+            input.wait(prompt, limit)
+        });
+        let output_wait = self.output_tokens_per_minute.and_then(|limit| {
+            output.refill(now, limit);
+            output.wait(1, limit)
+        });
+
+        let requests_fit = self
+            .requests_per_minute
+            .is_none_or(|limit| window.len() < limit as usize);
+        let used = window
+            .iter()
+            .fold(0u32, |sum, (_, tokens)| sum.saturating_add(*tokens));
+        let tokens_fit = self
+            .tokens_per_minute
+            .is_none_or(|limit| window.is_empty() || used.saturating_add(tokens) <= limit);
+        if requests_fit && tokens_fit && input_wait.is_none() && output_wait.is_none() {
+            window.push_back((now, tokens));
+            input.take(prompt);
+            return Ok(());
+        }
+        let window_wait = (!(requests_fit && tokens_fit))
+            .then(|| {
+                window
+                    .front()
+                    .map(|(sent, _)| (*sent + WINDOW).saturating_sub(now))
+            })
+            .flatten();
+        Err([window_wait, input_wait, output_wait]
+            .into_iter()
+            .flatten()
+            .max()
+            .unwrap_or(Duration::ZERO))
+    }
+
+    /// Waits for a turn to send a request of `tokens`, `prompt` of them in the prompt.
+    async fn acquire(&self, prompt: u32, tokens: u32) {
+        if self.requests_per_minute.is_none()
+            && self.tokens_per_minute.is_none()
+            && self.input_tokens_per_minute.is_none()
+            && self.output_tokens_per_minute.is_none()
+        {
+            return;
+        }
+        let _turn = self.queue.lock().await;
+        while let Err(delay) = self.try_admit(prompt, tokens) {
+            self.timer.sleep(delay).await;
+        }
+    }
+
+    /// Sends a request of `tokens`, `prompt` of them in the prompt, once the limits allow.
+    ///
+    /// `completion` returns the completion tokens of each item, given those counted so
+    /// far, which are taken from the output bucket.
+    fn limited<I, S>(
+        &self,
+        prompt: u32,
+        tokens: u32,
+        completion: impl Fn(&I, u32) -> u32 + Send,
+        attempt: impl FnOnce() -> S + Send,
+    ) -> impl Stream<Item = Result<I, M::Error>> + Send
+    where
+        I: Send,
+        S: Stream<Item = Result<I, M::Error>> + Send,
+    {
+        stream! {
+            self.acquire(prompt, tokens).await;
+            let items = attempt();
+            pin!(items);
+            let mut counted = 0u32;
+            while let Some(item) = items.next().await {
+                if let (Some(limit), Ok(item)) = (self.output_tokens_per_minute, &item) {
+                    let tokens = completion(item, counted);
+                    counted = counted.saturating_add(tokens);
+                    let mut bucket = self.output.lock();
+                    bucket.refill(self.clock.now(), limit);
+                    bucket.take(tokens);
+                }
+                yield item;
+            }
+        }
+    }
+}
+
+impl<M, C, T> LanguageModel for RateLimited<M, C, T>
+where
+    M: LanguageModel,
+    C: Clock + 'static,
+    T: Timer + 'static,
+{
+    type Error = M::Error;
+
+    fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+        let (prompt, tokens) = request_tokens(&request);
+        text_stream(self.limited(
+            prompt,
+            tokens,
+            |chunk: &String, _| text_tokens(chunk),
+            move || self.model.respond(request),
+        ))
+    }
+
+    fn respond_events(
+        &self,
+        request: Request,
+    ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+        let (prompt, tokens) = request_tokens(&request);
+        self.limited(prompt, tokens, event_tokens, move || {
+            self.model.respond_events(request)
+        })
+    }
+
+    fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+        let tokens = prompt_tokens(&[Message::user(prefix)]);
+        text_stream(self.limited(
+            tokens,
+            tokens,
+            |chunk: &String, _| text_tokens(chunk),
+            move || self.model.complete(prefix),
+        ))
+    }
+
+    fn profile(&self) -> Profile {
+        self.model.profile()
+    }
+}
+
+/// Removes the requests sent more than a minute before `now`.
+fn expire(window: &mut VecDeque<(Duration, u32)>, now: Duration) {
+    while window
+        .front()
+        .is_some_and(|(sent, _)| now.saturating_sub(*sent) >= WINDOW)
+    {
+        window.pop_front();
+    }
+}
+
+/// A token bucket holding a minute's worth of tokens and replenishing continuously.
+///
+/// Tracks the tokens taken from a full bucket in token-milliseconds, so a bucket
+/// refilled at `limit` tokens per minute regains `limit` units every millisecond. The
+/// bucket may go into debt when completions are longer than expected.
+#[derive(Debug)]
+struct Bucket {
+    taken: u64,
+    updated: Duration,
+}
+
+impl Bucket {
+    const fn new() -> Self {
+        Self {
+            taken: 0,
+            updated: Duration::ZERO,
+        }
+    }
+
+    /// Replenishes the bucket for the time elapsed until `now`.
+    fn refill(&mut self, now: Duration, limit: u32) {
+        let elapsed =
+            u64::try_from(now.saturating_sub(self.updated).as_millis()).unwrap_or(u64::MAX);
+        self.taken = self
+            .taken
+            .saturating_sub(elapsed.saturating_mul(u64::from(limit)));
+        self.updated = self.updated.max(now);
+    }
+
+    /// Returns how long to wait until the bucket holds `tokens`, or `None` if it does.
+    ///
+    /// A full bucket always admits, so requests larger than the bucket are not stuck.
+    fn wait(&self, tokens: u32, limit: u32) -> Option<Duration> {
+        let capacity = u64::from(limit) * MILLIS_PER_TOKEN;
+        let needed = self
+            .taken
+            .saturating_add(u64::from(tokens) * MILLIS_PER_TOKEN);
+        (self.taken > 0 && needed > capacity)
+            .then(|| Duration::from_millis((needed - capacity).div_ceil(u64::from(limit))))
+    }
+
+    fn take(&mut self, tokens: u32) {
+        self.taken = self
+            .taken
+            .saturating_add(u64::from(tokens) * MILLIS_PER_TOKEN);
+    }
+
+    fn available(&self, limit: u32) -> u32 {
+        let capacity = u64::from(limit) * MILLIS_PER_TOKEN;
+        u32::try_from(capacity.saturating_sub(self.taken) / MILLIS_PER_TOKEN).unwrap_or(u32::MAX)
+    }
+}
+
+fn prompt_tokens(messages: &[Message]) -> u32 {
+    u32::try_from(Estimator::new().count_message_tokens(messages)).unwrap_or(u32::MAX)
+}
+
+/// Returns the prompt tokens of `request`, and those plus `max_tokens`.
+fn request_tokens(request: &Request) -> (u32, u32) {
+    let prompt = prompt_tokens(&request.messages);
+    (
+        prompt,
+        prompt.saturating_add(request.parameters.max_tokens.unwrap_or(0)),
+    )
+}
+
+fn text_tokens(text: &str) -> u32 {
+    u32::try_from(Estimator::new().count_tokens(text)).unwrap_or(u32::MAX)
+}
+
+/// Counts text as it streams in, then tops it up to the reported usage.
+fn event_tokens(event: &StreamEvent, counted: u32) -> u32 {
+    match event {
+        StreamEvent::Text(text) => text_tokens(text),
+        StreamEvent::Usage(usage) => usage.completion_tokens.saturating_sub(counted),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{llm::model::Parameters, test_util::MockLanguageModel};
+    use alloc::{sync::Arc, vec::Vec};
+    use core::sync::atomic::{AtomicU64, Ordering};
+
+    /// Answers every request with "ok".
+    fn ok() -> MockLanguageModel {
+        MockLanguageModel::new().with_reply("ok")
+    }
+
+    type Sleeps = Arc<Mutex<Vec<Duration>>>;
+
+    /// A clock in milliseconds, advanced only by the timer, which records its sleeps.
+    fn simulated() -> (
+        Sleeps,
+        impl Fn() -> Duration + Send + Sync + 'static,
+        impl Fn(Duration) -> core::future::Ready<()> + Send + Sync + 'static,
+    ) {
+        let millis = Arc::new(AtomicU64::new(0));
+        let sleeps = Arc::new(Mutex::new(Vec::new()));
+        let (now, recorded) = (millis.clone(), sleeps.clone());
+        let clock = move || Duration::from_millis(now.load(Ordering::Relaxed));
+        let timer = move |delay: Duration| {
+            recorded.lock().push(delay);
+            let delay = u64::try_from(delay.as_millis()).unwrap_or(u64::MAX);
+            millis.fetch_add(delay, Ordering::Relaxed);
+            core::future::ready(())
+        };
+        (sleeps, clock, timer)
+    }
+
+    #[tokio::test]
+    async fn waits_for_the_request_window() {
+        let (sleeps, clock, timer) = simulated();
+        let model = RateLimited::new(ok(), clock, timer).with_requests_per_minute(2);
+
+        for _ in 0..3 {
+            assert_eq!(model.respond(Request::default()).await.unwrap(), "ok");
+        }
+        assert_eq!(*sleeps.lock(), [WINDOW]);
+        assert_eq!(model.usage().0, 1);
+    }
+
+    #[tokio::test]
+    async fn waits_for_the_token_window() {
+        let (sleeps, clock, timer) = simulated();
+        let model = RateLimited::new(ok(), clock, timer).with_tokens_per_minute(100);
+        let request = Request::oneshot("Be brief", "Hi")
+            .with_parameters(Parameters::default().max_tokens(60));
+        let tokens = request_tokens(&request).1;
+
+        assert_eq!(model.respond(request.clone()).await.unwrap(), "ok");
+        assert_eq!(model.usage(), (1, tokens));
+        assert_eq!(model.respond(request).await.unwrap(), "ok");
+        assert_eq!(*sleeps.lock(), [WINDOW]);
+
+        let oversized = Request::oneshot("Be brief", "Hi")
+            .with_parameters(Parameters::default().max_tokens(1000));
+        assert_eq!(model.respond(oversized).await.unwrap(), "ok");
+        assert_eq!(sleeps.lock().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn replenishes_token_buckets() {
+        let request = Request::new([Message::user("word ".repeat(30))]);
+        let (prompt, _) = request_tokens(&request);
+        assert!(prompt > 30 && prompt <= 60);
+
+        // The input bucket refills at one token per second, just enough for the prompt.
+        let (sleeps, clock, timer) = simulated();
+        let model = RateLimited::new(ok(), clock, timer).with_input_tokens_per_minute(60);
+        assert_eq!(model.respond(request.clone()).await.unwrap(), "ok");
+        assert_eq!(model.input_tokens_available(), Some(60 - prompt));
+        assert_eq!(model.respond(request.clone()).await.unwrap(), "ok");
+        let missing = u64::from(2 * prompt - 60);
+        assert_eq!(*sleeps.lock(), [Duration::from_secs(missing)]);
+
+        // The output bucket goes into debt for the completion, refilled at one token per
+        // minute.
+        let (sleeps, clock, timer) = simulated();
+        let model = RateLimited::new(ok(), clock, timer).with_output_tokens_per_minute(1);
+        assert_eq!(model.respond(request.clone()).await.unwrap(), "ok");
+        assert_eq!(model.output_tokens_available(), Some(0));
+        assert_eq!(model.respond(request).await.unwrap(), "ok");
+        assert_eq!(*sleeps.lock(), [WINDOW * text_tokens("ok")]);
+    }
+
+    #[test]
+    fn bucket_waits_only_for_the_missing_tokens() {
+        let mut bucket = Bucket::new();
+        assert_eq!(bucket.wait(1000, 60), None);
+        bucket.take(50);
+        assert_eq!(bucket.wait(10, 60), None);
+        assert_eq!(bucket.wait(20, 60), Some(Duration::from_secs(10)));
+
+        bucket.refill(Duration::from_secs(10), 60);
+        assert_eq!(bucket.available(60), 20);
+        assert_eq!(bucket.wait(20, 60), None);
+    }
+}
@@ -0,0 +1,668 @@
+use alloc::{format, string::String};
+use core::time::Duration;
+
+use async_stream::stream;
+use futures_core::Stream;
+use futures_lite::{StreamExt, pin};
+
+use crate::{
+    LanguageModel,
+    error::{Classify, ErrorKind},
+    llm::{
+        Message, Request, TextStream, event::StreamEvent, model::Profile, prompts,
+        stream::text_stream,
+    },
+    rng::SplitMix64,
+    time::Timer,
+};
+
+/// A language model retrying transient failures with exponential backoff.
+///
+/// A failure is retried when the policy has a [`RetryStrategy`] for its [`ErrorKind`] and
+/// it happens before the first chunk of the response. By default, only
+/// [transient](ErrorKind::is_transient) failures are retried. Once text has been streamed
+/// to the caller, a retry would repeat it, so later failures are passed through.
+///
+/// With [continuation](RetryPolicy::with_continuation) enabled, a response failing
+/// mid-stream is recovered instead: the request is sent again with the partial response
+/// and an instruction to continue from where it stopped, and the continuation is streamed
+/// after the text the caller already received, as if the response had never failed. This
+/// applies to [`respond`](LanguageModel::respond) and
+/// [`respond_events`](LanguageModel::respond_events), as long as the failed attempt only
+/// streamed text: an attempt that already streamed a tool call, usage or any other event
+/// is not continued, since the continuation would stream it again.
+///
+/// Each attempt sends the same request, including its
+/// [idempotency key](crate::llm::Request::with_idempotency_key), so providers supporting
+/// idempotency do not execute a retried request twice.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{
+///     LanguageModel,
+///     error::Classify,
+///     llm::Request,
+///     middleware::{Retry, RetryPolicy},
+/// };
+/// use core::time::Duration;
+///
+/// async fn answer<M>(model: M) -> ai_types::Result
+/// where
+///     M: LanguageModel<Error: Classify>,
+/// {
+///     let policy = RetryPolicy::default()
+///         .with_max_retries(5)
+///         .with_initial_backoff(Duration::from_millis(200));
+///     let model = Retry::new(model, tokio::time::sleep).with_policy(policy);
+///
+///     Ok(model.respond(Request::oneshot("Be brief", "Hello!")).await?)
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Retry<M, T> {
+    model: M,
+    timer: T,
+    policy: RetryPolicy,
+    random: SplitMix64,
+}
+
+impl<M: LanguageModel, T: Timer> Retry<M, T> {
+    /// Wraps `model`, waiting between attempts with `timer`.
+    #[must_use]
+    pub fn new(model: M, timer: T) -> Self {
+        Self {
+            model,
+            timer,
+            policy: RetryPolicy::default(),
+            random: SplitMix64::new(0x5EED),
+        }
+    }
+
+    /// Sets the retry policy.
+    #[must_use]
+    pub const fn with_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Returns the wrapped model.
+    #[must_use]
+    pub const fn model(&self) -> &M {
+        &self.model
+    }
+
+    /// Returns the retry policy.
+    #[must_use]
+    pub const fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+}
+
+impl<M, T> Retry<M, T>
+where
+    M: LanguageModel<Error: Classify>,
+    T: Timer,
+{
+    /// Streams the items of `attempt`, retrying failures.
+    ///
+    /// `attempt` is given the text streamed so far, which is only collected if `continuable`
+    /// and continuation is enabled.
+    fn retrying<I, S>(
+        &self,
+        continuable: bool,
+        mut attempt: impl FnMut(&str) -> S + Send,
+    ) -> impl Stream<Item = Result<I, M::Error>> + Send
+    where
+        I: Chunk + Send,
+        S: Stream<Item = Result<I, M::Error>> + Send,
+    {
+        let continues = continuable && self.policy.continuation;
+        stream! {
+            let mut retries = 0;
+            let mut partial = String::new();
+            loop {
+                let items = attempt(&partial);
+                pin!(items);
+
+                let mut started = false;
+                // Continuing is only safe after text: other items, such as tool calls,
+                // would be emitted again by the continuation.
+                let mut text_only = true;
+                let mut failure = None;
+                while let Some(item) = items.next().await {
+                    match item {
+                        Ok(item) => {
+                            started = true;
+                            match item.text() {
+                                Some(text) if continues => partial.push_str(text),
+                                Some(_) => {}
+                                None => text_only = false,
+                            }
+                            yield Ok(item);
+                        }
+                        Err(error)
+                            if (!started || (text_only && !partial.is_empty()))
+                                && retries < self.policy.max_retries
+                                && self.policy.strategy(error.kind()) != RetryStrategy::Never =>
+                        {
+                            failure = Some(error);
+                            break;
+                        }
+                        Err(error) => {
+                            yield Err(error);
+                            return;
+                        }
+                    }
+                }
+
+                let Some(error) = failure else {
+                    return;
+                };
+                let delay = match self.policy.strategy(error.kind()) {
+                    RetryStrategy::Fixed(delay) => delay,
+                    RetryStrategy::Backoff => self.policy.jittered(retries, self.random.next_f64()),
+                    _ => error
+                        .retry_after()
+                        .unwrap_or_else(|| self.policy.jittered(retries, self.random.next_f64())),
+                };
+                retries += 1;
+                self.timer.sleep(delay).await;
+            }
+        }
+    }
+}
+
+impl<M, T> LanguageModel for Retry<M, T>
+where
+    M: LanguageModel<Error: Classify>,
+    T: Timer + 'static,
+{
+    type Error = M::Error;
+
+    fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+        text_stream(self.retrying(true, move |partial| {
+            self.model.respond(continuation(&request, partial))
+        }))
+    }
+
+    fn respond_events(
+        &self,
+        request: Request,
+    ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+        self.retrying(true, move |partial| {
+            self.model.respond_events(continuation(&request, partial))
+        })
+    }
+
+    fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+        text_stream(self.retrying(false, move |_| self.model.complete(prefix)))
+    }
+
+    fn profile(&self) -> Profile {
+        self.model.profile()
+    }
+}
+
+/// Returns `request`, continuing the `partial` response if there is one.
+fn continuation(request: &Request, partial: &str) -> Request {
+    let mut request = request.clone();
+    if partial.is_empty() {
+        return request;
+    }
+    request.messages.push(Message::assistant(partial));
+    request
+        .messages
+        .push(Message::user(prompts::continue_response()));
+    // The continuation is a new request, which providers must not deduplicate.
+    request.idempotency_key = request
+        .idempotency_key
+        .map(|key| format!("{key}:continue-{}", partial.len()));
+    request
+}
+
+/// An item of a response stream, whose text a continuation picks up from.
+trait Chunk {
+    fn text(&self) -> Option<&str>;
+}
+
+impl Chunk for String {
+    fn text(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl Chunk for StreamEvent {
+    fn text(&self) -> Option<&str> {
+        match self {
+            Self::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// How [`Retry`] handles failures of one [`ErrorKind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RetryStrategy {
+    /// Never retry.
+    Never,
+    /// Wait for the delay requested by the provider through [`Classify::retry_after`],
+    /// falling back to exponential backoff when it requests none.
+    RetryAfter,
+    /// Wait with exponential backoff, ignoring any delay requested by the provider.
+    Backoff,
+    /// Wait a fixed delay.
+    Fixed(Duration),
+}
+
+/// The number of [`ErrorKind`] variants.
+const KINDS: usize = 7;
+
+/// How [`Retry`] spaces out its attempts.
+///
+/// The `n`-th retry waits `initial_backoff * multiplier^n`, capped at `max_backoff`, and
+/// randomly shortened or lengthened by up to `jitter` of the delay so that many clients
+/// failing together do not retry in lockstep.
+///
+/// Each [`ErrorKind`] has its own [`RetryStrategy`]. Transient kinds use
+/// [`RetryStrategy::RetryAfter`] and every other kind [`RetryStrategy::Never`], until
+/// changed with [`with_strategy`](Self::with_strategy):
+///
+/// ```rust
+/// use ai_types::{error::ErrorKind, middleware::{RetryPolicy, RetryStrategy}};
+/// use core::time::Duration;
+///
+/// let policy = RetryPolicy::default()
+///     .with_strategy(ErrorKind::ProviderUnavailable, RetryStrategy::Backoff)
+///     .with_strategy(ErrorKind::Timeout, RetryStrategy::Fixed(Duration::from_secs(1)))
+///     .with_strategy(ErrorKind::ContentFiltered, RetryStrategy::Never);
+/// assert_eq!(policy.strategy(ErrorKind::RateLimited), RetryStrategy::RetryAfter);
+/// assert_eq!(policy.strategy(ErrorKind::InvalidRequest), RetryStrategy::Never);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    strategies: [RetryStrategy; KINDS],
+    continuation: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        let mut strategies = [RetryStrategy::Never; KINDS];
+        for kind in [
+            ErrorKind::RateLimited,
+            ErrorKind::Timeout,
+            ErrorKind::ProviderUnavailable,
+        ] {
+            strategies[kind_index(kind)] = RetryStrategy::RetryAfter;
+        }
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+            strategies,
+            continuation: false,
+        }
+    }
+}
+
+/// Returns the position of `kind` in [`RetryPolicy::strategies`].
+const fn kind_index(kind: ErrorKind) -> usize {
+    match kind {
+        ErrorKind::RateLimited => 0,
+        ErrorKind::Timeout => 1,
+        ErrorKind::ProviderUnavailable => 2,
+        ErrorKind::InvalidRequest => 3,
+        ErrorKind::Authentication => 4,
+        ErrorKind::ContentFiltered => 5,
+        ErrorKind::Other => 6,
+    }
+}
+
+impl RetryPolicy {
+    /// Sets how failures of `kind` are retried.
+    #[must_use]
+    pub const fn with_strategy(mut self, kind: ErrorKind, strategy: RetryStrategy) -> Self {
+        self.strategies[kind_index(kind)] = strategy;
+        self
+    }
+
+    /// Returns how failures of `kind` are retried.
+    #[must_use]
+    pub const fn strategy(&self, kind: ErrorKind) -> RetryStrategy {
+        self.strategies[kind_index(kind)]
+    }
+
+    /// Sets the maximum number of retries after the first attempt.
+    #[must_use]
+    pub const fn with_max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Sets the delay before the first retry.
+    #[must_use]
+    pub const fn with_initial_backoff(mut self, delay: Duration) -> Self {
+        self.initial_backoff = delay;
+        self
+    }
+
+    /// Sets the longest delay between two attempts.
+    #[must_use]
+    pub const fn with_max_backoff(mut self, delay: Duration) -> Self {
+        self.max_backoff = delay;
+        self
+    }
+
+    /// Sets the factor by which the delay grows after each retry.
+    #[must_use]
+    pub const fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Sets the maximum random deviation, as a fraction of the delay between 0 and 1.
+    ///
+    /// Values outside that range are clamped to it, and NaN disables jitter.
+    #[must_use]
+    pub const fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = if jitter.is_nan() {
+            0.0
+        } else {
+            jitter.clamp(0.0, 1.0)
+        };
+        self
+    }
+
+    /// Sets whether responses failing after text was streamed are continued by a new
+    /// request, instead of failing. Disabled by default.
+    ///
+    /// See [`Retry`] for details.
+    #[must_use]
+    pub const fn with_continuation(mut self, enabled: bool) -> Self {
+        self.continuation = enabled;
+        self
+    }
+
+    /// Returns whether responses failing mid-stream are continued.
+    #[must_use]
+    pub const fn continuation(&self) -> bool {
+        self.continuation
+    }
+
+    /// Returns the maximum number of retries.
+    #[must_use]
+    pub const fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Returns the delay before retry number `retry`, counting from zero, without jitter.
+    #[must_use]
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let max = self.max_backoff.as_secs_f64();
+        let mut delay = self.initial_backoff.as_secs_f64();
+        for _ in 0..retry {
+            if delay >= max {
+                break;
+            }
+            delay *= self.multiplier;
+        }
+        Duration::try_from_secs_f64(delay.min(max)).unwrap_or(self.max_backoff)
+    }
+
+    /// Applies jitter to [`backoff`](Self::backoff), `random` being uniform in `[0, 1)`.
+    fn jittered(&self, retry: u32, random: f64) -> Duration {
+        let factor = 1.0 + self.jitter * (2.0 * random - 1.0);
+        let delay = self.backoff(retry).as_secs_f64() * factor;
+        Duration::try_from_secs_f64(delay).unwrap_or(Duration::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::{ErrorKind, ProviderError},
+        llm::tool::ToolCall,
+        test_util::{MockLanguageModel, MockReply},
+    };
+    use alloc::{string::ToString, sync::Arc, vec, vec::Vec};
+    use spin::Mutex;
+
+    /// Fails the first attempts with the given errors, midway if flagged, then answers.
+    fn failing(failures: impl IntoIterator<Item = (ProviderError, bool)>) -> MockLanguageModel {
+        let replies: Vec<MockReply> = failures
+            .into_iter()
+            .map(|(error, midway)| {
+                if midway {
+                    MockReply::text("partial").then_fail(error)
+                } else {
+                    MockReply::error(error)
+                }
+            })
+            .collect();
+        let answer = MockReply::chunks(["attempt ".to_string(), replies.len().to_string()]);
+        MockLanguageModel::new()
+            .with_replies(replies)
+            .with_reply(answer)
+    }
+
+    fn recording_timer() -> (
+        Arc<Mutex<Vec<Duration>>>,
+        impl Fn(Duration) -> core::future::Ready<()> + Send + Sync + 'static,
+    ) {
+        let sleeps = Arc::new(Mutex::new(Vec::new()));
+        let recorded = sleeps.clone();
+        let timer = move |delay| {
+            recorded.lock().push(delay);
+            core::future::ready(())
+        };
+        (sleeps, timer)
+    }
+
+    fn error(kind: ErrorKind) -> ProviderError {
+        ProviderError::new(kind, "failed")
+    }
+
+    #[tokio::test]
+    async fn retries_transient_failures_with_backoff() {
+        let (sleeps, timer) = recording_timer();
+        let model = failing([
+            (error(ErrorKind::ProviderUnavailable), false),
+            (error(ErrorKind::Timeout), false),
+        ]);
+        let policy = RetryPolicy::default()
+            .with_initial_backoff(Duration::from_secs(1))
+            .with_jitter(0.0);
+        let model = Retry::new(model, timer).with_policy(policy);
+
+        assert_eq!(
+            model.respond(Request::default()).await.unwrap(),
+            "attempt 2"
+        );
+        assert_eq!(
+            *sleeps.lock(),
+            [Duration::from_secs(1), Duration::from_secs(2)]
+        );
+    }
+
+    #[tokio::test]
+    async fn honors_retry_after() {
+        let (sleeps, timer) = recording_timer();
+        let limited = error(ErrorKind::RateLimited).with_retry_after(Duration::from_secs(7));
+        let model = Retry::new(failing([(limited, false)]), timer);
+
+        assert!(model.respond(Request::default()).await.is_ok());
+        assert_eq!(*sleeps.lock(), [Duration::from_secs(7)]);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_permanent_or_late_failures() {
+        let (sleeps, timer) = recording_timer();
+        let model = Retry::new(failing([(error(ErrorKind::InvalidRequest), false)]), timer);
+        let failure = model.respond(Request::default()).await.unwrap_err();
+        assert_eq!(failure.kind, ErrorKind::InvalidRequest);
+        assert!(sleeps.lock().is_empty());
+
+        let (sleeps, timer) = recording_timer();
+        let model = Retry::new(failing([(error(ErrorKind::Timeout), true)]), timer);
+        assert!(model.respond(Request::default()).await.is_err());
+        assert!(sleeps.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn continues_responses_failing_mid_stream() {
+        let (sleeps, timer) = recording_timer();
+        let model = failing([(error(ErrorKind::Timeout), true)]);
+        let policy = RetryPolicy::default().with_continuation(true);
+        let model = Retry::new(model, timer).with_policy(policy);
+        let request = Request::oneshot("Be brief", "Hello!").with_idempotency_key("key");
+
+        assert_eq!(model.respond(request).await.unwrap(), "partialattempt 1");
+        assert_eq!(sleeps.lock().len(), 1);
+        let requests = model.model().requests();
+        let continued = &requests[1];
+        assert_eq!(continued.messages.len(), 4);
+        assert_eq!(continued.messages[2].role(), crate::llm::Role::Assistant);
+        assert_eq!(continued.messages[2].content(), "partial");
+        assert_eq!(
+            continued.messages[3].content(),
+            prompts::continue_response()
+        );
+        assert_eq!(continued.idempotency_key.as_deref(), Some("key:continue-7"));
+    }
+
+    #[tokio::test]
+    async fn continues_events_only_after_text() {
+        let timeout = || error(ErrorKind::Timeout);
+        let policy = RetryPolicy::default().with_continuation(true);
+        let text_only = MockLanguageModel::new().with_replies([
+            MockReply::chunks(["partial"]).then_fail(timeout()),
+            "rest".into(),
+        ]);
+        let model = Retry::new(text_only, recording_timer().1).with_policy(policy);
+        let events: Vec<_> = model
+            .respond_events(Request::oneshot("Be brief", "Hello!"))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(
+            events,
+            [
+                StreamEvent::Text("partial".into()),
+                StreamEvent::Text("rest".into()),
+                StreamEvent::Done
+            ]
+        );
+
+        let call = ToolCall::new("call-1", "search", "{}");
+        let with_call = MockLanguageModel::new().with_replies([
+            MockReply::chunks(["partial"])
+                .with_tool_call(call.clone())
+                .then_fail(timeout()),
+            "rest".into(),
+        ]);
+        let (sleeps, timer) = recording_timer();
+        let model = Retry::new(with_call, timer).with_policy(policy);
+        let events: Vec<_> = model
+            .respond_events(Request::oneshot("Be brief", "Hello!"))
+            .collect()
+            .await;
+        assert_eq!(
+            events,
+            [
+                Ok(StreamEvent::Text("partial".into())),
+                Ok(StreamEvent::ToolCall(call)),
+                Err(timeout())
+            ]
+        );
+        assert!(sleeps.lock().is_empty());
+        assert_eq!(model.model().requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let (sleeps, timer) = recording_timer();
+        let model = failing(vec![(error(ErrorKind::Timeout), false); 5]);
+        let policy = RetryPolicy::default().with_max_retries(2);
+        let model = Retry::new(model, timer).with_policy(policy);
+
+        assert!(model.respond(Request::default()).await.is_err());
+        assert_eq!(sleeps.lock().len(), 2);
+        assert_eq!(model.model().requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn strategies_apply_per_kind() {
+        let (sleeps, timer) = recording_timer();
+        let limited = error(ErrorKind::RateLimited).with_retry_after(Duration::from_secs(7));
+        let model = failing([
+            (limited, false),
+            (error(ErrorKind::Other), false),
+            (error(ErrorKind::Timeout), false),
+        ]);
+        let policy = RetryPolicy::default()
+            .with_jitter(0.0)
+            .with_initial_backoff(Duration::from_secs(1))
+            .with_strategy(ErrorKind::RateLimited, RetryStrategy::Backoff)
+            .with_strategy(
+                ErrorKind::Other,
+                RetryStrategy::Fixed(Duration::from_secs(3)),
+            );
+        let model = Retry::new(model, timer).with_policy(policy);
+
+        assert!(model.respond(Request::default()).await.is_ok());
+        assert_eq!(
+            *sleeps.lock(),
+            [
+                Duration::from_secs(1),
+                Duration::from_secs(3),
+                Duration::from_secs(4)
+            ]
+        );
+
+        let (sleeps, timer) = recording_timer();
+        let policy = RetryPolicy::default().with_strategy(ErrorKind::Timeout, RetryStrategy::Never);
+        let model =
+            Retry::new(failing([(error(ErrorKind::Timeout), false)]), timer).with_policy(policy);
+        assert!(model.respond(Request::default()).await.is_err());
+        assert!(sleeps.lock().is_empty());
+    }
+
+    #[test]
+    fn backoff_grows_and_caps() {
+        let policy = RetryPolicy::default()
+            .with_initial_backoff(Duration::from_secs(1))
+            .with_max_backoff(Duration::from_secs(5));
+        assert_eq!(policy.backoff(0), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(4));
+        assert_eq!(policy.backoff(3), Duration::from_secs(5));
+        assert_eq!(policy.backoff(1000), Duration::from_secs(5));
+
+        let jittered = policy.jittered(0, 0.0);
+        assert_eq!(jittered, Duration::from_millis(800));
+    }
+
+    #[test]
+    fn jitter_is_normalized() {
+        let policy = RetryPolicy::default().with_initial_backoff(Duration::from_secs(1));
+        assert_eq!(
+            policy.with_jitter(f64::NAN).jittered(0, 0.0),
+            Duration::from_secs(1)
+        );
+        assert_eq!(
+            policy.with_jitter(f64::INFINITY).jittered(0, 0.75),
+            Duration::from_millis(1500)
+        );
+
+        let unbounded = policy.with_max_backoff(Duration::MAX);
+        assert_eq!(unbounded.jittered(200, 0.99), Duration::MAX);
+    }
+}
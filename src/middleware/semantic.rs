@@ -0,0 +1,394 @@
+use alloc::{borrow::Cow, collections::VecDeque, format, string::String, sync::Arc, vec::Vec};
+use core::{fmt, time::Duration};
+
+use async_stream::stream;
+use futures_core::Stream;
+use futures_lite::{StreamExt, pin};
+use spin::Mutex;
+
+use crate::{
+    EmbeddingModel, LanguageModel,
+    embedding::{Embedding, cosine_similarity},
+    llm::{Request, TextStream, event::StreamEvent, model::Profile, stream::text_stream},
+    time::Clock,
+};
+
+/// The default minimum similarity for a cache hit.
+const DEFAULT_THRESHOLD: f32 = 0.95;
+
+type Namespace = dyn Fn(&Request) -> String + Send + Sync;
+
+/// A language model reusing responses to similar prompts.
+///
+/// [`Cached`](super::Cached) only serves requests identical to earlier ones, but users
+/// phrase the same question in many ways: "How do I reset my password?" and "I forgot my
+/// password, what do I do?" deserve the same answer. A semantic cache embeds the last
+/// message of each request, and serves the cached response of the most similar earlier
+/// message if their [cosine similarity](cosine_similarity) reaches a threshold, `0.95` by
+/// default.
+///
+/// Only requests sharing everything but their last message are compared: the same
+/// earlier messages, parameters, tools, response format and namespace. Namespaces isolate
+/// tenants or features sharing a cache, and are derived from each request by the function
+/// given to [`with_namespace`](Self::with_namespace). Entries can expire after a time to
+/// live, and the oldest entries are evicted beyond the capacity.
+///
+/// Only complete, successful responses are cached. If embedding fails, the request is
+/// passed through uncached. [`respond_events`](LanguageModel::respond_events),
+/// [`respond_n`](LanguageModel::respond_n) and [`complete`](LanguageModel::complete) are
+/// never cached.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{EmbeddingModel, LanguageModel, llm::Request, middleware::SemanticCache};
+/// use std::time::{Duration, Instant};
+///
+/// async fn answer(
+///     model: impl LanguageModel,
+///     embedder: impl EmbeddingModel + Send + Sync + 'static,
+/// ) -> ai_types::Result {
+///     let start = Instant::now();
+///     let model = SemanticCache::new(model, embedder, 10_000)
+///         .with_threshold(0.92)
+///         .with_ttl(Duration::from_secs(24 * 3600), move || start.elapsed());
+///
+///     let ask = |question: &str| Request::oneshot("You are a support agent.", question);
+///     model.respond(ask("How do I reset my password?")).await?;
+///     // Likely served from the cache.
+///     Ok(model.respond(ask("How can I reset my password?")).await?)
+/// }
+/// ```
+pub struct SemanticCache<M, E> {
+    model: M,
+    embedder: E,
+    threshold: f32,
+    capacity: usize,
+    ttl: Option<(Duration, Arc<dyn Clock>)>,
+    namespace: Option<Arc<Namespace>>,
+    entries: Mutex<VecDeque<Entry>>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    scope: Scope,
+    embedding: Embedding,
+    response: String,
+    expires: Option<Duration>,
+}
+
+/// What requests must share for their responses to be compared.
+#[derive(Debug, PartialEq, Eq)]
+struct Scope {
+    namespace: Option<String>,
+    context: String,
+}
+
+impl<M: fmt::Debug, E: fmt::Debug> fmt::Debug for SemanticCache<M, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SemanticCache")
+            .field("model", &self.model)
+            .field("embedder", &self.embedder)
+            .field("threshold", &self.threshold)
+            .field("capacity", &self.capacity)
+            .field("ttl", &self.ttl.as_ref().map(|(ttl, _)| ttl))
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M, E> SemanticCache<M, E> {
+    /// Wraps `model`, embedding prompts with `embedder` and caching up to `capacity`
+    /// responses.
+    #[must_use]
+    pub const fn new(model: M, embedder: E, capacity: usize) -> Self {
+        Self {
+            model,
+            embedder,
+            threshold: DEFAULT_THRESHOLD,
+            capacity,
+            ttl: None,
+            namespace: None,
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Sets the minimum cosine similarity for a cache hit.
+    #[must_use]
+    pub const fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Expires entries `ttl` after they were cached, as measured with `clock`.
+    #[must_use]
+    pub fn with_ttl(mut self, ttl: Duration, clock: impl Clock + 'static) -> Self {
+        self.ttl = Some((ttl, Arc::new(clock)));
+        self
+    }
+
+    /// Derives the namespace of each request with `namespace`.
+    ///
+    /// Requests in different namespaces never share responses.
+    #[must_use]
+    pub fn with_namespace(
+        mut self,
+        namespace: impl Fn(&Request) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.namespace = Some(Arc::new(namespace));
+        self
+    }
+
+    /// Returns the wrapped model.
+    #[must_use]
+    pub const fn model(&self) -> &M {
+        &self.model
+    }
+
+    /// Returns the number of cached responses, including expired ones not evicted yet.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    /// Returns whether the cache is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes every cached response.
+    pub fn clear(&self) {
+        self.entries.lock().clear();
+    }
+
+    /// Returns everything but the last message of `request`, with its namespace.
+    fn scope(&self, request: &Request) -> Scope {
+        let history = request.messages.split_last().map_or(&[][..], |(_, h)| h);
+        Scope {
+            namespace: self.namespace.as_ref().map(|namespace| namespace(request)),
+            context: format!(
+                "{history:?}\0{:?}\0{}\0{:?}",
+                request.parameters,
+                request.tools.canonical_json(),
+                request.response_format
+            ),
+        }
+    }
+
+    fn now(&self) -> Option<Duration> {
+        self.ttl.as_ref().map(|(_, clock)| clock.now())
+    }
+
+    fn lookup(&self, scope: &Scope, embedding: &[f32]) -> Option<String> {
+        let now = self.now();
+        let mut entries = self.entries.lock();
+        entries.retain(|entry| entry.expires.is_none_or(|expires| Some(expires) > now));
+        entries
+            .iter()
+            .filter(|entry| entry.scope == *scope)
+            .map(|entry| (cosine_similarity(embedding, &entry.embedding), entry))
+            .filter(|(similarity, _)| *similarity >= self.threshold)
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, entry)| entry.response.clone())
+    }
+
+    fn insert(&self, scope: Scope, embedding: Embedding, response: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        let expires = self
+            .ttl
+            .as_ref()
+            .map(|(ttl, clock)| clock.now().saturating_add(*ttl));
+        let mut entries = self.entries.lock();
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(Entry {
+            scope,
+            embedding,
+            response,
+            expires,
+        });
+    }
+}
+
+impl<M, E> LanguageModel for SemanticCache<M, E>
+where
+    M: LanguageModel,
+    E: EmbeddingModel + Send + Sync + 'static,
+{
+    type Error = M::Error;
+
+    fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+        let scope = self.scope(&request);
+        let prompt = request
+            .messages
+            .last()
+            .map_or(Cow::Borrowed(""), |message| message.content())
+            .into_owned();
+        text_stream(stream! {
+            let embedding = self.embedder.embed(&prompt).await.ok();
+            if let Some(hit) = embedding.as_ref().and_then(|e| self.lookup(&scope, e)) {
+                yield Ok(hit);
+                return;
+            }
+
+            let chunks = self.model.respond(request);
+            pin!(chunks);
+            let mut text = String::new();
+            while let Some(chunk) = chunks.next().await {
+                match chunk {
+                    Ok(chunk) => {
+                        text.push_str(&chunk);
+                        yield Ok(chunk);
+                    }
+                    Err(error) => {
+                        yield Err(error);
+                        return;
+                    }
+                }
+            }
+            if let Some(embedding) = embedding {
+                self.insert(scope, embedding, text);
+            }
+        })
+    }
+
+    fn respond_events(
+        &self,
+        request: Request,
+    ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+        self.model.respond_events(request)
+    }
+
+    fn respond_n(&self, request: Request) -> Vec<impl TextStream<Error = Self::Error> + Send> {
+        self.model.respond_n(request)
+    }
+
+    fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+        self.model.complete(prefix)
+    }
+
+    fn profile(&self) -> Profile {
+        self.model.profile()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        llm::{Message, model::Ability},
+        test_util::MockLanguageModel,
+    };
+    use alloc::vec;
+    use core::sync::atomic::{AtomicU64, Ordering};
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+
+    /// Answers the `n`th request with "answer n".
+    fn counting() -> MockLanguageModel {
+        MockLanguageModel::new().with_replies((0..3).map(|call| format!("answer {call}")))
+    }
+
+    /// Embeds texts by whether they mention passwords or billing.
+    struct Topics;
+
+    impl EmbeddingModel for Topics {
+        fn dim(&self) -> usize {
+            2
+        }
+
+        async fn embed(&self, text: &str) -> crate::Result<Vec<f32>> {
+            let text = text.to_lowercase();
+            Ok(vec![
+                f32::from(u8::from(text.contains("password"))),
+                f32::from(u8::from(text.contains("bill"))),
+            ])
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_similar_prompts_from_cache() {
+        let model = SemanticCache::new(counting(), Topics, 10);
+        let ask = |question: &str| Request::oneshot("Support", question);
+
+        let first = model.respond(ask("Reset my password")).await.unwrap();
+        assert_eq!(first, "answer 0");
+        let similar = model.respond(ask("I forgot my PASSWORD")).await.unwrap();
+        assert_eq!(similar, first);
+
+        let other = model.respond(ask("Where is my bill?")).await.unwrap();
+        assert_eq!(other, "answer 1");
+        let context = Request::oneshot("Sales", "Reset my password");
+        assert_eq!(model.respond(context).await.unwrap(), "answer 2");
+        assert_eq!(model.len(), 3);
+        assert_eq!(model.model().requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn isolates_namespaces_and_expires_entries() {
+        let time = Arc::new(AtomicU64::new(0));
+        let clock = time.clone();
+        let model = SemanticCache::new(counting(), Topics, 10)
+            // The application prefixes each question with the asking tenant.
+            .with_namespace(|request| {
+                let question = request.messages.last().map(Message::content);
+                let tenant = question
+                    .as_deref()
+                    .and_then(|question| question.split_once(':'));
+                tenant.map(|(tenant, _)| tenant.into()).unwrap_or_default()
+            })
+            .with_ttl(Duration::from_secs(60), move || {
+                Duration::from_secs(clock.load(Ordering::Relaxed))
+            });
+        let ask = |question: &str| Request::new([Message::user(question)]);
+
+        assert_eq!(
+            model.respond(ask("a: password?")).await.unwrap(),
+            "answer 0"
+        );
+        assert_eq!(
+            model.respond(ask("b: password?")).await.unwrap(),
+            "answer 1"
+        );
+        assert_eq!(
+            model.respond(ask("a: my password?")).await.unwrap(),
+            "answer 0"
+        );
+
+        time.store(61, Ordering::Relaxed);
+        assert_eq!(
+            model.respond(ask("a: password?")).await.unwrap(),
+            "answer 2"
+        );
+        assert_eq!(model.len(), 1);
+    }
+
+    #[derive(Debug, PartialEq, JsonSchema, Deserialize)]
+    struct Reset {
+        steps: Vec<String>,
+    }
+
+    #[derive(Debug, PartialEq, JsonSchema, Deserialize)]
+    struct Link {
+        url: String,
+    }
+
+    #[tokio::test]
+    async fn scopes_include_the_response_format() {
+        let model = MockLanguageModel::new()
+            .with_profile(Profile::new("mock", "", 4096).with_ability(Ability::StructuredOutput))
+            .with_replies([r#"{"steps": ["Click reset"]}"#, r#"{"url": "/reset"}"#]);
+        let model = SemanticCache::new(model, Topics, 10);
+        let request = || Request::new([Message::user("Reset my password")]);
+
+        let reset: Reset = model.generate(request()).await.unwrap();
+        assert_eq!(reset.steps, ["Click reset"]);
+        let link: Link = model.generate(request()).await.unwrap();
+        assert_eq!(link.url, "/reset");
+        assert_eq!(model.len(), 2);
+    }
+}
@@ -0,0 +1,149 @@
+use alloc::vec::Vec;
+
+use async_stream::stream;
+use futures_core::Stream;
+use futures_lite::{StreamExt, pin};
+
+use crate::{
+    LanguageModel,
+    llm::{Request, TextStream, event::StreamEvent, model::Profile},
+    provenance::Provenance,
+    time::Clock,
+};
+
+/// A language model reporting the provenance of its responses.
+///
+/// [`respond_events`](LanguageModel::respond_events) ends every successful response with a
+/// [`StreamEvent::Provenance`] trailer, just before [`StreamEvent::Done`]. The record names
+/// the model from its profile, the time the response started, and the request's
+/// [idempotency key](Request::with_idempotency_key) as its identifier. Text-only methods
+/// pass through unchanged.
+///
+/// The clock must read wall-clock time since the Unix epoch, unlike the monotonic clocks
+/// used elsewhere in the crate.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{LanguageModel, middleware::Tagged};
+/// use std::time::{SystemTime, UNIX_EPOCH};
+///
+/// fn tagged(model: impl LanguageModel) -> impl LanguageModel {
+///     Tagged::new(model, || {
+///         SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+///     })
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Tagged<M, C> {
+    model: M,
+    clock: C,
+}
+
+impl<M: LanguageModel, C: Clock> Tagged<M, C> {
+    /// Wraps `model`, timestamping responses with `clock`.
+    #[must_use]
+    pub const fn new(model: M, clock: C) -> Self {
+        Self { model, clock }
+    }
+
+    /// Returns the wrapped model.
+    #[must_use]
+    pub const fn model(&self) -> &M {
+        &self.model
+    }
+
+    /// Returns the provenance of a response to `request` generated now.
+    #[must_use]
+    pub fn provenance(&self, request: &Request) -> Provenance {
+        let provenance =
+            Provenance::new(self.model.profile().name).with_created_at(self.clock.now());
+        match &request.idempotency_key {
+            Some(key) => provenance.with_request_id(key.clone()),
+            None => provenance,
+        }
+    }
+}
+
+impl<M, C> LanguageModel for Tagged<M, C>
+where
+    M: LanguageModel,
+    C: Clock + 'static,
+{
+    type Error = M::Error;
+
+    fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+        self.model.respond(request)
+    }
+
+    fn respond_n(&self, request: Request) -> Vec<impl TextStream<Error = Self::Error> + Send> {
+        self.model.respond_n(request)
+    }
+
+    fn respond_events(
+        &self,
+        request: Request,
+    ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+        let provenance = self.provenance(&request);
+        let events = self.model.respond_events(request);
+        stream! {
+            pin!(events);
+            let mut provenance = Some(provenance);
+            while let Some(event) = events.next().await {
+                match event {
+                    Ok(StreamEvent::Done) => {
+                        if let Some(provenance) = provenance.take() {
+                            yield Ok(StreamEvent::Provenance(provenance));
+                        }
+                        yield Ok(StreamEvent::Done);
+                    }
+                    Err(error) => {
+                        yield Err(error);
+                        return;
+                    }
+                    event => yield event,
+                }
+            }
+        }
+    }
+
+    fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+        self.model.complete(prefix)
+    }
+
+    fn profile(&self) -> Profile {
+        self.model.profile()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::MockLanguageModel;
+    use core::time::Duration;
+
+    #[tokio::test]
+    async fn appends_provenance_before_done() {
+        let model = Tagged::new(MockLanguageModel::new().with_reply("Hi"), || {
+            Duration::from_secs(1_700_000_000)
+        });
+        let request = Request::oneshot("Be brief", "Hello").with_idempotency_key("req-7");
+        let events: Vec<_> = model
+            .respond_events(request)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        let provenance = Provenance::new("mock")
+            .with_created_at(Duration::from_secs(1_700_000_000))
+            .with_request_id("req-7");
+        assert_eq!(
+            events,
+            [
+                StreamEvent::Text("Hi".into()),
+                StreamEvent::Provenance(provenance),
+                StreamEvent::Done,
+            ]
+        );
+    }
+}
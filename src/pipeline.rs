@@ -0,0 +1,641 @@
+//! Declarative model pipelines.
+//!
+//! A deployment usually stacks the same building blocks: a few models behind a
+//! [`Router`], middleware such as [`Retry`] and [`Cached`] around them, guardrails
+//! moderating the responses, and settings for retrieval. A [`PipelineConfig`] describes
+//! such a stack as plain data, so it can live in a configuration file and change without
+//! recompiling. With the `serde` feature it deserializes from any format, such as JSON
+//! with [`PipelineConfig::from_json`] or TOML with the `toml` crate.
+//!
+//! Models are named by provider and model name. A [`Registry`] holds the providers a
+//! configuration may use, each under the name configurations refer to it by, along with the [`Timer`] needed by retries and the
+//! [`Moderation`] service needed by guardrails, and [`PipelineConfig::build`] assembles
+//! the described model against it.
+//!
+//! [Layers](LayerConfig) wrap the routed model in order, so the first layer is the
+//! innermost. The [retrieval settings](RagConfig) are not used by the model itself; they
+//! are carried along for the application's retriever.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::{
+//!     LanguageModel,
+//!     llm::{LanguageModelProvider, Request},
+//!     pipeline::{LayerConfig, ModelConfig, PipelineConfig, PolicyConfig, Registry},
+//! };
+//!
+//! async fn serve(openai: impl LanguageModelProvider<Model: 'static> + Send + Sync + 'static)
+//!     -> ai_types::Result
+//! {
+//!     let config = PipelineConfig::new()
+//!         .with_model(ModelConfig::new("openai", "gpt-4o-mini"))
+//!         .with_model(ModelConfig::new("openai", "gpt-4o"))
+//!         .with_policy(PolicyConfig::SmallestFit { headroom: 1024 })
+//!         .with_layer(LayerConfig::FitMaxTokens)
+//!         .with_layer(LayerConfig::Cache { capacity: 256 });
+//!
+//!     let registry = Registry::new().with_provider("openai", openai);
+//!     let model = config.build(&registry).await?;
+//!     Ok(model.respond(Request::oneshot("Be brief", "Hello!")).await?)
+//! }
+//! ```
+
+use alloc::{boxed::Box, collections::BTreeMap, string::String, sync::Arc, vec::Vec};
+use core::{fmt, time::Duration};
+
+use futures_lite::{FutureExt, future::Boxed};
+
+use crate::{
+    LanguageModel,
+    llm::{
+        BoxedError, BoxedLanguageModel, LanguageModelProvider, Request,
+        model::{Parameters, Profile},
+        router::{Cheapest, InOrder, Route, Router, RoutingPolicy, SmallestFit, Sticky, Weighted},
+    },
+    middleware::{Budget, Cached, Defaulted, Defaults, FitMaxTokens, Guarded, Retry, RetryPolicy},
+    moderation::{Moderation, ModerationResult},
+    time::Timer,
+};
+
+/// A model stack described as data.
+///
+/// See the [module documentation](crate::pipeline) for an example.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[non_exhaustive]
+pub struct PipelineConfig {
+    /// The models serving requests. Several models are put behind a [`Router`].
+    pub models: Vec<ModelConfig>,
+    /// How the router picks one of several models.
+    pub policy: PolicyConfig,
+    /// The middleware wrapping the routed model, innermost first.
+    pub layers: Vec<LayerConfig>,
+    /// Settings of the application's retriever, if it uses retrieval.
+    pub rag: Option<RagConfig>,
+}
+
+impl PipelineConfig {
+    /// Creates an empty configuration.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a configuration from JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `json` doesn't describe a configuration.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// Adds a model.
+    #[must_use]
+    pub fn with_model(mut self, model: ModelConfig) -> Self {
+        self.models.push(model);
+        self
+    }
+
+    /// Sets the routing policy.
+    #[must_use]
+    pub fn with_policy(mut self, policy: PolicyConfig) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Adds a layer around the layers added before.
+    #[must_use]
+    pub fn with_layer(mut self, layer: LayerConfig) -> Self {
+        self.layers.push(layer);
+        self
+    }
+
+    /// Sets the retrieval settings.
+    #[must_use]
+    pub fn with_rag(mut self, rag: RagConfig) -> Self {
+        self.rag = Some(rag);
+        self
+    }
+
+    /// Assembles the described model from the providers of `registry`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration has no models, names a provider missing
+    /// from `registry`, has a layer with invalid settings, or has a layer needing a timer
+    /// or moderation service that `registry` lacks.
+    pub async fn build(&self, registry: &Registry) -> Result<BoxedLanguageModel, PipelineError> {
+        let mut models = Vec::with_capacity(self.models.len());
+        for config in &self.models {
+            let provider = registry
+                .providers
+                .get(&config.provider)
+                .ok_or_else(|| PipelineError::UnknownProvider(config.provider.clone()))?;
+            models.push(provider(config.model.clone()).await);
+        }
+
+        let mut model = match models.len() {
+            0 => return Err(PipelineError::NoModels),
+            1 if self.models[0].tags.is_empty() => models.remove(0),
+            _ => {
+                let routes = models.into_iter().zip(&self.models).map(|(model, config)| {
+                    config
+                        .tags
+                        .iter()
+                        .fold(Route::new(model), |route, tag| route.with_tag(tag.clone()))
+                });
+                Router::new(routes)
+                    .with_policy(Dynamic(self.policy.build()))
+                    .boxed()
+            }
+        };
+        for layer in &self.layers {
+            model = layer.apply(model, registry)?;
+        }
+        Ok(model)
+    }
+}
+
+/// A model of a [`PipelineConfig`], named by provider.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct ModelConfig {
+    /// The name the provider is [registered](Registry::with_provider) under.
+    pub provider: String,
+    /// The name of the model, as passed to
+    /// [`get_model`](LanguageModelProvider::get_model).
+    pub model: String,
+    /// Tags of the model's [`Route`], which requests can require.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tags: Vec<String>,
+}
+
+impl ModelConfig {
+    /// Names the model `model` of the provider `provider`.
+    pub fn new(provider: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            provider: provider.into(),
+            model: model.into(),
+            tags: Vec::new(),
+        }
+    }
+
+    /// Adds a tag to the model's route.
+    #[must_use]
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+}
+
+/// A [`RoutingPolicy`] of a [`PipelineConfig`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum PolicyConfig {
+    /// The [`Cheapest`] model.
+    #[default]
+    Cheapest,
+    /// The first model, [`InOrder`].
+    InOrder,
+    /// The model with the [`SmallestFit`] context window.
+    SmallestFit {
+        /// Tokens left free in the context window beyond the prompt.
+        #[cfg_attr(feature = "serde", serde(default))]
+        headroom: u32,
+    },
+    /// Models [`Weighted`] by profile name. Unlisted models weigh 1.
+    Weighted {
+        /// The weight of each model, by profile name.
+        weights: BTreeMap<String, u32>,
+    },
+    /// Conversations kept on one model, the first [`Sticky`] to `policy`.
+    Sticky {
+        /// The policy routing the first turn of each conversation.
+        policy: Box<Self>,
+        /// How many conversations are remembered, if not the default.
+        #[cfg_attr(feature = "serde", serde(default))]
+        capacity: Option<usize>,
+    },
+}
+
+impl PolicyConfig {
+    fn build(&self) -> Box<dyn RoutingPolicy> {
+        match self {
+            Self::Cheapest => Box::new(Cheapest),
+            Self::InOrder => Box::new(InOrder),
+            Self::SmallestFit { headroom } => Box::new(SmallestFit::new().with_headroom(*headroom)),
+            Self::Weighted { weights } => Box::new(Weighted::new(weights.clone())),
+            Self::Sticky { policy, capacity } => {
+                let sticky = Sticky::new(Dynamic(policy.build()));
+                match capacity {
+                    Some(capacity) => Box::new(sticky.with_capacity(*capacity)),
+                    None => Box::new(sticky),
+                }
+            }
+        }
+    }
+}
+
+/// A middleware of a [`PipelineConfig`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum LayerConfig {
+    /// Application-wide [`Defaults`], applied with [`Defaulted`].
+    Defaults {
+        /// Parameters used when a request leaves them unset.
+        #[cfg_attr(feature = "serde", serde(default))]
+        parameters: Parameters,
+        /// Text put before the system prompt of every request.
+        #[cfg_attr(feature = "serde", serde(default))]
+        system_prefix: Option<String>,
+    },
+    /// `max_tokens` fit into the context window by [`FitMaxTokens`].
+    FitMaxTokens,
+    /// Transient failures retried by [`Retry`], with the registry's timer.
+    Retry {
+        /// The maximum number of retries, if not the default.
+        #[cfg_attr(feature = "serde", serde(default))]
+        max_retries: Option<u32>,
+        /// The delay before the first retry in milliseconds, if not the default.
+        #[cfg_attr(feature = "serde", serde(default))]
+        initial_backoff_ms: Option<u64>,
+        /// Whether responses failing mid-stream are continued.
+        #[cfg_attr(feature = "serde", serde(default))]
+        continuation: bool,
+    },
+    /// Spending limited by [`Budget`].
+    Budget {
+        /// The limit in USD.
+        limit: f64,
+    },
+    /// Repeated requests served by [`Cached`].
+    Cache {
+        /// How many responses are kept.
+        capacity: usize,
+    },
+    /// Responses moderated by [`Guarded`], with the registry's moderation service.
+    Guardrails {
+        /// Release the response in windows of this many sentences instead of whole.
+        #[cfg_attr(feature = "serde", serde(default))]
+        sentence_window: Option<usize>,
+    },
+}
+
+impl LayerConfig {
+    fn apply(
+        &self,
+        model: BoxedLanguageModel,
+        registry: &Registry,
+    ) -> Result<BoxedLanguageModel, PipelineError> {
+        Ok(match self {
+            Self::Defaults {
+                parameters,
+                system_prefix,
+            } => {
+                let mut defaults = Defaults::new().with_parameters(parameters.clone());
+                if let Some(prefix) = system_prefix {
+                    defaults = defaults.with_system_prefix(prefix.clone());
+                }
+                Defaulted::new(model, defaults).boxed()
+            }
+            Self::FitMaxTokens => FitMaxTokens::new(model).boxed(),
+            Self::Retry {
+                max_retries,
+                initial_backoff_ms,
+                continuation,
+            } => {
+                let timer = registry.timer.clone().ok_or(PipelineError::MissingTimer)?;
+                let mut policy = RetryPolicy::default().with_continuation(*continuation);
+                if let Some(retries) = max_retries {
+                    policy = policy.with_max_retries(*retries);
+                }
+                if let Some(delay) = initial_backoff_ms {
+                    policy = policy.with_initial_backoff(Duration::from_millis(*delay));
+                }
+                Retry::new(model, move |delay| timer(delay))
+                    .with_policy(policy)
+                    .boxed()
+            }
+            Self::Budget { limit } => Budget::new(model, *limit).boxed(),
+            Self::Cache { capacity } => Cached::new(model, *capacity).boxed(),
+            Self::Guardrails { sentence_window } => {
+                let moderation = registry
+                    .moderation
+                    .clone()
+                    .ok_or(PipelineError::MissingModeration)?;
+                let guarded = Guarded::new(model, SharedModeration(moderation));
+                match sentence_window {
+                    Some(0) => {
+                        return Err(PipelineError::InvalidLayer(
+                            "a guardrails sentence window needs at least one sentence".into(),
+                        ));
+                    }
+                    Some(sentences) => guarded.with_sentence_window(*sentences).boxed(),
+                    None => guarded.boxed(),
+                }
+            }
+        })
+    }
+}
+
+/// Settings of retrieval-augmented generation, for the application's retriever.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[non_exhaustive]
+pub struct RagConfig {
+    /// The embedding model, if the application has several.
+    pub embedding: Option<ModelConfig>,
+    /// How many documents are retrieved per query.
+    pub top_k: usize,
+    /// The lowest similarity of a retrieved document.
+    pub min_similarity: Option<f32>,
+    /// Whether retrieved documents are reranked before use.
+    pub rerank: bool,
+    /// The size of the chunks documents are split into, in tokens.
+    pub chunk_tokens: Option<u32>,
+}
+
+impl Default for RagConfig {
+    fn default() -> Self {
+        Self {
+            embedding: None,
+            top_k: 4,
+            min_similarity: None,
+            rerank: false,
+            chunk_tokens: None,
+        }
+    }
+}
+
+type ModelFactory = dyn Fn(String) -> Boxed<BoxedLanguageModel> + Send + Sync;
+type SharedTimer = Arc<dyn Fn(Duration) -> Boxed<()> + Send + Sync>;
+type ModerationFn = dyn Fn(String) -> Boxed<Result<ModerationResult, BoxedError>> + Send + Sync;
+
+/// The providers and services a [`PipelineConfig`] is built against.
+#[derive(Default)]
+pub struct Registry {
+    providers: BTreeMap<String, Arc<ModelFactory>>,
+    timer: Option<SharedTimer>,
+    moderation: Option<Arc<ModerationFn>>,
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field("providers", &self.providers.keys().collect::<Vec<_>>())
+            .field("timer", &self.timer.is_some())
+            .field("moderation", &self.moderation.is_some())
+            .finish()
+    }
+}
+
+impl Registry {
+    /// Creates an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `provider` under `name`, replacing any provider registered under it.
+    ///
+    /// Names are chosen by the application rather than taken from the provider's
+    /// [profile](LanguageModelProvider::profile), so several instances of one provider,
+    /// such as two accounts or regions, can be registered side by side.
+    #[must_use]
+    pub fn with_provider<P>(mut self, name: impl Into<String>, provider: P) -> Self
+    where
+        P: LanguageModelProvider<Model: 'static> + Send + Sync + 'static,
+    {
+        let name = name.into();
+        let provider = Arc::new(provider);
+        let factory: Arc<ModelFactory> = Arc::new(move |model: String| {
+            let provider = provider.clone();
+            async move { provider.get_model(&model).await.boxed() }.boxed()
+        });
+        self.providers.insert(name, factory);
+        self
+    }
+
+    /// Sets the timer used by [`Retry`] layers.
+    #[must_use]
+    pub fn with_timer(mut self, timer: impl Timer + 'static) -> Self {
+        let timer = Arc::new(timer);
+        self.timer = Some(Arc::new(move |duration| {
+            let timer = timer.clone();
+            async move { timer.sleep(duration).await }.boxed()
+        }));
+        self
+    }
+
+    /// Sets the moderation service used by [`Guardrails`](LayerConfig::Guardrails) layers.
+    #[must_use]
+    pub fn with_moderation(mut self, moderation: impl Moderation + Send + Sync + 'static) -> Self {
+        let moderation = Arc::new(moderation);
+        self.moderation = Some(Arc::new(move |content: String| {
+            let moderation = moderation.clone();
+            async move { moderation.moderate(&content).await.map_err(BoxedError::new) }.boxed()
+        }));
+        self
+    }
+}
+
+/// An error building a [`PipelineConfig`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PipelineError {
+    /// The configuration has no models.
+    NoModels,
+    /// A model's provider isn't in the registry.
+    UnknownProvider(String),
+    /// A [`Retry`](LayerConfig::Retry) layer needs a timer, and the registry has none.
+    MissingTimer,
+    /// A [`Guardrails`](LayerConfig::Guardrails) layer needs a moderation service, and the
+    /// registry has none.
+    MissingModeration,
+    /// A layer's settings are invalid, for the given reason.
+    InvalidLayer(String),
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoModels => f.write_str("the pipeline has no models"),
+            Self::UnknownProvider(name) => write!(f, "unknown provider `{name}`"),
+            Self::MissingTimer => f.write_str("retries need a timer in the registry"),
+            Self::MissingModeration => {
+                f.write_str("guardrails need a moderation service in the registry")
+            }
+            Self::InvalidLayer(reason) => write!(f, "invalid layer: {reason}"),
+        }
+    }
+}
+
+impl core::error::Error for PipelineError {}
+
+/// A routing policy chosen at runtime.
+struct Dynamic(Box<dyn RoutingPolicy>);
+
+impl RoutingPolicy for Dynamic {
+    fn select(&self, request: &Request, candidates: &[&Profile]) -> usize {
+        self.0.select(request, candidates)
+    }
+}
+
+/// The registry's moderation service.
+struct SharedModeration(Arc<ModerationFn>);
+
+impl Moderation for SharedModeration {
+    type Error = BoxedError;
+
+    fn moderate(
+        &self,
+        content: &str,
+    ) -> impl Future<Output = Result<ModerationResult, Self::Error>> + Send {
+        (self.0)(content.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{llm::provider, test_util::MockLanguageModel};
+    use alloc::vec;
+
+    struct Lab;
+
+    impl LanguageModelProvider for Lab {
+        type Model = MockLanguageModel;
+
+        async fn list_models(&self) -> Vec<String> {
+            vec!["seer".into(), "scribe".into()]
+        }
+
+        async fn get_model(&self, name: &str) -> MockLanguageModel {
+            MockLanguageModel::new()
+                .with_reply(name)
+                .with_profile(Profile::new(name, "", 4096))
+        }
+
+        fn profile() -> provider::Profile {
+            provider::Profile::new("lab", "A lab")
+        }
+    }
+
+    struct Allow;
+
+    impl Moderation for Allow {
+        type Error = core::convert::Infallible;
+
+        async fn moderate(&self, _content: &str) -> Result<ModerationResult, Self::Error> {
+            Ok(ModerationResult::new(false, Vec::new()))
+        }
+    }
+
+    #[tokio::test]
+    async fn builds_routed_and_layered_models() {
+        let config = PipelineConfig::new()
+            .with_model(ModelConfig::new("lab", "seer"))
+            .with_model(ModelConfig::new("backup", "scribe"))
+            .with_policy(PolicyConfig::Weighted {
+                weights: [("scribe".into(), 2)].into(),
+            })
+            .with_layer(LayerConfig::FitMaxTokens)
+            .with_layer(LayerConfig::Cache { capacity: 8 });
+        let registry = Registry::new()
+            .with_provider("lab", Lab)
+            .with_provider("backup", Lab);
+        let model = config.build(&registry).await.unwrap();
+
+        let answer = model
+            .respond(Request::oneshot("Be brief", "Hello!"))
+            .await
+            .unwrap();
+        assert_eq!(answer, "scribe");
+
+        let retrying = config.with_layer(LayerConfig::Retry {
+            max_retries: Some(1),
+            initial_backoff_ms: None,
+            continuation: false,
+        });
+        assert_eq!(
+            retrying.build(&registry).await.err(),
+            Some(PipelineError::MissingTimer)
+        );
+        assert_eq!(
+            PipelineConfig::new()
+                .with_model(ModelConfig::new("elsewhere", "seer"))
+                .build(&registry)
+                .await
+                .err(),
+            Some(PipelineError::UnknownProvider("elsewhere".into()))
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_layers() {
+        let registry = Registry::new()
+            .with_provider("lab", Lab)
+            .with_moderation(Allow);
+        let guarded = |sentence_window| {
+            PipelineConfig::new()
+                .with_model(ModelConfig::new("lab", "seer"))
+                .with_layer(LayerConfig::Guardrails { sentence_window })
+        };
+
+        assert!(guarded(Some(2)).build(&registry).await.is_ok());
+        assert!(matches!(
+            guarded(Some(0)).build(&registry).await.err(),
+            Some(PipelineError::InvalidLayer(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn parses_json_configuration() {
+        let config = PipelineConfig::from_json(
+            r#"{
+                "models": [{ "provider": "lab", "model": "seer", "tags": ["vision"] }],
+                "policy": { "type": "sticky", "policy": { "type": "smallest_fit" } },
+                "layers": [
+                    { "type": "defaults", "parameters": { "temperature": 0.2 } },
+                    { "type": "retry", "max_retries": 5 },
+                    { "type": "guardrails", "sentence_window": 2 }
+                ],
+                "rag": { "top_k": 8, "rerank": true }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.models,
+            [ModelConfig::new("lab", "seer").with_tag("vision")]
+        );
+        assert_eq!(
+            config.policy,
+            PolicyConfig::Sticky {
+                policy: Box::new(PolicyConfig::SmallestFit { headroom: 0 }),
+                capacity: None,
+            }
+        );
+        assert_eq!(
+            config.layers[1],
+            LayerConfig::Retry {
+                max_retries: Some(5),
+                initial_backoff_ms: None,
+                continuation: false,
+            }
+        );
+        let rag = config.rag.unwrap();
+        assert_eq!((rag.top_k, rag.rerank, rag.embedding), (8, true, None));
+    }
+}
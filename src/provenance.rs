@@ -0,0 +1,211 @@
+//! Provenance metadata for generated content.
+//!
+//! Regulations and platform policies increasingly require AI-generated content to say
+//! where it came from. A [`Provenance`] record names the model, the time of generation,
+//! and the request, and can be attached to generated artifacts:
+//!
+//! - **Text**: [`Tagged`](crate::middleware::Tagged) appends a
+//!   [`StreamEvent::Provenance`](crate::llm::event::StreamEvent::Provenance) trailer to
+//!   every event stream.
+//! - **Images**: a [`ProvenanceWriter`] embeds the record in the image file. [`PngText`]
+//!   writes PNG text chunks; other formats, such as EXIF for JPEG, can implement the trait.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::provenance::{PngText, Provenance, ProvenanceWriter};
+//! use core::time::Duration;
+//!
+//! fn tag(png: &[u8]) -> ai_types::Result<Vec<u8>> {
+//!     let provenance = Provenance::new("painter-2")
+//!         .with_created_at(Duration::from_secs(1_700_000_000))
+//!         .with_request_id("req-42");
+//!     PngText.write(png, &provenance)
+//! }
+//! ```
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::time::Duration;
+
+use anyhow::bail;
+
+/// Where a piece of generated content came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Provenance {
+    /// The name of the model that generated the content.
+    pub model: String,
+    /// When the content was generated, as the time elapsed since the Unix epoch.
+    pub created_at: Option<Duration>,
+    /// The identifier of the request that produced the content.
+    pub request_id: Option<String>,
+}
+
+impl Provenance {
+    /// Creates a record for content generated by `model`.
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            created_at: None,
+            request_id: None,
+        }
+    }
+
+    /// Sets when the content was generated, as the time elapsed since the Unix epoch.
+    #[must_use]
+    pub const fn with_created_at(mut self, created_at: Duration) -> Self {
+        self.created_at = Some(created_at);
+        self
+    }
+
+    /// Sets the identifier of the request that produced the content.
+    #[must_use]
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    /// Returns the record as key-value pairs, such as `("ai:model", "painter-2")`.
+    ///
+    /// Times are written in whole seconds since the Unix epoch. Unset fields are skipped.
+    #[must_use]
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        let mut entries = alloc::vec![("ai:model", self.model.clone())];
+        if let Some(created_at) = self.created_at {
+            entries.push(("ai:created_at", created_at.as_secs().to_string()));
+        }
+        if let Some(request_id) = &self.request_id {
+            entries.push(("ai:request_id", request_id.clone()));
+        }
+        entries
+    }
+}
+
+/// Embeds a [`Provenance`] record in a generated file.
+pub trait ProvenanceWriter {
+    /// Returns a copy of `file` carrying `provenance`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `file` is not in a format the writer understands.
+    fn write(&self, file: &[u8], provenance: &Provenance) -> crate::Result<Vec<u8>>;
+}
+
+/// Writes provenance into PNG images as international text (`iTXt`) chunks.
+///
+/// Each entry of [`Provenance::entries`] becomes one uncompressed chunk, placed just
+/// before the end of the image, where image viewers and metadata tools can read it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PngText;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+impl ProvenanceWriter for PngText {
+    fn write(&self, file: &[u8], provenance: &Provenance) -> crate::Result<Vec<u8>> {
+        if !file.starts_with(PNG_SIGNATURE) {
+            bail!("Not a PNG image");
+        }
+
+        // Find the IEND chunk by walking the chunk list.
+        let mut position = PNG_SIGNATURE.len();
+        let end = loop {
+            let Some(header) = file.get(position..position.saturating_add(8)) else {
+                bail!("Truncated PNG image: no IEND chunk");
+            };
+            let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+            if &header[4..] == b"IEND" {
+                break position;
+            }
+            position = position.saturating_add(12 + length as usize);
+        };
+
+        let mut tagged = Vec::with_capacity(file.len() + 128);
+        tagged.extend_from_slice(&file[..end]);
+        for (keyword, text) in provenance.entries() {
+            let mut data = Vec::with_capacity(keyword.len() + text.len() + 5);
+            data.extend_from_slice(keyword.as_bytes());
+            // Null separator, no compression, compression method, empty language tag
+            // and translated keyword.
+            data.extend_from_slice(&[0, 0, 0, 0, 0]);
+            data.extend_from_slice(text.as_bytes());
+            push_chunk(&mut tagged, *b"iTXt", &data)?;
+        }
+        tagged.extend_from_slice(&file[end..]);
+        Ok(tagged)
+    }
+}
+
+fn push_chunk(png: &mut Vec<u8>, kind: [u8; 4], data: &[u8]) -> crate::Result<()> {
+    let Ok(length) = u32::try_from(data.len()) else {
+        bail!("PNG chunk too large");
+    };
+    png.extend_from_slice(&length.to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(&kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+    Ok(())
+}
+
+/// The CRC-32 checksum used by PNG chunks.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png() -> Vec<u8> {
+        let mut png = PNG_SIGNATURE.to_vec();
+        push_chunk(&mut png, *b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0]).unwrap();
+        push_chunk(&mut png, *b"IEND", &[]).unwrap();
+        png
+    }
+
+    #[test]
+    fn crc_matches_reference() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn writes_text_chunks_before_iend() {
+        let provenance = Provenance::new("painter")
+            .with_created_at(Duration::from_millis(1_700_000_000_500))
+            .with_request_id("req-1");
+        let original = png();
+        let tagged = PngText.write(&original, &provenance).unwrap();
+
+        assert!(tagged.ends_with(&original[original.len() - 12..]));
+        let text = String::from_utf8_lossy(&tagged);
+        let model = text.find("ai:model\0\0\0\0\0painter").unwrap();
+        let created = text.find("ai:created_at\0\0\0\0\x001700000000").unwrap();
+        let request = text.find("ai:request_id\0\0\0\0\0req-1").unwrap();
+        assert!(model < created && created < request);
+        assert!(request < tagged.len() - 12);
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        let provenance = Provenance::new("painter");
+        assert!(PngText.write(b"GIF89a", &provenance).is_err());
+        assert!(PngText.write(PNG_SIGNATURE, &provenance).is_err());
+    }
+}
@@ -0,0 +1,101 @@
+//! Rate limit state reported by providers.
+//!
+//! Most providers report the state of the caller's rate limit alongside each response,
+//! usually through headers such as `x-ratelimit-remaining-requests`. Provider adapters
+//! translate these into a [`RateLimitInfo`], attached to successful responses as a
+//! [`StreamEvent::RateLimit`](crate::llm::event::StreamEvent::RateLimit) event and to
+//! errors through [`Classify::rate_limit`](crate::error::Classify::rate_limit). Rate
+//! limiters and routers use it to pace traffic before the provider starts rejecting it.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::rate_limit::RateLimitInfo;
+//! use core::time::Duration;
+//!
+//! // 500 requests per minute, 20 left, window resets in 10 seconds.
+//! let info = RateLimitInfo::new(500, 20, Duration::from_secs(10));
+//!
+//! assert!(!info.is_exhausted());
+//! assert_eq!(info.pacing_delay(), Duration::from_millis(500));
+//! ```
+
+use core::time::Duration;
+
+/// The state of a rate limit window, as reported by a provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct RateLimitInfo {
+    /// The number of requests (or tokens) allowed in the current window.
+    pub limit: u32,
+    /// The number of requests (or tokens) left in the current window.
+    pub remaining: u32,
+    /// The time until the window resets and `remaining` is restored to `limit`.
+    pub reset_after: Duration,
+}
+
+impl RateLimitInfo {
+    /// Creates a rate limit report.
+    #[must_use]
+    pub const fn new(limit: u32, remaining: u32, reset_after: Duration) -> Self {
+        Self {
+            limit,
+            remaining,
+            reset_after,
+        }
+    }
+
+    /// Returns whether no requests are left in the current window.
+    #[must_use]
+    pub const fn is_exhausted(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Returns the number of requests already used in the current window.
+    #[must_use]
+    pub const fn used(&self) -> u32 {
+        self.limit.saturating_sub(self.remaining)
+    }
+
+    /// Returns the fraction of the limit already used, between 0.0 and 1.0.
+    #[must_use]
+    pub fn usage_ratio(&self) -> f64 {
+        if self.limit == 0 {
+            return 1.0;
+        }
+        f64::from(self.used()) / f64::from(self.limit)
+    }
+
+    /// Returns how long to wait before the next request to spread the remaining requests
+    /// evenly over the rest of the window.
+    ///
+    /// When the window is exhausted, this is the time until it resets.
+    #[must_use]
+    pub fn pacing_delay(&self) -> Duration {
+        if self.is_exhausted() {
+            self.reset_after
+        } else {
+            self.reset_after / self.remaining
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exhausted_window_waits_for_reset() {
+        let info = RateLimitInfo::new(60, 0, Duration::from_secs(30));
+        assert!(info.is_exhausted());
+        assert_eq!(info.used(), 60);
+        assert_eq!(info.pacing_delay(), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn usage_ratio_handles_empty_limit() {
+        assert!((RateLimitInfo::new(200, 50, Duration::ZERO).usage_ratio() - 0.75).abs() < 1e-9);
+        assert!((RateLimitInfo::new(0, 0, Duration::ZERO).usage_ratio() - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,482 @@
+//! # Realtime Module
+//!
+//! Bidirectional speech-to-speech sessions.
+//!
+//! Realtime APIs, such as the `OpenAI` Realtime API or Gemini Live, keep a connection open
+//! for a whole conversation. The client streams microphone audio in, and the model streams
+//! back transcripts of the user, its own text and speech, and tool calls, interleaved as
+//! they happen. Turn taking is usually handled by the provider's voice activity detection.
+//!
+//! A [`RealtimeModel`] [connects](RealtimeModel::connect) a [`RealtimeSession`] configured
+//! by a [`SessionConfig`]. The session is driven through shared references, so audio can
+//! be sent from one task while [events](RealtimeSession::events) are read from another.
+//! Everything the model reports is a [`RealtimeEvent`], and [`SessionState`] follows the
+//! session's lifecycle from those events.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::realtime::{RealtimeEvent, RealtimeModel, RealtimeSession, SessionConfig};
+//! use futures_core::Stream;
+//! use futures_lite::{StreamExt, future, pin};
+//!
+//! async fn talk(
+//!     model: impl RealtimeModel,
+//!     microphone: impl Stream<Item = Vec<u8>> + Send,
+//! ) -> ai_types::Result<()> {
+//!     let config = SessionConfig::new().with_instructions("You are a friendly voice assistant.");
+//!     let session = model.connect(config).await?;
+//!
+//!     let send = async {
+//!         pin!(microphone);
+//!         while let Some(frame) = microphone.next().await {
+//!             session.send_audio(&frame).await?;
+//!         }
+//!         session.close().await
+//!     };
+//!     let receive = async {
+//!         let events = session.events();
+//!         pin!(events);
+//!         while let Some(event) = events.try_next().await? {
+//!             match event {
+//!                 RealtimeEvent::InputTranscript(text) => println!("user: {}", text.text()),
+//!                 RealtimeEvent::Audio(chunk) => { /* play chunk */ }
+//!                 RealtimeEvent::SpeechStarted => { /* stop playback */ }
+//!                 _ => {}
+//!             }
+//!         }
+//!         Ok(())
+//!     };
+//!     let (sent, received) = future::zip(send, receive).await;
+//!     sent.and(received)
+//! }
+//! ```
+
+use alloc::{string::String, vec::Vec};
+use core::{future::Future, time::Duration};
+
+use futures_core::Stream;
+
+use crate::{
+    audio::{Data, TranscriptEvent},
+    llm::{
+        tool::{ToolCall, ToolDefinition},
+        usage::Usage,
+    },
+};
+
+/// Connects realtime sessions.
+pub trait RealtimeModel: Send + Sync {
+    /// The session type returned by [`connect`](Self::connect).
+    type Session: RealtimeSession;
+
+    /// Opens a session configured by `config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection cannot be established or the configuration is
+    /// rejected.
+    fn connect(
+        &self,
+        config: SessionConfig,
+    ) -> impl Future<Output = crate::Result<Self::Session>> + Send;
+}
+
+/// An open speech-to-speech session.
+///
+/// All methods take `&self`, so a session can be shared between the task sending audio
+/// and the task reading events.
+pub trait RealtimeSession: Send + Sync {
+    /// Appends a frame of audio to the input buffer.
+    ///
+    /// Frames are encoded as configured by [`SessionConfig::sample_rate`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame cannot be sent or the session is closed.
+    fn send_audio(&self, frame: &[u8]) -> impl Future<Output = crate::Result<()>> + Send;
+
+    /// Ends the user's turn with the audio buffered so far.
+    ///
+    /// Only needed with [`TurnDetection::Manual`]; with server-side detection the model
+    /// commits the audio itself when the user stops speaking.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer is empty or the session is closed.
+    fn commit_audio(&self) -> impl Future<Output = crate::Result<()>> + Send;
+
+    /// Adds a user text message to the conversation.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message cannot be sent or the session is closed.
+    fn send_text(&self, text: &str) -> impl Future<Output = crate::Result<()>> + Send;
+
+    /// Returns the output of the tool call `call_id` to the model.
+    ///
+    /// The model doesn't respond to the result until [`create_response`] is called.
+    ///
+    /// [`create_response`]: Self::create_response
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the result cannot be sent or the session is closed.
+    fn send_tool_result(
+        &self,
+        call_id: &str,
+        output: &str,
+    ) -> impl Future<Output = crate::Result<()>> + Send;
+
+    /// Asks the model to respond to the conversation so far.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request cannot be sent or the session is closed.
+    fn create_response(&self) -> impl Future<Output = crate::Result<()>> + Send;
+
+    /// Cancels the response in progress, such as when the user interrupts it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request cannot be sent or the session is closed.
+    fn cancel_response(&self) -> impl Future<Output = crate::Result<()>> + Send;
+
+    /// Replaces the session's configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration is rejected or the session is closed.
+    fn update(&self, config: SessionConfig) -> impl Future<Output = crate::Result<()>> + Send;
+
+    /// Waits for the next event from the model.
+    ///
+    /// Returns `None` once the session is closed and every event has been read.
+    fn next_event(&self) -> impl Future<Output = Option<crate::Result<RealtimeEvent>>> + Send;
+
+    /// Closes the session.
+    ///
+    /// Pending events can still be read; the last one is [`RealtimeEvent::Closed`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the session cannot be closed cleanly.
+    fn close(&self) -> impl Future<Output = crate::Result<()>> + Send;
+
+    /// Returns the events of the session as a stream, ending when the session closes.
+    fn events(&self) -> impl Stream<Item = crate::Result<RealtimeEvent>> + Send + '_ {
+        futures_lite::stream::unfold(self, |session| async move {
+            let event = session.next_event().await?;
+            Some((event, session))
+        })
+    }
+}
+
+/// The configuration of a realtime session.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SessionConfig {
+    /// Instructions for the model, like a system prompt.
+    pub instructions: Option<String>,
+    /// The provider-specific name of the voice to speak with.
+    pub voice: Option<String>,
+    /// Tools the model may call.
+    pub tools: Vec<ToolDefinition>,
+    /// How the end of the user's turn is detected.
+    pub turn_detection: TurnDetection,
+    /// The sample rate of the 16-bit mono PCM audio sent and received, in hertz.
+    pub sample_rate: u32,
+    /// Whether the model responds with speech; otherwise it responds with text only.
+    pub audio_output: bool,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionConfig {
+    /// Creates a configuration with server-side turn detection and 24 kHz audio output.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            instructions: None,
+            voice: None,
+            tools: Vec::new(),
+            turn_detection: TurnDetection::Server {
+                silence: Duration::from_millis(500),
+            },
+            sample_rate: 24_000,
+            audio_output: true,
+        }
+    }
+
+    /// Sets the instructions for the model.
+    #[must_use]
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    /// Sets the voice to speak with.
+    #[must_use]
+    pub fn with_voice(mut self, voice: impl Into<String>) -> Self {
+        self.voice = Some(voice.into());
+        self
+    }
+
+    /// Adds a tool the model may call.
+    #[must_use]
+    pub fn with_tool(mut self, tool: ToolDefinition) -> Self {
+        self.tools.push(tool);
+        self
+    }
+
+    /// Sets how the end of the user's turn is detected.
+    #[must_use]
+    pub const fn with_turn_detection(mut self, turn_detection: TurnDetection) -> Self {
+        self.turn_detection = turn_detection;
+        self
+    }
+
+    /// Sets the sample rate of the audio, in hertz.
+    #[must_use]
+    pub const fn with_sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    /// Makes the model respond with text only.
+    #[must_use]
+    pub const fn text_only(mut self) -> Self {
+        self.audio_output = false;
+        self
+    }
+}
+
+/// How the end of the user's turn is detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum TurnDetection {
+    /// The provider detects speech and responds after the user is silent for `silence`.
+    Server {
+        /// How long the user must be silent to end the turn.
+        silence: Duration,
+    },
+    /// The client ends turns with [`RealtimeSession::commit_audio`] and asks for a
+    /// response with [`RealtimeSession::create_response`].
+    Manual,
+}
+
+/// An event reported by a [`RealtimeSession`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum RealtimeEvent {
+    /// The session is open and configured.
+    SessionStarted,
+    /// The user started speaking.
+    ///
+    /// Clients should stop playing the response in progress, which the model cancels.
+    SpeechStarted,
+    /// The user stopped speaking.
+    SpeechStopped,
+    /// The transcript of the user's speech.
+    InputTranscript(TranscriptEvent),
+    /// The model started a response.
+    ResponseStarted,
+    /// A chunk of the response text.
+    Text(String),
+    /// A chunk of the transcript of the response speech.
+    Transcript(String),
+    /// A chunk of the response speech.
+    Audio(Data),
+    /// The model called a tool.
+    ///
+    /// Answer with [`RealtimeSession::send_tool_result`].
+    ToolCall(ToolCall),
+    /// The response finished.
+    ResponseDone {
+        /// Whether the response was cancelled before it was complete.
+        cancelled: bool,
+        /// The tokens used by the response, if reported.
+        usage: Option<Usage>,
+    },
+    /// The session was closed.
+    Closed,
+}
+
+/// The lifecycle of a [`RealtimeSession`].
+///
+/// Starts [connecting](Self::Connecting) and is advanced by each [`RealtimeEvent`] with
+/// [`next`](Self::next).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SessionState {
+    /// Waiting for the session to start.
+    #[default]
+    Connecting,
+    /// Waiting for the user.
+    Listening,
+    /// The user is speaking.
+    UserSpeaking,
+    /// The model is responding.
+    Responding,
+    /// The session is closed.
+    Closed,
+}
+
+impl SessionState {
+    /// Returns the state after `event`.
+    ///
+    /// A response cancelled because the user started speaking leaves the state at
+    /// [`UserSpeaking`](Self::UserSpeaking).
+    #[must_use]
+    pub const fn next(self, event: &RealtimeEvent) -> Self {
+        match (self, event) {
+            (Self::Closed, _) | (_, RealtimeEvent::Closed) => Self::Closed,
+            (Self::Connecting, RealtimeEvent::SessionStarted)
+            | (Self::UserSpeaking, RealtimeEvent::SpeechStopped)
+            | (Self::Responding, RealtimeEvent::ResponseDone { .. }) => Self::Listening,
+            (Self::Connecting, _) => self,
+            (_, RealtimeEvent::SpeechStarted) => Self::UserSpeaking,
+            (_, RealtimeEvent::ResponseStarted) => Self::Responding,
+            _ => self,
+        }
+    }
+
+    /// Returns whether the session is still open.
+    #[must_use]
+    pub const fn is_open(self) -> bool {
+        !matches!(self, Self::Closed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{collections::VecDeque, vec, vec::Vec};
+    use futures_lite::StreamExt;
+    use spin::Mutex;
+
+    /// Replays scripted events and records the audio it is sent.
+    #[derive(Default)]
+    struct Scripted {
+        events: Mutex<VecDeque<RealtimeEvent>>,
+        audio: Mutex<Vec<u8>>,
+        tool_results: Mutex<Vec<(String, String)>>,
+    }
+
+    impl RealtimeSession for Scripted {
+        async fn send_audio(&self, frame: &[u8]) -> crate::Result<()> {
+            self.audio.lock().extend_from_slice(frame);
+            Ok(())
+        }
+
+        async fn commit_audio(&self) -> crate::Result<()> {
+            Ok(())
+        }
+
+        async fn send_text(&self, _text: &str) -> crate::Result<()> {
+            Ok(())
+        }
+
+        async fn send_tool_result(&self, call_id: &str, output: &str) -> crate::Result<()> {
+            self.tool_results
+                .lock()
+                .push((call_id.into(), output.into()));
+            Ok(())
+        }
+
+        async fn create_response(&self) -> crate::Result<()> {
+            Ok(())
+        }
+
+        async fn cancel_response(&self) -> crate::Result<()> {
+            Ok(())
+        }
+
+        async fn update(&self, _config: SessionConfig) -> crate::Result<()> {
+            Ok(())
+        }
+
+        async fn next_event(&self) -> Option<crate::Result<RealtimeEvent>> {
+            self.events.lock().pop_front().map(Ok)
+        }
+
+        async fn close(&self) -> crate::Result<()> {
+            self.events.lock().push_back(RealtimeEvent::Closed);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn streams_events_until_closed() {
+        let session = Scripted::default();
+        session.events.lock().extend([
+            RealtimeEvent::SessionStarted,
+            RealtimeEvent::ToolCall(ToolCall::new("call_1", "clock", "{}")),
+        ]);
+        session.send_audio(&[1, 2]).await.unwrap();
+        session.send_audio(&[3]).await.unwrap();
+        session.close().await.unwrap();
+
+        let events: Vec<_> = session.events().try_collect().await.unwrap();
+        for event in &events {
+            if let RealtimeEvent::ToolCall(call) = event {
+                session.send_tool_result(&call.id, "noon").await.unwrap();
+            }
+        }
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events.last(), Some(&RealtimeEvent::Closed));
+        assert_eq!(*session.audio.lock(), [1, 2, 3]);
+        assert_eq!(
+            *session.tool_results.lock(),
+            [("call_1".into(), "noon".into())]
+        );
+    }
+
+    #[test]
+    fn follows_the_session_lifecycle() {
+        let events = vec![
+            RealtimeEvent::Text("ignored before start".into()),
+            RealtimeEvent::SessionStarted,
+            RealtimeEvent::SpeechStarted,
+            RealtimeEvent::SpeechStopped,
+            RealtimeEvent::ResponseStarted,
+            RealtimeEvent::Audio(vec![0; 4]),
+            RealtimeEvent::SpeechStarted,
+            RealtimeEvent::ResponseDone {
+                cancelled: true,
+                usage: None,
+            },
+            RealtimeEvent::Closed,
+            RealtimeEvent::SessionStarted,
+        ];
+        let states: Vec<_> = events
+            .iter()
+            .scan(SessionState::default(), |state, event| {
+                *state = state.next(event);
+                Some(*state)
+            })
+            .collect();
+
+        assert_eq!(
+            states,
+            [
+                SessionState::Connecting,
+                SessionState::Listening,
+                SessionState::UserSpeaking,
+                SessionState::Listening,
+                SessionState::Responding,
+                SessionState::Responding,
+                SessionState::UserSpeaking,
+                SessionState::UserSpeaking,
+                SessionState::Closed,
+                SessionState::Closed,
+            ]
+        );
+        assert!(!SessionState::Closed.is_open());
+    }
+}
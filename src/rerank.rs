@@ -0,0 +1,139 @@
+//! # Rerank Module
+//!
+//! This module provides the [`Reranker`] trait for cross-encoder reranking models.
+//!
+//! Semantic search pipelines usually retrieve candidates with an
+//! [`EmbeddingModel`](crate::EmbeddingModel), then rerank them with a cross-encoder that
+//! scores each document against the query directly. Rerankers are slower than
+//! embeddings but considerably more accurate, so they are applied to a short list of
+//! candidates only.
+//!
+//! ```rust
+//! use ai_types::Reranker;
+//!
+//! async fn best_match<'a, R: Reranker>(
+//!     reranker: &R,
+//!     query: &str,
+//!     candidates: &[&'a str],
+//! ) -> ai_types::Result<Option<&'a str>> {
+//!     let results = reranker.rerank(query, candidates).await?;
+//!     Ok(results.first().map(|result| candidates[result.index]))
+//! }
+//! ```
+
+use alloc::vec::Vec;
+use core::future::Future;
+
+/// A document's relevance to a query, as scored by a [`Reranker`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RankedResult {
+    /// Index of the document in the slice passed to [`Reranker::rerank`].
+    pub index: usize,
+    /// Relevance score. Higher is more relevant; the scale is model-specific.
+    pub score: f32,
+}
+
+impl RankedResult {
+    /// Creates a new ranked result.
+    #[must_use]
+    pub const fn new(index: usize, score: f32) -> Self {
+        Self { index, score }
+    }
+}
+
+/// Scores documents by relevance to a query.
+///
+/// This trait provides a unified interface for rerankers (`Cohere`, `Voyage`, local
+/// cross-encoders, etc.), so they can be swapped like other models.
+///
+/// # Implementation Requirements
+///
+/// - Results must be sorted by descending [`score`](RankedResult::score)
+/// - Each [`index`](RankedResult::index) must refer to a document in the input slice
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{Reranker, rerank::RankedResult};
+///
+/// struct KeywordReranker;
+///
+/// impl Reranker for KeywordReranker {
+///     async fn rerank(&self, query: &str, documents: &[&str]) -> ai_types::Result<Vec<RankedResult>> {
+///         let mut results: Vec<_> = documents
+///             .iter()
+///             .enumerate()
+///             .map(|(index, document)| {
+///                 let hits = query.split_whitespace().filter(|word| document.contains(word)).count();
+///                 RankedResult::new(index, hits as f32)
+///             })
+///             .collect();
+///         results.sort_by(|a, b| b.score.total_cmp(&a.score));
+///         Ok(results)
+///     }
+/// }
+///
+/// # tokio_test::block_on(async {
+/// let results = KeywordReranker
+///     .rerank("rust async", &["python threads", "rust async runtimes"])
+///     .await
+///     .unwrap();
+/// assert_eq!(results[0].index, 1);
+/// # });
+/// ```
+pub trait Reranker {
+    /// Scores `documents` by relevance to `query`.
+    ///
+    /// # Returns
+    ///
+    /// The ranked documents, most relevant first.
+    fn rerank(
+        &self,
+        query: &str,
+        documents: &[&str],
+    ) -> impl Future<Output = crate::Result<Vec<RankedResult>>> + Send;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct LengthReranker;
+
+    impl Reranker for LengthReranker {
+        #[allow(clippy::cast_precision_loss)]
+        async fn rerank(
+            &self,
+            query: &str,
+            documents: &[&str],
+        ) -> crate::Result<Vec<RankedResult>> {
+            // Documents closer in length to the query rank higher.
+            let mut results: Vec<_> = documents
+                .iter()
+                .enumerate()
+                .map(|(index, document)| {
+                    RankedResult::new(index, -(document.len().abs_diff(query.len()) as f32))
+                })
+                .collect();
+            results.sort_by(|a, b| b.score.total_cmp(&a.score));
+            Ok(results)
+        }
+    }
+
+    #[tokio::test]
+    async fn rerank_sorts_by_score() {
+        let results = LengthReranker
+            .rerank("four", &["a long document", "tiny", "mid size"])
+            .await
+            .unwrap();
+
+        let order: Vec<_> = results.iter().map(|result| result.index).collect();
+        assert_eq!(order, [1, 2, 0]);
+    }
+
+    #[tokio::test]
+    async fn rerank_empty_documents() {
+        let results = LengthReranker.rerank("query", &[]).await.unwrap();
+        assert!(results.is_empty());
+    }
+}
@@ -0,0 +1,34 @@
+//! A small pseudo-random generator for jitter and simulations.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+const GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// The `SplitMix64` generator: tiny, fast, and of good statistical quality.
+///
+/// Not suitable for cryptography.
+#[derive(Debug)]
+pub struct SplitMix64(AtomicU64);
+
+impl SplitMix64 {
+    pub const fn new(seed: u64) -> Self {
+        Self(AtomicU64::new(seed))
+    }
+
+    pub fn next_u64(&self) -> u64 {
+        let state = self
+            .0
+            .fetch_add(GAMMA, Ordering::Relaxed)
+            .wrapping_add(GAMMA);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number uniformly distributed in `[0, 1)`.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn next_f64(&self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+}
@@ -0,0 +1,751 @@
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{
+    future::Future,
+    hash::Hasher,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use async_stream::stream;
+use futures_core::Stream;
+use spin::Mutex;
+
+use crate::{
+    AudioTranscriber, EmbeddingModel, ImageGenerator, LanguageModel,
+    audio::Segment,
+    error::{ErrorKind, ProviderError},
+    hash::Fnv1a,
+    image::{self, Data, Prompt, Size},
+    llm::{
+        Message, Request, TextStream, event::StreamEvent, logprobs::TokenLogprob,
+        metadata::ResponseMetadata, model::Profile, stream::text_stream, tool::ToolCall,
+        usage::Usage,
+    },
+    rng::SplitMix64,
+    time::{NoDelay, Timer},
+};
+
+/// A scripted response of a [`MockLanguageModel`].
+///
+/// A reply streams its events, then either fails with its error or ends with
+/// [`StreamEvent::Done`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MockReply {
+    events: Vec<StreamEvent>,
+    error: Option<ProviderError>,
+}
+
+impl MockReply {
+    /// Creates a reply streaming `text` in one chunk.
+    #[must_use]
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::chunks([text])
+    }
+
+    /// Creates a reply streaming `chunks` of text.
+    #[must_use]
+    pub fn chunks(chunks: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            events: chunks
+                .into_iter()
+                .map(|chunk| StreamEvent::Text(chunk.into()))
+                .collect(),
+            error: None,
+        }
+    }
+
+    /// Creates a reply failing with `error` before any output.
+    #[must_use]
+    pub fn error(error: ProviderError) -> Self {
+        Self::default().then_fail(error)
+    }
+
+    /// Adds a tool call after the text.
+    #[must_use]
+    pub fn with_tool_call(mut self, call: ToolCall) -> Self {
+        self.events.push(StreamEvent::ToolCall(call));
+        self
+    }
+
+    /// Reports `usage` at the end of the reply.
+    #[must_use]
+    pub fn with_usage(mut self, usage: Usage) -> Self {
+        self.events.push(StreamEvent::Usage(usage));
+        self
+    }
+
+    /// Reports the log probabilities of the text so far.
+    #[must_use]
+    pub fn with_logprobs(mut self, tokens: impl IntoIterator<Item = TokenLogprob>) -> Self {
+        self.events
+            .push(StreamEvent::Logprobs(tokens.into_iter().collect()));
+        self
+    }
+
+    /// Reports `metadata` at the end of the reply.
+    #[must_use]
+    pub fn with_metadata(mut self, metadata: ResponseMetadata) -> Self {
+        self.events.push(StreamEvent::Metadata(metadata));
+        self
+    }
+
+    /// Fails with `error` after the events, instead of ending normally.
+    #[must_use]
+    pub fn then_fail(mut self, error: ProviderError) -> Self {
+        self.error = Some(error);
+        self
+    }
+}
+
+impl From<&str> for MockReply {
+    fn from(text: &str) -> Self {
+        Self::text(text)
+    }
+}
+
+impl From<String> for MockReply {
+    fn from(text: String) -> Self {
+        Self::text(text)
+    }
+}
+
+/// A language model answering with scripted replies, for unit tests.
+///
+/// Each request gets the next reply of the script; once the script is exhausted, its last
+/// reply is repeated, and a model without a script answers with empty text. Every request
+/// is recorded, so tests can assert on what was sent.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{
+///     LanguageModel,
+///     error::{ErrorKind, ProviderError},
+///     llm::Request,
+///     test_util::{MockLanguageModel, MockReply},
+/// };
+///
+/// # tokio_test::block_on(async {
+/// let model = MockLanguageModel::new()
+///     .with_reply(MockReply::error(ProviderError::new(ErrorKind::RateLimited, "Slow down")))
+///     .with_reply("Hello!");
+///
+/// let request = Request::oneshot("Be brief", "Hi");
+/// assert!(model.respond(request.clone()).await.is_err());
+/// assert_eq!(model.respond(request).await.unwrap(), "Hello!");
+/// assert_eq!(model.requests().len(), 2);
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct MockLanguageModel<T = NoDelay> {
+    replies: Vec<MockReply>,
+    profile: Profile,
+    latency: Duration,
+    timer: T,
+    requests: Mutex<Vec<Request>>,
+}
+
+impl MockLanguageModel {
+    /// Creates a model without a script, answering with empty text.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            replies: Vec::new(),
+            profile: Profile::new("mock", "Mock model for testing", 128_000),
+            latency: Duration::ZERO,
+            timer: NoDelay,
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl Default for MockLanguageModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Timer> MockLanguageModel<T> {
+    /// Adds `reply` to the script.
+    #[must_use]
+    pub fn with_reply(mut self, reply: impl Into<MockReply>) -> Self {
+        self.replies.push(reply.into());
+        self
+    }
+
+    /// Adds `replies` to the script.
+    #[must_use]
+    pub fn with_replies(mut self, replies: impl IntoIterator<Item = impl Into<MockReply>>) -> Self {
+        self.replies.extend(replies.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets the profile of the model.
+    #[must_use]
+    pub fn with_profile(mut self, profile: Profile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Sets the delay before each reply.
+    #[must_use]
+    pub const fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Sets the timer used to simulate latency.
+    #[must_use]
+    pub fn with_timer<U: Timer>(self, timer: U) -> MockLanguageModel<U> {
+        MockLanguageModel {
+            replies: self.replies,
+            profile: self.profile,
+            latency: self.latency,
+            timer,
+            requests: self.requests,
+        }
+    }
+
+    /// Returns the requests received so far, in order.
+    ///
+    /// Completions are recorded as requests with a single user message.
+    #[must_use]
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().clone()
+    }
+
+    /// Records `request` and returns its reply.
+    fn reply(&self, request: Request) -> MockReply {
+        let mut requests = self.requests.lock();
+        let reply = scripted(&self.replies, requests.len()).cloned();
+        requests.push(request);
+        reply.unwrap_or_else(|| MockReply::text(""))
+    }
+
+    fn events(
+        &self,
+        request: Request,
+    ) -> impl Stream<Item = Result<StreamEvent, ProviderError>> + Send {
+        let reply = self.reply(request);
+        stream! {
+            if !self.latency.is_zero() {
+                self.timer.sleep(self.latency).await;
+            }
+            for event in reply.events {
+                yield Ok(event);
+            }
+            match reply.error {
+                Some(error) => yield Err(error),
+                None => yield Ok(StreamEvent::Done),
+            }
+        }
+    }
+
+    fn text(&self, request: Request) -> impl TextStream<Error = ProviderError> + Send {
+        let reply = self.reply(request);
+        text_stream(stream! {
+            if !self.latency.is_zero() {
+                self.timer.sleep(self.latency).await;
+            }
+            for event in reply.events {
+                if let StreamEvent::Text(chunk) = event {
+                    yield Ok(chunk);
+                }
+            }
+            if let Some(error) = reply.error {
+                yield Err(error);
+            }
+        })
+    }
+}
+
+impl<T: Timer + 'static> LanguageModel for MockLanguageModel<T> {
+    type Error = ProviderError;
+
+    fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+        self.text(request)
+    }
+
+    fn respond_events(
+        &self,
+        request: Request,
+    ) -> impl Stream<Item = Result<StreamEvent, Self::Error>> + Send {
+        self.events(request)
+    }
+
+    fn complete(&self, prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+        self.text(Request::new([Message::user(prefix)]))
+    }
+
+    fn profile(&self) -> Profile {
+        self.profile.clone()
+    }
+}
+
+/// An embedding model returning deterministic embeddings, for unit tests.
+///
+/// By default, each text is embedded as a pseudo-random unit vector derived from a hash
+/// of the text, so equal texts get equal embeddings and different texts almost
+/// orthogonal ones. Specific embeddings can be scripted per text, and the first calls can
+/// be made to fail.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{EmbeddingModel, test_util::MockEmbeddingModel};
+///
+/// # tokio_test::block_on(async {
+/// let model = MockEmbeddingModel::new(3)
+///     .with_embedding("cat", [1.0, 0.0, 0.0])
+///     .with_failures(1);
+///
+/// assert!(model.embed("cat").await.is_err());
+/// assert_eq!(model.embed("cat").await.unwrap(), [1.0, 0.0, 0.0]);
+/// assert_eq!(model.embed("dog").await.unwrap(), model.embed("dog").await.unwrap());
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct MockEmbeddingModel<T = NoDelay> {
+    dim: usize,
+    embeddings: BTreeMap<String, Vec<f32>>,
+    failures: AtomicUsize,
+    latency: Duration,
+    timer: T,
+    texts: Mutex<Vec<String>>,
+}
+
+impl MockEmbeddingModel {
+    /// Creates a model producing embeddings of `dim` dimensions.
+    #[must_use]
+    pub const fn new(dim: usize) -> Self {
+        Self {
+            dim,
+            embeddings: BTreeMap::new(),
+            failures: AtomicUsize::new(0),
+            latency: Duration::ZERO,
+            timer: NoDelay,
+            texts: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl<T: Timer> MockEmbeddingModel<T> {
+    /// Embeds `text` as `embedding`.
+    #[must_use]
+    pub fn with_embedding(
+        mut self,
+        text: impl Into<String>,
+        embedding: impl IntoIterator<Item = f32>,
+    ) -> Self {
+        self.embeddings
+            .insert(text.into(), embedding.into_iter().collect());
+        self
+    }
+
+    /// Fails the next `count` calls.
+    #[must_use]
+    pub fn with_failures(self, count: usize) -> Self {
+        self.failures.store(count, Ordering::Relaxed);
+        self
+    }
+
+    /// Sets the delay before each embedding.
+    #[must_use]
+    pub const fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Sets the timer used to simulate latency.
+    #[must_use]
+    pub fn with_timer<U: Timer>(self, timer: U) -> MockEmbeddingModel<U> {
+        MockEmbeddingModel {
+            dim: self.dim,
+            embeddings: self.embeddings,
+            failures: self.failures,
+            latency: self.latency,
+            timer,
+            texts: self.texts,
+        }
+    }
+
+    /// Returns the texts embedded so far, in order, including failed calls.
+    #[must_use]
+    pub fn texts(&self) -> Vec<String> {
+        self.texts.lock().clone()
+    }
+
+    /// Returns the hash-derived unit vector of `text`.
+    #[allow(clippy::cast_possible_truncation)]
+    fn derive(&self, text: &str) -> Vec<f32> {
+        let mut hasher = Fnv1a::new();
+        hasher.write(text.as_bytes());
+        let random = SplitMix64::new(hasher.finish());
+        let vector: Vec<f64> = (0..self.dim)
+            .map(|_| random.next_f64() * 2.0 - 1.0)
+            .collect();
+        let norm = crate::embedding::sqrt(vector.iter().map(|x| x * x).sum());
+        vector
+            .into_iter()
+            .map(|x| if norm > 0.0 { (x / norm) as f32 } else { 0.0 })
+            .collect()
+    }
+}
+
+impl<T: Timer> EmbeddingModel for MockEmbeddingModel<T> {
+    fn dim(&self) -> usize {
+        self.dim
+    }
+
+    fn embed(&self, text: &str) -> impl Future<Output = crate::Result<Vec<f32>>> + Send {
+        self.texts.lock().push(text.to_string());
+        let fail = take_failure(&self.failures);
+        let embedding = self
+            .embeddings
+            .get(text)
+            .cloned()
+            .unwrap_or_else(|| self.derive(text));
+        async move {
+            if !self.latency.is_zero() {
+                self.timer.sleep(self.latency).await;
+            }
+            if fail {
+                return Err(anyhow::anyhow!("Injected embedding failure"));
+            }
+            Ok(embedding)
+        }
+    }
+}
+
+/// An image generator returning scripted images, for unit tests.
+///
+/// By default, each image is the UTF-8 bytes of its prompt, so tests can check which
+/// prompt produced it. Scripted images replace it, streamed in order as progressively
+/// better versions. The first calls can be made to fail with a
+/// [`ProviderUnavailable`](ErrorKind::ProviderUnavailable) error.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{ImageGenerator, image::{Prompt, Size}, test_util::MockImageGenerator};
+/// use futures_lite::StreamExt;
+///
+/// # tokio_test::block_on(async {
+/// let generator = MockImageGenerator::new();
+/// let mut images = generator.create(Prompt::new("A lighthouse"), Size::square(512));
+/// assert_eq!(images.next().await.unwrap().unwrap(), b"A lighthouse");
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct MockImageGenerator<T = NoDelay> {
+    images: Vec<Data>,
+    failures: AtomicUsize,
+    latency: Duration,
+    timer: T,
+    prompts: Mutex<Vec<String>>,
+}
+
+impl MockImageGenerator {
+    /// Creates a generator returning the bytes of each prompt as its image.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            images: Vec::new(),
+            failures: AtomicUsize::new(0),
+            latency: Duration::ZERO,
+            timer: NoDelay,
+            prompts: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl Default for MockImageGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Timer> MockImageGenerator<T> {
+    /// Adds `image` to the images streamed by every call.
+    #[must_use]
+    pub fn with_image(mut self, image: impl Into<Data>) -> Self {
+        self.images.push(image.into());
+        self
+    }
+
+    /// Fails the next `count` calls.
+    #[must_use]
+    pub fn with_failures(self, count: usize) -> Self {
+        self.failures.store(count, Ordering::Relaxed);
+        self
+    }
+
+    /// Sets the delay before each image.
+    #[must_use]
+    pub const fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Sets the timer used to simulate latency.
+    #[must_use]
+    pub fn with_timer<U: Timer>(self, timer: U) -> MockImageGenerator<U> {
+        MockImageGenerator {
+            images: self.images,
+            failures: self.failures,
+            latency: self.latency,
+            timer,
+            prompts: self.prompts,
+        }
+    }
+
+    /// Returns the text of the prompts received so far, in order.
+    #[must_use]
+    pub fn prompts(&self) -> Vec<String> {
+        self.prompts.lock().clone()
+    }
+
+    fn images(
+        &self,
+        prompt: String,
+    ) -> impl Stream<Item = Result<Data, ProviderError>> + Unpin + Send {
+        let fail = take_failure(&self.failures);
+        let images = if self.images.is_empty() {
+            vec![prompt.as_bytes().to_vec()]
+        } else {
+            self.images.clone()
+        };
+        self.prompts.lock().push(prompt);
+        Box::pin(stream! {
+            if fail {
+                yield Err(ProviderError::new(
+                    ErrorKind::ProviderUnavailable,
+                    "Injected image generation failure",
+                ));
+                return;
+            }
+            for image in images {
+                if !self.latency.is_zero() {
+                    self.timer.sleep(self.latency).await;
+                }
+                yield Ok(image);
+            }
+        })
+    }
+}
+
+impl<T: Timer> ImageGenerator for MockImageGenerator<T> {
+    type Error = ProviderError;
+
+    fn create(
+        &self,
+        prompt: Prompt,
+        _size: Size,
+    ) -> impl Stream<Item = Result<Data, Self::Error>> + Unpin + Send {
+        self.images(prompt.text().to_string())
+    }
+
+    fn edit(
+        &self,
+        prompt: Prompt,
+        _mask: &[u8],
+    ) -> impl Stream<Item = Result<Data, Self::Error>> + Unpin + Send {
+        self.images(prompt.text().to_string())
+    }
+
+    fn profile(&self) -> image::Profile {
+        image::Profile::new("mock", "Mock image generator for testing").with_edit(true)
+    }
+}
+
+/// An audio transcriber returning scripted segments, for unit tests.
+///
+/// By default, audio is transcribed by reading it as UTF-8 text, so tests can pass speech
+/// as text. Scripted segments replace it for every call.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{AudioTranscriber, test_util::MockAudioTranscriber};
+/// use futures_lite::StreamExt;
+///
+/// # tokio_test::block_on(async {
+/// let transcriber = MockAudioTranscriber::new();
+/// let segments: Vec<_> = transcriber.transcribe(b"Hello there").collect().await;
+/// assert_eq!(segments[0].text, "Hello there");
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct MockAudioTranscriber<T = NoDelay> {
+    segments: Vec<Segment>,
+    latency: Duration,
+    timer: T,
+    calls: AtomicUsize,
+}
+
+impl MockAudioTranscriber {
+    /// Creates a transcriber reading audio as UTF-8 text.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            segments: Vec::new(),
+            latency: Duration::ZERO,
+            timer: NoDelay,
+            calls: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl Default for MockAudioTranscriber {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Timer> MockAudioTranscriber<T> {
+    /// Adds `segment` to the segments returned by every call.
+    #[must_use]
+    pub fn with_segment(mut self, segment: Segment) -> Self {
+        self.segments.push(segment);
+        self
+    }
+
+    /// Sets the delay before each segment.
+    #[must_use]
+    pub const fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Sets the timer used to simulate latency.
+    #[must_use]
+    pub fn with_timer<U: Timer>(self, timer: U) -> MockAudioTranscriber<U> {
+        MockAudioTranscriber {
+            segments: self.segments,
+            latency: self.latency,
+            timer,
+            calls: self.calls,
+        }
+    }
+
+    /// Returns the number of transcriptions so far.
+    #[must_use]
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::Relaxed)
+    }
+}
+
+impl<T: Timer> AudioTranscriber for MockAudioTranscriber<T> {
+    fn transcribe(&self, audio: &[u8]) -> impl Stream<Item = Segment> + Send {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        let segments = if self.segments.is_empty() {
+            vec![Segment::new(String::from_utf8_lossy(audio).into_owned())]
+        } else {
+            self.segments.clone()
+        };
+        stream! {
+            for segment in segments {
+                if !self.latency.is_zero() {
+                    self.timer.sleep(self.latency).await;
+                }
+                yield segment;
+            }
+        }
+    }
+}
+
+/// Returns the reply for call number `call`, repeating the last one.
+fn scripted<R>(replies: &[R], call: usize) -> Option<&R> {
+    replies.get(call).or_else(|| replies.last())
+}
+
+/// Consumes one injected failure, returning whether the call fails.
+fn take_failure(failures: &AtomicUsize) -> bool {
+    failures
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+            left.checked_sub(1)
+        })
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedding::cosine_similarity;
+    use alloc::sync::Arc;
+    use futures_lite::StreamExt;
+
+    #[tokio::test]
+    async fn language_model_follows_its_script() {
+        let sleeps = Arc::new(Mutex::new(Vec::new()));
+        let recorded = sleeps.clone();
+        let timeout = ProviderError::new(ErrorKind::Timeout, "Too slow");
+        let model = MockLanguageModel::new()
+            .with_reply(MockReply::chunks(["Hel", "lo"]).then_fail(timeout.clone()))
+            .with_reply(
+                MockReply::text("Calling")
+                    .with_tool_call(ToolCall::new("1", "clock", "{}"))
+                    .with_usage(Usage::new(3, 1)),
+            )
+            .with_latency(Duration::from_millis(50))
+            .with_timer(move |duration| {
+                recorded.lock().push(duration);
+                core::future::ready(())
+            });
+
+        assert_eq!(model.respond(Request::default()).await, Err(timeout));
+        let events: Vec<_> = model
+            .respond_events(Request::oneshot("", "What time is it?"))
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            events[1],
+            StreamEvent::ToolCall(ToolCall::new("1", "clock", "{}"))
+        );
+        assert_eq!(events[3], StreamEvent::Done);
+        assert_eq!(model.complete("Once").await.unwrap(), "Calling");
+
+        assert_eq!(model.requests().len(), 3);
+        assert_eq!(sleeps.lock().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn other_mocks_are_deterministic() {
+        let model = MockEmbeddingModel::new(8).with_failures(1);
+        assert!(model.embed("cat").await.is_err());
+        let cat = model.embed("cat").await.unwrap();
+        let dog = model.embed("dog").await.unwrap();
+        assert_eq!(cat.len(), 8);
+        assert_eq!(cat, model.embed("cat").await.unwrap());
+        assert!((cosine_similarity(&cat, &cat) - 1.0).abs() < 1e-5);
+        assert!(cosine_similarity(&cat, &dog) < 0.99);
+        assert_eq!(model.texts(), ["cat", "cat", "dog", "cat"]);
+
+        let generator = MockImageGenerator::new().with_failures(1);
+        let prompt = Prompt::new("A boat");
+        let failed: Vec<_> = generator
+            .create(prompt.clone(), Size::square(64))
+            .collect()
+            .await;
+        assert!(failed[0].is_err());
+        let images: Vec<_> = generator
+            .create(prompt, Size::square(64))
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(images, [b"A boat".to_vec()]);
+
+        let transcriber = MockAudioTranscriber::new().with_segment(Segment::new("Scripted"));
+        let segments: Vec<_> = transcriber.transcribe(b"ignored").collect().await;
+        assert_eq!(segments, [Segment::new("Scripted")]);
+        assert_eq!(transcriber.calls(), 1);
+    }
+}
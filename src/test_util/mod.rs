@@ -0,0 +1,22 @@
+//! Test utilities for code built on this crate.
+//!
+//! These models never contact a provider, so applications can test and load-test their
+//! pipelines deterministically and offline.
+//!
+//! - [`SyntheticModel`] streams generated text with configurable chunking, token rate,
+//!   latency, and injected errors.
+//! - [`MockLanguageModel`], [`MockEmbeddingModel`], [`MockImageGenerator`] and
+//!   [`MockAudioTranscriber`] return scripted or deterministic results, with optional
+//!   latency and injected failures, for unit tests.
+//! - [`NeedleTest`] measures how much of its declared context length a model can recall
+//!   facts from.
+
+mod mock;
+mod needle;
+mod synthetic;
+
+pub use mock::{
+    MockAudioTranscriber, MockEmbeddingModel, MockImageGenerator, MockLanguageModel, MockReply,
+};
+pub use needle::{NeedleReport, NeedleResult, NeedleTest};
+pub use synthetic::{SyntheticError, SyntheticModel};
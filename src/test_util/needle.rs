@@ -0,0 +1,332 @@
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::Write;
+
+use serde_json::Value;
+
+use crate::{
+    LanguageModel,
+    llm::{
+        Request,
+        probe::ProbeOutcome,
+        token::{Estimator, TokenCounter},
+    },
+    rng::SplitMix64,
+};
+
+/// Filler sentences surrounding the planted fact.
+const FILLER: [&str; 4] = [
+    "The committee reviewed the quarterly figures and agreed to meet again next week. ",
+    "Rain is expected along the coast, with clearer skies further inland by evening. ",
+    "The library extended its opening hours during the examination period this spring. ",
+    "Several suppliers reported delays caused by congestion at the northern port. ",
+];
+
+/// Words used to build the codes of planted facts.
+const WORDS: [&str; 8] = [
+    "HERON", "MAPLE", "COBALT", "LANTERN", "QUARTZ", "SPARROW", "TUNDRA", "VELVET",
+];
+
+/// Measures how much of its context window a model can actually use.
+///
+/// Providers declare a [`context_length`](crate::llm::model::Profile::context_length),
+/// but recall often degrades well before it. A needle test plants a fact, an access
+/// code, at a given depth of a synthetic document of a given length, and asks the model
+/// to return it through [structured generation](LanguageModel::generate). Every
+/// combination of length and depth is tested, one request each.
+///
+/// By default, lengths are an eighth, a quarter, half, three quarters and all of the
+/// declared context length, and depths are the start, a quarter, the middle, three
+/// quarters and the end of the document. Codes are pseudo-random but deterministic.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{LanguageModel, test_util::NeedleTest};
+///
+/// async fn benchmark(model: impl LanguageModel) {
+///     let report = NeedleTest::new()
+///         .with_lengths([4_000, 32_000, 128_000])
+///         .with_depths([0.1, 0.5, 0.9])
+///         .run(&model)
+///         .await;
+///     println!(
+///         "{} recalls facts reliably up to {} of {} declared tokens",
+///         report.model,
+///         report.effective_context_length(),
+///         report.declared_context_length,
+///     );
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct NeedleTest {
+    lengths: Vec<usize>,
+    depths: Vec<f64>,
+    seed: u64,
+}
+
+impl NeedleTest {
+    /// Creates a test with the default lengths and depths.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            lengths: Vec::new(),
+            depths: Vec::new(),
+            seed: 0,
+        }
+    }
+
+    /// Sets the document lengths to test, in estimated tokens.
+    #[must_use]
+    pub fn with_lengths(mut self, lengths: impl IntoIterator<Item = usize>) -> Self {
+        self.lengths = lengths.into_iter().collect();
+        self
+    }
+
+    /// Sets the depths to plant the fact at, from `0.0` for the start of the document to
+    /// `1.0` for its end.
+    #[must_use]
+    pub fn with_depths(mut self, depths: impl IntoIterator<Item = f64>) -> Self {
+        self.depths = depths.into_iter().map(|d| d.clamp(0.0, 1.0)).collect();
+        self
+    }
+
+    /// Sets the seed the codes of planted facts are derived from.
+    #[must_use]
+    pub const fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Runs every combination of length and depth against `model`, one after another.
+    pub async fn run<M: LanguageModel>(&self, model: &M) -> NeedleReport {
+        let profile = model.profile();
+        let declared = profile.context_length as usize;
+        let mut lengths = if self.lengths.is_empty() {
+            alloc::vec![
+                declared / 8,
+                declared / 4,
+                declared / 2,
+                declared / 4 * 3,
+                declared
+            ]
+        } else {
+            self.lengths.clone()
+        };
+        lengths.sort_unstable();
+        lengths.dedup();
+        let depths = if self.depths.is_empty() {
+            alloc::vec![0.0, 0.25, 0.5, 0.75, 1.0]
+        } else {
+            self.depths.clone()
+        };
+
+        let random = SplitMix64::new(self.seed);
+        let mut results = Vec::with_capacity(lengths.len() * depths.len());
+        for &tokens in &lengths {
+            for &depth in &depths {
+                let code = code(&random);
+                let outcome = recall(model, &haystack(tokens, depth, &code), &code).await;
+                results.push(NeedleResult {
+                    tokens,
+                    depth,
+                    outcome,
+                });
+            }
+        }
+        NeedleReport {
+            model: profile.name,
+            declared_context_length: profile.context_length,
+            results,
+        }
+    }
+}
+
+/// The results of a [`NeedleTest`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct NeedleReport {
+    /// The name of the tested model, from its profile.
+    pub model: String,
+    /// The context length declared by the model's profile.
+    pub declared_context_length: u32,
+    /// One result per combination of length and depth, shortest documents first.
+    pub results: Vec<NeedleResult>,
+}
+
+impl NeedleReport {
+    /// Returns the longest tested length at which the fact was recalled at every depth,
+    /// as were all shorter lengths, or `0` if the shortest length already failed.
+    #[must_use]
+    pub fn effective_context_length(&self) -> usize {
+        let mut effective = 0;
+        let mut results = self.results.iter().peekable();
+        while let Some(first) = results.peek() {
+            let tokens = first.tokens;
+            let mut passed = true;
+            while let Some(result) = results.next_if(|r| r.tokens == tokens) {
+                passed &= result.outcome.is_passed();
+            }
+            if !passed {
+                break;
+            }
+            effective = tokens;
+        }
+        effective
+    }
+
+    /// Returns the fraction of cases in which the fact was recalled.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn recall_rate(&self) -> f64 {
+        if self.results.is_empty() {
+            return 0.0;
+        }
+        let passed = self
+            .results
+            .iter()
+            .filter(|r| r.outcome.is_passed())
+            .count();
+        passed as f64 / self.results.len() as f64
+    }
+}
+
+/// The outcome of one case of a [`NeedleTest`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct NeedleResult {
+    /// The length of the document, in estimated tokens.
+    pub tokens: usize,
+    /// Where the fact was planted, from `0.0` for the start to `1.0` for the end.
+    pub depth: f64,
+    /// Whether the model recalled the fact.
+    pub outcome: ProbeOutcome,
+}
+
+fn code(random: &SplitMix64) -> String {
+    let value = random.next_u64();
+    let word = WORDS[(value & 7) as usize];
+    format!("{}-{word}", 1000 + (value >> 32) % 9000)
+}
+
+/// Builds a document of about `tokens` tokens with the fact planted at `depth`, followed
+/// by the question.
+fn haystack(tokens: usize, depth: f64, code: &str) -> String {
+    let estimator = Estimator::new();
+    let filler = FILLER
+        .iter()
+        .map(|s| estimator.count_tokens(s))
+        .sum::<usize>()
+        / FILLER.len();
+    let sentences = (tokens / filler.max(1)).max(1);
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    let position = (depth * sentences as f64).round() as usize;
+
+    let mut document = String::from("Document:\n");
+    for index in 0..=sentences {
+        if index == position {
+            let _ = write!(document, "The access code for the archive room is {code}. ");
+        }
+        if index < sentences {
+            document.push_str(FILLER[index % FILLER.len()]);
+        }
+    }
+    document.push_str("\n\nWhat is the access code for the archive room?");
+    document
+}
+
+async fn recall<M: LanguageModel>(model: &M, document: &str, code: &str) -> ProbeOutcome {
+    let request = Request::oneshot(
+        "Answer from the document the user provides, with a JSON object whose `code` field \
+         holds the answer.",
+        document,
+    );
+    match model.generate::<BTreeMap<String, Value>>(request).await {
+        Ok(object) => match object.get("code").and_then(Value::as_str) {
+            Some(answer) if answer.contains(code) => ProbeOutcome::Passed,
+            Some(answer) => ProbeOutcome::Failed(format!("Expected {code}, got `{answer}`")),
+            None => ProbeOutcome::Failed("The object has no `code` string".into()),
+        },
+        Err(error) if error.is::<serde_json::Error>() => {
+            ProbeOutcome::Failed(format!("Invalid JSON: {error}"))
+        }
+        Err(error) => ProbeOutcome::Error(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{TextStream, model::Profile, stream::text_stream};
+    use alloc::vec;
+    use core::convert::Infallible;
+
+    /// Recalls codes planted within the first `window` bytes of the document.
+    struct Forgetful {
+        window: usize,
+    }
+
+    impl LanguageModel for Forgetful {
+        type Error = Infallible;
+
+        fn respond(&self, request: Request) -> impl TextStream<Error = Self::Error> + Send {
+            let document = request.messages[1].content().into_owned();
+            let visible = &document[..self.window.min(document.len())];
+            let code = visible
+                .find("room is ")
+                .map(|start| &visible[start + 8..])
+                .and_then(|rest| rest.split('.').next())
+                .unwrap_or("unknown");
+            let answer = format!(r#"{{"code": "{code}"}}"#);
+            text_stream(futures_lite::stream::iter(vec![Ok(answer)]))
+        }
+
+        fn complete(&self, _prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+            text_stream(futures_lite::stream::empty())
+        }
+
+        fn profile(&self) -> Profile {
+            Profile::new("forgetful", "Only reads the start of documents", 8000)
+        }
+    }
+
+    #[test]
+    fn haystack_plants_fact_at_depth() {
+        let document = haystack(2000, 0.25, "1234-HERON");
+        let tokens = Estimator::new().count_tokens(&document);
+        assert!((1800..2200).contains(&tokens), "{tokens} tokens");
+
+        let needle = document.find("1234-HERON").unwrap();
+        assert!(needle.abs_diff(document.len() / 4) < document.len() / 20);
+        assert!(haystack(500, 0.0, "X").starts_with("Document:\nThe access code"));
+    }
+
+    #[tokio::test]
+    async fn finds_effective_context_length() {
+        let model = Forgetful { window: 10_000 };
+        let report = NeedleTest::new().run(&model).await;
+
+        assert_eq!(report.declared_context_length, 8000);
+        assert_eq!(report.results.len(), 25);
+        assert_eq!(report.effective_context_length(), 2000);
+        assert!(report.recall_rate() > 0.5 && report.recall_rate() < 1.0);
+
+        let report = NeedleTest::new()
+            .with_lengths([100])
+            .with_depths([1.0])
+            .run(&Forgetful { window: 10 })
+            .await;
+        assert!(matches!(report.results[0].outcome, ProbeOutcome::Failed(_)));
+        assert_eq!(report.effective_context_length(), 0);
+    }
+}
@@ -0,0 +1,321 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use async_stream::stream;
+
+use crate::{
+    LanguageModel,
+    error::{Classify, ErrorKind},
+    llm::{Request, TextStream, model::Profile, stream::text_stream},
+    rng::SplitMix64,
+    time::{NoDelay, Timer},
+};
+
+/// A language model that streams synthetic text, for load testing.
+///
+/// The response is split into whitespace-separated tokens and streamed in chunks of
+/// [`chunk_size`](Self::with_chunk_size) tokens. A [`Timer`] simulates the time to first
+/// token and the token rate; the default [`NoDelay`] timer streams as fast as possible.
+///
+/// Errors can be injected at a given rate. They are pseudo-random but deterministic: the
+/// same seed always fails the same chunks of the same requests. Injected errors are
+/// classified as [`ProviderUnavailable`](ErrorKind::ProviderUnavailable), so they exercise
+/// retry logic.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{LanguageModel, llm::Request, test_util::SyntheticModel};
+/// use core::time::Duration;
+/// use futures_lite::StreamExt;
+///
+/// # tokio_test::block_on(async {
+/// let model = SyntheticModel::new()
+///     .with_response("one two three four five")
+///     .with_chunk_size(2)
+///     .with_latency(Duration::from_millis(200))
+///     .with_tokens_per_second(50.0)
+///     .with_timer(tokio::time::sleep);
+///
+/// let chunks: Vec<_> = model.respond(Request::oneshot("", "Hi")).try_collect().await.unwrap();
+/// assert_eq!(chunks, ["one two ", "three four ", "five"]);
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct SyntheticModel<T = NoDelay> {
+    response: String,
+    chunk_size: usize,
+    latency: Duration,
+    tokens_per_second: Option<f64>,
+    error_rate: f64,
+    seed: u64,
+    requests: AtomicU64,
+    timer: T,
+}
+
+impl SyntheticModel {
+    /// Creates a model that streams a fixed lorem ipsum response, one token per chunk,
+    /// without delays or errors.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            response: "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod \
+                       tempor incididunt ut labore et dolore magna aliqua."
+                .to_string(),
+            chunk_size: 1,
+            latency: Duration::ZERO,
+            tokens_per_second: None,
+            error_rate: 0.0,
+            seed: 0,
+            requests: AtomicU64::new(0),
+            timer: NoDelay,
+        }
+    }
+}
+
+impl Default for SyntheticModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Timer> SyntheticModel<T> {
+    /// Sets the text streamed in response to every request.
+    #[must_use]
+    pub fn with_response(mut self, response: impl Into<String>) -> Self {
+        self.response = response.into();
+        self
+    }
+
+    /// Streams `tokens` synthetic tokens in response to every request.
+    #[must_use]
+    pub fn with_tokens(self, tokens: usize) -> Self {
+        let response = (0..tokens)
+            .map(|index| alloc::format!("token{index}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.with_response(response)
+    }
+
+    /// Sets the number of tokens per streamed chunk. Values below one are treated as one.
+    #[must_use]
+    pub const fn with_chunk_size(mut self, tokens: usize) -> Self {
+        self.chunk_size = if tokens == 0 { 1 } else { tokens };
+        self
+    }
+
+    /// Sets the delay before the first chunk.
+    #[must_use]
+    pub const fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Sets the generation speed. Each chunk is delayed accordingly.
+    #[must_use]
+    pub const fn with_tokens_per_second(mut self, rate: f64) -> Self {
+        self.tokens_per_second = Some(rate);
+        self
+    }
+
+    /// Sets the probability, between 0 and 1, that a chunk fails with a
+    /// [`SyntheticError`]. A failed chunk ends the stream.
+    #[must_use]
+    pub const fn with_error_rate(mut self, rate: f64) -> Self {
+        self.error_rate = rate;
+        self
+    }
+
+    /// Sets the seed deciding which chunks fail.
+    #[must_use]
+    pub const fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the timer used to simulate latency and token rate.
+    #[must_use]
+    pub fn with_timer<U: Timer>(self, timer: U) -> SyntheticModel<U> {
+        SyntheticModel {
+            response: self.response,
+            chunk_size: self.chunk_size,
+            latency: self.latency,
+            tokens_per_second: self.tokens_per_second,
+            error_rate: self.error_rate,
+            seed: self.seed,
+            requests: self.requests,
+            timer,
+        }
+    }
+
+    /// Returns the number of requests served so far.
+    #[must_use]
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    fn chunks(&self) -> Vec<String> {
+        let tokens: Vec<&str> = self.response.split_inclusive(char::is_whitespace).collect();
+        tokens
+            .chunks(self.chunk_size)
+            .map(<[&str]>::concat)
+            .collect()
+    }
+
+    fn stream(&self) -> impl TextStream<Error = SyntheticError> + Send + '_ {
+        let request = self.requests.fetch_add(1, Ordering::Relaxed);
+        let chunks = self.chunks();
+
+        text_stream(stream! {
+            if !self.latency.is_zero() {
+                self.timer.sleep(self.latency).await;
+            }
+            let random = SplitMix64::new(self.seed ^ request.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+            for (index, chunk) in chunks.into_iter().enumerate() {
+                if let Some(rate) = self.tokens_per_second.filter(|rate| *rate > 0.0) {
+                    let tokens = chunk.split_whitespace().count().max(1);
+                    #[allow(clippy::cast_precision_loss)]
+                    let delay = Duration::try_from_secs_f64(tokens as f64 / rate);
+                    self.timer.sleep(delay.unwrap_or(Duration::MAX)).await;
+                }
+                if random.next_f64() < self.error_rate {
+                    yield Err(SyntheticError { request, chunk: index });
+                    break;
+                }
+                yield Ok(chunk);
+            }
+        })
+    }
+}
+
+impl<T: Timer + 'static> LanguageModel for SyntheticModel<T> {
+    type Error = SyntheticError;
+
+    fn respond(&self, _request: Request) -> impl TextStream<Error = Self::Error> + Send {
+        self.stream()
+    }
+
+    fn complete(&self, _prefix: &str) -> impl TextStream<Error = Self::Error> + Send {
+        self.stream()
+    }
+
+    fn profile(&self) -> Profile {
+        Profile::new("synthetic", "Synthetic model for load testing", 128_000)
+    }
+}
+
+/// An error injected by [`SyntheticModel`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyntheticError {
+    /// Index of the failed request, counting from zero.
+    pub request: u64,
+    /// Index of the failed chunk within the response.
+    pub chunk: usize,
+}
+
+impl fmt::Display for SyntheticError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Synthetic failure in request {} at chunk {}",
+            self.request, self.chunk
+        )
+    }
+}
+
+impl core::error::Error for SyntheticError {}
+
+impl Classify for SyntheticError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::ProviderUnavailable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{sync::Arc, vec};
+    use futures_lite::StreamExt;
+    use spin::Mutex;
+
+    #[tokio::test]
+    async fn streams_chunks() {
+        let model = SyntheticModel::new().with_tokens(5).with_chunk_size(2);
+        let chunks: Vec<_> = model
+            .respond(Request::default())
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(chunks, ["token0 token1 ", "token2 token3 ", "token4"]);
+        assert_eq!(model.requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn errors_are_deterministic() {
+        let model = || {
+            SyntheticModel::new()
+                .with_tokens(50)
+                .with_error_rate(0.1)
+                .with_seed(7)
+        };
+
+        let first: Vec<_> = model().respond(Request::default()).collect().await;
+        let second: Vec<_> = model().respond(Request::default()).collect().await;
+        assert_eq!(first, second);
+        assert!(first.last().unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn never_fails_without_error_rate() {
+        let model = SyntheticModel::new().with_tokens(100);
+        assert!(model.respond(Request::default()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn simulates_latency_and_rate() {
+        let sleeps = Arc::new(Mutex::new(Vec::new()));
+        let recorded = sleeps.clone();
+        let model = SyntheticModel::new()
+            .with_tokens(4)
+            .with_chunk_size(2)
+            .with_latency(Duration::from_millis(300))
+            .with_tokens_per_second(10.0)
+            .with_timer(move |duration| {
+                recorded.lock().push(duration);
+                core::future::ready(())
+            });
+
+        model.respond(Request::default()).await.unwrap();
+        assert_eq!(
+            *sleeps.lock(),
+            vec![
+                Duration::from_millis(300),
+                Duration::from_millis(200),
+                Duration::from_millis(200),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn tiny_rates_do_not_overflow() {
+        let sleeps = Arc::new(Mutex::new(Vec::new()));
+        let recorded = sleeps.clone();
+        let model = SyntheticModel::new()
+            .with_tokens(1)
+            .with_tokens_per_second(f64::MIN_POSITIVE)
+            .with_timer(move |duration| {
+                recorded.lock().push(duration);
+                core::future::ready(())
+            });
+
+        model.respond(Request::default()).await.unwrap();
+        assert_eq!(*sleeps.lock(), [Duration::MAX]);
+    }
+}
@@ -0,0 +1,71 @@
+//! Time sources for a `no_std` environment.
+//!
+//! The crate cannot read the system clock or sleep by itself. Components that enforce
+//! time limits take a [`Clock`], and components that wait take a [`Timer`]. Any closure
+//! returning the elapsed time is a clock:
+//!
+//! ```rust
+//! use ai_types::time::Clock;
+//! use std::time::Instant;
+//!
+//! let start = Instant::now();
+//! let clock = move || start.elapsed();
+//! assert!(clock.now() < std::time::Duration::from_secs(60));
+//! ```
+//!
+//! and any async sleep function is a timer:
+//!
+//! ```rust
+//! use ai_types::time::Timer;
+//! use std::time::Duration;
+//!
+//! # tokio_test::block_on(async {
+//! let timer = tokio::time::sleep;
+//! timer.sleep(Duration::from_millis(1)).await;
+//! # });
+//! ```
+
+use core::{future::Future, time::Duration};
+
+/// A monotonic clock.
+///
+/// [`now`](Clock::now) returns the time elapsed since an arbitrary, fixed starting point.
+/// Only differences between two readings are meaningful.
+pub trait Clock: Send + Sync {
+    /// Returns the current reading of the clock.
+    fn now(&self) -> Duration;
+}
+
+impl<F: Fn() -> Duration + Send + Sync> Clock for F {
+    fn now(&self) -> Duration {
+        self()
+    }
+}
+
+/// An asynchronous timer.
+pub trait Timer: Send + Sync {
+    /// Waits for `duration` to elapse.
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send;
+}
+
+impl<F, Fut> Timer for F
+where
+    F: Fn(Duration) -> Fut + Send + Sync,
+    Fut: Future<Output = ()> + Send,
+{
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send {
+        self(duration)
+    }
+}
+
+/// A [`Timer`] that never waits.
+///
+/// Useful in tests, where delays only slow the suite down.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoDelay;
+
+impl Timer for NoDelay {
+    fn sleep(&self, _duration: Duration) -> impl Future<Output = ()> + Send {
+        core::future::ready(())
+    }
+}
@@ -0,0 +1,830 @@
+//! # Voice Module
+//!
+//! End-to-end voice assistant pipelines.
+//!
+//! A [`VoicePipeline`] composes voice activity detection, an [`AudioTranscriber`], a
+//! [`LanguageModel`] with optional tools, and an [`AudioGenerator`] into a single
+//! conversational loop:
+//!
+//! ```text
+//! microphone ─▶ VAD ─▶ transcriber ─▶ language model (+ tools) ─▶ speech ─▶ speaker
+//! ```
+//!
+//! The pipeline tracks whose turn it is ([`TurnState`]) and supports barge-in: when the
+//! user starts speaking while the assistant is still thinking or talking, the response
+//! is cancelled and the pipeline listens again. Everything that happens is reported as
+//! a [`VoiceEvent`].
+//!
+//! An [`Interpreter`] chains the same traits differently: it transcribes speech,
+//! translates each sentence with the language model, and speaks the translation, emitting
+//! [`InterpreterEvent`]s.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ai_types::{
+//!     AudioGenerator, AudioTranscriber, LanguageModel,
+//!     voice::{EnergyDetector, VoiceEvent, VoicePipeline},
+//! };
+//! use futures_core::Stream;
+//! use futures_lite::{StreamExt, pin};
+//!
+//! async fn talk(
+//!     microphone: impl Stream<Item = Vec<u8>> + Unpin + Send,
+//!     transcriber: impl AudioTranscriber + Sync,
+//!     model: impl LanguageModel,
+//!     voice: impl AudioGenerator + Sync,
+//! ) -> ai_types::Result<()> {
+//!     let mut pipeline = VoicePipeline::new(EnergyDetector::new(500), transcriber, model, voice)
+//!         .with_system_prompt("You are a friendly voice assistant. Keep answers short.");
+//!
+//!     let events = pipeline.run(microphone);
+//!     pin!(events);
+//!     while let Some(event) = events.try_next().await? {
+//!         match event {
+//!             VoiceEvent::UserTranscript(text) => println!("user: {text}"),
+//!             VoiceEvent::Audio(chunk) => { /* play chunk */ }
+//!             VoiceEvent::BargeIn => { /* stop playback */ }
+//!             _ => {}
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use alloc::{string::String, vec::Vec};
+use core::{future::poll_fn, mem, pin::Pin, task::Poll};
+
+use async_stream::try_stream;
+use futures_core::Stream;
+use futures_lite::{StreamExt, pin};
+
+use crate::{
+    AudioGenerator, AudioTranscriber, LanguageModel,
+    audio::{Data, Transcript, TranscriptEvent},
+    llm::{
+        Message, Request, Tool,
+        agent::{self, AgentEvent, AgentLimits},
+        prompts,
+        stream::sentence_end,
+        tool::{ToolCall, Tools},
+    },
+};
+
+/// Characters of untranslated speech after which it is translated without waiting for
+/// the end of the sentence.
+const DEFAULT_MAX_PENDING: usize = 200;
+
+/// Decides whether a frame of audio contains speech.
+pub trait VoiceActivityDetector: Send {
+    /// Returns whether `frame` contains speech.
+    fn is_speech(&mut self, frame: &[u8]) -> bool;
+}
+
+/// A voice activity detector based on signal energy.
+///
+/// Expects 16-bit little-endian PCM frames and reports speech when the mean absolute
+/// amplitude reaches the threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnergyDetector {
+    threshold: u16,
+}
+
+impl EnergyDetector {
+    /// Creates a detector with the given amplitude threshold.
+    #[must_use]
+    pub const fn new(threshold: u16) -> Self {
+        Self { threshold }
+    }
+}
+
+impl VoiceActivityDetector for EnergyDetector {
+    fn is_speech(&mut self, frame: &[u8]) -> bool {
+        let samples = frame.chunks_exact(2);
+        let count = samples.len() as u64;
+        if count == 0 {
+            return false;
+        }
+        let energy: u64 = samples
+            .map(|sample| u64::from(i16::from_le_bytes([sample[0], sample[1]]).unsigned_abs()))
+            .sum();
+        energy / count >= u64::from(self.threshold)
+    }
+}
+
+/// Whose turn it is in a voice conversation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TurnState {
+    /// Waiting for the user to speak.
+    Listening,
+    /// The user is speaking.
+    UserSpeaking,
+    /// Transcribing the user and generating a response.
+    Thinking,
+    /// Playing the assistant's response.
+    Speaking,
+}
+
+/// An event emitted by [`VoicePipeline::run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VoiceEvent {
+    /// The turn state changed.
+    StateChanged(TurnState),
+    /// The transcript of the user's turn.
+    UserTranscript(String),
+    /// A chunk of the assistant's response text.
+    ResponseText(String),
+    /// The assistant requested tool calls.
+    ToolCalls(Vec<ToolCall>),
+    /// The assistant called a tool.
+    ToolResult {
+        /// The tool call requested by the model.
+        call: ToolCall,
+        /// The tool's output.
+        output: String,
+    },
+    /// A chunk of synthesized response audio.
+    Audio(Data),
+    /// The user interrupted the assistant; playback should stop immediately.
+    BargeIn,
+    /// The assistant finished its turn.
+    TurnComplete,
+}
+
+/// A voice assistant composing VAD, transcription, a language model, and speech synthesis.
+///
+/// See the [module documentation](crate::voice) for an overview and an example.
+#[derive(Debug)]
+pub struct VoicePipeline<V, T, M, G> {
+    detector: V,
+    transcriber: T,
+    model: M,
+    generator: G,
+    messages: Vec<Message>,
+    tools: Tools,
+    end_of_turn_silence: usize,
+    limits: AgentLimits,
+}
+
+impl<V, T, M, G> VoicePipeline<V, T, M, G>
+where
+    V: VoiceActivityDetector,
+    T: AudioTranscriber + Sync,
+    M: LanguageModel,
+    G: AudioGenerator + Sync,
+{
+    /// Creates a pipeline from its components.
+    ///
+    /// The user's turn ends after 25 consecutive silent frames (half a second of 20 ms
+    /// frames), and tool calling in each turn is bounded by [`AgentLimits::default`].
+    pub fn new(detector: V, transcriber: T, model: M, generator: G) -> Self {
+        Self {
+            detector,
+            transcriber,
+            model,
+            generator,
+            messages: Vec::new(),
+            tools: Tools::new(),
+            end_of_turn_silence: 25,
+            limits: AgentLimits::default(),
+        }
+    }
+
+    /// Adds a pinned system prompt to the conversation.
+    #[must_use]
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.messages.push(Message::system(prompt).pinned(true));
+        self
+    }
+
+    /// Registers a tool the model may call.
+    #[must_use]
+    pub fn with_tool(mut self, tool: impl Tool) -> Self {
+        self.tools.register(tool);
+        self
+    }
+
+    /// Replaces the tool registry.
+    #[must_use]
+    pub fn with_tools(mut self, tools: Tools) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Sets how many consecutive silent frames end the user's turn.
+    #[must_use]
+    pub const fn with_end_of_turn_silence(mut self, frames: usize) -> Self {
+        self.end_of_turn_silence = frames;
+        self
+    }
+
+    /// Sets the limits bounding tool calling in each turn.
+    #[must_use]
+    pub fn with_limits(mut self, limits: AgentLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Returns the conversation history.
+    #[must_use]
+    pub const fn messages(&self) -> &[Message] {
+        self.messages.as_slice()
+    }
+
+    /// Runs the conversation over a stream of audio frames.
+    ///
+    /// Frames must be in the format expected by the voice activity detector and the
+    /// transcriber. The returned stream ends once the input ends and the current
+    /// response is complete.
+    ///
+    /// # Errors
+    ///
+    /// The stream yields an error and ends if the language model or a tool fails, or
+    /// with an [`AgentAbortReason`](crate::llm::agent::AgentAbortReason) if tool calling
+    /// exceeds its limits.
+    pub fn run<'a, S>(
+        &'a mut self,
+        input: S,
+    ) -> impl Stream<Item = crate::Result<VoiceEvent>> + Send + 'a
+    where
+        S: Stream<Item = Data> + Unpin + Send + 'a,
+    {
+        let Self {
+            detector,
+            transcriber,
+            model,
+            generator,
+            messages,
+            tools,
+            end_of_turn_silence,
+            limits,
+        } = self;
+        let end_of_turn_silence = *end_of_turn_silence;
+        let (transcriber, model, generator, tools, limits) =
+            (&*transcriber, &*model, &*generator, &*tools, &*limits);
+
+        try_stream! {
+            let mut input = input;
+            let mut input_done = false;
+            let mut utterance = Vec::new();
+            let mut user_speaking = false;
+            let mut silence = 0;
+
+            yield VoiceEvent::StateChanged(TurnState::Listening);
+
+            while !input_done {
+                let Some(frame) = input.next().await else {
+                    break;
+                };
+
+                if detector.is_speech(&frame) {
+                    if !user_speaking {
+                        user_speaking = true;
+                        yield VoiceEvent::StateChanged(TurnState::UserSpeaking);
+                    }
+                    silence = 0;
+                    utterance.extend_from_slice(&frame);
+                    continue;
+                }
+
+                if !user_speaking {
+                    continue;
+                }
+                utterance.extend_from_slice(&frame);
+                silence += 1;
+                if silence < end_of_turn_silence {
+                    continue;
+                }
+
+                user_speaking = false;
+                silence = 0;
+                yield VoiceEvent::StateChanged(TurnState::Thinking);
+
+                let audio = mem::take(&mut utterance);
+                let transcript: Transcript = transcriber.transcribe(&audio).collect().await;
+                let transcript = transcript.text();
+                if transcript.is_empty() {
+                    yield VoiceEvent::StateChanged(TurnState::Listening);
+                    continue;
+                }
+                yield VoiceEvent::UserTranscript(transcript.clone());
+                messages.push(Message::user(transcript));
+
+                let response = respond(
+                    model,
+                    generator,
+                    Request::new(messages.clone()).with_tools(tools.clone()),
+                    limits.clone(),
+                );
+                pin!(response);
+
+                let mut reply = String::new();
+                let barged_in = loop {
+                    let next = next(&mut input, input_done, response.as_mut()).await;
+                    match next {
+                        Next::Frame(Some(frame)) => {
+                            if detector.is_speech(&frame) {
+                                utterance.extend_from_slice(&frame);
+                                break true;
+                            }
+                        }
+                        Next::Frame(None) => input_done = true,
+                        Next::Response(Some(event)) => {
+                            let event = event?;
+                            record(messages, &mut reply, &event);
+                            yield event;
+                        }
+                        Next::Response(None) => break false,
+                    }
+                };
+
+                if !reply.is_empty() {
+                    messages.push(Message::assistant(reply));
+                }
+
+                if barged_in {
+                    user_speaking = true;
+                    yield VoiceEvent::BargeIn;
+                    yield VoiceEvent::StateChanged(TurnState::UserSpeaking);
+                } else {
+                    yield VoiceEvent::TurnComplete;
+                    if !input_done {
+                        yield VoiceEvent::StateChanged(TurnState::Listening);
+                    }
+                }
+            }
+        }
+    }
+}
+
+enum Next<T> {
+    Frame(Option<Data>),
+    Response(Option<T>),
+}
+
+/// Waits for the next input frame or response event, whichever comes first.
+///
+/// Input is no longer polled once it has ended.
+async fn next<S, R>(input: &mut S, input_done: bool, mut response: Pin<&mut R>) -> Next<R::Item>
+where
+    S: Stream<Item = Data> + Unpin,
+    R: Stream,
+{
+    poll_fn(|cx| {
+        if !input_done {
+            if let Poll::Ready(frame) = Pin::new(&mut *input).poll_next(cx) {
+                return Poll::Ready(Next::Frame(frame));
+            }
+        }
+        response.as_mut().poll_next(cx).map(Next::Response)
+    })
+    .await
+}
+
+/// Records the assistant's reply, tool calls, and tool results of a turn in the history.
+fn record(messages: &mut Vec<Message>, reply: &mut String, event: &VoiceEvent) {
+    match event {
+        VoiceEvent::ResponseText(chunk) => reply.push_str(chunk),
+        VoiceEvent::ToolCalls(calls) => {
+            messages.push(Message::assistant_tool_calls(
+                mem::take(reply),
+                calls.clone(),
+            ));
+        }
+        VoiceEvent::ToolResult { call, output } => {
+            messages.push(Message::tool_result(call.id.clone(), output.clone()));
+        }
+        _ => {}
+    }
+}
+
+/// Generates one assistant turn: response text, tool calls, and synthesized speech.
+///
+/// Speech is synthesized sentence by sentence so playback can start before the model
+/// finishes.
+fn respond<'a, M: LanguageModel, G: AudioGenerator + Sync>(
+    model: &'a M,
+    generator: &'a G,
+    request: Request,
+    limits: AgentLimits,
+) -> impl Stream<Item = crate::Result<VoiceEvent>> + Send + 'a {
+    try_stream! {
+        let events = agent::run(model, request, limits);
+        pin!(events);
+
+        let mut speaking = false;
+        let mut pending = String::new();
+        loop {
+            let event = events.try_next().await?;
+            let finished = event.is_none();
+
+            let mut sentences = Vec::new();
+            match event {
+                Some(AgentEvent::Text(chunk)) => {
+                    pending.push_str(&chunk);
+                    yield VoiceEvent::ResponseText(chunk);
+                    while let Some(end) = sentence_end(&pending) {
+                        sentences.push(pending.drain(..end).collect::<String>());
+                    }
+                }
+                Some(AgentEvent::ToolCalls(calls)) => yield VoiceEvent::ToolCalls(calls),
+                Some(AgentEvent::ToolResult { call, output }) => {
+                    yield VoiceEvent::ToolResult { call, output };
+                    sentences.push(mem::take(&mut pending));
+                }
+                Some(_) => {}
+                None => sentences.push(mem::take(&mut pending)),
+            }
+
+            for sentence in &sentences {
+                let sentence = sentence.trim();
+                if sentence.is_empty() {
+                    continue;
+                }
+                if !speaking {
+                    speaking = true;
+                    yield VoiceEvent::StateChanged(TurnState::Speaking);
+                }
+                let audio = generator.generate(sentence);
+                pin!(audio);
+                while let Some(chunk) = audio.next().await {
+                    yield VoiceEvent::Audio(chunk);
+                }
+            }
+
+            if finished {
+                break;
+            }
+        }
+    }
+}
+
+/// An event emitted by [`Interpreter::run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InterpreterEvent {
+    /// A sentence of the source speech, about to be translated.
+    Transcript(String),
+    /// A chunk of the translation.
+    Translation(String),
+    /// A chunk of synthesized translation audio.
+    Audio(Data),
+}
+
+/// A simultaneous interpreter: speech in, translated speech out.
+///
+/// The source speech is transcribed with
+/// [`transcribe_stream`](AudioTranscriber::transcribe_stream), and cut into sentences as
+/// soon as they are complete. Each sentence is translated by the language model, and the
+/// translation is spoken sentence by sentence while it streams, so the first words are
+/// heard before the speaker or the model has finished. Speech that runs on without a
+/// sentence break is translated once it exceeds a length limit, to bound the delay.
+///
+/// Sentences are translated independently, without the context of earlier ones.
+///
+/// # Example
+///
+/// ```rust
+/// use ai_types::{
+///     AudioGenerator, AudioTranscriber, LanguageModel,
+///     voice::{Interpreter, InterpreterEvent},
+/// };
+/// use futures_core::Stream;
+/// use futures_lite::{StreamExt, pin};
+///
+/// async fn interpret(
+///     microphone: impl Stream<Item = Vec<u8>> + Send,
+///     transcriber: impl AudioTranscriber + Sync,
+///     model: impl LanguageModel,
+///     voice: impl AudioGenerator + Sync,
+/// ) -> ai_types::Result<()> {
+///     let interpreter =
+///         Interpreter::new(transcriber, model, voice, "French").with_source_language("English");
+///
+///     let events = interpreter.run(microphone);
+///     pin!(events);
+///     while let Some(event) = events.try_next().await? {
+///         if let InterpreterEvent::Audio(chunk) = event {
+///             // play chunk
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Interpreter<T, M, G> {
+    transcriber: T,
+    model: M,
+    generator: G,
+    source_language: Option<String>,
+    target_language: String,
+    max_pending: usize,
+}
+
+impl<T, M, G> Interpreter<T, M, G>
+where
+    T: AudioTranscriber + Sync,
+    M: LanguageModel,
+    G: AudioGenerator + Sync,
+{
+    /// Creates an interpreter into `target_language`, such as `"French"`, detecting the
+    /// source language.
+    pub fn new(transcriber: T, model: M, generator: G, target_language: impl Into<String>) -> Self {
+        Self {
+            transcriber,
+            model,
+            generator,
+            source_language: None,
+            target_language: target_language.into(),
+            max_pending: DEFAULT_MAX_PENDING,
+        }
+    }
+
+    /// Sets the language of the source speech.
+    #[must_use]
+    pub fn with_source_language(mut self, language: impl Into<String>) -> Self {
+        self.source_language = Some(language.into());
+        self
+    }
+
+    /// Translates speech without a sentence break once it reaches `characters`, 200 by
+    /// default.
+    #[must_use]
+    pub const fn with_max_pending(mut self, characters: usize) -> Self {
+        self.max_pending = characters;
+        self
+    }
+
+    /// Interprets a stream of audio, in the format expected by the transcriber.
+    ///
+    /// The returned stream ends once the input ends and its last sentence is spoken.
+    ///
+    /// # Errors
+    ///
+    /// The stream yields an error and ends if transcription or the language model fails.
+    pub fn run<'a>(
+        &'a self,
+        audio: impl Stream<Item = Data> + Send + 'a,
+    ) -> impl Stream<Item = crate::Result<InterpreterEvent>> + Send + 'a {
+        try_stream! {
+            let transcript = self.transcriber.transcribe_stream(audio);
+            pin!(transcript);
+
+            let mut pending = String::new();
+            let mut finished = false;
+            while !finished {
+                let mut sentences = Vec::new();
+                match transcript.try_next().await? {
+                    Some(TranscriptEvent::Final(segment)) => {
+                        if !pending.is_empty() && !pending.ends_with(char::is_whitespace) {
+                            pending.push(' ');
+                        }
+                        pending.push_str(&segment.text);
+                        while let Some(end) = sentence_end(&pending) {
+                            sentences.push(pending.drain(..end).collect::<String>());
+                        }
+                        if ends_sentence(&pending) || pending.chars().count() >= self.max_pending {
+                            sentences.push(mem::take(&mut pending));
+                        }
+                    }
+                    Some(_) => {}
+                    None => {
+                        finished = true;
+                        sentences.push(mem::take(&mut pending));
+                    }
+                }
+
+                for sentence in sentences {
+                    let sentence = sentence.trim();
+                    if sentence.is_empty() {
+                        continue;
+                    }
+                    yield InterpreterEvent::Transcript(sentence.into());
+
+                    let prompt =
+                        prompts::translate(self.source_language.as_deref(), &self.target_language);
+                    let translation = self.model.respond(Request::oneshot(prompt, sentence));
+                    pin!(translation);
+                    let mut spoken = String::new();
+                    let mut translated = false;
+                    while !translated {
+                        let mut ready = Vec::new();
+                        if let Some(chunk) = translation.try_next().await? {
+                            spoken.push_str(&chunk);
+                            yield InterpreterEvent::Translation(chunk);
+                            while let Some(end) = sentence_end(&spoken) {
+                                ready.push(spoken.drain(..end).collect::<String>());
+                            }
+                        } else {
+                            translated = true;
+                            ready.push(mem::take(&mut spoken));
+                        }
+                        for text in &ready {
+                            let text = text.trim();
+                            if text.is_empty() {
+                                continue;
+                            }
+                            let audio = self.generator.generate(text);
+                            pin!(audio);
+                            while let Some(chunk) = audio.next().await {
+                                yield InterpreterEvent::Audio(chunk);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns whether `text` ends with a sentence terminator.
+fn ends_sentence(text: &str) -> bool {
+    text.trim_end()
+        .ends_with(['.', '!', '?', '\n', '。', '！', '？'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        audio::{self, Segment},
+        test_util::{MockLanguageModel, MockReply},
+    };
+    use alloc::{vec, vec::Vec};
+
+    /// Frames starting with a non-zero byte are speech.
+    struct FirstByteDetector;
+
+    impl VoiceActivityDetector for FirstByteDetector {
+        fn is_speech(&mut self, frame: &[u8]) -> bool {
+            frame.first().is_some_and(|&byte| byte != 0)
+        }
+    }
+
+    struct EchoTranscriber;
+
+    impl AudioTranscriber for EchoTranscriber {
+        fn transcribe(&self, audio: &[u8]) -> impl Stream<Item = Segment> + Send {
+            let speech = audio.iter().filter(|&&byte| byte != 0).count();
+            futures_lite::stream::iter(vec![Segment::new(alloc::format!("{speech} speech frames"))])
+        }
+    }
+
+    struct LengthVoice;
+
+    impl AudioGenerator for LengthVoice {
+        fn generate(&self, prompt: &str) -> impl Stream<Item = Data> + Send {
+            futures_lite::stream::iter(vec![vec![0xAA; prompt.len()]])
+        }
+
+        fn profile(&self) -> audio::Profile {
+            audio::Profile::new("length", "Emits one byte per character")
+        }
+    }
+
+    fn pipeline()
+    -> VoicePipeline<FirstByteDetector, EchoTranscriber, MockLanguageModel, LengthVoice> {
+        let model = MockLanguageModel::new()
+            .with_reply(MockReply::chunks(["Hello there. ", "How can I help?"]));
+        VoicePipeline::new(FirstByteDetector, EchoTranscriber, model, LengthVoice)
+            .with_system_prompt("Be brief.")
+            .with_end_of_turn_silence(2)
+    }
+
+    #[tokio::test]
+    async fn full_turn() {
+        let mut pipeline = pipeline();
+        let frames = futures_lite::stream::iter(vec![vec![1], vec![1], vec![0], vec![0]]);
+
+        let events: Vec<_> = pipeline.run(frames).map(Result::unwrap).collect().await;
+        assert_eq!(
+            events,
+            vec![
+                VoiceEvent::StateChanged(TurnState::Listening),
+                VoiceEvent::StateChanged(TurnState::UserSpeaking),
+                VoiceEvent::StateChanged(TurnState::Thinking),
+                VoiceEvent::UserTranscript("2 speech frames".into()),
+                VoiceEvent::ResponseText("Hello there. ".into()),
+                VoiceEvent::StateChanged(TurnState::Speaking),
+                VoiceEvent::Audio(vec![0xAA; 12]),
+                VoiceEvent::ResponseText("How can I help?".into()),
+                VoiceEvent::Audio(vec![0xAA; 15]),
+                VoiceEvent::TurnComplete,
+            ]
+        );
+
+        let roles: Vec<_> = pipeline.messages().iter().map(Message::role).collect();
+        assert_eq!(
+            roles,
+            [
+                crate::llm::Role::System,
+                crate::llm::Role::User,
+                crate::llm::Role::Assistant
+            ]
+        );
+        assert_eq!(
+            pipeline.messages()[2].content(),
+            "Hello there. How can I help?"
+        );
+    }
+
+    #[tokio::test]
+    async fn barge_in_cancels_response() {
+        let mut pipeline = pipeline();
+        // The second utterance starts while the response is being generated.
+        let frames =
+            futures_lite::stream::iter(vec![vec![1], vec![0], vec![0], vec![1], vec![0], vec![0]]);
+
+        let events: Vec<_> = pipeline.run(frames).map(Result::unwrap).collect().await;
+        assert!(events.contains(&VoiceEvent::BargeIn));
+        assert_eq!(
+            events
+                .iter()
+                .filter(|event| matches!(event, VoiceEvent::UserTranscript(_)))
+                .count(),
+            2
+        );
+        assert_eq!(events.last(), Some(&VoiceEvent::TurnComplete));
+    }
+
+    /// Reads audio as text, with `|` separating segments.
+    struct TextTranscriber;
+
+    impl AudioTranscriber for TextTranscriber {
+        fn transcribe(&self, audio: &[u8]) -> impl Stream<Item = Segment> + Send {
+            let text = String::from_utf8_lossy(audio).into_owned();
+            let segments: Vec<Segment> = text.split('|').map(Segment::new).collect();
+            futures_lite::stream::iter(segments)
+        }
+    }
+
+    /// Translates the three sentences of `interprets_sentence_by_sentence` into upper case,
+    /// one word per chunk.
+    fn shouting() -> MockLanguageModel {
+        MockLanguageModel::new().with_replies([
+            MockReply::chunks(["HI."]),
+            MockReply::chunks(["BYE ", "NOW."]),
+            MockReply::chunks(["HOW ", "ARE ", "YOU?"]),
+        ])
+    }
+
+    async fn interpret(
+        interpreter: &Interpreter<TextTranscriber, MockLanguageModel, LengthVoice>,
+        speech: &str,
+    ) -> Vec<InterpreterEvent> {
+        let audio = futures_lite::stream::iter(vec![speech.as_bytes().to_vec()]);
+        interpreter.run(audio).map(Result::unwrap).collect().await
+    }
+
+    #[tokio::test]
+    async fn interprets_sentence_by_sentence() {
+        let interpreter = Interpreter::new(TextTranscriber, shouting(), LengthVoice, "Shouting");
+        let events = interpret(&interpreter, "Hi. Bye now. How are|you?").await;
+        assert_eq!(
+            events,
+            vec![
+                InterpreterEvent::Transcript("Hi.".into()),
+                InterpreterEvent::Translation("HI.".into()),
+                InterpreterEvent::Audio(vec![0xAA; 3]),
+                InterpreterEvent::Transcript("Bye now.".into()),
+                InterpreterEvent::Translation("BYE ".into()),
+                InterpreterEvent::Translation("NOW.".into()),
+                InterpreterEvent::Audio(vec![0xAA; 8]),
+                InterpreterEvent::Transcript("How are you?".into()),
+                InterpreterEvent::Translation("HOW ".into()),
+                InterpreterEvent::Translation("ARE ".into()),
+                InterpreterEvent::Translation("YOU?".into()),
+                InterpreterEvent::Audio(vec![0xAA; 12]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn long_speech_is_translated_without_waiting() {
+        let interpreter = Interpreter::new(TextTranscriber, shouting(), LengthVoice, "Shouting")
+            .with_max_pending(10);
+        let events = interpret(&interpreter, "and then we went|on and|on").await;
+        let transcripts: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                InterpreterEvent::Transcript(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(transcripts, ["and then we went", "on and on"]);
+    }
+
+    #[test]
+    fn energy_detector() {
+        let mut detector = EnergyDetector::new(1000);
+        let loud: Vec<u8> = [2000_i16, -2000]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let quiet: Vec<u8> = [10_i16, -10].iter().flat_map(|s| s.to_le_bytes()).collect();
+        assert!(detector.is_speech(&loud));
+        assert!(!detector.is_speech(&quiet));
+        assert!(!detector.is_speech(&[]));
+    }
+}